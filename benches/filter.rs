@@ -0,0 +1,47 @@
+//! Benchmarks for convolution and filtering.
+
+#[macro_use]
+extern crate criterion;
+extern crate image;
+extern crate imageproc;
+
+use criterion::Criterion;
+use imageproc::bench_utils::{set_simd_enabled, textured_gray_image, textured_rgb_image};
+use imageproc::filter::{box_filter, filter3x3, gaussian_blur_f32, median_filter, separable_filter_equal};
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+const SOBEL: [i32; 9] = [-1, 0, 1, -2, 0, 2, -1, 0, 1];
+const AVERAGE_5: [f32; 5] = [0.2; 5];
+
+macro_rules! bench_gray {
+    ($c:expr, $name:expr, |$image:ident| $body:expr) => {
+        $c.bench_function_over_inputs($name, |b, &&size| {
+            let $image = textured_gray_image(size, size, 1);
+            b.iter(|| $body);
+        }, &SIZES);
+    };
+}
+
+macro_rules! bench_rgb {
+    ($c:expr, $name:expr, |$image:ident| $body:expr) => {
+        $c.bench_function_over_inputs($name, |b, &&size| {
+            let $image = textured_rgb_image(size, size, 1);
+            b.iter(|| $body);
+        }, &SIZES);
+    };
+}
+
+fn filters(c: &mut Criterion) {
+    bench_gray!(c, "filter3x3_i32_gray", |image| filter3x3::<_, _, i16>(&image, &SOBEL));
+    bench_gray!(c, "separable_filter_gray", |image| separable_filter_equal(&image, &AVERAGE_5));
+    bench_rgb!(c, "separable_filter_rgb", |image| separable_filter_equal(&image, &AVERAGE_5));
+    bench_gray!(c, "gaussian_blur_gray", |image| gaussian_blur_f32(&image, 3.0));
+    bench_gray!(c, "box_filter_gray", |image| box_filter(&image, 7, 7));
+    set_simd_enabled(false);
+    bench_gray!(c, "box_filter_gray_scalar", |image| box_filter(&image, 7, 7));
+    set_simd_enabled(true);
+    bench_gray!(c, "median_filter_gray", |image| median_filter(&image, 3, 3));
+}
+
+criterion_group!(benches, filters);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+//! Benchmarks for evaluating Haar-like features.
+
+#[macro_use]
+extern crate criterion;
+extern crate image;
+extern crate imageproc;
+
+use criterion::Criterion;
+use imageproc::bench_utils::{sample_haar_features, set_simd_enabled, textured_gray_image};
+use imageproc::integral_image::integral_image;
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+
+// Evaluates 100 features of a 24x24 frame at every fourth position in each direction.
+fn haar_evaluate(c: &mut Criterion) {
+    c.bench_function_over_inputs("haar_evaluate", |b, &&size| {
+        let image = textured_gray_image(size, size, 1);
+        let integral = integral_image(&image);
+        let features = sample_haar_features(24, 100);
+        b.iter(|| {
+            let mut total = 0i64;
+            for y in (0..size - 24).step_by(4) {
+                for x in (0..size - 24).step_by(4) {
+                    for feature in &features {
+                        total += feature.evaluate_at(&integral, x, y).unwrap() as i64;
+                    }
+                }
+            }
+            total
+        });
+    }, &SIZES);
+}
+
+// Evaluates 100 features of a 24x24 frame at every position in every fourth row.
+fn bench_evaluate_row(c: &mut Criterion, name: &str) {
+    c.bench_function_over_inputs(name, |b, &&size| {
+        let image = textured_gray_image(size, size, 1);
+        let integral = integral_image(&image);
+        let features = sample_haar_features(24, 100);
+        let mut values = vec![0; (size - 24) as usize];
+        b.iter(|| {
+            let mut total = 0i64;
+            for y in (0..size - 24).step_by(4) {
+                for feature in &features {
+                    feature.evaluate_row(&integral, y, &mut values).unwrap();
+                    total += values.iter().map(|&v| v as i64).sum::<i64>();
+                }
+            }
+            total
+        });
+    }, &SIZES);
+}
+
+fn haar_evaluate_row(c: &mut Criterion) {
+    bench_evaluate_row(c, "haar_evaluate_row");
+    set_simd_enabled(false);
+    bench_evaluate_row(c, "haar_evaluate_row_scalar");
+    set_simd_enabled(true);
+}
+
+criterion_group!(benches, haar_evaluate, haar_evaluate_row);
+criterion_main!(benches);
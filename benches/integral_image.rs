@@ -0,0 +1,34 @@
+//! Benchmarks for integral image computation.
+
+#[macro_use]
+extern crate criterion;
+extern crate image;
+extern crate imageproc;
+
+use criterion::Criterion;
+use imageproc::bench_utils::{set_simd_enabled, textured_gray_image};
+use imageproc::integral_image::{integral_image, integral_squared_image, rotated_integral_image};
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+
+macro_rules! bench_integral {
+    ($c:expr, $name:expr, $function:ident) => {
+        $c.bench_function_over_inputs($name, |b, &&size| {
+            let image = textured_gray_image(size, size, 1);
+            b.iter(|| $function(&image));
+        }, &SIZES);
+    };
+}
+
+fn integral_images(c: &mut Criterion) {
+    bench_integral!(c, "integral_image", integral_image);
+    bench_integral!(c, "integral_squared_image", integral_squared_image);
+    bench_integral!(c, "rotated_integral_image", rotated_integral_image);
+    set_simd_enabled(false);
+    bench_integral!(c, "integral_image_scalar", integral_image);
+    bench_integral!(c, "integral_squared_image_scalar", integral_squared_image);
+    set_simd_enabled(true);
+}
+
+criterion_group!(benches, integral_images);
+criterion_main!(benches);
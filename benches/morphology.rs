@@ -0,0 +1,37 @@
+//! Benchmarks for binary and grayscale morphology.
+
+#[macro_use]
+extern crate criterion;
+extern crate image;
+extern crate imageproc;
+
+use criterion::Criterion;
+use imageproc::bench_utils::{binary_image, textured_gray_image};
+use imageproc::distance_transform::Norm;
+use imageproc::morphology::{dilate, erode, grayscale_dilate, open, Mask};
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+
+macro_rules! bench_binary {
+    ($c:expr, $name:expr, |$image:ident| $body:expr) => {
+        $c.bench_function_over_inputs($name, |b, &&size| {
+            let $image = binary_image(size, size, 0.3, 1);
+            b.iter(|| $body);
+        }, &SIZES);
+    };
+}
+
+fn morphology(c: &mut Criterion) {
+    bench_binary!(c, "dilate_l1", |image| dilate(&image, Norm::L1, 4));
+    bench_binary!(c, "erode_linf", |image| erode(&image, Norm::LInf, 4));
+    bench_binary!(c, "open_linf", |image| open(&image, Norm::LInf, 2));
+
+    c.bench_function_over_inputs("grayscale_dilate_disk", |b, &&size| {
+        let image = textured_gray_image(size, size, 1);
+        let mask = Mask::disk(3);
+        b.iter(|| grayscale_dilate(&image, &mask));
+    }, &SIZES);
+}
+
+criterion_group!(benches, morphology);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+//! Benchmarks for geometric transformations.
+
+#[macro_use]
+extern crate criterion;
+extern crate image;
+extern crate imageproc;
+
+use criterion::Criterion;
+use image::{Luma, Rgb};
+use imageproc::bench_utils::{bench_projection, textured_gray_image, textured_rgb_image};
+use imageproc::geometric_transformations::{resize, warp, Interpolation, ResizeFilter};
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+
+macro_rules! bench_warp {
+    ($c:expr, $name:expr, $make_image:ident, $default:expr, $interpolation:expr) => {
+        $c.bench_function_over_inputs($name, |b, &&size| {
+            let image = $make_image(size, size, 1);
+            let projection = bench_projection(size, size, 0.3);
+            b.iter(|| warp(&image, &projection, $interpolation, $default));
+        }, &SIZES);
+    };
+}
+
+fn warps(c: &mut Criterion) {
+    bench_warp!(c, "warp_nearest_gray", textured_gray_image, Luma([0u8]), Interpolation::Nearest);
+    bench_warp!(c, "warp_bilinear_gray", textured_gray_image, Luma([0u8]), Interpolation::Bilinear);
+    bench_warp!(c, "warp_bilinear_rgb", textured_rgb_image, Rgb([0u8; 3]), Interpolation::Bilinear);
+
+    c.bench_function_over_inputs("resize_bicubic", |b, &&size| {
+        let image = textured_gray_image(size, size, 1);
+        b.iter(|| resize(&image, size / 2 + 1, size / 2 + 1, ResizeFilter::Bicubic));
+    }, &SIZES);
+}
+
+criterion_group!(benches, warps);
+criterion_main!(benches);
@@ -49,7 +49,8 @@ fn main() {
     // Detect lines using Hough transform
     let options = LineDetectionOptions {
         vote_threshold: 40,
-        suppression_radius: 8
+        suppression_radius: 8,
+        angle_resolution_in_degrees: 1.0,
     };
     let lines: Vec<PolarLine> = detect_lines(&edges, options);
 
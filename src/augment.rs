@@ -0,0 +1,528 @@
+//! Random data augmentation for training machine learning models.
+//!
+//! Each [`Augmentation`](trait.Augmentation.html) transforms an image together with the
+//! bounding boxes and masks annotating it, so that the annotations remain correct.
+//! Augmentations can be chained into a [`Pipeline`](struct.Pipeline.html), which is
+//! seeded so that results are reproducible.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # extern crate imageproc;
+//! # fn main() {
+//! use image::{Rgb, RgbImage};
+//! use imageproc::augment::{BrightnessContrast, HorizontalFlip, Pipeline, RandomCrop, Rotation, Sample};
+//! use imageproc::rect::Rect;
+//!
+//! let pipeline = Pipeline::new()
+//!     .then(HorizontalFlip { probability: 0.5 })
+//!     .then(Rotation { max_angle: 0.1 })
+//!     .then(RandomCrop { width: 64, height: 64 })
+//!     .then(BrightnessContrast { max_brightness: 20.0, max_contrast: 0.2 });
+//!
+//! let mut sample = Sample::new(RgbImage::new(100, 80));
+//! sample.boxes.push(Rect::at(30, 20).of_size(40, 40));
+//!
+//! let augmented = pipeline.apply(sample, 17);
+//! assert_eq!(augmented.image.dimensions(), (64, 64));
+//! # }
+//! ```
+
+use image::{FilterType, GrayImage, ImageBuffer, Luma, Pixel};
+use image::imageops::{flip_horizontal, flip_vertical, resize};
+use geometric_transformations::{rotate, Interpolation};
+use conv::ValueInto;
+use definitions::{Clamp, HasBlack, Image, MaybeSend, MaybeSync};
+use math::cast;
+use noise::gaussian_noise_mut;
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Range};
+use rect::{Rect, RotatedRect};
+use training::symmetric_sample;
+use std::f32;
+
+/// An image together with its annotations.
+#[derive(Clone)]
+pub struct Sample<P: Pixel> {
+    /// The image.
+    pub image: Image<P>,
+    /// Bounding boxes of objects in the image. Boxes which are moved
+    /// entirely outside the image by an augmentation are removed.
+    pub boxes: Vec<Rect>,
+    /// Masks with the same dimensions as the image, e.g. segmentation labels.
+    /// These are transformed geometrically in the same way as the image,
+    /// but are not affected by photometric augmentations.
+    pub masks: Vec<GrayImage>,
+}
+
+impl<P: Pixel> Sample<P> {
+    /// A sample with no boxes or masks.
+    pub fn new(image: Image<P>) -> Sample<P> {
+        Sample { image, boxes: vec![], masks: vec![] }
+    }
+}
+
+/// A random transformation of a sample.
+pub trait Augmentation<P: Pixel> {
+    /// Transforms `sample`, using `rng` for all random choices.
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P>;
+}
+
+/// A sequence of augmentations, applied in order.
+pub struct Pipeline<P: Pixel> {
+    steps: Vec<Box<dyn Augmentation<P>>>,
+}
+
+impl<P: Pixel> Pipeline<P> {
+    /// A pipeline which leaves samples unchanged.
+    pub fn new() -> Pipeline<P> {
+        Pipeline { steps: vec![] }
+    }
+
+    /// Appends an augmentation to this pipeline.
+    pub fn then<A: Augmentation<P> + 'static>(mut self, augmentation: A) -> Pipeline<P> {
+        self.steps.push(Box::new(augmentation));
+        self
+    }
+
+    /// Applies each augmentation in turn. The result depends only on `sample` and `seed`.
+    pub fn apply(&self, sample: Sample<P>, seed: usize) -> Sample<P> {
+        let seed_array: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+        self.apply_with_rng(sample, &mut rng)
+    }
+}
+
+impl<P: Pixel> Default for Pipeline<P> {
+    fn default() -> Pipeline<P> {
+        Pipeline::new()
+    }
+}
+
+impl<P: Pixel> Augmentation<P> for Pipeline<P> {
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        self.steps.iter().fold(sample, |s, step| step.apply_with_rng(s, rng))
+    }
+}
+
+/// Mirrors the sample left to right with the given probability.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HorizontalFlip {
+    /// Probability of flipping, between 0 and 1.
+    pub probability: f32,
+}
+
+impl<P: Pixel + 'static> Augmentation<P> for HorizontalFlip {
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        if rng.gen::<f32>() >= self.probability {
+            return sample;
+        }
+        let width = sample.image.width() as i32;
+        Sample {
+            image: flip_horizontal(&sample.image),
+            boxes: sample.boxes.iter()
+                .map(|b| Rect::at(width - 1 - b.right(), b.top()).of_size(b.width(), b.height()))
+                .collect(),
+            masks: sample.masks.iter().map(flip_horizontal).collect(),
+        }
+    }
+}
+
+/// Mirrors the sample top to bottom with the given probability.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VerticalFlip {
+    /// Probability of flipping, between 0 and 1.
+    pub probability: f32,
+}
+
+impl<P: Pixel + 'static> Augmentation<P> for VerticalFlip {
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        if rng.gen::<f32>() >= self.probability {
+            return sample;
+        }
+        let height = sample.image.height() as i32;
+        Sample {
+            image: flip_vertical(&sample.image),
+            boxes: sample.boxes.iter()
+                .map(|b| Rect::at(b.left(), height - 1 - b.bottom()).of_size(b.width(), b.height()))
+                .collect(),
+            masks: sample.masks.iter().map(flip_vertical).collect(),
+        }
+    }
+}
+
+/// Rotates the sample clockwise about its centre by an angle chosen uniformly
+/// from `[-max_angle, max_angle]` radians. The image keeps its dimensions, and
+/// areas rotated in from outside the image are black.
+///
+/// Each box is replaced by the bounding box of its rotated corners, clipped to the image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rotation {
+    /// Maximum rotation angle in radians.
+    pub max_angle: f32,
+}
+
+impl<P> Augmentation<P> for Rotation
+where
+    P: Pixel + HasBlack + MaybeSync + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        let theta = symmetric_sample(rng, self.max_angle);
+        let (width, height) = sample.image.dimensions();
+        let center = (width as f32 / 2.0, height as f32 / 2.0);
+        let (sin, cos) = theta.sin_cos();
+
+        let boxes = sample.boxes.iter()
+            .filter_map(|b| {
+                // Pixel centres are at integer coordinates, so a box covers
+                // [left - 0.5, right + 0.5] horizontally.
+                let dx = (b.left() + b.right()) as f32 / 2.0 - center.0;
+                let dy = (b.top() + b.bottom()) as f32 / 2.0 - center.1;
+                let rect = RotatedRect::new(
+                    (center.0 + cos * dx - sin * dy, center.1 + sin * dx + cos * dy),
+                    b.width() as f32,
+                    b.height() as f32,
+                    theta,
+                );
+                let bounds = rect.bounding_rect();
+                let (min_x, min_y) = (bounds.left(), bounds.top());
+                let (max_x, max_y) = (bounds.right(), bounds.bottom());
+                clip_box(
+                    (min_x + 0.5).round() as i32,
+                    (min_y + 0.5).round() as i32,
+                    (max_x - 0.5).round() as i32,
+                    (max_y - 0.5).round() as i32,
+                    width,
+                    height,
+                )
+            })
+            .collect();
+
+        Sample {
+            image: rotate(&sample.image, center, theta, Interpolation::Bilinear, P::black()),
+            boxes,
+            masks: sample.masks.iter()
+                .map(|m| rotate(m, center, theta, Interpolation::Nearest, Luma([0u8])))
+                .collect(),
+        }
+    }
+}
+
+/// Crops a window of the given size from a uniformly random position in the sample.
+/// If the sample is smaller than the window in either dimension then the whole of
+/// that dimension is kept.
+///
+/// Boxes are clipped to the window, and removed if they lie entirely outside it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RandomCrop {
+    /// Width of the cropped window.
+    pub width: u32,
+    /// Height of the cropped window.
+    pub height: u32,
+}
+
+impl<P: Pixel + 'static> Augmentation<P> for RandomCrop {
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        let (width, height) = sample.image.dimensions();
+        let w = self.width.min(width);
+        let h = self.height.min(height);
+        let left = rng.gen_range(0, width - w + 1);
+        let top = rng.gen_range(0, height - h + 1);
+
+        Sample {
+            image: crop(&sample.image, left, top, w, h),
+            boxes: sample.boxes.iter()
+                .filter_map(|b| clip_box(
+                    b.left() - left as i32,
+                    b.top() - top as i32,
+                    b.right() - left as i32,
+                    b.bottom() - top as i32,
+                    w,
+                    h,
+                ))
+                .collect(),
+            masks: sample.masks.iter().map(|m| crop(m, left, top, w, h)).collect(),
+        }
+    }
+}
+
+/// Resizes the sample by a factor chosen uniformly from `[min_scale, max_scale]`.
+///
+/// The image is resized using a triangle filter and masks by nearest neighbour sampling.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Scale {
+    /// Smallest scale factor.
+    pub min_scale: f32,
+    /// Largest scale factor.
+    pub max_scale: f32,
+}
+
+impl<P: Pixel + 'static> Augmentation<P> for Scale {
+    fn apply_with_rng(&self, sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        let scale = if self.max_scale > self.min_scale {
+            Range::new(self.min_scale, self.max_scale).ind_sample(rng)
+        } else {
+            self.min_scale
+        };
+        let (width, height) = sample.image.dimensions();
+        let new_width = ((width as f32 * scale).round() as u32).max(1);
+        let new_height = ((height as f32 * scale).round() as u32).max(1);
+        let sx = new_width as f32 / width as f32;
+        let sy = new_height as f32 / height as f32;
+
+        Sample {
+            image: resize(&sample.image, new_width, new_height, FilterType::Triangle),
+            boxes: sample.boxes.iter()
+                .filter_map(|b| {
+                    // Scale the box's edges, rather than its pixel centres.
+                    let left = (b.left() as f32 * sx).round() as i32;
+                    let top = (b.top() as f32 * sy).round() as i32;
+                    let right = ((b.right() + 1) as f32 * sx).round() as i32 - 1;
+                    let bottom = ((b.bottom() + 1) as f32 * sy).round() as i32 - 1;
+                    clip_box(left, top, right.max(left), bottom.max(top), new_width, new_height)
+                })
+                .collect(),
+            masks: sample.masks.iter()
+                .map(|m| resize(m, new_width, new_height, FilterType::Nearest))
+                .collect(),
+        }
+    }
+}
+
+/// Randomly changes the brightness and contrast of the image. Boxes and masks are unchanged.
+///
+/// Each subpixel value `v` is mapped to `mean + c * (v - mean) + b`, where `mean` is the mean
+/// of all subpixels in the image, `b` is chosen uniformly from `[-max_brightness, max_brightness]`
+/// and `c` is chosen uniformly from `[1 - max_contrast, 1 + max_contrast]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BrightnessContrast {
+    /// Maximum change in brightness, in subpixel units.
+    pub max_brightness: f32,
+    /// Maximum relative change in contrast.
+    pub max_contrast: f32,
+}
+
+impl<P> Augmentation<P> for BrightnessContrast
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    fn apply_with_rng(&self, mut sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        let brightness = symmetric_sample(rng, self.max_brightness);
+        let contrast = 1.0 + symmetric_sample(rng, self.max_contrast);
+
+        let count = sample.image.len().max(1) as f32;
+        let mean = sample.image.iter().map(|v| cast::<_, f32>(*v)).sum::<f32>() / count;
+        for v in sample.image.iter_mut() {
+            let value: f32 = cast(*v);
+            *v = P::Subpixel::clamp(mean + contrast * (value - mean) + brightness);
+        }
+        sample
+    }
+}
+
+/// Adds Gaussian noise with mean zero and the given standard deviation to
+/// every subpixel of the image. Boxes and masks are unchanged.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GaussianNoise {
+    /// Standard deviation of the noise.
+    pub stddev: f64,
+}
+
+impl<P> Augmentation<P> for GaussianNoise
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f64> + Clamp<f64>,
+{
+    fn apply_with_rng(&self, mut sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        gaussian_noise_mut(&mut sample.image, 0.0, self.stddev, rng.gen::<usize>());
+        sample
+    }
+}
+
+/// Blacks out `count` squares with side length `size`, centred at uniformly random
+/// positions in the image. Squares may extend beyond the image. Boxes and masks are unchanged.
+///
+/// See [DeVries and Taylor](https://arxiv.org/abs/1708.04552).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cutout {
+    /// Number of squares.
+    pub count: usize,
+    /// Side length of each square.
+    pub size: u32,
+}
+
+impl<P: Pixel + HasBlack + 'static> Augmentation<P> for Cutout {
+    fn apply_with_rng(&self, mut sample: Sample<P>, rng: &mut StdRng) -> Sample<P> {
+        let (width, height) = sample.image.dimensions();
+        if width == 0 || height == 0 || self.size == 0 {
+            return sample;
+        }
+        let half = (self.size / 2) as i32;
+        for _ in 0..self.count {
+            let cx = rng.gen_range(0, width) as i32;
+            let cy = rng.gen_range(0, height) as i32;
+            let square = clip_box(cx - half, cy - half, cx - half + self.size as i32 - 1, cy - half + self.size as i32 - 1, width, height);
+            if let Some(square) = square {
+                for y in square.top()..square.bottom() + 1 {
+                    for x in square.left()..square.right() + 1 {
+                        sample.image.put_pixel(x as u32, y as u32, P::black());
+                    }
+                }
+            }
+        }
+        sample
+    }
+}
+
+/// Returns the rect with the given inclusive bounds clipped to an image of the given
+/// size, or `None` if it lies entirely outside the image.
+fn clip_box(left: i32, top: i32, right: i32, bottom: i32, width: u32, height: u32) -> Option<Rect> {
+    Rect::from_bounds(left, top, right, bottom).and_then(|r| r.clamp_to_image(width, height))
+}
+
+fn crop<P: Pixel + 'static>(image: &Image<P>, left: u32, top: u32, width: u32, height: u32) -> Image<P> {
+    ImageBuffer::from_fn(width, height, |x, y| *image.get_pixel(left + x, top + y))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use test::{Bencher, black_box};
+
+    fn rng(seed: usize) -> StdRng {
+        let seed_array: &[_] = &[seed];
+        SeedableRng::from_seed(seed_array)
+    }
+
+    fn sample_with_box(width: u32, height: u32, b: Rect) -> Sample<Luma<u8>> {
+        let mut mask = GrayImage::new(width, height);
+        for y in b.top()..b.bottom() + 1 {
+            for x in b.left()..b.right() + 1 {
+                mask.put_pixel(x as u32, y as u32, Luma([255u8]));
+            }
+        }
+        Sample { image: mask.clone(), boxes: vec![b], masks: vec![mask] }
+    }
+
+    /// Bounding box of the non-zero pixels in a mask.
+    fn mask_bounds(mask: &GrayImage) -> Option<Rect> {
+        let points: Vec<(u32, u32)> = mask.enumerate_pixels()
+            .filter(|p| p.2[0] > 0)
+            .map(|p| (p.0, p.1))
+            .collect();
+        if points.is_empty() {
+            return None;
+        }
+        let left = points.iter().map(|p| p.0).min().unwrap();
+        let right = points.iter().map(|p| p.0).max().unwrap();
+        let top = points.iter().map(|p| p.1).min().unwrap();
+        let bottom = points.iter().map(|p| p.1).max().unwrap();
+        Some(Rect::at(left as i32, top as i32).of_size(right - left + 1, bottom - top + 1))
+    }
+
+    #[test]
+    fn test_flips_keep_boxes_consistent_with_masks() {
+        let sample = sample_with_box(20, 10, Rect::at(2, 1).of_size(5, 3));
+
+        let flipped = HorizontalFlip { probability: 1.0 }.apply_with_rng(sample.clone(), &mut rng(1));
+        assert_eq!(flipped.boxes, vec![Rect::at(13, 1).of_size(5, 3)]);
+        assert_eq!(mask_bounds(&flipped.masks[0]), Some(flipped.boxes[0]));
+
+        let flipped = VerticalFlip { probability: 1.0 }.apply_with_rng(sample.clone(), &mut rng(1));
+        assert_eq!(flipped.boxes, vec![Rect::at(2, 6).of_size(5, 3)]);
+        assert_eq!(mask_bounds(&flipped.masks[0]), Some(flipped.boxes[0]));
+
+        let unchanged = HorizontalFlip { probability: 0.0 }.apply_with_rng(sample.clone(), &mut rng(1));
+        assert_pixels_eq!(unchanged.image, sample.image);
+    }
+
+    #[test]
+    fn test_rotation_box_contains_rotated_mask() {
+        let sample = sample_with_box(60, 60, Rect::at(20, 25).of_size(15, 8));
+        for seed in 0..10 {
+            let rotated = Rotation { max_angle: 0.5 }.apply_with_rng(sample.clone(), &mut rng(seed));
+            let b = rotated.boxes[0];
+            let m = mask_bounds(&rotated.masks[0]).unwrap();
+            assert!(b.left() <= m.left() && b.top() <= m.top(), "{:?} {:?}", b, m);
+            assert!(b.right() >= m.right() && b.bottom() >= m.bottom(), "{:?} {:?}", b, m);
+            assert!(b.width() <= m.width() + 2 && b.height() <= m.height() + 2, "{:?} {:?}", b, m);
+        }
+    }
+
+    #[test]
+    fn test_random_crop_clips_and_drops_boxes() {
+        let mut sample = sample_with_box(40, 40, Rect::at(10, 10).of_size(20, 20));
+        sample.boxes.push(Rect::at(0, 0).of_size(2, 2));
+        for seed in 0..10 {
+            let cropped = RandomCrop { width: 25, height: 15 }.apply_with_rng(sample.clone(), &mut rng(seed));
+            assert_eq!(cropped.image.dimensions(), (25, 15));
+            assert_eq!(mask_bounds(&cropped.masks[0]), cropped.boxes.first().cloned());
+            assert!(cropped.boxes.len() <= 2);
+        }
+    }
+
+    #[test]
+    fn test_random_crop_larger_than_image() {
+        let sample = sample_with_box(10, 10, Rect::at(1, 1).of_size(2, 2));
+        let cropped = RandomCrop { width: 20, height: 5 }.apply_with_rng(sample, &mut rng(3));
+        assert_eq!(cropped.image.dimensions(), (10, 5));
+    }
+
+    #[test]
+    fn test_scale_scales_boxes() {
+        let sample = sample_with_box(20, 20, Rect::at(4, 6).of_size(6, 4));
+        let scaled = Scale { min_scale: 2.0, max_scale: 2.0 }.apply_with_rng(sample, &mut rng(1));
+        assert_eq!(scaled.image.dimensions(), (40, 40));
+        assert_eq!(scaled.boxes, vec![Rect::at(8, 12).of_size(12, 8)]);
+        assert_eq!(mask_bounds(&scaled.masks[0]), Some(scaled.boxes[0]));
+    }
+
+    #[test]
+    fn test_brightness_contrast_leaves_masks_alone() {
+        let mut sample = sample_with_box(10, 10, Rect::at(1, 1).of_size(3, 3));
+        sample.image = GrayImage::from_pixel(10, 10, Luma([100u8]));
+        let mask = sample.masks[0].clone();
+        let adjusted = BrightnessContrast { max_brightness: 30.0, max_contrast: 0.5 }
+            .apply_with_rng(sample, &mut rng(5));
+        assert_pixels_eq!(adjusted.masks[0], mask);
+        let value = adjusted.image.get_pixel(0, 0)[0];
+        assert!(value >= 70 && value <= 130 && value != 100);
+        assert!(adjusted.image.pixels().all(|p| p[0] == value));
+    }
+
+    #[test]
+    fn test_cutout_blacks_out_pixels() {
+        let sample = Sample::new(RgbImage::from_pixel(20, 20, Rgb([200, 200, 200])));
+        let cut = Cutout { count: 1, size: 5 }.apply_with_rng(sample, &mut rng(2));
+        let black = cut.image.pixels().filter(|p| **p == Rgb([0, 0, 0])).count();
+        assert!(black > 0 && black <= 25);
+    }
+
+    #[test]
+    fn test_pipeline_is_deterministic() {
+        let pipeline = Pipeline::new()
+            .then(HorizontalFlip { probability: 0.5 })
+            .then(Rotation { max_angle: 0.3 })
+            .then(GaussianNoise { stddev: 5.0 });
+        let sample = sample_with_box(30, 30, Rect::at(5, 5).of_size(10, 10));
+
+        let first = pipeline.apply(sample.clone(), 9);
+        let second = pipeline.apply(sample, 9);
+        assert_pixels_eq!(first.image, second.image);
+        assert_eq!(first.boxes, second.boxes);
+    }
+
+    #[bench]
+    fn bench_pipeline(b: &mut Bencher) {
+        let image = RgbImage::from_fn(200, 200, |x, y| Rgb([x as u8, y as u8, (x + y) as u8]));
+        let pipeline = Pipeline::new()
+            .then(HorizontalFlip { probability: 0.5 })
+            .then(Rotation { max_angle: 0.2 })
+            .then(RandomCrop { width: 150, height: 150 })
+            .then(BrightnessContrast { max_brightness: 20.0, max_contrast: 0.2 });
+        b.iter(|| black_box(pipeline.apply(Sample::new(image.clone()), 1)));
+    }
+}
@@ -0,0 +1,832 @@
+//! Background subtraction for video from a static camera.
+//!
+//! Each background model is updated with every frame passed to its `apply` method,
+//! which returns a mask labelling each pixel of the frame as background, foreground
+//! or (for models that support it) shadow.
+
+use image::{GrayImage, Luma, Pixel};
+use definitions::{Clamp, Image};
+use error::{check_dimensions, Result};
+use conv::ValueInto;
+use math::{cast, squared_distance};
+use num::Zero;
+use std::marker::PhantomData;
+
+/// Mask value for pixels that belong to the background.
+pub const BACKGROUND: u8 = 0;
+
+/// Mask value for pixels that are darker than the background but otherwise
+/// match it, and so are likely to be shadows cast by foreground objects.
+pub const SHADOW: u8 = 127;
+
+/// Mask value for pixels that do not match the background.
+pub const FOREGROUND: u8 = 255;
+
+/// Options for a [`RunningGaussian`](struct.RunningGaussian.html) background model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RunningGaussianOptions {
+    /// Weight given to each new frame when updating the model, between 0 and 1.
+    pub learning_rate: f32,
+    /// A pixel matches the background if its squared distance from the background mean
+    /// is less than `variance_threshold` times the background variance.
+    pub variance_threshold: f32,
+    /// Variance assigned to every pixel when the model is initialised.
+    pub initial_variance: f32,
+    /// Lower bound on the variance of every pixel. Prevents the model
+    /// becoming over-confident in completely static regions.
+    pub min_variance: f32,
+    /// If set, foreground pixels whose colour is a scaling of the background colour by
+    /// a factor between `shadow_threshold` and 1 are labelled as `SHADOW`.
+    pub shadow_threshold: Option<f32>,
+}
+
+impl Default for RunningGaussianOptions {
+    fn default() -> Self {
+        RunningGaussianOptions {
+            learning_rate: 0.01,
+            variance_threshold: 16.0,
+            initial_variance: 225.0,
+            min_variance: 16.0,
+            shadow_threshold: Some(0.5),
+        }
+    }
+}
+
+/// Models each background pixel by a single Gaussian whose mean and variance
+/// are exponentially weighted running averages over all frames seen.
+///
+/// The first frame passed to `apply` is used to initialise the model,
+/// and is labelled as entirely background.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::background::{RunningGaussian, RunningGaussianOptions, FOREGROUND, BACKGROUND};
+///
+/// let mut model = RunningGaussian::new(RunningGaussianOptions::default());
+///
+/// let empty_scene = GrayImage::from_pixel(10, 10, Luma([100u8]));
+/// for _ in 0..10 {
+///     model.apply(&empty_scene).unwrap();
+/// }
+///
+/// let mut with_object = empty_scene.clone();
+/// with_object.put_pixel(5, 5, Luma([250u8]));
+///
+/// let mask = model.apply(&with_object).unwrap();
+/// assert_eq!(mask.get_pixel(5, 5)[0], FOREGROUND);
+/// assert_eq!(mask.get_pixel(0, 0)[0], BACKGROUND);
+/// # }
+/// ```
+pub struct RunningGaussian<P: Pixel> {
+    options: RunningGaussianOptions,
+    width: u32,
+    height: u32,
+    /// Per-channel means, with the channel varying fastest.
+    means: Vec<f32>,
+    /// Per-pixel variances, shared between all channels.
+    variances: Vec<f32>,
+    pixel: PhantomData<P>,
+}
+
+impl<P> RunningGaussian<P>
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    /// Creates a background model, which will be initialised by the first frame it sees.
+    pub fn new(options: RunningGaussianOptions) -> RunningGaussian<P> {
+        RunningGaussian {
+            options,
+            width: 0,
+            height: 0,
+            means: vec![],
+            variances: vec![],
+            pixel: PhantomData,
+        }
+    }
+
+    /// Labels each pixel of `frame` as background, foreground or shadow,
+    /// and then updates the model with `frame`.
+    ///
+    /// Returns `ImageProcError::DimensionMismatch`, without updating the model, if `frame`
+    /// does not have the same dimensions as the frames previously passed to this model.
+    pub fn apply(&mut self, frame: &Image<P>) -> Result<GrayImage> {
+        let (width, height) = frame.dimensions();
+        let channels = P::channel_count() as usize;
+        let mut mask = GrayImage::new(width, height);
+
+        if self.means.is_empty() {
+            self.width = width;
+            self.height = height;
+            self.means = frame.pixels()
+                .flat_map(|p| p.channels().iter().map(|c| cast(*c)).collect::<Vec<f32>>())
+                .collect();
+            self.variances = vec![self.options.initial_variance; (width * height) as usize];
+            return Ok(mask);
+        }
+
+        check_dimensions((self.width, self.height), (width, height))?;
+
+        let alpha = self.options.learning_rate;
+        for (i, (x, y, p)) in frame.enumerate_pixels().enumerate() {
+            let sample = to_f32(p);
+            let mean = &mut self.means[i * channels..(i + 1) * channels];
+            let variance = &mut self.variances[i];
+
+            let dist2 = squared_distance(&sample, mean);
+            let label = if dist2 < self.options.variance_threshold * *variance {
+                BACKGROUND
+            } else {
+                match self.options.shadow_threshold {
+                    Some(tau) if is_shadow(&sample, mean, *variance, self.options.variance_threshold, tau) => SHADOW,
+                    _ => FOREGROUND,
+                }
+            };
+            mask.put_pixel(x, y, Luma([label]));
+
+            for (m, s) in mean.iter_mut().zip(sample.iter()) {
+                *m += alpha * (s - *m);
+            }
+            *variance += alpha * (dist2 / channels as f32 - *variance);
+            *variance = variance.max(self.options.min_variance);
+        }
+
+        Ok(mask)
+    }
+
+    /// Returns the current mean of the model as an image, or `None`
+    /// if the model has not yet seen any frames.
+    pub fn background(&self) -> Option<Image<P>> {
+        if self.means.is_empty() {
+            return None;
+        }
+        let channels = P::channel_count() as usize;
+        Some(background_from_means(self.width, self.height, |i| &self.means[i * channels..(i + 1) * channels]))
+    }
+}
+
+/// Options for a [`MixtureOfGaussians`](struct.MixtureOfGaussians.html) background model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MixtureOfGaussiansOptions {
+    /// Maximum number of Gaussian components used to model each pixel.
+    pub max_components: usize,
+    /// The learning rate is `1 / min(frames_seen, history)`. So the model adapts quickly
+    /// to its first few frames and afterwards behaves like an exponentially weighted
+    /// average over roughly the last `history` frames.
+    pub history: u32,
+    /// A pixel is labelled as background if its squared distance from the mean of one of
+    /// the background components is less than `variance_threshold` times that component's variance.
+    pub variance_threshold: f32,
+    /// A pixel updates an existing component if its squared distance from the component's mean
+    /// is less than `variance_threshold_gen` times the component's variance. Otherwise a new
+    /// component is created. This is typically smaller than `variance_threshold`.
+    pub variance_threshold_gen: f32,
+    /// Components are treated as background, in decreasing order of weight,
+    /// until their total weight exceeds `background_ratio`.
+    pub background_ratio: f32,
+    /// Variance of newly created components.
+    pub initial_variance: f32,
+    /// Lower bound on component variances.
+    pub min_variance: f32,
+    /// Upper bound on component variances.
+    pub max_variance: f32,
+    /// Components lose weight at a rate proportional to this constant in addition
+    /// to the usual decay, so that rarely supported components are discarded.
+    pub complexity_reduction: f32,
+    /// If set, foreground pixels whose colour is a scaling of the colour of a background component
+    /// by a factor between `shadow_threshold` and 1 are labelled as `SHADOW`.
+    pub shadow_threshold: Option<f32>,
+}
+
+impl Default for MixtureOfGaussiansOptions {
+    fn default() -> Self {
+        MixtureOfGaussiansOptions {
+            max_components: 5,
+            history: 500,
+            variance_threshold: 16.0,
+            variance_threshold_gen: 9.0,
+            background_ratio: 0.9,
+            initial_variance: 15.0,
+            min_variance: 4.0,
+            max_variance: 75.0,
+            complexity_reduction: 0.05,
+            shadow_threshold: Some(0.5),
+        }
+    }
+}
+
+/// Models each background pixel by an adaptive mixture of Gaussians, following
+/// [Zivkovic and van der Heijden] (the algorithm OpenCV calls MOG2).
+///
+/// Unlike [`RunningGaussian`](struct.RunningGaussian.html) this can model backgrounds
+/// which alternate between several colours, such as swaying foliage or flickering screens.
+///
+/// [Zivkovic and van der Heijden]: https://doi.org/10.1016/j.patrec.2005.11.005
+pub struct MixtureOfGaussians<P: Pixel> {
+    options: MixtureOfGaussiansOptions,
+    width: u32,
+    height: u32,
+    frames_seen: u32,
+    /// Number of components currently in use at each pixel.
+    num_components: Vec<usize>,
+    /// Component weights, `max_components` per pixel, sorted in decreasing order.
+    weights: Vec<f32>,
+    /// Component variances, `max_components` per pixel.
+    variances: Vec<f32>,
+    /// Component means, `max_components * channels` per pixel.
+    means: Vec<f32>,
+    pixel: PhantomData<P>,
+}
+
+impl<P> MixtureOfGaussians<P>
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    /// Creates a background model, which will be initialised by the first frame it sees.
+    pub fn new(options: MixtureOfGaussiansOptions) -> MixtureOfGaussians<P> {
+        assert!(options.max_components > 0, "max_components must be positive");
+        MixtureOfGaussians {
+            options,
+            width: 0,
+            height: 0,
+            frames_seen: 0,
+            num_components: vec![],
+            weights: vec![],
+            variances: vec![],
+            means: vec![],
+            pixel: PhantomData,
+        }
+    }
+
+    /// Labels each pixel of `frame` as background, foreground or shadow,
+    /// and then updates the model with `frame`.
+    ///
+    /// Returns `ImageProcError::DimensionMismatch`, without updating the model, if `frame`
+    /// does not have the same dimensions as the frames previously passed to this model.
+    pub fn apply(&mut self, frame: &Image<P>) -> Result<GrayImage> {
+        let (width, height) = frame.dimensions();
+        let channels = P::channel_count() as usize;
+        let k_max = self.options.max_components;
+
+        if self.frames_seen == 0 {
+            let n = (width * height) as usize;
+            self.width = width;
+            self.height = height;
+            self.num_components = vec![0; n];
+            self.weights = vec![0.0; n * k_max];
+            self.variances = vec![0.0; n * k_max];
+            self.means = vec![0.0; n * k_max * channels];
+        }
+
+        check_dimensions((self.width, self.height), (width, height))?;
+
+        self.frames_seen = self.frames_seen.saturating_add(1);
+        let alpha = 1.0 / self.frames_seen.min(self.options.history) as f32;
+
+        let mut mask = GrayImage::new(width, height);
+        for (i, (x, y, p)) in frame.enumerate_pixels().enumerate() {
+            let sample = to_f32(p);
+            let mut mixture = Mixture {
+                num_components: &mut self.num_components[i],
+                weights: &mut self.weights[i * k_max..(i + 1) * k_max],
+                variances: &mut self.variances[i * k_max..(i + 1) * k_max],
+                means: &mut self.means[i * k_max * channels..(i + 1) * k_max * channels],
+            };
+            let label = mixture.update(&sample, alpha, &self.options);
+            mask.put_pixel(x, y, Luma([label]));
+        }
+
+        Ok(mask)
+    }
+
+    /// Returns the mean of the most heavily weighted component at each pixel as an image,
+    /// or `None` if the model has not yet seen any frames.
+    pub fn background(&self) -> Option<Image<P>> {
+        if self.frames_seen == 0 {
+            return None;
+        }
+        let stride = self.options.max_components * P::channel_count() as usize;
+        let channels = P::channel_count() as usize;
+        Some(background_from_means(self.width, self.height, |i| &self.means[i * stride..i * stride + channels]))
+    }
+}
+
+/// The model for a single pixel in a `MixtureOfGaussians`.
+struct Mixture<'a> {
+    num_components: &'a mut usize,
+    weights: &'a mut [f32],
+    variances: &'a mut [f32],
+    means: &'a mut [f32],
+}
+
+impl<'a> Mixture<'a> {
+    fn mean(&self, k: usize, channels: usize) -> &[f32] {
+        &self.means[k * channels..(k + 1) * channels]
+    }
+
+    fn swap(&mut self, j: usize, k: usize, channels: usize) {
+        self.weights.swap(j, k);
+        self.variances.swap(j, k);
+        for c in 0..channels {
+            self.means.swap(j * channels + c, k * channels + c);
+        }
+    }
+
+    /// Classifies `sample` and then updates the mixture with it.
+    fn update(&mut self, sample: &[f32], alpha: f32, options: &MixtureOfGaussiansOptions) -> u8 {
+        let channels = sample.len();
+        let prune = alpha * options.complexity_reduction;
+
+        let mut is_background = false;
+        let mut fitted = false;
+        let mut total_weight = 0.0;
+
+        let mut k = 0;
+        while k < *self.num_components {
+            let mut weight = (1.0 - alpha) * self.weights[k] - prune;
+
+            if !fitted {
+                let variance = self.variances[k];
+                let dist2 = squared_distance(sample, self.mean(k, channels));
+
+                if total_weight < options.background_ratio && dist2 < options.variance_threshold * variance {
+                    is_background = true;
+                }
+
+                if dist2 < options.variance_threshold_gen * variance {
+                    fitted = true;
+                    weight += alpha;
+                    let rate = alpha / weight;
+                    for c in 0..channels {
+                        let m = &mut self.means[k * channels + c];
+                        *m += rate * (sample[c] - *m);
+                    }
+                    let updated = variance + rate * (dist2 - variance);
+                    self.variances[k] = updated.max(options.min_variance).min(options.max_variance);
+                }
+            }
+
+            total_weight += weight.max(0.0);
+            self.weights[k] = weight;
+            k += 1;
+        }
+
+        // Discard components whose weight has decayed away, keeping the rest in order.
+        let mut kept = 0;
+        for k in 0..*self.num_components {
+            if self.weights[k] > 0.0 {
+                if k != kept {
+                    self.swap(k, kept, channels);
+                }
+                kept += 1;
+            }
+        }
+        *self.num_components = kept;
+
+        if total_weight > 0.0 {
+            for w in self.weights[..kept].iter_mut() {
+                *w /= total_weight;
+            }
+        }
+
+        if !fitted {
+            // Replace the weakest component if all are in use.
+            let k_max = self.weights.len();
+            let k = if kept == k_max { k_max - 1 } else { kept };
+            *self.num_components = k + 1;
+            if k == 0 {
+                self.weights[0] = 1.0;
+            } else {
+                for w in self.weights[..k].iter_mut() {
+                    *w *= 1.0 - alpha;
+                }
+                self.weights[k] = alpha;
+            }
+            self.variances[k] = options.initial_variance;
+            self.means[k * channels..(k + 1) * channels].copy_from_slice(sample);
+        }
+
+        // Restore ordering by decreasing weight. Only one weight has increased
+        // relative to the others, so a single pass of insertion sort suffices.
+        for k in 1..*self.num_components {
+            let mut j = k;
+            while j > 0 && self.weights[j] > self.weights[j - 1] {
+                self.swap(j, j - 1, channels);
+                j -= 1;
+            }
+        }
+
+        if is_background {
+            return BACKGROUND;
+        }
+
+        if let Some(tau) = options.shadow_threshold {
+            let mut weight = 0.0;
+            for k in 0..*self.num_components {
+                if is_shadow(sample, self.mean(k, channels), self.variances[k], options.variance_threshold, tau) {
+                    return SHADOW;
+                }
+                weight += self.weights[k];
+                if weight > options.background_ratio {
+                    break;
+                }
+            }
+        }
+
+        FOREGROUND
+    }
+}
+
+/// Options for a [`TemporalMedian`](struct.TemporalMedian.html) background model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TemporalMedianOptions {
+    /// Number of most recent frames over which the median is taken.
+    pub window_size: usize,
+    /// A pixel is labelled as foreground if any of its channels differs
+    /// from the background median by more than `threshold`.
+    pub threshold: f32,
+}
+
+impl Default for TemporalMedianOptions {
+    fn default() -> Self {
+        TemporalMedianOptions {
+            window_size: 25,
+            threshold: 30.0,
+        }
+    }
+}
+
+/// Models the background as the per-pixel median of the last `window_size` frames.
+///
+/// This is simpler and often more robust than
+/// [`MixtureOfGaussians`](struct.MixtureOfGaussians.html) for fixed cameras
+/// where motion is sporadic - any object that occupies a pixel for less than half
+/// of the window has no effect on the background estimate.
+///
+/// The most recent frames are kept in a ring buffer, and a sorted copy of each
+/// pixel's recent values is maintained incrementally, so each frame costs
+/// `O(window_size)` per subpixel rather than requiring a full sort.
+///
+/// The first frame passed to `apply` is used to initialise the model,
+/// and is labelled as entirely background.
+pub struct TemporalMedian<P: Pixel> {
+    options: TemporalMedianOptions,
+    width: u32,
+    height: u32,
+    /// The most recent frames, stored contiguously as a ring buffer of subpixels.
+    history: Vec<P::Subpixel>,
+    /// Slot in `history` that the next frame will be written to.
+    next_slot: usize,
+    /// Number of frames currently held in `history`.
+    frames_held: usize,
+    /// For each subpixel, its values in the frames in `history` in increasing
+    /// order. Each subpixel's values occupy `window_size` entries.
+    sorted: Vec<P::Subpixel>,
+}
+
+impl<P> TemporalMedian<P>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Ord + ValueInto<f32>,
+{
+    /// Creates a background model, which will be initialised by the first frame it sees.
+    pub fn new(options: TemporalMedianOptions) -> TemporalMedian<P> {
+        assert!(options.window_size > 0, "window_size must be positive");
+        TemporalMedian {
+            options,
+            width: 0,
+            height: 0,
+            history: vec![],
+            next_slot: 0,
+            frames_held: 0,
+            sorted: vec![],
+        }
+    }
+
+    /// Labels each pixel of `frame` as background or foreground by comparing
+    /// against the median of the previous frames, and then adds `frame` to the window.
+    ///
+    /// Returns `ImageProcError::DimensionMismatch`, without updating the model, if `frame`
+    /// does not have the same dimensions as the frames previously passed to this model.
+    pub fn apply(&mut self, frame: &Image<P>) -> Result<GrayImage> {
+        let (width, height) = frame.dimensions();
+        let channels = P::channel_count() as usize;
+        let window = self.options.window_size;
+        let mut mask = GrayImage::new(width, height);
+
+        if self.frames_held == 0 {
+            let len = (width * height) as usize * channels;
+            self.width = width;
+            self.height = height;
+            self.history = vec![P::Subpixel::zero(); len * window];
+            self.sorted = vec![P::Subpixel::zero(); len * window];
+        } else {
+            check_dimensions((self.width, self.height), (width, height))?;
+
+            for (i, (x, y, p)) in frame.enumerate_pixels().enumerate() {
+                let is_foreground = p.channels().iter().enumerate().any(|(c, v)| {
+                    let median: f32 = cast(self.median_at(i * channels + c));
+                    let v: f32 = cast(*v);
+                    (v - median).abs() > self.options.threshold
+                });
+                if is_foreground {
+                    mask.put_pixel(x, y, Luma([FOREGROUND]));
+                }
+            }
+        }
+
+        self.push(frame);
+        Ok(mask)
+    }
+
+    /// Returns the current per-pixel median as an image, or `None`
+    /// if the model has not yet seen any frames.
+    pub fn background(&self) -> Option<Image<P>> {
+        if self.frames_held == 0 {
+            return None;
+        }
+        let channels = P::channel_count() as usize;
+        let mut out = Image::<P>::new(self.width, self.height);
+        for (i, p) in out.pixels_mut().enumerate() {
+            for (c, v) in p.channels_mut().iter_mut().enumerate() {
+                *v = self.median_at(i * channels + c);
+            }
+        }
+        Some(out)
+    }
+
+    /// The median of the held values of the subpixel with index `j`.
+    /// If an even number of frames are held this is the upper of the two middle values.
+    fn median_at(&self, j: usize) -> P::Subpixel {
+        let start = j * self.options.window_size;
+        self.sorted[start + self.frames_held / 2]
+    }
+
+    /// Adds a frame to the ring buffer, evicting the oldest if the buffer is full.
+    fn push(&mut self, frame: &Image<P>) {
+        let window = self.options.window_size;
+        let len = frame.len();
+        let is_full = self.frames_held == window;
+        let slot = self.next_slot;
+
+        for (j, &value) in frame.iter().enumerate() {
+            let values = &mut self.sorted[j * window..(j + 1) * window];
+            let held = &mut values[..self.frames_held + if is_full { 0 } else { 1 }];
+
+            // Overwrite the evicted value (or the new empty entry) in place and
+            // then move the new value into position, keeping `held` sorted.
+            let mut pos = if is_full {
+                let old = self.history[slot * len + j];
+                held.binary_search(&old).expect("evicted value must be present")
+            } else {
+                held.len() - 1
+            };
+            held[pos] = value;
+            while pos > 0 && held[pos - 1] > held[pos] {
+                held.swap(pos - 1, pos);
+                pos -= 1;
+            }
+            while pos + 1 < held.len() && held[pos + 1] < held[pos] {
+                held.swap(pos, pos + 1);
+                pos += 1;
+            }
+
+            self.history[slot * len + j] = value;
+        }
+
+        self.next_slot = (slot + 1) % window;
+        if !is_full {
+            self.frames_held += 1;
+        }
+    }
+}
+
+/// Returns true if `sample` is a darkened version of `mean`, i.e. if it is close to
+/// `a * mean` for some `a` between `tau` and 1.
+fn is_shadow(sample: &[f32], mean: &[f32], variance: f32, variance_threshold: f32, tau: f32) -> bool {
+    let numerator: f32 = sample.iter().zip(mean.iter()).map(|(s, m)| s * m).sum();
+    let denominator: f32 = mean.iter().map(|m| m * m).sum();
+    if denominator == 0.0 {
+        return false;
+    }
+    let a = numerator / denominator;
+    if a > 1.0 || a < tau {
+        return false;
+    }
+    let dist2: f32 = sample.iter().zip(mean.iter()).map(|(s, m)| (a * m - s).powi(2)).sum();
+    dist2 < variance_threshold * variance * a * a
+}
+
+fn to_f32<P>(p: &P) -> Vec<f32>
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32>,
+{
+    p.channels().iter().map(|c| cast(*c)).collect()
+}
+
+/// Builds an image from per-pixel channel means, where `mean(i)` returns the
+/// means for the `i`th pixel in row-major order.
+fn background_from_means<'a, P, F>(width: u32, height: u32, mean: F) -> Image<P>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Clamp<f32>,
+    F: Fn(usize) -> &'a [f32],
+{
+    let mut out = Image::<P>::new(width, height);
+    for (i, p) in out.pixels_mut().enumerate() {
+        for (c, m) in p.channels_mut().iter_mut().zip(mean(i).iter()) {
+            *c = P::Subpixel::clamp(*m);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::ImageProcError;
+    use image::{GrayImage, Rgb, RgbImage};
+    use noise::gaussian_noise;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_running_gaussian_first_frame_is_background() {
+        let mut model = RunningGaussian::new(RunningGaussianOptions::default());
+        let frame = GrayImage::from_pixel(3, 2, Luma([17u8]));
+        assert_pixels_eq!(model.apply(&frame).unwrap(), GrayImage::new(3, 2));
+        assert_pixels_eq!(model.background().unwrap(), frame);
+    }
+
+    #[test]
+    fn test_running_gaussian_detects_foreground_and_shadow() {
+        let mut model = RunningGaussian::new(RunningGaussianOptions::default());
+        let scene = RgbImage::from_pixel(4, 4, Rgb([100u8, 150u8, 200u8]));
+        for _ in 0..20 {
+            model.apply(&scene).unwrap();
+        }
+
+        let mut frame = scene.clone();
+        frame.put_pixel(1, 1, Rgb([250, 20, 20]));
+        frame.put_pixel(2, 2, Rgb([60, 90, 120]));
+
+        let mask = model.apply(&frame).unwrap();
+        assert_eq!(mask.get_pixel(1, 1)[0], FOREGROUND);
+        assert_eq!(mask.get_pixel(2, 2)[0], SHADOW);
+        assert_eq!(mask.get_pixel(0, 0)[0], BACKGROUND);
+    }
+
+    #[test]
+    fn test_running_gaussian_absorbs_static_changes() {
+        let mut options = RunningGaussianOptions::default();
+        options.learning_rate = 0.2;
+        let mut model = RunningGaussian::new(options);
+
+        model.apply(&GrayImage::from_pixel(2, 2, Luma([50u8]))).unwrap();
+        let changed = GrayImage::from_pixel(2, 2, Luma([200u8]));
+        assert_eq!(model.apply(&changed).unwrap().get_pixel(0, 0)[0], FOREGROUND);
+        for _ in 0..50 {
+            model.apply(&changed).unwrap();
+        }
+        assert_eq!(model.apply(&changed).unwrap().get_pixel(0, 0)[0], BACKGROUND);
+    }
+
+    #[test]
+    fn test_running_gaussian_rejects_mismatched_frames() {
+        let mut model = RunningGaussian::new(RunningGaussianOptions::default());
+        model.apply(&GrayImage::new(2, 2)).unwrap();
+        assert_eq!(
+            model.apply(&GrayImage::new(3, 2)).unwrap_err(),
+            ImageProcError::DimensionMismatch { expected: (2, 2), actual: (3, 2) }
+        );
+        assert!(model.apply(&GrayImage::new(2, 2)).is_ok());
+    }
+
+    #[test]
+    fn test_mixture_of_gaussians_models_bimodal_background() {
+        let mut model = MixtureOfGaussians::new(MixtureOfGaussiansOptions::default());
+        let dark = GrayImage::from_pixel(3, 3, Luma([40u8]));
+        let bright = GrayImage::from_pixel(3, 3, Luma([200u8]));
+
+        // A background which alternates between two intensities.
+        for i in 0..100 {
+            model.apply(if i % 2 == 0 { &dark } else { &bright }).unwrap();
+        }
+
+        assert_eq!(model.apply(&dark).unwrap().get_pixel(1, 1)[0], BACKGROUND);
+        assert_eq!(model.apply(&bright).unwrap().get_pixel(1, 1)[0], BACKGROUND);
+
+        let other = GrayImage::from_pixel(3, 3, Luma([250u8]));
+        assert_eq!(model.apply(&other).unwrap().get_pixel(1, 1)[0], FOREGROUND);
+    }
+
+    #[test]
+    fn test_mixture_of_gaussians_tolerates_noise() {
+        let mut model = MixtureOfGaussians::new(MixtureOfGaussiansOptions::default());
+        let scene = GrayImage::from_fn(20, 20, |x, y| Luma([(x * 10 + y) as u8]));
+        for i in 0..50 {
+            model.apply(&gaussian_noise(&scene, 0.0, 2.0, i)).unwrap();
+        }
+
+        let mask = model.apply(&gaussian_noise(&scene, 0.0, 2.0, 1000)).unwrap();
+        let foreground = mask.pixels().filter(|p| p[0] != BACKGROUND).count();
+        assert!(foreground < 10, "{} foreground pixels", foreground);
+    }
+
+    #[test]
+    fn test_mixture_of_gaussians_detects_shadow() {
+        let mut model = MixtureOfGaussians::new(MixtureOfGaussiansOptions::default());
+        let scene = RgbImage::from_pixel(4, 4, Rgb([100u8, 150u8, 200u8]));
+        for _ in 0..20 {
+            model.apply(&scene).unwrap();
+        }
+
+        let mut frame = scene.clone();
+        frame.put_pixel(1, 1, Rgb([250, 20, 20]));
+        frame.put_pixel(2, 2, Rgb([70, 105, 140]));
+
+        let mask = model.apply(&frame).unwrap();
+        assert_eq!(mask.get_pixel(1, 1)[0], FOREGROUND);
+        assert_eq!(mask.get_pixel(2, 2)[0], SHADOW);
+        assert_eq!(mask.get_pixel(0, 0)[0], BACKGROUND);
+        assert_pixels_eq!(model.background().unwrap(), scene);
+    }
+
+    #[test]
+    fn test_temporal_median_tracks_median_of_window() {
+        let options = TemporalMedianOptions { window_size: 3, threshold: 10.0 };
+        let mut model = TemporalMedian::new(options);
+        for v in &[10u8, 50, 30, 20, 90] {
+            model.apply(&GrayImage::from_pixel(1, 1, Luma([*v]))).unwrap();
+        }
+        // Window now holds 30, 20 and 90.
+        assert_eq!(model.background().unwrap().get_pixel(0, 0)[0], 30);
+    }
+
+    #[test]
+    fn test_temporal_median_ignores_sporadic_motion() {
+        let options = TemporalMedianOptions { window_size: 5, threshold: 20.0 };
+        let mut model = TemporalMedian::new(options);
+        let scene = RgbImage::from_pixel(4, 4, Rgb([80u8, 90u8, 100u8]));
+
+        // An object passes through pixel (1, 1) in two of the frames.
+        for i in 0..10 {
+            let mut frame = scene.clone();
+            if i == 3 || i == 6 {
+                frame.put_pixel(1, 1, Rgb([250, 250, 250]));
+            }
+            model.apply(&frame).unwrap();
+        }
+        assert_pixels_eq!(model.background().unwrap(), scene);
+
+        let mut frame = scene.clone();
+        frame.put_pixel(2, 3, Rgb([80, 90, 200]));
+        let mask = model.apply(&frame).unwrap();
+        assert_eq!(mask.get_pixel(2, 3)[0], FOREGROUND);
+        assert_eq!(mask.pixels().filter(|p| p[0] == FOREGROUND).count(), 1);
+    }
+
+    #[test]
+    fn test_temporal_median_matches_sorting_window() {
+        let options = TemporalMedianOptions { window_size: 4, threshold: 10.0 };
+        let mut model = TemporalMedian::new(options);
+        let values = [7u8, 3, 3, 200, 15, 3, 99, 42, 42, 0, 255, 17];
+        for (i, v) in values.iter().enumerate() {
+            model.apply(&GrayImage::from_pixel(1, 1, Luma([*v]))).unwrap();
+            let start = if i >= 3 { i - 3 } else { 0 };
+            let mut window = values[start..i + 1].to_vec();
+            window.sort();
+            let expected = window[window.len() / 2];
+            assert_eq!(model.background().unwrap().get_pixel(0, 0)[0], expected);
+        }
+    }
+
+    #[bench]
+    fn bench_running_gaussian_rgb(b: &mut Bencher) {
+        let scene = RgbImage::from_fn(100, 100, |x, y| Rgb([x as u8, y as u8, (x + y) as u8]));
+        let mut model = RunningGaussian::new(RunningGaussianOptions::default());
+        model.apply(&scene).unwrap();
+        b.iter(|| black_box(model.apply(&scene).unwrap()));
+    }
+
+    #[bench]
+    fn bench_mixture_of_gaussians_rgb(b: &mut Bencher) {
+        let scene = RgbImage::from_fn(100, 100, |x, y| Rgb([x as u8, y as u8, (x + y) as u8]));
+        let mut model = MixtureOfGaussians::new(MixtureOfGaussiansOptions::default());
+        model.apply(&scene).unwrap();
+        b.iter(|| black_box(model.apply(&scene).unwrap()));
+    }
+
+    #[bench]
+    fn bench_temporal_median_rgb(b: &mut Bencher) {
+        let scene = RgbImage::from_fn(100, 100, |x, y| Rgb([x as u8, y as u8, (x + y) as u8]));
+        let mut model = TemporalMedian::new(TemporalMedianOptions::default());
+        model.apply(&scene).unwrap();
+        b.iter(|| black_box(model.apply(&scene).unwrap()));
+    }
+}
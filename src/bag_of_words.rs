@@ -0,0 +1,476 @@
+//! Bag of visual words models: building a vocabulary of descriptors by k-means
+//! clustering, and encoding an image's descriptors as a histogram over that vocabulary.
+//!
+//! Descriptors are slices of `f32`s, such as those produced by the [`dense`](../dense/index.html)
+//! or [`hog`](../hog/index.html) modules. All descriptors passed to a vocabulary must have
+//! the same length.
+//!
+//! # Examples
+//! ```
+//! use imageproc::bag_of_words::{build_vocabulary, Encoding, Initialization, KMeansOptions};
+//!
+//! let descriptors = vec![
+//!     vec![0.0, 0.1], vec![0.1, 0.0], vec![0.0, 0.0],
+//!     vec![5.0, 5.1], vec![5.1, 5.0],
+//! ];
+//! let options = KMeansOptions {
+//!     num_words: 2,
+//!     max_iterations: 10,
+//!     initialization: Initialization::PlusPlus,
+//!     batch_size: None,
+//! };
+//!
+//! let vocabulary = build_vocabulary(&descriptors, options, 1).unwrap();
+//! assert_eq!(vocabulary.num_words(), 2);
+//!
+//! let histogram = vocabulary.encode(&descriptors, Encoding::Hard);
+//! let mut counts = histogram.clone();
+//! counts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+//! assert_eq!(counts, vec![0.4, 0.6]);
+//! ```
+
+use error::{ImageProcError, Result};
+use math::squared_distance;
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Range};
+use std::f32;
+
+/// How the initial cluster centres are chosen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Initialization {
+    /// Choose distinct descriptors uniformly at random.
+    Random,
+    /// Choose descriptors at random with probability proportional to their squared distance
+    /// from the nearest centre already chosen, as in
+    /// [k-means++](https://en.wikipedia.org/wiki/K-means%2B%2B).
+    PlusPlus,
+}
+
+/// Options for [`build_vocabulary`](fn.build_vocabulary.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KMeansOptions {
+    /// Number of words in the vocabulary, i.e. the number of clusters.
+    pub num_words: usize,
+    /// Maximum number of iterations. Each iteration processes every descriptor,
+    /// or a single batch if `batch_size` is set.
+    pub max_iterations: usize,
+    /// How the initial centres are chosen.
+    pub initialization: Initialization,
+    /// If set, use [mini-batch k-means](https://dl.acm.org/citation.cfm?id=1772862),
+    /// updating centres using a random sample of this many descriptors per iteration.
+    /// This is much faster than the standard algorithm for large descriptor sets.
+    pub batch_size: Option<usize>,
+}
+
+/// How an image's descriptors are combined into a histogram over a vocabulary.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Encoding {
+    /// Each descriptor contributes to the bin of its nearest word only.
+    Hard,
+    /// Each descriptor contributes to every bin, in proportion to
+    /// `exp(-d * d / (2 * sigma * sigma))` where `d` is its distance to the bin's word.
+    /// The contributions of each descriptor sum to 1.
+    Soft {
+        /// Controls how quickly contributions fall with distance. Must be positive.
+        sigma: f32,
+    },
+}
+
+/// A set of words, i.e. cluster centres in descriptor space.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Vocabulary {
+    /// The words, concatenated.
+    words: Vec<f32>,
+    /// Length of each word.
+    dimension: usize,
+}
+
+impl Vocabulary {
+    /// Constructs a vocabulary from its words.
+    ///
+    /// # Panics
+    /// If `words` is empty, its entries are empty, or its entries have different lengths.
+    pub fn new<D: AsRef<[f32]>>(words: &[D]) -> Vocabulary {
+        assert!(!words.is_empty(), "vocabulary must have at least one word");
+        let dimension = words[0].as_ref().len();
+        assert!(dimension > 0, "words must be non-empty");
+        let mut data = Vec::with_capacity(words.len() * dimension);
+        for w in words {
+            assert_eq!(w.as_ref().len(), dimension, "words must have equal lengths");
+            data.extend_from_slice(w.as_ref());
+        }
+        Vocabulary { words: data, dimension }
+    }
+
+    /// Number of words in the vocabulary.
+    pub fn num_words(&self) -> usize {
+        self.words.len() / self.dimension
+    }
+
+    /// Length of each word.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// The word with index `i`.
+    pub fn word(&self, i: usize) -> &[f32] {
+        &self.words[i * self.dimension..(i + 1) * self.dimension]
+    }
+
+    /// Returns the index of the word nearest to `descriptor`, and their squared distance.
+    /// Ties are resolved in favour of the word with the smaller index.
+    ///
+    /// # Panics
+    /// If `descriptor` does not have length `self.dimension()`.
+    pub fn nearest_word(&self, descriptor: &[f32]) -> (usize, f32) {
+        assert_eq!(descriptor.len(), self.dimension, "descriptor length must match vocabulary");
+        nearest(&self.words, self.dimension, descriptor)
+    }
+
+    /// Returns a histogram over the vocabulary of the given descriptors, normalised by the
+    /// number of descriptors. If `descriptors` is empty then every entry is 0.
+    ///
+    /// # Panics
+    /// If any descriptor does not have length `self.dimension()`.
+    pub fn encode<D: AsRef<[f32]>>(&self, descriptors: &[D], encoding: Encoding) -> Vec<f32> {
+        let num_words = self.num_words();
+        let mut histogram = vec![0f32; num_words];
+        let mut weights = vec![0f32; num_words];
+
+        for d in descriptors {
+            let d = d.as_ref();
+            match encoding {
+                Encoding::Hard => histogram[self.nearest_word(d).0] += 1.0,
+                Encoding::Soft { sigma } => {
+                    let (_, nearest_distance) = self.nearest_word(d);
+                    let mut total = 0.0;
+                    for (i, w) in weights.iter_mut().enumerate() {
+                        // Subtracting the nearest distance avoids every weight
+                        // underflowing to zero for descriptors far from all words.
+                        let excess = squared_distance(self.word(i), d) - nearest_distance;
+                        *w = (-excess / (2.0 * sigma * sigma)).exp();
+                        total += *w;
+                    }
+                    for (h, w) in histogram.iter_mut().zip(weights.iter()) {
+                        *h += w / total;
+                    }
+                }
+            }
+        }
+
+        if !descriptors.is_empty() {
+            for h in histogram.iter_mut() {
+                *h /= descriptors.len() as f32;
+            }
+        }
+        histogram
+    }
+}
+
+/// Builds a vocabulary by k-means clustering of `descriptors`. The result depends
+/// only on the inputs and `seed`.
+///
+/// Standard k-means stops early if an iteration doesn't change the word assigned to any
+/// descriptor. A word to which no descriptors are assigned keeps its previous value.
+///
+/// Returns `ImageProcError::InvalidOptions` if `descriptors` is empty, its entries
+/// are empty or have different lengths, there are fewer descriptors than words, `num_words` is 0,
+/// or `batch_size` is `Some(0)`.
+pub fn build_vocabulary<D: AsRef<[f32]>>(
+    descriptors: &[D],
+    options: KMeansOptions,
+    seed: usize,
+) -> Result<Vocabulary> {
+    validate(descriptors, options)?;
+
+    let seed_array: &[_] = &[seed];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+
+    let dimension = descriptors[0].as_ref().len();
+    let mut words = match options.initialization {
+        Initialization::Random => initialize_random(descriptors, options.num_words, &mut rng),
+        Initialization::PlusPlus => initialize_plus_plus(descriptors, options.num_words, &mut rng),
+    };
+
+    match options.batch_size {
+        None => lloyd(descriptors, &mut words, dimension, options.max_iterations),
+        Some(batch_size) => mini_batch(descriptors, &mut words, dimension, options.max_iterations, batch_size, &mut rng),
+    }
+
+    Ok(Vocabulary { words, dimension })
+}
+
+fn validate<D: AsRef<[f32]>>(descriptors: &[D], options: KMeansOptions) -> Result<()> {
+    let mut errors = vec![];
+    if options.num_words == 0 {
+        errors.push("num_words must be non-zero".to_owned());
+    }
+    if descriptors.len() < options.num_words || descriptors.is_empty() {
+        errors.push(format!(
+            "need at least {} descriptors, found {}",
+            options.num_words.max(1),
+            descriptors.len()
+        ));
+    }
+    if let Some(first) = descriptors.first() {
+        let dimension = first.as_ref().len();
+        if dimension == 0 {
+            errors.push("descriptors must be non-empty".to_owned());
+        }
+        if descriptors.iter().any(|d| d.as_ref().len() != dimension) {
+            errors.push("descriptors must have equal lengths".to_owned());
+        }
+    }
+    if options.batch_size == Some(0) {
+        errors.push("batch_size must be non-zero".to_owned());
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+    Ok(())
+}
+
+fn initialize_random<D: AsRef<[f32]>>(descriptors: &[D], k: usize, rng: &mut StdRng) -> Vec<f32> {
+    let mut indices: Vec<usize> = (0..descriptors.len()).collect();
+    // Partial Fisher-Yates shuffle.
+    for i in 0..k {
+        let j = Range::new(i, indices.len()).ind_sample(rng);
+        indices.swap(i, j);
+    }
+    indices[..k].iter().flat_map(|&i| descriptors[i].as_ref().iter().cloned()).collect()
+}
+
+fn initialize_plus_plus<D: AsRef<[f32]>>(descriptors: &[D], k: usize, rng: &mut StdRng) -> Vec<f32> {
+    let first = Range::new(0, descriptors.len()).ind_sample(rng);
+    let mut words: Vec<f32> = descriptors[first].as_ref().to_vec();
+    let mut distances: Vec<f32> = descriptors
+        .iter()
+        .map(|d| squared_distance(d.as_ref(), &words))
+        .collect();
+
+    for _ in 1..k {
+        let total: f64 = distances.iter().map(|&d| d as f64).sum();
+        let next = if total > 0.0 {
+            let target = rng.gen::<f64>() * total;
+            let mut cumulative = 0.0;
+            let mut chosen = distances.len() - 1;
+            for (i, &d) in distances.iter().enumerate() {
+                cumulative += d as f64;
+                if cumulative > target && d > 0.0 {
+                    chosen = i;
+                    break;
+                }
+            }
+            chosen
+        } else {
+            // Every descriptor equals a chosen word.
+            Range::new(0, descriptors.len()).ind_sample(rng)
+        };
+
+        let word = descriptors[next].as_ref();
+        words.extend_from_slice(word);
+        for (distance, d) in distances.iter_mut().zip(descriptors) {
+            *distance = distance.min(squared_distance(d.as_ref(), word));
+        }
+    }
+    words
+}
+
+fn lloyd<D: AsRef<[f32]>>(descriptors: &[D], words: &mut [f32], dimension: usize, max_iterations: usize) {
+    let k = words.len() / dimension;
+    let mut assignments = vec![usize::MAX; descriptors.len()];
+    let mut sums = vec![0f64; words.len()];
+    let mut counts = vec![0usize; k];
+
+    for _ in 0..max_iterations {
+        let mut changed = false;
+        for (a, d) in assignments.iter_mut().zip(descriptors) {
+            let (nearest, _) = nearest(words, dimension, d.as_ref());
+            changed |= *a != nearest;
+            *a = nearest;
+        }
+        if !changed {
+            break;
+        }
+
+        for s in sums.iter_mut() {
+            *s = 0.0;
+        }
+        for c in counts.iter_mut() {
+            *c = 0;
+        }
+        for (&a, d) in assignments.iter().zip(descriptors) {
+            counts[a] += 1;
+            for (s, &x) in sums[a * dimension..(a + 1) * dimension].iter_mut().zip(d.as_ref()) {
+                *s += x as f64;
+            }
+        }
+        for (i, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let range = i * dimension..(i + 1) * dimension;
+            for (w, &s) in words[range.clone()].iter_mut().zip(&sums[range]) {
+                *w = (s / count as f64) as f32;
+            }
+        }
+    }
+}
+
+fn mini_batch<D: AsRef<[f32]>>(
+    descriptors: &[D],
+    words: &mut [f32],
+    dimension: usize,
+    max_iterations: usize,
+    batch_size: usize,
+    rng: &mut StdRng,
+) {
+    let k = words.len() / dimension;
+    let mut counts = vec![0usize; k];
+    let mut batch = Vec::with_capacity(batch_size);
+    let range = Range::new(0, descriptors.len());
+
+    for _ in 0..max_iterations {
+        batch.clear();
+        for _ in 0..batch_size {
+            let d = descriptors[range.ind_sample(rng)].as_ref();
+            batch.push((d, nearest(words, dimension, d).0));
+        }
+        // Each word moves towards each descriptor assigned to it with a step size
+        // that decreases with the number of descriptors it has been assigned so far.
+        for &(d, a) in &batch {
+            counts[a] += 1;
+            let rate = 1.0 / counts[a] as f32;
+            for (w, &x) in words[a * dimension..(a + 1) * dimension].iter_mut().zip(d) {
+                *w += rate * (x - *w);
+            }
+        }
+    }
+}
+
+/// Index of the word nearest to `descriptor`, and their squared distance.
+fn nearest(words: &[f32], dimension: usize, descriptor: &[f32]) -> (usize, f32) {
+    let mut best = (0, f32::INFINITY);
+    for (i, w) in words.chunks(dimension).enumerate() {
+        let d = squared_distance(w, descriptor);
+        if d < best.1 {
+            best = (i, d);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test::{Bencher, black_box};
+
+    /// Points scattered around three well separated centres.
+    fn clustered(n: usize, seed: usize) -> Vec<Vec<f32>> {
+        let centres = [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]];
+        let seed_array: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+        let noise = Range::new(-1.0f32, 1.0);
+        (0..n)
+            .map(|i| {
+                let c = centres[i % 3];
+                vec![c[0] + noise.ind_sample(&mut rng), c[1] + noise.ind_sample(&mut rng)]
+            })
+            .collect()
+    }
+
+    fn options(initialization: Initialization, batch_size: Option<usize>) -> KMeansOptions {
+        KMeansOptions { num_words: 3, max_iterations: 50, initialization, batch_size }
+    }
+
+    fn sorted_words(vocabulary: &Vocabulary) -> Vec<Vec<f32>> {
+        let mut words: Vec<Vec<f32>> = (0..vocabulary.num_words())
+            .map(|i| vocabulary.word(i).iter().map(|x| x.round()).collect())
+            .collect();
+        words.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        words
+    }
+
+    #[test]
+    fn test_build_vocabulary_finds_clusters() {
+        let descriptors = clustered(300, 1);
+        let expected = vec![vec![0.0, 0.0], vec![0.0, 10.0], vec![10.0, 0.0]];
+        for &init in &[Initialization::Random, Initialization::PlusPlus] {
+            for &batch_size in &[None, Some(30)] {
+                let vocabulary = build_vocabulary(&descriptors, options(init, batch_size), 2).unwrap();
+                assert_eq!(sorted_words(&vocabulary), expected, "{:?}, {:?}", init, batch_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_vocabulary_is_deterministic() {
+        let descriptors = clustered(60, 3);
+        let opts = options(Initialization::PlusPlus, Some(10));
+        assert_eq!(
+            build_vocabulary(&descriptors, opts, 7).unwrap(),
+            build_vocabulary(&descriptors, opts, 7).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_plus_plus_with_duplicate_descriptors() {
+        let descriptors = vec![vec![1.0], vec![1.0], vec![1.0]];
+        let vocabulary = build_vocabulary(&descriptors, options(Initialization::PlusPlus, None), 0).unwrap();
+        assert_eq!(vocabulary.num_words(), 3);
+        assert_eq!(vocabulary.word(2), &[1.0]);
+    }
+
+    #[test]
+    fn test_build_vocabulary_invalid() {
+        let descriptors = vec![vec![1.0, 2.0], vec![3.0]];
+        let result = build_vocabulary(&descriptors, options(Initialization::Random, Some(0)), 0);
+        assert_eq!(
+            result,
+            Err(ImageProcError::InvalidOptions(
+                "need at least 3 descriptors, found 2, descriptors must have equal lengths, \
+                 batch_size must be non-zero".to_owned()
+            ))
+        );
+        let empty: Vec<Vec<f32>> = vec![];
+        assert!(build_vocabulary(&empty, KMeansOptions { num_words: 0, ..options(Initialization::Random, None) }, 0).is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let vocabulary = Vocabulary::new(&[[0.0, 0.0], [4.0, 0.0]]);
+        assert_eq!(vocabulary.nearest_word(&[1.0, 1.0]), (0, 2.0));
+        // Equidistant descriptors go to the earlier word.
+        assert_eq!(vocabulary.nearest_word(&[2.0, 0.0]), (0, 4.0));
+
+        let descriptors = [[1.0, 0.0], [3.0, 0.0], [5.0, 0.0], [4.0, 1.0]];
+        assert_eq!(vocabulary.encode(&descriptors, Encoding::Hard), vec![0.25, 0.75]);
+        let no_descriptors: [[f32; 2]; 0] = [];
+        assert_eq!(vocabulary.encode(&no_descriptors, Encoding::Hard), vec![0.0, 0.0]);
+
+        // Squared distances 1 and 9, so weights are proportional to exp(-1 / 8) and exp(-9 / 8).
+        let soft = vocabulary.encode(&descriptors[..1], Encoding::Soft { sigma: 2.0 });
+        let expected = 1.0 / (1.0 + (-1f32).exp());
+        assert!((soft[0] - expected).abs() < 1e-6);
+        assert!((soft[0] + soft[1] - 1.0).abs() < 1e-6);
+
+        // Far from both words.
+        let far = vocabulary.encode(&[[1000.0, 0.0]], Encoding::Soft { sigma: 0.1 });
+        assert_eq!(far, vec![0.0, 1.0]);
+    }
+
+    #[bench]
+    fn bench_build_vocabulary(b: &mut Bencher) {
+        let descriptors = clustered(3000, 1);
+        let opts = KMeansOptions { num_words: 20, ..options(Initialization::PlusPlus, None) };
+        b.iter(|| black_box(build_vocabulary(&descriptors, opts, 1).unwrap()));
+    }
+
+    #[bench]
+    fn bench_build_vocabulary_mini_batch(b: &mut Bencher) {
+        let descriptors = clustered(3000, 1);
+        let opts = KMeansOptions { num_words: 20, ..options(Initialization::PlusPlus, Some(100)) };
+        b.iter(|| black_box(build_vocabulary(&descriptors, opts, 1).unwrap()));
+    }
+}
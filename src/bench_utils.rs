@@ -0,0 +1,153 @@
+//! Helpers for generating representative inputs to benchmarks.
+//!
+//! These are used by the benchmarks in this crate's `benches` directory, and are
+//! exposed so that downstream crates can measure the performance of their own
+//! pipelines on the same inputs. All functions are deterministic: the same arguments
+//! always produce the same output.
+//!
+//! [`set_simd_enabled`](fn.set_simd_enabled.html) allows benchmarks to compare the
+//! explicitly vectorised loops used by some functions with their scalar equivalents.
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use geometric_transformations::Projection;
+use haar::HaarFeature;
+use rand::{Rng, SeedableRng, StdRng};
+use simd;
+use std::f32;
+
+/// Side lengths of the square images used by the benchmarks in this crate.
+pub const BENCH_IMAGE_SIZES: [u32; 3] = [64, 256, 1024];
+
+/// Returns a smoothly varying intensity in `[0, 1]` at `(x, y)`, with a few broad
+/// bright and dark regions across an image of the given size.
+fn smooth_intensity(x: u32, y: u32, width: u32, height: u32) -> f32 {
+    let u = x as f32 / width.max(1) as f32;
+    let v = y as f32 / height.max(1) as f32;
+    let waves = (3.0 * f32::consts::PI * u).sin() * (2.0 * f32::consts::PI * v).cos();
+    (0.5 * (u + v) + 0.25 * waves).max(0.0).min(1.0)
+}
+
+fn rng_from_seed(seed: usize) -> StdRng {
+    let seed_array: &[_] = &[seed];
+    SeedableRng::from_seed(seed_array)
+}
+
+fn add_noise(value: f32, noise: i32) -> u8 {
+    (value * 255.0 + noise as f32).max(0.0).min(255.0) as u8
+}
+
+/// Returns a grayscale image of smooth gradients with added uniform noise of
+/// amplitude up to 16 intensity levels.
+///
+/// Unlike [`gray_bench_image`](../utils/fn.gray_bench_image.html) this has both large
+/// uniform-ish regions and fine detail, so is more representative of natural images
+/// for algorithms whose running time depends on the image content.
+pub fn textured_gray_image(width: u32, height: u32, seed: usize) -> GrayImage {
+    let mut rng = rng_from_seed(seed);
+    GrayImage::from_fn(width, height, |x, y| {
+        let noise = rng.gen_range(-16, 17);
+        Luma([add_noise(smooth_intensity(x, y, width, height), noise)])
+    })
+}
+
+/// Returns an RGB image whose channels are independently generated as in
+/// [`textured_gray_image`](fn.textured_gray_image.html), with the gradients of the
+/// channels running in different directions.
+pub fn textured_rgb_image(width: u32, height: u32, seed: usize) -> RgbImage {
+    let mut rng = rng_from_seed(seed);
+    RgbImage::from_fn(width, height, |x, y| {
+        let r = smooth_intensity(x, y, width, height);
+        let g = smooth_intensity(width - 1 - x, y, width, height);
+        let b = smooth_intensity(x, height - 1 - y, width, height);
+        Rgb([
+            add_noise(r, rng.gen_range(-16, 17)),
+            add_noise(g, rng.gen_range(-16, 17)),
+            add_noise(b, rng.gen_range(-16, 17)),
+        ])
+    })
+}
+
+/// Returns a binary image whose pixels are 255 with probability `foreground_fraction`
+/// and 0 otherwise. Suitable as input to the functions in
+/// [`morphology`](../morphology/index.html) and
+/// [`distance_transform`](../distance_transform/index.html).
+pub fn binary_image(width: u32, height: u32, foreground_fraction: f32, seed: usize) -> GrayImage {
+    let mut rng = rng_from_seed(seed);
+    GrayImage::from_fn(width, height, |_, _| {
+        if rng.gen::<f32>() < foreground_fraction {
+            Luma([255u8])
+        } else {
+            Luma([0u8])
+        }
+    })
+}
+
+/// Returns `count` Haar-like features for a square frame with side `frame_size`,
+/// sampled evenly from the full set yielded by
+/// [`HaarFeature::enumerate`](../haar/struct.HaarFeature.html#method.enumerate) so that
+/// all feature types and a range of sizes are represented.
+///
+/// Returns every feature if `count` is at least the total number of features.
+pub fn sample_haar_features(frame_size: u8, count: usize) -> Vec<HaarFeature> {
+    let all: Vec<HaarFeature> = HaarFeature::enumerate(frame_size, frame_size).collect();
+    if count >= all.len() {
+        return all;
+    }
+    (0..count).map(|i| all[i * all.len() / count]).collect()
+}
+
+/// Returns a projection that rotates an image of the given size by `theta` radians
+/// about its center and scales it up by 10%, a typical non-trivial warp.
+pub fn bench_projection(width: u32, height: u32, theta: f32) -> Projection {
+    let center = (width as f32 / 2.0, height as f32 / 2.0);
+    Projection::translate(center.0, center.1)
+        * Projection::rotate(theta)
+        * Projection::scale(1.1, 1.1)
+        * Projection::translate(-center.0, -center.1)
+}
+
+/// Enables or disables the explicitly vectorised loops used by the box filter, integral
+/// images, Haar-like feature evaluation and correlation with single-channel kernels.
+/// They are enabled by default, and produce the same results as the scalar code.
+///
+/// This setting is global, so affects every thread.
+pub fn set_simd_enabled(enabled: bool) {
+    simd::set_enabled(enabled);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use haar::number_of_haar_features;
+
+    #[test]
+    fn test_generated_images_are_deterministic() {
+        assert_pixels_eq!(textured_gray_image(20, 10, 3), textured_gray_image(20, 10, 3));
+        assert_pixels_eq!(textured_rgb_image(20, 10, 3), textured_rgb_image(20, 10, 3));
+        assert_pixels_eq!(binary_image(20, 10, 0.3, 3), binary_image(20, 10, 0.3, 3));
+    }
+
+    #[test]
+    fn test_binary_image_values() {
+        let image = binary_image(30, 30, 0.5, 1);
+        assert!(image.pixels().all(|p| p[0] == 0 || p[0] == 255));
+        assert!(image.pixels().any(|p| p[0] == 0));
+        assert!(image.pixels().any(|p| p[0] == 255));
+        assert!(binary_image(10, 10, 0.0, 1).pixels().all(|p| p[0] == 0));
+        assert!(binary_image(10, 10, 1.0, 1).pixels().all(|p| p[0] == 255));
+    }
+
+    #[test]
+    fn test_sample_haar_features() {
+        let total = number_of_haar_features(8, 8) as usize;
+        assert_eq!(sample_haar_features(8, 10).len(), 10);
+        assert_eq!(sample_haar_features(8, total + 5).len(), total);
+    }
+
+    #[test]
+    fn test_bench_projection_fixes_center() {
+        let projection = bench_projection(100, 60, 0.3);
+        let (x, y) = projection * (50.0, 30.0);
+        assert!((x - 50.0).abs() < 1e-3 && (y - 30.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,461 @@
+//! Binary feature descriptors, which describe the neighbourhood of a keypoint by the
+//! results of a fixed set of intensity comparisons. Descriptors are compared using
+//! the Hamming distance, which is much cheaper to compute than distances between
+//! floating point descriptors.
+//!
+//! See [BRIEF](https://www.cs.ubc.ca/~lowe/525/papers/calonder_eccv10.pdf) and
+//! [ORB](http://www.willowgarage.com/sites/default/files/orb_final.pdf).
+
+use definitions::Position;
+use filter::gaussian_blur_f32;
+use image::{GenericImageView, GrayImage};
+use rand::{SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Normal};
+use std::borrow::Cow;
+use std::cmp;
+
+/// A set of pairs of offsets from a keypoint. Each pair defines a binary test
+/// comparing the intensities at the two offsets.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BriefPattern {
+    tests: Vec<[(i32, i32); 2]>,
+}
+
+impl BriefPattern {
+    /// Creates a pattern from the given pairs of offsets.
+    ///
+    /// # Panics
+    /// If `tests` is empty.
+    pub fn new(tests: Vec<[(i32, i32); 2]>) -> BriefPattern {
+        assert!(!tests.is_empty(), "pattern must contain at least one test");
+        BriefPattern { tests }
+    }
+
+    /// Creates a pattern of `length` tests whose offsets are drawn independently from an
+    /// isotropic Gaussian with standard deviation `patch_size / 5` and then clamped to the
+    /// `patch_size` square centred on the keypoint. This is the pattern that performed best
+    /// in the BRIEF paper. The same seed always produces the same pattern, and descriptors
+    /// can only be compared if they were computed using the same pattern.
+    ///
+    /// # Panics
+    /// If `length` or `patch_size` is 0.
+    pub fn random(length: usize, patch_size: u32, seed: usize) -> BriefPattern {
+        assert!(length > 0, "length must be positive");
+        assert!(patch_size > 0, "patch_size must be positive");
+        let seed_array: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+        let normal = Normal::new(0.0, patch_size as f64 / 5.0);
+        let half = (patch_size as i32 - 1) / 2;
+
+        let mut offset = || {
+            let mut sample = || (normal.ind_sample(&mut rng).round() as i32).max(-half).min(half);
+            (sample(), sample())
+        };
+        let tests = (0..length).map(|_| [offset(), offset()]).collect();
+        BriefPattern { tests }
+    }
+
+    /// Number of tests in this pattern, i.e. the length in bits of the descriptors it computes.
+    pub fn num_tests(&self) -> usize {
+        self.tests.len()
+    }
+
+    /// The pairs of offsets compared by this pattern.
+    pub fn tests(&self) -> &[[(i32, i32); 2]] {
+        &self.tests
+    }
+
+    /// Greatest distance of any offset in this pattern from the keypoint.
+    pub fn radius(&self) -> f32 {
+        self.tests
+            .iter()
+            .flat_map(|t| t.iter())
+            .map(|&(dx, dy)| ((dx * dx + dy * dy) as f32).sqrt())
+            .fold(0.0, f32::max)
+    }
+
+    /// Greatest absolute value of any coordinate of an offset in this pattern.
+    fn extent(&self) -> u32 {
+        self.tests
+            .iter()
+            .flat_map(|t| t.iter())
+            .map(|&(dx, dy)| cmp::max(dx.abs(), dy.abs()) as u32)
+            .max()
+            .unwrap()
+    }
+}
+
+/// The binary descriptor of a keypoint.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BinaryDescriptor {
+    /// x-coordinate of the keypoint.
+    pub x: u32,
+    /// y-coordinate of the keypoint.
+    pub y: u32,
+    /// Angle in radians by which the pattern was rotated, measured clockwise from the
+    /// positive x-axis as the y-axis points down. This is 0 for BRIEF descriptors.
+    pub angle: f32,
+    bits: Vec<u64>,
+    length: usize,
+}
+
+impl BinaryDescriptor {
+    /// Number of bits in this descriptor.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The result of the test with the given index.
+    ///
+    /// # Panics
+    /// If `index` is not less than the length of this descriptor.
+    pub fn bit(&self, index: usize) -> bool {
+        assert!(index < self.length, "index {} out of bounds for descriptor of length {}", index, self.length);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Number of tests whose results differ between this descriptor and `other`.
+    ///
+    /// # Panics
+    /// If the descriptors have different lengths.
+    pub fn hamming_distance(&self, other: &BinaryDescriptor) -> u32 {
+        assert_eq!(self.length, other.length, "descriptors must have the same length");
+        self.bits.iter().zip(&other.bits).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+}
+
+/// Computes the [BRIEF](https://www.cs.ubc.ca/~lowe/525/papers/calonder_eccv10.pdf)
+/// descriptor of each keypoint. Bit `i` of a descriptor is set if the intensity at the first
+/// offset of test `i` of `pattern` is less than the intensity at the second offset.
+///
+/// The image is first smoothed by a Gaussian with standard deviation `sigma` to reduce
+/// sensitivity to noise. A value of 2 is typical, and no smoothing is applied if `sigma`
+/// is 0. Keypoints for which any test lies outside the image are skipped, so the result
+/// may have fewer entries than `keypoints`.
+///
+/// BRIEF descriptors are not rotation invariant. See
+/// [`orb_descriptors`](fn.orb_descriptors.html) for a rotation invariant variant.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::binary_descriptors::{brief_descriptors, BriefPattern};
+/// use imageproc::corners::Corner;
+///
+/// let image = GrayImage::from_fn(40, 20, |x, y| Luma([((x % 20) * 7 + y * y) as u8]));
+/// let pattern = BriefPattern::random(256, 15, 1);
+///
+/// // The image repeats horizontally every 20 pixels.
+/// let keypoints = [Corner::new(9, 9, 0.0), Corner::new(29, 9, 0.0), Corner::new(1, 1, 0.0)];
+/// let descriptors = brief_descriptors(&image, &keypoints, &pattern, 0.0);
+///
+/// // The last keypoint is too close to the image boundary to be described.
+/// assert_eq!(descriptors.len(), 2);
+/// assert_eq!(descriptors[0].hamming_distance(&descriptors[1]), 0);
+/// # }
+/// ```
+pub fn brief_descriptors<K: Position>(
+    image: &GrayImage,
+    keypoints: &[K],
+    pattern: &BriefPattern,
+    sigma: f32,
+) -> Vec<BinaryDescriptor> {
+    let smoothed = smooth(image, sigma);
+    let margin = pattern.extent();
+    keypoints
+        .iter()
+        .filter(|k| is_within_margin(&smoothed, k.x(), k.y(), margin))
+        .map(|k| describe(&smoothed, k.x(), k.y(), 0.0, pattern))
+        .collect()
+}
+
+/// Computes the rotation invariant [ORB](http://www.willowgarage.com/sites/default/files/orb_final.pdf)
+/// descriptor of each keypoint.
+///
+/// The orientation of each keypoint is the direction from it to the intensity centroid of
+/// the disc of radius `pattern.radius()` around it, and the offsets of `pattern` are rotated
+/// by this angle before computing a [BRIEF](fn.brief_descriptors.html) descriptor.
+/// Rotating the image therefore leaves descriptors approximately unchanged.
+///
+/// The image is smoothed as for `brief_descriptors`, and keypoints for which the rotated
+/// pattern or disc may extend outside the image are skipped.
+pub fn orb_descriptors<K: Position>(
+    image: &GrayImage,
+    keypoints: &[K],
+    pattern: &BriefPattern,
+    sigma: f32,
+) -> Vec<BinaryDescriptor> {
+    let smoothed = smooth(image, sigma);
+    let radius = pattern.radius();
+    // Rotated offsets have magnitude at most radius, so round to
+    // offsets whose coordinates have magnitude at most ceil(radius).
+    let margin = radius.ceil() as u32;
+    keypoints
+        .iter()
+        .filter(|k| is_within_margin(&smoothed, k.x(), k.y(), margin))
+        .map(|k| {
+            let angle = intensity_centroid_angle(&smoothed, k.x(), k.y(), radius);
+            describe(&smoothed, k.x(), k.y(), angle, pattern)
+        })
+        .collect()
+}
+
+/// A match between descriptors returned by [`match_descriptors`](fn.match_descriptors.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DescriptorMatch {
+    /// Index of the query descriptor.
+    pub query: usize,
+    /// Index of the nearest train descriptor to the query descriptor.
+    pub train: usize,
+    /// Hamming distance between the two descriptors.
+    pub distance: u32,
+}
+
+/// Matches each query descriptor to its nearest train descriptor by Hamming distance,
+/// using brute force search. Ties are resolved in favour of the earlier train descriptor.
+///
+/// A match is only returned if its distance is less than `max_ratio` times the distance
+/// to the second nearest train descriptor, which rejects ambiguous matches. This is
+/// [Lowe's ratio test](https://www.cs.ubc.ca/~lowe/papers/ijcv04.pdf). Values of 0.7 to 0.8
+/// are typical. All matches pass the test if there is only one train descriptor.
+///
+/// Matches are returned in order of query index.
+///
+/// # Panics
+/// If `max_ratio` is not positive, or the descriptors do not all have the same length.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::binary_descriptors::{brief_descriptors, match_descriptors, BriefPattern};
+/// use imageproc::corners::Corner;
+///
+/// let image = GrayImage::from_fn(60, 30, |x, y| Luma([((x * x + 3 * y * y + x * y) % 251) as u8]));
+/// let pattern = BriefPattern::random(256, 15, 7);
+/// let keypoints = [Corner::new(10, 15, 0.0), Corner::new(30, 15, 0.0), Corner::new(45, 15, 0.0)];
+/// let descriptors = brief_descriptors(&image, &keypoints, &pattern, 1.0);
+///
+/// // Every descriptor is its own unambiguous nearest neighbour.
+/// let matches = match_descriptors(&descriptors, &descriptors, 0.8);
+/// assert_eq!(matches.len(), 3);
+/// assert!(matches.iter().all(|m| m.query == m.train && m.distance == 0));
+/// # }
+/// ```
+pub fn match_descriptors(
+    query: &[BinaryDescriptor],
+    train: &[BinaryDescriptor],
+    max_ratio: f32,
+) -> Vec<DescriptorMatch> {
+    assert!(max_ratio > 0.0, "max_ratio must be positive, got {}", max_ratio);
+    let mut matches = vec![];
+    for (q, descriptor) in query.iter().enumerate() {
+        let mut best: Option<(usize, u32)> = None;
+        let mut second_distance = None;
+        for (t, candidate) in train.iter().enumerate() {
+            let distance = descriptor.hamming_distance(candidate);
+            match best {
+                Some((_, best_distance)) if distance >= best_distance => {
+                    if second_distance.map_or(true, |d| distance < d) {
+                        second_distance = Some(distance);
+                    }
+                }
+                _ => {
+                    second_distance = best.map(|(_, d)| d);
+                    best = Some((t, distance));
+                }
+            }
+        }
+        if let Some((t, distance)) = best {
+            let unambiguous = second_distance.map_or(true, |d| (distance as f32) < max_ratio * d as f32);
+            if unambiguous {
+                matches.push(DescriptorMatch { query: q, train: t, distance });
+            }
+        }
+    }
+    matches
+}
+
+fn smooth<'a>(image: &'a GrayImage, sigma: f32) -> Cow<'a, GrayImage> {
+    assert!(sigma >= 0.0, "sigma must be non-negative, got {}", sigma);
+    if sigma > 0.0 { Cow::Owned(gaussian_blur_f32(image, sigma)) } else { Cow::Borrowed(image) }
+}
+
+fn is_within_margin(image: &GrayImage, x: u32, y: u32, margin: u32) -> bool {
+    x >= margin && y >= margin && x + margin < image.width() && y + margin < image.height()
+}
+
+/// Direction from (x, y) to the intensity centroid of the disc of the given radius around it.
+/// Requires the disc to lie within the image.
+fn intensity_centroid_angle(image: &GrayImage, x: u32, y: u32, radius: f32) -> f32 {
+    let r = radius.floor() as i32;
+    let (mut m10, mut m01) = (0f32, 0f32);
+    for dy in -r..r + 1 {
+        for dx in -r..r + 1 {
+            if (dx * dx + dy * dy) as f32 > radius * radius {
+                continue;
+            }
+            let intensity = unsafe {
+                image.unsafe_get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32)[0]
+            } as f32;
+            m10 += dx as f32 * intensity;
+            m01 += dy as f32 * intensity;
+        }
+    }
+    m01.atan2(m10)
+}
+
+/// Requires every offset of `pattern`, rotated by `angle`, to lie within the image.
+fn describe(image: &GrayImage, x: u32, y: u32, angle: f32, pattern: &BriefPattern) -> BinaryDescriptor {
+    let (sin, cos) = angle.sin_cos();
+    let intensity = |(dx, dy): (i32, i32)| {
+        let (dx, dy) = if angle == 0.0 {
+            (dx, dy)
+        } else {
+            let (dx, dy) = (dx as f32, dy as f32);
+            ((dx * cos - dy * sin).round() as i32, (dx * sin + dy * cos).round() as i32)
+        };
+        unsafe { image.unsafe_get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32)[0] }
+    };
+
+    let length = pattern.num_tests();
+    let mut bits = vec![0u64; (length + 63) / 64];
+    for (i, test) in pattern.tests.iter().enumerate() {
+        if intensity(test[0]) < intensity(test[1]) {
+            bits[i / 64] |= 1 << (i % 64);
+        }
+    }
+    BinaryDescriptor { x, y, angle, bits, length }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use corners::Corner;
+    use image::{GrayImage, Luma};
+    use noise::gaussian_noise;
+    use test::{Bencher, black_box};
+
+    fn noise_image(side: u32, seed: usize) -> GrayImage {
+        let image = gaussian_noise(&GrayImage::from_pixel(side, side, Luma([128])), 0.0, 60.0, seed);
+        // Smooth so that rounding rotated offsets to neighbouring pixels changes little.
+        gaussian_blur_f32(&image, 1.5)
+    }
+
+    /// Rotates a square image by 90 degrees clockwise.
+    fn rotate90(image: &GrayImage) -> GrayImage {
+        let side = image.width();
+        GrayImage::from_fn(side, side, |x, y| *image.get_pixel(y, side - 1 - x))
+    }
+
+    #[test]
+    fn test_random_pattern() {
+        let pattern = BriefPattern::random(300, 31, 3);
+        assert_eq!(pattern.num_tests(), 300);
+        assert_eq!(pattern, BriefPattern::random(300, 31, 3));
+        assert!(pattern != BriefPattern::random(300, 31, 4));
+        assert!(pattern.tests().iter().flat_map(|t| t.iter()).all(|&(x, y)| x.abs() <= 15 && y.abs() <= 15));
+        assert!(pattern.radius() <= 15.0 * 2f32.sqrt());
+    }
+
+    #[test]
+    fn test_descriptor_bits_and_distance() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6;
+            9, 8, 7);
+        let pattern = BriefPattern::new(vec![
+            [(-1, -1), (1, 1)],
+            [(1, 1), (-1, 1)],
+            [(0, 0), (0, -1)],
+        ]);
+        let d = brief_descriptors(&image, &[Corner::new(1, 1, 0.0)], &pattern, 0.0);
+        assert_eq!(d.len(), 1);
+        assert_eq!((d[0].bit(0), d[0].bit(1), d[0].bit(2)), (true, true, false));
+        assert_eq!(d[0].length(), 3);
+        assert_eq!(brief_descriptors(&image, &[Corner::new(0, 1, 0.0)], &pattern, 0.0), vec![]);
+
+        let reversed = BriefPattern::new(pattern.tests().iter().map(|t| [t[1], t[0]]).collect());
+        let e = brief_descriptors(&image, &[Corner::new(1, 1, 0.0)], &reversed, 0.0);
+        assert_eq!(d[0].hamming_distance(&e[0]), 3);
+    }
+
+    #[test]
+    fn test_long_descriptors() {
+        let image = noise_image(41, 1);
+        let pattern = BriefPattern::random(200, 21, 1);
+        let d = brief_descriptors(&image, &[Corner::new(20, 20, 0.0)], &pattern, 0.0);
+        let ones = (0..200).filter(|&i| d[0].bit(i)).count();
+        // Roughly half of the tests on a noisy image succeed.
+        assert!(ones > 50 && ones < 150, "{}", ones);
+    }
+
+    #[test]
+    fn test_orb_rotation_invariance() {
+        let image = noise_image(61, 2);
+        let rotated = rotate90(&image);
+        let pattern = BriefPattern::random(256, 21, 5);
+        let keypoint = [Corner::new(30, 30, 0.0)];
+
+        let orb = orb_descriptors(&image, &keypoint, &pattern, 1.0);
+        let orb_rotated = orb_descriptors(&rotated, &keypoint, &pattern, 1.0);
+        let angle_change = orb_rotated[0].angle - orb[0].angle;
+        assert!((angle_change.sin() - 1.0).abs() < 1e-3, "{}", angle_change);
+        assert!(orb[0].hamming_distance(&orb_rotated[0]) < 20);
+
+        let brief = brief_descriptors(&image, &keypoint, &pattern, 1.0);
+        let brief_rotated = brief_descriptors(&rotated, &keypoint, &pattern, 1.0);
+        assert!(brief[0].hamming_distance(&brief_rotated[0]) > 80);
+    }
+
+    #[test]
+    fn test_match_descriptors() {
+        let image = noise_image(80, 3);
+        let pattern = BriefPattern::random(256, 15, 2);
+        let keypoints: Vec<Corner> = (0..5).map(|i| Corner::new(10 + 14 * i, 40, 0.0)).collect();
+        let train = brief_descriptors(&image, &keypoints, &pattern, 1.0);
+        let query = vec![train[3].clone(), train[1].clone()];
+
+        assert_eq!(
+            match_descriptors(&query, &train, 0.8),
+            vec![
+                DescriptorMatch { query: 0, train: 3, distance: 0 },
+                DescriptorMatch { query: 1, train: 1, distance: 0 },
+            ]
+        );
+
+        // Duplicated train descriptors make every match ambiguous.
+        let duplicated = vec![train[1].clone(), train[1].clone()];
+        assert_eq!(match_descriptors(&query[1..], &duplicated, 0.8), vec![]);
+        assert_eq!(
+            match_descriptors(&query[1..], &duplicated[..1], 0.8),
+            vec![DescriptorMatch { query: 0, train: 0, distance: 0 }]
+        );
+        assert_eq!(match_descriptors(&query, &[], 0.8), vec![]);
+    }
+
+    #[bench]
+    fn bench_orb_descriptors(b: &mut Bencher) {
+        let image = noise_image(200, 4);
+        let pattern = BriefPattern::random(256, 31, 1);
+        let keypoints: Vec<Corner> = (0..100).map(|i| Corner::new(30 + (i % 10) * 14, 30 + (i / 10) * 14, 0.0)).collect();
+        b.iter(|| black_box(orb_descriptors(&image, &keypoints, &pattern, 2.0)));
+    }
+
+    #[bench]
+    fn bench_match_descriptors(b: &mut Bencher) {
+        let image = noise_image(200, 5);
+        let pattern = BriefPattern::random(256, 31, 1);
+        let keypoints: Vec<Corner> = (0..100).map(|i| Corner::new(30 + (i % 10) * 14, 30 + (i / 10) * 14, 0.0)).collect();
+        let descriptors = brief_descriptors(&image, &keypoints, &pattern, 2.0);
+        b.iter(|| black_box(match_descriptors(&descriptors, &descriptors, 0.8)));
+    }
+}
@@ -0,0 +1,754 @@
+//! Functions for detecting blobs, i.e. bright or dark regions of roughly uniform
+//! intensity, at multiple scales.
+
+use image::{GenericImageView, GrayImage, Luma};
+use definitions::{Image, Position, Score};
+use error::{check_non_empty, ImageProcError, Result};
+use filter::separable_filter_equal;
+use integral_image::integral_image;
+use map::map_subpixels;
+use pyramid::{gaussian_pyramid, normalized_gaussian_kernel, PyramidOptions};
+
+/// A location, scale and score for a detected blob.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Blob {
+    /// x-coordinate of the blob centre.
+    pub x: u32,
+    /// y-coordinate of the blob centre.
+    pub y: u32,
+    /// Standard deviation of the Gaussian whose second derivatives are
+    /// approximated by the filter that detected this blob. This is roughly
+    /// proportional to the blob's radius.
+    pub scale: f32,
+    /// Determinant of the Hessian at the blob centre.
+    pub score: f32,
+    /// True if the blob is darker than its surroundings, i.e. the
+    /// trace of the Hessian is positive.
+    pub dark: bool,
+}
+
+impl Position for Blob {
+    /// x-coordinate of the blob centre.
+    fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// y-coordinate of the blob centre.
+    fn y(&self) -> u32 {
+        self.y
+    }
+}
+
+impl Score for Blob {
+    fn score(&self) -> f32 {
+        self.score
+    }
+}
+
+/// Options for [`hessian_blobs`](fn.hessian_blobs.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HessianOptions {
+    /// Number of octaves to search. Each octave doubles the range of filter
+    /// sizes and the sampling step of the previous one.
+    pub num_octaves: u32,
+    /// Sampling step for the first octave, in pixels.
+    pub initial_step: u32,
+    /// Minimum determinant of the Hessian for a blob to be detected. Responses
+    /// are computed with intensities scaled to the range [0, 1].
+    pub threshold: f32,
+}
+
+impl Default for HessianOptions {
+    fn default() -> Self {
+        HessianOptions {
+            num_octaves: 4,
+            initial_step: 2,
+            threshold: 0.0004,
+        }
+    }
+}
+
+/// Number of filter sizes per octave.
+const LAYERS_PER_OCTAVE: u32 = 4;
+
+/// Relative weight of the mixed derivative in the determinant, compensating
+/// for the box filter approximation.
+const DXY_WEIGHT: f32 = 0.81;
+
+/// Side length of the box filter for layer `layer` of octave `octave`.
+/// The first octave uses sizes 9, 15, 21 and 27, the second 15, 27, 39 and 51, etc.
+fn filter_size(octave: u32, layer: u32) -> u32 {
+    3 * ((2 << octave) * (layer + 1) + 1)
+}
+
+/// Computes the determinant of the Hessian at every pixel of an image, approximating
+/// the second derivatives of a Gaussian by box filters of side `filter_size`, as in
+/// [SURF](https://en.wikipedia.org/wiki/Speeded_up_robust_features). A filter size of 9
+/// approximates a Gaussian with standard deviation 1.2, and the standard deviation
+/// scales linearly with filter size.
+///
+/// Intensities are scaled to the range [0, 1] and responses are normalised by the filter
+/// area, so responses at different filter sizes are comparable. Boxes are clipped to the
+/// image bounds.
+///
+/// # Panics
+/// If `filter_size` is not an odd multiple of 3 that is at least 9.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::GrayImage;
+/// use imageproc::blobs::determinant_of_hessian;
+///
+/// // Constant images have no second derivatives.
+/// let image = GrayImage::from_pixel(30, 30, image::Luma([100]));
+/// let response = determinant_of_hessian(&image, 9);
+/// assert_eq!(response.get_pixel(15, 15)[0], 0.0);
+/// # }
+/// ```
+pub fn determinant_of_hessian(image: &GrayImage, filter_size: u32) -> Image<Luma<f32>> {
+    assert!(
+        filter_size >= 9 && filter_size % 6 == 3,
+        "filter_size must be an odd multiple of 3 that is at least 9, got {}",
+        filter_size
+    );
+    let integral = integral_image(image);
+    let (width, height) = image.dimensions();
+    let mut out = Image::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let (det, _) = hessian_response(&integral, x as i64, y as i64, filter_size as i64);
+            out.put_pixel(x, y, Luma([det]));
+        }
+    }
+    out
+}
+
+/// Detects blobs as local maxima of the determinant of the Hessian over position and
+/// scale, using the box filter approximation described in
+/// [`determinant_of_hessian`](fn.determinant_of_hessian.html).
+///
+/// Each octave evaluates four filter sizes on a grid whose spacing doubles with each
+/// octave. A sample is reported if its response exceeds `options.threshold` and is
+/// strictly greater than its 26 neighbours in position and scale within its octave,
+/// so only the middle two filter sizes of each octave can produce blobs. Samples whose
+/// largest filter would extend past the image boundary are not considered.
+///
+/// Returns `ImageProcError::InvalidOptions` if `num_octaves` or `initial_step` is 0.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::blobs::{hessian_blobs, HessianOptions};
+/// use imageproc::drawing::draw_filled_circle_mut;
+///
+/// // A dark disk of radius 6 on a light background.
+/// let mut image = GrayImage::from_pixel(60, 60, Luma([220]));
+/// draw_filled_circle_mut(&mut image, (30, 30), 6, Luma([20]));
+///
+/// let options = HessianOptions { initial_step: 1, ..HessianOptions::default() };
+/// let blobs = hessian_blobs(&image, options).unwrap();
+///
+/// // Weaker responses of the opposite polarity occur just outside the disk.
+/// let strongest = blobs.iter().max_by(|a, b| a.score.partial_cmp(&b.score).unwrap()).unwrap();
+/// assert_eq!((strongest.x, strongest.y), (30, 30));
+/// assert!(strongest.dark);
+/// # }
+/// ```
+pub fn hessian_blobs(image: &GrayImage, options: HessianOptions) -> Result<Vec<Blob>> {
+    let mut errors = vec![];
+    if options.num_octaves == 0 {
+        errors.push("num_octaves must be non-zero");
+    }
+    if options.initial_step == 0 {
+        errors.push("initial_step must be non-zero");
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+
+    let integral = integral_image(image);
+    let (width, height) = image.dimensions();
+    let mut blobs = vec![];
+
+    for octave in 0..options.num_octaves {
+        let step = options.initial_step << octave;
+        let largest = filter_size(octave, LAYERS_PER_OCTAVE - 1);
+        // Samples within this distance of the boundary would have their
+        // largest filter clipped.
+        let border = largest / 2 + 1;
+        if width < 2 * border + 1 || height < 2 * border + 1 {
+            break;
+        }
+
+        let grid_width = ((width + step - 1) / step) as usize;
+        let grid_height = ((height + step - 1) / step) as usize;
+
+        let layers: Vec<Vec<(f32, bool)>> = (0..LAYERS_PER_OCTAVE)
+            .map(|layer| {
+                let size = filter_size(octave, layer) as i64;
+                let mut responses = Vec::with_capacity(grid_width * grid_height);
+                for gy in 0..grid_height {
+                    for gx in 0..grid_width {
+                        let (x, y) = ((gx as u32 * step) as i64, (gy as u32 * step) as i64);
+                        responses.push(hessian_response(&integral, x, y, size));
+                    }
+                }
+                responses
+            })
+            .collect();
+
+        for layer in 1..LAYERS_PER_OCTAVE as usize - 1 {
+            for gy in 1..grid_height - 1 {
+                for gx in 1..grid_width - 1 {
+                    let (det, trace_positive) = layers[layer][gy * grid_width + gx];
+                    let (x, y) = (gx as u32 * step, gy as u32 * step);
+                    if det <= options.threshold
+                        || x < border || y < border
+                        || x >= width - border || y >= height - border {
+                        continue;
+                    }
+                    if is_maximum(&layers, layer, gx, gy, grid_width, det) {
+                        let size = filter_size(octave, layer as u32);
+                        blobs.push(Blob {
+                            x,
+                            y,
+                            scale: 1.2 * size as f32 / 9.0,
+                            score: det,
+                            dark: trace_positive,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(blobs)
+}
+
+/// Returns true if `value` is strictly greater than the 26 neighbouring
+/// responses of (gx, gy) in `layer` and its adjacent layers.
+fn is_maximum(layers: &[Vec<(f32, bool)>], layer: usize, gx: usize, gy: usize, grid_width: usize, value: f32) -> bool {
+    for l in layer - 1..layer + 2 {
+        for y in gy - 1..gy + 2 {
+            for x in gx - 1..gx + 2 {
+                if (l, x, y) == (layer, gx, gy) {
+                    continue;
+                }
+                if layers[l][y * grid_width + x].0 >= value {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Determinant of the approximated Hessian at (x, y) for the given filter
+/// size, and whether its trace is positive.
+fn hessian_response(integral: &Image<Luma<u32>>, x: i64, y: i64, size: i64) -> (f32, bool) {
+    let lobe = size / 3;
+    let half = (size - 1) / 2;
+    let sum = |left: i64, top: i64, w: i64, h: i64| clipped_box_sum(integral, left, top, w, h);
+
+    // Three lobes of length `lobe` weighted 1, -2, 1 along the derivative direction,
+    // each 2 * lobe - 1 wide.
+    let dxx = sum(x - half, y - lobe + 1, size, 2 * lobe - 1)
+        - 3.0 * sum(x - lobe / 2, y - lobe + 1, lobe, 2 * lobe - 1);
+    let dyy = sum(x - lobe + 1, y - half, 2 * lobe - 1, size)
+        - 3.0 * sum(x - lobe + 1, y - lobe / 2, 2 * lobe - 1, lobe);
+    // Four lobe * lobe squares diagonally adjacent to (x, y).
+    let dxy = sum(x + 1, y + 1, lobe, lobe) + sum(x - lobe, y - lobe, lobe, lobe)
+        - sum(x + 1, y - lobe, lobe, lobe) - sum(x - lobe, y + 1, lobe, lobe);
+
+    let normalization = 1.0 / (255.0 * (size * size) as f32);
+    let (dxx, dyy, dxy) = (dxx * normalization, dyy * normalization, dxy * normalization);
+    (dxx * dyy - DXY_WEIGHT * dxy * dxy, dxx + dyy > 0.0)
+}
+
+/// Sum of the pixels in the box with top left corner (left, top) and the given size,
+/// clipped to the image bounds.
+fn clipped_box_sum(integral: &Image<Luma<u32>>, left: i64, top: i64, width: i64, height: i64) -> f32 {
+    // The integral image is one larger than its source in each dimension.
+    let (w, h) = (integral.width() as i64 - 1, integral.height() as i64 - 1);
+    let clamp = |v: i64, max: i64| if v < 0 { 0 } else if v > max { max } else { v } as u32;
+    let (x0, x1) = (clamp(left, w), clamp(left + width, w));
+    let (y0, y1) = (clamp(top, h), clamp(top + height, h));
+    if x0 >= x1 || y0 >= y1 {
+        return 0.0;
+    }
+    let at = |x, y| unsafe { integral.unsafe_get_pixel(x, y)[0] } as i64;
+    (at(x1, y1) - at(x1, y0) - at(x0, y1) + at(x0, y0)) as f32
+}
+
+/// A blob detected by [`dog_blobs`](fn.dog_blobs.html), with subpixel position and scale.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DogKeypoint {
+    /// x-coordinate of the blob centre.
+    pub x: f32,
+    /// y-coordinate of the blob centre.
+    pub y: f32,
+    /// Standard deviation of the Gaussian at which the blob was detected, in pixels of
+    /// the input image. A disk of radius `r` is detected with `sigma` roughly equal to `r / sqrt(2)`.
+    pub sigma: f32,
+    /// Interpolated difference of Gaussians at the blob centre, with intensities scaled
+    /// to the range [0, 1]. Positive for blobs darker than their surroundings and
+    /// negative for blobs brighter than their surroundings.
+    pub response: f32,
+}
+
+impl Score for DogKeypoint {
+    fn score(&self) -> f32 {
+        self.response.abs()
+    }
+}
+
+/// Options for [`dog_blobs`](fn.dog_blobs.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DogOptions {
+    /// Maximum number of octaves to search. Each octave halves the size of the image
+    /// and doubles the range of scales of the previous one. Octaves that would be too
+    /// small to contain any keypoints are not created.
+    pub num_octaves: u32,
+    /// Number of scales per octave at which extrema are detected.
+    pub scales_per_octave: u32,
+    /// Standard deviation of the Gaussian blur at the smallest scale. The input image is
+    /// assumed to have been blurred with standard deviation 0.5 by the camera, so this
+    /// must be greater than 0.5.
+    pub initial_sigma: f32,
+    /// Minimum absolute interpolated response of a keypoint. Intensities are scaled
+    /// to the range [0, 1].
+    pub contrast_threshold: f32,
+    /// Maximum ratio between the principal curvatures of the difference of Gaussians at a
+    /// keypoint. Larger values keep more keypoints that lie along edges. Must be greater than 1.
+    pub edge_threshold: f32,
+}
+
+impl Default for DogOptions {
+    fn default() -> Self {
+        DogOptions {
+            num_octaves: 4,
+            scales_per_octave: 3,
+            initial_sigma: 1.6,
+            contrast_threshold: 0.03,
+            edge_threshold: 10.0,
+        }
+    }
+}
+
+impl DogOptions {
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.num_octaves == 0 {
+            errors.push("num_octaves must be non-zero");
+        }
+        if self.scales_per_octave == 0 {
+            errors.push("scales_per_octave must be non-zero");
+        }
+        if self.initial_sigma <= ASSUMED_BLUR || self.initial_sigma.is_nan() {
+            errors.push("initial_sigma must be greater than 0.5");
+        }
+        if self.contrast_threshold < 0.0 || self.contrast_threshold.is_nan() {
+            errors.push("contrast_threshold must be non-negative");
+        }
+        if self.edge_threshold <= 1.0 || self.edge_threshold.is_nan() {
+            errors.push("edge_threshold must be greater than 1");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Standard deviation of the blur assumed to be present in input images.
+const ASSUMED_BLUR: f32 = 0.5;
+
+/// Keypoints within this many pixels of the boundary of an octave are not considered.
+const DOG_BORDER: usize = 5;
+
+/// Maximum number of times a candidate keypoint may move to a neighbouring sample
+/// during refinement.
+const MAX_REFINEMENT_STEPS: usize = 5;
+
+/// Detects blobs as extrema of a difference of Gaussians (DoG) scale space, as in the
+/// keypoint detector of [SIFT](https://en.wikipedia.org/wiki/Scale-invariant_feature_transform).
+///
+/// The image is repeatedly blurred to produce `options.scales_per_octave + 3` Gaussian images
+/// per octave, with the standard deviation increasing by a constant factor each time, and
+/// the differences between consecutive Gaussian images are searched for samples that are
+/// strictly greater or strictly smaller than their 26 neighbours in position and scale.
+/// The base image of each octave is taken from a [Gaussian pyramid](../pyramid/fn.gaussian_pyramid.html)
+/// with scale factor 0.5.
+///
+/// Each extremum is refined to subpixel position and scale by fitting a quadratic to its
+/// neighbourhood, and is rejected if the refinement does not converge, if its interpolated
+/// response is smaller in magnitude than `options.contrast_threshold`, or if it lies on an
+/// edge rather than a blob, as determined by `options.edge_threshold`.
+///
+/// Keypoints are returned ordered by octave, scale and then position.
+///
+/// Returns `ImageProcError::EmptyImage` if the image is empty and
+/// `ImageProcError::InvalidOptions` if any option is invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::blobs::{dog_blobs, DogOptions};
+/// use imageproc::drawing::draw_filled_circle_mut;
+///
+/// // A bright disk of radius 8 on a dark background.
+/// let mut image = GrayImage::from_pixel(80, 80, Luma([30]));
+/// draw_filled_circle_mut(&mut image, (40, 40), 8, Luma([230]));
+///
+/// let keypoints = dog_blobs(&image, DogOptions::default()).unwrap();
+///
+/// // Weaker responses occur where the rasterised disk boundary is flat.
+/// let keypoint = keypoints.iter().max_by(|a, b| a.response.abs().partial_cmp(&b.response.abs()).unwrap()).unwrap();
+/// assert!((keypoint.x - 40.0).abs() < 0.5 && (keypoint.y - 40.0).abs() < 0.5);
+/// assert!(keypoint.sigma > 4.5 && keypoint.sigma < 7.0);
+/// assert!(keypoint.response < 0.0);
+/// # }
+/// ```
+pub fn dog_blobs(image: &GrayImage, options: DogOptions) -> Result<Vec<DogKeypoint>> {
+    check_non_empty(image.dimensions())?;
+    options.validate()?;
+
+    let scales = options.scales_per_octave as usize;
+    let sigma0 = options.initial_sigma;
+    let k = 2f32.powf(1.0 / scales as f32);
+    // Blurring the base image of an octave with these kernels in turn gives images with
+    // standard deviations sigma0 * k^i for i in 1..scales + 3.
+    let kernels: Vec<Vec<f32>> = (1..scales + 3)
+        .map(|i| {
+            let previous = sigma0 * k.powi(i as i32 - 1);
+            let total = previous * k;
+            normalized_gaussian_kernel((total * total - previous * previous).sqrt())
+        })
+        .collect();
+
+    let (width, height) = image.dimensions();
+    let image: Image<Luma<f32>> = map_subpixels(image, |p| p as f32 / 255.0);
+    let base_sigma = (sigma0 * sigma0 - ASSUMED_BLUR * ASSUMED_BLUR).sqrt();
+    let base = separable_filter_equal(&image, &normalized_gaussian_kernel(base_sigma));
+    // Each octave has twice the blur of the previous one, relative to its own size.
+    let pyramid_options = PyramidOptions {
+        max_levels: options.num_octaves,
+        scale_factor: 0.5,
+        sigma: sigma0 * 3f32.sqrt(),
+        min_size: 2 * DOG_BORDER as u32 + 3,
+    };
+    let pyramid = gaussian_pyramid(&base, pyramid_options)?;
+
+    let mut keypoints = vec![];
+    for (octave, level) in pyramid.levels().iter().enumerate() {
+        let (w, h) = level.dimensions();
+        if (w as usize) < 2 * DOG_BORDER + 3 || (h as usize) < 2 * DOG_BORDER + 3 {
+            break;
+        }
+        let mut gaussians = vec![level.clone()];
+        for kernel in &kernels {
+            let next = separable_filter_equal(gaussians.last().unwrap(), kernel);
+            gaussians.push(next);
+        }
+        let space = DogSpace {
+            layers: gaussians
+                .windows(2)
+                .map(|g| g[1].iter().zip(g[0].iter()).map(|(b, a)| b - a).collect())
+                .collect(),
+            width: w as usize,
+            height: h as usize,
+        };
+
+        let (x_ratio, y_ratio) = (width as f32 / w as f32, height as f32 / h as f32);
+        for layer in 1..scales + 1 {
+            for y in DOG_BORDER..space.height - DOG_BORDER {
+                for x in DOG_BORDER..space.width - DOG_BORDER {
+                    if space.at(layer, x, y).abs() <= 0.5 * options.contrast_threshold
+                        || !space.is_extremum(layer, x, y) {
+                        continue;
+                    }
+                    if let Some((kx, ky, klayer, response)) = space.refine(layer, x, y, &options) {
+                        keypoints.push(DogKeypoint {
+                            x: (kx + 0.5) * x_ratio - 0.5,
+                            y: (ky + 0.5) * y_ratio - 0.5,
+                            sigma: sigma0 * k.powf(klayer) / pyramid.scale(octave),
+                            response,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(keypoints)
+}
+
+/// The difference of Gaussians images for a single octave.
+struct DogSpace {
+    layers: Vec<Vec<f32>>,
+    width: usize,
+    height: usize,
+}
+
+impl DogSpace {
+    fn at(&self, layer: usize, x: usize, y: usize) -> f32 {
+        self.layers[layer][y * self.width + x]
+    }
+
+    /// Returns true if the sample is strictly greater or strictly smaller than its
+    /// 26 neighbours in position and scale.
+    fn is_extremum(&self, layer: usize, x: usize, y: usize) -> bool {
+        let value = self.at(layer, x, y);
+        let (mut greater, mut smaller) = (true, true);
+        for l in layer - 1..layer + 2 {
+            for ny in y - 1..y + 2 {
+                for nx in x - 1..x + 2 {
+                    if (l, nx, ny) == (layer, x, y) {
+                        continue;
+                    }
+                    let neighbour = self.at(l, nx, ny);
+                    greater &= value > neighbour;
+                    smaller &= value < neighbour;
+                }
+            }
+            if !greater && !smaller {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Refines an extremum by repeatedly fitting a quadratic to its neighbourhood and
+    /// moving to the sample nearest the fitted extremum. Returns the interpolated position,
+    /// layer and response, or `None` if the keypoint is rejected.
+    fn refine(&self, layer: usize, x: usize, y: usize, options: &DogOptions) -> Option<(f32, f32, f32, f32)> {
+        let (mut layer, mut x, mut y) = (layer, x, y);
+        for _ in 0..MAX_REFINEMENT_STEPS {
+            let (l0, x0, y0) = (layer as i32, x as i32, y as i32);
+            let d = move |dl: i32, dx: i32, dy: i32| self.at((l0 + dl) as usize, (x0 + dx) as usize, (y0 + dy) as usize);
+            let centre = d(0, 0, 0);
+            let gradient = [
+                0.5 * (d(0, 1, 0) - d(0, -1, 0)),
+                0.5 * (d(0, 0, 1) - d(0, 0, -1)),
+                0.5 * (d(1, 0, 0) - d(-1, 0, 0)),
+            ];
+            let dxx = d(0, 1, 0) + d(0, -1, 0) - 2.0 * centre;
+            let dyy = d(0, 0, 1) + d(0, 0, -1) - 2.0 * centre;
+            let dss = d(1, 0, 0) + d(-1, 0, 0) - 2.0 * centre;
+            let dxy = 0.25 * (d(0, 1, 1) - d(0, 1, -1) - d(0, -1, 1) + d(0, -1, -1));
+            let dxs = 0.25 * (d(1, 1, 0) - d(1, -1, 0) - d(-1, 1, 0) + d(-1, -1, 0));
+            let dys = 0.25 * (d(1, 0, 1) - d(1, 0, -1) - d(-1, 0, 1) + d(-1, 0, -1));
+            let hessian = [[dxx, dxy, dxs], [dxy, dyy, dys], [dxs, dys, dss]];
+
+            let offset = solve_3x3(&hessian, &[-gradient[0], -gradient[1], -gradient[2]])?;
+            if offset.iter().all(|o| o.abs() <= 0.5) {
+                let response = centre + 0.5 * (gradient[0] * offset[0] + gradient[1] * offset[1] + gradient[2] * offset[2]);
+                if response.abs() < options.contrast_threshold {
+                    return None;
+                }
+                // The ratio of the principal curvatures is large for points on edges.
+                let trace = dxx + dyy;
+                let det = dxx * dyy - dxy * dxy;
+                let r = options.edge_threshold;
+                if det <= 0.0 || trace * trace * r >= (r + 1.0) * (r + 1.0) * det {
+                    return None;
+                }
+                return Some((x as f32 + offset[0], y as f32 + offset[1], layer as f32 + offset[2], response));
+            }
+
+            let (nx, ny, nl) = (
+                (x as f32 + offset[0]).round(),
+                (y as f32 + offset[1]).round(),
+                (layer as f32 + offset[2]).round(),
+            );
+            let border = DOG_BORDER as f32;
+            if !(nl >= 1.0 && nl <= (self.layers.len() - 2) as f32
+                && nx >= border && nx < (self.width - DOG_BORDER) as f32
+                && ny >= border && ny < (self.height - DOG_BORDER) as f32) {
+                return None;
+            }
+            x = nx as usize;
+            y = ny as usize;
+            layer = nl as usize;
+        }
+        None
+    }
+}
+
+/// Solves the 3x3 linear system `a * x = b` using Cramer's rule. Returns `None` if `a`
+/// is singular.
+fn solve_3x3(a: &[[f32; 3]; 3], b: &[f32; 3]) -> Option<[f32; 3]> {
+    let det = |m: &[[f32; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let denominator = det(a);
+    if denominator == 0.0 {
+        return None;
+    }
+    let mut x = [0.0; 3];
+    for (column, value) in x.iter_mut().enumerate() {
+        let mut m = *a;
+        for row in 0..3 {
+            m[row][column] = b[row];
+        }
+        *value = det(&m) / denominator;
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use drawing::draw_filled_circle_mut;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn disk_image(radius: i32, background: u8, foreground: u8) -> GrayImage {
+        let mut image = GrayImage::from_pixel(100, 100, Luma([background]));
+        draw_filled_circle_mut(&mut image, (50, 50), radius, Luma([foreground]));
+        image
+    }
+
+    #[test]
+    fn test_filter_sizes() {
+        let sizes: Vec<u32> = (0..3).flat_map(|o| (0..4).map(move |l| filter_size(o, l))).collect();
+        assert_eq!(sizes, vec![9, 15, 21, 27, 15, 27, 39, 51, 27, 51, 75, 99]);
+    }
+
+    #[test]
+    fn test_determinant_of_hessian_sign() {
+        let dark = disk_image(4, 200, 0);
+        let bright = disk_image(4, 0, 200);
+        let dark_response = determinant_of_hessian(&dark, 15);
+        let bright_response = determinant_of_hessian(&bright, 15);
+        assert!(dark_response.get_pixel(50, 50)[0] > 0.0);
+        assert!((dark_response.get_pixel(50, 50)[0] - bright_response.get_pixel(50, 50)[0]).abs() < 1e-6);
+        // A straight edge is a saddle or ridge rather than a blob.
+        let mut edge = GrayImage::new(40, 40);
+        for y in 0..40 {
+            for x in 20..40 {
+                edge.put_pixel(x, y, Luma([255]));
+            }
+        }
+        assert!(determinant_of_hessian(&edge, 9).get_pixel(20, 20)[0] <= 0.0);
+    }
+
+    #[test]
+    fn test_hessian_blobs_polarity_and_scale() {
+        // Weak responses of the opposite polarity occur just outside the disk.
+        let options = HessianOptions { initial_step: 1, threshold: 0.005, ..HessianOptions::default() };
+        for &(radius, expected_size) in &[(4, 15), (8, 27)] {
+            for &(background, foreground) in &[(220, 20), (20, 220)] {
+                let image = disk_image(radius, background, foreground);
+                let blobs = hessian_blobs(&image, options).unwrap();
+                assert_eq!(blobs.len(), 1, "radius {}, {:?}", radius, blobs);
+                let blob = blobs[0];
+                assert_eq!((blob.x, blob.y), (50, 50));
+                assert_eq!(blob.dark, background > foreground);
+                assert_eq!(blob.scale, 1.2 * expected_size as f32 / 9.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hessian_blobs_small_or_constant_image() {
+        let options = HessianOptions::default();
+        assert!(hessian_blobs(&GrayImage::new(10, 10), options).unwrap().is_empty());
+        assert!(hessian_blobs(&GrayImage::new(0, 0), options).unwrap().is_empty());
+        assert!(hessian_blobs(&GrayImage::from_pixel(80, 80, Luma([7])), options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hessian_blobs_invalid_options() {
+        let options = HessianOptions { num_octaves: 0, initial_step: 0, threshold: 0.0 };
+        assert_eq!(
+            hessian_blobs(&GrayImage::new(10, 10), options),
+            Err(ImageProcError::InvalidOptions(
+                "num_octaves must be non-zero, initial_step must be non-zero".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_dog_blobs_polarity_and_scale() {
+        let mut sigmas = vec![];
+        for &radius in &[3, 4, 6] {
+            for &(background, foreground) in &[(220, 20), (20, 220)] {
+                let image = disk_image(radius, background, foreground);
+                let keypoints = dog_blobs(&image, DogOptions::default()).unwrap();
+                assert_eq!(keypoints.len(), 1, "radius {}, {:?}", radius, keypoints);
+                let keypoint = keypoints[0];
+                assert!((keypoint.x - 50.0).abs() < 0.1 && (keypoint.y - 50.0).abs() < 0.1, "{:?}", keypoint);
+                assert_eq!(keypoint.response > 0.0, background > foreground);
+                let expected = radius as f32 / 2f32.sqrt();
+                assert!(keypoint.sigma > 0.8 * expected && keypoint.sigma < 1.1 * expected, "{:?}", keypoint);
+                sigmas.push(keypoint.sigma);
+            }
+        }
+        assert!(sigmas[0] < sigmas[2] && sigmas[2] < sigmas[4]);
+    }
+
+    #[test]
+    fn test_dog_blobs_off_centre_in_non_square_image() {
+        let mut image = GrayImage::from_pixel(121, 101, Luma([200]));
+        draw_filled_circle_mut(&mut image, (70, 33), 6, Luma([0]));
+        let keypoints = dog_blobs(&image, DogOptions::default()).unwrap();
+        assert_eq!(keypoints.len(), 1);
+        assert!((keypoints[0].x - 70.0).abs() < 0.1 && (keypoints[0].y - 33.0).abs() < 0.1, "{:?}", keypoints);
+    }
+
+    #[test]
+    fn test_dog_blobs_rejects_edges_and_flat_regions() {
+        let options = DogOptions::default();
+        let edge = GrayImage::from_fn(60, 60, |x, _| if x < 30 { Luma([0]) } else { Luma([255]) });
+        assert!(dog_blobs(&edge, options).unwrap().is_empty());
+        assert!(dog_blobs(&GrayImage::from_pixel(60, 60, Luma([77])), options).unwrap().is_empty());
+        assert!(dog_blobs(&GrayImage::new(5, 5), options).unwrap().is_empty());
+        assert_eq!(dog_blobs(&GrayImage::new(0, 5), options), Err(ImageProcError::EmptyImage));
+    }
+
+    #[test]
+    fn test_dog_blobs_invalid_options() {
+        let options = DogOptions {
+            num_octaves: 0,
+            scales_per_octave: 0,
+            initial_sigma: 0.5,
+            contrast_threshold: -1.0,
+            edge_threshold: 1.0,
+        };
+        assert_eq!(
+            dog_blobs(&GrayImage::new(10, 10), options),
+            Err(ImageProcError::InvalidOptions(
+                "num_octaves must be non-zero, scales_per_octave must be non-zero, \
+                 initial_sigma must be greater than 0.5, contrast_threshold must be non-negative, \
+                 edge_threshold must be greater than 1"
+                    .to_owned()
+            ))
+        );
+    }
+
+    #[bench]
+    fn bench_hessian_blobs(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| black_box(hessian_blobs(&image, HessianOptions::default()).unwrap()));
+    }
+
+    #[bench]
+    fn bench_dog_blobs(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| black_box(dog_blobs(&image, DogOptions::default()).unwrap()));
+    }
+}
@@ -0,0 +1,352 @@
+//! Conversions between RGB and other color spaces, and conversion to grayscale.
+//!
+//! RGB values are interpreted as [sRGB](https://en.wikipedia.org/wiki/SRGB). Converted
+//! colors are returned as arrays of `f32`s, with the ranges of each channel given in the
+//! documentation of each function. Conversions back to RGB round to the nearest integer
+//! and clamp to the range [0, 255].
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+
+fn to_unit(p: Rgb<u8>) -> [f32; 3] {
+    [p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0]
+}
+
+fn to_u8(v: f32) -> u8 {
+    v.round().max(0.0).min(255.0) as u8
+}
+
+fn from_unit(c: [f32; 3]) -> Rgb<u8> {
+    Rgb([to_u8(c[0] * 255.0), to_u8(c[1] * 255.0), to_u8(c[2] * 255.0)])
+}
+
+/// Returns the hue in degrees, and the maximum and minimum of the channels.
+fn hue_max_min(c: [f32; 3]) -> (f32, f32, f32) {
+    let (r, g, b) = (c[0], c[1], c[2]);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (if hue < 0.0 { hue + 360.0 } else { hue }, max, min)
+}
+
+/// Returns the RGB color with the given hue in degrees, chroma and offset.
+fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> Rgb<u8> {
+    let h = (hue % 360.0 + 360.0) % 360.0 / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    from_unit([r + offset, g + offset, b + offset])
+}
+
+/// Converts an RGB color to [HSV](https://en.wikipedia.org/wiki/HSL_and_HSV), returning
+/// `[hue, saturation, value]`. Hue is in degrees in the range [0, 360), and saturation and
+/// value are in the range [0, 1]. Grays have hue and saturation 0.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::color::{hsv_to_rgb, rgb_to_hsv};
+///
+/// assert_eq!(rgb_to_hsv(Rgb([255, 0, 0])), [0.0, 1.0, 1.0]);
+/// assert_eq!(rgb_to_hsv(Rgb([0, 0, 255])), [240.0, 1.0, 1.0]);
+/// assert_eq!(hsv_to_rgb([120.0, 0.5, 1.0]), Rgb([128, 255, 128]));
+/// # }
+/// ```
+pub fn rgb_to_hsv(p: Rgb<u8>) -> [f32; 3] {
+    let (hue, max, min) = hue_max_min(to_unit(p));
+    let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+    [hue, saturation, max]
+}
+
+/// Converts an [HSV](https://en.wikipedia.org/wiki/HSL_and_HSV) color to RGB. See
+/// [`rgb_to_hsv`](fn.rgb_to_hsv.html) for the channel ranges. Hues outside [0, 360)
+/// are wrapped.
+pub fn hsv_to_rgb(hsv: [f32; 3]) -> Rgb<u8> {
+    let (hue, saturation, value) = (hsv[0], hsv[1], hsv[2]);
+    let chroma = value * saturation;
+    from_hue_chroma(hue, chroma, value - chroma)
+}
+
+/// Converts an RGB color to [HSL](https://en.wikipedia.org/wiki/HSL_and_HSV), returning
+/// `[hue, saturation, lightness]`. Hue is in degrees in the range [0, 360), and saturation
+/// and lightness are in the range [0, 1]. Grays have hue and saturation 0.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::color::{hsl_to_rgb, rgb_to_hsl};
+///
+/// assert_eq!(rgb_to_hsl(Rgb([255, 0, 0])), [0.0, 1.0, 0.5]);
+/// assert_eq!(rgb_to_hsl(Rgb([255, 255, 255])), [0.0, 0.0, 1.0]);
+/// assert_eq!(hsl_to_rgb([120.0, 1.0, 0.25]), Rgb([0, 128, 0]));
+/// # }
+/// ```
+pub fn rgb_to_hsl(p: Rgb<u8>) -> [f32; 3] {
+    let (hue, max, min) = hue_max_min(to_unit(p));
+    let lightness = (max + min) / 2.0;
+    let saturation = if max == min { 0.0 } else { (max - min) / (1.0 - (2.0 * lightness - 1.0).abs()) };
+    [hue, saturation, lightness]
+}
+
+/// Converts an [HSL](https://en.wikipedia.org/wiki/HSL_and_HSV) color to RGB. See
+/// [`rgb_to_hsl`](fn.rgb_to_hsl.html) for the channel ranges. Hues outside [0, 360)
+/// are wrapped.
+pub fn hsl_to_rgb(hsl: [f32; 3]) -> Rgb<u8> {
+    let (hue, saturation, lightness) = (hsl[0], hsl[1], hsl[2]);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+}
+
+// Reference white for the D65 illuminant, and the constants of the CIE Lab definition.
+const WHITE_X: f32 = 0.95047;
+const WHITE_Z: f32 = 1.08883;
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// Converts an RGB color to [CIE Lab](https://en.wikipedia.org/wiki/CIELAB_color_space)
+/// with a D65 white point, returning `[l, a, b]`. Lightness `l` is in the range [0, 100],
+/// and `a` and `b` are roughly in the range [-128, 128].
+///
+/// Euclidean distances between Lab colors approximate perceived color differences, so
+/// clustering and filtering algorithms often work better on Lab colors than RGB colors.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::color::{lab_to_rgb, rgb_to_lab};
+///
+/// let lab = rgb_to_lab(Rgb([255, 255, 255]));
+/// assert!((lab[0] - 100.0).abs() < 0.01 && lab[1].abs() < 0.01 && lab[2].abs() < 0.02);
+///
+/// assert_eq!(lab_to_rgb(rgb_to_lab(Rgb([30, 140, 200]))), Rgb([30, 140, 200]));
+/// # }
+/// ```
+pub fn rgb_to_lab(p: Rgb<u8>) -> [f32; 3] {
+    let c = to_unit(p);
+    let (r, g, b) = (srgb_to_linear(c[0]), srgb_to_linear(c[1]), srgb_to_linear(c[2]));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / WHITE_X;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / WHITE_Z;
+    let f = |t: f32| if t > LAB_EPSILON { t.cbrt() } else { (LAB_KAPPA * t + 16.0) / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Converts a [CIE Lab](https://en.wikipedia.org/wiki/CIELAB_color_space) color with a
+/// D65 white point to RGB. Colors outside the sRGB gamut are clamped.
+pub fn lab_to_rgb(lab: [f32; 3]) -> Rgb<u8> {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    let f_inv = |t: f32| {
+        let cube = t * t * t;
+        if cube > LAB_EPSILON { cube } else { (116.0 * t - 16.0) / LAB_KAPPA }
+    };
+    let (x, y, z) = (f_inv(fx) * WHITE_X, f_inv(fy), f_inv(fz) * WHITE_Z);
+    let r = 3.2406 * x - 1.5372 * y - 0.4986 * z;
+    let g = -0.9689 * x + 1.8758 * y + 0.0415 * z;
+    let b = 0.0557 * x - 0.2040 * y + 1.0570 * z;
+    let channel = |c: f32| linear_to_srgb(c.max(0.0).min(1.0));
+    from_unit([channel(r), channel(g), channel(b)])
+}
+
+/// Converts an RGB color to [YCbCr](https://en.wikipedia.org/wiki/YCbCr) as used by JPEG,
+/// i.e. full range BT.601, returning `[y, cb, cr]`. All channels are in the range [0, 255],
+/// and grays have `cb = cr = 128`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::color::{rgb_to_ycbcr, ycbcr_to_rgb};
+///
+/// let gray = rgb_to_ycbcr(Rgb([100, 100, 100]));
+/// assert!((gray[0] - 100.0).abs() < 1e-3 && (gray[1] - 128.0).abs() < 1e-3 && (gray[2] - 128.0).abs() < 1e-3);
+///
+/// assert_eq!(ycbcr_to_rgb(rgb_to_ycbcr(Rgb([12, 200, 99]))), Rgb([12, 200, 99]));
+/// # }
+/// ```
+pub fn rgb_to_ycbcr(p: Rgb<u8>) -> [f32; 3] {
+    let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        128.0 - 0.168_736 * r - 0.331_264 * g + 0.5 * b,
+        128.0 + 0.5 * r - 0.418_688 * g - 0.081_312 * b,
+    ]
+}
+
+/// Converts a full range BT.601 [YCbCr](https://en.wikipedia.org/wiki/YCbCr) color to RGB.
+/// See [`rgb_to_ycbcr`](fn.rgb_to_ycbcr.html).
+pub fn ycbcr_to_rgb(ycbcr: [f32; 3]) -> Rgb<u8> {
+    let (y, cb, cr) = (ycbcr[0], ycbcr[1] - 128.0, ycbcr[2] - 128.0);
+    Rgb([
+        to_u8(y + 1.402 * cr),
+        to_u8(y - 0.344_136 * cb - 0.714_136 * cr),
+        to_u8(y + 1.772 * cb),
+    ])
+}
+
+/// Weights of the red, green and blue channels used to compute the luma of a color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LumaWeights {
+    /// Weight of the red channel.
+    pub red: f32,
+    /// Weight of the green channel.
+    pub green: f32,
+    /// Weight of the blue channel.
+    pub blue: f32,
+}
+
+impl LumaWeights {
+    /// The weights used by BT.601 and JPEG.
+    pub const BT601: LumaWeights = LumaWeights { red: 0.299, green: 0.587, blue: 0.114 };
+    /// The weights used by BT.709 and sRGB.
+    pub const BT709: LumaWeights = LumaWeights { red: 0.2126, green: 0.7152, blue: 0.0722 };
+    /// Equal weights, i.e. the mean of the channels.
+    pub const EQUAL: LumaWeights = LumaWeights { red: 1.0 / 3.0, green: 1.0 / 3.0, blue: 1.0 / 3.0 };
+}
+
+/// Converts an RGB image to grayscale by taking a weighted sum of its channels, rounded to
+/// the nearest integer and clamped to [0, 255].
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::color::{grayscale, LumaWeights};
+///
+/// let image = rgb_image!([255, 0, 0], [0, 255, 0], [30, 60, 90]);
+///
+/// assert_pixels_eq!(grayscale(&image, LumaWeights::BT601), gray_image!(76, 150, 54));
+/// assert_pixels_eq!(grayscale(&image, LumaWeights::EQUAL), gray_image!(85, 85, 60));
+/// # }
+/// ```
+pub fn grayscale(image: &RgbImage, weights: LumaWeights) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let p = image.get_pixel(x, y);
+        let luma = weights.red * p[0] as f32 + weights.green * p[1] as f32 + weights.blue * p[2] as f32;
+        Luma([to_u8(luma)])
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::rgb_bench_image;
+    use test::{Bencher, black_box};
+
+    fn sample_colors() -> Vec<Rgb<u8>> {
+        let mut colors = vec![];
+        for r in 0..18 {
+            for g in 0..18 {
+                for b in 0..18 {
+                    colors.push(Rgb([15 * r as u8, 15 * g as u8, 15 * b as u8]));
+                }
+            }
+        }
+        colors
+    }
+
+    #[test]
+    fn test_hsv_round_trip() {
+        for &c in &sample_colors() {
+            assert_eq!(hsv_to_rgb(rgb_to_hsv(c)), c);
+        }
+    }
+
+    #[test]
+    fn test_hsl_round_trip() {
+        for &c in &sample_colors() {
+            assert_eq!(hsl_to_rgb(rgb_to_hsl(c)), c);
+        }
+    }
+
+    #[test]
+    fn test_lab_round_trip() {
+        for &c in &sample_colors() {
+            assert_eq!(lab_to_rgb(rgb_to_lab(c)), c);
+        }
+    }
+
+    #[test]
+    fn test_ycbcr_round_trip() {
+        for &c in &sample_colors() {
+            let d = ycbcr_to_rgb(rgb_to_ycbcr(c));
+            for i in 0..3 {
+                assert!((c[i] as i32 - d[i] as i32).abs() <= 1, "{:?} {:?}", c, d);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hsv_hsl_known_values() {
+        let near = |a: [f32; 3], b: [f32; 3]| a.iter().zip(&b).all(|(p, q)| (p - q).abs() < 1e-3);
+        assert!(near(rgb_to_hsv(Rgb([255, 255, 0])), [60.0, 1.0, 1.0]));
+        assert!(near(rgb_to_hsv(Rgb([255, 0, 255])), [300.0, 1.0, 1.0]));
+        assert!(near(rgb_to_hsv(Rgb([0, 0, 0])), [0.0, 0.0, 0.0]));
+        assert!(near(rgb_to_hsl(Rgb([0, 255, 255])), [180.0, 1.0, 0.5]));
+        assert!(near(rgb_to_hsl(Rgb([51, 102, 153])), [210.0, 0.5, 0.4]));
+        assert_eq!(hsv_to_rgb([420.0, 1.0, 1.0]), Rgb([255, 255, 0]));
+        assert_eq!(hsv_to_rgb([-60.0, 1.0, 1.0]), Rgb([255, 0, 255]));
+    }
+
+    #[test]
+    fn test_lab_known_values() {
+        let near = |a: [f32; 3], b: [f32; 3]| a.iter().zip(&b).all(|(p, q)| (p - q).abs() < 0.5);
+        assert!(near(rgb_to_lab(Rgb([0, 0, 0])), [0.0, 0.0, 0.0]));
+        assert!(near(rgb_to_lab(Rgb([255, 0, 0])), [53.24, 80.09, 67.20]));
+        assert!(near(rgb_to_lab(Rgb([0, 255, 0])), [87.73, -86.18, 83.18]));
+        assert!(near(rgb_to_lab(Rgb([0, 0, 255])), [32.30, 79.19, -107.86]));
+    }
+
+    #[test]
+    fn test_grayscale_weights() {
+        let image = rgb_image!([255, 255, 255], [0, 0, 255]);
+        assert_pixels_eq!(grayscale(&image, LumaWeights::BT709), gray_image!(255, 18));
+        assert_pixels_eq!(grayscale(&image, LumaWeights::BT601), gray_image!(255, 29));
+    }
+
+    #[bench]
+    fn bench_rgb_to_lab(b: &mut Bencher) {
+        let image = rgb_bench_image(100, 100);
+        b.iter(|| {
+            let lab: Vec<[f32; 3]> = image.pixels().map(|p| rgb_to_lab(*p)).collect();
+            black_box(lab);
+        });
+    }
+}
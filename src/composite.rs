@@ -0,0 +1,364 @@
+//! Functions for blending images together and compositing one image onto another.
+
+use image::{GenericImage, GenericImageView, ImageBuffer, Pixel, Primitive};
+use conv::ValueInto;
+use definitions::{Clamp, Image};
+use math::cast;
+use num::{Bounded, NumCast};
+use pixelops::{has_alpha, weighted_sum};
+
+/// A per-channel operation used to combine two images.
+///
+/// Channel values are interpreted relative to the maximum channel value `m`, which is the
+/// largest value of the subpixel type for integer subpixels and 1 for floating point
+/// subpixels. For channel values `a` and `b`:
+///
+/// * `Multiply` gives `a * b / m`, which is never brighter than either input.
+/// * `Screen` gives `a + b - a * b / m`, which is never darker than either input.
+/// * `Add` gives `a + b`, saturating at `m`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Multiplies normalised channel values.
+    Multiply,
+    /// Inverts, multiplies and inverts again.
+    Screen,
+    /// Adds channel values, saturating at the maximum channel value.
+    Add,
+}
+
+/// Returns the maximum channel value of the subpixel type `S`: `S::max_value()` for
+/// integer types and 1 for floating point types.
+fn max_channel_value<S>() -> f64
+where
+    S: Primitive + ValueInto<f64>,
+{
+    // Casting 0.5 to an integer type truncates it to zero.
+    let is_float = <S as NumCast>::from(0.5).map_or(false, |h| h != S::zero());
+    if is_float { 1.0 } else { cast(<S as Bounded>::max_value()) }
+}
+
+/// Combines two pixels channel-wise using the given blend mode.
+///
+/// For pixel types with an alpha channel only the color channels are combined and the
+/// alpha of `a` is kept.
+pub fn blend_pixels<P>(a: P, b: P, mode: BlendMode) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f64> + Clamp<f64>,
+{
+    let max = max_channel_value::<P::Subpixel>();
+    let num_color_channels = if has_alpha::<P>() {
+        P::channel_count() as usize - 1
+    } else {
+        P::channel_count() as usize
+    };
+
+    let mut out = a;
+    for (c, (&p, &q)) in out.channels_mut()[..num_color_channels]
+        .iter_mut()
+        .zip(a.channels().iter().zip(b.channels()))
+    {
+        let p: f64 = cast(p);
+        let q: f64 = cast(q);
+        let combined = match mode {
+            BlendMode::Multiply => p * q / max,
+            BlendMode::Screen => p + q - p * q / max,
+            BlendMode::Add => (p + q).min(max),
+        };
+        *c = P::Subpixel::clamp(combined);
+    }
+    out
+}
+
+/// Combines two images pixel-wise using the given blend mode. See
+/// [`blend_pixels`](fn.blend_pixels.html) for details.
+///
+/// # Panics
+///
+/// If `a` and `b` do not have the same dimensions.
+pub fn blend_with_mode<I, J>(a: &I, b: &J, mode: BlendMode) -> Image<I::Pixel>
+where
+    I: GenericImageView,
+    J: GenericImageView<Pixel = I::Pixel>,
+    I::Pixel: 'static,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f64> + Clamp<f64>,
+{
+    assert_dimensions_match!(a, b);
+    ImageBuffer::from_fn(a.width(), a.height(), |x, y| {
+        blend_pixels(a.get_pixel(x, y), b.get_pixel(x, y), mode)
+    })
+}
+
+/// Combines `b` into `a` pixel-wise in place using the given blend mode.
+/// See [`blend_with_mode`](fn.blend_with_mode.html).
+///
+/// # Panics
+///
+/// If `a` and `b` do not have the same dimensions.
+pub fn blend_with_mode_mut<I, J>(a: &mut I, b: &J, mode: BlendMode)
+where
+    I: GenericImage,
+    J: GenericImageView<Pixel = I::Pixel>,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f64> + Clamp<f64>,
+{
+    assert_dimensions_match!(a, b);
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            let blended = blend_pixels(a.get_pixel(x, y), b.get_pixel(x, y), mode);
+            a.put_pixel(x, y, blended);
+        }
+    }
+}
+
+/// Linearly mixes two images, returning `(1 - alpha) * a + alpha * b`.
+///
+/// All channels are mixed, including any alpha channel.
+///
+/// # Panics
+///
+/// If `a` and `b` do not have the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::composite::blend;
+///
+/// let a = gray_image!(
+///     0, 100);
+///
+/// let b = gray_image!(
+///     200, 200);
+///
+/// assert_pixels_eq!(
+///     blend(&a, &b, 0.25),
+///     gray_image!(50, 125));
+/// # }
+/// ```
+pub fn blend<I, J>(a: &I, b: &J, alpha: f32) -> Image<I::Pixel>
+where
+    I: GenericImageView,
+    J: GenericImageView<Pixel = I::Pixel>,
+    I::Pixel: 'static,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    assert_dimensions_match!(a, b);
+    ImageBuffer::from_fn(a.width(), a.height(), |x, y| {
+        weighted_sum(a.get_pixel(x, y), b.get_pixel(x, y), 1.0 - alpha, alpha)
+    })
+}
+
+/// Linearly mixes `b` into `a` in place, setting `a` to `(1 - alpha) * a + alpha * b`.
+/// See [`blend`](fn.blend.html).
+///
+/// # Panics
+///
+/// If `a` and `b` do not have the same dimensions.
+pub fn blend_mut<I, J>(a: &mut I, b: &J, alpha: f32)
+where
+    I: GenericImage,
+    J: GenericImageView<Pixel = I::Pixel>,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    assert_dimensions_match!(a, b);
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            let mixed = weighted_sum(a.get_pixel(x, y), b.get_pixel(x, y), 1.0 - alpha, alpha);
+            a.put_pixel(x, y, mixed);
+        }
+    }
+}
+
+/// Composites `foreground` onto a copy of `background`, with the top-left corner of
+/// `foreground` placed at `(x, y)`. See [`overlay_mut`](fn.overlay_mut.html).
+pub fn overlay<I, J>(background: &I, foreground: &J, x: i32, y: i32) -> Image<I::Pixel>
+where
+    I: GenericImageView,
+    J: GenericImageView<Pixel = I::Pixel>,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(background.width(), background.height());
+    out.copy_from(background, 0, 0);
+    overlay_mut(&mut out, foreground, x, y);
+    out
+}
+
+/// Composites `foreground` onto `background` in place, with the top-left corner of
+/// `foreground` placed at `(x, y)`. Any parts of `foreground` lying outside `background`
+/// are ignored.
+///
+/// For pixel types with an alpha channel the foreground is alpha-blended onto the
+/// background using the "source over" operator. For other pixel types background
+/// pixels are replaced by foreground pixels.
+pub fn overlay_mut<I, J>(background: &mut I, foreground: &J, x: i32, y: i32)
+where
+    I: GenericImage,
+    J: GenericImageView<Pixel = I::Pixel>,
+{
+    let (bg_width, bg_height) = (background.width() as i64, background.height() as i64);
+    let (fg_width, fg_height) = (foreground.width() as i64, foreground.height() as i64);
+    let (x, y) = (x as i64, y as i64);
+
+    // Range of foreground coordinates that land inside the background.
+    let fx_start = (-x).max(0).min(fg_width);
+    let fy_start = (-y).max(0).min(fg_height);
+    let fx_end = (bg_width - x).min(fg_width).max(fx_start);
+    let fy_end = (bg_height - y).min(fg_height).max(fy_start);
+
+    for fy in fy_start..fy_end {
+        for fx in fx_start..fx_end {
+            let (bx, by) = ((fx + x) as u32, (fy + y) as u32);
+            let mut pixel = background.get_pixel(bx, by);
+            pixel.blend(&foreground.get_pixel(fx as u32, fy as u32));
+            background.put_pixel(bx, by, pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, ImageBuffer, Luma, Rgba, RgbaImage};
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_blend_endpoints() {
+        let a = gray_image!(10, 20; 30, 40);
+        let b = gray_image!(90, 80; 70, 60);
+        assert_pixels_eq!(blend(&a, &b, 0.0), a);
+        assert_pixels_eq!(blend(&a, &b, 1.0), b);
+        assert_pixels_eq!(blend(&a, &b, 0.5), gray_image!(50, 50; 50, 50));
+    }
+
+    #[test]
+    fn test_in_place_blends_match_allocating_versions() {
+        let a = gray_image!(10, 200; 30, 140);
+        let b = gray_image!(90, 80; 255, 0);
+
+        let mut mixed = a.clone();
+        blend_mut(&mut mixed, &b, 0.3);
+        assert_pixels_eq!(mixed, blend(&a, &b, 0.3));
+
+        for &mode in &[BlendMode::Multiply, BlendMode::Screen, BlendMode::Add] {
+            let mut combined = a.clone();
+            blend_with_mode_mut(&mut combined, &b, mode);
+            assert_pixels_eq!(combined, blend_with_mode(&a, &b, mode));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_blend_mismatched_dimensions() {
+        let a = GrayImage::new(2, 3);
+        let b = GrayImage::new(3, 2);
+        let _ = blend(&a, &b, 0.5);
+    }
+
+    #[test]
+    fn test_blend_pixels_modes() {
+        let (a, b) = (Luma([255u8]), Luma([51u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Multiply), Luma([51u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Screen), Luma([255u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Add), Luma([255u8]));
+
+        let (a, b) = (Luma([0u8]), Luma([51u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Multiply), Luma([0u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Screen), Luma([51u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Add), Luma([51u8]));
+
+        let (a, b) = (Luma([100u8]), Luma([60u8]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Add), Luma([160u8]));
+    }
+
+    #[test]
+    fn test_blend_pixels_float() {
+        let (a, b) = (Luma([0.5f32]), Luma([0.5f32]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Multiply), Luma([0.25f32]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Screen), Luma([0.75f32]));
+        assert_eq!(blend_pixels(a, b, BlendMode::Add), Luma([1.0f32]));
+    }
+
+    #[test]
+    fn test_blend_pixels_keeps_alpha() {
+        let a = Rgba([255u8, 0u8, 255u8, 100u8]);
+        let b = Rgba([51u8, 255u8, 255u8, 200u8]);
+        assert_eq!(blend_pixels(a, b, BlendMode::Multiply), Rgba([51u8, 0u8, 255u8, 100u8]));
+    }
+
+    #[test]
+    fn test_blend_with_mode() {
+        let a = gray_image!(255, 0; 100, 255);
+        let b = gray_image!(51, 51; 60, 255);
+        let expected = gray_image!(51, 0; 23, 255);
+        assert_pixels_eq!(blend_with_mode(&a, &b, BlendMode::Multiply), expected);
+    }
+
+    #[test]
+    fn test_overlay_opaque_pixels_replace() {
+        let background = gray_image!(
+            1, 2, 3;
+            4, 5, 6;
+            7, 8, 9);
+
+        let foreground = gray_image!(
+            10, 11;
+            12, 13);
+
+        let expected = gray_image!(
+            1, 2,  3;
+            4, 10, 11;
+            7, 12, 13);
+        assert_pixels_eq!(overlay(&background, &foreground, 1, 1), expected);
+
+        let expected = gray_image!(
+            13, 2, 3;
+            4,  5, 6;
+            7,  8, 9);
+        assert_pixels_eq!(overlay(&background, &foreground, -1, -1), expected);
+
+        assert_pixels_eq!(overlay(&background, &foreground, 3, 0), background);
+        assert_pixels_eq!(overlay(&background, &foreground, -2, 1), background);
+    }
+
+    #[test]
+    fn test_overlay_respects_alpha() {
+        let background = RgbaImage::from_pixel(2, 1, Rgba([0u8, 0u8, 0u8, 255u8]));
+        let mut foreground = RgbaImage::new(2, 1);
+        foreground.put_pixel(0, 0, Rgba([255u8, 255u8, 255u8, 255u8]));
+        foreground.put_pixel(1, 0, Rgba([255u8, 255u8, 255u8, 0u8]));
+
+        let composited = overlay(&background, &foreground, 0, 0);
+        assert_eq!(*composited.get_pixel(0, 0), Rgba([255u8, 255u8, 255u8, 255u8]));
+        assert_eq!(*composited.get_pixel(1, 0), Rgba([0u8, 0u8, 0u8, 255u8]));
+
+        foreground.put_pixel(0, 0, Rgba([255u8, 255u8, 255u8, 128u8]));
+        let composited = overlay(&background, &foreground, 0, 0);
+        let p = composited.get_pixel(0, 0);
+        // Blending is performed in floating point and the result truncated.
+        assert!(p[0] >= 127 && p[0] <= 128);
+        assert!(p[3] >= 254);
+    }
+
+    #[bench]
+    fn bench_blend_with_mode_gray(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        let other: GrayImage = ImageBuffer::from_fn(500, 500, |x, y| Luma([(x + y) as u8]));
+        b.iter(|| {
+            let blended = blend_with_mode(&image, &other, BlendMode::Screen);
+            black_box(blended);
+        });
+    }
+
+    #[bench]
+    fn bench_overlay_rgba(b: &mut Bencher) {
+        let background = RgbaImage::from_pixel(500, 500, Rgba([10u8, 20u8, 30u8, 255u8]));
+        let foreground = RgbaImage::from_pixel(300, 300, Rgba([200u8, 100u8, 50u8, 128u8]));
+        b.iter(|| {
+            let composited = overlay(&background, &foreground, 100, 100);
+            black_box(composited);
+        });
+    }
+}
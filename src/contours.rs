@@ -0,0 +1,509 @@
+//! Functions for finding and measuring the borders of regions in binary images.
+//!
+//! Contours are found by the border following algorithm of Suzuki and Abe, from
+//! "Topological Structural Analysis of Digitized Binary Images by Border Following".
+
+use image::GrayImage;
+use geometry::{LineSegment, Polygon};
+
+/// Whether a contour is the outer border of a foreground region, or the border of a
+/// hole within one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BorderType {
+    /// The outer border of a region of foreground pixels.
+    Outer,
+    /// The border of a region of background pixels enclosed by foreground pixels.
+    Hole,
+}
+
+/// A border traced around a connected region of an image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contour {
+    /// The foreground pixels on the border, in order. Outer borders run anticlockwise as
+    /// displayed (i.e. with `y` increasing downwards) and hole borders clockwise.
+    pub points: Vec<(u32, u32)>,
+    /// Whether this is an outer border or a hole border.
+    pub border_type: BorderType,
+    /// The index of the innermost contour enclosing this one, or `None` if this contour is
+    /// not enclosed by any other. The parent of a hole border is the outer border of the
+    /// region containing the hole, and the parent of an outer border is the border of the
+    /// hole it lies in.
+    pub parent: Option<usize>,
+}
+
+impl Contour {
+    /// The polygon whose vertices are the centres of this contour's points.
+    pub fn to_polygon(&self) -> Polygon {
+        Polygon::new(self.points.iter().map(|&(x, y)| (x as f32, y as f32)).collect())
+    }
+
+    /// The area enclosed by the polygon through the centres of this contour's points.
+    ///
+    /// This is smaller than the number of pixels in the region, as the polygon passes
+    /// through the centres of the border pixels.
+    pub fn area(&self) -> f32 {
+        self.to_polygon().area()
+    }
+
+    /// The length of the closed polygon through the centres of this contour's points.
+    pub fn perimeter(&self) -> f32 {
+        if self.points.len() < 2 {
+            return 0.0;
+        }
+        self.to_polygon().perimeter()
+    }
+
+    /// The moments of the region enclosed by the polygon through the centres of this
+    /// contour's points.
+    pub fn moments(&self) -> Moments {
+        let vertices: Vec<(f64, f64)> = self.points.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+        Moments::of_polygon(&vertices)
+    }
+}
+
+/// Spatial moments of a region, up to order two. Moment `mpq` is the integral of
+/// `x^p * y^q` over the region.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Moments {
+    /// Area of the region.
+    pub m00: f64,
+    /// First order moment in `x`.
+    pub m10: f64,
+    /// First order moment in `y`.
+    pub m01: f64,
+    /// Second order moment in `x`.
+    pub m20: f64,
+    /// Second order mixed moment.
+    pub m11: f64,
+    /// Second order moment in `y`.
+    pub m02: f64,
+}
+
+impl Moments {
+    /// Computes the moments of the region bounded by a polygon, using Green's theorem.
+    /// The vertices may run in either direction.
+    pub fn of_polygon(vertices: &[(f64, f64)]) -> Moments {
+        let mut m = Moments { m00: 0.0, m10: 0.0, m01: 0.0, m20: 0.0, m11: 0.0, m02: 0.0 };
+        for i in 0..vertices.len() {
+            let (x0, y0) = vertices[i];
+            let (x1, y1) = vertices[(i + 1) % vertices.len()];
+            let a = x0 * y1 - x1 * y0;
+            m.m00 += a;
+            m.m10 += a * (x0 + x1);
+            m.m01 += a * (y0 + y1);
+            m.m20 += a * (x0 * x0 + x0 * x1 + x1 * x1);
+            m.m11 += a * (x0 * (2.0 * y0 + y1) + x1 * (y0 + 2.0 * y1));
+            m.m02 += a * (y0 * y0 + y0 * y1 + y1 * y1);
+        }
+        // Anticlockwise polygons have negative signed area.
+        let sign = if m.m00 < 0.0 { -1.0 } else { 1.0 };
+        Moments {
+            m00: sign * m.m00 / 2.0,
+            m10: sign * m.m10 / 6.0,
+            m01: sign * m.m01 / 6.0,
+            m20: sign * m.m20 / 12.0,
+            m11: sign * m.m11 / 24.0,
+            m02: sign * m.m02 / 12.0,
+        }
+    }
+
+    /// The centroid of the region, or `None` if it has zero area.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        if self.m00 == 0.0 {
+            return None;
+        }
+        Some((self.m10 / self.m00, self.m01 / self.m00))
+    }
+
+    /// The second order central moments `(mu20, mu11, mu02)`, i.e. the second order
+    /// moments about the centroid, or `None` if the region has zero area.
+    pub fn central(&self) -> Option<(f64, f64, f64)> {
+        let (cx, cy) = self.centroid()?;
+        Some((
+            self.m20 - cx * self.m10,
+            self.m11 - cx * self.m01,
+            self.m02 - cy * self.m01,
+        ))
+    }
+}
+
+/// Finds the borders of all regions of non-zero pixels in an image, and of all holes within
+/// these regions. Foreground regions are eight-connected and holes are four-connected.
+///
+/// Contours are returned in the order in which their first point is reached by a raster scan
+/// of the image, so every contour appears after its parent.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contours::{find_contours, BorderType};
+///
+/// let image = gray_image!(
+///     0,   0,   0,   0,   0;
+///     0, 255, 255, 255,   0;
+///     0, 255,   0, 255,   0;
+///     0, 255, 255, 255,   0;
+///     0,   0,   0,   0,   0);
+///
+/// let contours = find_contours(&image);
+/// assert_eq!(contours.len(), 2);
+///
+/// let outer = &contours[0];
+/// assert_eq!(outer.border_type, BorderType::Outer);
+/// assert_eq!(outer.parent, None);
+/// assert_eq!(
+///     outer.points,
+///     vec![(1, 1), (1, 2), (1, 3), (2, 3), (3, 3), (3, 2), (3, 1), (2, 1)]);
+///
+/// let hole = &contours[1];
+/// assert_eq!(hole.border_type, BorderType::Hole);
+/// assert_eq!(hole.parent, Some(0));
+/// assert_eq!(hole.points.len(), 4);
+/// # }
+/// ```
+pub fn find_contours(image: &GrayImage) -> Vec<Contour> {
+    find_contours_with_threshold(image, 0)
+}
+
+/// As [`find_contours`](fn.find_contours.html), but treating pixels as foreground if
+/// and only if their intensity is greater than `threshold`.
+pub fn find_contours_with_threshold(image: &GrayImage, threshold: u8) -> Vec<Contour> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+
+    // Labels, padded with a frame of background pixels so that
+    // every pixel we trace from has eight neighbours.
+    let stride = width + 2;
+    let mut f = vec![0i32; stride * (height + 2)];
+    for (i, p) in image.iter().enumerate() {
+        if *p > threshold {
+            f[(i / width + 1) * stride + i % width + 1] = 1;
+        }
+    }
+
+    // Offsets to the eight neighbours of a pixel, clockwise as displayed from east.
+    let s = stride as isize;
+    let offsets = [1, s + 1, s, s - 1, -1, -s - 1, -s, -s + 1];
+    let direction = |from: usize, to: usize| {
+        let d = to as isize - from as isize;
+        offsets.iter().position(|&o| o == d).unwrap()
+    };
+    let neighbour = |from: usize, dir: usize| (from as isize + offsets[dir % 8]) as usize;
+    let to_point = |index: usize| ((index % stride - 1) as u32, (index / stride - 1) as u32);
+
+    let mut contours: Vec<Contour> = Vec::new();
+    let mut nbd = 1i32;
+
+    for y in 1..height + 1 {
+        // The border most recently encountered on this row, where 1 is the frame.
+        let mut lnbd = 1i32;
+        for x in 1..width + 1 {
+            let start = y * stride + x;
+            let value = f[start];
+            if value == 0 {
+                continue;
+            }
+
+            let (from, border_type) = if value == 1 && f[start - 1] == 0 {
+                (start - 1, BorderType::Outer)
+            } else if value >= 1 && f[start + 1] == 0 {
+                if value > 1 {
+                    lnbd = value;
+                }
+                (start + 1, BorderType::Hole)
+            } else {
+                if value != 1 {
+                    lnbd = value.abs();
+                }
+                continue;
+            };
+
+            nbd += 1;
+            let parent = if lnbd <= 1 {
+                None
+            } else {
+                let previous = (lnbd - 2) as usize;
+                if contours[previous].border_type == border_type {
+                    contours[previous].parent
+                } else {
+                    Some(previous)
+                }
+            };
+
+            let mut points = Vec::new();
+            let first_dir = direction(start, from);
+            let first = (0..8).map(|i| neighbour(start, first_dir + i)).find(|&n| f[n] != 0);
+
+            match first {
+                None => {
+                    // An isolated pixel.
+                    f[start] = -nbd;
+                    points.push(to_point(start));
+                }
+                Some(first) => {
+                    let (mut previous, mut current) = (first, start);
+                    loop {
+                        points.push(to_point(current));
+
+                        // Search anticlockwise from the pixel after previous.
+                        let previous_dir = direction(current, previous);
+                        let mut east_is_background = false;
+                        let mut next = current;
+                        for i in 1..9 {
+                            let dir = (previous_dir + 8 - i) % 8;
+                            let n = neighbour(current, dir);
+                            if f[n] != 0 {
+                                next = n;
+                                break;
+                            }
+                            if dir == 0 {
+                                east_is_background = true;
+                            }
+                        }
+
+                        if east_is_background {
+                            f[current] = -nbd;
+                        } else if f[current] == 1 {
+                            f[current] = nbd;
+                        }
+
+                        if next == start && current == first {
+                            break;
+                        }
+                        previous = current;
+                        current = next;
+                    }
+                }
+            }
+
+            contours.push(Contour {
+                points,
+                border_type,
+                parent,
+            });
+
+            if f[start] != 1 {
+                lnbd = f[start].abs();
+            }
+        }
+    }
+
+    contours
+}
+
+/// Simplifies a polyline using the Douglas-Peucker algorithm, keeping only those vertices
+/// needed for every removed vertex to lie within distance `epsilon` of the simplified curve.
+///
+/// If `closed` is true then `curve` is treated as a closed polygon, with an implicit edge
+/// from its last vertex to its first.
+///
+/// # Examples
+/// ```
+/// use imageproc::contours::approximate_polygon_dp;
+///
+/// let curve = vec![(0.0, 0.0), (1.0, 0.1), (2.0, -0.1), (3.0, 5.0), (4.0, 6.0), (5.0, 7.0)];
+///
+/// assert_eq!(
+///     approximate_polygon_dp(&curve, 0.5, false),
+///     vec![(0.0, 0.0), (2.0, -0.1), (3.0, 5.0), (5.0, 7.0)]);
+/// ```
+pub fn approximate_polygon_dp(curve: &[(f32, f32)], epsilon: f32, closed: bool) -> Vec<(f32, f32)> {
+    if curve.len() < 3 {
+        return curve.to_vec();
+    }
+    if !closed {
+        return simplify_open(curve, epsilon);
+    }
+
+    // Split the polygon at the vertex furthest from its first vertex, and
+    // simplify each of the resulting open curves.
+    let first = curve[0];
+    let distance_squared = |p: (f32, f32)| (p.0 - first.0).powi(2) + (p.1 - first.1).powi(2);
+    let mut split = 0;
+    for (i, p) in curve.iter().enumerate() {
+        if distance_squared(*p) > distance_squared(curve[split]) {
+            split = i;
+        }
+    }
+    if split == 0 {
+        return vec![first];
+    }
+
+    let mut second_half = curve[split..].to_vec();
+    second_half.push(first);
+
+    let mut simplified = simplify_open(&curve[..split + 1], epsilon);
+    simplified.pop();
+    simplified.extend(simplify_open(&second_half, epsilon));
+    simplified.pop();
+    simplified
+}
+
+fn simplify_open(curve: &[(f32, f32)], epsilon: f32) -> Vec<(f32, f32)> {
+    let mut keep = vec![false; curve.len()];
+    keep[0] = true;
+    keep[curve.len() - 1] = true;
+
+    let mut ranges = vec![(0, curve.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let segment = LineSegment::new(curve[start], curve[end]);
+        let mut furthest = None;
+        let mut max_distance = epsilon;
+        for i in start + 1..end {
+            let distance = segment.distance_to_point(curve[i]);
+            if distance > max_distance {
+                max_distance = distance;
+                furthest = Some(i);
+            }
+        }
+        if let Some(i) = furthest {
+            keep[i] = true;
+            ranges.push((start, i));
+            ranges.push((i, end));
+        }
+    }
+
+    curve.iter().zip(keep).filter(|&(_, k)| k).map(|(p, _)| *p).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma};
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_find_contours_empty_and_single_pixel() {
+        assert!(find_contours(&GrayImage::new(0, 0)).is_empty());
+        assert!(find_contours(&GrayImage::new(4, 3)).is_empty());
+
+        let mut image = GrayImage::new(3, 3);
+        image.put_pixel(2, 1, Luma([1]));
+        let contours = find_contours(&image);
+        assert_eq!(contours, vec![Contour {
+            points: vec![(2, 1)],
+            border_type: BorderType::Outer,
+            parent: None,
+        }]);
+        assert_eq!(contours[0].area(), 0.0);
+        assert_eq!(contours[0].perimeter(), 0.0);
+    }
+
+    #[test]
+    fn test_find_contours_region_touching_image_boundary() {
+        let image = GrayImage::from_pixel(3, 2, Luma([255]));
+        let contours = find_contours(&image);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].points, vec![(0, 0), (0, 1), (1, 1), (2, 1), (2, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn test_find_contours_diagonal_line_is_one_region() {
+        let image = gray_image!(
+            1, 0, 0;
+            0, 1, 0;
+            0, 0, 1);
+        let contours = find_contours(&image);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].points, vec![(0, 0), (1, 1), (2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn test_find_contours_hierarchy() {
+        // An outer square containing a hole, which contains
+        // an island, plus a separate region to its right.
+        let image = gray_image!(
+            1, 1, 1, 1, 1, 1, 1, 0, 0;
+            1, 0, 0, 0, 0, 0, 1, 0, 1;
+            1, 0, 1, 1, 1, 0, 1, 0, 1;
+            1, 0, 1, 1, 1, 0, 1, 0, 0;
+            1, 0, 0, 0, 0, 0, 1, 0, 0;
+            1, 1, 1, 1, 1, 1, 1, 0, 0);
+        let contours = find_contours(&image);
+
+        let summary: Vec<(BorderType, Option<usize>, (u32, u32))> = contours
+            .iter()
+            .map(|c| (c.border_type, c.parent, c.points[0]))
+            .collect();
+        assert_eq!(summary, vec![
+            (BorderType::Outer, None, (0, 0)),
+            (BorderType::Hole, Some(0), (0, 1)),
+            (BorderType::Outer, None, (8, 1)),
+            (BorderType::Outer, Some(1), (2, 2)),
+        ]);
+
+        assert_eq!(contours[0].area(), 30.0);
+        assert_eq!(contours[0].perimeter(), 22.0);
+        // The hole border passes through the inner pixels of the outer ring.
+        assert_eq!(contours[1].points.len(), 18);
+        assert_eq!(contours[3].area(), 2.0);
+    }
+
+    #[test]
+    fn test_find_contours_with_threshold() {
+        let image = gray_image!(
+            10, 200, 200;
+            10, 200, 90);
+        assert_eq!(find_contours_with_threshold(&image, 100)[0].points, vec![(1, 0), (1, 1), (2, 0)]);
+        assert_eq!(find_contours_with_threshold(&image, 5).len(), 1);
+        assert!(find_contours_with_threshold(&image, 200).is_empty());
+    }
+
+    #[test]
+    fn test_contour_moments() {
+        let contour = Contour {
+            points: vec![(1, 1), (5, 1), (5, 3), (1, 3)],
+            border_type: BorderType::Outer,
+            parent: None,
+        };
+        let m = contour.moments();
+        assert_eq!(m.m00, 8.0);
+        assert_eq!(m.centroid(), Some((3.0, 2.0)));
+        // For a w x h rectangle, mu20 = w^3 * h / 12 and mu02 = w * h^3 / 12.
+        let (mu20, mu11, mu02) = m.central().unwrap();
+        assert!((mu20 - 64.0 * 2.0 / 12.0).abs() < 1e-9);
+        assert!(mu11.abs() < 1e-9);
+        assert!((mu02 - 4.0 * 8.0 / 12.0).abs() < 1e-9);
+
+        // Reversing the direction doesn't change the moments.
+        let reversed: Vec<(f64, f64)> = contour.points.iter().rev().map(|&(x, y)| (x as f64, y as f64)).collect();
+        assert_eq!(Moments::of_polygon(&reversed), m);
+    }
+
+    #[test]
+    fn test_approximate_polygon_dp_closed() {
+        // A square with extra vertices along its edges.
+        let square = vec![
+            (0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0),
+            (2.0, 2.0), (1.0, 2.1), (0.0, 2.0), (0.0, 1.0)];
+        assert_eq!(
+            approximate_polygon_dp(&square, 0.2, true),
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+        assert_eq!(approximate_polygon_dp(&square, 0.05, true).len(), 5);
+        assert_eq!(approximate_polygon_dp(&[(1.0, 1.0), (1.0, 1.0), (1.0, 1.0)], 1.0, true), vec![(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_approximate_polygon_dp_of_contour() {
+        let image = GrayImage::from_fn(20, 12, |x, y| {
+            if x > 2 && x < 17 && y > 1 && y < 10 { Luma([255]) } else { Luma([0]) }
+        });
+        let contour = &find_contours(&image)[0];
+        let points: Vec<(f32, f32)> = contour.points.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+        assert_eq!(
+            approximate_polygon_dp(&points, 1.0, true),
+            vec![(3.0, 2.0), (3.0, 9.0), (16.0, 9.0), (16.0, 2.0)]);
+    }
+
+    #[bench]
+    fn bench_find_contours(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| {
+            let contours = find_contours_with_threshold(&image, 5);
+            black_box(contours);
+        });
+    }
+}
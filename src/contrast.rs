@@ -1,25 +1,209 @@
 //! Functions for manipulating the contrast of images.
+//!
+//! The thresholding functions in this module are also re-exported from
+//! [`threshold`](../threshold/index.html).
 
 use std::cmp::{min, max};
-use image::{GrayImage, ImageBuffer, Luma};
-use definitions::{HasBlack, HasWhite};
-use integral_image::{integral_image, sum_image_pixels};
-use stats::{cumulative_histogram, histogram};
-use rayon::prelude::*;
+use image::{GenericImageView, GrayImage, Luma, Primitive};
+use definitions::{Image, MaybeSync};
+use integral_image::{integral_image, integral_image_as, sum_image_pixels};
+use stats::{cumulative_histogram, histogram_u16, histogram_view, min_max, Bins};
+use parallel::for_each_row;
+use view::enumerate_pixels;
 
 /// Applies an adaptive threshold to an image.
 ///
 /// This algorithm compares each pixel's brightness with the average brightness of the pixels
 /// in the (2 * `block_radius` + 1) square block centered on it. If the pixel if at least as bright
 /// as the threshold then it will have a value of 255 in the output image, otherwise 0.
+///
+/// Equivalent to calling [`adaptive_threshold_with_method`](fn.adaptive_threshold_with_method.html)
+/// with `AdaptiveMethod::Mean` and an offset of 0.
 pub fn adaptive_threshold(image: &GrayImage, block_radius: u32) -> GrayImage {
+    adaptive_threshold_with_method(image, block_radius, AdaptiveMethod::Mean, 0)
+}
+
+/// Applies an adaptive threshold to an image in place.
+/// See [`adaptive_threshold`](fn.adaptive_threshold.html).
+pub fn adaptive_threshold_mut(image: &mut GrayImage, block_radius: u32) {
+    adaptive_threshold_with_method_mut(image, block_radius, AdaptiveMethod::Mean, 0);
+}
+
+/// How [`adaptive_threshold_with_method`](fn.adaptive_threshold_with_method.html) computes
+/// the local brightness around each pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AdaptiveMethod {
+    /// The mean of the pixels in the block, rounded down.
+    Mean,
+    /// A Gaussian weighted mean of the pixels in the block, rounded to the nearest integer.
+    /// The Gaussian has standard deviation `block_radius / 2`, so that the block is
+    /// truncated at two standard deviations.
+    Gaussian,
+}
+
+/// Applies an adaptive threshold to an image.
+///
+/// Each pixel is compared with the local brightness of the (2 * `block_radius` + 1) square
+/// block centered on it, computed using `method`. Pixels at least as bright as the local
+/// brightness minus `offset` have a value of 255 in the output image, and all other pixels
+/// have a value of 0. Blocks are truncated at the image boundaries.
+///
+/// Positive values of `offset` classify more pixels as foreground. This can be used to
+/// prevent noise in regions of near constant brightness from appearing in the output.
+///
+/// # Panics
+/// If `block_radius` is 0.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::{adaptive_threshold_with_method, AdaptiveMethod};
+///
+/// let image = gray_image!(
+///     100, 100, 100, 100;
+///     100,  98, 100, 250;
+///     100, 100, 100, 100);
+///
+/// // Without an offset, the pixel of brightness 98 is background.
+/// assert_pixels_eq!(
+///     adaptive_threshold_with_method(&image, 1, AdaptiveMethod::Mean, 0),
+///     gray_image!(
+///         255, 255,   0,   0;
+///         255,   0,   0, 255;
+///         255, 255,   0,   0));
+///
+/// // With an offset of 5, only pixels near the bright pixel are background.
+/// assert_pixels_eq!(
+///     adaptive_threshold_with_method(&image, 1, AdaptiveMethod::Mean, 5),
+///     gray_image!(
+///         255, 255,   0,   0;
+///         255, 255,   0, 255;
+///         255, 255,   0,   0));
+/// # }
+/// ```
+pub fn adaptive_threshold_with_method(
+    image: &GrayImage,
+    block_radius: u32,
+    method: AdaptiveMethod,
+    offset: i32,
+) -> GrayImage {
+    let mut out = image.clone();
+    adaptive_threshold_with_method_mut(&mut out, block_radius, method, offset);
+    out
+}
+
+/// Applies an adaptive threshold to an image in place.
+/// See [`adaptive_threshold_with_method`](fn.adaptive_threshold_with_method.html).
+///
+/// # Panics
+/// If `block_radius` is 0.
+pub fn adaptive_threshold_with_method_mut(
+    image: &mut GrayImage,
+    block_radius: u32,
+    method: AdaptiveMethod,
+    offset: i32,
+) {
     assert!(block_radius > 0);
+    let local = match method {
+        AdaptiveMethod::Mean => local_means(image, block_radius),
+        AdaptiveMethod::Gaussian => local_gaussian_means(image, block_radius),
+    };
+
+    for (p, l) in image.iter_mut().zip(local.iter()) {
+        *p = if *p as i32 >= *l as i32 - offset { 255 } else { 0 };
+    }
+}
+
+/// Applies [Bradley-Roth] adaptive thresholding to an image.
+///
+/// Each pixel is compared with the mean of the square window centered on it, whose side is
+/// `window_fraction` times the image width, rounded to the nearest integer and at least 1.
+/// Pixels brighter than the mean reduced by `offset_percent` percent have a value of 255 in
+/// the output image, and all other pixels have a value of 0. Windows are truncated at the
+/// image boundaries.
+///
+/// Window sums are computed from a single integral image, so the running time does not
+/// depend on the window size. The paper suggests a `window_fraction` of 1/8 and an
+/// `offset_percent` of 15, which work well for binarizing scanned documents.
+///
+/// [Bradley-Roth]: https://doi.org/10.1080/2151237X.2007.10129236
+///
+/// # Panics
+/// If `window_fraction` is not in the range (0, 1] or `offset_percent` is not in the range
+/// [0, 100].
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::bradley_threshold;
+///
+/// let image = gray_image!(
+///     200, 200, 200, 200;
+///     200,  50, 200, 200;
+///     200, 200, 200, 200);
+///
+/// assert_pixels_eq!(
+///     bradley_threshold(&image, 0.75, 15.0),
+///     gray_image!(
+///         255, 255, 255, 255;
+///         255,   0, 255, 255;
+///         255, 255, 255, 255));
+/// # }
+/// ```
+pub fn bradley_threshold(image: &GrayImage, window_fraction: f32, offset_percent: f32) -> GrayImage {
+    let mut out = image.clone();
+    bradley_threshold_mut(&mut out, window_fraction, offset_percent);
+    out
+}
+
+/// Applies [Bradley-Roth] adaptive thresholding to an image in place.
+/// See [`bradley_threshold`](fn.bradley_threshold.html).
+///
+/// [Bradley-Roth]: https://doi.org/10.1080/2151237X.2007.10129236
+///
+/// # Panics
+/// If `window_fraction` is not in the range (0, 1] or `offset_percent` is not in the range
+/// [0, 100].
+pub fn bradley_threshold_mut(image: &mut GrayImage, window_fraction: f32, offset_percent: f32) {
+    assert!(window_fraction > 0.0 && window_fraction <= 1.0, "window_fraction must be in the range (0, 1]");
+    assert!(offset_percent >= 0.0 && offset_percent <= 100.0, "offset_percent must be in the range [0, 100]");
+
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let side = max(1, (width as f32 * window_fraction).round() as u32);
+    let half = side / 2;
+    let scale = 1.0 - offset_percent as f64 / 100.0;
+    let integral: Image<Luma<u64>> = integral_image_as(image);
+
+    for y in 0..height {
+        let (top, bottom) = (y.saturating_sub(half), min(height - 1, y + half));
+        for x in 0..width {
+            let (left, right) = (x.saturating_sub(half), min(width - 1, x + half));
+            let count = ((bottom - top + 1) * (right - left + 1)) as u64;
+            let sum = sum_image_pixels(&integral, left, top, right, bottom);
+            let p = image.get_pixel_mut(x, y);
+            p[0] = if (p[0] as u64 * count) as f64 > sum as f64 * scale { 255 } else { 0 };
+        }
+    }
+}
+
+/// Returns the mean of the (2 * `block_radius` + 1) square block centered on each pixel,
+/// rounded down, in row-major order.
+fn local_means(image: &GrayImage, block_radius: u32) -> Vec<u32> {
     let integral = integral_image(image);
-    let mut out = ImageBuffer::from_pixel(image.width(), image.height(), Luma::black());
+    let mut means = Vec::with_capacity(image.len());
 
     for y in 0..image.height() {
         for x in 0..image.width() {
-            let current_pixel = image.get_pixel(x, y);
             // Traverse all neighbors in (2 * block_radius + 1) x (2 * block_radius + 1)
             let (y_low, y_high) = (
                 max(0, y as i32 - (block_radius as i32)) as u32,
@@ -32,27 +216,171 @@ pub fn adaptive_threshold(image: &GrayImage, block_radius: u32) -> GrayImage {
 
             // Number of pixels in the block, adjusted for edge cases.
             let w = (y_high - y_low + 1) * (x_high - x_low + 1);
-            let mean = sum_image_pixels(&integral, x_low, y_low, x_high, y_high) / w;
+            means.push(sum_image_pixels(&integral, x_low, y_low, x_high, y_high) / w);
+        }
+    }
+    means
+}
 
-            if current_pixel[0] as u32 >= mean as u32 {
-                out.put_pixel(x, y, Luma::white());
+/// Returns the Gaussian weighted mean of the (2 * `block_radius` + 1) square block
+/// centered on each pixel, rounded to the nearest integer, in row-major order.
+fn local_gaussian_means(image: &GrayImage, block_radius: u32) -> Vec<u32> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let radius = block_radius as usize;
+    let sigma = block_radius as f32 / 2.0;
+    let weights: Vec<f32> = (0..radius + 1)
+        .map(|d| (-((d * d) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    // The weights are separable, so normalising each pass by the sum of the weights
+    // inside the image normalises the result by the total weight of the truncated block.
+    let smooth = |values: &[f32], len: usize, stride: usize, out: &mut [f32], start: usize| {
+        for i in 0..len {
+            let (lo, hi) = (i.saturating_sub(radius), min(len - 1, i + radius));
+            let (mut sum, mut total_weight) = (0f32, 0f32);
+            for j in lo..hi + 1 {
+                let w = weights[max(i, j) - min(i, j)];
+                sum += w * values[start + j * stride];
+                total_weight += w;
             }
+            out[start + i * stride] = sum / total_weight;
         }
+    };
+
+    let pixels: Vec<f32> = image.iter().map(|p| *p as f32).collect();
+    let mut horizontal = vec![0f32; pixels.len()];
+    for y in 0..height {
+        smooth(&pixels, width, 1, &mut horizontal, y * width);
     }
-    out
+    let mut smoothed = vec![0f32; pixels.len()];
+    for x in 0..width {
+        smooth(&horizontal, height, width, &mut smoothed, x);
+    }
+    smoothed.iter().map(|v| v.round() as u32).collect()
 }
 
 /// Returns the [Otsu threshold level] of an 8bpp image.
 ///
 /// [Otsu threshold level]: https://en.wikipedia.org/wiki/Otsu%27s_method
-pub fn otsu_level(image: &GrayImage) -> u8 {
-    let hist = histogram(image);
-    let (width, height) = image.dimensions();
-    let total_weight = width * height;
+pub fn otsu_level<I>(image: &I) -> u8
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
+    otsu_level_from_histogram(&histogram_view(image))
+}
+
+/// Binarizes an 8bpp image using its [Otsu threshold level](fn.otsu_level.html), and
+/// returns the binarized image along with the level used. Pixels with intensity less
+/// than or equal to the level are assigned to the background.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::otsu_threshold;
+///
+/// let image = gray_image!(
+///     10,  12, 200;
+///     11, 201, 199);
+///
+/// let (thresholded, level) = otsu_threshold(&image);
+/// assert_eq!(level, 12);
+/// assert_pixels_eq!(
+///     thresholded,
+///     gray_image!(
+///         0,   0, 255;
+///         0, 255, 255));
+/// # }
+/// ```
+pub fn otsu_threshold(image: &GrayImage) -> (GrayImage, u8) {
+    let level = otsu_level(image);
+    (threshold(image, level), level)
+}
+
+/// Returns the Otsu threshold level of an 8bpp image with the given histogram.
+pub(crate) fn otsu_level_from_histogram(hist: &[u32; 256]) -> u8 {
+    otsu_bin(hist) as u8
+}
+
+/// Returns the [Otsu threshold level] of a 16bpp image.
+///
+/// [Otsu threshold level]: https://en.wikipedia.org/wiki/Otsu%27s_method
+pub fn otsu_level_u16<I>(image: &I) -> u16
+where
+    I: GenericImageView<Pixel = Luma<u16>>,
+{
+    otsu_bin(&histogram_u16(image)) as u16
+}
+
+/// Returns the [Otsu threshold level] of a floating point image, computed
+/// from a histogram with `num_bins` equally sized bins spanning the range of
+/// intensities in the image. `NaN`s are ignored.
+///
+/// The level returned is the largest intensity in the background class, so
+/// that [`binarize`](fn.binarize.html) separates the classes exactly.
+///
+/// [Otsu threshold level]: https://en.wikipedia.org/wiki/Otsu%27s_method
+///
+/// # Panics
+/// If `num_bins` is zero.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::{binarize, otsu_level_f32};
+///
+/// let image = gray_image!(type: f32,
+///     0.10, 0.12, 0.80;
+///     0.11, 0.79, 0.81);
+///
+/// let level = otsu_level_f32(&image, 256);
+/// assert_eq!(level, 0.12);
+///
+/// assert_pixels_eq!(
+///     binarize(&image, level),
+///     gray_image!(
+///         0, 0, 255;
+///         0, 255, 255));
+/// # }
+/// ```
+pub fn otsu_level_f32<I>(image: &I, num_bins: usize) -> f32
+where
+    I: GenericImageView<Pixel = Luma<f32>>,
+{
+    assert!(num_bins > 0, "num_bins must be non-zero");
+    let (lower, upper) = match min_max(image) {
+        Some((lower, upper)) if upper > lower => (lower, upper),
+        Some((lower, _)) => return lower,
+        None => return ::std::f32::NAN,
+    };
+
+    let bins = Bins::new(num_bins, lower as f64, upper as f64);
+    let mut hist = vec![0u32; num_bins];
+    let mut bin_maxima = vec![lower; num_bins];
+    for (_, _, p) in enumerate_pixels(image) {
+        if let Some(bin) = bins.index(p[0] as f64) {
+            hist[bin] += 1;
+            bin_maxima[bin] = f32::max(bin_maxima[bin], p[0]);
+        }
+    }
+
+    let level = otsu_bin(&hist);
+    bin_maxima[..level + 1].iter().cloned().fold(lower, f32::max)
+}
+
+/// Returns the index of the bin in `hist` at which to threshold using Otsu's method.
+/// Bins at or below this index form the background class.
+fn otsu_bin(hist: &[u32]) -> usize {
+    let total_weight: u32 = hist.iter().sum();
 
     // Sum of all pixel intensities, to use when calculating means.
     let total_pixel_sum = hist.iter().enumerate().fold(0f64, |sum, (t, h)| {
-        sum + (t as u32 * h) as f64
+        sum + t as f64 * *h as f64
     });
 
     // Sum of all pixel intensities in the background class.
@@ -65,7 +393,7 @@ pub fn otsu_level(image: &GrayImage) -> u8 {
     let mut foreground_weight;
 
     let mut largest_variance = 0f64;
-    let mut best_threshold = 0u8;
+    let mut best_threshold = 0;
 
     for (threshold, hist_count) in hist.iter().enumerate() {
         background_weight = background_weight + hist_count;
@@ -78,7 +406,7 @@ pub fn otsu_level(image: &GrayImage) -> u8 {
             break;
         };
 
-        background_pixel_sum += (threshold as u32 * hist_count) as f64;
+        background_pixel_sum += threshold as f64 * *hist_count as f64;
         let foreground_pixel_sum = total_pixel_sum - background_pixel_sum;
 
         let background_mean = background_pixel_sum / (background_weight as f64);
@@ -90,7 +418,7 @@ pub fn otsu_level(image: &GrayImage) -> u8 {
 
         if intra_class_variance > largest_variance {
             largest_variance = intra_class_variance;
-            best_threshold = threshold as u8;
+            best_threshold = threshold;
         }
     }
 
@@ -126,6 +454,46 @@ pub fn threshold(image: &GrayImage, thresh: u8) -> GrayImage {
     out
 }
 
+/// Returns a binarized image from a grayscale image with any subpixel type,
+/// e.g. `u16` or `f32`, obtained by applying the given threshold. Pixels with
+/// intensity less than or equal to the threshold are assigned to the background
+/// (0), and all other pixels to the foreground (255).
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::binarize;
+///
+/// let image = gray_image!(type: u16,
+///     1000, 8000, 2000;
+///     5000, 9000, 7000);
+///
+/// let binarized = gray_image!(
+///     0, 255,   0;
+///     0, 255, 255);
+///
+/// assert_pixels_eq!(binarize(&image, 5000), binarized);
+/// # }
+/// ```
+pub fn binarize<T>(image: &Image<Luma<T>>, thresh: T) -> GrayImage
+where
+    T: Primitive + MaybeSync + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = GrayImage::new(width, height);
+    let raw: &[T] = &**image;
+    for_each_row(&mut out, |y, row| {
+        let start = y as usize * width as usize;
+        for (o, p) in row.iter_mut().zip(raw[start..start + width as usize].iter()) {
+            *o = if *p <= thresh { 0 } else { 255 };
+        }
+    });
+    out
+}
+
 /// Mutates given image to form a binarized version produced by applying
 /// the given threshold. Pixels with intensity
 /// equal to the threshold are assigned to the background.
@@ -152,21 +520,113 @@ pub fn threshold(image: &GrayImage, thresh: u8) -> GrayImage {
 /// # }
 /// ```
 pub fn threshold_mut(image: &mut GrayImage, thresh: u8) {
-    for p in image.iter_mut() {
-        *p = if *p <= thresh { 0 } else { 255 };
+    apply_lut_mut(image, &threshold_lut(thresh));
+}
+
+/// Writes a binarized version of `image` to `out`. See [`threshold`](fn.threshold.html).
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn threshold_into(image: &GrayImage, thresh: u8, out: &mut GrayImage) {
+    apply_lut_into(image, &threshold_lut(thresh), out);
+}
+
+/// Returns the intensity mapping used by `threshold`.
+pub(crate) fn threshold_lut(thresh: u8) -> [u8; 256] {
+    let mut lut = [0u8; 256];
+    for (p, l) in lut.iter_mut().enumerate() {
+        *l = if p <= thresh as usize { 0 } else { 255 };
     }
+    lut
+}
+
+/// Returns a copy of `image` with each intensity `p` replaced by `lut[p]`.
+///
+/// Any point operation on an 8bpp image can be expressed as a lookup table, and applying
+/// a lookup table is usually much faster than evaluating the operation for every pixel.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::apply_lut;
+///
+/// let image = gray_image!(
+///     0,  1, 2;
+///     3, 16, 255);
+///
+/// let mut square_root = [0u8; 256];
+/// for (p, l) in square_root.iter_mut().enumerate() {
+///     *l = (p as f32).sqrt() as u8;
+/// }
+///
+/// assert_pixels_eq!(
+///     apply_lut(&image, &square_root),
+///     gray_image!(
+///         0, 1, 1;
+///         1, 4, 15));
+/// # }
+/// ```
+pub fn apply_lut(image: &GrayImage, lut: &[u8; 256]) -> GrayImage {
+    let mut out = image.clone();
+    apply_lut_mut(&mut out, lut);
+    out
+}
+
+/// Replaces each intensity `p` in `image` with `lut[p]`. See [`apply_lut`](fn.apply_lut.html).
+pub fn apply_lut_mut(image: &mut GrayImage, lut: &[u8; 256]) {
+    for_each_row(image, |_, row| {
+        for p in row.iter_mut() {
+            *p = lut[*p as usize];
+        }
+    });
+}
+
+/// Writes `lut[p]` to `out` for each intensity `p` in `image`. See [`apply_lut`](fn.apply_lut.html).
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn apply_lut_into(image: &GrayImage, lut: &[u8; 256], out: &mut GrayImage) {
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    let width = image.width() as usize;
+    let raw: &[u8] = &**image;
+    for_each_row(out, |y, row| {
+        let start = y as usize * width;
+        for (o, p) in row.iter_mut().zip(raw[start..start + width].iter()) {
+            *o = lut[*p as usize];
+        }
+    });
 }
 
 /// Equalises the histogram of an 8bpp grayscale image in place. See also
 /// [histogram equalization (wikipedia)](https://en.wikipedia.org/wiki/Histogram_equalization).
 pub fn equalize_histogram_mut(image: &mut GrayImage) {
-    let hist = cumulative_histogram(image);
-    let total = hist[255] as f32;
+    let lut = equalize_histogram_lut(&cumulative_histogram(image));
+    apply_lut_mut(image, &lut);
+}
 
-    image.par_iter_mut().for_each(|p| {
-        let fraction = unsafe { *hist.get_unchecked(*p as usize) as f32 / total };
-        *p = (f32::min(255f32, 255f32 * fraction)) as u8;
-    });
+/// Writes the histogram equalisation of an 8bpp grayscale image to `out`.
+/// See [`equalize_histogram`](fn.equalize_histogram.html).
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn equalize_histogram_into(image: &GrayImage, out: &mut GrayImage) {
+    let lut = equalize_histogram_lut(&cumulative_histogram(image));
+    apply_lut_into(image, &lut, out);
+}
+
+/// Returns the intensity mapping used to equalise the histogram of an 8bpp image
+/// with the given cumulative histogram.
+pub(crate) fn equalize_histogram_lut(cumulative_hist: &[u32; 256]) -> [u8; 256] {
+    let total = cumulative_hist[255] as f32;
+    let mut lut = [0u8; 256];
+    for (l, h) in lut.iter_mut().zip(cumulative_hist.iter()) {
+        let fraction = *h as f32 / total;
+        *l = (f32::min(255f32, 255f32 * fraction)) as u8;
+    }
+    lut
 }
 
 /// Equalises the histogram of an 8bpp grayscale image. See also
@@ -177,6 +637,149 @@ pub fn equalize_histogram(image: &GrayImage) -> GrayImage {
     out
 }
 
+/// Applies contrast limited adaptive histogram equalization (CLAHE) to an 8bpp grayscale image.
+///
+/// The image is divided into a grid of `tiles_x` by `tiles_y` tiles, and an equalization
+/// mapping is computed from the histogram of each tile. Each output pixel is a bilinear
+/// interpolation of the results of applying the mappings of the four tiles whose centres
+/// are nearest to it, so that there are no discontinuities at tile boundaries. Pixels
+/// nearer the image boundary than the nearest tile centre use fewer tiles.
+///
+/// Before computing each mapping, the histogram bins of the tile are clipped to
+/// `clip_limit` times the mean bin count, and the clipped counts are redistributed
+/// evenly between all bins. This limits how much contrast is amplified in nearly
+/// uniform regions. Smaller values of `clip_limit` give weaker equalization, and large
+/// values give the same result as equalizing the histogram of each tile.
+///
+/// If the image is narrower than `tiles_x` or shorter than `tiles_y` then fewer tiles are used.
+///
+/// # Panics
+/// If `tiles_x` or `tiles_y` is zero, or `clip_limit` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::contrast::{clahe, equalize_histogram};
+///
+/// let image = GrayImage::from_fn(40, 40, |x, y| Luma([100 + ((x + y) % 8) as u8]));
+///
+/// // With a single tile and no effective clipping, CLAHE equalizes the whole image.
+/// assert_eq!(*clahe(&image, 1, 1, 1000.0), *equalize_histogram(&image));
+///
+/// // Stronger clipping gives less contrast.
+/// let range = |image: &GrayImage| image.iter().max().unwrap() - image.iter().min().unwrap();
+/// assert!(range(&clahe(&image, 4, 4, 2.0)) < range(&clahe(&image, 4, 4, 40.0)));
+/// # }
+/// ```
+pub fn clahe(image: &GrayImage, tiles_x: u32, tiles_y: u32, clip_limit: f32) -> GrayImage {
+    let mut out = image.clone();
+    clahe_mut(&mut out, tiles_x, tiles_y, clip_limit);
+    out
+}
+
+/// Applies contrast limited adaptive histogram equalization (CLAHE) to an 8bpp grayscale
+/// image in place. See [`clahe`](fn.clahe.html).
+///
+/// # Panics
+/// If `tiles_x` or `tiles_y` is zero, or `clip_limit` is not positive.
+pub fn clahe_mut(image: &mut GrayImage, tiles_x: u32, tiles_y: u32, clip_limit: f32) {
+    assert!(tiles_x > 0 && tiles_y > 0, "the number of tiles must be non-zero");
+    assert!(clip_limit > 0.0, "clip_limit must be positive");
+
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (tiles_x, tiles_y) = (min(tiles_x, width), min(tiles_y, height));
+
+    // Tile i along an axis of length len covers [i * len / tiles, (i + 1) * len / tiles).
+    let tile_start = |i: u32, len: u32, tiles: u32| (i as u64 * len as u64 / tiles as u64) as u32;
+
+    let mut luts = Vec::with_capacity((tiles_x * tiles_y) as usize);
+    for ty in 0..tiles_y {
+        let (y0, y1) = (tile_start(ty, height, tiles_y), tile_start(ty + 1, height, tiles_y));
+        for tx in 0..tiles_x {
+            let (x0, x1) = (tile_start(tx, width, tiles_x), tile_start(tx + 1, width, tiles_x));
+            let mut hist = [0u32; 256];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    hist[image.get_pixel(x, y)[0] as usize] += 1;
+                }
+            }
+            let count = (x1 - x0) * (y1 - y0);
+            let limit = f32::max(1.0, clip_limit * count as f32 / 256.0) as u32;
+            clip_histogram(&mut hist, limit);
+            for i in 1..256 {
+                hist[i] += hist[i - 1];
+            }
+            luts.push(equalize_histogram_lut(&hist));
+        }
+    }
+
+    // For each row or column, the two nearest tiles and the weight of the second.
+    let interpolation = |len: u32, tiles: u32| -> Vec<(usize, usize, f32)> {
+        let centre = |i: u32| (tile_start(i, len, tiles) + tile_start(i + 1, len, tiles)) as f32 / 2.0 - 0.5;
+        (0..len)
+            .map(|p| {
+                let p = p as f32;
+                if p <= centre(0) {
+                    return (0, 0, 0.0);
+                }
+                if p >= centre(tiles - 1) {
+                    return ((tiles - 1) as usize, (tiles - 1) as usize, 0.0);
+                }
+                let mut i = 0;
+                while centre(i + 1) < p {
+                    i += 1;
+                }
+                let weight = (p - centre(i)) / (centre(i + 1) - centre(i));
+                (i as usize, i as usize + 1, weight)
+            })
+            .collect()
+    };
+    let xs = interpolation(width, tiles_x);
+    let ys = interpolation(height, tiles_y);
+
+    let stride = tiles_x as usize;
+    for (y, &(top, bottom, bottom_weight)) in ys.iter().enumerate() {
+        for (x, &(left, right, right_weight)) in xs.iter().enumerate() {
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+            let p = pixel[0] as usize;
+            let map = |tx: usize, ty: usize| luts[ty * stride + tx][p] as f32;
+            let upper = (1.0 - right_weight) * map(left, top) + right_weight * map(right, top);
+            let lower = (1.0 - right_weight) * map(left, bottom) + right_weight * map(right, bottom);
+            let value = (1.0 - bottom_weight) * upper + bottom_weight * lower;
+            pixel[0] = value.round() as u8;
+        }
+    }
+}
+
+/// Clips each bin of `hist` to `limit` and redistributes the excess evenly between all bins.
+/// Bins may exceed `limit` after redistribution.
+fn clip_histogram(hist: &mut [u32; 256], limit: u32) {
+    let mut excess = 0;
+    for h in hist.iter_mut() {
+        if *h > limit {
+            excess += *h - limit;
+            *h = limit;
+        }
+    }
+    let increment = excess / 256;
+    let remainder = (excess % 256) as usize;
+    for h in hist.iter_mut() {
+        *h += increment;
+    }
+    if remainder > 0 {
+        let step = 256 / remainder;
+        for i in 0..remainder {
+            hist[i * step] += 1;
+        }
+    }
+}
+
 /// Adjusts contrast of an 8bpp grayscale image in place so that its
 /// histogram is as close as possible to that of the target image.
 pub fn match_histogram_mut(image: &mut GrayImage, target: &GrayImage) {
@@ -184,9 +787,28 @@ pub fn match_histogram_mut(image: &mut GrayImage, target: &GrayImage) {
     let target_histc = cumulative_histogram(target);
     let lut = histogram_lut(&image_histc, &target_histc);
 
-    for p in image.iter_mut() {
-        *p = lut[*p as usize] as u8;
+    let mut lut_u8 = [0u8; 256];
+    for (l, m) in lut_u8.iter_mut().zip(lut.iter()) {
+        *l = *m as u8;
     }
+    apply_lut_mut(image, &lut_u8);
+}
+
+/// Writes a version of an 8bpp grayscale image whose histogram is as close as possible
+/// to that of the target image to `out`. See [`match_histogram`](fn.match_histogram.html).
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn match_histogram_into(image: &GrayImage, target: &GrayImage, out: &mut GrayImage) {
+    let image_histc = cumulative_histogram(image);
+    let target_histc = cumulative_histogram(target);
+    let lut = histogram_lut(&image_histc, &target_histc);
+
+    let mut lut_u8 = [0u8; 256];
+    for (l, m) in lut_u8.iter_mut().zip(lut.iter()) {
+        *l = *m as u8;
+    }
+    apply_lut_into(image, &lut_u8, out);
 }
 
 /// Adjusts contrast of an 8bpp grayscale image so that its
@@ -275,18 +897,101 @@ pub fn stretch_contrast(image: &GrayImage, lower: u8, upper: u8) -> GrayImage {
 ///
 /// See the [`stretch_contrast`](fn.stretch_contrast.html) documentation for more.
 pub fn stretch_contrast_mut(image: &mut GrayImage, lower: u8, upper: u8) {
+    apply_lut_mut(image, &stretch_contrast_lut(lower, upper));
+}
+
+/// Writes a linearly contrast-stretched version of `image` to `out`.
+///
+/// See the [`stretch_contrast`](fn.stretch_contrast.html) documentation for more.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions, or `upper` is not strictly
+/// greater than `lower`.
+pub fn stretch_contrast_into(image: &GrayImage, lower: u8, upper: u8, out: &mut GrayImage) {
+    apply_lut_into(image, &stretch_contrast_lut(lower, upper), out);
+}
+
+/// Returns the intensity mapping used by `stretch_contrast`.
+pub(crate) fn stretch_contrast_lut(lower: u8, upper: u8) -> [u8; 256] {
     assert!(upper > lower, "upper must be strictly greater than lower");
     let len = (upper - lower) as u16;
-    for p in image.iter_mut() {
-        if *p >= upper {
-            *p = 255;
-        } else if *p <= lower {
-            *p = 0;
+    let mut lut = [0u8; 256];
+    for (p, l) in lut.iter_mut().enumerate() {
+        let p = p as u8;
+        *l = if p >= upper {
+            255
+        } else if p <= lower {
+            0
         } else {
-            let scaled = (255 * (*p as u16 - lower as u16)) / len;
-            *p = scaled as u8;
-        }
+            ((255 * (p as u16 - lower as u16)) / len) as u8
+        };
     }
+    lut
+}
+
+/// Applies gamma correction to an 8bpp grayscale image, mapping each intensity `p` to
+/// `255 * (p / 255)^gamma`, rounded to the nearest integer.
+///
+/// Values of `gamma` less than 1 brighten the image, and values greater than 1 darken it.
+/// Black and white are unchanged.
+///
+/// # Panics
+/// If `gamma` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::contrast::gamma_correct;
+///
+/// let image = gray_image!(
+///     0,  64, 128;
+///   192, 240, 255);
+///
+/// assert_pixels_eq!(
+///     gamma_correct(&image, 0.5),
+///     gray_image!(
+///         0, 128, 181;
+///       221, 247, 255));
+///
+/// assert_pixels_eq!(
+///     gamma_correct(&image, 2.0),
+///     gray_image!(
+///         0,  16,  64;
+///       145, 226, 255));
+/// # }
+/// ```
+pub fn gamma_correct(image: &GrayImage, gamma: f32) -> GrayImage {
+    apply_lut(image, &gamma_lut(gamma))
+}
+
+/// Applies gamma correction to an 8bpp grayscale image in place.
+///
+/// See the [`gamma_correct`](fn.gamma_correct.html) documentation for more.
+pub fn gamma_correct_mut(image: &mut GrayImage, gamma: f32) {
+    apply_lut_mut(image, &gamma_lut(gamma));
+}
+
+/// Writes a gamma corrected version of `image` to `out`.
+///
+/// See the [`gamma_correct`](fn.gamma_correct.html) documentation for more.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions, or `gamma` is not positive.
+pub fn gamma_correct_into(image: &GrayImage, gamma: f32, out: &mut GrayImage) {
+    apply_lut_into(image, &gamma_lut(gamma), out);
+}
+
+/// Returns the intensity mapping used by `gamma_correct`.
+pub(crate) fn gamma_lut(gamma: f32) -> [u8; 256] {
+    assert!(gamma > 0.0, "gamma must be positive");
+    let mut lut = [0u8; 256];
+    for (p, l) in lut.iter_mut().enumerate() {
+        *l = (255.0 * (p as f32 / 255.0).powf(gamma)).round() as u8;
+    }
+    lut
 }
 
 #[cfg(test)]
@@ -294,9 +999,75 @@ mod test {
     use super::*;
     use definitions::{HasBlack, HasWhite};
     use utils::gray_bench_image;
-    use image::{GrayImage, Luma};
+    use image::{GrayImage, ImageBuffer, Luma};
+    use property_testing::GrayTestImage;
+    use quickcheck::{quickcheck, TestResult};
     use test::{Bencher, black_box};
 
+    #[test]
+    fn test_otsu_level_u16_matches_otsu_level() {
+        fn prop(image: GrayTestImage) -> bool {
+            let image = image.0;
+            let wide = ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+                Luma([image.get_pixel(x, y)[0] as u16])
+            });
+            otsu_level_u16(&wide) == otsu_level(&image) as u16
+        }
+        quickcheck(prop as fn(GrayTestImage) -> bool);
+    }
+
+    #[test]
+    fn test_otsu_level_f32_separates_classes() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let image = image.0;
+            if image.width() == 0 || image.height() == 0 {
+                return TestResult::discard();
+            }
+            let float = ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+                Luma([image.get_pixel(x, y)[0] as f32 / 255.0])
+            });
+            // With one bin per intensity the binned histogram matches the 8bpp histogram
+            // up to an offset, so the classes should match.
+            let (lower, upper) = min_max(&image).unwrap();
+            let num_bins = (upper - lower) as usize + 1;
+            let expected = threshold(&image, otsu_level(&image));
+            let actual = binarize(&float, otsu_level_f32(&float, num_bins));
+            TestResult::from_bool(upper == lower || *actual == *expected)
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_binarize_matches_threshold() {
+        let image = gray_image!(
+            10, 80, 20;
+            50, 90, 70);
+        assert_pixels_eq!(binarize(&image, 50), threshold(&image, 50));
+    }
+
+    #[test]
+    fn test_into_variants_match_allocating_variants() {
+        let image = gray_image!(
+            10, 80, 20, 255;
+            50, 90, 70, 0);
+        let target = gray_image!(
+            0, 0, 100, 200;
+            30, 30, 30, 30);
+        let mut out = GrayImage::new(4, 2);
+
+        threshold_into(&image, 50, &mut out);
+        assert_pixels_eq!(out, threshold(&image, 50));
+
+        stretch_contrast_into(&image, 20, 90, &mut out);
+        assert_pixels_eq!(out, stretch_contrast(&image, 20, 90));
+
+        equalize_histogram_into(&image, &mut out);
+        assert_pixels_eq!(out, equalize_histogram(&image));
+
+        match_histogram_into(&image, &target, &mut out);
+        assert_pixels_eq!(out, match_histogram(&image, &target));
+    }
+
     #[test]
     fn adaptive_threshold_constant() {
         let image = GrayImage::from_pixel(3, 3, Luma([100u8]));
@@ -351,6 +1122,213 @@ mod test {
         }
     }
 
+    #[test]
+    fn adaptive_threshold_gaussian_constant() {
+        let image = GrayImage::from_pixel(7, 4, Luma([100u8]));
+        let binary = adaptive_threshold_with_method(&image, 3, AdaptiveMethod::Gaussian, 0);
+        assert_pixels_eq!(binary, GrayImage::from_pixel(7, 4, Luma::white()));
+    }
+
+    #[test]
+    fn adaptive_threshold_gaussian_weights_nearby_pixels_more() {
+        // The centre pixel is brighter than the mean of the block, but
+        // darker than the Gaussian weighted mean as its neighbours are bright.
+        let image = gray_image!(
+            0, 0, 200, 100, 200, 0, 0);
+        let gaussian = adaptive_threshold_with_method(&image, 3, AdaptiveMethod::Gaussian, 0);
+        let mean = adaptive_threshold_with_method(&image, 3, AdaptiveMethod::Mean, 0);
+        assert_eq!(gaussian.get_pixel(3, 0)[0], 0);
+        assert_eq!(mean.get_pixel(3, 0)[0], 255);
+    }
+
+    #[test]
+    fn adaptive_threshold_offset() {
+        let mut image = GrayImage::from_pixel(5, 5, Luma([100u8]));
+        image.put_pixel(2, 2, Luma([97u8]));
+        for method in &[AdaptiveMethod::Mean, AdaptiveMethod::Gaussian] {
+            let binary = adaptive_threshold_with_method(&image, 2, *method, 0);
+            assert_eq!(binary.get_pixel(2, 2)[0], 0);
+            let binary = adaptive_threshold_with_method(&image, 2, *method, 3);
+            assert_pixels_eq!(binary, GrayImage::from_pixel(5, 5, Luma::white()));
+            let binary = adaptive_threshold_with_method(&image, 2, *method, -5);
+            assert_pixels_eq!(binary, GrayImage::from_pixel(5, 5, Luma::black()));
+        }
+    }
+
+    #[test]
+    fn bradley_threshold_separates_strokes_from_gradient_background() {
+        // Dark vertical strokes on a background whose brightness increases from left to
+        // right. No global threshold separates the strokes from the background.
+        let image = GrayImage::from_fn(64, 16, |x, y| {
+            let background = 100 + 2 * x as u8;
+            if x % 16 == 8 && y > 2 && y < 13 { Luma([background - 60]) } else { Luma([background]) }
+        });
+        let expected = GrayImage::from_fn(64, 16, |x, y| {
+            if x % 16 == 8 && y > 2 && y < 13 { Luma::black() } else { Luma::white() }
+        });
+        assert_pixels_eq!(bradley_threshold(&image, 0.125, 15.0), expected);
+    }
+
+    #[test]
+    fn bradley_threshold_offset() {
+        let mut image = GrayImage::from_pixel(5, 5, Luma([100u8]));
+        image.put_pixel(2, 2, Luma([95u8]));
+        assert_eq!(bradley_threshold(&image, 1.0, 0.0).get_pixel(2, 2)[0], 0);
+        assert_pixels_eq!(bradley_threshold(&image, 1.0, 10.0), GrayImage::from_pixel(5, 5, Luma::white()));
+    }
+
+    #[test]
+    fn bradley_threshold_empty_image() {
+        let image = GrayImage::new(0, 3);
+        assert_eq!(bradley_threshold(&image, 0.5, 15.0).dimensions(), (0, 3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bradley_threshold_rejects_zero_window_fraction() {
+        bradley_threshold(&GrayImage::new(4, 4), 0.0, 15.0);
+    }
+
+    #[test]
+    fn test_otsu_threshold_matches_otsu_level() {
+        fn prop(image: GrayTestImage) -> bool {
+            let image = image.0;
+            let (thresholded, level) = otsu_threshold(&image);
+            level == otsu_level(&image) && *thresholded == *threshold(&image, level)
+        }
+        quickcheck(prop as fn(GrayTestImage) -> bool);
+    }
+
+    #[bench]
+    fn bench_adaptive_threshold_gaussian(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| {
+            let thresholded = adaptive_threshold_with_method(&image, 10, AdaptiveMethod::Gaussian, 0);
+            black_box(thresholded);
+        });
+    }
+
+    #[bench]
+    fn bench_bradley_threshold(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| {
+            let thresholded = bradley_threshold(&image, 0.125, 15.0);
+            black_box(thresholded);
+        });
+    }
+
+    #[test]
+    fn test_clahe_single_tile_without_clipping_equalizes_histogram() {
+        fn prop(image: GrayTestImage) -> bool {
+            let image = image.0;
+            *clahe(&image, 1, 1, 1.0e6) == *equalize_histogram(&image)
+        }
+        quickcheck(prop as fn(GrayTestImage) -> bool);
+    }
+
+    #[test]
+    fn test_clahe_constant_image_is_constant() {
+        let image = GrayImage::from_pixel(32, 18, Luma([90]));
+        let equalized = clahe(&image, 4, 3, 3.0);
+        let first = equalized.get_pixel(0, 0)[0];
+        assert!(equalized.pixels().all(|p| p[0] == first));
+    }
+
+    #[test]
+    fn test_clahe_more_tiles_than_pixels() {
+        let image = gray_image!(
+            10, 20, 30;
+            40, 50, 60);
+        assert_pixels_eq!(clahe(&image, 8, 8, 1.0e6), clahe(&image, 3, 2, 1.0e6));
+        assert_eq!(clahe(&GrayImage::new(0, 5), 2, 2, 2.0).dimensions(), (0, 5));
+    }
+
+    #[test]
+    fn test_clahe_equalizes_tiles_independently() {
+        // Far from the centre line, each half is equalized using only its own histogram.
+        let image = GrayImage::from_fn(40, 10, |x, y| {
+            let base = if x < 20 { 10 } else { 200 };
+            Luma([base + ((x + y) % 5) as u8])
+        });
+        let equalized = clahe(&image, 2, 1, 1.0e6);
+        let left = image.view(0, 0, 20, 10).to_image();
+        let right = image.view(20, 0, 20, 10).to_image();
+        let left_lut = equalize_histogram_lut(&cumulative_histogram(&left));
+        let right_lut = equalize_histogram_lut(&cumulative_histogram(&right));
+        for y in 0..10 {
+            assert_eq!(equalized.get_pixel(3, y)[0], left_lut[image.get_pixel(3, y)[0] as usize]);
+            assert_eq!(equalized.get_pixel(35, y)[0], right_lut[image.get_pixel(35, y)[0] as usize]);
+        }
+    }
+
+    #[test]
+    fn test_clip_histogram() {
+        let mut hist = [0u32; 256];
+        hist[3] = 600;
+        hist[7] = 20;
+        clip_histogram(&mut hist, 50);
+        // 550 excess pixels: 2 added to every bin, and 1 to each of 38 evenly spaced bins.
+        assert_eq!(hist.iter().sum::<u32>(), 620);
+        assert_eq!(hist[0], 3);
+        assert_eq!(hist[1], 2);
+        assert_eq!(hist[3], 52);
+        assert_eq!(hist[7], 22);
+    }
+
+    #[bench]
+    fn bench_clahe(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| {
+            let equalized = clahe(&image, 8, 8, 4.0);
+            black_box(equalized);
+        });
+    }
+
+    #[test]
+    fn test_gamma_correct() {
+        let image = GrayImage::from_fn(16, 16, |x, y| Luma([(16 * y + x) as u8]));
+        assert_pixels_eq!(gamma_correct(&image, 1.0), image);
+
+        let brighter = gamma_correct(&image, 0.4);
+        let darker = gamma_correct(&image, 2.5);
+        for ((p, b), d) in image.iter().zip(brighter.iter()).zip(darker.iter()) {
+            assert!(b >= p && d <= p);
+        }
+        assert_eq!(brighter.get_pixel(15, 15)[0], 255);
+        assert_eq!(darker.get_pixel(0, 0)[0], 0);
+
+        let mut out = GrayImage::new(16, 16);
+        gamma_correct_into(&image, 0.4, &mut out);
+        assert_pixels_eq!(out, brighter);
+        let mut image = image;
+        gamma_correct_mut(&mut image, 2.5);
+        assert_pixels_eq!(image, darker);
+    }
+
+    #[test]
+    fn test_apply_lut_variants_match() {
+        let image = gray_bench_image(9, 7);
+        let mut lut = [0u8; 256];
+        for (p, l) in lut.iter_mut().enumerate() {
+            *l = (255 - p) as u8 / 3;
+        }
+        let expected = ImageBuffer::from_fn(9, 7, |x, y| Luma([lut[image.get_pixel(x, y)[0] as usize]]));
+        assert_pixels_eq!(apply_lut(&image, &lut), expected);
+
+        let mut out = GrayImage::new(9, 7);
+        apply_lut_into(&image, &lut, &mut out);
+        assert_pixels_eq!(out, expected);
+    }
+
+    #[bench]
+    fn bench_gamma_correct(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let corrected = gamma_correct(&image, 2.2);
+            black_box(corrected);
+        });
+    }
+
     #[bench]
     fn bench_adaptive_threshold(b: &mut Bencher) {
         let image = gray_bench_image(200, 200);
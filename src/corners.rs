@@ -1,12 +1,18 @@
 //! Functions for detecting corners, also known as interest points.
 
-use image::{GrayImage, GenericImageView};
-use definitions::{Position, Score};
+use image::{GenericImageView, GrayImage, Luma};
+use definitions::{Image, Position, Score};
+use error::{ImageProcError, Result};
+use filter::{filter3x3, gaussian_blur_f32};
+use map::map_subpixels;
+use structure_tensor::structure_tensor;
+use suppress::local_maxima;
 
 /// A location and score for a detected corner.
 /// The scores need not be comparable between different
 /// corner detectors.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Corner {
     /// x-coordinate of the corner.
     pub x: u32,
@@ -45,6 +51,334 @@ impl Score for Corner {
     }
 }
 
+/// A corner detected at a characteristic scale.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScaledCorner {
+    /// x-coordinate of the corner.
+    pub x: u32,
+    /// y-coordinate of the corner.
+    pub y: u32,
+    /// Characteristic scale of the corner, i.e. the standard deviation of the
+    /// Gaussian used to integrate gradients around it.
+    pub scale: f32,
+    /// Scale-normalised Harris response of the corner.
+    pub score: f32,
+}
+
+impl Position for ScaledCorner {
+    /// x-coordinate of the corner.
+    fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// y-coordinate of the corner.
+    fn y(&self) -> u32 {
+        self.y
+    }
+}
+
+impl Score for ScaledCorner {
+    fn score(&self) -> f32 {
+        self.score
+    }
+}
+
+/// Options for [`corners_harris_laplace`](fn.corners_harris_laplace.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HarrisLaplaceOptions {
+    /// Smallest integration scale to search.
+    pub initial_scale: f32,
+    /// Ratio between consecutive scales. Must be greater than 1.
+    pub scale_factor: f32,
+    /// Number of scales to search. Must be at least 3, as the first and last
+    /// scales are only used for comparison.
+    pub num_scales: usize,
+    /// Sensitivity parameter of the Harris response.
+    /// See [`StructureTensor::harris_response`](../structure_tensor/struct.StructureTensor.html#method.harris_response).
+    pub sensitivity: f32,
+    /// Minimum scale-normalised Harris response for a corner to be detected.
+    /// Responses are computed with intensities scaled to the range [0, 1].
+    pub threshold: f32,
+}
+
+impl Default for HarrisLaplaceOptions {
+    fn default() -> Self {
+        HarrisLaplaceOptions {
+            initial_scale: 1.5,
+            scale_factor: 1.4,
+            num_scales: 8,
+            sensitivity: 0.04,
+            threshold: 1e-4,
+        }
+    }
+}
+
+/// Finds scale-covariant corners using the
+/// [Harris-Laplace](https://doi.org/10.1023/B:VISI.0000027790.02288.f2) detector.
+///
+/// At each scale s the image is smoothed with a Gaussian of standard deviation 0.7 * s
+/// and a Harris response computed from its gradients integrated over a Gaussian window
+/// of standard deviation s. Gradients are scale-normalised so that responses at different
+/// scales are comparable. A pixel is a candidate corner at scale s if its response exceeds
+/// `options.threshold` and those of its eight neighbours. A candidate is reported if its
+/// scale-normalised Laplacian of Gaussian magnitude is greater at s than at the adjacent
+/// scales, so that each corner is reported at its characteristic scale. A point may be
+/// reported at more than one scale.
+///
+/// Returns `ImageProcError::InvalidOptions` if `initial_scale` is not positive,
+/// `scale_factor` is not greater than 1 or `num_scales` is less than 3.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::corners::{corners_harris_laplace, HarrisLaplaceOptions};
+/// use imageproc::drawing::draw_filled_rect_mut;
+/// use imageproc::rect::Rect;
+///
+/// let mut image = GrayImage::new(61, 61);
+/// draw_filled_rect_mut(&mut image, Rect::at(20, 20).of_size(21, 21), Luma([255]));
+///
+/// let corners = corners_harris_laplace(&image, HarrisLaplaceOptions::default()).unwrap();
+///
+/// // At a large enough scale the whole square looks like a corner,
+/// // giving the strongest response at its centre.
+/// let strongest = corners.iter().max_by(|a, b| a.score.partial_cmp(&b.score).unwrap()).unwrap();
+/// assert_eq!((strongest.x, strongest.y), (30, 30));
+/// assert!(strongest.scale > 5.0);
+/// # }
+/// ```
+pub fn corners_harris_laplace(image: &GrayImage, options: HarrisLaplaceOptions) -> Result<Vec<ScaledCorner>> {
+    let mut errors = vec![];
+    if options.initial_scale <= 0.0 || options.initial_scale.is_nan() {
+        errors.push("initial_scale must be positive");
+    }
+    if options.scale_factor <= 1.0 || options.scale_factor.is_nan() {
+        errors.push("scale_factor must be greater than 1");
+    }
+    if options.num_scales < 3 {
+        errors.push("num_scales must be at least 3");
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return Ok(vec![]);
+    }
+
+    let input: Image<Luma<f32>> = map_subpixels(image, |p| p as f32 / 255.0);
+    let scales: Vec<f32> = (0..options.num_scales)
+        .map(|n| options.initial_scale * options.scale_factor.powi(n as i32))
+        .collect();
+    let laplacians: Vec<Image<Luma<f32>>> = scales.iter().map(|&s| scale_normalised_laplacian(&input, s)).collect();
+
+    let mut corners = vec![];
+    for n in 1..options.num_scales - 1 {
+        let scale = scales[n];
+        let differentiation_scale = 0.7 * scale;
+        // Sobel responses are 8 times the derivative, and each tensor entry is a product of two.
+        let normalisation = (differentiation_scale * differentiation_scale / 64.0).powi(2);
+        let harris = structure_tensor(&gaussian_blur_f32(&input, differentiation_scale), scale)
+            .map(|t| normalisation * t.harris_response(options.sensitivity));
+
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let score = harris.get_pixel(x, y)[0];
+                if score <= options.threshold || !is_spatial_maximum(&harris, x, y, score) {
+                    continue;
+                }
+                let laplacian = laplacians[n].get_pixel(x, y)[0];
+                if laplacian > laplacians[n - 1].get_pixel(x, y)[0] && laplacian > laplacians[n + 1].get_pixel(x, y)[0] {
+                    corners.push(ScaledCorner { x, y, scale, score });
+                }
+            }
+        }
+    }
+
+    Ok(corners)
+}
+
+/// Returns true if `value` is strictly greater than the responses of the
+/// eight neighbours of (x, y). Requires (x, y) not to be on the image boundary.
+fn is_spatial_maximum(responses: &Image<Luma<f32>>, x: u32, y: u32, value: f32) -> bool {
+    for ny in y - 1..y + 2 {
+        for nx in x - 1..x + 2 {
+            if (nx, ny) != (x, y) && responses.get_pixel(nx, ny)[0] >= value {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Magnitude of the Laplacian of Gaussian with standard deviation `scale`,
+/// multiplied by `scale^2`.
+fn scale_normalised_laplacian(image: &Image<Luma<f32>>, scale: f32) -> Image<Luma<f32>> {
+    let laplacian_kernel = [0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0];
+    let mut laplacian: Image<Luma<f32>> = filter3x3(&gaussian_blur_f32(image, scale), &laplacian_kernel);
+    for p in laplacian.iter_mut() {
+        *p = scale * scale * p.abs();
+    }
+    laplacian
+}
+
+/// The cornerness measure computed from the [structure tensor](../structure_tensor/index.html)
+/// at each pixel by [`corner_response`](fn.corner_response.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CornerResponse {
+    /// The smaller eigenvalue of the structure tensor, as used by the
+    /// [Shi-Tomasi](https://doi.org/10.1109/CVPR.1994.323794) detector.
+    MinEigenvalue,
+    /// The [Harris response](../structure_tensor/struct.StructureTensor.html#method.harris_response)
+    /// with the given sensitivity.
+    Harris {
+        /// Weight of the squared trace subtracted from the determinant. Typically 0.04 to 0.06.
+        sensitivity: f32,
+    },
+}
+
+/// Computes a corner response at each pixel from Sobel gradients whose products are
+/// averaged by a Gaussian with standard deviation `sigma`. Responses are computed with
+/// intensities scaled to the range [0, 1].
+///
+/// # Panics
+/// If `sigma` is negative.
+pub fn corner_response(image: &GrayImage, sigma: f32, response: CornerResponse) -> Image<Luma<f32>> {
+    let input: Image<Luma<f32>> = map_subpixels(image, |p| p as f32 / 255.0);
+    let tensors = structure_tensor(&input, sigma);
+    match response {
+        CornerResponse::MinEigenvalue => tensors.map(|t| t.eigenvalues().1),
+        CornerResponse::Harris { sensitivity } => tensors.map(|t| t.harris_response(sensitivity)),
+    }
+}
+
+/// Options for [`good_features_to_track`](fn.good_features_to_track.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GoodFeaturesOptions {
+    /// Maximum number of corners to return.
+    pub max_corners: usize,
+    /// Corners with a response less than `quality_level` times the strongest response
+    /// in the image are rejected. Must be in the range [0, 1).
+    pub quality_level: f32,
+    /// Minimum Euclidean distance between returned corners. Must be non-negative.
+    pub min_distance: f32,
+    /// Standard deviation of the Gaussian used to average gradient products. Must be positive.
+    pub sigma: f32,
+    /// Cornerness measure to use.
+    pub response: CornerResponse,
+}
+
+impl Default for GoodFeaturesOptions {
+    fn default() -> Self {
+        GoodFeaturesOptions {
+            max_corners: 100,
+            quality_level: 0.01,
+            min_distance: 10.0,
+            sigma: 1.0,
+            response: CornerResponse::MinEigenvalue,
+        }
+    }
+}
+
+/// Finds the strongest corners in an image using the method of
+/// [Shi and Tomasi](https://doi.org/10.1109/CVPR.1994.323794).
+///
+/// Candidates are the pixels away from the image boundary whose
+/// [corner response](fn.corner_response.html) is positive, at least `options.quality_level`
+/// times the strongest response, and greater than the responses of their eight neighbours.
+/// Candidates are considered in decreasing order of response, and each is accepted if it
+/// is at least `options.min_distance` from every corner accepted so far, until
+/// `options.max_corners` corners have been found. Corners are returned in decreasing order
+/// of score, with ties resolved lexicographically by `(y, x)`.
+///
+/// Returns `ImageProcError::InvalidOptions` if `quality_level` is not in the range [0, 1),
+/// `min_distance` is negative or `sigma` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::corners::{good_features_to_track, GoodFeaturesOptions};
+/// use imageproc::drawing::draw_filled_rect_mut;
+/// use imageproc::rect::Rect;
+///
+/// let mut image = GrayImage::new(40, 40);
+/// draw_filled_rect_mut(&mut image, Rect::at(10, 10).of_size(20, 20), Luma([255]));
+///
+/// let options = GoodFeaturesOptions { max_corners: 10, ..GoodFeaturesOptions::default() };
+/// let corners = good_features_to_track(&image, options).unwrap();
+///
+/// // One corner is found near each corner of the square.
+/// assert_eq!(corners.len(), 4);
+/// for c in &corners {
+///     let near = |v: u32| (v as i32 - 10).abs() <= 1 || (v as i32 - 29).abs() <= 1;
+///     assert!(near(c.x) && near(c.y));
+/// }
+/// # }
+/// ```
+pub fn good_features_to_track(image: &GrayImage, options: GoodFeaturesOptions) -> Result<Vec<Corner>> {
+    let mut errors = vec![];
+    if !(options.quality_level >= 0.0 && options.quality_level < 1.0) {
+        errors.push("quality_level must be in the range [0, 1)");
+    }
+    if options.min_distance < 0.0 || options.min_distance.is_nan() {
+        errors.push("min_distance must be non-negative");
+    }
+    if options.sigma <= 0.0 || options.sigma.is_nan() {
+        errors.push("sigma must be positive");
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return Ok(vec![]);
+    }
+
+    let responses = corner_response(image, options.sigma, options.response);
+    let strongest = responses.iter().cloned().fold(0.0, f32::max);
+    let threshold = options.quality_level * strongest;
+
+    let mut candidates = vec![];
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let score = responses.get_pixel(x, y)[0];
+            if score > 0.0 && score >= threshold && is_spatial_maximum(&responses, x, y, score) {
+                candidates.push(Corner::new(x, y, score));
+            }
+        }
+    }
+    // Sorting is stable, so equal scores remain in (y, x) order.
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+    let min_distance_squared = options.min_distance * options.min_distance;
+    let mut corners: Vec<Corner> = vec![];
+    for candidate in candidates {
+        if corners.len() == options.max_corners {
+            break;
+        }
+        let is_isolated = corners.iter().all(|c| {
+            let dx = c.x as f32 - candidate.x as f32;
+            let dy = c.y as f32 - candidate.y as f32;
+            dx * dx + dy * dy >= min_distance_squared
+        });
+        if is_isolated {
+            corners.push(candidate);
+        }
+    }
+
+    Ok(corners)
+}
+
 /// Variants of the [FAST](https://en.wikipedia.org/wiki/Features_from_accelerated_segment_test)
 /// corner detector. These classify a point based on its intensity relative to the 16 pixels
 /// in the Bresenham circle of radius 3 around it. A point P with intensity I is detected as a
@@ -53,6 +387,7 @@ impl Score for Corner {
 /// I - t, for some user-provided threshold t. The score of a corner is
 /// the greatest threshold for which the given pixel still qualifies as
 /// a corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Fast {
     /// Corners require a section of length as least nine.
     Nine,
@@ -61,7 +396,10 @@ pub enum Fast {
 }
 
 /// Finds corners using FAST-12 features. See comment on Fast enum.
-pub fn corners_fast12(image: &GrayImage, threshold: u8) -> Vec<Corner> {
+pub fn corners_fast12<I>(image: &I, threshold: u8) -> Vec<Corner>
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
     let (width, height) = image.dimensions();
     let mut corners = vec![];
 
@@ -78,7 +416,10 @@ pub fn corners_fast12(image: &GrayImage, threshold: u8) -> Vec<Corner> {
 }
 
 /// Finds corners using FAST-9 features. See comment on Fast enum.
-pub fn corners_fast9(image: &GrayImage, threshold: u8) -> Vec<Corner> {
+pub fn corners_fast9<I>(image: &I, threshold: u8) -> Vec<Corner>
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
     let (width, height) = image.dimensions();
     let mut corners = vec![];
 
@@ -94,6 +435,47 @@ pub fn corners_fast9(image: &GrayImage, threshold: u8) -> Vec<Corner> {
     corners
 }
 
+/// Finds corners using the given variant of FAST. See comment on Fast enum.
+///
+/// If `suppression_radius` is `Some(r)` then only corners with the highest score in the
+/// (2 * r + 1) square block centred on them are returned, as clusters of adjacent pixels
+/// are usually detected around each true corner. Ties are resolved lexicographically.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::corners::{corners_fast, Fast};
+///
+/// // A bright square on a dark background.
+/// let image = GrayImage::from_fn(20, 20, |x, y| {
+///     if x >= 5 && x < 15 && y >= 5 && y < 15 { Luma([255]) } else { Luma([0]) }
+/// });
+///
+/// let all = corners_fast(&image, 50, Fast::Nine, None);
+/// let suppressed = corners_fast(&image, 50, Fast::Nine, Some(3));
+///
+/// // Each of the four corners of the square is detected exactly once.
+/// assert!(all.len() > 4);
+/// assert_eq!(suppressed.len(), 4);
+/// # }
+/// ```
+pub fn corners_fast<I>(image: &I, threshold: u8, variant: Fast, suppression_radius: Option<u32>) -> Vec<Corner>
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
+    let corners = match variant {
+        Fast::Nine => corners_fast9(image, threshold),
+        Fast::Twelve => corners_fast12(image, threshold),
+    };
+    match suppression_radius {
+        Some(radius) => local_maxima(&corners, radius),
+        None => corners,
+    }
+}
+
 /// The score of a corner detected using the FAST
 /// detector is the largest threshold for which this
 /// pixel is still a corner. We input the threshold at which
@@ -101,7 +483,10 @@ pub fn corners_fast9(image: &GrayImage, threshold: u8) -> Vec<Corner> {
 /// Note that the corner check uses a strict inequality, so if
 /// the smallest intensity difference between the center pixel
 /// and a corner pixel is n then the corner will have a score of n - 1.
-pub fn fast_corner_score(image: &GrayImage, threshold: u8, x: u32, y: u32, variant: Fast) -> u8 {
+pub fn fast_corner_score<I>(image: &I, threshold: u8, x: u32, y: u32, variant: Fast) -> u8
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
     let mut max = 255u8;
     let mut min = threshold;
 
@@ -139,7 +524,10 @@ pub fn fast_corner_score(image: &GrayImage, threshold: u8, x: u32, y: u32, varia
 /// Checks if the given pixel is a corner according to the FAST9 detector.
 /// The current implementation is extremely inefficient.
 // TODO: Make this much faster!
-fn is_corner_fast9(image: &GrayImage, threshold: u8, x: u32, y: u32) -> bool {
+fn is_corner_fast9<I>(image: &I, threshold: u8, x: u32, y: u32) -> bool
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
     let (width, height) = image.dimensions();
     if x < 3 || y < 3 || x >= width - 3 || y >= height - 3 {
         return false;
@@ -179,7 +567,10 @@ fn is_corner_fast9(image: &GrayImage, threshold: u8, x: u32, y: u32) -> bool {
 }
 
 /// Checks if the given pixel is a corner according to the FAST12 detector.
-fn is_corner_fast12(image: &GrayImage, threshold: u8, x: u32, y: u32) -> bool {
+fn is_corner_fast12<I>(image: &I, threshold: u8, x: u32, y: u32) -> bool
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
     let (width, height) = image.dimensions();
 
     if x < 3 || y < 3 || x >= width - 3 || y >= height - 3 {
@@ -228,15 +619,18 @@ fn is_corner_fast12(image: &GrayImage, threshold: u8, x: u32, y: u32) -> bool {
 }
 
 #[inline]
-unsafe fn get_circle(
-    image: &GrayImage,
+unsafe fn get_circle<I>(
+    image: &I,
     x: u32,
     y: u32,
     p0: i16,
     p4: i16,
     p8: i16,
     p12: i16,
-) -> [i16; 16] {
+) -> [i16; 16]
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
     [
         p0,
         image.unsafe_get_pixel(x + 1, y - 3)[0] as i16,
@@ -302,7 +696,10 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
-    use test::Bencher;
+    use drawing::draw_filled_rect_mut;
+    use rect::Rect;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
 
     #[test]
     fn test_is_corner_fast12_12_contiguous_darker_pixels() {
@@ -480,4 +877,154 @@ mod test {
         let score = fast_corner_score(&image, 9, 3, 3, Fast::Nine);
         assert_eq!(score, 9);
     }
+
+    #[test]
+    fn test_corners_fast9_of_view() {
+        let image = gray_image!(
+            10, 10, 10, 10, 10, 10, 10, 10;
+            10, 10, 10, 00, 00, 00, 10, 10;
+            10, 10, 00, 10, 10, 10, 00, 10;
+            10, 00, 10, 10, 10, 10, 10, 00;
+            10, 00, 10, 10, 20, 10, 10, 00;
+            10, 00, 10, 10, 10, 10, 10, 00;
+            10, 10, 10, 10, 10, 10, 10, 10;
+            10, 10, 10, 10, 10, 10, 10, 10);
+
+        // Corner locations are relative to the view.
+        let view = image.view(1, 1, 7, 7);
+        assert_eq!(corners_fast9(&view, 5), vec![Corner::new(3, 3, 19.0)]);
+        assert_eq!(corners_fast9(&view, 5), corners_fast9(&view.to_image(), 5));
+    }
+
+    #[test]
+    fn test_corners_fast_suppression() {
+        let image = square_image(6, 12);
+        assert_eq!(corners_fast(&image, 30, Fast::Twelve, None), corners_fast12(&image, 30));
+
+        let all = corners_fast(&image, 30, Fast::Nine, None);
+        assert_eq!(all, corners_fast9(&image, 30));
+
+        let suppressed = corners_fast(&image, 30, Fast::Nine, Some(2));
+        assert!(!suppressed.is_empty() && suppressed.len() < all.len());
+        for c in &suppressed {
+            assert!(all.contains(c));
+            for d in &all {
+                let near = (c.x as i32 - d.x as i32).abs() <= 2 && (c.y as i32 - d.y as i32).abs() <= 2;
+                assert!(!near || d.score <= c.score, "{:?} is suppressed by {:?}", c, d);
+            }
+        }
+    }
+
+    #[bench]
+    fn bench_corners_fast9_suppressed(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| black_box(corners_fast(&image, 2, Fast::Nine, Some(3))));
+    }
+
+    #[test]
+    fn test_corner_response() {
+        let image = square_image(10, 20);
+        let min_eigenvalue = corner_response(&image, 1.0, CornerResponse::MinEigenvalue);
+        let harris = corner_response(&image, 1.0, CornerResponse::Harris { sensitivity: 0.04 });
+        // Corners respond more strongly than edges, which respond no more than flat regions.
+        assert!(min_eigenvalue.get_pixel(10, 10)[0] > 10.0 * min_eigenvalue.get_pixel(20, 10)[0].abs());
+        assert!(harris.get_pixel(10, 10)[0] > 0.0);
+        assert!(harris.get_pixel(20, 10)[0] < 0.0);
+        assert_eq!(min_eigenvalue.get_pixel(20, 20)[0], 0.0);
+    }
+
+    #[test]
+    fn test_good_features_to_track_min_distance_and_max_corners() {
+        let image = square_image(10, 6);
+        let options = GoodFeaturesOptions { min_distance: 0.0, ..GoodFeaturesOptions::default() };
+        let all = good_features_to_track(&image, options).unwrap();
+        assert!(all.windows(2).all(|w| w[0].score >= w[1].score));
+
+        // The corners of the square are 5 pixels apart, so are all kept at this distance.
+        let options = GoodFeaturesOptions { min_distance: 4.0, ..GoodFeaturesOptions::default() };
+        let spaced = good_features_to_track(&image, options).unwrap();
+        assert_eq!(spaced.len(), 4);
+        for (i, c) in spaced.iter().enumerate() {
+            assert!(all.contains(c));
+            for d in &spaced[..i] {
+                let distance = ((c.x as f32 - d.x as f32).powi(2) + (c.y as f32 - d.y as f32).powi(2)).sqrt();
+                assert!(distance >= 4.0);
+            }
+        }
+
+        let options = GoodFeaturesOptions { max_corners: 2, min_distance: 4.0, ..GoodFeaturesOptions::default() };
+        assert_eq!(good_features_to_track(&image, options).unwrap(), spaced[..2].to_vec());
+
+        let options = GoodFeaturesOptions { min_distance: 20.0, ..GoodFeaturesOptions::default() };
+        assert_eq!(good_features_to_track(&image, options).unwrap(), spaced[..1].to_vec());
+    }
+
+    #[test]
+    fn test_good_features_to_track_flat_image_and_invalid_options() {
+        let options = GoodFeaturesOptions::default();
+        assert_eq!(good_features_to_track(&GrayImage::new(10, 10), options), Ok(vec![]));
+        assert_eq!(good_features_to_track(&GrayImage::new(2, 10), options), Ok(vec![]));
+
+        let options = GoodFeaturesOptions { quality_level: 1.0, min_distance: -1.0, sigma: 0.0, ..options };
+        assert_eq!(
+            good_features_to_track(&GrayImage::new(10, 10), options),
+            Err(ImageProcError::InvalidOptions(
+                "quality_level must be in the range [0, 1), min_distance must be non-negative, \
+                 sigma must be positive".to_owned()
+            ))
+        );
+    }
+
+    #[bench]
+    fn bench_good_features_to_track(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| black_box(good_features_to_track(&image, GoodFeaturesOptions::default()).unwrap()));
+    }
+
+    fn square_image(offset: u32, side: u32) -> GrayImage {
+        let mut image = GrayImage::new(2 * offset + side, 2 * offset + side);
+        draw_filled_rect_mut(&mut image, Rect::at(offset as i32, offset as i32).of_size(side, side), Luma([255]));
+        image
+    }
+
+    fn strongest(corners: &[ScaledCorner]) -> ScaledCorner {
+        *corners.iter().max_by(|a, b| a.score.partial_cmp(&b.score).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_corners_harris_laplace_scale_covariance() {
+        let options = HarrisLaplaceOptions { num_scales: 12, ..HarrisLaplaceOptions::default() };
+        // Squares with sides of odd length, so that each has a unique centre pixel.
+        let small = strongest(&corners_harris_laplace(&square_image(15, 15), options).unwrap());
+        let large = strongest(&corners_harris_laplace(&square_image(30, 31), options).unwrap());
+        assert_eq!((small.x, small.y), (22, 22));
+        assert_eq!((large.x, large.y), (45, 45));
+        // Doubling the size of a feature doubles its scale, i.e. two steps of 1.4.
+        assert!((large.scale - small.scale * 1.4 * 1.4).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_corners_harris_laplace_flat_or_small_image() {
+        let options = HarrisLaplaceOptions::default();
+        assert!(corners_harris_laplace(&GrayImage::from_pixel(30, 30, Luma([50])), options).unwrap().is_empty());
+        assert!(corners_harris_laplace(&GrayImage::new(2, 2), options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corners_harris_laplace_invalid_options() {
+        let options = HarrisLaplaceOptions { initial_scale: 0.0, scale_factor: 1.0, num_scales: 2, ..HarrisLaplaceOptions::default() };
+        assert_eq!(
+            corners_harris_laplace(&GrayImage::new(10, 10), options),
+            Err(ImageProcError::InvalidOptions(
+                "initial_scale must be positive, scale_factor must be greater than 1, \
+                 num_scales must be at least 3".to_owned()
+            ))
+        );
+    }
+
+    #[bench]
+    fn bench_corners_harris_laplace(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| black_box(corners_harris_laplace(&image, HarrisLaplaceOptions::default()).unwrap()));
+    }
 }
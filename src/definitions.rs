@@ -47,6 +47,11 @@ impl_black_white!(Rgb<u16>, Rgb([u16::MIN; 3]), Rgb([u16::MAX; 3]));
 impl_black_white!(Rgba<u8>, Rgba([u8::MIN, u8::MIN, u8::MIN, u8::MAX]), Rgba([u8::MAX, u8::MAX, u8::MAX, u8::MAX]));
 impl_black_white!(Rgba<u16>, Rgba([u16::MIN, u16::MIN, u16::MIN, u16::MAX]), Rgba([u16::MAX, u16::MAX, u16::MAX, u16::MAX]));
 
+// Floating point intensities are taken to lie between 0 and 1.
+impl_black_white!(Luma<f32>, Luma([0.0]), Luma([1.0]));
+impl_black_white!(Rgb<f32>, Rgb([0.0; 3]), Rgb([1.0; 3]));
+impl_black_white!(Rgba<f32>, Rgba([0.0, 0.0, 0.0, 1.0]), Rgba([1.0; 4]));
+
 /// Something with a 2d position.
 pub trait Position {
     /// x-coordinate.
@@ -61,6 +66,38 @@ pub trait Score {
     fn score(&self) -> f32;
 }
 
+/// `Send` if the `rayon` feature is enabled, and implemented by every type otherwise.
+///
+/// Functions which process rows in parallel use this in place of `Send`, so that
+/// their signatures only require thread safety when it is used.
+#[cfg(feature = "rayon")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "rayon")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// `Send` if the `rayon` feature is enabled, and implemented by every type otherwise.
+///
+/// Functions which process rows in parallel use this in place of `Send`, so that
+/// their signatures only require thread safety when it is used.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// `Sync` if the `rayon` feature is enabled, and implemented by every type otherwise.
+/// See [`MaybeSend`](trait.MaybeSend.html).
+#[cfg(feature = "rayon")]
+pub trait MaybeSync: Sync {}
+#[cfg(feature = "rayon")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// `Sync` if the `rayon` feature is enabled, and implemented by every type otherwise.
+/// See [`MaybeSend`](trait.MaybeSend.html).
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSync for T {}
+
 /// A type to which we can clamp a value of type T.
 /// Implementations are not required to handle `NaN`s gracefully.
 pub trait Clamp<T> {
@@ -97,6 +134,27 @@ implement_clamp!(i32, u16, u16::MIN, u16::MAX, u16::MIN as i32, u16::MAX as i32)
 implement_clamp!(i32, i16, i16::MIN, i16::MAX, i16::MIN as i32, i16::MAX as i32);
 implement_clamp!(u16, u8, u8::MIN, u8::MAX, u8::MIN as u16, u8::MAX as u16);
 
+/// Creates an implementation of Clamp<T> for type T, which leaves values unchanged.
+macro_rules! implement_identity_clamp {
+    ($t:ty) => (
+        impl Clamp<$t> for $t {
+            fn clamp(x: $t) -> $t {
+                x
+            }
+        }
+    )
+}
+
+implement_identity_clamp!(f32);
+implement_identity_clamp!(f64);
+implement_identity_clamp!(i32);
+
+impl Clamp<f64> for f32 {
+    fn clamp(x: f64) -> f32 {
+        x as f32
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Clamp;
@@ -124,4 +182,12 @@ mod test {
         let w: u16 = Clamp::clamp(-5f32);
         assert_eq!(w, 0u16);
     }
+
+    #[test]
+    fn test_clamp_identity() {
+        let t: f32 = Clamp::clamp(-1.5f32);
+        assert_eq!(t, -1.5f32);
+        let u: i32 = Clamp::clamp(-70000i32);
+        assert_eq!(u, -70000i32);
+    }
 }
@@ -0,0 +1,353 @@
+//! Functions for removing noise from images.
+//!
+//! See also [`median_filter`](../filter/fn.median_filter.html) for removing impulse noise and
+//! [`bilateral_filter`](../filter/fn.bilateral_filter.html) for edge preserving smoothing.
+
+use image::Pixel;
+use definitions::{Image, MaybeSync};
+use error::{ImageProcError, Result};
+use integral_image::{integral_image_as, integral_squared_image_as};
+use map::{ChannelMap, WithChannel};
+
+/// Applies an adaptive [Wiener filter](https://en.wikipedia.org/wiki/Wiener_filter) to an
+/// image. Each pixel is moved towards the mean `m` of the `(2 * radius + 1) * (2 * radius + 1)`
+/// window centred on it, by an amount depending on the variance `v` of the window and the
+/// variance of the noise `n`: the output is `m + max(v - n, 0) / max(v, n) * (p - m)`, where `p`
+/// is the input pixel. Flat regions are smoothed heavily, while edges and textures, whose
+/// windows vary much more than the noise, are largely preserved.
+///
+/// If `noise_variance` is `None` then the noise variance of each channel is estimated as the
+/// mean of the window variances for that channel. Windows are clipped to the image bounds.
+/// Image channels are handled independently.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::denoise::wiener_filter;
+///
+/// let image = gray_image!(
+///     10, 12, 10, 200, 200;
+///     12, 10, 12, 200, 200;
+///     10, 12, 10, 200, 200);
+///
+/// // Small variations are removed, but the edge is kept.
+/// let filtered = wiener_filter(&image, 1, Some(4.0));
+/// assert_eq!(filtered.get_pixel(0, 1)[0], 11);
+/// assert_eq!(filtered.get_pixel(4, 1)[0], 200);
+/// # }
+/// ```
+pub fn wiener_filter<P>(image: &Image<P>, radius: u32, noise_variance: Option<f32>) -> Image<P>
+where
+    P: Pixel<Subpixel = u8> + WithChannel<u64> + MaybeSync + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = image.clone();
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    let integral: Image<ChannelMap<P, u64>> = integral_image_as(image);
+    let integral_squared: Image<ChannelMap<P, u64>> = integral_squared_image_as(image);
+    let num_channels = P::channel_count() as usize;
+    let sums: &[u64] = &integral;
+    let squared_sums: &[u64] = &integral_squared;
+
+    // Mean and variance of each channel of the window around each pixel.
+    let mut stats = Vec::with_capacity(width as usize * height as usize * num_channels);
+    for y in 0..height {
+        let (top, bottom) = (y.saturating_sub(radius), (y + radius).min(height - 1) + 1);
+        for x in 0..width {
+            let (left, right) = (x.saturating_sub(radius), (x + radius).min(width - 1) + 1);
+            let count = ((right - left) * (bottom - top)) as f64;
+            let corner = |x: u32, y: u32| (y as usize * (width as usize + 1) + x as usize) * num_channels;
+            for c in 0..num_channels {
+                let rect_sum = |s: &[u64]| {
+                    (s[corner(right, bottom) + c] + s[corner(left, top) + c])
+                        - (s[corner(right, top) + c] + s[corner(left, bottom) + c])
+                };
+                let mean = rect_sum(sums) as f64 / count;
+                let variance = (rect_sum(squared_sums) as f64 / count - mean * mean).max(0.0);
+                stats.push((mean, variance));
+            }
+        }
+    }
+
+    let noise: Vec<f64> = match noise_variance {
+        Some(n) => vec![n as f64; num_channels],
+        None => {
+            let mut totals = vec![0.0; num_channels];
+            for (i, s) in stats.iter().enumerate() {
+                totals[i % num_channels] += s.1;
+            }
+            totals.iter().map(|t| t / (width as f64 * height as f64)).collect()
+        }
+    };
+
+    for (i, (p, &(mean, variance))) in out.iter_mut().zip(stats.iter()).enumerate() {
+        let noise = noise[i % num_channels];
+        let gain = if variance > noise { (variance - noise) / variance } else { 0.0 };
+        let value = mean + gain * (*p as f64 - mean);
+        *p = value.round().max(0.0).min(255.0) as u8;
+    }
+
+    out
+}
+
+/// Options for [`non_local_means`](fn.non_local_means.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NonLocalMeansOptions {
+    /// Filtering strength. Larger values remove more noise but also more detail. Values
+    /// around the standard deviation of the noise are a reasonable starting point.
+    pub strength: f32,
+    /// Radius of the square patches compared to determine the similarity of two pixels.
+    pub patch_radius: u32,
+    /// Radius of the square window around each pixel that is searched for similar patches.
+    pub search_radius: u32,
+}
+
+impl Default for NonLocalMeansOptions {
+    fn default() -> Self {
+        NonLocalMeansOptions {
+            strength: 10.0,
+            patch_radius: 3,
+            search_radius: 10,
+        }
+    }
+}
+
+impl NonLocalMeansOptions {
+    fn validate(&self) -> Result<()> {
+        if self.strength <= 0.0 || self.strength.is_nan() {
+            return Err(ImageProcError::InvalidOptions(format!(
+                "strength must be positive, got {}",
+                self.strength
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Denoises an image using the [non-local means](https://en.wikipedia.org/wiki/Non-local_means)
+/// algorithm of Buades, Coll and Morel.
+///
+/// Each output pixel is a weighted mean of the pixels within `options.search_radius` of it,
+/// with the weight of each pixel determined by the similarity of the patches of radius
+/// `options.patch_radius` centred on the two pixels. If `d` is the mean squared difference
+/// between the two patches, averaged over channels, the weight is `exp(-d / strength^2)`.
+/// The pixel itself is given the largest weight of any other pixel in its search window.
+/// As repeated structures anywhere in the search window contribute, this preserves edges
+/// and textures much better than local smoothing.
+///
+/// Patch distances for all pixels are computed from an integral image of squared differences
+/// for each offset in the search window, so the running time is proportional to the
+/// number of pixels multiplied by the area of the search window, and independent of the
+/// patch size. Patches are clipped to the image bounds, and pixels outside the image are
+/// padded by continuity when searching.
+///
+/// Returns `ImageProcError::InvalidOptions` if `options.strength` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::denoise::{non_local_means, NonLocalMeansOptions};
+/// use imageproc::noise::gaussian_noise;
+///
+/// let clean = GrayImage::from_fn(40, 40, |x, _| if x < 20 { Luma([60]) } else { Luma([180]) });
+/// let noisy = gaussian_noise(&clean, 0.0, 10.0, 1);
+///
+/// let denoised = non_local_means(&noisy, NonLocalMeansOptions::default()).unwrap();
+///
+/// let error = |image: &GrayImage| {
+///     image.pixels().zip(clean.pixels()).map(|(p, q)| (p[0] as f64 - q[0] as f64).powi(2)).sum::<f64>()
+/// };
+/// assert!(error(&denoised) < error(&noisy) / 4.0);
+/// # }
+/// ```
+pub fn non_local_means<P>(image: &Image<P>, options: NonLocalMeansOptions) -> Result<Image<P>>
+where
+    P: Pixel<Subpixel = u8> + 'static,
+{
+    options.validate()?;
+    let (width, height) = image.dimensions();
+    let mut out = image.clone();
+    if width == 0 || height == 0 {
+        return Ok(out);
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let num_channels = P::channel_count() as usize;
+    let pixels: &[u8] = image;
+    let search = options.search_radius as i64;
+    let patch = options.patch_radius as usize;
+    let scale = 1.0 / (options.strength as f64 * options.strength as f64 * num_channels as f64);
+
+    let mut sums = vec![0f64; w * h * num_channels];
+    let mut total_weights = vec![0f64; w * h];
+    let mut max_weights = vec![0f64; w * h];
+    // Integral image of the squared differences between the image and its translation
+    // by the current offset.
+    let mut integral = vec![0u64; (w + 1) * (h + 1)];
+    let clamp = |v: i64, len: usize| v.max(0).min(len as i64 - 1) as usize;
+
+    for dy in -search..search + 1 {
+        for dx in -search..search + 1 {
+            if (dx, dy) == (0, 0) {
+                continue;
+            }
+            // Source pixel index for the translation of each pixel.
+            let shifted = |x: usize, y: usize| clamp(y as i64 + dy, h) * w + clamp(x as i64 + dx, w);
+
+            for y in 0..h {
+                let mut row_sum = 0u64;
+                for x in 0..w {
+                    let (p, q) = ((y * w + x) * num_channels, shifted(x, y) * num_channels);
+                    for c in 0..num_channels {
+                        let diff = pixels[p + c] as i64 - pixels[q + c] as i64;
+                        row_sum += (diff * diff) as u64;
+                    }
+                    integral[(y + 1) * (w + 1) + x + 1] = integral[y * (w + 1) + x + 1] + row_sum;
+                }
+            }
+
+            for y in 0..h {
+                let (top, bottom) = (y.saturating_sub(patch), (y + patch).min(h - 1) + 1);
+                for x in 0..w {
+                    let (left, right) = (x.saturating_sub(patch), (x + patch).min(w - 1) + 1);
+                    let at = |x: usize, y: usize| integral[y * (w + 1) + x];
+                    let sum = (at(right, bottom) + at(left, top)) - (at(right, top) + at(left, bottom));
+                    let distance = sum as f64 / ((right - left) * (bottom - top)) as f64;
+                    let weight = (-distance * scale).exp();
+
+                    let i = y * w + x;
+                    let q = shifted(x, y) * num_channels;
+                    for c in 0..num_channels {
+                        sums[i * num_channels + c] += weight * pixels[q + c] as f64;
+                    }
+                    total_weights[i] += weight;
+                    if weight > max_weights[i] {
+                        max_weights[i] = weight;
+                    }
+                }
+            }
+        }
+    }
+
+    for (i, p) in out.iter_mut().enumerate() {
+        let pixel = i / num_channels;
+        // A search window containing only the pixel itself leaves it unchanged.
+        let own_weight = if options.search_radius == 0 { 1.0 } else { max_weights[pixel] };
+        let total = total_weights[pixel] + own_weight;
+        if total > 0.0 {
+            let value = (sums[i] + own_weight * pixels[i] as f64) / total;
+            *p = value.round().max(0.0).min(255.0) as u8;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+    use noise::gaussian_noise;
+    use utils::{gray_bench_image, rgb_bench_image};
+    use test::{Bencher, black_box};
+
+    fn squared_error<P: Pixel<Subpixel = u8> + 'static>(a: &Image<P>, b: &Image<P>) -> f64 {
+        a.iter().zip(b.iter()).map(|(p, q)| (*p as f64 - *q as f64).powi(2)).sum()
+    }
+
+    // Two flat regions separated by a vertical edge.
+    fn step_image(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, _| if x < width / 2 { Luma([70]) } else { Luma([170]) })
+    }
+
+    #[test]
+    fn test_wiener_filter_constant_image() {
+        let image = GrayImage::from_pixel(10, 8, Luma([42]));
+        assert_pixels_eq!(wiener_filter(&image, 2, None), image);
+    }
+
+    #[test]
+    fn test_wiener_filter_zero_noise_is_identity() {
+        let image = gray_bench_image(20, 15);
+        assert_pixels_eq!(wiener_filter(&image, 2, Some(0.0)), image);
+    }
+
+    #[test]
+    fn test_wiener_filter_reduces_noise() {
+        let clean = step_image(40, 30);
+        let noisy = gaussian_noise(&clean, 0.0, 8.0, 3);
+        let estimated = wiener_filter(&noisy, 2, None);
+        let known = wiener_filter(&noisy, 2, Some(64.0));
+        assert!(squared_error(&estimated, &clean) < squared_error(&noisy, &clean) / 2.0);
+        assert!(squared_error(&known, &clean) < squared_error(&noisy, &clean) / 2.0);
+        // The edge is preserved.
+        assert!(known.get_pixel(18, 15)[0] < 100 && known.get_pixel(21, 15)[0] > 140);
+    }
+
+    #[test]
+    fn test_wiener_filter_rgb_channels_independent() {
+        let image = RgbImage::from_fn(6, 6, |x, _| Rgb([x as u8 * 10, 50, if x < 3 { 0 } else { 200 }]));
+        let filtered = wiener_filter(&image, 1, None);
+        assert!(filtered.pixels().all(|p| p[1] == 50));
+        assert_eq!(wiener_filter(&RgbImage::new(0, 3), 1, None).dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn test_non_local_means_constant_image() {
+        let image = GrayImage::from_pixel(12, 9, Luma([99]));
+        let denoised = non_local_means(&image, NonLocalMeansOptions { search_radius: 3, ..Default::default() }).unwrap();
+        assert_pixels_eq!(denoised, image);
+    }
+
+    #[test]
+    fn test_non_local_means_reduces_noise_and_keeps_edges() {
+        let clean = step_image(40, 30);
+        let noisy = gaussian_noise(&clean, 0.0, 10.0, 7);
+        let options = NonLocalMeansOptions { strength: 10.0, patch_radius: 2, search_radius: 5 };
+        let denoised = non_local_means(&noisy, options).unwrap();
+        assert!(squared_error(&denoised, &clean) < squared_error(&noisy, &clean) / 4.0);
+        for y in 0..30 {
+            assert!(denoised.get_pixel(19, y)[0] < 100 && denoised.get_pixel(20, y)[0] > 140);
+        }
+    }
+
+    #[test]
+    fn test_non_local_means_zero_search_radius_is_identity() {
+        let image = rgb_bench_image(8, 8);
+        let options = NonLocalMeansOptions { search_radius: 0, ..Default::default() };
+        assert_pixels_eq!(non_local_means(&image, options).unwrap(), image);
+    }
+
+    #[test]
+    fn test_non_local_means_invalid_options() {
+        let image = gray_bench_image(5, 5);
+        let options = NonLocalMeansOptions { strength: 0.0, ..Default::default() };
+        assert!(non_local_means(&image, options).is_err());
+        assert_eq!(
+            non_local_means(&GrayImage::new(0, 0), NonLocalMeansOptions::default()).unwrap().dimensions(),
+            (0, 0)
+        );
+    }
+
+    #[bench]
+    fn bench_wiener_filter(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| black_box(wiener_filter(&image, 3, None)));
+    }
+
+    #[bench]
+    fn bench_non_local_means(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        let options = NonLocalMeansOptions { strength: 10.0, patch_radius: 3, search_radius: 5 };
+        b.iter(|| black_box(non_local_means(&image, options).unwrap()));
+    }
+}
@@ -0,0 +1,350 @@
+//! Dense extraction of descriptors on regular grids of patches, at multiple scales.
+//!
+//! Unlike keypoint-based descriptors, dense descriptors are computed for every patch
+//! of a regular grid covering the image. This is the usual input to bag-of-words
+//! models and texture classifiers.
+
+use image::{FilterType, GenericImageView, GrayImage};
+use image::imageops::resize;
+use error::{ImageProcError, Result};
+use hog::{hog_blocks, HogOptions};
+use local_binary_patterns::{local_binary_pattern, PatternMapping};
+use multiarray::Array3d;
+use rect::RectF;
+
+/// The descriptor computed for each patch by [`dense_descriptors`](fn.dense_descriptors.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DenseDescriptor {
+    /// The normalised [`HoG`](../hog/index.html) block descriptors. Each patch is a block,
+    /// so patches have side `block_side * cell_side` and are `block_stride * cell_side`
+    /// pixels apart. Any pixels on the right or bottom of an image that can't be covered by a
+    /// whole number of blocks are ignored.
+    Hog(HogOptions),
+    /// Histograms of [local binary patterns](../local_binary_patterns/index.html),
+    /// normalised to sum to 1. Pixels on the image boundary don't have a local binary
+    /// pattern so aren't counted.
+    Lbp {
+        /// Width and height of each patch in pixels.
+        patch_side: u32,
+        /// Distance in pixels between the top left corners of adjacent patches.
+        stride: u32,
+        /// If true, patterns are grouped into 10 bins by
+        /// [`UNIFORM_REPRESENTATIVE_2`](../local_binary_patterns/static.UNIFORM_REPRESENTATIVE_2.html),
+        /// making the histograms invariant to rotation. Otherwise there is a bin for
+        /// each of the 256 patterns.
+        uniform: bool,
+    },
+}
+
+/// Options for [`dense_descriptors`](fn.dense_descriptors.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DenseOptions {
+    /// Descriptor computed for each patch.
+    pub descriptor: DenseDescriptor,
+    /// Number of scales at which descriptors are computed. Must be at least 1.
+    pub num_scales: u32,
+    /// Ratio between the sizes of the images used at successive scales. Must be
+    /// greater than 0 and less than 1 if `num_scales` is greater than 1.
+    pub scale_factor: f32,
+}
+
+/// The descriptors computed at a single scale by [`dense_descriptors`](fn.dense_descriptors.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureMap {
+    /// Factor by which the input image was scaled before computing descriptors.
+    pub scale: f32,
+    /// Width and height of each patch in the scaled image.
+    pub patch_side: u32,
+    /// Distance between adjacent patches in the scaled image.
+    pub stride: u32,
+    /// The descriptors. The innermost dimension is position within a descriptor, then
+    /// the horizontal location of the patch in the grid, then its vertical location.
+    pub descriptors: Array3d<f32>,
+}
+
+impl FeatureMap {
+    /// The number of patches in each row and column of the grid.
+    pub fn grid_dimensions(&self) -> (usize, usize) {
+        let lengths = self.descriptors.lengths();
+        (lengths[1], lengths[2])
+    }
+
+    /// Length of each descriptor.
+    pub fn descriptor_length(&self) -> usize {
+        self.descriptors.lengths()[0]
+    }
+
+    /// The descriptor for the patch in column `gx` and row `gy` of the grid.
+    pub fn descriptor(&self, gx: usize, gy: usize) -> &[f32] {
+        self.descriptors.inner_slice(gx, gy)
+    }
+
+    /// The region covered by the patch in column `gx` and row `gy` of the grid,
+    /// in the coordinates of the input image.
+    pub fn patch(&self, gx: usize, gy: usize) -> RectF {
+        let stride = self.stride as f32 / self.scale;
+        let side = self.patch_side as f32 / self.scale;
+        RectF::new(gx as f32 * stride, gy as f32 * stride, side, side)
+    }
+
+    /// Iterates over the patches in row-major order, yielding the region covered by each
+    /// patch in input image coordinates along with its descriptor.
+    pub fn patches<'a>(&'a self) -> impl Iterator<Item = (RectF, &'a [f32])> + 'a {
+        let (grid_width, grid_height) = self.grid_dimensions();
+        (0..grid_height).flat_map(move |gy| {
+            (0..grid_width).map(move |gx| (self.patch(gx, gy), self.descriptor(gx, gy)))
+        })
+    }
+}
+
+/// Computes descriptors for a regular grid of patches in `image` and in successively
+/// downscaled copies of it. Returns a feature map for each scale at which the image is
+/// large enough to contain at least one patch, starting with the input image at scale 1.
+///
+/// Returns `ImageProcError::InvalidOptions` if the options are invalid.
+///
+/// # Examples
+/// ```
+/// use imageproc::dense::{dense_descriptors, DenseDescriptor, DenseOptions};
+/// use imageproc::utils::gray_bench_image;
+///
+/// let image = gray_bench_image(64, 48);
+/// let options = DenseOptions {
+///     descriptor: DenseDescriptor::Lbp { patch_side: 16, stride: 8, uniform: true },
+///     num_scales: 2,
+///     scale_factor: 0.5,
+/// };
+///
+/// let maps = dense_descriptors(&image, options).unwrap();
+/// assert_eq!(maps.len(), 2);
+/// assert_eq!(maps[0].grid_dimensions(), (7, 5));
+/// assert_eq!(maps[0].descriptor_length(), 10);
+/// // The second scale is computed from a 32x24 image.
+/// assert_eq!(maps[1].grid_dimensions(), (3, 2));
+/// assert_eq!(maps[1].patch(1, 1).left(), 16.0);
+/// ```
+pub fn dense_descriptors(image: &GrayImage, options: DenseOptions) -> Result<Vec<FeatureMap>> {
+    validate_options(options)?;
+
+    let mut maps = Vec::new();
+    for level in 0..options.num_scales {
+        let scale = options.scale_factor.powi(level as i32);
+        let scaled;
+        let current = if level == 0 {
+            image
+        } else {
+            let width = (image.width() as f32 * scale).round() as u32;
+            let height = (image.height() as f32 * scale).round() as u32;
+            if width == 0 || height == 0 {
+                break;
+            }
+            scaled = resize(image, width, height, FilterType::Triangle);
+            &scaled
+        };
+
+        let map = match options.descriptor {
+            DenseDescriptor::Hog(hog_options) => dense_hog(current, hog_options, scale)?,
+            DenseDescriptor::Lbp { patch_side, stride, uniform } => {
+                dense_lbp(current, patch_side, stride, uniform, scale)
+            }
+        };
+        match map {
+            Some(map) => maps.push(map),
+            // Smaller scales won't fit any patches either.
+            None => break,
+        }
+    }
+    Ok(maps)
+}
+
+fn validate_options(options: DenseOptions) -> Result<()> {
+    let mut errors = vec![];
+    if options.num_scales == 0 {
+        errors.push("num_scales must be at least 1".to_owned());
+    }
+    if options.num_scales > 1 && !(options.scale_factor > 0.0 && options.scale_factor < 1.0) {
+        errors.push(format!("scale_factor must be in (0, 1), found {}", options.scale_factor));
+    }
+    match options.descriptor {
+        DenseDescriptor::Hog(h) => {
+            if h.orientations == 0 || h.cell_side == 0 || h.block_side == 0 || h.block_stride == 0 {
+                errors.push("HoG orientations, cell_side, block_side and block_stride must be non-zero".to_owned());
+            }
+        }
+        DenseDescriptor::Lbp { patch_side, stride, .. } => {
+            if patch_side == 0 || stride == 0 {
+                errors.push("patch_side and stride must be non-zero".to_owned());
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+    Ok(())
+}
+
+/// Number of patches of side `patch_side` spaced `stride` apart that fit in `length`.
+fn grid_length(length: u32, patch_side: u32, stride: u32) -> usize {
+    if length < patch_side {
+        0
+    } else {
+        ((length - patch_side) / stride + 1) as usize
+    }
+}
+
+fn dense_hog(image: &GrayImage, options: HogOptions, scale: f32) -> Result<Option<FeatureMap>> {
+    let cell_side = options.cell_side as u32;
+    let patch_side = options.block_side as u32 * cell_side;
+    let stride = options.block_stride as u32 * cell_side;
+
+    let blocks_wide = grid_length(image.width(), patch_side, stride);
+    let blocks_high = grid_length(image.height(), patch_side, stride);
+    if blocks_wide == 0 || blocks_high == 0 {
+        return Ok(None);
+    }
+
+    // Crop to the region covered by whole blocks, so that the HoG options are valid.
+    let width = patch_side + (blocks_wide as u32 - 1) * stride;
+    let height = patch_side + (blocks_high as u32 - 1) * stride;
+    let descriptors = if (width, height) == image.dimensions() {
+        hog_blocks(image, options)?
+    } else {
+        hog_blocks(&image.view(0, 0, width, height).to_image(), options)?
+    };
+
+    Ok(Some(FeatureMap { scale, patch_side, stride, descriptors }))
+}
+
+fn dense_lbp(image: &GrayImage, patch_side: u32, stride: u32, uniform: bool, scale: f32) -> Option<FeatureMap> {
+    let (width, height) = image.dimensions();
+    let grid_width = grid_length(width, patch_side, stride);
+    let grid_height = grid_length(height, patch_side, stride);
+    if grid_width == 0 || grid_height == 0 {
+        return None;
+    }
+
+    // Maps each pattern to its histogram bin.
+    let mapping = if uniform { PatternMapping::RotationInvariantUniform } else { PatternMapping::Basic };
+    let bins = mapping.bins();
+    let num_bins = mapping.num_bins();
+
+    // The bin for each pixel with a pattern, in row-major order.
+    let pixel_bins: Vec<Option<usize>> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| local_binary_pattern(image, x, y).map(|p| bins[p as usize]))
+        .collect();
+
+    let mut descriptors = Array3d::new([num_bins, grid_width, grid_height]);
+    {
+        let mut view = descriptors.view_mut();
+        for gy in 0..grid_height {
+            for gx in 0..grid_width {
+                let histogram = view.inner_slice_mut(gx, gy);
+                let (left, top) = (gx as u32 * stride, gy as u32 * stride);
+                let mut count = 0;
+                for y in top..top + patch_side {
+                    let row = &pixel_bins[(y * width) as usize..((y + 1) * width) as usize];
+                    for bin in row[left as usize..(left + patch_side) as usize].iter().flat_map(|b| b) {
+                        histogram[*bin] += 1.0;
+                        count += 1;
+                    }
+                }
+                if count > 0 {
+                    for h in histogram.iter_mut() {
+                        *h /= count as f32;
+                    }
+                }
+            }
+        }
+    }
+
+    Some(FeatureMap { scale, patch_side, stride, descriptors })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hog::hog;
+    use image::Luma;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn lbp_options(patch_side: u32, stride: u32, uniform: bool) -> DenseOptions {
+        DenseOptions {
+            descriptor: DenseDescriptor::Lbp { patch_side, stride, uniform },
+            num_scales: 1,
+            scale_factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_dense_hog_matches_hog_of_each_block() {
+        let image = gray_bench_image(30, 22);
+        let hog_options = HogOptions::new(9, false, 4, 2, 1);
+        let options = DenseOptions { descriptor: DenseDescriptor::Hog(hog_options), num_scales: 1, scale_factor: 1.0 };
+
+        let maps = dense_descriptors(&image, options).unwrap();
+        assert_eq!(maps.len(), 1);
+        let map = &maps[0];
+        // Cropped to 28x20, i.e. 7x5 cells.
+        assert_eq!(map.grid_dimensions(), (6, 4));
+        assert_eq!(map.patch(2, 1), RectF::new(8.0, 4.0, 8.0, 8.0));
+
+        let cropped = image.view(0, 0, 28, 20).to_image();
+        let expected = hog(&cropped, hog_options).unwrap();
+        assert_eq!(map.descriptors.data(), &expected[..]);
+    }
+
+    #[test]
+    fn test_dense_lbp_histograms() {
+        // A horizontal ramp. Every interior pixel is brighter than its three
+        // neighbours on the left, so has pattern 11100000.
+        let image = GrayImage::from_fn(6, 5, |x, _| Luma([10 * x as u8]));
+        let maps = dense_descriptors(&image, lbp_options(4, 2, false)).unwrap();
+        let map = &maps[0];
+        assert_eq!(map.grid_dimensions(), (2, 1));
+        assert_eq!(map.descriptor_length(), 256);
+        for (_, descriptor) in map.patches() {
+            assert_eq!(descriptor[0b11100000], 1.0);
+            assert_eq!(descriptor.iter().sum::<f32>(), 1.0);
+        }
+
+        let uniform = dense_descriptors(&image, lbp_options(4, 2, true)).unwrap();
+        // 11100000 has least rotation 00000111, which is the fourth uniform representative.
+        assert_eq!(uniform[0].descriptor(1, 0)[3], 1.0);
+    }
+
+    #[test]
+    fn test_dense_descriptors_scales() {
+        let image = gray_bench_image(40, 40);
+        let options = DenseOptions { num_scales: 5, scale_factor: 0.5, ..lbp_options(8, 4, true) };
+        let maps = dense_descriptors(&image, options).unwrap();
+        // Scales 1, 0.5 and 0.25 fit at least one 8x8 patch, but 0.125 doesn't.
+        assert_eq!(maps.len(), 3);
+        assert_eq!(maps[2].scale, 0.25);
+        assert_eq!(maps[2].grid_dimensions(), (1, 1));
+        assert_eq!(maps[2].patch(0, 0), RectF::new(0.0, 0.0, 32.0, 32.0));
+    }
+
+    #[test]
+    fn test_dense_descriptors_invalid_options() {
+        let image = gray_bench_image(10, 10);
+        let options = DenseOptions { num_scales: 2, scale_factor: 1.5, ..lbp_options(0, 1, true) };
+        assert_eq!(
+            dense_descriptors(&image, options),
+            Err(ImageProcError::InvalidOptions(
+                "scale_factor must be in (0, 1), found 1.5, patch_side and stride must be non-zero".to_owned()
+            ))
+        );
+    }
+
+    #[bench]
+    fn bench_dense_lbp(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        let options = DenseOptions { num_scales: 3, scale_factor: 0.5, ..lbp_options(16, 8, true) };
+        b.iter(|| {
+            let maps = dense_descriptors(&image, options).unwrap();
+            black_box(maps);
+        });
+    }
+}
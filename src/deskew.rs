@@ -0,0 +1,139 @@
+//! Functions for estimating and correcting the skew of scanned documents.
+//!
+//! The skew of a page of text is estimated from the projection profiles of its dark pixels:
+//! when the projection direction is parallel to the lines of text the profile alternates
+//! sharply between lines and the gaps between them, so has the greatest variance. Profiles
+//! are computed using the [Radon transform](../radon/index.html).
+
+use image::{GrayImage, Luma};
+use contrast::otsu_level;
+use geometric_transformations::{rotate_about_center, Interpolation};
+use radon::{dominant_angle, radon_transform};
+use std::f32;
+
+/// Estimates the angle in radians by which the lines of dark text in `image` are rotated
+/// clockwise from horizontal, searching angles in `[-max_angle, max_angle]` at multiples
+/// of `step`.
+///
+/// Pixels no brighter than the image's [Otsu level](../contrast/fn.otsu_level.html) are
+/// treated as text. Returns 0 for images of constant intensity.
+///
+/// # Panics
+/// If `step` is not positive or `max_angle` is negative.
+pub fn estimate_skew(image: &GrayImage, max_angle: f32, step: f32) -> f32 {
+    assert!(step > 0.0, "step must be positive");
+    assert!(max_angle >= 0.0, "max_angle must be non-negative");
+    if image.width() == 0 || image.height() == 0 {
+        return 0.0;
+    }
+
+    let level = otsu_level(image);
+    let mut ink = GrayImage::new(image.width(), image.height());
+    let mut count = 0;
+    for (i, p) in ink.iter_mut().zip(image.iter()) {
+        if *p <= level {
+            *i = 1;
+            count += 1;
+        }
+    }
+    if count == 0 || count == image.len() {
+        return 0.0;
+    }
+
+    // Lines of text at angle a are parallel to the lines of integration at angle a + pi / 2.
+    let n = (max_angle / step).floor() as i32;
+    let angles: Vec<f32> = (-n..n + 1).map(|k| f32::consts::FRAC_PI_2 + k as f32 * step).collect();
+    let sinogram = radon_transform(&ink, &angles);
+    dominant_angle(&sinogram, &angles).unwrap() - f32::consts::FRAC_PI_2
+}
+
+/// Estimates the skew of a scanned page of dark text on a light background, and returns
+/// the estimated angle in radians along with the image rotated to correct it.
+///
+/// Angles up to 15 degrees either side of horizontal are searched in steps of 0.1 degrees;
+/// see [`estimate_skew`](fn.estimate_skew.html) for details. Positive angles indicate
+/// lines of text that descend to the right. The corrected image is rotated about its
+/// centre using bilinear interpolation, and pixels that lie outside the original image
+/// are set to white.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::deskew::deskew;
+/// use imageproc::drawing::draw_line_segment_mut;
+///
+/// // Black lines on a white page, descending to the right by 3 degrees.
+/// let mut page = GrayImage::from_pixel(120, 120, Luma([255]));
+/// let slope = 3f32.to_radians().tan();
+/// for i in 0..8 {
+///     let y = 10.0 + 12.0 * i as f32;
+///     draw_line_segment_mut(&mut page, (10.0, y), (110.0, y + 100.0 * slope), Luma([0]));
+/// }
+///
+/// let (angle, corrected) = deskew(&page);
+/// assert!((angle.to_degrees() - 3.0).abs() < 0.5);
+/// assert_eq!(corrected.dimensions(), page.dimensions());
+/// # }
+/// ```
+pub fn deskew(image: &GrayImage) -> (f32, GrayImage) {
+    let angle = estimate_skew(image, 15f32.to_radians(), 0.1f32.to_radians());
+    let corrected = rotate_about_center(image, -angle, Interpolation::Bilinear, Luma([255]));
+    (angle, corrected)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use drawing::draw_line_segment_mut;
+    use test::{Bencher, black_box};
+
+    fn lined_page(size: u32, degrees: f32) -> GrayImage {
+        let mut page = GrayImage::from_pixel(size, size, Luma([255]));
+        let slope = degrees.to_radians().tan();
+        let margin = size as f32 / 10.0;
+        let length = size as f32 - 2.0 * margin;
+        let mut y = margin;
+        while y < size as f32 - margin {
+            let start = (margin, y - 0.5 * length * slope);
+            let end = (margin + length, y + 0.5 * length * slope);
+            draw_line_segment_mut(&mut page, start, end, Luma([0]));
+            draw_line_segment_mut(&mut page, (start.0, start.1 + 1.0), (end.0, end.1 + 1.0), Luma([0]));
+            y += 10.0;
+        }
+        page
+    }
+
+    #[test]
+    fn test_estimate_skew() {
+        for &degrees in &[-7.0, -2.5, 0.0, 1.0, 4.0, 12.0] {
+            let page = lined_page(150, degrees);
+            let estimate = estimate_skew(&page, 15f32.to_radians(), 0.25f32.to_radians());
+            assert!((estimate.to_degrees() - degrees).abs() < 0.6, "expected {}, got {}", degrees, estimate.to_degrees());
+        }
+    }
+
+    #[test]
+    fn test_deskew_levels_lines() {
+        let page = lined_page(150, -5.0);
+        let (angle, corrected) = deskew(&page);
+        assert!((angle.to_degrees() + 5.0).abs() < 0.6);
+        let residual = estimate_skew(&corrected, 15f32.to_radians(), 0.25f32.to_radians());
+        assert!(residual.to_degrees().abs() < 0.6);
+    }
+
+    #[test]
+    fn test_estimate_skew_constant_image() {
+        let page = GrayImage::from_pixel(20, 10, Luma([200]));
+        assert_eq!(estimate_skew(&page, 0.2, 0.01), 0.0);
+        assert_eq!(estimate_skew(&GrayImage::new(0, 0), 0.2, 0.01), 0.0);
+    }
+
+    #[bench]
+    fn bench_deskew(b: &mut Bencher) {
+        let page = lined_page(200, 3.0);
+        b.iter(|| black_box(deskew(&page)));
+    }
+}
@@ -0,0 +1,429 @@
+//! Functions for smoothing images by diffusion.
+//!
+//! [`anisotropic`](fn.anisotropic.html) implements Perona and Malik's anisotropic diffusion,
+//! which smooths within regions while preserving the edges between them, and
+//! [`coherence_enhancing`](fn.coherence_enhancing.html) smooths along the flow of oriented
+//! textures such as fingerprints to close gaps in them. See also
+//! [`bilateral_filter`](../filter/fn.bilateral_filter.html) and the [`denoise`](../denoise/index.html)
+//! module for other edge preserving filters.
+
+use image::{ImageBuffer, Luma, Pixel, Primitive};
+use conv::ValueInto;
+use definitions::{Clamp, Image, MaybeSync};
+use error::{ImageProcError, Result};
+use filter::gaussian_blur_f32;
+use math::cast;
+use structure_tensor::structure_tensor;
+
+/// The function used by [`anisotropic`](fn.anisotropic.html) to compute how strongly
+/// intensity flows between neighbouring pixels from the magnitude of the difference
+/// between them.
+///
+/// Both functions are 1 for equal pixels and fall towards 0 as the difference grows
+/// large relative to `kappa`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Conduction {
+    /// `exp(-(d / kappa)^2)`, which favours high contrast edges over low contrast ones.
+    Exponential,
+    /// `1 / (1 + (d / kappa)^2)`, which favours wide regions over smaller ones.
+    Quadratic,
+}
+
+impl Conduction {
+    fn coefficient(&self, difference: f32, kappa: f32) -> f32 {
+        let ratio = difference / kappa;
+        match *self {
+            Conduction::Exponential => (-ratio * ratio).exp(),
+            Conduction::Quadratic => 1.0 / (1.0 + ratio * ratio),
+        }
+    }
+}
+
+/// Smooths an image using the anisotropic diffusion of Perona and Malik, from "Scale-space
+/// and edge detection using anisotropic diffusion", 1990.
+///
+/// At each of `iterations` steps every pixel `p` is updated to
+/// `p + lambda * sum(c(q - p) * (q - p))`, where the sum is over the four neighbours `q` of
+/// `p` and `c` is given by `conduction` with parameter `kappa`. Differences much smaller
+/// than `kappa` are smoothed, while differences much larger than it are treated as edges
+/// and preserved. No intensity flows across the image boundary.
+///
+/// Intermediate results are kept at full precision, and only the output is clamped to the
+/// range of the subpixel type. Image channels are handled independently.
+///
+/// Returns `ImageProcError::InvalidOptions` if `kappa` is not positive or `lambda` is not
+/// in `(0, 0.25]`, the range over which the update is stable.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::diffusion::{anisotropic, Conduction};
+///
+/// let image = gray_image!(
+///     10, 16, 10, 200, 194, 200;
+///     16, 10, 16, 194, 200, 194);
+///
+/// let smoothed = anisotropic(&image, 20, 10.0, 0.25, Conduction::Exponential).unwrap();
+///
+/// // The small variations either side of the edge are removed, but the edge remains.
+/// for y in 0..2 {
+///     for x in 0..3 {
+///         assert!((smoothed.get_pixel(x, y)[0] as i32 - 13).abs() <= 1);
+///         assert!((smoothed.get_pixel(x + 3, y)[0] as i32 - 197).abs() <= 1);
+///     }
+/// }
+/// # }
+/// ```
+pub fn anisotropic<P>(
+    image: &Image<P>,
+    iterations: u32,
+    kappa: f32,
+    lambda: f32,
+    conduction: Conduction,
+) -> Result<Image<P>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let mut errors = vec![];
+    if kappa <= 0.0 || kappa.is_nan() {
+        errors.push(format!("kappa must be positive, got {}", kappa));
+    }
+    if !(lambda > 0.0 && lambda <= 0.25) {
+        errors.push(format!("lambda must be in the range (0, 0.25], got {}", lambda));
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let num_channels = P::channel_count() as usize;
+    let mut current: Vec<f32> = image.iter().map(|c| cast(*c)).collect();
+    let mut next = current.clone();
+
+    for _ in 0..iterations {
+        for y in 0..h {
+            for x in 0..w {
+                let i = (y * w + x) * num_channels;
+                let mut neighbours = [None; 4];
+                if x > 0 {
+                    neighbours[0] = Some(i - num_channels);
+                }
+                if x + 1 < w {
+                    neighbours[1] = Some(i + num_channels);
+                }
+                if y > 0 {
+                    neighbours[2] = Some(i - w * num_channels);
+                }
+                if y + 1 < h {
+                    neighbours[3] = Some(i + w * num_channels);
+                }
+                for c in 0..num_channels {
+                    let value = current[i + c];
+                    let mut flux = 0.0;
+                    for n in neighbours.iter().filter_map(|n| *n) {
+                        let difference = current[n + c] - value;
+                        flux += conduction.coefficient(difference.abs(), kappa) * difference;
+                    }
+                    next[i + c] = value + lambda * flux;
+                }
+            }
+        }
+        ::std::mem::swap(&mut current, &mut next);
+    }
+
+    let mut out: Image<P> = ImageBuffer::new(width, height);
+    for (o, v) in out.iter_mut().zip(current) {
+        *o = P::Subpixel::clamp(v);
+    }
+    Ok(out)
+}
+
+/// Options for [`coherence_enhancing`](fn.coherence_enhancing.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CoherenceEnhancingOptions {
+    /// Number of diffusion steps.
+    pub iterations: u32,
+    /// Time step of each iteration. Must be in `(0, 0.25]`.
+    pub step: f32,
+    /// Standard deviation of the Gaussian applied to the image before computing gradients,
+    /// so that the flow is not disturbed by noise. Must not be negative.
+    pub noise_scale: f32,
+    /// Standard deviation of the Gaussian used to average gradient products in the
+    /// [`structure_tensor`](../structure_tensor/fn.structure_tensor.html), i.e. the size
+    /// of the neighbourhood over which the flow direction is estimated. Must not be negative.
+    pub integration_scale: f32,
+    /// Smallest rate of diffusion in any direction, which keeps the diffusion well posed.
+    /// Must be in `[0, 1]`.
+    pub alpha: f32,
+}
+
+impl Default for CoherenceEnhancingOptions {
+    fn default() -> Self {
+        CoherenceEnhancingOptions {
+            iterations: 20,
+            step: 0.2,
+            noise_scale: 1.0,
+            integration_scale: 4.0,
+            alpha: 0.01,
+        }
+    }
+}
+
+impl CoherenceEnhancingOptions {
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if !(self.step > 0.0 && self.step <= 0.25) {
+            errors.push(format!("step must be in the range (0, 0.25], got {}", self.step));
+        }
+        if self.noise_scale < 0.0 || self.noise_scale.is_nan() {
+            errors.push(format!("noise_scale must not be negative, got {}", self.noise_scale));
+        }
+        if self.integration_scale < 0.0 || self.integration_scale.is_nan() {
+            errors.push(format!("integration_scale must not be negative, got {}", self.integration_scale));
+        }
+        if !(self.alpha >= 0.0 && self.alpha <= 1.0) {
+            errors.push(format!("alpha must be in the range [0, 1], got {}", self.alpha));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ImageProcError::InvalidOptions(errors.join(", ")))
+        }
+    }
+}
+
+/// Smooths an image along the flow of its oriented structures using a variant of Weickert's
+/// coherence-enhancing diffusion, from "Coherence-enhancing diffusion filtering", 1999.
+///
+/// At each iteration the [`structure_tensor`](../structure_tensor/fn.structure_tensor.html)
+/// of the current image gives the local gradient direction and its
+/// [`coherence`](../structure_tensor/struct.StructureTensor.html#method.coherence) `c`.
+/// The image then diffuses at rate `alpha` across the flow and at rate
+/// `alpha + (1 - alpha) * c` along it, so that strongly oriented textures are smoothed
+/// along their lines and gaps in the lines are closed, while edges are kept. No intensity
+/// flows across the image boundary, and only the output is clamped to the range of the
+/// subpixel type.
+///
+/// Returns `ImageProcError::InvalidOptions` if any option is invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::diffusion::{coherence_enhancing, CoherenceEnhancingOptions};
+///
+/// // Vertical stripes, broken by a horizontal gap.
+/// let image = GrayImage::from_fn(30, 30, |x, y| {
+///     let on_stripe = x % 6 < 3;
+///     Luma([if on_stripe && (y < 13 || y > 16) { 40 } else { 200 }])
+/// });
+///
+/// let options = CoherenceEnhancingOptions { iterations: 60, ..Default::default() };
+/// let smoothed = coherence_enhancing(&image, options).unwrap();
+///
+/// // The gap in the stripes is partly filled, but the stripes are not blurred together.
+/// assert!(smoothed.get_pixel(13, 15)[0] < 150);
+/// assert!(smoothed.get_pixel(16, 5)[0] > 150);
+/// # }
+/// ```
+pub fn coherence_enhancing<T>(image: &Image<Luma<T>>, options: CoherenceEnhancingOptions) -> Result<Image<Luma<T>>>
+where
+    T: Primitive + ValueInto<f32> + Clamp<f32> + MaybeSync + 'static,
+{
+    options.validate()?;
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as i64, height as i64);
+    let mut current: Image<Luma<f32>> = ImageBuffer::from_fn(width, height, |x, y| Luma([cast(image.get_pixel(x, y)[0])]));
+    let mut next = current.clone();
+    // Neighbours outside the image take the value of the nearest pixel in it, so no
+    // intensity flows across the boundary.
+    let index = |x: i64, y: i64| (y.max(0).min(h - 1) * w + x.max(0).min(w - 1)) as usize;
+
+    for _ in 0..options.iterations {
+        let tensors = if options.noise_scale > 0.0 {
+            structure_tensor(&gaussian_blur_f32(&current, options.noise_scale), options.integration_scale)
+        } else {
+            structure_tensor(&current, options.integration_scale)
+        };
+
+        // Entries of the diffusion tensor [[a, b], [b, c]] at each pixel.
+        let mut a = Vec::with_capacity((width * height) as usize);
+        let mut b = Vec::with_capacity((width * height) as usize);
+        let mut c = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let tensor = tensors.get(x, y);
+                let across = options.alpha;
+                let along = options.alpha + (1.0 - options.alpha) * tensor.coherence();
+                let (sin, cos) = tensor.orientation().sin_cos();
+                a.push(across * cos * cos + along * sin * sin);
+                b.push((across - along) * cos * sin);
+                c.push(across * sin * sin + along * cos * cos);
+            }
+        }
+
+        {
+            let u: &[f32] = &current;
+            for y in 0..h {
+                for x in 0..w {
+                    let i = index(x, y);
+                    let (left, right, up, down) = (index(x - 1, y), index(x + 1, y), index(x, y - 1), index(x, y + 1));
+                    let diagonal = |dx: i64, dy: i64| u[index(x + dx, y + dy)];
+                    let flow = 0.5 * ((a[right] + a[i]) * (u[right] - u[i]) - (a[i] + a[left]) * (u[i] - u[left]))
+                        + 0.5 * ((c[down] + c[i]) * (u[down] - u[i]) - (c[i] + c[up]) * (u[i] - u[up]))
+                        + 0.25 * (b[right] * (diagonal(1, 1) - diagonal(1, -1)) - b[left] * (diagonal(-1, 1) - diagonal(-1, -1)))
+                        + 0.25 * (b[down] * (diagonal(1, 1) - diagonal(-1, 1)) - b[up] * (diagonal(1, -1) - diagonal(-1, -1)));
+                    next.put_pixel(x as u32, y as u32, Luma([u[i] + options.step * flow]));
+                }
+            }
+        }
+        ::std::mem::swap(&mut current, &mut next);
+    }
+
+    let mut out = ImageBuffer::new(width, height);
+    for (o, v) in out.iter_mut().zip(current.iter()) {
+        *o = T::clamp(*v);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma, RgbImage};
+    use noise::gaussian_noise;
+    use utils::{gray_bench_image, rgb_bench_image};
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_anisotropic_single_step() {
+        let image = gray_image!(type: f32, 0.0, 10.0, 0.0);
+        // Each difference of 10 has conduction 1 / (1 + 1) = 0.5.
+        let expected = gray_image!(type: f32, 1.25, 7.5, 1.25);
+        let smoothed = anisotropic(&image, 1, 10.0, 0.25, Conduction::Quadratic).unwrap();
+        assert_pixels_eq!(smoothed, expected);
+    }
+
+    #[test]
+    fn test_anisotropic_conserves_total_intensity() {
+        let image: Image<Luma<f32>> = ImageBuffer::from_fn(9, 7, |x, y| Luma([((x * 37 + y * 11) % 17) as f32]));
+        let total: f32 = image.iter().sum();
+        for &conduction in &[Conduction::Exponential, Conduction::Quadratic] {
+            let smoothed = anisotropic(&image, 10, 5.0, 0.2, conduction).unwrap();
+            assert!((smoothed.iter().sum::<f32>() - total).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_anisotropic_preserves_edges() {
+        let clean = GrayImage::from_fn(40, 30, |x, _| if x < 20 { Luma([60]) } else { Luma([180]) });
+        let noisy = gaussian_noise(&clean, 0.0, 5.0, 3);
+        let smoothed = anisotropic(&noisy, 20, 15.0, 0.25, Conduction::Exponential).unwrap();
+
+        let error = |image: &GrayImage| {
+            image.iter().zip(clean.iter()).map(|(p, q)| (*p as f64 - *q as f64).powi(2)).sum::<f64>()
+        };
+        assert!(error(&smoothed) < error(&noisy) / 4.0);
+        for y in 0..30 {
+            assert!(smoothed.get_pixel(19, y)[0] < 80);
+            assert!(smoothed.get_pixel(20, y)[0] > 160);
+        }
+    }
+
+    #[test]
+    fn test_anisotropic_trivial_inputs() {
+        let image = rgb_bench_image(5, 4);
+        assert_pixels_eq!(anisotropic(&image, 0, 10.0, 0.25, Conduction::Quadratic).unwrap(), image);
+        let constant = GrayImage::from_pixel(6, 5, Luma([77]));
+        assert_pixels_eq!(anisotropic(&constant, 5, 10.0, 0.25, Conduction::Exponential).unwrap(), constant);
+        let empty = RgbImage::new(0, 0);
+        assert_eq!(anisotropic(&empty, 3, 10.0, 0.25, Conduction::Quadratic).unwrap().dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn test_anisotropic_invalid_options() {
+        let image = gray_bench_image(5, 5);
+        for &(kappa, lambda) in &[(0.0, 0.25), (10.0, 0.0), (10.0, 0.3), (-1.0, 0.1)] {
+            match anisotropic(&image, 1, kappa, lambda, Conduction::Exponential) {
+                Err(ImageProcError::InvalidOptions(_)) => {}
+                other => panic!("expected InvalidOptions, got {:?}", other.map(|i| i.dimensions())),
+            }
+        }
+    }
+
+    fn broken_stripes() -> GrayImage {
+        GrayImage::from_fn(30, 30, |x, y| {
+            let on_stripe = x % 6 < 3;
+            Luma([if on_stripe && (y < 13 || y > 16) { 40 } else { 200 }])
+        })
+    }
+
+    #[test]
+    fn test_coherence_enhancing_closes_gaps() {
+        let options = CoherenceEnhancingOptions {
+            iterations: 60,
+            ..Default::default()
+        };
+        let smoothed = coherence_enhancing(&broken_stripes(), options).unwrap();
+        for x in 6..24 {
+            let (gap, below) = (smoothed.get_pixel(x, 15)[0], smoothed.get_pixel(x, 25)[0]);
+            if x % 6 == 1 {
+                // The middle of each stripe is darkened in the gap.
+                assert!(gap < 150, "gap at x = {} has value {}", x, gap);
+                assert!(below < 60);
+            } else if x % 6 == 4 {
+                // The middle of the space between stripes stays bright.
+                assert!(gap > 150, "gap at x = {} has value {}", x, gap);
+                assert!(below > 180);
+            }
+        }
+    }
+
+    #[test]
+    fn test_coherence_enhancing_trivial_inputs() {
+        let constant = GrayImage::from_pixel(12, 9, Luma([90]));
+        let options = CoherenceEnhancingOptions::default();
+        assert_pixels_eq!(coherence_enhancing(&constant, options).unwrap(), constant);
+
+        let image = gray_bench_image(8, 6);
+        let none = CoherenceEnhancingOptions { iterations: 0, ..options };
+        assert_pixels_eq!(coherence_enhancing(&image, none).unwrap(), image);
+
+        let empty = GrayImage::new(0, 0);
+        assert_eq!(coherence_enhancing(&empty, options).unwrap().dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn test_coherence_enhancing_invalid_options() {
+        let image = gray_bench_image(5, 5);
+        let options = CoherenceEnhancingOptions {
+            step: 0.5,
+            noise_scale: -1.0,
+            alpha: 2.0,
+            ..Default::default()
+        };
+        match coherence_enhancing(&image, options) {
+            Err(ImageProcError::InvalidOptions(message)) => assert_eq!(message.matches("must").count(), 3),
+            other => panic!("expected InvalidOptions, got {:?}", other.map(|i| i.dimensions())),
+        }
+    }
+
+    #[bench]
+    fn bench_coherence_enhancing(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| black_box(coherence_enhancing(&image, CoherenceEnhancingOptions::default()).unwrap()));
+    }
+
+    #[bench]
+    fn bench_anisotropic(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| black_box(anisotropic(&image, 10, 20.0, 0.25, Conduction::Exponential).unwrap()));
+    }
+}
@@ -2,9 +2,10 @@
 //! image from the nearest pixel of interest.
 
 use std::cmp::min;
-use std::{u8, f64};
+use std::{u8, u32, f64};
 use image::{GenericImage, GenericImageView, GrayImage, ImageBuffer, Luma};
 use definitions::Image;
+use parallel::for_each_row;
 
 /// How to measure distance between coordinates.
 /// See the [`distance_transform`](fn.distance_transform.html) documentation for examples.
@@ -95,84 +96,183 @@ pub(crate) enum DistanceFrom {
     Background,
 }
 
+// For the L1 norm we first compute the distance from each pixel to the nearest source
+// pixel in its column, and then process each row independently to find the distance to
+// the nearest source pixel in the image. The row passes are run using the helpers from
+// the `parallel` module.
 pub(crate) fn distance_transform_impl(image: &mut GrayImage, norm: Norm, from: DistanceFrom) {
-    let max_distance = Luma([min(image.width() + image.height(), 255u32) as u8]);
-
-    unsafe {
-        // Top-left to bottom-right
-        for y in 0..image.height() {
-            for x in 0..image.width() {
-                if from == DistanceFrom::Foreground {
-                    if image.unsafe_get_pixel(x, y)[0] > 0u8 {
-                        image.unsafe_put_pixel(x, y, Luma([0u8]));
-                        continue;
-                    }
-                } else {
-                    if image.unsafe_get_pixel(x, y)[0] == 0u8 {
-                        image.unsafe_put_pixel(x, y, Luma([0u8]));
-                        continue;
-                    }
-                }
-
-                image.unsafe_put_pixel(x, y, max_distance);
+    match norm {
+        Norm::L1 => {
+            let max_distance = min(image.width() + image.height(), 255u32) as u8;
+            let column_distances = column_distances(image, from, max_distance);
+            let width = image.width() as usize;
+            for_each_row(image, |y, row| {
+                let start = y as usize * width;
+                row.copy_from_slice(&column_distances[start..start + width]);
+                l1_row_distances(row);
+            });
+        }
+        Norm::LInf => chamfer_distance_transform(image, Norm::LInf, from),
+    }
+}
 
-                if x > 0 {
-                    check(image, x, y, x - 1, y);
+// Sets each pixel to near if it is within distance k of a source pixel, and to far otherwise.
+//
+// This gives the same result as thresholding the output of distance_transform_impl,
+// but doesn't need to compute exact distances for the LInf norm.
+pub(crate) fn threshold_distance_impl(
+    image: &mut GrayImage,
+    norm: Norm,
+    from: DistanceFrom,
+    k: u8,
+    near: u8,
+    far: u8,
+) {
+    let max_distance = min(image.width() + image.height(), 255u32) as u8;
+    let column_distances = column_distances(image, from, max_distance);
+    let width = image.width() as usize;
+
+    for_each_row(image, |y, row| {
+        let start = y as usize * width;
+        let column_distances = &column_distances[start..start + width];
+        match norm {
+            Norm::L1 => {
+                row.copy_from_slice(column_distances);
+                l1_row_distances(row);
+                for p in row.iter_mut() {
+                    *p = if *p <= k { near } else { far };
                 }
-
-                if y > 0 {
-                    check(image, x, y, x, y - 1);
-
-                    if norm == Norm::LInf {
-                        if x > 0 {
-                            check(image, x, y, x - 1, y - 1);
-                        }
-                        if x < image.width() - 1 {
-                            check(image, x, y, x + 1, y - 1);
-                        }
-                    }
+            }
+            Norm::LInf => {
+                // A pixel is within LInf distance k of a source pixel if and only if
+                // one of the pixels within k of it in its row is within k of a source
+                // pixel in its column. We count these pixels using running sums.
+                let mut counts = vec![0u32; width + 1];
+                for x in 0..width {
+                    counts[x + 1] = counts[x] + (column_distances[x] <= k) as u32;
+                }
+                let k = k as usize;
+                for (x, p) in row.iter_mut().enumerate() {
+                    let left = x.saturating_sub(k);
+                    let right = min(x + k + 1, width);
+                    *p = if counts[right] > counts[left] { near } else { far };
                 }
             }
         }
+    });
+}
 
-        // Bottom-right to top-left
-        for y in (0..image.height()).rev() {
-            for x in (0..image.width()).rev() {
-                if x < image.width() - 1 {
-                    check(image, x, y, x + 1, y);
-                }
+// Returns the distance from each pixel to the nearest source pixel in the same
+// column, saturating at max_distance and stored in row-major order.
+fn column_distances(image: &GrayImage, from: DistanceFrom, max_distance: u8) -> Vec<u8> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut distances: Vec<u8> = image
+        .iter()
+        .map(|&p| {
+            let is_source = match from {
+                DistanceFrom::Foreground => p > 0,
+                DistanceFrom::Background => p == 0,
+            };
+            if is_source { 0 } else { max_distance }
+        })
+        .collect();
+
+    // Top to bottom
+    for y in 1..height {
+        let (above, current) = distances.split_at_mut(y * width);
+        let above = &above[(y - 1) * width..];
+        for (c, a) in current[..width].iter_mut().zip(above) {
+            *c = min(*c as u16, *a as u16 + 1) as u8;
+        }
+    }
 
-                if y < image.height() - 1 {
-                    check(image, x, y, x, y + 1);
+    // Bottom to top
+    for y in (1..height).rev() {
+        let (current, below) = distances.split_at_mut(y * width);
+        let current = &mut current[(y - 1) * width..];
+        for (c, b) in current.iter_mut().zip(&below[..width]) {
+            *c = min(*c as u16, *b as u16 + 1) as u8;
+        }
+    }
 
-                    if norm == Norm::LInf {
-                        if x < image.width() - 1 {
-                            check(image, x, y, x + 1, y + 1);
-                        }
-                        if x > 0 {
-                            check(image, x, y, x - 1, y + 1);
-                        }
-                    }
-                }
-            }
+    distances
+}
+
+// Replaces the column distances g along a row with min_i |x - i| + g[i] for each x.
+//
+// Saturating each g[i] at the maximum distance doesn't change the (saturated)
+// output, so all calculations can be performed at type u8.
+fn l1_row_distances(row: &mut [u8]) {
+    for x in 1..row.len() {
+        row[x] = min(row[x] as u16, row[x - 1] as u16 + 1) as u8;
+    }
+    for x in (1..row.len()).rev() {
+        row[x - 1] = min(row[x - 1] as u16, row[x] as u16 + 1) as u8;
+    }
+}
+
+// Two-pass chamfer transform, which is exact for both norms. For the LInf norm this is
+// faster than combining the column distances along each row.
+//
+// The forward pass relaxes each row against the row above and then against its left
+// neighbours, and the backward pass against the row below and then its right neighbours.
+// Every value a pixel is compared with is final for that pass by the time it is read,
+// so this gives the same result as visiting the pixels one at a time in raster order.
+fn chamfer_distance_transform(image: &mut GrayImage, norm: Norm, from: DistanceFrom) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let max_distance = min(width + height, 255) as u8;
+    let diagonals = norm == Norm::LInf;
+
+    for p in image.iter_mut() {
+        let is_source = match from {
+            DistanceFrom::Foreground => *p > 0,
+            DistanceFrom::Background => *p == 0,
+        };
+        *p = if is_source { 0 } else { max_distance };
+    }
+
+    // Top-left to bottom-right
+    for y in 0..height {
+        let (before, rest) = image.split_at_mut(y * width);
+        let current = &mut rest[..width];
+        if y > 0 {
+            relax_from_row(current, &before[(y - 1) * width..], diagonals);
+        }
+        for x in 1..width {
+            current[x] = min(current[x] as u16, current[x - 1] as u16 + 1) as u8;
+        }
+    }
+
+    // Bottom-right to top-left
+    for y in (0..height).rev() {
+        let (rest, after) = image.split_at_mut((y + 1) * width);
+        let current = &mut rest[y * width..];
+        if y + 1 < height {
+            relax_from_row(current, &after[..width], diagonals);
+        }
+        for x in (1..width).rev() {
+            current[x - 1] = min(current[x - 1] as u16, current[x] as u16 + 1) as u8;
         }
     }
 }
 
-// Sets image[current_x, current_y] to min(image[current_x, current_y], image[candidate_x, candidate_y] + 1).
-// We avoid overflow by performing the arithmetic at type u16. We could use u8::saturating_add instead, but
-// (based on the benchmarks tests) this appears to be considerably slower.
-unsafe fn check(
-    image: &mut GrayImage,
-    current_x: u32,
-    current_y: u32,
-    candidate_x: u32,
-    candidate_y: u32,
-) {
-    let current = image.unsafe_get_pixel(current_x, current_y)[0] as u16;
-    let candidate_incr = image.unsafe_get_pixel(candidate_x, candidate_y)[0] as u16 + 1;
-    if candidate_incr < current {
-        image.unsafe_put_pixel(current_x, current_y, Luma([candidate_incr as u8]));
+// Sets each entry of row to the minimum of its current value and one more than the
+// entry of other directly above or below it, and also the entries diagonally adjacent
+// to it if diagonals is true. We avoid overflow by performing the arithmetic at type u16.
+fn relax_from_row(row: &mut [u8], other: &[u8], diagonals: bool) {
+    for (c, o) in row.iter_mut().zip(other) {
+        *c = min(*c as u16, *o as u16 + 1) as u8;
+    }
+    if diagonals {
+        for (c, o) in row[1..].iter_mut().zip(other) {
+            *c = min(*c as u16, *o as u16 + 1) as u8;
+        }
+        for (c, o) in row.iter_mut().zip(&other[1..]) {
+            *c = min(*c as u16, *o as u16 + 1) as u8;
+        }
     }
 }
 
@@ -200,9 +300,8 @@ pub fn euclidean_squared_distance_transform(image: &Image<Luma<u8>>) -> Image<Lu
 
     // Compute 1d transforms of each row
     for y in 0..height {
-        for x in 0..width {
-            row_buffer[x as usize] = result.get_pixel(x, y)[0];
-        }
+        let start = y as usize * width as usize;
+        row_buffer.copy_from_slice(&(*result)[start..start + width as usize]);
         let mut sink = Row { image: &mut result, row: y };
         distance_transform_1d_mut(&row_buffer, &mut sink, &mut row_envelope);
     }
@@ -210,6 +309,249 @@ pub fn euclidean_squared_distance_transform(image: &Image<Luma<u8>>) -> Image<Lu
     result
 }
 
+/// Computes the `L2` (Euclidean) distance transform of `image`, i.e. the square root of
+/// [`euclidean_squared_distance_transform`](fn.euclidean_squared_distance_transform.html).
+///
+/// Distances are exact, and are infinite for all pixels if there are no foreground pixels.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::distance_transform::euclidean_distance_transform;
+///
+/// let image = gray_image!(
+///     0, 0, 0;
+///     0, 0, 0;
+///     0, 0, 1);
+///
+/// let distances = gray_image!(type: f32,
+///     8f32.sqrt(), 5f32.sqrt(), 2.0;
+///     5f32.sqrt(), 2f32.sqrt(), 1.0;
+///     2.0,         1.0,         0.0);
+///
+/// assert_pixels_eq_within!(euclidean_distance_transform(&image), distances, 1e-6);
+/// # }
+/// ```
+pub fn euclidean_distance_transform(image: &Image<Luma<u8>>) -> Image<Luma<f32>> {
+    let squared = euclidean_squared_distance_transform(image);
+    let (width, height) = squared.dimensions();
+    let mut out = Image::new(width, height);
+    for (o, s) in out.iter_mut().zip(squared.iter()) {
+        *o = s.sqrt() as f32;
+    }
+    out
+}
+
+/// The result of [`euclidean_feature_transform`](fn.euclidean_feature_transform.html).
+#[derive(Clone, Debug)]
+pub struct FeatureTransform {
+    /// The squared Euclidean distance of each pixel from its nearest foreground pixel.
+    pub squared_distances: Image<Luma<f64>>,
+    /// The x coordinate of the nearest foreground pixel to each pixel.
+    pub nearest_x: Image<Luma<u32>>,
+    /// The y coordinate of the nearest foreground pixel to each pixel.
+    pub nearest_y: Image<Luma<u32>>,
+}
+
+impl FeatureTransform {
+    /// Returns the coordinates of the nearest foreground pixel to `(x, y)`,
+    /// or `None` if there are no foreground pixels.
+    ///
+    /// # Panics
+    /// If `(x, y)` is outside the image.
+    pub fn nearest(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        let nx = self.nearest_x.get_pixel(x, y)[0];
+        if nx == u32::MAX {
+            None
+        } else {
+            Some((nx, self.nearest_y.get_pixel(x, y)[0]))
+        }
+    }
+}
+
+/// Computes the squared Euclidean distance of each pixel from the nearest foreground pixel,
+/// as for [`euclidean_squared_distance_transform`](fn.euclidean_squared_distance_transform.html),
+/// together with the coordinates of that foreground pixel.
+///
+/// This map from pixels to their nearest foreground pixels is known as the feature transform,
+/// and can be used to assign each pixel to the Voronoi region of the foreground pixels, or to
+/// find the medial axis of a shape. Where several foreground pixels are equally near to a pixel
+/// one of them is chosen arbitrarily. If there are no foreground pixels then all distances are
+/// infinite and all coordinates are `u32::MAX`.
+///
+/// Uses the algorithm from [Distance Transforms of Sampled Functions], recording the location
+/// of the parabola in the lower envelope at each pixel.
+///
+/// [Distance Transforms of Sampled Functions]: http://www.cs.cornell.edu/~dph/papers%5Cdt.pdf
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::distance_transform::euclidean_feature_transform;
+///
+/// // Two seeds, with labels 1 and 2.
+/// let seeds = gray_image!(
+///     1, 0, 0, 0;
+///     0, 0, 0, 0;
+///     0, 0, 0, 2);
+///
+/// let features = euclidean_feature_transform(&seeds);
+/// assert_eq!(features.nearest(0, 2), Some((0, 0)));
+/// assert_eq!(features.squared_distances.get_pixel(0, 2)[0], 4.0);
+///
+/// // Label each pixel by its nearest seed.
+/// let voronoi = gray_image!(
+///     1, 1, 1, 2;
+///     1, 1, 2, 2;
+///     1, 2, 2, 2);
+///
+/// let mut labels = seeds.clone();
+/// for (x, y, p) in labels.enumerate_pixels_mut() {
+///     let (nx, ny) = features.nearest(x, y).unwrap();
+///     *p = *seeds.get_pixel(nx, ny);
+/// }
+/// assert_pixels_eq!(labels, voronoi);
+/// # }
+/// ```
+pub fn euclidean_feature_transform(image: &Image<Luma<u8>>) -> FeatureTransform {
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as usize, height as usize);
+
+    // The squared distance from each pixel to the nearest foreground pixel
+    // in its column, and the row of that pixel, in row-major order.
+    let mut column_distances = vec![f64::INFINITY; w * h];
+    let mut column_nearest = vec![0usize; w * h];
+
+    let mut column_envelope = LowerEnvelope::new(h);
+    let mut values = vec![0f64; h];
+    let mut locations = vec![0usize; h];
+    for x in 0..width {
+        let source = Column { image, column: x };
+        {
+            let mut sink = Nearest { values: &mut values, locations: &mut locations };
+            distance_transform_1d_mut(&source, &mut sink, &mut column_envelope);
+        }
+        for y in 0..h {
+            column_distances[y * w + x as usize] = values[y];
+            column_nearest[y * w + x as usize] = locations[y];
+        }
+    }
+
+    let mut squared_distances = ImageBuffer::new(width, height);
+    let mut nearest_x = ImageBuffer::new(width, height);
+    let mut nearest_y = ImageBuffer::new(width, height);
+
+    let mut row_envelope = LowerEnvelope::new(w);
+    let mut values = vec![0f64; w];
+    let mut locations = vec![0usize; w];
+    for y in 0..height {
+        let start = y as usize * w;
+        {
+            let mut sink = Nearest { values: &mut values, locations: &mut locations };
+            distance_transform_1d_mut(&column_distances[start..start + w], &mut sink, &mut row_envelope);
+        }
+        for x in 0..width {
+            let value = values[x as usize];
+            let (nx, ny) = if value.is_infinite() {
+                (u32::MAX, u32::MAX)
+            } else {
+                let column = locations[x as usize];
+                (column as u32, column_nearest[start + column] as u32)
+            };
+            squared_distances.put_pixel(x, y, Luma([value]));
+            nearest_x.put_pixel(x, y, Luma([nx]));
+            nearest_y.put_pixel(x, y, Luma([ny]));
+        }
+    }
+
+    FeatureTransform { squared_distances, nearest_x, nearest_y }
+}
+
+/// Returns the distance of each pixel from a foreground pixel in `image`, as for
+/// [`distance_transform`](fn.distance_transform.html) but without saturating distances at 255.
+///
+/// If there are no foreground pixels then all distances are `u32::MAX`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::distance_transform::{distance_transform_u32, Norm};
+///
+/// let mut image = GrayImage::new(400, 1);
+/// image.put_pixel(0, 0, Luma([255]));
+///
+/// let distances = distance_transform_u32(&image, Norm::L1);
+/// assert_eq!(distances.get_pixel(399, 0)[0], 399);
+/// # }
+/// ```
+pub fn distance_transform_u32(image: &GrayImage, norm: Norm) -> Image<Luma<u32>> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut out: Image<Luma<u32>> = ImageBuffer::new(image.width(), image.height());
+    if width == 0 || height == 0 {
+        return out;
+    }
+    let diagonals = norm == Norm::LInf;
+    let relax = |c: &mut u32, o: u32| *c = min(*c, o.saturating_add(1));
+
+    for (o, p) in out.iter_mut().zip(image.iter()) {
+        *o = if *p > 0 { 0 } else { u32::MAX };
+    }
+
+    // Two-pass chamfer transform, as in chamfer_distance_transform.
+    for y in 0..height {
+        let (before, rest) = out.split_at_mut(y * width);
+        let current = &mut rest[..width];
+        if y > 0 {
+            let above = &before[(y - 1) * width..];
+            for x in 0..width {
+                relax(&mut current[x], above[x]);
+                if diagonals && x > 0 {
+                    relax(&mut current[x], above[x - 1]);
+                }
+                if diagonals && x + 1 < width {
+                    relax(&mut current[x], above[x + 1]);
+                }
+            }
+        }
+        for x in 1..width {
+            let left = current[x - 1];
+            relax(&mut current[x], left);
+        }
+    }
+
+    for y in (0..height).rev() {
+        let (rest, after) = out.split_at_mut((y + 1) * width);
+        let current = &mut rest[y * width..];
+        if y + 1 < height {
+            let below = &after[..width];
+            for x in 0..width {
+                relax(&mut current[x], below[x]);
+                if diagonals && x > 0 {
+                    relax(&mut current[x], below[x - 1]);
+                }
+                if diagonals && x + 1 < width {
+                    relax(&mut current[x], below[x + 1]);
+                }
+            }
+        }
+        for x in (1..width).rev() {
+            let right = current[x];
+            relax(&mut current[x - 1], right);
+        }
+    }
+
+    out
+}
+
 struct LowerEnvelope {
     // Indices of the parabolas in the lower envelope.
     locations: Vec<usize>,
@@ -232,6 +574,10 @@ impl LowerEnvelope {
 trait Sink {
     fn put(&mut self, idx: usize, value: f64);
     fn len(&self) -> usize;
+    // Called instead of put, with the index of the source entry giving value.
+    fn put_nearest(&mut self, idx: usize, value: f64, _nearest: usize) {
+        self.put(idx, value);
+    }
 }
 
 trait Source {
@@ -267,6 +613,26 @@ impl<'a> Sink for ColumnMut<'a> {
     }
 }
 
+// Records the 1d transform of a row or column together with the location
+// of the source entry giving each value.
+struct Nearest<'a> {
+    values: &'a mut [f64],
+    locations: &'a mut [usize]
+}
+
+impl<'a> Sink for Nearest<'a> {
+    fn put(&mut self, idx: usize, value: f64) {
+        self.values[idx] = value;
+    }
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+    fn put_nearest(&mut self, idx: usize, value: f64, nearest: usize) {
+        self.values[idx] = value;
+        self.locations[idx] = nearest;
+    }
+}
+
 impl Source for Vec<f64> {
     fn get(&self, idx: usize) -> f64 {
         self[idx]
@@ -302,7 +668,7 @@ impl<'a> Source for Column<'a> {
 
 fn distance_transform_1d_mut<S, T>(f: &S, result: &mut T, envelope: &mut LowerEnvelope)
 where
-    S: Source,
+    S: Source + ?Sized,
     T: Sink
 {
     assert!(result.len() == f.len());
@@ -366,7 +732,7 @@ where
             k = k + 1;
         }
         let dist = q as f64 - envelope.locations[k] as f64;
-        result.put(q, dist * dist + f.get(envelope.locations[k]));
+        result.put_nearest(q, dist * dist + f.get(envelope.locations[k]), envelope.locations[k]);
     }
 }
 
@@ -414,6 +780,95 @@ mod test {
         assert_pixels_eq!(distances, expected);
     }
 
+    // Exhaustive search over all pixels, used to validate distance_transform_impl against.
+    fn distance_transform_reference(image: &GrayImage, norm: Norm, from: DistanceFrom) -> GrayImage {
+        let (width, height) = image.dimensions();
+        let max_distance = min(width + height, 255);
+
+        GrayImage::from_fn(width, height, |x, y| {
+            let mut best = max_distance;
+            for (xc, yc, p) in image.enumerate_pixels() {
+                let is_source = match from {
+                    DistanceFrom::Foreground => p[0] > 0,
+                    DistanceFrom::Background => p[0] == 0,
+                };
+                if is_source {
+                    let dx = (x as i32 - xc as i32).abs() as u32;
+                    let dy = (y as i32 - yc as i32).abs() as u32;
+                    let d = match norm {
+                        Norm::L1 => dx + dy,
+                        Norm::LInf => max(dx, dy),
+                    };
+                    best = min(best, d);
+                }
+            }
+            Luma([best as u8])
+        })
+    }
+
+    #[test]
+    fn test_distance_transform_matches_reference_implementation() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            for &norm in &[Norm::L1, Norm::LInf] {
+                for &from in &[DistanceFrom::Foreground, DistanceFrom::Background] {
+                    let expected = distance_transform_reference(&image.0, norm, from);
+                    let mut actual = image.0.clone();
+                    distance_transform_impl(&mut actual, norm, from);
+                    if let Some(err) = pixel_diff_summary(&actual, &expected) {
+                        return TestResult::error(err);
+                    }
+                }
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_threshold_distance_matches_reference_implementation() {
+        fn prop(image: GrayTestImage, k: u8) -> TestResult {
+            let k = k % 8;
+            for &norm in &[Norm::L1, Norm::LInf] {
+                for &from in &[DistanceFrom::Foreground, DistanceFrom::Background] {
+                    let distances = distance_transform_reference(&image.0, norm, from);
+                    let expected = GrayImage::from_fn(image.0.width(), image.0.height(), |x, y| {
+                        Luma([if distances.get_pixel(x, y)[0] <= k { 1 } else { 2 }])
+                    });
+                    let mut actual = image.0.clone();
+                    threshold_distance_impl(&mut actual, norm, from, k, 1, 2);
+                    if let Some(err) = pixel_diff_summary(&actual, &expected) {
+                        return TestResult::error(err);
+                    }
+                }
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(GrayTestImage, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_distance_transform_sparse_foreground() {
+        let image = gray_image!(
+            0,   0,   0,   0,   0,   0,   0;
+            0,   1,   0,   0,   0,   0,   0;
+            0,   0,   0,   0,   0,   0,   0;
+            0,   0,   0,   0,   0,   0,   1);
+
+        let l1 = gray_image!(
+            2,   1,   2,   3,   4,   4,   3;
+            1,   0,   1,   2,   3,   3,   2;
+            2,   1,   2,   3,   3,   2,   1;
+            3,   2,   3,   3,   2,   1,   0);
+        assert_pixels_eq!(distance_transform(&image, Norm::L1), l1);
+
+        let linf = gray_image!(
+            1,   1,   1,   2,   3,   3,   3;
+            1,   0,   1,   2,   2,   2,   2;
+            1,   1,   1,   2,   2,   1,   1;
+            2,   2,   2,   2,   2,   1,   0);
+        assert_pixels_eq!(distance_transform(&image, Norm::LInf), linf);
+    }
+
     impl<'a> Sink for Vec<f64> {
         fn put(&mut self, idx: usize, value: f64) {
             self[idx] = value;
@@ -522,6 +977,57 @@ mod test {
         quickcheck(prop as fn(GrayTestImage) -> TestResult);
     }
 
+    #[test]
+    fn test_distance_transform_u32_matches_distance_transform_below_saturation() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            for &norm in &[Norm::L1, Norm::LInf] {
+                let expected = distance_transform(&image.0, norm);
+                let actual = distance_transform_u32(&image.0, norm);
+                let has_foreground = image.0.iter().any(|p| *p > 0);
+                for (e, a) in expected.iter().zip(actual.iter()) {
+                    if has_foreground && *e != *a as u8 || !has_foreground && *a != u32::MAX {
+                        return TestResult::failed();
+                    }
+                }
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_distance_transform_u32_does_not_saturate() {
+        let mut image = GrayImage::new(300, 300);
+        image.put_pixel(0, 0, Luma([1]));
+
+        let l1 = distance_transform_u32(&image, Norm::L1);
+        let linf = distance_transform_u32(&image, Norm::LInf);
+        for (x, y, p) in l1.enumerate_pixels() {
+            assert_eq!(p[0], x + y);
+            assert_eq!(linf.get_pixel(x, y)[0], max(x, y));
+        }
+
+        let empty = GrayImage::new(3, 2);
+        assert!(distance_transform_u32(&empty, Norm::L1).iter().all(|d| *d == u32::MAX));
+    }
+
+    #[test]
+    fn test_euclidean_distance_transform_is_root_of_squared_transform() {
+        let image = gray_image!(
+            1, 0, 0, 0, 0;
+            0, 0, 0, 0, 0;
+            0, 0, 0, 0, 1);
+        let squared = euclidean_squared_distance_transform(&image);
+        let distances = euclidean_distance_transform(&image);
+        for (s, d) in squared.iter().zip(distances.iter()) {
+            assert!((s.sqrt() as f32 - d).abs() < 1e-6);
+        }
+        assert_eq!(distances.get_pixel(2, 1)[0], 5f32.sqrt());
+
+        let empty = GrayImage::new(2, 2);
+        assert!(euclidean_distance_transform(&empty).iter().all(|d| d.is_infinite()));
+    }
+
     #[test]
     fn test_euclidean_squared_distance_transform_example() {
         let image = gray_image!(
@@ -544,6 +1050,50 @@ mod test {
         assert_pixels_eq_within!(dist, expected, 1e-6);
     }
 
+    #[test]
+    fn test_euclidean_feature_transform_matches_reference_implementation() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let expected = euclidean_squared_distance_transform_reference(&image.0);
+            let features = euclidean_feature_transform(&image.0);
+            if let Some(err) = pixel_diff_summary(&features.squared_distances, &expected) {
+                return TestResult::error(err);
+            }
+            for (x, y, d) in expected.enumerate_pixels() {
+                match features.nearest(x, y) {
+                    None => if !d[0].is_infinite() {
+                        return TestResult::failed();
+                    },
+                    Some((nx, ny)) => {
+                        let dx = nx as f64 - x as f64;
+                        let dy = ny as f64 - y as f64;
+                        if image.0.get_pixel(nx, ny)[0] == 0 || dx * dx + dy * dy != d[0] {
+                            return TestResult::failed();
+                        }
+                    }
+                }
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_euclidean_feature_transform_without_foreground() {
+        let features = euclidean_feature_transform(&GrayImage::new(3, 2));
+        assert!(features.squared_distances.iter().all(|d| d.is_infinite()));
+        assert!(features.nearest_x.iter().chain(features.nearest_y.iter()).all(|c| *c == u32::MAX));
+        assert_eq!(features.nearest(2, 1), None);
+
+        let empty = euclidean_feature_transform(&GrayImage::new(0, 0));
+        assert_eq!(empty.squared_distances.dimensions(), (0, 0));
+    }
+
+    #[bench]
+    fn bench_euclidean_feature_transform_100(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| black_box(euclidean_feature_transform(&image)));
+    }
+
     macro_rules! bench_euclidean_squared_distance_transform {
         ($name:ident, side: $s:expr) => {
             #[bench]
@@ -284,12 +284,391 @@ where
     out
 }
 
+/// An ellipse rotated clockwise about its center, as drawn by
+/// [`draw_hollow_rotated_ellipse`](fn.draw_hollow_rotated_ellipse.html) and
+/// [`draw_filled_rotated_ellipse`](fn.draw_filled_rotated_ellipse.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RotatedEllipse {
+    /// Center of the ellipse.
+    pub center: (f32, f32),
+    /// Radius along the ellipse's own x-axis.
+    pub width_radius: f32,
+    /// Radius along the ellipse's own y-axis.
+    pub height_radius: f32,
+    /// Clockwise rotation of the ellipse about its center, in radians.
+    pub angle: f32,
+}
+
+impl RotatedEllipse {
+    /// Construct an ellipse from its center, radii along its own axes and clockwise
+    /// rotation in radians.
+    pub fn new(center: (f32, f32), width_radius: f32, height_radius: f32, angle: f32) -> RotatedEllipse {
+        RotatedEllipse { center, width_radius, height_radius, angle }
+    }
+
+    /// Returns the point at parameter `t` on this ellipse.
+    fn point(&self, t: f32) -> (f32, f32) {
+        let (sin_a, cos_a) = self.angle.sin_cos();
+        let (sin_t, cos_t) = t.sin_cos();
+        let u = self.width_radius * cos_t;
+        let v = self.height_radius * sin_t;
+        (self.center.0 + u * cos_a - v * sin_a, self.center.1 + u * sin_a + v * cos_a)
+    }
+}
+
+/// The section of a [`RotatedEllipse`](struct.RotatedEllipse.html) between the ellipse
+/// parameters `start_angle` and `end_angle`, in radians, as drawn by
+/// [`draw_hollow_arc`](fn.draw_hollow_arc.html) and [`draw_filled_arc`](fn.draw_filled_arc.html).
+///
+/// The parameter `t` corresponds to the point `(width_radius * cos(t), height_radius * sin(t))`
+/// before rotation, so for a circle it is the clockwise angle from the rotated x-axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EllipticalArc {
+    /// The ellipse the arc lies on.
+    pub ellipse: RotatedEllipse,
+    /// Ellipse parameter at which the arc starts.
+    pub start_angle: f32,
+    /// Ellipse parameter at which the arc ends.
+    pub end_angle: f32,
+}
+
+impl EllipticalArc {
+    /// Construct the section of `ellipse` between the parameters `start_angle` and `end_angle`.
+    pub fn new(ellipse: RotatedEllipse, start_angle: f32, end_angle: f32) -> EllipticalArc {
+        EllipticalArc { ellipse, start_angle, end_angle }
+    }
+}
+
+/// Draws the section of a rotated ellipse between parameters `start` and `end` as a
+/// sequence of line segments.
+fn draw_elliptic_polyline_mut<I>(
+    image: &mut I,
+    ellipse: &RotatedEllipse,
+    start: f32,
+    end: f32,
+    color: I::Pixel,
+) where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    // Aim for segments of at most two pixels in length.
+    let max_radius = ellipse.width_radius.abs().max(ellipse.height_radius.abs());
+    let length_bound = max_radius * (end - start).abs();
+    let num_segments = ((length_bound / 2.0).ceil() as i32).max(4);
+
+    let point = |t: f32| {
+        let (x, y) = ellipse.point(t);
+        (x.round(), y.round())
+    };
+
+    let t_interval = (end - start) / num_segments as f32;
+    let mut p1 = point(start);
+    for i in 0..num_segments {
+        let p2 = point(start + (i as f32 + 1.0) * t_interval);
+        draw_line_segment_mut(image, p1, p2, color);
+        p1 = p2;
+    }
+}
+
+/// Calls `f(x, y)` for every pixel of a `width` by `height` image whose center lies inside
+/// `ellipse` and for which `accept(t)` holds, where `t` is the ellipse parameter of the ray
+/// from the center through `(x, y)`.
+fn fill_rotated_ellipse<F, G>(
+    (width, height): (u32, u32),
+    ellipse: &RotatedEllipse,
+    accept: G,
+    mut f: F,
+) where
+    F: FnMut(u32, u32),
+    G: Fn(f32) -> bool,
+{
+    let a = ellipse.width_radius.abs();
+    let b = ellipse.height_radius.abs();
+    if a == 0.0 || b == 0.0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let center = ellipse.center;
+    let (sin_a, cos_a) = ellipse.angle.sin_cos();
+    let half_width = (a * a * cos_a * cos_a + b * b * sin_a * sin_a).sqrt();
+    let half_height = (a * a * sin_a * sin_a + b * b * cos_a * cos_a).sqrt();
+
+    let x_min = (center.0 - half_width).floor().max(0.0) as u32;
+    let y_min = (center.1 - half_height).floor().max(0.0) as u32;
+    let x_max = (center.0 + half_width).ceil().min(width as f32 - 1.0);
+    let y_max = (center.1 + half_height).ceil().min(height as f32 - 1.0);
+    if x_max < 0.0 || y_max < 0.0 {
+        return;
+    }
+    let (x_max, y_max) = (x_max as u32, y_max as u32);
+
+    for y in y_min..y_max + 1 {
+        for x in x_min..x_max + 1 {
+            let dx = x as f32 - center.0;
+            let dy = y as f32 - center.1;
+            // Rotate back into the frame of the axis-aligned ellipse.
+            let u = (dx * cos_a + dy * sin_a) / a;
+            let v = (-dx * sin_a + dy * cos_a) / b;
+            if u * u + v * v <= 1.0 && accept(v.atan2(u)) {
+                f(x, y);
+            }
+        }
+    }
+}
+
+/// Returns true if the ellipse parameter `t` lies between `start` and `end`, modulo `2 * PI`.
+fn in_parameter_range(t: f32, start: f32, end: f32) -> bool {
+    let two_pi = 2.0 * f32::consts::PI;
+    let (lo, hi) = if start <= end { (start, end) } else { (end, start) };
+    if hi - lo >= two_pi {
+        return true;
+    }
+    let mut offset = (t - lo) % two_pi;
+    if offset < 0.0 {
+        offset += two_pi;
+    }
+    offset <= hi - lo
+}
+
+/// Draw as much of a rotated ellipse as lies inside the image bounds.
+///
+/// The outline is approximated by line segments.
+pub fn draw_hollow_rotated_ellipse<I>(
+    image: &I,
+    ellipse: RotatedEllipse,
+    color: I::Pixel,
+) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_hollow_rotated_ellipse_mut(&mut out, ellipse, color);
+    out
+}
+
+/// Draw as much of a rotated ellipse as lies inside the image bounds.
+///
+/// The outline is approximated by line segments.
+pub fn draw_hollow_rotated_ellipse_mut<I>(
+    image: &mut I,
+    ellipse: RotatedEllipse,
+    color: I::Pixel,
+) where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let two_pi = 2.0 * f32::consts::PI;
+    draw_elliptic_polyline_mut(image, &ellipse, 0.0, two_pi, color);
+}
+
+/// Draw as much of a rotated ellipse, including its contents, as lies inside the image bounds.
+///
+/// A pixel is filled if its center lies inside the ellipse.
+pub fn draw_filled_rotated_ellipse<I>(
+    image: &I,
+    ellipse: RotatedEllipse,
+    color: I::Pixel,
+) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_filled_rotated_ellipse_mut(&mut out, ellipse, color);
+    out
+}
+
+/// Draw as much of a rotated ellipse, including its contents, as lies inside the image bounds.
+///
+/// A pixel is filled if its center lies inside the ellipse.
+pub fn draw_filled_rotated_ellipse_mut<I>(
+    image: &mut I,
+    ellipse: RotatedEllipse,
+    color: I::Pixel,
+) where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let dimensions = image.dimensions();
+    fill_rotated_ellipse(dimensions, &ellipse, |_| true, |x, y| image.put_pixel(x, y, color));
+}
+
+/// Draw as much of an elliptical arc as lies inside the image bounds.
+///
+/// The arc is the section of the ellipse drawn by
+/// [`draw_hollow_rotated_ellipse`](fn.draw_hollow_rotated_ellipse.html) between
+/// `arc.start_angle` and `arc.end_angle`.
+pub fn draw_hollow_arc<I>(
+    image: &I,
+    arc: EllipticalArc,
+    color: I::Pixel,
+) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_hollow_arc_mut(&mut out, arc, color);
+    out
+}
+
+/// Draw as much of an elliptical arc as lies inside the image bounds.
+///
+/// The arc is the section of the ellipse drawn by
+/// [`draw_hollow_rotated_ellipse`](fn.draw_hollow_rotated_ellipse.html) between
+/// `arc.start_angle` and `arc.end_angle`.
+pub fn draw_hollow_arc_mut<I>(
+    image: &mut I,
+    arc: EllipticalArc,
+    color: I::Pixel,
+) where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    draw_elliptic_polyline_mut(image, &arc.ellipse, arc.start_angle, arc.end_angle, color);
+}
+
+/// Draw as much of an elliptical sector as lies inside the image bounds.
+///
+/// The sector is bounded by the arc drawn by [`draw_hollow_arc`](fn.draw_hollow_arc.html)
+/// and by the straight lines joining its end points to the center of the ellipse.
+/// A pixel is filled if its center lies inside the sector.
+pub fn draw_filled_arc<I>(
+    image: &I,
+    arc: EllipticalArc,
+    color: I::Pixel,
+) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_filled_arc_mut(&mut out, arc, color);
+    out
+}
+
+/// Draw as much of an elliptical sector as lies inside the image bounds.
+///
+/// The sector is bounded by the arc drawn by [`draw_hollow_arc`](fn.draw_hollow_arc.html)
+/// and by the straight lines joining its end points to the center of the ellipse.
+/// A pixel is filled if its center lies inside the sector.
+pub fn draw_filled_arc_mut<I>(
+    image: &mut I,
+    arc: EllipticalArc,
+    color: I::Pixel,
+) where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let dimensions = image.dimensions();
+    fill_rotated_ellipse(
+        dimensions,
+        &arc.ellipse,
+        |t| in_parameter_range(t, arc.start_angle, arc.end_angle),
+        |x, y| image.put_pixel(x, y, color),
+    );
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use image::{GrayImage, Luma};
     use test::{Bencher, black_box};
 
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn filled_axis_aligned_ellipse(width: u32, height: u32, center: (i32, i32), a: f32, b: f32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let u = (x as i32 - center.0) as f32 / a;
+            let v = (y as i32 - center.1) as f32 / b;
+            if u * u + v * v <= 1.0 { Luma([255u8]) } else { Luma([0u8]) }
+        })
+    }
+
+    #[test]
+    fn test_draw_filled_rotated_ellipse_unrotated() {
+        let image = GrayImage::new(20, 20);
+        let drawn = draw_filled_rotated_ellipse(&image, RotatedEllipse::new((9.0, 10.0), 6.5, 3.5, 0.0), Luma([255u8]));
+        let expected = filled_axis_aligned_ellipse(20, 20, (9, 10), 6.5, 3.5);
+        assert_pixels_eq!(drawn, expected);
+    }
+
+    #[test]
+    fn test_draw_filled_rotated_ellipse_quarter_turn() {
+        let image = GrayImage::new(20, 20);
+        let drawn = draw_filled_rotated_ellipse(&image, RotatedEllipse::new((9.0, 10.0), 6.5, 3.5, FRAC_PI_2), Luma([255u8]));
+        let expected = filled_axis_aligned_ellipse(20, 20, (9, 10), 3.5, 6.5);
+        assert_pixels_eq!(drawn, expected);
+    }
+
+    #[test]
+    fn test_draw_filled_rotated_ellipse_outside_image() {
+        let image = GrayImage::new(10, 10);
+        let drawn = draw_filled_rotated_ellipse(&image, RotatedEllipse::new((-20.0, 30.0), 5.0, 3.0, 0.3), Luma([255u8]));
+        assert_pixels_eq!(drawn, image);
+    }
+
+    #[test]
+    fn test_draw_filled_arc_full_turn_matches_ellipse() {
+        let image = GrayImage::new(20, 20);
+        let color = Luma([255u8]);
+        let ellipse = RotatedEllipse::new((9.0, 10.0), 6.5, 3.5, 0.4);
+        let arc = draw_filled_arc(&image, EllipticalArc::new(ellipse, 0.0, 2.0 * PI), color);
+        let ellipse = draw_filled_rotated_ellipse(&image, ellipse, color);
+        assert_pixels_eq!(arc, ellipse);
+    }
+
+    #[test]
+    fn test_draw_filled_arc_quarter() {
+        let image = GrayImage::new(21, 21);
+        let circle = RotatedEllipse::new((10.0, 10.0), 8.0, 8.0, 0.0);
+        let drawn = draw_filled_arc(&image, EllipticalArc::new(circle, 0.0, FRAC_PI_2), Luma([255u8]));
+        for (x, y, p) in drawn.enumerate_pixels() {
+            if x < 10 || y < 10 {
+                assert_eq!(p[0], 0, "pixel ({}, {}) should not be filled", x, y);
+            }
+        }
+        assert_eq!(drawn.get_pixel(10, 10)[0], 255);
+        assert_eq!(drawn.get_pixel(14, 14)[0], 255);
+        assert_eq!(drawn.get_pixel(18, 10)[0], 255);
+        assert_eq!(drawn.get_pixel(10, 18)[0], 255);
+    }
+
+    #[test]
+    fn test_draw_hollow_rotated_ellipse() {
+        let image = GrayImage::new(30, 30);
+        let drawn = draw_hollow_rotated_ellipse(&image, RotatedEllipse::new((15.0, 15.0), 10.0, 5.0, FRAC_PI_2), Luma([255u8]));
+        // A quarter turn clockwise maps the ellipse's x-axis onto the image's y-axis.
+        assert_eq!(drawn.get_pixel(15, 25)[0], 255);
+        assert_eq!(drawn.get_pixel(15, 5)[0], 255);
+        assert_eq!(drawn.get_pixel(10, 15)[0], 255);
+        assert_eq!(drawn.get_pixel(20, 15)[0], 255);
+        assert_eq!(drawn.get_pixel(15, 15)[0], 0);
+        assert_eq!(drawn.get_pixel(25, 15)[0], 0);
+    }
+
+    #[test]
+    fn test_draw_hollow_arc() {
+        let image = GrayImage::new(30, 30);
+        let circle = RotatedEllipse::new((15.0, 15.0), 10.0, 10.0, 0.0);
+        let drawn = draw_hollow_arc(&image, EllipticalArc::new(circle, 0.0, FRAC_PI_2), Luma([255u8]));
+        assert_eq!(drawn.get_pixel(25, 15)[0], 255);
+        assert_eq!(drawn.get_pixel(15, 25)[0], 255);
+        assert_eq!(drawn.get_pixel(5, 15)[0], 0);
+        assert_eq!(drawn.get_pixel(15, 5)[0], 0);
+        for (x, y, p) in drawn.enumerate_pixels() {
+            if p[0] > 0 {
+                assert!(x >= 15 && y >= 15, "pixel ({}, {}) is outside the arc", x, y);
+            }
+        }
+    }
+
+
     macro_rules! bench_hollow_ellipse {
         ($name:ident, $center:expr, $width_radius:expr, $height_radius:expr) => {
             #[bench]
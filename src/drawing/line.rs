@@ -1,11 +1,24 @@
 use image::{GenericImage, ImageBuffer, Pixel};
 use definitions::Image;
+use error::{check_non_empty, Result};
 use std::mem::{swap, transmute};
 use std::f32;
 use std::i32;
 
 /// Iterates over the coordinates in a line segment using
 /// [Bresenham's line drawing algorithm](https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm).
+///
+/// Coordinates are yielded in order of increasing x for shallow lines and increasing y
+/// for steep lines, regardless of the order of the end points. They are not clipped to
+/// any image, so can be used to sample intensity profiles along a ray.
+///
+/// # Examples
+/// ```
+/// use imageproc::drawing::BresenhamLineIter;
+///
+/// let points: Vec<(i32, i32)> = BresenhamLineIter::new((0.0, 0.0), (4.0, 2.0)).collect();
+/// assert_eq!(points, vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 2)]);
+/// ```
 pub struct BresenhamLineIter {
     dx: f32,
     dy: f32,
@@ -99,6 +112,10 @@ pub struct BresenhamLinePixelIter<'a, P: Pixel + 'static> {
 impl<'a, P: Pixel + 'static> BresenhamLinePixelIter<'a, P> {
     /// Creates a [`BresenhamLinePixelIter`](struct.BresenhamLinePixelIter.html) which will iterate over
     /// the image pixels with coordinates between `start` and `end`.
+    ///
+    /// # Panics
+    /// If `image` is empty. See [`try_new`](#method.try_new) for a version that returns
+    /// an error instead.
     pub fn new(image: &Image<P>, start: (f32, f32), end: (f32, f32)) -> BresenhamLinePixelIter<P> {
         assert!(image.width() >= 1 && image.height() >= 1, "BresenhamLinePixelIter does not support empty images");
         BresenhamLinePixelIter {
@@ -106,6 +123,15 @@ impl<'a, P: Pixel + 'static> BresenhamLinePixelIter<'a, P> {
             image: image
         }
     }
+
+    /// Creates a [`BresenhamLinePixelIter`](struct.BresenhamLinePixelIter.html) which will iterate over
+    /// the image pixels with coordinates between `start` and `end`.
+    ///
+    /// Returns `ImageProcError::EmptyImage` if `image` is empty.
+    pub fn try_new(image: &'a Image<P>, start: (f32, f32), end: (f32, f32)) -> Result<BresenhamLinePixelIter<'a, P>> {
+        check_non_empty(image.dimensions())?;
+        Ok(BresenhamLinePixelIter::new(image, start, end))
+    }
 }
 
 impl <'a, P: Pixel + 'static> Iterator for BresenhamLinePixelIter<'a, P> {
@@ -126,6 +152,10 @@ pub struct BresenhamLinePixelIterMut<'a, P: Pixel + 'static> {
 impl<'a, P: Pixel + 'static> BresenhamLinePixelIterMut<'a, P> {
     /// Creates a [`BresenhamLinePixelIterMut`](struct.BresenhamLinePixelIterMut.html) which will iterate over
     /// the image pixels with coordinates between `start` and `end`.
+    ///
+    /// # Panics
+    /// If `image` is empty. See [`try_new`](#method.try_new) for a version that returns
+    /// an error instead.
     pub fn new(image: &mut Image<P>, start: (f32, f32), end: (f32, f32)) -> BresenhamLinePixelIterMut<P> {
         assert!(image.width() >= 1 && image.height() >= 1, "BresenhamLinePixelIterMut does not support empty images");
         BresenhamLinePixelIterMut {
@@ -133,6 +163,15 @@ impl<'a, P: Pixel + 'static> BresenhamLinePixelIterMut<'a, P> {
             image: image
         }
     }
+
+    /// Creates a [`BresenhamLinePixelIterMut`](struct.BresenhamLinePixelIterMut.html) which will iterate over
+    /// the image pixels with coordinates between `start` and `end`.
+    ///
+    /// Returns `ImageProcError::EmptyImage` if `image` is empty.
+    pub fn try_new(image: &'a mut Image<P>, start: (f32, f32), end: (f32, f32)) -> Result<BresenhamLinePixelIterMut<'a, P>> {
+        check_non_empty(image.dimensions())?;
+        Ok(BresenhamLinePixelIterMut::new(image, start, end))
+    }
 }
 
 impl <'a, P: Pixel + 'static> Iterator for BresenhamLinePixelIterMut<'a, P> {
@@ -147,6 +186,87 @@ impl <'a, P: Pixel + 'static> Iterator for BresenhamLinePixelIterMut<'a, P> {
     }
 }
 
+/// Iterates over the coordinates and weights of the pixels in an antialiased line segment,
+/// using [Xiaolin Wu's line algorithm](https://en.wikipedia.org/wiki/Xiaolin_Wu%27s_line_algorithm).
+///
+/// For each step along the major axis of the line two pixels are yielded, one on each side
+/// of the ideal line along the minor axis, with weights in `[0, 1]` summing to 1 that give
+/// how much of the line covers each pixel. Steps are in order of increasing x for shallow
+/// lines and increasing y for steep lines. Coordinates are not clipped to any image.
+///
+/// # Examples
+/// ```
+/// use imageproc::drawing::WuLineIter;
+///
+/// let pixels: Vec<((i32, i32), f32)> = WuLineIter::new((0, 0), (2, 1)).collect();
+/// assert_eq!(pixels, vec![
+///     ((0, 0), 1.0), ((0, 1), 0.0),
+///     ((1, 0), 0.5), ((1, 1), 0.5),
+///     ((2, 1), 1.0), ((2, 2), 0.0)
+/// ]);
+/// ```
+pub struct WuLineIter {
+    is_steep: bool,
+    x: i32,
+    end_x: i32,
+    y: f32,
+    gradient: f32,
+    upper: bool,
+}
+
+impl WuLineIter {
+    /// Creates a [`WuLineIter`](struct.WuLineIter.html) which will iterate over the pixels
+    /// of the antialiased line segment between `start` and `end`.
+    pub fn new(start: (i32, i32), end: (i32, i32)) -> WuLineIter {
+        let (mut x0, mut y0) = (start.0, start.1);
+        let (mut x1, mut y1) = (end.0, end.1);
+
+        let is_steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if is_steep {
+            swap(&mut x0, &mut y0);
+            swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            swap(&mut x0, &mut x1);
+            swap(&mut y0, &mut y1);
+        }
+
+        WuLineIter {
+            is_steep,
+            x: x0,
+            end_x: x1,
+            y: y0 as f32,
+            gradient: (y1 - y0) as f32 / (x1 - x0) as f32,
+            upper: false,
+        }
+    }
+}
+
+impl Iterator for WuLineIter {
+    type Item = ((i32, i32), f32);
+
+    fn next(&mut self) -> Option<((i32, i32), f32)> {
+        if self.x > self.end_x {
+            return None;
+        }
+        let (x, y) = if self.upper {
+            (self.x, self.y as i32 + 1)
+        } else {
+            (self.x, self.y as i32)
+        };
+        let weight = if self.upper { self.y.fract() } else { 1.0 - self.y.fract() };
+
+        if self.upper {
+            self.x += 1;
+            self.y += self.gradient;
+        }
+        self.upper = !self.upper;
+
+        let point = if self.is_steep { (y, x) } else { (x, y) };
+        Some((point, weight))
+    }
+}
+
 /// Draws as much of the line segment between start and end as lies inside the image bounds.
 /// Uses [Bresenham's line drawing algorithm](https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm).
 pub fn draw_line_segment<I>(
@@ -226,92 +346,11 @@ pub fn draw_antialiased_line_segment_mut<I, B>(
     I::Pixel: 'static,
     B: Fn(I::Pixel, I::Pixel, f32) -> I::Pixel,
 {
-    let (mut x0, mut y0) = (start.0, start.1);
-    let (mut x1, mut y1) = (end.0, end.1);
-
-    let is_steep = (y1 - y0).abs() > (x1 - x0).abs();
-
-    if is_steep {
-        if y0 > y1 {
-            swap(&mut x0, &mut x1);
-            swap(&mut y0, &mut y1);
-        }
-        let plotter = Plotter {
-            image: image,
-            transform: |x, y| (y, x),
-            blend: blend,
-        };
-        plot_wu_line(plotter, (y0, x0), (y1, x1), color);
-    } else {
-        if x0 > x1 {
-            swap(&mut x0, &mut x1);
-            swap(&mut y0, &mut y1);
-        }
-        let plotter = Plotter {
-            image: image,
-            transform: |x, y| (x, y),
-            blend: blend,
-        };
-        plot_wu_line(plotter, (x0, y0), (x1, y1), color);
-    };
-}
-
-fn plot_wu_line<I, T, B>(
-    mut plotter: Plotter<I, T, B>,
-    start: (i32, i32),
-    end: (i32, i32),
-    color: I::Pixel,
-) where
-    I: GenericImage,
-    I::Pixel: 'static,
-    T: Fn(i32, i32) -> (i32, i32),
-    B: Fn(I::Pixel, I::Pixel, f32) -> I::Pixel,
-{
-    let dx = end.0 - start.0;
-    let dy = end.1 - start.1;
-    let gradient = dy as f32 / dx as f32;
-    let mut fy = start.1 as f32;
-
-    for x in start.0..(end.0 + 1) {
-        plotter.plot(x, fy as i32, color, 1.0 - fy.fract());
-        plotter.plot(x, fy as i32 + 1, color, fy.fract());
-        fy += gradient;
-    }
-}
-
-struct Plotter<'a, I: 'a, T, B>
-where
-    I: GenericImage,
-    I::Pixel: 'static,
-    T: Fn(i32, i32) -> (i32, i32),
-    B: Fn(I::Pixel, I::Pixel, f32) -> I::Pixel,
-{
-    image: &'a mut I,
-    transform: T,
-    blend: B,
-}
-
-impl<'a, I, T, B> Plotter<'a, I, T, B>
-where
-    I: GenericImage,
-    I::Pixel: 'static,
-    T: Fn(i32, i32) -> (i32, i32),
-    B: Fn(I::Pixel, I::Pixel, f32) -> I::Pixel,
-{
-    fn in_bounds(&self, x: i32, y: i32) -> bool {
-        x >= 0 && x < self.image.width() as i32 && y >= 0 && y < self.image.height() as i32
-    }
-
-    pub fn plot(&mut self, x: i32, y: i32, line_color: I::Pixel, line_weight: f32) {
-        let (x_trans, y_trans) = (self.transform)(x, y);
-        if self.in_bounds(x_trans, y_trans) {
-            let original = self.image.get_pixel(x_trans as u32, y_trans as u32);
-            let blended = (self.blend)(line_color, original, line_weight);
-            self.image.put_pixel(
-                x_trans as u32,
-                y_trans as u32,
-                blended,
-            );
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    for ((x, y), weight) in WuLineIter::new(start, end) {
+        if x >= 0 && x < width && y >= 0 && y < height {
+            let original = image.get_pixel(x as u32, y as u32);
+            image.put_pixel(x as u32, y as u32, blend(color, original, weight));
         }
     }
 }
@@ -321,10 +360,45 @@ mod test {
     use super::*;
     use image::{GrayImage, Luma};
     use test::{Bencher, black_box};
+    use std::cmp::max;
 
     // As draw_line_segment is implemented in terms of BresenhamLineIter we
     // haven't bothered wriing any tests specifically for BresenhamLineIter itself.
 
+    #[test]
+    fn test_bresenham_line_pixel_iter_try_new_empty_image() {
+        use error::ImageProcError;
+
+        let mut empty = GrayImage::new(0, 3);
+        assert!(match BresenhamLinePixelIter::try_new(&empty, (0.0, 0.0), (1.0, 1.0)) {
+            Err(ImageProcError::EmptyImage) => true,
+            _ => false,
+        });
+        assert!(match BresenhamLinePixelIterMut::try_new(&mut empty, (0.0, 0.0), (1.0, 1.0)) {
+            Err(ImageProcError::EmptyImage) => true,
+            _ => false,
+        });
+
+        let image = GrayImage::from_pixel(3, 3, Luma([7]));
+        let pixels: Vec<_> = BresenhamLinePixelIter::try_new(&image, (0.0, 0.0), (2.0, 2.0)).unwrap().collect();
+        assert_eq!(pixels, vec![&Luma([7]); 3]);
+    }
+
+    #[test]
+    fn test_wu_line_iter_is_symmetric_in_end_points() {
+        for &(start, end) in &[((0, 0), (5, 2)), ((1, 4), (3, -3)), ((2, 2), (2, 2)), ((-1, 0), (6, 0))] {
+            let forward: Vec<_> = WuLineIter::new(start, end).collect();
+            let backward: Vec<_> = WuLineIter::new(end, start).collect();
+            assert_eq!(forward, backward);
+
+            let major = max((end.0 - start.0).abs(), (end.1 - start.1).abs());
+            assert_eq!(forward.len() as i32, 2 * (major + 1));
+            for pair in forward.chunks(2) {
+                assert!((pair[0].1 + pair[1].1 - 1.0).abs() < 1e-6);
+            }
+        }
+    }
+
     // Octants for line directions:
     //
     //   \ 5 | 6 /
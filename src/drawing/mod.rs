@@ -8,10 +8,20 @@ pub use self::bezier::{
 
 mod conics;
 pub use self::conics::{
+    RotatedEllipse,
+    EllipticalArc,
     draw_hollow_ellipse,
     draw_hollow_ellipse_mut,
     draw_filled_ellipse,
     draw_filled_ellipse_mut,
+    draw_hollow_rotated_ellipse,
+    draw_hollow_rotated_ellipse_mut,
+    draw_filled_rotated_ellipse,
+    draw_filled_rotated_ellipse_mut,
+    draw_hollow_arc,
+    draw_hollow_arc_mut,
+    draw_filled_arc,
+    draw_filled_arc_mut,
     draw_hollow_circle,
     draw_hollow_circle_mut,
     draw_filled_circle,
@@ -29,6 +39,7 @@ pub use self::line::{
     BresenhamLineIter,
     BresenhamLinePixelIter,
     BresenhamLinePixelIterMut,
+    WuLineIter,
     draw_line_segment,
     draw_line_segment_mut,
     draw_antialiased_line_segment,
@@ -38,8 +49,16 @@ pub use self::line::{
 mod polygon;
 pub use self::polygon::{
     Point,
+    draw_antialiased_polygon,
+    draw_antialiased_polygon_mut,
     draw_convex_polygon,
-    draw_convex_polygon_mut
+    draw_convex_polygon_mut,
+    draw_polygon,
+    draw_polygon_mut,
+    try_draw_convex_polygon,
+    try_draw_convex_polygon_mut,
+    try_draw_polygon,
+    try_draw_polygon_mut
 };
 
 mod rect;
@@ -53,7 +72,8 @@ pub use self::rect::{
 mod text;
 pub use self::text::{
     draw_text,
-    draw_text_mut
+    draw_text_mut,
+    text_size
 };
 
 use image::GenericImage;
@@ -1,16 +1,18 @@
 use image::{GenericImage, ImageBuffer};
 use definitions::Image;
+use error::{ImageProcError, Result};
+use geometry::FillRule;
 use std::cmp::{min, max};
 use std::f32;
 use std::i32;
 use drawing::draw_if_in_bounds;
-use drawing::line::draw_line_segment_mut;
+use drawing::line::{draw_antialiased_line_segment_mut, draw_line_segment_mut};
 
 /// A 2D point.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Point<T: Copy + PartialEq + Eq> {
-    x: T,
-    y: T,
+    pub(crate) x: T,
+    pub(crate) y: T,
 }
 
 impl<T: Copy + PartialEq + Eq> Point<T> {
@@ -25,6 +27,11 @@ impl<T: Copy + PartialEq + Eq> Point<T> {
 /// An implicit edge is added from the last to the first point in the slice.
 ///
 /// Does not validate that input is convex.
+///
+/// # Panics
+/// If the first and last points of `poly` are equal. See
+/// [`try_draw_convex_polygon`](fn.try_draw_convex_polygon.html) for a version that
+/// returns an error instead.
 pub fn draw_convex_polygon<I>(image: &I, poly: &[Point<i32>], color: I::Pixel) -> Image<I::Pixel>
 where
     I: GenericImage,
@@ -36,11 +43,29 @@ where
     out
 }
 
+/// Draws as much of a filled convex polygon as lies within image bounds, as for
+/// [`draw_convex_polygon`](fn.draw_convex_polygon.html).
+///
+/// Returns `ImageProcError::InvalidOptions` if the first and last points of `poly` are equal.
+pub fn try_draw_convex_polygon<I>(image: &I, poly: &[Point<i32>], color: I::Pixel) -> Result<Image<I::Pixel>>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    check_open_path(poly)?;
+    Ok(draw_convex_polygon(image, poly, color))
+}
+
 /// Draws as much of a filled convex polygon as lies within image bounds. The provided
 /// list of points should be an open path, i.e. the first and last points must not be equal.
 /// An implicit edge is added from the last to the first point in the slice.
 ///
 /// Does not validate that input is convex.
+///
+/// # Panics
+/// If the first and last points of `poly` are equal. See
+/// [`try_draw_convex_polygon_mut`](fn.try_draw_convex_polygon_mut.html) for a version
+/// that returns an error instead.
 pub fn draw_convex_polygon_mut<I>(image: &mut I, poly: &[Point<i32>], color: I::Pixel)
 where
     I: GenericImage,
@@ -127,4 +152,361 @@ where
         let end = (edge[1].x as f32, edge[1].y as f32);
         draw_line_segment_mut(image, start, end, color);
     }
-}
\ No newline at end of file
+}
+
+/// Draws as much of a filled convex polygon as lies within image bounds in place, as for
+/// [`draw_convex_polygon_mut`](fn.draw_convex_polygon_mut.html).
+///
+/// Returns `ImageProcError::InvalidOptions` if the first and last points of `poly` are equal.
+pub fn try_draw_convex_polygon_mut<I>(image: &mut I, poly: &[Point<i32>], color: I::Pixel) -> Result<()>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    check_open_path(poly)?;
+    draw_convex_polygon_mut(image, poly, color);
+    Ok(())
+}
+
+/// Draws as much of a filled polygon, including its boundary, as lies within image bounds.
+/// The polygon may be non-convex and self-intersecting, and `rule` decides which of its
+/// regions are filled. The provided list of points should be an open path, i.e. the first
+/// and last points must not be equal. An implicit edge is added from the last to the first
+/// point in the slice.
+///
+/// Pixels are filled if their centers lie inside the polygon as defined by
+/// [`point_in_polygon`](../geometry/fn.point_in_polygon.html), and the polygon's edges
+/// are then drawn as in [`draw_line_segment`](fn.draw_line_segment.html).
+///
+/// # Panics
+/// If the first and last points of `poly` are equal. See
+/// [`try_draw_polygon`](fn.try_draw_polygon.html) for a version that returns an
+/// error instead.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::{draw_polygon, Point};
+/// use imageproc::geometry::FillRule;
+///
+/// // A square traced twice, so that its interior is wound around twice.
+/// let square = [
+///     Point::new(0, 0), Point::new(4, 0), Point::new(4, 4), Point::new(0, 4),
+///     Point::new(0, 0), Point::new(4, 0), Point::new(4, 4), Point::new(0, 4),
+/// ];
+/// let image = GrayImage::new(5, 5);
+///
+/// assert_pixels_eq!(
+///     draw_polygon(&image, &square, FillRule::EvenOdd, Luma([1])),
+///     gray_image!(
+///         1, 1, 1, 1, 1;
+///         1, 0, 0, 0, 1;
+///         1, 0, 0, 0, 1;
+///         1, 0, 0, 0, 1;
+///         1, 1, 1, 1, 1));
+///
+/// assert_pixels_eq!(
+///     draw_polygon(&image, &square, FillRule::NonZero, Luma([1])),
+///     GrayImage::from_pixel(5, 5, Luma([1])));
+/// # }
+/// ```
+pub fn draw_polygon<I>(image: &I, poly: &[Point<i32>], rule: FillRule, color: I::Pixel) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_polygon_mut(&mut out, poly, rule, color);
+    out
+}
+
+/// Draws as much of a filled polygon, including its boundary, as lies within image bounds,
+/// as for [`draw_polygon`](fn.draw_polygon.html).
+///
+/// Returns `ImageProcError::InvalidOptions` if the first and last points of `poly` are equal.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::{try_draw_polygon, Point};
+/// use imageproc::geometry::FillRule;
+///
+/// let image = GrayImage::new(5, 5);
+/// let triangle = [Point::new(0, 0), Point::new(4, 0), Point::new(0, 4)];
+/// assert!(try_draw_polygon(&image, &triangle, FillRule::EvenOdd, Luma([1])).is_ok());
+///
+/// let closed = [Point::new(0, 0), Point::new(4, 0), Point::new(0, 4), Point::new(0, 0)];
+/// assert!(try_draw_polygon(&image, &closed, FillRule::EvenOdd, Luma([1])).is_err());
+/// # }
+/// ```
+pub fn try_draw_polygon<I>(image: &I, poly: &[Point<i32>], rule: FillRule, color: I::Pixel) -> Result<Image<I::Pixel>>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    check_open_path(poly)?;
+    Ok(draw_polygon(image, poly, rule, color))
+}
+
+/// Draws as much of a filled polygon, including its boundary, as lies within image bounds.
+/// See [`draw_polygon`](fn.draw_polygon.html).
+///
+/// # Panics
+/// If the first and last points of `poly` are equal. See
+/// [`try_draw_polygon_mut`](fn.try_draw_polygon_mut.html) for a version that returns
+/// an error instead.
+pub fn draw_polygon_mut<I>(image: &mut I, poly: &[Point<i32>], rule: FillRule, color: I::Pixel)
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    fill_polygon(image, poly, rule, color);
+    for (start, end) in polygon_edges(poly) {
+        let start = (start.x as f32, start.y as f32);
+        let end = (end.x as f32, end.y as f32);
+        draw_line_segment_mut(image, start, end, color);
+    }
+}
+
+/// Draws as much of a filled polygon, including its boundary, as lies within image bounds
+/// in place, as for [`draw_polygon_mut`](fn.draw_polygon_mut.html).
+///
+/// Returns `ImageProcError::InvalidOptions` if the first and last points of `poly` are equal.
+pub fn try_draw_polygon_mut<I>(image: &mut I, poly: &[Point<i32>], rule: FillRule, color: I::Pixel) -> Result<()>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    check_open_path(poly)?;
+    draw_polygon_mut(image, poly, rule, color);
+    Ok(())
+}
+
+/// Draws as much of a filled polygon as lies within image bounds, with antialiased edges.
+/// The polygon is filled as in [`draw_polygon`](fn.draw_polygon.html), and its edges are drawn
+/// as in [`draw_antialiased_line_segment`](fn.draw_antialiased_line_segment.html).
+///
+/// The parameters of blend are (polygon color, original color, polygon weight).
+/// Consider using [`interpolate`](../pixelops/fn.interpolate.html) for blend.
+///
+/// # Panics
+/// If the first and last points of `poly` are equal.
+pub fn draw_antialiased_polygon<I, B>(
+    image: &I,
+    poly: &[Point<i32>],
+    rule: FillRule,
+    color: I::Pixel,
+    blend: B,
+) -> Image<I::Pixel>
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+    B: Fn(I::Pixel, I::Pixel, f32) -> I::Pixel,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_antialiased_polygon_mut(&mut out, poly, rule, color, blend);
+    out
+}
+
+/// Draws as much of a filled polygon as lies within image bounds, with antialiased edges.
+/// See [`draw_antialiased_polygon`](fn.draw_antialiased_polygon.html).
+///
+/// # Panics
+/// If the first and last points of `poly` are equal.
+pub fn draw_antialiased_polygon_mut<I, B>(
+    image: &mut I,
+    poly: &[Point<i32>],
+    rule: FillRule,
+    color: I::Pixel,
+    blend: B,
+) where
+    I: GenericImage,
+    I::Pixel: 'static,
+    B: Fn(I::Pixel, I::Pixel, f32) -> I::Pixel,
+{
+    fill_polygon(image, poly, rule, color);
+    for (start, end) in polygon_edges(poly) {
+        draw_antialiased_line_segment_mut(image, (start.x, start.y), (end.x, end.y), color, &blend);
+    }
+}
+
+// Returns an error if poly is closed, i.e. its first and last points are equal.
+fn check_open_path(poly: &[Point<i32>]) -> Result<()> {
+    match (poly.first(), poly.last()) {
+        (Some(first), Some(last)) if first == last => Err(ImageProcError::InvalidOptions(
+            format!("first point {:?} == last point {:?}", first, last)
+        )),
+        _ => Ok(()),
+    }
+}
+
+// The edges of an open path of points, including the implicit edge from the last
+// point to the first.
+fn polygon_edges<'a>(poly: &'a [Point<i32>]) -> impl Iterator<Item = (Point<i32>, Point<i32>)> + 'a {
+    poly.iter().cloned().zip(poly.iter().cycle().skip(1).cloned())
+}
+
+// Fills the pixels whose centers lie inside poly under rule, one row at a time.
+fn fill_polygon<I>(image: &mut I, poly: &[Point<i32>], rule: FillRule, color: I::Pixel)
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    if poly.is_empty() {
+        return;
+    }
+    if poly[0] == poly[poly.len() - 1] {
+        panic!(
+            "First point {:?} == last point {:?}",
+            poly[0],
+            poly[poly.len() - 1]
+        );
+    }
+
+    let (width, height) = image.dimensions();
+    let y_min = max(0, poly.iter().map(|p| p.y).min().unwrap());
+    let y_max = min(height as i32 - 1, poly.iter().map(|p| p.y).max().unwrap());
+
+    // The x coordinates at which the edges cross each row, and +1 for edges
+    // running down the image or -1 for edges running up it.
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+
+    for y in y_min..y_max + 1 {
+        let yf = y as f32;
+        crossings.clear();
+        for (a, b) in polygon_edges(poly) {
+            if (a.y <= y) != (b.y <= y) {
+                let (ax, ay, bx, by) = (a.x as f32, a.y as f32, b.x as f32, b.y as f32);
+                let x = ax + (yf - ay) * (bx - ax) / (by - ay);
+                crossings.push((x, if a.y <= y { 1 } else { -1 }));
+            }
+        }
+        crossings.sort_by(|c, d| c.0.partial_cmp(&d.0).unwrap());
+
+        // The winding number of a pixel is the sum of the directions of the crossings to
+        // its right, which is minus the sum of those at or to its left.
+        let mut winding = 0;
+        for (i, c) in crossings.iter().enumerate() {
+            winding -= c.1;
+            let inside = match rule {
+                FillRule::NonZero => winding != 0,
+                FillRule::EvenOdd => i % 2 == 0,
+            };
+            if !inside || i + 1 == crossings.len() {
+                continue;
+            }
+            // Pixels with c.0 <= x < next.0.
+            let from = max(0, c.0.ceil() as i32);
+            let to = min(width as i32, crossings[i + 1].0.ceil() as i32);
+            for x in from..to {
+                image.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geometry::point_in_polygon;
+    use image::{GrayImage, Luma};
+    use pixelops::interpolate;
+
+    fn pentagram() -> Vec<Point<i32>> {
+        vec![
+            Point::new(10, 0),
+            Point::new(16, 19),
+            Point::new(0, 7),
+            Point::new(20, 7),
+            Point::new(4, 19),
+        ]
+    }
+
+    #[test]
+    fn test_fill_polygon_matches_point_in_polygon() {
+        let star = pentagram();
+        let vertices: Vec<(f32, f32)> = star.iter().map(|p| (p.x as f32, p.y as f32)).collect();
+
+        for &rule in &[FillRule::EvenOdd, FillRule::NonZero] {
+            let mut image = GrayImage::new(22, 22);
+            fill_polygon(&mut image, &star, rule, Luma([1]));
+            for (x, y, p) in image.enumerate_pixels() {
+                let inside = point_in_polygon((x as f32, y as f32), &vertices, rule);
+                assert_eq!(p[0] == 1, inside, "({}, {}) with {:?}", x, y, rule);
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_draw_polygon_rejects_closed_path() {
+        let image = GrayImage::new(5, 5);
+        let mut closed = vec![Point::new(0, 0), Point::new(4, 0), Point::new(0, 4)];
+        closed.push(closed[0]);
+        let open = &closed[..3];
+
+        assert_pixels_eq!(
+            try_draw_polygon(&image, open, FillRule::EvenOdd, Luma([1])).unwrap(),
+            draw_polygon(&image, open, FillRule::EvenOdd, Luma([1]))
+        );
+        assert_pixels_eq!(
+            try_draw_convex_polygon(&image, open, Luma([1])).unwrap(),
+            draw_convex_polygon(&image, open, Luma([1]))
+        );
+
+        let mut mutated = image.clone();
+        assert!(try_draw_polygon(&image, &closed, FillRule::NonZero, Luma([1])).is_err());
+        assert!(try_draw_polygon_mut(&mut mutated, &closed, FillRule::NonZero, Luma([1])).is_err());
+        assert!(try_draw_convex_polygon(&image, &closed, Luma([1])).is_err());
+        assert!(try_draw_convex_polygon_mut(&mut mutated, &closed, Luma([1])).is_err());
+        assert_pixels_eq!(mutated, image);
+
+        assert!(try_draw_polygon(&image, &[], FillRule::EvenOdd, Luma([1])).is_ok());
+    }
+
+    #[test]
+    fn test_draw_polygon_fill_rules() {
+        let star = pentagram();
+        let image = GrayImage::new(22, 22);
+
+        // The central pentagon is wound around twice.
+        let even_odd = draw_polygon(&image, &star, FillRule::EvenOdd, Luma([1]));
+        let non_zero = draw_polygon(&image, &star, FillRule::NonZero, Luma([1]));
+        assert_eq!(even_odd.get_pixel(10, 11)[0], 0);
+        assert_eq!(non_zero.get_pixel(10, 11)[0], 1);
+        // A point of the star.
+        assert_eq!(even_odd.get_pixel(10, 3)[0], 1);
+        assert_eq!(non_zero.get_pixel(10, 3)[0], 1);
+        // Outside the star.
+        assert_eq!(non_zero.get_pixel(1, 18)[0], 0);
+    }
+
+    #[test]
+    fn test_draw_polygon_partially_out_of_bounds() {
+        let triangle = [Point::new(-5, -5), Point::new(7, 1), Point::new(1, 7)];
+        let drawn = draw_polygon(&GrayImage::new(6, 6), &triangle, FillRule::NonZero, Luma([1]));
+        assert_eq!(drawn.get_pixel(0, 0)[0], 1);
+        assert_eq!(drawn.get_pixel(3, 3)[0], 1);
+        assert_eq!(drawn.get_pixel(5, 5)[0], 0);
+    }
+
+    #[test]
+    fn test_draw_antialiased_polygon() {
+        let triangle = [Point::new(1, 1), Point::new(18, 4), Point::new(3, 15)];
+        let image = GrayImage::from_pixel(20, 20, Luma([0]));
+        let drawn = draw_antialiased_polygon(&image, &triangle, FillRule::NonZero, Luma([200]), interpolate);
+
+        assert_eq!(drawn.get_pixel(6, 6)[0], 200);
+        assert_eq!(drawn.get_pixel(18, 18)[0], 0);
+        // Some pixels along the sloped edges are partially covered.
+        assert!(drawn.pixels().any(|p| p[0] > 0 && p[0] < 200));
+    }
+}
@@ -1,14 +1,68 @@
 
-use image::{GenericImage, ImageBuffer, Pixel};
+use image::{GenericImage, GenericImageView, ImageBuffer, Pixel};
 use definitions::{Clamp, Image};
 use conv::ValueInto;
+use std::cmp::max;
 use std::f32;
 use std::i32;
 
-use pixelops::weighted_sum;
+use pixelops::{has_alpha, weighted_sum};
 use rusttype::{Font, Scale, point, PositionedGlyph};
 
+/// Lays out `text` on a single line, with the top of the line at `y = 0`.
+fn layout_glyphs<'a>(scale: Scale, font: &'a Font<'a>, text: &str) -> Vec<PositionedGlyph<'a>> {
+    let v_metrics = font.v_metrics(scale);
+    let offset = point(0.0, v_metrics.ascent);
+    font.layout(text, scale, offset).collect()
+}
+
+/// Composites `color` onto `pixel` with the given glyph coverage.
+///
+/// For pixel types with an alpha channel the alpha of `color` is scaled by `coverage`
+/// and the result is alpha-blended onto `pixel`. For all other pixel types `pixel`
+/// and `color` are linearly interpolated.
+fn composite_glyph_pixel<P>(pixel: P, color: P, coverage: f32) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    if has_alpha::<P>() {
+        let glyph_color = color.map_with_alpha(|c| c, |a| {
+            let a: f32 = a.value_into().unwrap();
+            P::Subpixel::clamp(a * coverage)
+        });
+        let mut blended = pixel;
+        blended.blend(&glyph_color);
+        blended
+    } else {
+        weighted_sum(pixel, color, 1.0 - coverage, coverage)
+    }
+}
+
+/// Returns the width and height of the region modified by drawing `text` with
+/// `draw_text_mut` at position `(0, 0)`, i.e. drawing at `(x, y)` only affects
+/// pixels with x-coordinate in `[x, x + width)` and y-coordinate in `[y, y + height)`.
+///
+/// Returns `(0, 0)` if no glyph produces any visible pixels.
+pub fn text_size<'a>(scale: Scale, font: &'a Font<'a>, text: &str) -> (u32, u32) {
+    let mut width = 0;
+    let mut height = 0;
+    for g in layout_glyphs(scale, font, text) {
+        if let Some(bb) = g.pixel_bounding_box() {
+            width = max(width, bb.max.x);
+            height = max(height, bb.max.y);
+        }
+    }
+    (width as u32, height as u32)
+}
+
 /// Draws colored text on an image in place. `scale` is augmented font scaling on both the x and y axis (in pixels). Note that this function *does not* support newlines, you must do this manually
+///
+/// For pixel types with an alpha channel each glyph pixel is alpha-blended onto the
+/// image, using the alpha of `color` scaled by the glyph coverage. For other pixel types
+/// the existing pixel is interpolated towards `color` by the glyph coverage.
+///
+/// Use [`text_size`](fn.text_size.html) to measure the extent of the text before drawing it.
 pub fn draw_text_mut<'a, I>(
     image: &'a mut I,
     color: I::Pixel,
@@ -21,10 +75,10 @@ pub fn draw_text_mut<'a, I>(
     I: GenericImage,
     <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
 {
-    let v_metrics = font.v_metrics(scale);
-    let offset = point(0.0, v_metrics.ascent);
+    let glyphs = layout_glyphs(scale, font, text);
 
-    let glyphs: Vec<PositionedGlyph> = font.layout(text, scale, offset).collect();
+    let image_width = image.width() as i32;
+    let image_height = image.height() as i32;
 
     for g in glyphs {
         if let Some(bb) = g.pixel_bounding_box() {
@@ -35,22 +89,21 @@ pub fn draw_text_mut<'a, I>(
                 let image_x = gx + x as i32;
                 let image_y = gy + y as i32;
 
-                let image_width = image.width() as i32;
-                let image_height = image.height() as i32;
-
                 if image_x >= 0 && image_x < image_width && image_y >= 0 && image_y < image_height {
                     let pixel = image.get_pixel(image_x as u32, image_y as u32);
-                    let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
-                    image.put_pixel(image_x as u32, image_y as u32, weighted_color);
+                    let composited = composite_glyph_pixel(pixel, color, gv);
+                    image.put_pixel(image_x as u32, image_y as u32, composited);
                 }
             })
         }
     }
 }
 
-/// Draws colored text on an image in place. `scale` is augmented font scaling on both the x and y axis (in pixels). Note that this function *does not* support newlines, you must do this manually
+/// Draws colored text on a new copy of an image. `scale` is augmented font scaling on both the x and y axis (in pixels). Note that this function *does not* support newlines, you must do this manually
+///
+/// See [`draw_text_mut`](fn.draw_text_mut.html) for details of how glyphs are composited.
 pub fn draw_text<'a, I>(
-    image: &'a mut I,
+    image: &'a I,
     color: I::Pixel,
     x: u32,
     y: u32,
@@ -59,7 +112,7 @@ pub fn draw_text<'a, I>(
     text: &'a str,
 ) -> Image<I::Pixel>
 where
-    I: GenericImage,
+    I: GenericImageView,
     <I::Pixel as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
     I::Pixel: 'static,
 {
@@ -68,3 +121,97 @@ where
     draw_text_mut(&mut out, color, x, y, scale, font, text);
     out
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+    use rusttype::FontCollection;
+
+    fn test_font() -> Font<'static> {
+        let bytes = include_bytes!("../../examples/DejaVuSans.ttf") as &[u8];
+        FontCollection::from_bytes(bytes).unwrap().into_font().unwrap()
+    }
+
+    #[test]
+    fn test_text_size_empty() {
+        let font = test_font();
+        assert_eq!(text_size(Scale::uniform(20.0), &font, ""), (0, 0));
+    }
+
+    #[test]
+    fn test_text_size_grows_with_text() {
+        let font = test_font();
+        let scale = Scale::uniform(20.0);
+        let (w1, h1) = text_size(scale, &font, "H");
+        let (w2, h2) = text_size(scale, &font, "HH");
+        assert!(w1 > 0 && h1 > 0);
+        assert!(w2 > w1);
+        assert_eq!(h1, h2);
+    }
+
+    #[test]
+    fn test_draw_text_stays_within_text_size() {
+        let font = test_font();
+        let scale = Scale::uniform(16.0);
+        let text = "Hello";
+        let (x, y) = (5, 7);
+        let (w, h) = text_size(scale, &font, text);
+
+        let image = GrayImage::new(80, 40);
+        let drawn = draw_text(&image, Luma([255u8]), x, y, scale, &font, text);
+
+        let mut any_drawn = false;
+        for (px, py, p) in drawn.enumerate_pixels() {
+            if p[0] > 0 {
+                any_drawn = true;
+                assert!(px >= x && px < x + w, "x = {} outside [{}, {})", px, x, x + w);
+                assert!(py >= y && py < y + h, "y = {} outside [{}, {})", py, y, y + h);
+            }
+        }
+        assert!(any_drawn);
+    }
+
+    #[test]
+    fn test_draw_text_rgb_interpolates_towards_color() {
+        let font = test_font();
+        let image = RgbImage::from_pixel(40, 30, Rgb([0u8, 0u8, 0u8]));
+        let drawn = draw_text(&image, Rgb([0u8, 200u8, 0u8]), 2, 2, Scale::uniform(20.0), &font, "A");
+
+        for p in drawn.pixels() {
+            assert_eq!(p[0], 0);
+            assert_eq!(p[2], 0);
+        }
+        assert!(drawn.pixels().any(|p| p[1] > 150));
+    }
+
+    #[test]
+    fn test_draw_text_rgba_blends_alpha() {
+        let font = test_font();
+        let image = RgbaImage::new(40, 30);
+        let color = Rgba([255u8, 255u8, 255u8, 255u8]);
+        let drawn = draw_text(&image, color, 2, 2, Scale::uniform(20.0), &font, "A");
+
+        let mut any_drawn = false;
+        for p in drawn.pixels() {
+            if p[3] > 0 {
+                any_drawn = true;
+                // Blending onto a fully transparent background keeps the text color and
+                // takes its alpha from the glyph coverage.
+                assert_eq!(&p.data[..3], &[255u8, 255u8, 255u8]);
+            } else {
+                assert_eq!(*p, Rgba([0u8, 0u8, 0u8, 0u8]));
+            }
+        }
+        assert!(any_drawn);
+    }
+
+    #[test]
+    fn test_draw_text_rgba_respects_color_alpha() {
+        let font = test_font();
+        let image = RgbaImage::from_pixel(40, 30, Rgba([0u8, 0u8, 0u8, 255u8]));
+        let color = Rgba([255u8, 255u8, 255u8, 0u8]);
+        let drawn = draw_text(&image, color, 2, 2, Scale::uniform(20.0), &font, "A");
+        assert_pixels_eq!(drawn, image);
+    }
+}
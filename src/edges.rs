@@ -29,6 +29,9 @@ use filter::gaussian_blur_f32;
 /// of the horizontal and vertical Sobel gradients at `p`.
 pub fn canny(image: &GrayImage, low_threshold: f32, high_threshold: f32) -> GrayImage {
     assert!(high_threshold >= low_threshold);
+    if image.width() < 3 || image.height() < 3 {
+        return GrayImage::new(image.width(), image.height());
+    }
     // Heavily based on the implementation proposed by wikipedia.
     // 1. Gaussian blur.
     const SIGMA: f32 = 1.4;
@@ -144,9 +147,20 @@ fn hysteresis(
                         (nx - 1, ny - 1),
                         (nx - 1, ny),
                         (nx - 1, ny + 1),
+                        (nx, ny - 1),
+                        (nx + 1, ny - 1),
                     ];
 
                     for neighbor_idx in &neighbor_indices {
+                        // The output border is never marked as an edge, matching
+                        // the non-maximum suppression step.
+                        if neighbor_idx.0 == 0 || neighbor_idx.1 == 0
+                            || neighbor_idx.0 >= input.width() - 1
+                            || neighbor_idx.1 >= input.height() - 1
+                        {
+                            continue;
+                        }
+
                         let in_neighbor = *input.get_pixel(neighbor_idx.0, neighbor_idx.1);
                         let out_neighbor = *out.get_pixel(neighbor_idx.0, neighbor_idx.1);
                         if in_neighbor[0] >= low_thresh && out_neighbor[0] == 0 {
@@ -181,6 +195,46 @@ mod test {
         image
     }
 
+    #[test]
+    fn test_canny_tiny_image() {
+        let image = GrayImage::new(2, 5);
+        let edges = canny(&image, 10.0, 20.0);
+        assert_eq!(edges.dimensions(), (2, 5));
+        assert!(edges.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_canny_constant_image_has_no_edges() {
+        let image = GrayImage::from_pixel(20, 20, Luma([100]));
+        let edges = canny(&image, 10.0, 20.0);
+        assert!(edges.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_canny_traces_square_boundary() {
+        let mut image = GrayImage::new(40, 40);
+        draw_filled_rect_mut(&mut image, Rect::at(10, 10).of_size(20, 20), Luma([255]));
+        let edges = canny(&image, 50.0, 100.0);
+
+        // All edge pixels lie close to the square's boundary.
+        for (x, y, p) in edges.enumerate_pixels() {
+            if p[0] == 0 {
+                continue;
+            }
+            assert_eq!(p[0], 255);
+            let near_vertical = (x as i32 - 10).abs() <= 1 || (x as i32 - 29).abs() <= 1;
+            let near_horizontal = (y as i32 - 10).abs() <= 1 || (y as i32 - 29).abs() <= 1;
+            assert!(near_vertical || near_horizontal, "unexpected edge at ({}, {})", x, y);
+        }
+        // Each side is traced along its whole length, away from the corners.
+        for t in 13..27 {
+            assert!((8..13).any(|x| edges.get_pixel(x, t)[0] == 255));
+            assert!((27..32).any(|x| edges.get_pixel(x, t)[0] == 255));
+            assert!((8..13).any(|y| edges.get_pixel(t, y)[0] == 255));
+            assert!((27..32).any(|y| edges.get_pixel(t, y)[0] == 255));
+        }
+    }
+
     #[bench]
     fn bench_canny(b: &mut test::Bencher) {
         let image = edge_detect_bench_image(250, 250);
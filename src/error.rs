@@ -0,0 +1,125 @@
+//! Errors returned by functions in this crate when given invalid inputs.
+//!
+//! Most checks, such as comparing the dimensions of two images, take constant time, so
+//! functions which perform them have no unchecked variants. Checks which would otherwise
+//! be repeated in an inner loop can be skipped by calling an `unsafe` `_unchecked` variant,
+//! such as [`HaarFeature::evaluate_at_unchecked`](../haar/struct.HaarFeature.html#method.evaluate_at_unchecked).
+
+use rect::Rect;
+use std::error::Error;
+use std::fmt;
+use std::result;
+
+/// The ways in which the inputs to a function in this crate can be invalid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageProcError {
+    /// An image with zero width or height was provided where a non-empty image is required.
+    EmptyImage,
+    /// A kernel or template was too large for the image it was applied to.
+    KernelTooLarge {
+        /// The (width, height) of the kernel.
+        kernel: (u32, u32),
+        /// The (width, height) of the image.
+        image: (u32, u32),
+    },
+    /// Two images, or an image and a model built from earlier images, have different dimensions.
+    DimensionMismatch {
+        /// The (width, height) required.
+        expected: (u32, u32),
+        /// The (width, height) provided.
+        actual: (u32, u32),
+    },
+    /// A region extends outside the image it is read from.
+    OutOfBounds {
+        /// The region read.
+        region: Rect,
+        /// The (width, height) of the image.
+        image: (u32, u32),
+    },
+    /// The provided options are invalid, or incompatible with the input. The message
+    /// describes every problem found.
+    InvalidOptions(String),
+}
+
+/// The result of a function in this crate that validates its inputs.
+pub type Result<T> = result::Result<T, ImageProcError>;
+
+impl fmt::Display for ImageProcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImageProcError::EmptyImage => write!(f, "image must be non-empty"),
+            ImageProcError::KernelTooLarge { kernel, image } => write!(
+                f,
+                "kernel of size {}x{} is too large for image of size {}x{}",
+                kernel.0, kernel.1, image.0, image.1
+            ),
+            ImageProcError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "expected dimensions {}x{}, found {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            ImageProcError::OutOfBounds { region, image } => write!(
+                f,
+                "region of size {}x{} at ({}, {}) is outside image of size {}x{}",
+                region.width(), region.height(), region.left(), region.top(), image.0, image.1
+            ),
+            ImageProcError::InvalidOptions(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ImageProcError {
+    fn description(&self) -> &str {
+        match *self {
+            ImageProcError::EmptyImage => "empty image",
+            ImageProcError::KernelTooLarge { .. } => "kernel too large",
+            ImageProcError::DimensionMismatch { .. } => "dimension mismatch",
+            ImageProcError::OutOfBounds { .. } => "region out of bounds",
+            ImageProcError::InvalidOptions(ref message) => message,
+        }
+    }
+}
+
+/// Returns an error if either dimension of `image` is zero.
+pub(crate) fn check_non_empty(image: (u32, u32)) -> Result<()> {
+    if image.0 == 0 || image.1 == 0 {
+        return Err(ImageProcError::EmptyImage);
+    }
+    Ok(())
+}
+
+/// Returns an error if `actual` is not equal to `expected`.
+pub(crate) fn check_dimensions(expected: (u32, u32), actual: (u32, u32)) -> Result<()> {
+    if expected != actual {
+        return Err(ImageProcError::DimensionMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            ImageProcError::KernelTooLarge { kernel: (5, 4), image: (5, 5) }.to_string(),
+            "kernel of size 5x4 is too large for image of size 5x5"
+        );
+        assert_eq!(
+            ImageProcError::OutOfBounds { region: Rect::at(1, 2).of_size(3, 4), image: (3, 3) }.to_string(),
+            "region of size 3x4 at (1, 2) is outside image of size 3x3"
+        );
+        assert_eq!(
+            check_dimensions((2, 3), (3, 2)).unwrap_err().to_string(),
+            "expected dimensions 2x3, found 3x2"
+        );
+    }
+
+    #[test]
+    fn test_check_non_empty() {
+        assert_eq!(check_non_empty((1, 1)), Ok(()));
+        assert_eq!(check_non_empty((0, 1)), Err(ImageProcError::EmptyImage));
+        assert_eq!(check_non_empty((1, 0)), Err(ImageProcError::EmptyImage));
+    }
+}
@@ -0,0 +1,340 @@
+//! Evaluation of object detectors against ground truth bounding boxes.
+//!
+//! Detections are matched to ground truth following the [PASCAL VOC] protocol:
+//! detections are considered in decreasing order of score, and each is matched to the
+//! unmatched ground truth box it overlaps most, provided that their intersection over union
+//! is at least a given threshold. Matched detections are true positives, unmatched detections
+//! are false positives, and unmatched ground truth boxes are false negatives.
+//!
+//! [PASCAL VOC]: http://host.robots.ox.ac.uk/pascal/VOC/voc2012/htmldoc/devkit_doc.html#SECTION00044000000000000000
+
+use rect::Rect;
+use std::cmp::Ordering;
+
+/// A bounding box predicted by a detector, with its confidence score.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Detection {
+    /// The predicted bounding box.
+    pub rect: Rect,
+    /// Confidence of the detector in this prediction. Higher is more confident.
+    pub score: f32,
+}
+
+/// The detections and ground truth boxes for a single image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvaluationImage {
+    /// Boxes predicted by the detector being evaluated.
+    pub detections: Vec<Detection>,
+    /// The true locations of objects in the image.
+    pub ground_truth: Vec<Rect>,
+}
+
+/// Returns the index of the ground truth box matched to each detection, or `None`
+/// for detections which are false positives. The result has the same order as `detections`.
+///
+/// # Examples
+/// ```
+/// use imageproc::evaluation::{match_detections, Detection};
+/// use imageproc::rect::Rect;
+///
+/// let ground_truth = [Rect::at(0, 0).of_size(10, 10)];
+/// let detections = [
+///     Detection { rect: Rect::at(1, 0).of_size(10, 10), score: 0.5 },
+///     Detection { rect: Rect::at(0, 1).of_size(10, 10), score: 0.9 },
+///     Detection { rect: Rect::at(50, 50).of_size(10, 10), score: 0.7 },
+/// ];
+///
+/// // The highest scoring detection claims the ground truth box, so the
+/// // other overlapping detection is a duplicate and counts as a false positive.
+/// assert_eq!(match_detections(&detections, &ground_truth, 0.5), vec![None, Some(0), None]);
+/// ```
+pub fn match_detections(detections: &[Detection], ground_truth: &[Rect], iou_threshold: f32) -> Vec<Option<usize>> {
+    let mut matches = vec![None; detections.len()];
+    let mut claimed = vec![false; ground_truth.len()];
+
+    for d in indices_by_decreasing_score(detections) {
+        let mut best: Option<(usize, f32)> = None;
+        for (g, truth) in ground_truth.iter().enumerate() {
+            if claimed[g] {
+                continue;
+            }
+            let overlap = detections[d].rect.iou(*truth);
+            if overlap >= iou_threshold && best.map_or(true, |(_, b)| overlap > b) {
+                best = Some((g, overlap));
+            }
+        }
+        if let Some((g, _)) = best {
+            claimed[g] = true;
+            matches[d] = Some(g);
+        }
+    }
+
+    matches
+}
+
+/// Numbers of true positives, false positives and false negatives.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct DetectionCounts {
+    /// Detections matched to a ground truth box.
+    pub true_positives: usize,
+    /// Detections not matched to any ground truth box.
+    pub false_positives: usize,
+    /// Ground truth boxes not matched to any detection.
+    pub false_negatives: usize,
+}
+
+impl DetectionCounts {
+    /// The fraction of detections which are correct, or 1 if there are no detections.
+    pub fn precision(&self) -> f32 {
+        ratio_or_one(self.true_positives, self.true_positives + self.false_positives)
+    }
+
+    /// The fraction of ground truth boxes which are detected, or 1 if there are no ground truth boxes.
+    pub fn recall(&self) -> f32 {
+        ratio_or_one(self.true_positives, self.true_positives + self.false_negatives)
+    }
+
+    /// The harmonic mean of precision and recall, or 0 if both are 0.
+    pub fn f1_score(&self) -> f32 {
+        let (p, r) = (self.precision(), self.recall());
+        if p + r == 0.0 { 0.0 } else { 2.0 * p * r / (p + r) }
+    }
+}
+
+/// Counts the true positives, false positives and false negatives over all `images`,
+/// ignoring detections with score less than `score_threshold`.
+pub fn count_detections(images: &[EvaluationImage], iou_threshold: f32, score_threshold: f32) -> DetectionCounts {
+    let mut counts = DetectionCounts::default();
+    for image in images {
+        let detections: Vec<Detection> = image.detections.iter()
+            .filter(|d| d.score >= score_threshold)
+            .cloned()
+            .collect();
+        let matched = match_detections(&detections, &image.ground_truth, iou_threshold)
+            .into_iter()
+            .filter(|m| m.is_some())
+            .count();
+        counts.true_positives += matched;
+        counts.false_positives += detections.len() - matched;
+        counts.false_negatives += image.ground_truth.len() - matched;
+    }
+    counts
+}
+
+/// A point on a precision-recall curve.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PrecisionRecallPoint {
+    /// Detections with at least this score are kept.
+    pub score_threshold: f32,
+    /// Precision of the kept detections.
+    pub precision: f32,
+    /// Recall of the kept detections.
+    pub recall: f32,
+}
+
+/// Returns the precision and recall of the detections in `images` for every distinct
+/// score threshold, in decreasing order of threshold.
+///
+/// Recall is 0 at every point if there are no ground truth boxes.
+pub fn precision_recall_curve(images: &[EvaluationImage], iou_threshold: f32) -> Vec<PrecisionRecallPoint> {
+    let mut scored: Vec<(f32, bool)> = vec![];
+    let mut total_ground_truth = 0;
+    for image in images {
+        let matches = match_detections(&image.detections, &image.ground_truth, iou_threshold);
+        scored.extend(image.detections.iter().zip(matches).map(|(d, m)| (d.score, m.is_some())));
+        total_ground_truth += image.ground_truth.len();
+    }
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let mut curve: Vec<PrecisionRecallPoint> = vec![];
+    let (mut true_positives, mut false_positives) = (0, 0);
+    for (i, &(score, is_match)) in scored.iter().enumerate() {
+        if is_match {
+            true_positives += 1;
+        } else {
+            false_positives += 1;
+        }
+        // Detections with equal scores are all kept or all discarded.
+        if i + 1 < scored.len() && scored[i + 1].0 == score {
+            continue;
+        }
+        curve.push(PrecisionRecallPoint {
+            score_threshold: score,
+            precision: true_positives as f32 / (true_positives + false_positives) as f32,
+            recall: if total_ground_truth == 0 { 0.0 } else { true_positives as f32 / total_ground_truth as f32 },
+        });
+    }
+    curve
+}
+
+/// Returns the area under a precision-recall curve, after replacing the precision at
+/// each recall by the maximum precision at any higher recall (the all-point interpolated
+/// average precision used since PASCAL VOC 2010).
+///
+/// `curve` must be in increasing order of recall, as returned by
+/// [`precision_recall_curve`](fn.precision_recall_curve.html).
+/// Returns 0 for an empty curve.
+///
+/// # Examples
+/// ```
+/// use imageproc::evaluation::{average_precision, precision_recall_curve, Detection, EvaluationImage};
+/// use imageproc::rect::Rect;
+///
+/// let a = Rect::at(0, 0).of_size(10, 10);
+/// let b = Rect::at(20, 0).of_size(10, 10);
+/// let c = Rect::at(40, 0).of_size(10, 10);
+///
+/// let image = EvaluationImage {
+///     ground_truth: vec![a, b],
+///     detections: vec![
+///         Detection { rect: a, score: 0.9 },
+///         Detection { rect: c, score: 0.8 },
+///         Detection { rect: b, score: 0.7 },
+///     ],
+/// };
+///
+/// // Recall 0.5 at precision 1, then recall 1 at precision 2/3.
+/// let curve = precision_recall_curve(&[image], 0.5);
+/// assert!((average_precision(&curve) - (0.5 * 1.0 + 0.5 * 2.0 / 3.0)).abs() < 1e-6);
+/// ```
+pub fn average_precision(curve: &[PrecisionRecallPoint]) -> f32 {
+    let mut interpolated: Vec<f32> = curve.iter().map(|p| p.precision).collect();
+    for i in (0..interpolated.len().saturating_sub(1)).rev() {
+        interpolated[i] = interpolated[i].max(interpolated[i + 1]);
+    }
+
+    let mut area = 0.0;
+    let mut previous_recall = 0.0;
+    for (point, precision) in curve.iter().zip(interpolated) {
+        area += (point.recall - previous_recall) * precision;
+        previous_recall = point.recall;
+    }
+    area
+}
+
+/// Returns the mean over object classes of the [average precision](fn.average_precision.html)
+/// of each class. `classes[c]` contains the detections and ground truth for class `c` in each image.
+/// Returns 0 if `classes` is empty.
+pub fn mean_average_precision(classes: &[Vec<EvaluationImage>], iou_threshold: f32) -> f32 {
+    if classes.is_empty() {
+        return 0.0;
+    }
+    let total: f32 = classes.iter()
+        .map(|images| average_precision(&precision_recall_curve(images, iou_threshold)))
+        .sum();
+    total / classes.len() as f32
+}
+
+fn indices_by_decreasing_score(detections: &[Detection]) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..detections.len()).collect();
+    indices.sort_by(|&a, &b| {
+        detections[b].score.partial_cmp(&detections[a].score).unwrap_or(Ordering::Equal)
+    });
+    indices
+}
+
+fn ratio_or_one(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 { 1.0 } else { numerator as f32 / denominator as f32 }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn detection(x: i32, y: i32, score: f32) -> Detection {
+        Detection { rect: Rect::at(x, y).of_size(10, 10), score }
+    }
+
+    fn truth(x: i32, y: i32) -> Rect {
+        Rect::at(x, y).of_size(10, 10)
+    }
+
+    #[test]
+    fn test_match_detections_prefers_best_overlap() {
+        let ground_truth = [truth(0, 0), truth(4, 0)];
+        // Overlaps the second box more than the first.
+        let detections = [detection(3, 0, 1.0)];
+        assert_eq!(match_detections(&detections, &ground_truth, 0.3), vec![Some(1)]);
+    }
+
+    #[test]
+    fn test_match_detections_respects_threshold() {
+        let ground_truth = [truth(0, 0)];
+        // IoU of 50 / 150.
+        let detections = [detection(5, 0, 1.0)];
+        assert_eq!(match_detections(&detections, &ground_truth, 0.3), vec![Some(0)]);
+        assert_eq!(match_detections(&detections, &ground_truth, 0.5), vec![None]);
+    }
+
+    #[test]
+    fn test_count_detections() {
+        let images = vec![
+            EvaluationImage {
+                detections: vec![detection(0, 0, 0.9), detection(50, 50, 0.2)],
+                ground_truth: vec![truth(0, 0), truth(20, 20)],
+            },
+            EvaluationImage {
+                detections: vec![detection(0, 0, 0.8)],
+                ground_truth: vec![],
+            },
+        ];
+
+        let counts = count_detections(&images, 0.5, 0.0);
+        assert_eq!(counts, DetectionCounts { true_positives: 1, false_positives: 2, false_negatives: 1 });
+        assert_eq!(counts.precision(), 1.0 / 3.0);
+        assert_eq!(counts.recall(), 0.5);
+        assert!((counts.f1_score() - 0.4).abs() < 1e-6);
+
+        let counts = count_detections(&images, 0.5, 0.5);
+        assert_eq!(counts, DetectionCounts { true_positives: 1, false_positives: 1, false_negatives: 1 });
+    }
+
+    #[test]
+    fn test_empty_counts() {
+        let counts = DetectionCounts::default();
+        assert_eq!(counts.precision(), 1.0);
+        assert_eq!(counts.recall(), 1.0);
+        assert_eq!(counts.f1_score(), 1.0);
+    }
+
+    #[test]
+    fn test_precision_recall_curve_merges_equal_scores() {
+        let images = vec![EvaluationImage {
+            detections: vec![detection(0, 0, 0.5), detection(50, 0, 0.5), detection(20, 0, 0.3)],
+            ground_truth: vec![truth(0, 0), truth(20, 0)],
+        }];
+        let curve = precision_recall_curve(&images, 0.5);
+        assert_eq!(curve, vec![
+            PrecisionRecallPoint { score_threshold: 0.5, precision: 0.5, recall: 0.5 },
+            PrecisionRecallPoint { score_threshold: 0.3, precision: 2.0 / 3.0, recall: 1.0 },
+        ]);
+        // Precision at recall 0.5 is interpolated up to 2/3.
+        assert_eq!(average_precision(&curve), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_perfect_detector_has_unit_average_precision() {
+        let images: Vec<EvaluationImage> = (0..5)
+            .map(|i| EvaluationImage {
+                detections: vec![detection(i, 0, 0.1 * i as f32), detection(30, 30, 0.05)],
+                ground_truth: vec![truth(i, 0), truth(30, 30)],
+            })
+            .collect();
+        let curve = precision_recall_curve(&images, 0.5);
+        assert_eq!(average_precision(&curve), 1.0);
+    }
+
+    #[test]
+    fn test_mean_average_precision() {
+        let perfect = vec![EvaluationImage {
+            detections: vec![detection(0, 0, 1.0)],
+            ground_truth: vec![truth(0, 0)],
+        }];
+        let missed = vec![EvaluationImage {
+            detections: vec![detection(50, 50, 1.0)],
+            ground_truth: vec![truth(0, 0)],
+        }];
+        assert_eq!(mean_average_precision(&[perfect, missed], 0.5), 0.5);
+        assert_eq!(mean_average_precision(&[], 0.5), 0.0);
+    }
+}
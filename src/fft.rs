@@ -0,0 +1,196 @@
+//! Fast Fourier transforms, used to speed up correlation with large kernels.
+
+use num::Complex;
+use std::f64::consts::PI;
+
+/// Returns true if computing the valid correlation of an image of the given size with a
+/// kernel of the given size is expected to be faster using [`correlate`](fn.correlate.html)
+/// than by summing products directly.
+pub(crate) fn fft_is_faster(image: (u32, u32), kernel: (u32, u32)) -> bool {
+    let out_width = image.0.saturating_sub(kernel.0) as f64 + 1.0;
+    let out_height = image.1.saturating_sub(kernel.1) as f64 + 1.0;
+    let direct_cost = out_width * out_height * kernel.0 as f64 * kernel.1 as f64;
+
+    let n = (image.0 as usize).next_power_of_two() as f64 * (image.1 as usize).next_power_of_two() as f64;
+    // Three transforms, each costing roughly n * log2(n) butterflies,
+    // where a butterfly is several times as expensive as a multiply-add.
+    let fft_cost = 20.0 * n * n.log2();
+
+    direct_cost > fft_cost
+}
+
+/// Returns the valid correlation of `image` with `kernel`, i.e. the image of size
+/// `(width - k_width + 1, height - k_height + 1)` whose value at `(x, y)` is the sum over
+/// all kernel positions `(i, j)` of `kernel(i, j) * image(x + i, y + j)`.
+///
+/// Both inputs are in row-major order. The kernel must be no larger than the image.
+pub(crate) fn correlate(
+    image: &[f64],
+    width: usize,
+    height: usize,
+    kernel: &[f64],
+    k_width: usize,
+    k_height: usize,
+) -> Vec<f64> {
+    assert!(k_width <= width && k_height <= height, "kernel must be no larger than image");
+    let (fft_width, fft_height) = (width.next_power_of_two(), height.next_power_of_two());
+
+    let image = transform(image, width, height, fft_width, fft_height);
+    let kernel = transform(kernel, k_width, k_height, fft_width, fft_height);
+
+    // The inverse transform of a * conj(k) is the circular correlation of the inputs,
+    // which equals the valid correlation wherever the kernel doesn't wrap around.
+    let mut product: Vec<Complex<f64>> = image.iter().zip(kernel.iter()).map(|(a, k)| a * k.conj()).collect();
+    fft_2d(&mut product, fft_width, fft_height, true);
+
+    let scale = 1.0 / (fft_width * fft_height) as f64;
+    let (out_width, out_height) = (width - k_width + 1, height - k_height + 1);
+    let mut out = Vec::with_capacity(out_width * out_height);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            out.push(product[y * fft_width + x].re * scale);
+        }
+    }
+    out
+}
+
+/// Zero pads `data` to `fft_width` by `fft_height` and returns its 2d Fourier transform.
+fn transform(data: &[f64], width: usize, height: usize, fft_width: usize, fft_height: usize) -> Vec<Complex<f64>> {
+    let mut padded = vec![Complex::new(0.0, 0.0); fft_width * fft_height];
+    for y in 0..height {
+        for x in 0..width {
+            padded[y * fft_width + x].re = data[y * width + x];
+        }
+    }
+    fft_2d(&mut padded, fft_width, fft_height, false);
+    padded
+}
+
+/// Computes the 2d Fourier transform of `data` in place, or its inverse without
+/// scaling if `inverse` is true. Both dimensions must be powers of two.
+fn fft_2d(data: &mut [Complex<f64>], width: usize, height: usize, inverse: bool) {
+    for row in data.chunks_mut(width) {
+        fft(row, inverse);
+    }
+    let mut column = vec![Complex::new(0.0, 0.0); height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = data[y * width + x];
+        }
+        fft(&mut column, inverse);
+        for y in 0..height {
+            data[y * width + x] = column[y];
+        }
+    }
+}
+
+/// Computes the Fourier transform of `data` in place using the iterative radix-2
+/// Cooley-Tukey algorithm, or its inverse without scaling if `inverse` is true.
+/// The length of `data` must be a power of two.
+fn fft(data: &mut [Complex<f64>], inverse: bool) {
+    let n = data.len();
+    debug_assert!(n.is_power_of_two());
+    if n < 2 {
+        return;
+    }
+
+    // Bit reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        let step = Complex::new(angle.cos(), angle.sin());
+        for start in (0..n).step_by(len) {
+            let mut twiddle = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let even = data[start + k];
+                let odd = data[start + k + len / 2] * twiddle;
+                data[start + k] = even + odd;
+                data[start + k + len / 2] = even - odd;
+                twiddle = twiddle * step;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::{quickcheck, TestResult};
+
+    fn direct_correlation(
+        image: &[f64],
+        width: usize,
+        height: usize,
+        kernel: &[f64],
+        k_width: usize,
+        k_height: usize,
+    ) -> Vec<f64> {
+        let mut out = vec![];
+        for y in 0..height - k_height + 1 {
+            for x in 0..width - k_width + 1 {
+                let mut sum = 0.0;
+                for j in 0..k_height {
+                    for i in 0..k_width {
+                        sum += kernel[j * k_width + i] * image[(y + j) * width + x + i];
+                    }
+                }
+                out.push(sum);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_fft_inverse() {
+        let input: Vec<Complex<f64>> = (0..16).map(|i| Complex::new(i as f64, (i * i % 7) as f64)).collect();
+        let mut data = input.clone();
+        fft(&mut data, false);
+        // The first output is the sum of the inputs.
+        assert!((data[0] - Complex::new(120.0, 29.0)).norm() < 1e-9);
+        fft(&mut data, true);
+        for (d, i) in data.iter().zip(input.iter()) {
+            assert!((d / 16.0 - i).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_correlate_matches_direct_correlation() {
+        fn prop(width: u8, height: u8, k_width: u8, k_height: u8, seed: Vec<u8>) -> TestResult {
+            let (width, height) = (width as usize % 20 + 1, height as usize % 20 + 1);
+            let (k_width, k_height) = (k_width as usize % width + 1, k_height as usize % height + 1);
+            if seed.is_empty() {
+                return TestResult::discard();
+            }
+            let value = |i: usize| seed[i % seed.len()] as f64 - (i % 5) as f64;
+            let image: Vec<f64> = (0..width * height).map(|i| value(i)).collect();
+            let kernel: Vec<f64> = (0..k_width * k_height).map(|i| value(3 * i + 1) / 10.0).collect();
+
+            let expected = direct_correlation(&image, width, height, &kernel, k_width, k_height);
+            let actual = correlate(&image, width, height, &kernel, k_width, k_height);
+            let close = actual.iter().zip(expected.iter()).all(|(a, e)| (a - e).abs() < 1e-6);
+            TestResult::from_bool(actual.len() == expected.len() && close)
+        }
+        quickcheck(prop as fn(u8, u8, u8, u8, Vec<u8>) -> TestResult);
+    }
+
+    #[test]
+    fn test_fft_is_faster() {
+        assert!(!fft_is_faster((100, 100), (3, 3)));
+        assert!(fft_is_faster((500, 500), (64, 64)));
+    }
+}
@@ -0,0 +1,264 @@
+use image::{GrayImage, Luma, Pixel};
+use definitions::Image;
+use filter::gaussian_blur_f32;
+use std::cmp::{min, max};
+
+/// Applies a bilateral filter to an image. Each output pixel is a weighted mean of the
+/// pixels in a window around its corresponding input pixel, where the weight of a pixel
+/// falls off with both its distance from the centre of the window and with the difference
+/// between its value and the value of the centre pixel. This smooths noise while
+/// preserving edges.
+///
+/// The weight of pixel `q` when filtering pixel `p` is
+/// `exp(-|p - q|^2 / (2 * sigma_spatial^2)) * exp(-|I(p) - I(q)|^2 / (2 * sigma_color^2))`,
+/// where `|I(p) - I(q)|` is the Euclidean distance between the two pixels' channel values.
+/// The window is truncated at radius `ceil(2 * sigma_spatial)`. Pads by continuity.
+///
+/// Performs `O(sigma_spatial^2)` operations per pixel. See
+/// [`bilateral_filter_approx`](fn.bilateral_filter_approx.html) for a faster approximation
+/// for grayscale images.
+///
+/// # Panics
+/// If `sigma_spatial` or `sigma_color` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::bilateral_filter;
+///
+/// // Small fluctuations are smoothed away, but the edge is preserved.
+/// let image = gray_image!(
+///     10, 12, 10, 200, 202, 200;
+///     12, 10, 12, 202, 200, 202);
+///
+/// let filtered = gray_image!(
+///     11, 11, 11, 201, 201, 201;
+///     11, 11, 11, 201, 201, 201);
+///
+/// assert_pixels_eq!(bilateral_filter(&image, 5.0, 10.0), filtered);
+/// # }
+/// ```
+pub fn bilateral_filter<P>(image: &Image<P>, sigma_spatial: f32, sigma_color: f32) -> Image<P>
+where
+    P: Pixel<Subpixel = u8> + 'static,
+{
+    assert!(sigma_spatial > 0.0, "sigma_spatial must be positive");
+    assert!(sigma_color > 0.0, "sigma_color must be positive");
+
+    let (width, height) = image.dimensions();
+    let mut out = Image::<P>::new(width, height);
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    let radius = (2.0 * sigma_spatial).ceil() as i64;
+    let side = (2 * radius + 1) as usize;
+    let mut spatial_weights = Vec::with_capacity(side * side);
+    for dy in -radius..(radius + 1) {
+        for dx in -radius..(radius + 1) {
+            let d2 = (dx * dx + dy * dy) as f32;
+            spatial_weights.push((-d2 / (2.0 * sigma_spatial * sigma_spatial)).exp());
+        }
+    }
+    // The colour weight factorises as a product of per-channel weights.
+    let color_weights: Vec<f32> = (0..256)
+        .map(|d| (-(d * d) as f32 / (2.0 * sigma_color * sigma_color)).exp())
+        .collect();
+
+    let clamp_x = |x: i64| min(max(0, x), width as i64 - 1) as u32;
+    let clamp_y = |y: i64| min(max(0, y), height as i64 - 1) as u32;
+    let mut sums = vec![0f32; P::channel_count() as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let centre = *image.get_pixel(x, y);
+            let mut total_weight = 0f32;
+            let mut k = 0;
+
+            for dy in -radius..(radius + 1) {
+                let py = clamp_y(y as i64 + dy);
+                for dx in -radius..(radius + 1) {
+                    let p = image.get_pixel(clamp_x(x as i64 + dx), py);
+                    let mut weight = spatial_weights[k];
+                    k += 1;
+                    for (c, q) in centre.channels().iter().zip(p.channels()) {
+                        weight *= color_weights[(*c as i32 - *q as i32).abs() as usize];
+                    }
+                    total_weight += weight;
+                    for (s, q) in sums.iter_mut().zip(p.channels()) {
+                        *s += weight * *q as f32;
+                    }
+                }
+            }
+
+            // The centre pixel always has weight one, so total_weight is positive.
+            for (o, s) in out.get_pixel_mut(x, y).channels_mut().iter_mut().zip(sums.iter_mut()) {
+                *o = (*s / total_weight).round() as u8;
+                *s = 0.0;
+            }
+        }
+    }
+
+    out
+}
+
+/// Approximates [`bilateral_filter`](fn.bilateral_filter.html) for grayscale images.
+///
+/// Uses the piecewise-linear method of Durand and Dorsey: the image is filtered with a
+/// Gaussian blur once for each of `num_levels` evenly spaced intensities, and each output
+/// pixel is linearly interpolated between the results for the two levels nearest its
+/// input intensity. The running time depends on `num_levels` but is otherwise independent
+/// of `sigma_color`, and grows only linearly with `sigma_spatial`.
+///
+/// Larger values of `num_levels` give more accurate results. Levels closer together than
+/// `sigma_color` give little extra accuracy.
+///
+/// # Panics
+/// If `sigma_spatial` or `sigma_color` is not positive, or `num_levels` is less than 2.
+pub fn bilateral_filter_approx(
+    image: &GrayImage,
+    sigma_spatial: f32,
+    sigma_color: f32,
+    num_levels: u32,
+) -> GrayImage {
+    assert!(sigma_spatial > 0.0, "sigma_spatial must be positive");
+    assert!(sigma_color > 0.0, "sigma_color must be positive");
+    assert!(num_levels >= 2, "num_levels must be at least 2");
+
+    let (width, height) = image.dimensions();
+    let step = 255.0 / (num_levels - 1) as f32;
+    let mut acc = vec![0f32; (width * height) as usize];
+
+    for level in 0..num_levels {
+        let value = level as f32 * step;
+        let color_weight = |p: u8| {
+            let d = p as f32 - value;
+            (-d * d / (2.0 * sigma_color * sigma_color)).exp()
+        };
+        let weights: Image<Luma<f32>> = Image::from_fn(width, height, |x, y| {
+            Luma([color_weight(image.get_pixel(x, y)[0])])
+        });
+        let weighted: Image<Luma<f32>> = Image::from_fn(width, height, |x, y| {
+            let p = image.get_pixel(x, y)[0];
+            Luma([color_weight(p) * p as f32])
+        });
+        let weights = gaussian_blur_f32(&weights, sigma_spatial);
+        let weighted = gaussian_blur_f32(&weighted, sigma_spatial);
+
+        for (a, (p, (w, v))) in acc.iter_mut().zip(image.iter().zip(weights.iter().zip(weighted.iter()))) {
+            // Each pixel only takes contributions from the levels either side of its value.
+            let interpolation_weight = 1.0 - (*p as f32 - value).abs() / step;
+            if interpolation_weight > 0.0 {
+                let filtered = if *w > 0.0 { v / w } else { *p as f32 };
+                *a += interpolation_weight * filtered;
+            }
+        }
+    }
+
+    let mut out = GrayImage::new(width, height);
+    for (o, a) in out.iter_mut().zip(acc.iter()) {
+        *o = a.round().max(0.0).min(255.0) as u8;
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_bilateral_filter_constant_image() {
+        let image = GrayImage::from_pixel(6, 5, Luma([17]));
+        assert_pixels_eq!(bilateral_filter(&image, 2.0, 5.0), image);
+        assert_pixels_eq!(bilateral_filter_approx(&image, 2.0, 5.0, 8), image);
+    }
+
+    #[test]
+    fn test_bilateral_filter_preserves_step_edge() {
+        let image = GrayImage::from_fn(10, 6, |x, _| if x < 5 { Luma([30]) } else { Luma([220]) });
+        assert_pixels_eq!(bilateral_filter(&image, 3.0, 10.0), image);
+        assert_pixels_eq!(bilateral_filter_approx(&image, 3.0, 10.0, 16), image);
+    }
+
+    #[test]
+    fn test_bilateral_filter_large_color_sigma_approaches_gaussian_blur() {
+        let image = gray_image!(
+            0, 0, 0, 0, 0;
+            0, 0, 0, 0, 0;
+            0, 0, 100, 0, 0;
+            0, 0, 0, 0, 0;
+            0, 0, 0, 0, 0);
+        let filtered = bilateral_filter(&image, 1.0, 1.0e6);
+
+        // Each output is the normalised Gaussian weight of the centre pixel.
+        let spatial = |d2: f32| (-d2 / 2.0).exp();
+        let mut total = 0.0;
+        for dy in -2..3 {
+            for dx in -2..3 {
+                total += spatial((dx * dx + dy * dy) as f32);
+            }
+        }
+        assert_eq!(filtered.get_pixel(2, 2)[0], (100.0 / total).round() as u8);
+        assert_eq!(filtered.get_pixel(3, 2)[0], (100.0 * spatial(1.0) / total).round() as u8);
+        assert_eq!(filtered.get_pixel(3, 3)[0], (100.0 * spatial(2.0) / total).round() as u8);
+    }
+
+    #[test]
+    fn test_bilateral_filter_uses_distance_between_colors() {
+        // The green channel is constant, but the red channel differences
+        // prevent mixing across the boundary.
+        let image = RgbImage::from_fn(6, 3, |x, _| {
+            if x < 3 { Rgb([0, 100, 50]) } else { Rgb([250, 100, 60]) }
+        });
+        let filtered = bilateral_filter(&image, 2.0, 20.0);
+        assert_pixels_eq!(filtered, image);
+    }
+
+    #[test]
+    fn test_bilateral_filter_approx_close_to_exact() {
+        let image = GrayImage::from_fn(30, 20, |x, y| {
+            let base = if x + y < 25 { 60.0 } else { 180.0 };
+            let noise = ((x * 7 + y * 13) % 11) as f32 - 5.0;
+            Luma([(base + noise) as u8])
+        });
+        let exact = bilateral_filter(&image, 2.0, 20.0);
+        let approx = bilateral_filter_approx(&image, 2.0, 20.0, 32);
+
+        let total_diff: i32 = exact.iter().zip(approx.iter())
+            .map(|(e, a)| (*e as i32 - *a as i32).abs())
+            .sum();
+        let mean_diff = total_diff as f32 / (30 * 20) as f32;
+        assert!(mean_diff < 1.5, "mean_diff = {}", mean_diff);
+    }
+
+    #[test]
+    fn test_bilateral_filter_empty_image() {
+        let image = GrayImage::new(0, 4);
+        assert_eq!(bilateral_filter(&image, 1.0, 1.0).dimensions(), (0, 4));
+        assert_eq!(bilateral_filter_approx(&image, 1.0, 1.0, 4).dimensions(), (0, 4));
+    }
+
+    #[bench]
+    fn bench_bilateral_filter(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| {
+            let filtered = bilateral_filter(&image, 2.0, 5.0);
+            black_box(filtered);
+        });
+    }
+
+    #[bench]
+    fn bench_bilateral_filter_approx(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| {
+            let filtered = bilateral_filter_approx(&image, 2.0, 5.0, 16);
+            black_box(filtered);
+        });
+    }
+}
@@ -0,0 +1,256 @@
+//! Kernels whose dimensions are known at compile time.
+
+use image::Pixel;
+use conv::ValueInto;
+use definitions::Image;
+use math::cast;
+use num::Num;
+use std::cmp::min;
+
+/// A 2D kernel of width `W` and height `H`, used to filter images via correlation.
+///
+/// This computes the same results as [`Kernel`](struct.Kernel.html), but as the kernel
+/// dimensions are known at compile time the loops over kernel entries are unrolled.
+/// This is usually faster than a `Kernel` of the same size, except for the combinations
+/// of pixel and kernel types for which `Kernel::filter` uses SIMD instructions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::Kernel3x3;
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6;
+///     7, 8, 9);
+///
+/// let laplacian = Kernel3x3::new([
+///     [0,  1, 0],
+///     [1, -4, 1],
+///     [0,  1, 0]]);
+///
+/// let filtered = laplacian.filter(&image, |c, a| *c = a);
+///
+/// assert_pixels_eq!(
+///     filtered,
+///     gray_image!(type: i32,
+///         4,  3,  2;
+///         1,  0, -1;
+///        -2, -3, -4));
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedKernel<K, const W: usize, const H: usize> {
+    data: [[K; W]; H],
+}
+
+/// A 3x3 [`FixedKernel`](struct.FixedKernel.html).
+pub type Kernel3x3<K> = FixedKernel<K, 3, 3>;
+
+/// A 5x5 [`FixedKernel`](struct.FixedKernel.html).
+pub type Kernel5x5<K> = FixedKernel<K, 5, 5>;
+
+impl<K: Num + Copy, const W: usize, const H: usize> FixedKernel<K, W, H> {
+    /// Construct a kernel from its rows.
+    ///
+    /// # Panics
+    /// If `W` or `H` is zero.
+    pub fn new(data: [[K; W]; H]) -> FixedKernel<K, W, H> {
+        assert!(W > 0 && H > 0, "kernel dimensions must be non-zero");
+        FixedKernel { data }
+    }
+
+    /// The rows of this kernel.
+    pub fn rows(&self) -> &[[K; W]; H] {
+        &self.data
+    }
+
+    /// Returns 2d correlation of an image. Intermediate calculations are performed
+    /// at type K, and the results converted to pixel Q via f. Pads by continuity.
+    ///
+    /// The kernel is anchored at `(W / 2, H / 2)`, as for [`Kernel::filter`](struct.Kernel.html#method.filter).
+    pub fn filter<P, F, Q>(&self, image: &Image<P>, f: F) -> Image<Q>
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+    {
+        let (width, height) = image.dimensions();
+        let mut out = Image::<Q>::new(width, height);
+        self.filter_into(image, f, &mut out);
+        out
+    }
+
+    /// Writes the result of [`filter`](#method.filter) to `out`.
+    ///
+    /// # Panics
+    /// If `out` and `image` have different dimensions.
+    pub fn filter_into<P, F, Q>(&self, image: &Image<P>, mut f: F, out: &mut Image<Q>)
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+    {
+        assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (width, height) = (width as usize, height as usize);
+        let channels = P::channel_count() as usize;
+        let out_channels = Q::channel_count() as usize;
+        let row_len = width * channels;
+        let mut acc = vec![K::zero(); channels];
+
+        // Outputs in columns interior_start..interior_end don't need any padding.
+        let interior_start = min(W / 2, width);
+        let interior_end = if width + W / 2 >= W { width + W / 2 + 1 - W } else { 0 };
+        let interior_end = interior_end.max(interior_start);
+
+        for (y, out_row) in out.chunks_mut(width * out_channels).enumerate() {
+            let mut rows: [&[P::Subpixel]; H] = [&[]; H];
+            for (k_y, row) in rows.iter_mut().enumerate() {
+                let y_p = min((y + k_y).saturating_sub(H / 2), height - 1);
+                *row = &(**image)[y_p * row_len..(y_p + 1) * row_len];
+            }
+
+            for x in 0..width {
+                if x >= interior_start && x < interior_end {
+                    let start = (x - W / 2) * channels;
+                    if channels == 1 {
+                        acc[0] = self.correlate_interior(&rows, start);
+                    } else {
+                        for (c, a) in acc.iter_mut().enumerate() {
+                            *a = self.correlate_interior_channel(&rows, start, channels, c);
+                        }
+                    }
+                } else {
+                    let mut offsets = [0usize; W];
+                    for (k_x, o) in offsets.iter_mut().enumerate() {
+                        *o = min((x + k_x).saturating_sub(W / 2), width - 1) * channels;
+                    }
+                    for (c, a) in acc.iter_mut().enumerate() {
+                        let mut sum = K::zero();
+                        for (row, k_row) in rows.iter().zip(self.data.iter()) {
+                            for (o, k) in offsets.iter().zip(k_row.iter()) {
+                                sum = sum + cast(row[o + c]) * *k;
+                            }
+                        }
+                        *a = sum;
+                    }
+                }
+
+                let start = x * out_channels;
+                for (a, c) in acc.iter().zip(out_row[start..start + out_channels].iter_mut()) {
+                    f(c, *a);
+                }
+            }
+        }
+    }
+
+    // Correlates the kernel with the single-channel rows starting at index start.
+    #[inline]
+    fn correlate_interior<S>(&self, rows: &[&[S]; H], start: usize) -> K
+    where
+        S: Copy + ValueInto<K>,
+    {
+        let mut sum = K::zero();
+        for (row, k_row) in rows.iter().zip(self.data.iter()) {
+            let row = &row[start..start + W];
+            for k_x in 0..W {
+                sum = sum + cast(row[k_x]) * k_row[k_x];
+            }
+        }
+        sum
+    }
+
+    // Correlates the kernel with channel c of the interleaved rows starting at index start.
+    #[inline]
+    fn correlate_interior_channel<S>(&self, rows: &[&[S]; H], start: usize, channels: usize, c: usize) -> K
+    where
+        S: Copy + ValueInto<K>,
+    {
+        let mut sum = K::zero();
+        for (row, k_row) in rows.iter().zip(self.data.iter()) {
+            let row = &row[start..start + W * channels];
+            for k_x in 0..W {
+                sum = sum + cast(row[k_x * channels + c]) * k_row[k_x];
+            }
+        }
+        sum
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use filter::Kernel;
+    use image::{Luma, Rgb};
+    use utils::{gray_bench_image, rgb_bench_image};
+    use test::{Bencher, black_box};
+
+    fn check_matches_kernel<const W: usize, const H: usize>(data: [[i32; W]; H]) {
+        let fixed = FixedKernel::new(data);
+        let flat: Vec<i32> = data.iter().flat_map(|r| r.iter().cloned()).collect();
+        let kernel = Kernel::new(&flat, W as u32, H as u32);
+
+        for &(width, height) in &[(1, 1), (2, 7), (4, 3), (9, 2), (37, 11)] {
+            let image = gray_bench_image(width, height);
+            let expected: Image<Luma<i32>> = kernel.filter(&image, |c, a| *c = a);
+            let actual: Image<Luma<i32>> = fixed.filter(&image, |c, a| *c = a);
+            assert_pixels_eq!(actual, expected);
+
+            let image = rgb_bench_image(width, height);
+            let expected: Image<Rgb<i32>> = kernel.filter(&image, |c, a| *c = a);
+            let actual: Image<Rgb<i32>> = fixed.filter(&image, |c, a| *c = a);
+            assert_pixels_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_fixed_kernel_matches_kernel() {
+        check_matches_kernel([[1, -2, 3], [4, 5, -6], [-7, 8, 9]]);
+        check_matches_kernel([
+            [1, 2, 3, 4, 5],
+            [-6, 7, -8, 9, -10],
+            [11, 12, 13, 14, 15],
+            [-16, 17, 18, 19, -20],
+            [21, -22, 23, 24, 25],
+        ]);
+        check_matches_kernel([[1, 2, 3, 4], [5, 6, -7, 8]]);
+        check_matches_kernel([[3]]);
+        check_matches_kernel([[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]]);
+    }
+
+    #[bench]
+    fn bench_fixed_kernel3x3_f32_filter(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        let kernel = Kernel3x3::new([[0.1f32, 0.2, 0.1], [0.2, 0.4, 0.2], [0.1, 0.2, 0.1]]);
+
+        b.iter(|| {
+            let filtered: Image<Luma<f32>> = kernel.filter(&image, |c, a| *c = a);
+            black_box(filtered);
+        });
+    }
+
+    #[bench]
+    fn bench_fixed_kernel5x5_rgb_i32_filter(b: &mut Bencher) {
+        let image = rgb_bench_image(500, 500);
+        let mut data = [[0i32; 5]; 5];
+        for (i, k) in data.iter_mut().flat_map(|r| r.iter_mut()).enumerate() {
+            *k = i as i32 - 12;
+        }
+        let kernel = Kernel5x5::new(data);
+
+        b.iter(|| {
+            let filtered: Image<Rgb<i32>> = kernel.filter(&image, |c, a| *c = a);
+            black_box(filtered);
+        });
+    }
+}
@@ -0,0 +1,179 @@
+//! Banks of Gabor filters for texture analysis.
+
+use super::{GaborOptions, Kernel};
+use definitions::Image;
+use image::{GrayImage, Luma};
+use std::f32;
+
+/// Returns the options for a bank of Gabor filters with `orientations` evenly spaced values
+/// of `theta` in `[base.theta, base.theta + PI)` at each of the given `scales`.
+///
+/// The filters at scale `s` have the `sigma` and `wavelength` of `base` multiplied by `s`.
+/// The other options are taken from `base`. Filters are ordered by scale and then by
+/// orientation.
+///
+/// # Panics
+/// If `orientations` is 0.
+pub fn gabor_bank(base: GaborOptions, orientations: u32, scales: &[f32]) -> Vec<GaborOptions> {
+    assert!(orientations > 0, "orientations must be positive");
+    let step = f32::consts::PI / orientations as f32;
+    let mut bank = Vec::with_capacity(orientations as usize * scales.len());
+    for &scale in scales {
+        for k in 0..orientations {
+            bank.push(GaborOptions {
+                sigma: base.sigma * scale,
+                theta: base.theta + k as f32 * step,
+                wavelength: base.wavelength * scale,
+                ..base
+            });
+        }
+    }
+    bank
+}
+
+/// The strongest response at each pixel to a bank of Gabor filters, computed by
+/// [`gabor_bank_response`](fn.gabor_bank_response.html).
+#[derive(Clone, Debug)]
+pub struct GaborResponse {
+    /// The largest magnitude of the response to any filter in the bank.
+    pub magnitude: Image<Luma<f32>>,
+    /// The `theta` of the filter with the largest magnitude of response.
+    pub orientation: Image<Luma<f32>>,
+}
+
+/// Filters `image` with each Gabor filter in `bank`, and returns the largest magnitude
+/// of response at each pixel along with the orientation of the filter giving it.
+///
+/// The magnitude of the response to a filter is computed from the responses to the
+/// kernels given by [`Kernel::gabor`](struct.Kernel.html#method.gabor) with phases
+/// `phase` and `phase + PI / 2`, so does not depend on the position of a pixel within
+/// the stripes of a texture. The mean of each kernel is subtracted before filtering,
+/// so that regions of constant intensity have no response. Ties are resolved in favour
+/// of the earliest filter in `bank`. Pads by continuity.
+///
+/// Use [`gabor_bank`](fn.gabor_bank.html) to generate filters at regularly spaced
+/// orientations and scales.
+///
+/// # Panics
+/// If `bank` is empty, or any of its options are invalid for `Kernel::gabor`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::filter::{gabor_bank, gabor_bank_response, GaborOptions};
+///
+/// // Horizontal stripes with a period of 6 pixels.
+/// let image = GrayImage::from_fn(40, 40, |_, y| Luma([if y % 6 < 3 { 50 } else { 200 }]));
+///
+/// let base = GaborOptions { sigma: 3.0, theta: 0.0, wavelength: 6.0, aspect_ratio: 1.0, phase: 0.0 };
+/// let bank = gabor_bank(base, 4, &[1.0]);
+/// let response = gabor_bank_response(&image, &bank);
+///
+/// // The normals to the stripes of the strongest filter are vertical.
+/// assert_eq!(response.orientation.get_pixel(20, 20)[0], bank[2].theta);
+/// assert!(response.magnitude.get_pixel(20, 20)[0] > 0.0);
+/// # }
+/// ```
+pub fn gabor_bank_response(image: &GrayImage, bank: &[GaborOptions]) -> GaborResponse {
+    assert!(!bank.is_empty(), "bank must contain at least one filter");
+    let (width, height) = image.dimensions();
+    let mut magnitude = Image::from_pixel(width, height, Luma([-1.0]));
+    let mut orientation = Image::new(width, height);
+
+    for options in bank {
+        let even = zero_mean(Kernel::gabor(*options));
+        let odd = zero_mean(Kernel::gabor(GaborOptions {
+            phase: options.phase + f32::consts::FRAC_PI_2,
+            ..*options
+        }));
+        let even_response: Image<Luma<f32>> = even.filter(image, |c, a| *c = a);
+        let odd_response: Image<Luma<f32>> = odd.filter(image, |c, a| *c = a);
+
+        let responses = even_response.iter().zip(odd_response.iter());
+        for ((m, o), (e, d)) in magnitude.iter_mut().zip(orientation.iter_mut()).zip(responses) {
+            let value = (e * e + d * d).sqrt();
+            if value > *m {
+                *m = value;
+                *o = options.theta;
+            }
+        }
+    }
+
+    GaborResponse { magnitude, orientation }
+}
+
+fn zero_mean(kernel: Kernel<f32>) -> Kernel<f32> {
+    let mean = kernel.data().iter().sum::<f32>() / kernel.data().len() as f32;
+    let data = kernel.data().iter().map(|k| k - mean).collect();
+    Kernel::from_vec(data, kernel.width(), kernel.height())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn base() -> GaborOptions {
+        GaborOptions {
+            sigma: 2.0,
+            theta: 0.0,
+            wavelength: 5.0,
+            aspect_ratio: 1.0,
+            phase: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_gabor_bank() {
+        let bank = gabor_bank(base(), 3, &[1.0, 2.0]);
+        assert_eq!(bank.len(), 6);
+        let step = f32::consts::PI / 3.0;
+        for (i, options) in bank.iter().enumerate() {
+            let scale = if i < 3 { 1.0 } else { 2.0 };
+            assert_eq!(options.sigma, 2.0 * scale);
+            assert_eq!(options.wavelength, 5.0 * scale);
+            assert_eq!(options.theta, (i % 3) as f32 * step);
+            assert_eq!(options.aspect_ratio, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_gabor_bank_response_finds_stripe_orientation() {
+        let bank = gabor_bank(base(), 4, &[1.0, 1.5]);
+        let vertical = GrayImage::from_fn(40, 40, |x, _| Luma([if x % 5 < 2 { 30 } else { 220 }]));
+        let horizontal = GrayImage::from_fn(40, 40, |_, y| Luma([if y % 5 < 2 { 30 } else { 220 }]));
+
+        for &(ref image, theta) in &[(vertical, 0.0), (horizontal, f32::consts::FRAC_PI_2)] {
+            let response = gabor_bank_response(image, &bank);
+            let mut magnitudes = vec![];
+            for y in 12..28 {
+                for x in 12..28 {
+                    assert_eq!(response.orientation.get_pixel(x, y)[0], theta);
+                    magnitudes.push(response.magnitude.get_pixel(x, y)[0]);
+                }
+            }
+            // The magnitude does not depend on the phase of the stripes.
+            let max = magnitudes.iter().cloned().fold(0.0, f32::max);
+            let min = magnitudes.iter().cloned().fold(f32::INFINITY, f32::min);
+            assert!(min > 0.8 * max, "min {}, max {}", min, max);
+        }
+    }
+
+    #[test]
+    fn test_gabor_bank_response_constant_image() {
+        let image = GrayImage::from_pixel(20, 20, Luma([120]));
+        let response = gabor_bank_response(&image, &gabor_bank(base(), 2, &[1.0]));
+        assert!(response.magnitude.iter().all(|m| *m < 1e-2));
+    }
+
+    #[bench]
+    fn bench_gabor_bank_response(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        let bank = gabor_bank(base(), 4, &[1.0, 2.0]);
+        b.iter(|| black_box(gabor_bank_response(&image, &bank)));
+    }
+}
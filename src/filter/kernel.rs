@@ -0,0 +1,382 @@
+//! Constructors for common kernels, normalization and separability analysis.
+
+use super::{gaussian_kernel_f32, Kernel};
+use conv::ValueInto;
+use gradients::{HORIZONTAL_SOBEL, VERTICAL_SOBEL};
+use math::cast;
+use num::Num;
+use std::cmp::Ordering;
+use std::f32;
+
+/// Parameters of a [Gabor filter](https://en.wikipedia.org/wiki/Gabor_filter), used by
+/// [`Kernel::gabor`](struct.Kernel.html#method.gabor).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GaborOptions {
+    /// Standard deviation of the Gaussian envelope, in pixels.
+    pub sigma: f32,
+    /// Orientation of the normal to the stripes of the filter, in radians.
+    pub theta: f32,
+    /// Wavelength of the sinusoidal factor, in pixels.
+    pub wavelength: f32,
+    /// Ratio of the extent of the envelope along the stripes to its extent across them.
+    /// 1 gives a circular envelope.
+    pub aspect_ratio: f32,
+    /// Phase offset of the sinusoidal factor, in radians. 0 gives a symmetric filter
+    /// and `PI / 2` an antisymmetric one.
+    pub phase: f32,
+}
+
+impl Kernel<f32> {
+    /// A square Gaussian kernel with standard deviation `sigma`, normalized to sum to 1.
+    ///
+    /// The kernel has the same radius as the one used by
+    /// [`gaussian_blur_f32`](fn.gaussian_blur_f32.html).
+    ///
+    /// # Panics
+    /// If `sigma` is not positive.
+    pub fn gaussian(sigma: f32) -> Kernel<f32> {
+        assert!(sigma > 0.0, "sigma must be positive");
+        let row = gaussian_kernel_f32(sigma);
+        let size = row.len() as u32;
+        let mut data = Vec::with_capacity(row.len() * row.len());
+        for y in &row {
+            data.extend(row.iter().map(|x| x * y));
+        }
+        let mut kernel = Kernel::from_vec(data, size, size);
+        kernel.normalize_sum();
+        kernel
+    }
+
+    /// A kernel whose entries are all equal and sum to 1, i.e. that computes the mean of
+    /// the `width` by `height` region around each pixel.
+    pub fn box_kernel(width: u32, height: u32) -> Kernel<f32> {
+        let weight = 1.0 / (width * height) as f32;
+        Kernel::from_vec(vec![weight; (width * height) as usize], width, height)
+    }
+
+    /// A [Gabor](https://en.wikipedia.org/wiki/Gabor_filter) kernel: a sinusoid multiplied by
+    /// a Gaussian envelope. The kernel is square, with radius large enough to contain three
+    /// standard deviations of the envelope in every direction.
+    ///
+    /// See [`gabor_bank_response`](fn.gabor_bank_response.html) for applying filters at
+    /// several orientations and scales.
+    ///
+    /// # Panics
+    /// If `sigma`, `wavelength` or `aspect_ratio` is not positive.
+    pub fn gabor(options: GaborOptions) -> Kernel<f32> {
+        let GaborOptions { sigma, theta, wavelength, aspect_ratio, phase } = options;
+        assert!(sigma > 0.0, "sigma must be positive");
+        assert!(wavelength > 0.0, "wavelength must be positive");
+        assert!(aspect_ratio > 0.0, "aspect_ratio must be positive");
+
+        let radius = (3.0 * sigma / aspect_ratio.min(1.0)).ceil() as i32;
+        let size = (2 * radius + 1) as u32;
+        let (sin, cos) = theta.sin_cos();
+        let mut data = Vec::with_capacity((size * size) as usize);
+        for y in -radius..radius + 1 {
+            for x in -radius..radius + 1 {
+                let (x, y) = (x as f32, y as f32);
+                let across = x * cos + y * sin;
+                let along = -x * sin + y * cos;
+                let envelope = (-(across * across + aspect_ratio * aspect_ratio * along * along)
+                    / (2.0 * sigma * sigma)).exp();
+                data.push(envelope * (2.0 * f32::consts::PI * across / wavelength + phase).cos());
+            }
+        }
+        Kernel::from_vec(data, size, size)
+    }
+
+    /// Scales the entries of this kernel so that they sum to 1.
+    ///
+    /// # Panics
+    /// If the entries sum to 0.
+    pub fn normalize_sum(&mut self) {
+        let sum: f32 = self.data.iter().sum();
+        assert!(sum != 0.0, "cannot normalize a kernel whose entries sum to zero");
+        for k in &mut self.data {
+            *k /= sum;
+        }
+    }
+
+    /// Scales the entries of this kernel so that their absolute values sum to 1.
+    /// This is useful for kernels whose entries sum to zero, such as derivative kernels.
+    ///
+    /// # Panics
+    /// If all entries are 0.
+    pub fn normalize_abs_sum(&mut self) {
+        let sum: f32 = self.data.iter().map(|k| k.abs()).sum();
+        assert!(sum != 0.0, "cannot normalize a kernel whose entries are all zero");
+        for k in &mut self.data {
+            *k /= sum;
+        }
+    }
+}
+
+impl Kernel<i32> {
+    /// The 3x3 Sobel kernel for detecting horizontal gradients,
+    /// [`HORIZONTAL_SOBEL`](../gradients/static.HORIZONTAL_SOBEL.html).
+    pub fn sobel_horizontal() -> Kernel<i32> {
+        Kernel::new(&HORIZONTAL_SOBEL, 3, 3)
+    }
+
+    /// The 3x3 Sobel kernel for detecting vertical gradients,
+    /// [`VERTICAL_SOBEL`](../gradients/static.VERTICAL_SOBEL.html).
+    pub fn sobel_vertical() -> Kernel<i32> {
+        Kernel::new(&VERTICAL_SOBEL, 3, 3)
+    }
+
+    /// The 3x3 discrete Laplacian using the four neighbours of each pixel.
+    pub fn laplacian() -> Kernel<i32> {
+        Kernel::new(&[0, 1, 0, 1, -4, 1, 0, 1, 0], 3, 3)
+    }
+}
+
+impl<K: Num + Copy + ValueInto<f64>> Kernel<K> {
+    /// Returns `(horizontal, vertical)` factors of this kernel with `data[y * width + x]`
+    /// approximately equal to `vertical[y] * horizontal[x]`, or `None` if no such
+    /// factors exist or any entry of the kernel is not finite.
+    ///
+    /// The factors are computed from the largest singular value of the kernel and its
+    /// singular vectors. The kernel is treated as separable if the Frobenius norm of the
+    /// difference between it and the product of the factors is at most `tolerance` times
+    /// the Frobenius norm of the kernel.
+    ///
+    /// The factors are only determined up to scale. They are scaled to have the same norm, and
+    /// signed so that the vertical factor has a positive sum, or if its sum is zero so that the
+    /// horizontal factor does. If the kernel is anchored at its centre then filtering with
+    /// [`separable_filter`](fn.separable_filter.html) using the factors gives the same results
+    /// as filtering with the kernel, up to rounding.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::filter::Kernel;
+    ///
+    /// let (horizontal, vertical) = Kernel::gaussian(1.5).separable_factors(1e-5).unwrap();
+    /// assert_eq!(horizontal.len(), 7);
+    /// assert!((horizontal[3] - vertical[3]).abs() < 1e-5);
+    ///
+    /// assert!(Kernel::laplacian().separable_factors(1e-5).is_none());
+    /// ```
+    pub fn separable_factors(&self, tolerance: f64) -> Option<(Vec<f32>, Vec<f32>)> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let a: Vec<f64> = self.data.iter().map(|k| cast(*k)).collect();
+        if a.iter().any(|k| !k.is_finite()) {
+            return None;
+        }
+        let norm_sq: f64 = a.iter().map(|k| k * k).sum();
+        if norm_sq == 0.0 {
+            return Some((vec![0.0; width], vec![0.0; height]));
+        }
+
+        // Power iteration for the largest singular value and its singular vectors,
+        // starting from the row of largest norm so that the first product is non-zero.
+        let mut v = a
+            .chunks(width)
+            .max_by(|p, q| dot(p, p).partial_cmp(&dot(q, q)).unwrap_or(Ordering::Equal))
+            .unwrap()
+            .to_vec();
+        normalize(&mut v);
+        let mut u = vec![0.0; height];
+        let mut sigma = 0.0;
+
+        for _ in 0..100 {
+            for (u, row) in u.iter_mut().zip(a.chunks(width)) {
+                *u = dot(row, &v);
+            }
+            normalize(&mut u);
+
+            for (x, v) in v.iter_mut().enumerate() {
+                *v = u.iter().enumerate().map(|(y, u)| a[y * width + x] * u).sum();
+            }
+            let next_sigma = normalize(&mut v);
+
+            let converged = (next_sigma - sigma).abs() <= 1e-12 * next_sigma;
+            sigma = next_sigma;
+            if converged {
+                break;
+            }
+        }
+
+        let residual_sq: f64 = a
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let r = k - sigma * u[i / width] * v[i % width];
+                r * r
+            })
+            .sum();
+        if residual_sq > tolerance * tolerance * norm_sq {
+            return None;
+        }
+
+        let vertical_sum: f64 = u.iter().sum();
+        let horizontal_sum: f64 = v.iter().sum();
+        let is_small = |x: f64| x.abs() <= 1e-9;
+        let sign = if vertical_sum < 0.0 && !is_small(vertical_sum)
+            || is_small(vertical_sum) && horizontal_sum < 0.0
+        {
+            -1.0
+        } else {
+            1.0
+        };
+        let s = sign * sigma.sqrt();
+        let horizontal = v.iter().map(|x| (x * s) as f32).collect();
+        let vertical = u.iter().map(|y| (y * s) as f32).collect();
+        Some((horizontal, vertical))
+    }
+}
+
+fn dot(p: &[f64], q: &[f64]) -> f64 {
+    p.iter().zip(q).map(|(p, q)| p * q).sum()
+}
+
+// Scales p to unit length and returns its original length.
+fn normalize(p: &mut [f64]) -> f64 {
+    let norm = dot(p, p).sqrt();
+    for x in p {
+        *x /= norm;
+    }
+    norm
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use filter::separable_filter;
+    use definitions::Image;
+    use image::Luma;
+    use utils::gray_bench_image;
+
+    fn assert_factors_reconstruct<K>(kernel: &Kernel<K>, horizontal: &[f32], vertical: &[f32])
+    where
+        K: Num + Copy + ValueInto<f64>,
+    {
+        assert_eq!(horizontal.len(), kernel.width() as usize);
+        assert_eq!(vertical.len(), kernel.height() as usize);
+        for (i, k) in kernel.data().iter().enumerate() {
+            let k: f64 = cast(*k);
+            let product = vertical[i / horizontal.len()] * horizontal[i % horizontal.len()];
+            assert!((k as f32 - product).abs() < 1e-4, "{} != {}", k, product);
+        }
+    }
+
+    #[test]
+    fn test_gaussian_is_normalized_and_separable() {
+        let kernel = Kernel::gaussian(1.2);
+        assert_eq!((kernel.width(), kernel.height(), kernel.anchor()), (7, 7, (3, 3)));
+        let sum: f32 = kernel.data().iter().sum();
+        assert!((sum - 1.0).abs() < 1e-5);
+
+        let (horizontal, vertical) = kernel.separable_factors(1e-4).unwrap();
+        assert_factors_reconstruct(&kernel, &horizontal, &vertical);
+        for (h, v) in horizontal.iter().zip(&vertical) {
+            assert!((h - v).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_sobel_separable_factors() {
+        let kernel = Kernel::sobel_horizontal();
+        let (horizontal, vertical) = kernel.separable_factors(1e-6).unwrap();
+        assert_factors_reconstruct(&kernel, &horizontal, &vertical);
+        assert!(horizontal[0] < 0.0 && horizontal[2] > 0.0);
+        assert!(vertical.iter().all(|v| *v > 0.0));
+
+        let kernel = Kernel::sobel_vertical();
+        let (horizontal, vertical) = kernel.separable_factors(1e-6).unwrap();
+        assert_factors_reconstruct(&kernel, &horizontal, &vertical);
+        assert!(horizontal.iter().all(|h| *h > 0.0));
+    }
+
+    #[test]
+    fn test_separable_factors_rejects_non_finite_kernels() {
+        for &value in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let kernel = Kernel::new(&[1.0, 2.0, value, 4.0], 2, 2);
+            assert!(kernel.separable_factors(1e-3).is_none());
+        }
+    }
+
+    #[test]
+    fn test_separable_factors_rejects_non_separable_kernels() {
+        assert!(Kernel::laplacian().separable_factors(1e-3).is_none());
+        let gabor = Kernel::gabor(GaborOptions {
+            sigma: 2.0,
+            theta: 0.6,
+            wavelength: 4.0,
+            aspect_ratio: 0.5,
+            phase: 0.0,
+        });
+        assert!(gabor.separable_factors(1e-3).is_none());
+    }
+
+    #[test]
+    fn test_separable_factors_of_zero_kernel() {
+        let kernel = Kernel::new(&[0, 0, 0, 0, 0, 0], 3, 2);
+        assert_eq!(kernel.separable_factors(0.0), Some((vec![0.0; 3], vec![0.0; 2])));
+    }
+
+    #[test]
+    fn test_separable_filter_with_factors_matches_filter() {
+        let image = gray_bench_image(30, 20);
+        let kernel = Kernel::gaussian(2.0);
+        let (horizontal, vertical) = kernel.separable_factors(1e-4).unwrap();
+
+        let expected: Image<Luma<f32>> = kernel.filter(&image, |c, a| *c = a);
+        let image_f32: Image<Luma<f32>> = Image::from_fn(30, 20, |x, y| Luma([image.get_pixel(x, y)[0] as f32]));
+        let actual = separable_filter(&image_f32, &horizontal, &vertical);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-2, "{} != {}", a, e);
+        }
+    }
+
+    #[test]
+    fn test_gabor() {
+        let options = GaborOptions {
+            sigma: 1.0,
+            theta: 0.0,
+            wavelength: 4.0,
+            aspect_ratio: 1.0,
+            phase: 0.0,
+        };
+        let kernel = Kernel::gabor(options);
+        assert_eq!((kernel.width(), kernel.height()), (7, 7));
+        // The centre of a symmetric filter is the peak of the envelope.
+        assert_eq!(kernel.data()[3 * 7 + 3], 1.0);
+        // With theta = 0 the stripes are vertical, so a quarter wavelength
+        // horizontally from the centre is a zero of the sinusoid.
+        assert!(kernel.data()[3 * 7 + 4].abs() < 1e-6);
+        // Vertically the filter is a Gaussian.
+        assert!((kernel.data()[1 * 7 + 3] - (-2.0f32).exp()).abs() < 1e-6);
+
+        // Axis aligned, circular Gabor filters are separable.
+        assert!(kernel.separable_factors(1e-4).is_some());
+
+        let elongated = Kernel::gabor(GaborOptions { aspect_ratio: 0.5, ..options });
+        assert_eq!(elongated.width(), 13);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut kernel = Kernel::new(&[1.0, 2.0, 1.0], 3, 1);
+        kernel.normalize_sum();
+        assert_eq!(kernel.data(), &[0.25, 0.5, 0.25]);
+
+        let mut kernel = Kernel::new(&[-1.0, 0.0, 3.0], 3, 1);
+        kernel.normalize_abs_sum();
+        assert_eq!(kernel.data(), &[-0.25, 0.0, 0.75]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_normalize_sum_panics_for_zero_sum() {
+        let mut kernel = Kernel::new(&[-1.0, 0.0, 1.0], 3, 1);
+        kernel.normalize_sum();
+    }
+
+    #[test]
+    fn test_box_kernel() {
+        let kernel = Kernel::box_kernel(2, 2);
+        assert_eq!(kernel.data(), &[0.25; 4]);
+        assert_eq!(kernel.anchor(), (1, 1));
+    }
+}
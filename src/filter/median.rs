@@ -2,10 +2,12 @@ use image::{GenericImageView, Pixel};
 use definitions::Image;
 use std::cmp::{min, max};
 
-/// Applies a median filter of given `radius` to an image. Each output pixel is the median
-/// of the pixels in a `2 * radius + 1` square of pixels in the input image.
+/// Applies a median filter of given radii to an image. Each output pixel is the median
+/// of the pixels in a `(2 * x_radius + 1) * (2 * y_radius + 1)` rectangle of pixels in
+/// the input image.
 ///
-/// Pads by continuity. Performs O(radius) operations per pixel.
+/// Pads by continuity. Uses the sliding column histogram algorithm of Perreault and Hébert,
+/// so performs a constant number of operations per pixel, independent of the radii.
 ///
 /// # Examples
 /// ```
@@ -40,7 +42,7 @@ use std::cmp::{min, max};
 ///     9, 11, 11
 /// );
 ///
-/// assert_pixels_eq!(median_filter(&image, 1), filtered);
+/// assert_pixels_eq!(median_filter(&image, 1, 1), filtered);
 /// # }
 /// ```
 ///
@@ -72,206 +74,223 @@ use std::cmp::{min, max};
 ///     [ 9,  2, 10], [11,  3, 10], [11,  3, 10]
 /// );
 ///
-/// assert_pixels_eq!(median_filter(&image, 1), filtered);
+/// assert_pixels_eq!(median_filter(&image, 1, 1), filtered);
 /// # }
 /// ```
-pub fn median_filter<P>(image: &Image<P>, radius: u32) -> Image<P>
+pub fn median_filter<I, P>(image: &I, x_radius: u32, y_radius: u32) -> Image<P>
 where
-    P: Pixel<Subpixel=u8> + 'static
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel=u8> + 'static,
 {
     let (width, height) = image.dimensions();
-
-    if width == 0 || height == 0 {
-        return image.clone();
-    }
-
     let mut out = Image::<P>::new(width, height);
-    let r = radius as i32;
-
-    let mut hist = initialise_histogram_for_top_left_pixel(&image, radius);
-    slide_down_column(&mut hist, &image, &mut out, 0, r);
-
-    for x in 1..width {
-        if x % 2 == 0 {
-            slide_right(&mut hist, &image, x, 0, r);
-            slide_down_column(&mut hist, &image, &mut out, x, r);
-        }
-        else {
-            slide_right(&mut hist, &image, x, height - 1, r);
-            slide_up_column(&mut hist, &image, &mut out, x, r);
-        }
-    }
-
+    median_filter_into(image, x_radius, y_radius, &mut out);
     out
 }
 
-fn initialise_histogram_for_top_left_pixel<P>(image: &Image<P>, radius: u32) -> HistSet
+/// Writes the result of applying [`median_filter`](fn.median_filter.html) to `image` to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn median_filter_into<I, P>(image: &I, x_radius: u32, y_radius: u32, out: &mut Image<P>)
 where
-    P: Pixel<Subpixel=u8> + 'static
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel=u8> + 'static,
 {
-    let (width, height) = image.dimensions();
-    let kernel_size = (2 * radius + 1) * (2 * radius + 1);
-    let num_channels = P::channel_count();
-
-    let mut hist = HistSet::new(num_channels, kernel_size);
-    let r = radius as i32;
-
-    for dy in -r..(r + 1) {
-        let py = min(max(0, dy), height as i32 - 1) as u32;
-
-        for dx in -r..(r + 1) {
-            let px = min(max(0, dx), width as i32 - 1) as u32;
-
-            hist.incr(image, px, py);
-        }
-    }
-
-    hist
+    rank_filter_into(image, x_radius, y_radius, 50, out);
 }
 
-fn slide_right<P>(hist: &mut HistSet, image: &Image<P>, x: u32, y: u32, r: i32)
+/// Applies a percentile filter of given radii to an image. Each output pixel is the
+/// `percentile`th percentile of the pixels in a `(2 * x_radius + 1) * (2 * y_radius + 1)`
+/// rectangle of pixels in the input image, i.e. the value with index
+/// `round(percentile * (n - 1) / 100)` when the `n` pixels in the rectangle are sorted.
+///
+/// A percentile of 50 is equivalent to [`median_filter`](fn.median_filter.html), and
+/// percentiles of 0 and 100 are equivalent to [`min_filter`](fn.min_filter.html) and
+/// [`max_filter`](fn.max_filter.html), although those functions are faster.
+///
+/// Pads by continuity. Image channels are handled independently. Performs a constant number
+/// of operations per pixel, independent of the radii.
+///
+/// # Panics
+/// If `percentile` is greater than 100.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::percentile_filter;
+///
+/// let image = gray_image!(
+///     10, 50, 20, 40, 30, 70, 60);
+///
+/// // The second smallest of each horizontal run of five pixels,
+/// // e.g. 20 for the run 10, 50, 20, 40, 30 centred on the third pixel.
+/// assert_pixels_eq!(
+///     percentile_filter(&image, 2, 0, 25),
+///     gray_image!(
+///         10, 10, 20, 30, 30, 40, 60));
+/// # }
+/// ```
+pub fn percentile_filter<I, P>(image: &I, x_radius: u32, y_radius: u32, percentile: u8) -> Image<P>
 where
-    P: Pixel<Subpixel=u8> + 'static
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel=u8> + 'static,
 {
+    assert!(percentile <= 100, "percentile must be at most 100, got {}", percentile);
     let (width, height) = image.dimensions();
-
-    let prev_x = max(0, x as i32 - r - 1) as u32;
-    let next_x = min(x as i32 + r, width as i32 - 1) as u32;
-
-    for dy in -r..(r + 1) {
-        let py = min(max(0, y as i32 + dy), (height - 1) as i32) as u32;
-
-        hist.decr(image, prev_x, py);
-        hist.incr(image, next_x, py);
-    }
+    let mut out = Image::<P>::new(width, height);
+    rank_filter_into(image, x_radius, y_radius, percentile, &mut out);
+    out
 }
 
-fn slide_down_column<P>(hist: &mut HistSet, image: &Image<P>, out: &mut Image<P>, x: u32, r: i32)
+/// Writes the given percentile of each rectangular window to `out`, using the sliding
+/// column histogram algorithm of Perreault and Hébert.
+fn rank_filter_into<I, P>(image: &I, x_radius: u32, y_radius: u32, percentile: u8, out: &mut Image<P>)
 where
-    P: Pixel<Subpixel=u8> + 'static
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel=u8> + 'static,
 {
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
     let (width, height) = image.dimensions();
-    hist.set_to_median(out, x, 0);
 
-    for y in 1..height {
-        let prev_y = max(0, y as i32 - r - 1) as u32;
-        let next_y = min(y as i32 + r, height as i32 - 1) as u32;
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let (rx, ry) = (x_radius as i64, y_radius as i64);
+    let clamp_x = |x: i64| min(max(0, x), width as i64 - 1) as u32;
+    let clamp_y = |y: i64| min(max(0, y), height as i64 - 1) as u32;
+
+    let num_channels = P::channel_count() as usize;
+    let kernel_size = (2 * x_radius as u64 + 1) * (2 * y_radius as u64 + 1);
+    let rank = (percentile as u64 * (kernel_size - 1) + 50) / 100;
+    let mut columns = ColumnHistograms::new(width, num_channels);
+    let mut kernel = HistSet::new(num_channels, rank);
+
+    // Each column histogram initially holds the (padded) column of
+    // height 2 * y_radius + 1 centred on the first row.
+    for x in 0..width {
+        for dy in -ry..(ry + 1) {
+            columns.incr(x, image.get_pixel(x, clamp_y(dy)).channels());
+        }
+    }
 
-        for dx in -r..(r + 1) {
-            let px = min(max(0, x as i32 + dx), (width - 1) as i32) as u32;
+    for y in 0..height {
+        if y > 0 {
+            let prev_y = clamp_y(y as i64 - ry - 1);
+            let next_y = clamp_y(y as i64 + ry);
+            for x in 0..width {
+                columns.decr(x, image.get_pixel(x, prev_y).channels());
+                columns.incr(x, image.get_pixel(x, next_y).channels());
+            }
+        }
 
-            hist.decr(image, px, prev_y);
-            hist.incr(image, px, next_y);
+        kernel.clear();
+        for dx in -rx..(rx + 1) {
+            kernel.add(columns.column(clamp_x(dx)));
         }
+        kernel.set_to_rank(out, 0, y);
 
-        hist.set_to_median(out, x, y);
+        for x in 1..width {
+            kernel.subtract(columns.column(clamp_x(x as i64 - rx - 1)));
+            kernel.add(columns.column(clamp_x(x as i64 + rx)));
+            kernel.set_to_rank(out, x, y);
+        }
     }
 }
 
-fn slide_up_column<P>(hist: &mut HistSet, image: &Image<P>, out: &mut Image<P>, x: u32, r: i32)
-where
-    P: Pixel<Subpixel=u8> + 'static
-{
-    let (width, height) = image.dimensions();
-    hist.set_to_median(out, x, height - 1);
+// A 256-slot histogram per image channel for each column of an image.
+// Used to implement median_filter and percentile_filter.
+struct ColumnHistograms {
+    // The histograms for column x are stored contiguously, starting
+    // at index 256 * num_channels * x.
+    data: Vec<u32>,
+    num_channels: usize,
+}
 
-    for y in (0..(height-1)).rev() {
-        let prev_y = min(y as i32 + r + 1, height as i32 - 1) as u32;
-        let next_y = max(0, y as i32 - r) as u32;
+impl ColumnHistograms {
+    fn new(width: u32, num_channels: usize) -> ColumnHistograms {
+        ColumnHistograms {
+            data: vec![0; 256 * num_channels * width as usize],
+            num_channels,
+        }
+    }
 
-        for dx in -r..(r + 1) {
-            let px = min(max(0, x as i32 + dx), (width - 1) as i32) as u32;
+    fn column(&self, x: u32) -> &[u32] {
+        let len = 256 * self.num_channels;
+        let start = x as usize * len;
+        &self.data[start..start + len]
+    }
 
-            hist.decr(image, px, prev_y);
-            hist.incr(image, px, next_y);
+    fn incr(&mut self, x: u32, channels: &[u8]) {
+        let start = x as usize * 256 * self.num_channels;
+        for (c, v) in channels.iter().enumerate() {
+            self.data[start + 256 * c + *v as usize] += 1;
         }
+    }
 
-        hist.set_to_median(out, x, y);
+    fn decr(&mut self, x: u32, channels: &[u8]) {
+        let start = x as usize * 256 * self.num_channels;
+        for (c, v) in channels.iter().enumerate() {
+            self.data[start + 256 * c + *v as usize] -= 1;
+        }
     }
 }
 
-// A collection of 256-slot histograms, one per image channel.
-// Used to implement median_filter.
+// A collection of 256-slot histograms, one per image channel, stored contiguously.
+// Used to implement median_filter and percentile_filter.
 struct HistSet {
-    // One histogram per image channel.
-    data: Vec<[u32; 256]>,
-    // Calls to `median` will only return the correct answer
-    // if there are `expected_count` entries in the relevant
-    // histogram in `data`.
-    expected_count: u32,
+    data: Vec<u32>,
+    // The index in sorted order of the value returned by `channel_rank`.
+    rank: u64,
 }
 
 impl HistSet {
-    fn new(num_channels: u8, expected_count: u32) -> HistSet {
-        // Can't use vec![[0u32; 256], num_channels as usize]
-        // because arrays of length > 32 aren't cloneable.
-        let mut data = vec![];
-        for _ in 0..num_channels {
-            data.push([0u32; 256]);
+    fn new(num_channels: usize, rank: u64) -> HistSet {
+        HistSet {
+            data: vec![0; 256 * num_channels],
+            rank,
         }
+    }
 
-        HistSet {
-            data: data,
-            expected_count: expected_count
+    fn clear(&mut self) {
+        for d in &mut self.data {
+            *d = 0;
         }
     }
 
-    fn incr<P>(&mut self, image: &Image<P>, x: u32, y: u32) 
-    where
-        P: Pixel<Subpixel=u8> + 'static
-    {
-        unsafe {
-            let pixel = image.unsafe_get_pixel(x, y);
-            let channels = pixel.channels();
-            for c in 0..channels.len() {
-                let p = *channels.get_unchecked(c) as usize;
-                let hist = self.data.get_unchecked_mut(c);
-                *hist.get_unchecked_mut(p) += 1;
-            }
+    fn add(&mut self, hists: &[u32]) {
+        for (d, h) in self.data.iter_mut().zip(hists) {
+            *d += *h;
         }
     }
 
-    fn decr<P>(&mut self, image: &Image<P>, x: u32, y: u32) 
-    where
-        P: Pixel<Subpixel=u8> + 'static
-    {
-        unsafe {
-            let pixel = image.unsafe_get_pixel(x, y);
-            let channels = pixel.channels();
-            for c in 0..channels.len() {
-                let p = *channels.get_unchecked(c) as usize;
-                let hist = self.data.get_unchecked_mut(c);
-                *hist.get_unchecked_mut(p) -= 1;
-            }
+    fn subtract(&mut self, hists: &[u32]) {
+        for (d, h) in self.data.iter_mut().zip(hists) {
+            *d -= *h;
         }
     }
 
-    fn set_to_median<P>(&self, image: &mut Image<P>, x: u32, y: u32)
+    fn set_to_rank<P>(&self, image: &mut Image<P>, x: u32, y: u32)
     where
         P: Pixel<Subpixel=u8> + 'static
     {
-        unsafe {
-            let target = image.get_pixel_mut(x, y);
-            let channels = target.channels_mut();
-            for c in 0..channels.len() {
-                *channels.get_unchecked_mut(c) = self.channel_median(c as u8);
-            }
+        let target = image.get_pixel_mut(x, y);
+        for (c, channel) in target.channels_mut().iter_mut().enumerate() {
+            *channel = self.channel_rank(c);
         }
     }
 
-    fn channel_median(&self, c: u8) -> u8 {
-        let hist = unsafe {
-            self.data.get_unchecked(c as usize)
-        };
+    fn channel_rank(&self, c: usize) -> u8 {
+        let hist = &self.data[256 * c..256 * (c + 1)];
+        let mut count = 0u64;
 
-        let mut count = 0;
-
-        for i in 0..256 {
-            unsafe {
-                count += *hist.get_unchecked(i);
-            }
-
-            if 2 * count >= self.expected_count {
+        for (i, h) in hist.iter().enumerate() {
+            count += *h as u64;
+            if count > self.rank {
                 return i as u8;
             }
         }
@@ -283,6 +302,7 @@ impl HistSet {
 #[cfg(test)]
 mod test {
     use super::*;
+    use filter::{max_filter, min_filter};
     use utils::gray_bench_image;
     use image::{GrayImage, Luma};
     use quickcheck::{quickcheck, TestResult};
@@ -297,7 +317,7 @@ mod test {
             fn $name(b: &mut Bencher) {
                 let image = gray_bench_image($s, $s);
                 b.iter(|| {
-                    let filtered = median_filter(&image, $r);
+                    let filtered = median_filter(&image, $r, $r);
                     black_box(filtered);
                 })
             }
@@ -307,10 +327,11 @@ mod test {
     bench_median_filter!(bench_median_filter_s100_r1, side: 100, radius: 1);
     bench_median_filter!(bench_median_filter_s100_r4, side: 100, radius: 4);
     bench_median_filter!(bench_median_filter_s100_r8, side: 100, radius: 8);
+    bench_median_filter!(bench_median_filter_s100_r32, side: 100, radius: 32);
 
     // Reference implementation of median filter - written to be as simple as possible,
     // to validate faster versions against.
-    fn reference_median_filter(image: &GrayImage, radius: u32) -> GrayImage {
+    fn reference_median_filter(image: &GrayImage, x_radius: u32, y_radius: u32) -> GrayImage {
         let (width, height) = image.dimensions();
 
         if width == 0 || height == 0 {
@@ -318,17 +339,16 @@ mod test {
         }
 
         let mut out = GrayImage::new(width, height);
-        let filter_side = (2 * radius + 1) as usize;
-        let mut neighbors = vec![0u8; filter_side * filter_side];
+        let mut neighbors = vec![0u8; ((2 * x_radius + 1) * (2 * y_radius + 1)) as usize];
 
-        let r = radius as i32;
+        let (rx, ry) = (x_radius as i32, y_radius as i32);
 
         for y in 0..height {
             for x in 0..width {
                 let mut idx = 0;
 
-                for dy in -r..(r + 1) {
-                    for dx in -r..(r + 1) {
+                for dy in -ry..(ry + 1) {
+                    for dx in -rx..(rx + 1) {
                         let px = min(max(0, x as i32 + dx), (width - 1) as i32) as u32;
                         let py = min(max(0, y as i32 + dy), (height - 1) as i32) as u32;
 
@@ -355,16 +375,102 @@ mod test {
 
     #[test]
     fn test_median_filter_matches_reference_implementation() {
-        fn prop(image: GrayTestImage, radius: u32) -> TestResult {
-            let radius = radius % 5;
-            let expected = reference_median_filter(&image.0, radius);
-            let actual = median_filter(&image.0, radius);
+        fn prop(image: GrayTestImage, x_radius: u32, y_radius: u32) -> TestResult {
+            let (x_radius, y_radius) = (x_radius % 5, y_radius % 5);
+            let expected = reference_median_filter(&image.0, x_radius, y_radius);
+            let actual = median_filter(&image.0, x_radius, y_radius);
+
+            match pixel_diff_summary(&actual, &expected) {
+                None => TestResult::passed(),
+                Some(err) => TestResult::error(err),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage, u32, u32) -> TestResult);
+    }
 
+    #[test]
+    fn test_percentile_filter_matches_sorted_windows() {
+        fn prop(image: GrayTestImage, x_radius: u32, y_radius: u32, percentile: u8) -> TestResult {
+            let (x_radius, y_radius, percentile) = (x_radius % 4, y_radius % 4, percentile % 101);
+            let (width, height) = image.0.dimensions();
+            let (rx, ry) = (x_radius as i32, y_radius as i32);
+            let n = ((2 * rx + 1) * (2 * ry + 1)) as usize;
+            let index = (percentile as usize * (n - 1) + 50) / 100;
+            let expected = GrayImage::from_fn(width, height, |x, y| {
+                let mut window = vec![];
+                for dy in -ry..(ry + 1) {
+                    for dx in -rx..(rx + 1) {
+                        let px = min(max(0, x as i32 + dx), width as i32 - 1) as u32;
+                        let py = min(max(0, y as i32 + dy), height as i32 - 1) as u32;
+                        window.push(image.0.get_pixel(px, py)[0]);
+                    }
+                }
+                window.sort();
+                Luma([window[index]])
+            });
+            let actual = percentile_filter(&image.0, x_radius, y_radius, percentile);
             match pixel_diff_summary(&actual, &expected) {
                 None => TestResult::passed(),
                 Some(err) => TestResult::error(err),
             }
         }
-        quickcheck(prop as fn(GrayTestImage, u32) -> TestResult);
+        quickcheck(prop as fn(GrayTestImage, u32, u32, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_percentile_filter_extremes() {
+        let image = gray_bench_image(30, 20);
+        assert_pixels_eq!(percentile_filter(&image, 3, 2, 0), min_filter(&image, 3, 2));
+        assert_pixels_eq!(percentile_filter(&image, 3, 2, 100), max_filter(&image, 3, 2));
+        assert_pixels_eq!(percentile_filter(&image, 3, 2, 50), median_filter(&image, 3, 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_percentile_filter_rejects_percentile_above_100() {
+        percentile_filter(&gray_bench_image(5, 5), 1, 1, 101);
+    }
+
+    #[test]
+    fn test_median_filter_of_view_matches_filter_of_copy() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let (width, height) = image.0.dimensions();
+            let view = image.0.view(width / 3, height / 2, width - width / 3, height - height / 2);
+            let expected = median_filter(&view.to_image(), 1, 2);
+            let actual = median_filter(&view, 1, 2);
+
+            match pixel_diff_summary(&actual, &expected) {
+                None => TestResult::passed(),
+                Some(err) => TestResult::error(err),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_median_filter_with_unequal_radii() {
+        let image = gray_image!(
+            1, 9, 2, 8;
+            7, 3, 6, 4;
+            5, 5, 0, 9);
+
+        // Medians of horizontal runs of three pixels.
+        let horizontal = gray_image!(
+            1, 2, 8, 8;
+            7, 6, 4, 4;
+            5, 5, 5, 9);
+        assert_pixels_eq!(median_filter(&image, 1, 0), horizontal);
+
+        // Medians of vertical runs of three pixels.
+        let vertical = gray_image!(
+            1, 9, 2, 8;
+            5, 5, 2, 8;
+            5, 5, 0, 9);
+        assert_pixels_eq!(median_filter(&image, 0, 1), vertical);
+
+        // Radii larger than the image.
+        let big = median_filter(&image, 10, 20);
+        assert_eq!(big.dimensions(), (4, 3));
+        assert_pixels_eq!(big, reference_median_filter(&image, 10, 20));
     }
 }
@@ -0,0 +1,278 @@
+use image::{GenericImageView, Pixel};
+use definitions::Image;
+use num::Bounded;
+
+/// Applies a minimum filter of given radii to an image. Each output pixel is the minimum
+/// of the pixels in a `(2 * x_radius + 1) * (2 * y_radius + 1)` rectangle of pixels in the
+/// input image. This is a grayscale erosion by a rectangular structuring element.
+///
+/// Pads by continuity. Image channels are handled independently. Uses the algorithm of
+/// van Herk and Gil and Werman, so performs a constant number of comparisons per pixel,
+/// independent of the radii.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::min_filter;
+///
+/// let image = gray_image!(
+///     9, 8, 7, 6;
+///     5, 4, 3, 2;
+///     1, 9, 9, 9);
+///
+/// assert_pixels_eq!(
+///     min_filter(&image, 1, 0),
+///     gray_image!(
+///         8, 7, 6, 6;
+///         4, 3, 2, 2;
+///         1, 1, 9, 9));
+///
+/// assert_pixels_eq!(
+///     min_filter(&image, 1, 1),
+///     gray_image!(
+///         4, 3, 2, 2;
+///         1, 1, 2, 2;
+///         1, 1, 2, 2));
+/// # }
+/// ```
+pub fn min_filter<I, P>(image: &I, x_radius: u32, y_radius: u32) -> Image<P>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+{
+    extremum_filter(image, x_radius, y_radius, P::Subpixel::max_value(), |a, b| if b < a { b } else { a })
+}
+
+/// Applies a maximum filter of given radii to an image. Each output pixel is the maximum
+/// of the pixels in a `(2 * x_radius + 1) * (2 * y_radius + 1)` rectangle of pixels in the
+/// input image. This is a grayscale dilation by a rectangular structuring element.
+///
+/// Pads by continuity. Image channels are handled independently. Uses the algorithm of
+/// van Herk and Gil and Werman, so performs a constant number of comparisons per pixel,
+/// independent of the radii.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::max_filter;
+///
+/// let image = gray_image!(
+///     0, 0, 0, 0, 0;
+///     0, 0, 0, 0, 0;
+///     0, 0, 0, 0, 7);
+///
+/// assert_pixels_eq!(
+///     max_filter(&image, 2, 1),
+///     gray_image!(
+///         0, 0, 0, 0, 0;
+///         0, 0, 7, 7, 7;
+///         0, 0, 7, 7, 7));
+/// # }
+/// ```
+pub fn max_filter<I, P>(image: &I, x_radius: u32, y_radius: u32) -> Image<P>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+{
+    extremum_filter(image, x_radius, y_radius, P::Subpixel::min_value(), |a, b| if b > a { b } else { a })
+}
+
+/// Applies `op` over rectangular windows, one row and then one column at a time.
+/// `identity` must satisfy `op(identity, v) == v` for all `v`.
+fn extremum_filter<I, P, F>(image: &I, x_radius: u32, y_radius: u32, identity: P::Subpixel, op: F) -> Image<P>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+    F: Fn(P::Subpixel, P::Subpixel) -> P::Subpixel,
+{
+    let (width, height) = image.dimensions();
+    let mut out = Image::<P>::new(width, height);
+    if width == 0 || height == 0 {
+        return out;
+    }
+    for y in 0..height {
+        for x in 0..width {
+            out.put_pixel(x, y, image.get_pixel(x, y));
+        }
+    }
+
+    let num_channels = P::channel_count() as usize;
+    let (width, height) = (width as usize, height as usize);
+    let mut line = Vec::with_capacity(width.max(height));
+    let mut filtered = Vec::with_capacity(width.max(height));
+    let mut scratch = RunningExtremum::new(identity);
+    {
+        let data: &mut [P::Subpixel] = &mut out;
+
+        for y in 0..height {
+            for c in 0..num_channels {
+                let index = |x: usize| (y * width + x) * num_channels + c;
+                line.clear();
+                line.extend((0..width).map(|x| data[index(x)]));
+                scratch.apply(&line, x_radius as usize, &op, &mut filtered);
+                for x in 0..width {
+                    data[index(x)] = filtered[x];
+                }
+            }
+        }
+
+        for x in 0..width {
+            for c in 0..num_channels {
+                let index = |y: usize| (y * width + x) * num_channels + c;
+                line.clear();
+                line.extend((0..height).map(|y| data[index(y)]));
+                scratch.apply(&line, y_radius as usize, &op, &mut filtered);
+                for y in 0..height {
+                    data[index(y)] = filtered[y];
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Buffers for computing running minima or maxima of a sequence using the
+/// van Herk/Gil-Werman algorithm.
+struct RunningExtremum<T> {
+    identity: T,
+    prefix: Vec<T>,
+    suffix: Vec<T>,
+}
+
+impl<T: Copy> RunningExtremum<T> {
+    fn new(identity: T) -> RunningExtremum<T> {
+        RunningExtremum {
+            identity,
+            prefix: vec![],
+            suffix: vec![],
+        }
+    }
+
+    /// Writes `op` applied to the values within `radius` of each entry of `values` to `out`.
+    /// Windows are clipped to the bounds of `values`.
+    fn apply<F: Fn(T, T) -> T>(&mut self, values: &[T], radius: usize, op: &F, out: &mut Vec<T>) {
+        let n = values.len();
+        // Larger windows cover every value from every position.
+        let radius = radius.min(n - 1);
+        let k = 2 * radius + 1;
+
+        // The input is padded with `radius` copies of the identity on each side, and then
+        // to a whole number of blocks of length k. prefix[j] and suffix[j] hold the result
+        // of op over the padded values from the start of the block containing j to j, and
+        // from j to the end of its block, respectively.
+        let padded_len = (n + 2 * radius + k - 1) / k * k;
+        let identity = self.identity;
+        let padded = |j: usize| if j >= radius && j < radius + n { values[j - radius] } else { identity };
+
+        self.prefix.clear();
+        self.prefix.resize(padded_len, identity);
+        self.suffix.clear();
+        self.suffix.resize(padded_len, identity);
+        let mut start = 0;
+        while start < padded_len {
+            let end = start + k;
+            self.prefix[start] = padded(start);
+            for j in start + 1..end {
+                self.prefix[j] = op(self.prefix[j - 1], padded(j));
+            }
+            self.suffix[end - 1] = padded(end - 1);
+            for j in (start..end - 1).rev() {
+                self.suffix[j] = op(self.suffix[j + 1], padded(j));
+            }
+            start = end;
+        }
+
+        // The window for entry i covers padded entries i to i + k - 1, which span at most
+        // two blocks.
+        out.clear();
+        out.extend((0..n).map(|i| op(self.suffix[i], self.prefix[i + k - 1])));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+    use quickcheck::{quickcheck, TestResult};
+    use property_testing::GrayTestImage;
+    use utils::{gray_bench_image, pixel_diff_summary};
+    use test::{Bencher, black_box};
+    use std::cmp::{min, max};
+
+    // Reference implementation of min and max filters, for 8-bit grayscale images.
+    fn reference_filter<F: Fn(u8, u8) -> u8>(image: &GrayImage, x_radius: u32, y_radius: u32, op: F) -> GrayImage {
+        let (width, height) = image.dimensions();
+        let (rx, ry) = (x_radius as i64, y_radius as i64);
+        GrayImage::from_fn(width, height, |x, y| {
+            let mut result = image.get_pixel(x, y)[0];
+            for dy in -ry..(ry + 1) {
+                for dx in -rx..(rx + 1) {
+                    let px = min(max(0, x as i64 + dx), width as i64 - 1) as u32;
+                    let py = min(max(0, y as i64 + dy), height as i64 - 1) as u32;
+                    result = op(result, image.get_pixel(px, py)[0]);
+                }
+            }
+            Luma([result])
+        })
+    }
+
+    #[test]
+    fn test_min_and_max_filters_match_reference_implementation() {
+        fn prop(image: GrayTestImage, x_radius: u32, y_radius: u32) -> TestResult {
+            let (x_radius, y_radius) = (x_radius % 7, y_radius % 7);
+            let expected_min = reference_filter(&image.0, x_radius, y_radius, min);
+            let expected_max = reference_filter(&image.0, x_radius, y_radius, max);
+            if let Some(err) = pixel_diff_summary(&min_filter(&image.0, x_radius, y_radius), &expected_min) {
+                return TestResult::error(err);
+            }
+            match pixel_diff_summary(&max_filter(&image.0, x_radius, y_radius), &expected_max) {
+                None => TestResult::passed(),
+                Some(err) => TestResult::error(err),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage, u32, u32) -> TestResult);
+    }
+
+    #[test]
+    fn test_min_max_filters_radius_larger_than_image() {
+        let image = gray_image!(
+            3, 9, 2;
+            7, 1, 8);
+        assert_pixels_eq!(min_filter(&image, 10, 20), GrayImage::from_pixel(3, 2, Luma([1])));
+        assert_pixels_eq!(max_filter(&image, 10, 20), GrayImage::from_pixel(3, 2, Luma([9])));
+        assert_pixels_eq!(min_filter(&image, 0, 0), image);
+        assert_eq!(max_filter(&GrayImage::new(0, 4), 2, 2).dimensions(), (0, 4));
+    }
+
+    #[test]
+    fn test_min_max_filters_channels_and_floats() {
+        let image: RgbImage = ImageBuffer::from_fn(4, 1, |x, _| Rgb([x as u8, 10 - x as u8, 5]));
+        assert_eq!(
+            max_filter(&image, 1, 0).into_raw(),
+            vec![1, 10, 5, 2, 10, 5, 3, 9, 5, 3, 8, 5]
+        );
+
+        let floats: ImageBuffer<Luma<f32>, Vec<f32>> = ImageBuffer::from_raw(4, 1, vec![-1.5, 2.0, -3.0, 0.5]).unwrap();
+        assert_eq!(min_filter(&floats, 1, 0).into_raw(), vec![-1.5, -3.0, -3.0, -3.0]);
+        assert_eq!(max_filter(&floats, 1, 3).into_raw(), vec![2.0, 2.0, 2.0, 0.5]);
+    }
+
+    #[bench]
+    fn bench_min_filter_s100_r1(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| black_box(min_filter(&image, 1, 1)));
+    }
+
+    #[bench]
+    fn bench_min_filter_s100_r32(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        b.iter(|| black_box(min_filter(&image, 32, 32)));
+    }
+}
@@ -1,17 +1,36 @@
 //! Functions for filtering images.
 
+mod bilateral;
+pub use self::bilateral::{bilateral_filter, bilateral_filter_approx};
+
+mod fixed;
+pub use self::fixed::{FixedKernel, Kernel3x3, Kernel5x5};
+
+mod gabor;
+pub use self::gabor::{gabor_bank, gabor_bank_response, GaborResponse};
+
+mod kernel;
+pub use self::kernel::GaborOptions;
+
 mod median;
-pub use self::median::median_filter;
+pub use self::median::{median_filter, median_filter_into, percentile_filter};
+
+mod min_max;
+pub use self::min_max::{max_filter, min_filter};
 
-use image::{GrayImage, GenericImage, GenericImageView, ImageBuffer, Luma, Pixel, Primitive};
+use image::{GrayImage, Luma, Pixel, Primitive};
 
-use integral_image::{column_running_sum, row_running_sum};
+use integral_image::{integral_image_as, row_running_sum};
 use map::{WithChannel, ChannelMap};
-use definitions::{Clamp, Image};
+use definitions::{Clamp, Image, MaybeSend, MaybeSync};
+use fft::{correlate, fft_is_faster};
 use num::Num;
 
 use conv::ValueInto;
 use math::cast;
+use parallel::for_each_row;
+use simd::{self, cast_rows, cast_slice, is_type};
+use view::{pad, Border};
 use std::cmp::{min, max};
 use std::f32;
 
@@ -26,45 +45,145 @@ use std::f32;
 // TODO: directly instead of using an integral image.
 // TODO: more formats!
 pub fn box_filter(image: &GrayImage, x_radius: u32, y_radius: u32) -> Image<Luma<u8>> {
+    let mut out = image.clone();
+    box_filter_mut(&mut out, x_radius, y_radius);
+    out
+}
+
+/// Writes the result of applying [`box_filter`](fn.box_filter.html) to `image` to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn box_filter_into(image: &GrayImage, x_radius: u32, y_radius: u32, out: &mut GrayImage) {
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    out.copy_from_slice(image);
+    box_filter_mut(out, x_radius, y_radius);
+}
 
+/// Applies [`box_filter`](fn.box_filter.html) to an image in place.
+pub fn box_filter_mut(image: &mut GrayImage, x_radius: u32, y_radius: u32) {
     let (width, height) = image.dimensions();
-    let mut out = ImageBuffer::new(width, height);
+    if width == 0 || height == 0 {
+        return;
+    }
 
-    let kernel_width = 2 * x_radius + 1;
-    let kernel_height = 2 * y_radius + 1;
+    let (w, h) = (width as usize, height as usize);
+    let (x_radius, y_radius) = (x_radius as usize, y_radius as usize);
+    let kernel_width = 2 * x_radius as u32 + 1;
+    let kernel_height = 2 * y_radius as u32 + 1;
 
-    let mut row_buffer = vec![0; (width + 2 * x_radius) as usize];
+    // TODO: This way we pay rounding errors for each of the
+    // TODO: x and y convolutions. Is there a better way?
+    let mut row_buffer = vec![0; w + 2 * x_radius];
+    let mut window_sums = vec![0u32; w];
     for y in 0..height {
-        row_running_sum(image, y, &mut row_buffer, x_radius);
-        let val = row_buffer[(2 * x_radius) as usize] / kernel_width;
-        unsafe {
-            out.unsafe_put_pixel(0, y, Luma([val as u8]));
-        }
-        for x in 1..width {
-            // TODO: This way we pay rounding errors for each of the
-            // TODO: x and y convolutions. Is there a better way?
-            let u = (x + 2 * x_radius) as usize;
-            let l = (x - 1) as usize;
-            let val = (row_buffer[u] - row_buffer[l]) / kernel_width;
-            unsafe {
-                out.unsafe_put_pixel(x, y, Luma([val as u8]));
-            }
+        row_running_sum(image, y, &mut row_buffer, x_radius as u32);
+        window_sums[0] = row_buffer[2 * x_radius];
+        let windows = row_buffer[2 * x_radius + 1..].iter().zip(row_buffer.iter());
+        for (s, (u, l)) in window_sums[1..].iter_mut().zip(windows) {
+            *s = u - l;
         }
+        let row = &mut (**image)[y as usize * w..(y as usize + 1) * w];
+        divide_into(&window_sums, kernel_width, row);
     }
 
-    let mut col_buffer = vec![0; (height + 2 * y_radius) as usize];
-    for x in 0..width {
-        column_running_sum(&out, x, &mut col_buffer, y_radius);
-        let val = col_buffer[(2 * y_radius) as usize] / kernel_height;
-        unsafe {
-            out.unsafe_put_pixel(x, 0, Luma([val as u8]));
+    // Slide a window of rows down the image, keeping a running sum for each column.
+    // This computes the same sums as running down each column in turn, but reads
+    // the image in memory order.
+    let rows = image.to_vec();
+    let row = |y: usize| &rows[y * w..(y + 1) * w];
+    let clamp_row = |y: isize| max(0, min(y, h as isize - 1)) as usize;
+
+    let mut sums = vec![0u32; w];
+    for y in -(y_radius as isize)..y_radius as isize + 1 {
+        for (s, p) in sums.iter_mut().zip(row(clamp_row(y))) {
+            *s += *p as u32;
         }
-        for y in 1..height {
-            let u = (y + 2 * y_radius) as usize;
-            let l = (y - 1) as usize;
-            let val = (col_buffer[u] - col_buffer[l]) / kernel_height;
-            unsafe {
-                out.unsafe_put_pixel(x, y, Luma([val as u8]));
+    }
+
+    for (y, out_row) in image.chunks_mut(w).enumerate() {
+        divide_into(&sums, kernel_height, out_row);
+        if y + 1 < h {
+            let entering = row(clamp_row((y + y_radius + 1) as isize));
+            let leaving = row(clamp_row(y as isize - y_radius as isize));
+            for ((s, e), l) in sums.iter_mut().zip(entering).zip(leaving) {
+                *s = *s + *e as u32 - *l as u32;
+            }
+        }
+    }
+}
+
+// Writes each sum divided by divisor, rounded down, to out.
+fn divide_into(sums: &[u32], divisor: u32, out: &mut [u8]) {
+    let n = simd::divide_u32_u8(sums, divisor, out);
+    for (p, s) in out[n..].iter_mut().zip(&sums[n..]) {
+        *p = (s / divisor) as u8;
+    }
+}
+
+/// Computes the mean of the pixels in the `(2 * x_radius + 1) * (2 * y_radius + 1)` rectangle
+/// around each pixel of an image, for images with any number of `u8` or `u16` channels.
+///
+/// Unlike [`box_filter`](fn.box_filter.html), which truncates the result of each of its
+/// horizontal and vertical passes, the means are computed exactly from an integral image and
+/// returned unrounded. Pads by continuity. Performs a constant number of operations per pixel,
+/// independent of the radii.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::mean_filter;
+///
+/// let image = rgb_image!(
+///     [1, 10, 0], [2, 20, 0], [6, 30, 1]);
+///
+/// let expected = rgb_image!(type: f32,
+///     [4.0 / 3.0, 40.0 / 3.0, 0.0], [3.0, 20.0, 1.0 / 3.0], [14.0 / 3.0, 80.0 / 3.0, 2.0 / 3.0]);
+///
+/// assert_pixels_eq!(mean_filter(&image, 1, 0), expected);
+/// # }
+/// ```
+pub fn mean_filter<P>(image: &Image<P>, x_radius: u32, y_radius: u32) -> Image<ChannelMap<P, f32>>
+where
+    P: Pixel + WithChannel<u64> + WithChannel<f32> + MaybeSync + 'static,
+    P::Subpixel: MaybeSync,
+    u64: From<P::Subpixel>,
+{
+    let (width, height) = image.dimensions();
+    let mut out = Image::<ChannelMap<P, f32>>::new(width, height);
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    let integral: Image<ChannelMap<P, u64>> = integral_image_as::<u64, P>(image);
+    let num_channels = P::channel_count() as usize;
+    let area = ((2 * x_radius as u64 + 1) * (2 * y_radius as u64 + 1)) as f64;
+
+    // Sum of channel c over the rectangle [left, right] * [top, bottom].
+    let rect_sum = |c: usize, left: u32, top: u32, right: u32, bottom: u32| -> u64 {
+        let at = |x: u32, y: u32| integral.get_pixel(x, y).channels()[c];
+        (at(right + 1, bottom + 1) + at(left, top)) - (at(right + 1, top) + at(left, bottom + 1))
+    };
+
+    let mut x_spans = Vec::with_capacity(3);
+    let mut y_spans = Vec::with_capacity(3);
+
+    for y in 0..height {
+        padded_spans(y, y_radius, height, &mut y_spans);
+        for x in 0..width {
+            padded_spans(x, x_radius, width, &mut x_spans);
+            let pixel = out.get_pixel_mut(x, y);
+            for c in 0..num_channels {
+                let mut sum = 0u64;
+                for &(top, bottom, y_count) in &y_spans {
+                    for &(left, right, x_count) in &x_spans {
+                        sum += x_count * y_count * rect_sum(c, left, top, right, bottom);
+                    }
+                }
+                pixel.channels_mut()[c] = (sum as f64 / area) as f32;
             }
         }
     }
@@ -72,17 +191,88 @@ pub fn box_filter(image: &GrayImage, x_radius: u32, y_radius: u32) -> Image<Luma
     out
 }
 
-/// A 2D kernel, used to filter images via convolution.
-pub struct Kernel<'a, K: 'a> {
-    data: &'a [K],
+// Decomposes the window [centre - radius, centre + radius], padded by continuity,
+// into spans (start, end, multiplicity) of in-bounds indices: the first index repeated
+// for positions before the start, the in-bounds indices, and the last index repeated
+// for positions after the end.
+fn padded_spans(centre: u32, radius: u32, len: u32, spans: &mut Vec<(u32, u32, u64)>) {
+    let (centre, radius, last) = (centre as i64, radius as i64, len as i64 - 1);
+    spans.clear();
+    if radius > centre {
+        spans.push((0, 0, (radius - centre) as u64));
+    }
+    spans.push((max(0, centre - radius) as u32, min(last, centre + radius) as u32, 1));
+    if centre + radius > last {
+        spans.push((last as u32, last as u32, (centre + radius - last) as u64));
+    }
+}
+
+/// A 2D kernel, used to filter images via correlation.
+///
+/// The output of filtering an image with a kernel at pixel `(x, y)` is the sum over all kernel
+/// entries `k(i, j)` of `k(i, j) * image(x + i - anchor_x, y + j - anchor_y)`, where
+/// `(anchor_x, anchor_y)` is the kernel's [`anchor`](#method.anchor). By default the anchor is
+/// at `(width / 2, height / 2)`.
+///
+/// See the `impl` blocks below for constructors for common kernels, and
+/// [`separable_factors`](#method.separable_factors) for checking whether a kernel can be
+/// applied more efficiently via [`separable_filter`](fn.separable_filter.html).
+///
+/// 2D kernels are accepted by the methods of this type, such as [`filter`](#method.filter)
+/// and [`convolve`](#method.convolve), and by [`filter_clamped`](fn.filter_clamped.html),
+/// [`filter_clamped_into`](fn.filter_clamped_into.html) and
+/// [`filter_clamped_with_border`](fn.filter_clamped_with_border.html). The 1D filters
+/// [`separable_filter`](fn.separable_filter.html),
+/// [`separable_filter_equal`](fn.separable_filter_equal.html),
+/// [`horizontal_filter`](fn.horizontal_filter.html) and
+/// [`vertical_filter`](fn.vertical_filter.html), and their `_into` and `_mut` variants, take
+/// slices anchored at their centres instead. To apply a separable kernel with its default
+/// anchor using them, pass them its `separable_factors`:
+///
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::definitions::Image;
+/// use imageproc::filter::{separable_filter, Kernel};
+///
+/// let image = gray_image!(type: f32,
+///     1.0, 2.0, 3.0;
+///     4.0, 5.0, 6.0);
+///
+/// let kernel = Kernel::box_kernel(3, 3);
+/// let (horizontal, vertical) = kernel.separable_factors(1e-5).unwrap();
+///
+/// let direct: Image<Luma<f32>> = kernel.filter(&image, |out, v| *out = v);
+/// let separable = separable_filter(&image, &horizontal, &vertical);
+/// for (d, s) in direct.iter().zip(separable.iter()) {
+///     assert!((d - s).abs() < 1e-5);
+/// }
+/// # }
+/// ```
+///
+/// The Gaussian blurs, such as [`gaussian_blur_f32`](fn.gaussian_blur_f32.html), construct
+/// their own kernels from a standard deviation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel<K> {
+    data: Vec<K>,
     width: u32,
     height: u32,
+    anchor: (u32, u32),
 }
 
-impl<'a, K: Num + Copy + 'a> Kernel<'a, K> {
+impl<K: Num + Copy> Kernel<K> {
     /// Construct a kernel from a slice and its dimensions. The input slice is
     /// in row-major form.
-    pub fn new(data: &'a [K], width: u32, height: u32) -> Kernel<'a, K> {
+    pub fn new(data: &[K], width: u32, height: u32) -> Kernel<K> {
+        Kernel::from_vec(data.to_vec(), width, height)
+    }
+
+    /// Construct a kernel from a vector and its dimensions, without copying.
+    /// The input vector is in row-major form.
+    pub fn from_vec(data: Vec<K>, width: u32, height: u32) -> Kernel<K> {
         assert!(
             width * height == data.len() as u32,
             format!(
@@ -95,48 +285,326 @@ impl<'a, K: Num + Copy + 'a> Kernel<'a, K> {
             data: data,
             width: width,
             height: height,
+            anchor: (width / 2, height / 2),
         }
     }
 
+    /// Returns this kernel with its anchor at `(x, y)`.
+    ///
+    /// # Panics
+    /// If `(x, y)` is not a position in the kernel.
+    pub fn with_anchor(self, x: u32, y: u32) -> Kernel<K> {
+        assert!(x < self.width && y < self.height, "anchor must lie within the kernel");
+        Kernel { anchor: (x, y), ..self }
+    }
+
+    /// Width of the kernel.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the kernel.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The position in the kernel that is aligned with each output pixel.
+    pub fn anchor(&self) -> (u32, u32) {
+        self.anchor
+    }
+
+    /// The entries of the kernel, in row-major order.
+    pub fn data(&self) -> &[K] {
+        &self.data
+    }
+
+    /// Returns this kernel rotated by 180 degrees, with its anchor moved to match.
+    ///
+    /// Correlating with the flipped kernel is equivalent to convolving with this kernel.
+    pub fn flipped(&self) -> Kernel<K> {
+        let mut data = self.data.clone();
+        data.reverse();
+        Kernel {
+            data,
+            width: self.width,
+            height: self.height,
+            anchor: (self.width - 1 - self.anchor.0, self.height - 1 - self.anchor.1),
+        }
+    }
+
+    /// Returns the 2d convolution of an image with this kernel. Intermediate calculations are
+    /// performed at type K, and the results converted to pixel Q via f. Pads by continuity.
+    ///
+    /// This differs from [`filter`](#method.filter) only in that the kernel is
+    /// [`flipped`](#method.flipped) before being applied, so the two agree for symmetric kernels.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::filter::Kernel;
+    ///
+    /// let image = gray_image!(0, 0, 1, 0, 0);
+    ///
+    /// // Convolving with an impulse reproduces the kernel.
+    /// let kernel = Kernel::new(&[1, 2, 3], 3, 1);
+    /// let convolved = kernel.convolve(&image, |c, a| *c = a);
+    ///
+    /// assert_pixels_eq!(
+    ///     convolved,
+    ///     gray_image!(type: i32,
+    ///         0, 1, 2, 3, 0));
+    /// # }
+    /// ```
+    pub fn convolve<P, F, Q>(&self, image: &Image<P>, f: F) -> Image<Q>
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        self.flipped().filter(image, f)
+    }
+
     /// Returns 2d correlation of an image. Intermediate calculations are performed
     /// at type K, and the results converted to pixel Q via f. Pads by continuity.
-    pub fn filter<P, F, Q>(&self, image: &Image<P>, mut f: F) -> Image<Q>
+    ///
+    /// For single-channel images with `u8` subpixels and `i32` or `f32` kernels, or `f32`
+    /// subpixels and `f32` kernels, most outputs are computed using SIMD instructions if
+    /// the current CPU supports them. This is detected at runtime and doesn't change the results.
+    ///
+    /// For `f32` and `f64` kernels that are large relative to the image, the outputs are
+    /// instead computed using the fast Fourier transform, in `f64` arithmetic. The results then
+    /// differ from those of direct summation by floating point rounding errors. For inputs and
+    /// kernel entries of magnitude at most 1, these are typically below `1e-12` for `f64`
+    /// kernels and `1e-5` for `f32` kernels. Use [`filter_fft`](#method.filter_fft) to
+    /// compute the outputs using the Fourier transform regardless of the kernel size.
+    pub fn filter<P, F, Q>(&self, image: &Image<P>, f: F) -> Image<Q>
     where
         P: Pixel + 'static,
         <P as Pixel>::Subpixel: ValueInto<K>,
         Q: Pixel + 'static,
-        F: FnMut(&mut Q::Subpixel, K) -> (),
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
     {
         let (width, height) = image.dimensions();
         let mut out = Image::<Q>::new(width, height);
-        let num_channels = P::channel_count() as usize;
-        let zero = K::zero();
-        let mut acc = vec![zero; num_channels];
-        let (k_width, k_height) = (self.width, self.height);
+        self.filter_into(image, f, &mut out);
+        out
+    }
 
-        for y in 0..height {
-            for x in 0..width {
-                for k_y in 0..k_height {
-                    let y_p = min(
-                        height + height - 1,
-                        max(height, height + y + k_y - k_height / 2),
-                    ) - height;
-                    for k_x in 0..k_width {
-                        let x_p = min(
-                            width + width - 1,
-                            max(width, width + x + k_x - k_width / 2),
-                        ) - width;
-                        let (p, k) = unsafe {
-                            (
-                                image.unsafe_get_pixel(x_p, y_p),
-                                *self.data.get_unchecked((k_y * k_width + k_x) as usize),
-                            )
-                        };
-                        accumulate(&mut acc, &p, k);
+    /// Writes the result of [`filter`](#method.filter) to `out`.
+    ///
+    /// # Panics
+    /// If `out` and `image` have different dimensions.
+    pub fn filter_into<P, F, Q>(&self, image: &Image<P>, mut f: F, out: &mut Image<Q>)
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+        let (width, height) = image.dimensions();
+        let padded = ((width + self.width).saturating_sub(1), (height + self.height).saturating_sub(1));
+        if fft_is_faster(padded, (self.width, self.height)) && self.correlate_fft::<P, F, Q>(image, &mut f, out) {
+            return;
+        }
+
+        let row_len = width as usize * Q::channel_count() as usize;
+        let mut acc = vec![K::zero(); width as usize * P::channel_count() as usize];
+
+        let source = Source { raw: image, width, height };
+        if row_len > 0 {
+            for (y, row) in out.chunks_mut(row_len).enumerate() {
+                self.filter_row::<P, F, Q>(&source, y as u32, row, &mut acc, &mut f);
+            }
+        }
+    }
+
+    /// Returns the 2d correlation of an image, computed using the fast Fourier transform if
+    /// `K` is `f32` or `f64`. Otherwise this is equivalent to [`filter`](#method.filter).
+    ///
+    /// [`filter`](#method.filter) already uses the Fourier transform when this is estimated
+    /// to be quicker. This method forces its use regardless of the kernel size, with the same
+    /// rounding errors.
+    pub fn filter_fft<P, F, Q>(&self, image: &Image<P>, f: F) -> Image<Q>
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        let (width, height) = image.dimensions();
+        let mut out = Image::<Q>::new(width, height);
+        self.filter_fft_into(image, f, &mut out);
+        out
+    }
+
+    /// Writes the result of [`filter_fft`](#method.filter_fft) to `out`.
+    ///
+    /// # Panics
+    /// If `out` and `image` have different dimensions.
+    pub fn filter_fft_into<P, F, Q>(&self, image: &Image<P>, mut f: F, out: &mut Image<Q>)
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+        if !self.correlate_fft::<P, F, Q>(image, &mut f, out) {
+            self.filter_into(image, f, out);
+        }
+    }
+
+    /// Returns the 2d correlation of an image with this kernel, evaluated only at every
+    /// `stride`th pixel in each direction. This is equivalent to subsampling the output
+    /// of [`filter`](#method.filter), but only computes the outputs that are kept.
+    ///
+    /// Output pixel `(x, y)` is computed at input pixel `(stride * x, stride * y)`, so the
+    /// output has width `ceil(width / stride)` and height `ceil(height / stride)`. Pads by continuity.
+    ///
+    /// # Panics
+    /// If `stride` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::filter::Kernel;
+    ///
+    /// let image = gray_image!(
+    ///     1, 2, 3, 4, 5;
+    ///     6, 7, 8, 9, 10);
+    ///
+    /// // Horizontal differences, at every other pixel.
+    /// let kernel = Kernel::new(&[-1, 0, 1], 3, 1);
+    /// let filtered = kernel.filter_strided(&image, 2, |c, a| *c = a);
+    ///
+    /// assert_pixels_eq!(
+    ///     filtered,
+    ///     gray_image!(type: i32,
+    ///         1, 2, 1));
+    /// # }
+    /// ```
+    pub fn filter_strided<P, F, Q>(&self, image: &Image<P>, stride: u32, f: F) -> Image<Q>
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        self.filter_spaced(image, stride, 1, f)
+    }
+
+    /// Returns the 2d correlation of an image with this kernel with its taps spaced
+    /// `dilation` pixels apart, i.e. with `dilation - 1` zeros inserted between
+    /// adjacent kernel entries in each direction. This is the "à trous" filtering
+    /// used by stationary wavelet transforms. Pads by continuity.
+    ///
+    /// A dilation of 1 gives the same results as [`filter`](#method.filter).
+    ///
+    /// # Panics
+    /// If `dilation` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::filter::Kernel;
+    ///
+    /// let image = gray_image!(1, 2, 4, 8, 16);
+    ///
+    /// // Differences between pixels two apart.
+    /// let kernel = Kernel::new(&[-1, 0, 1], 3, 1);
+    /// let filtered = kernel.filter_dilated(&image, 2, |c, a| *c = a);
+    ///
+    /// assert_pixels_eq!(
+    ///     filtered,
+    ///     gray_image!(type: i32,
+    ///         3, 7, 15, 14, 12));
+    /// # }
+    /// ```
+    pub fn filter_dilated<P, F, Q>(&self, image: &Image<P>, dilation: u32, f: F) -> Image<Q>
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        self.filter_spaced(image, 1, dilation, f)
+    }
+
+    /// Combines [`filter_strided`](#method.filter_strided) and
+    /// [`filter_dilated`](#method.filter_dilated): correlates an image with this kernel
+    /// with taps spaced `dilation` pixels apart, at every `stride`th pixel in each direction.
+    ///
+    /// # Panics
+    /// If `stride` or `dilation` is zero.
+    pub fn filter_spaced<P, F, Q>(&self, image: &Image<P>, stride: u32, dilation: u32, mut f: F) -> Image<Q>
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        assert!(stride > 0, "stride must be non-zero");
+        assert!(dilation > 0, "dilation must be non-zero");
+
+        let (width, height) = image.dimensions();
+        let out_len = |len: u32| if len == 0 { 0 } else { (len - 1) / stride + 1 };
+        let (out_width, out_height) = (out_len(width), out_len(height));
+        let mut out = Image::<Q>::new(out_width, out_height);
+        if out_width == 0 || out_height == 0 {
+            return out;
+        }
+
+        // The input coordinates read for each output coordinate and kernel offset,
+        // clamped to the image bounds.
+        let taps = |out_len: u32, len: u32, k_len: u32, anchor: u32| -> Vec<u32> {
+            let mut taps = Vec::with_capacity((out_len * k_len) as usize);
+            for o in 0..out_len {
+                let centre = o as i64 * stride as i64;
+                for k in 0..k_len {
+                    let offset = (k as i64 - anchor as i64) * dilation as i64;
+                    taps.push(max(0, min(centre + offset, len as i64 - 1)) as u32);
+                }
+            }
+            taps
+        };
+        let x_taps = taps(out_width, width, self.width, self.anchor.0);
+        let y_taps = taps(out_height, height, self.height, self.anchor.1);
+
+        let zero = K::zero();
+        let out_channels = Q::channel_count() as usize;
+        let mut acc = vec![zero; P::channel_count() as usize];
+        let row_len = out_width as usize * out_channels;
+
+        for (y, row) in out.chunks_mut(row_len).enumerate() {
+            let y_taps = &y_taps[y * self.height as usize..(y + 1) * self.height as usize];
+            for (x, out_pixel) in row.chunks_mut(out_channels).enumerate() {
+                let x_taps = &x_taps[x * self.width as usize..(x + 1) * self.width as usize];
+                for (k_row, y_p) in self.data.chunks(self.width as usize).zip(y_taps) {
+                    for (k, x_p) in k_row.iter().zip(x_taps) {
+                        let p = unsafe { pixel_at::<P>(image, width, *x_p, *y_p) };
+                        accumulate(&mut acc, p, *k);
                     }
                 }
-                let out_channels = out.get_pixel_mut(x, y).channels_mut();
-                for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
+                for (a, c) in acc.iter_mut().zip(out_pixel.iter_mut()) {
                     f(c, *a);
                     *a = zero;
                 }
@@ -145,6 +613,261 @@ impl<'a, K: Num + Copy + 'a> Kernel<'a, K> {
 
         out
     }
+
+    // Computes row y of the output of filter for source. acc must have one entry
+    // per subpixel in a row of source, all zero.
+    fn filter_row<P, F, Q>(
+        &self,
+        source: &Source<P::Subpixel>,
+        y: u32,
+        row: &mut [Q::Subpixel],
+        acc: &mut [K],
+        f: &mut F,
+    ) where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        let zero = K::zero();
+        let Source { raw, width, height } = *source;
+        let (start, count) = self.filter_row_vectorised::<P, F, Q>(source, y, row, f);
+
+        let in_channels = P::channel_count() as usize;
+        let out_channels = Q::channel_count() as usize;
+        let row_len = width as usize * in_channels;
+        let (anchor_x, anchor_y) = (self.anchor.0 as isize, self.anchor.1 as usize);
+        // The pixels not written by filter_row_vectorised.
+        let remaining = [(0, start as usize), ((start + count) as usize, width as usize)];
+
+        // Each kernel entry adds a shifted copy of a source row to the accumulators.
+        // A kernel with zero width has no data, so the chunk size only needs to be non-zero.
+        for (k_y, k_row) in self.data.chunks(max(self.width as usize, 1)).enumerate() {
+            let y_p = min(height as usize - 1, (y as usize + k_y).saturating_sub(anchor_y));
+            let source_row = &raw[y_p * row_len..(y_p + 1) * row_len];
+            for (k_x, k) in k_row.iter().enumerate() {
+                for &(x_start, x_end) in &remaining {
+                    accumulate_shifted(
+                        &mut acc[x_start * in_channels..x_end * in_channels],
+                        source_row,
+                        (x_start, x_end),
+                        k_x as isize - anchor_x,
+                        in_channels,
+                        *k,
+                    );
+                }
+            }
+        }
+
+        for &(x_start, x_end) in &remaining {
+            for x in x_start..x_end {
+                let pixel_acc = &mut acc[x * in_channels..(x + 1) * in_channels];
+                for (a, c) in pixel_acc.iter_mut().zip(row[x * out_channels..(x + 1) * out_channels].iter_mut()) {
+                    f(c, *a);
+                    *a = zero;
+                }
+            }
+        }
+    }
+
+    // Writes the output of filter to out using the fast Fourier transform, if the kernel
+    // has a floating point type. Returns false without modifying out otherwise.
+    fn correlate_fft<P, F, Q>(&self, raw: &[P::Subpixel], f: &mut F, out: &mut Image<Q>) -> bool
+    where
+        P: Pixel + 'static,
+        <P as Pixel>::Subpixel: ValueInto<K>,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        let (width, height) = out.dimensions();
+        if width == 0 || height == 0 || self.data.is_empty() || !(is_type::<K, f32>() || is_type::<K, f64>()) {
+            return false;
+        }
+
+        // Padding by continuity, so that the valid correlation of the padded image
+        // with the kernel has the same size as the input.
+        let (k_width, k_height) = (self.width as usize, self.height as usize);
+        let (padded_width, padded_height) = (width as usize + k_width - 1, height as usize + k_height - 1);
+
+        let clamp = |p: usize, anchor: u32, len: u32| min(p.saturating_sub(anchor as usize), len as usize - 1) as u32;
+        let kernel: Vec<f64> = self.data.iter().map(|k| float_to_f64(*k)).collect();
+        let in_channels = P::channel_count() as usize;
+        let out_channels = Q::channel_count() as usize;
+
+        for c in 0..min(in_channels, out_channels) {
+            let mut padded = Vec::with_capacity(padded_width * padded_height);
+            for y in 0..padded_height {
+                let y_p = clamp(y, self.anchor.1, height);
+                for x in 0..padded_width {
+                    let x_p = clamp(x, self.anchor.0, width);
+                    let p = unsafe { pixel_at::<P>(raw, width, x_p, y_p) };
+                    padded.push(float_to_f64::<K>(cast(p.channels()[c])));
+                }
+            }
+
+            let filtered = correlate(&padded, padded_width, padded_height, &kernel, k_width, k_height);
+            for (value, out_pixel) in filtered.iter().zip(out.chunks_mut(out_channels)) {
+                f(&mut out_pixel[c], float_from_f64(*value));
+            }
+        }
+
+        true
+    }
+
+    // Computes the outputs in row y whose inputs don't require padding using the loops
+    // from the simd module, if they support the pixel and kernel types. Returns the x
+    // coordinate of the first output computed and the number of outputs computed.
+    fn filter_row_vectorised<P, F, Q>(
+        &self,
+        source: &Source<P::Subpixel>,
+        y: u32,
+        row: &mut [Q::Subpixel],
+        f: &mut F,
+    ) -> (u32, u32)
+    where
+        P: Pixel + 'static,
+        Q: Pixel + 'static,
+        F: FnMut(&mut Q::Subpixel, K),
+        K: 'static,
+    {
+        let input_is_u8 = is_type::<P::Subpixel, u8>();
+        let input_is_f32 = is_type::<P::Subpixel, f32>();
+        let kernel_is_i32 = is_type::<K, i32>();
+        let kernel_is_f32 = is_type::<K, f32>();
+        let Source { raw, width, height } = *source;
+
+        if P::channel_count() != 1
+            || Q::channel_count() != 1
+            || width < self.width
+            || !(input_is_u8 && (kernel_is_i32 || kernel_is_f32) || input_is_f32 && kernel_is_f32)
+        {
+            return (0, 0);
+        }
+
+        let row_len = width as usize;
+        let k_width = self.width as usize;
+        let rows: Vec<&[P::Subpixel]> = (0..self.height)
+            .map(|k_y| {
+                let y_p = min(height - 1, (y + k_y).saturating_sub(self.anchor.1));
+                let start = y_p as usize * row_len;
+                &raw[start..start + row_len]
+            })
+            .collect();
+
+        let start = self.anchor.0;
+        let mut emit = |acc: &[K]| {
+            for (c, a) in row[start as usize..].iter_mut().zip(acc) {
+                f(c, *a);
+            }
+            acc.len() as u32
+        };
+
+        let interior_len = row_len + 1 - k_width;
+        let count = if input_is_u8 && kernel_is_i32 {
+            let mut acc = vec![0i32; interior_len];
+            let n = simd::correlate_u8_i32(&cast_rows(&rows), cast_slice(&self.data), k_width, &mut acc);
+            emit(cast_slice(&acc[..n]))
+        } else if input_is_u8 {
+            let mut acc = vec![0f32; interior_len];
+            let n = simd::correlate_u8_f32(&cast_rows(&rows), cast_slice(&self.data), k_width, &mut acc);
+            emit(cast_slice(&acc[..n]))
+        } else {
+            let mut acc = vec![0f32; interior_len];
+            let n = simd::correlate_f32_f32(&cast_rows(&rows), cast_slice(&self.data), k_width, &mut acc);
+            emit(cast_slice(&acc[..n]))
+        };
+
+        (start, count)
+    }
+}
+
+// The subpixels of an image being filtered by Kernel::filter_row, and its dimensions.
+#[derive(Clone, Copy)]
+struct Source<'a, S: 'a> {
+    raw: &'a [S],
+    width: u32,
+    height: u32,
+}
+
+// Whether correlate_rows_vectorised supports subpixels of type S and the given kernel.
+fn is_vectorised<S: 'static, K: 'static>(kernel: &[K]) -> bool {
+    let input_is_u8 = is_type::<S, u8>();
+    let input_is_f32 = is_type::<S, f32>();
+    let kernel_is_i32 = is_type::<K, i32>();
+    let kernel_is_f32 = is_type::<K, f32>();
+    !kernel.is_empty() && (input_is_u8 && (kernel_is_i32 || kernel_is_f32) || input_is_f32 && kernel_is_f32)
+}
+
+// Computes leading entries of the correlation of single-channel rows with a kernel using
+// the loops from the simd module, clamping results to the subpixel type. The caller must
+// check is_vectorised first. Returns the number of entries written to out.
+fn correlate_rows_vectorised<S, K>(rows: &[&[S]], kernel: &[K], k_width: usize, out: &mut [S]) -> usize
+where
+    S: Clamp<K> + 'static,
+    K: Copy + 'static,
+{
+    fn write_clamped<S: Clamp<K>, K: Copy>(acc: &[K], out: &mut [S]) -> usize {
+        for (o, a) in out.iter_mut().zip(acc) {
+            *o = S::clamp(*a);
+        }
+        acc.len()
+    }
+
+    if is_type::<S, u8>() && is_type::<K, i32>() {
+        let mut acc = vec![0i32; out.len()];
+        let n = simd::correlate_u8_i32(&cast_rows(rows), cast_slice(kernel), k_width, &mut acc);
+        write_clamped(cast_slice::<i32, K>(&acc[..n]), out)
+    } else if is_type::<S, u8>() {
+        let mut acc = vec![0f32; out.len()];
+        let n = simd::correlate_u8_f32(&cast_rows(rows), cast_slice(kernel), k_width, &mut acc);
+        write_clamped(cast_slice::<f32, K>(&acc[..n]), out)
+    } else {
+        let mut acc = vec![0f32; out.len()];
+        let n = simd::correlate_f32_f32(&cast_rows(rows), cast_slice(kernel), k_width, &mut acc);
+        write_clamped(cast_slice::<f32, K>(&acc[..n]), out)
+    }
+}
+
+// Converts a value of type f32 or f64 to f64. Panics if T is any other type.
+fn float_to_f64<T: 'static + Copy>(x: T) -> f64 {
+    if is_type::<T, f32>() {
+        cast_slice::<T, f32>(&[x])[0] as f64
+    } else {
+        cast_slice::<T, f64>(&[x])[0]
+    }
+}
+
+// Converts an f64 to a value of type T, which must be f32 or f64.
+fn float_from_f64<T: 'static + Copy>(x: f64) -> T {
+    if is_type::<T, f32>() {
+        cast_slice::<f32, T>(&[x as f32])[0]
+    } else {
+        cast_slice::<f64, T>(&[x])[0]
+    }
+}
+
+// Returns the pixel at (x, y) of an image with the given width and subpixels raw.
+// The caller must ensure that (x, y) is in bounds.
+#[inline]
+unsafe fn pixel_at<P: Pixel>(raw: &[P::Subpixel], width: u32, x: u32, y: u32) -> &P {
+    let num_channels = P::channel_count() as usize;
+    let start = (y as usize * width as usize + x as usize) * num_channels;
+    P::from_slice(raw.get_unchecked(start..start + num_channels))
+}
+
+// Writes the accumulated channel values to out_channels and resets the accumulator.
+#[inline]
+fn flush<S, K>(acc: &mut [K], out_channels: &mut [S])
+where
+    S: Clamp<K>,
+    K: Num + Copy,
+{
+    for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
+        *c = S::clamp(*a);
+        *a = K::zero();
+    }
 }
 
 #[inline]
@@ -168,245 +891,555 @@ fn gaussian_kernel_f32(sigma: f32) -> Vec<f32> {
 /// Blurs an image using a Gaussian of standard deviation sigma.
 /// The kernel used has type f32 and all intermediate calculations are performed
 /// at this type.
+///
+/// The blur is applied as a horizontal and then a vertical 1d filter. Each 1d kernel
+/// is truncated at radius `ceil(2 * sigma)`, so has length `2 * ceil(2 * sigma) + 1`.
+/// Pads by continuity; see [`gaussian_blur_f32_with_border`](fn.gaussian_blur_f32_with_border.html)
+/// for other ways of handling the image boundary.
 // TODO: Integer type kernel, approximations via repeated box filter.
 pub fn gaussian_blur_f32<P>(image: &Image<P>, sigma: f32) -> Image<P>
 where
     P: Pixel + 'static,
-    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
 {
     let kernel = gaussian_kernel_f32(sigma);
     separable_filter_equal(image, &kernel)
 }
 
+/// Writes the result of [`gaussian_blur_f32`](fn.gaussian_blur_f32.html) to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn gaussian_blur_f32_into<P>(image: &Image<P>, sigma: f32, out: &mut Image<P>)
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let kernel = gaussian_kernel_f32(sigma);
+    separable_filter_into(image, &kernel, &kernel, out);
+}
+
+/// Applies [`gaussian_blur_f32`](fn.gaussian_blur_f32.html) to an image in place.
+pub fn gaussian_blur_f32_mut<P>(image: &mut Image<P>, sigma: f32)
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let kernel = gaussian_kernel_f32(sigma);
+    separable_filter_mut(image, &kernel, &kernel);
+}
+
+/// Blurs an image using a Gaussian of standard deviation sigma, reading pixels outside
+/// the image according to `border`.
+///
+/// With `Border::Replicate` this gives the same results as
+/// [`gaussian_blur_f32`](fn.gaussian_blur_f32.html).
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::filter::gaussian_blur_f32_with_border;
+/// use imageproc::view::Border;
+///
+/// let image = gray_image!(type: f32,
+///     1.0, 1.0, 1.0;
+///     1.0, 1.0, 1.0);
+///
+/// // Pixels near the boundary are darkened by the zero padding.
+/// let blurred = gaussian_blur_f32_with_border(&image, 1.0, Border::Constant(Luma([0.0])));
+/// assert!(blurred.get_pixel(0, 0)[0] < blurred.get_pixel(1, 0)[0]);
+/// # }
+/// ```
+pub fn gaussian_blur_f32_with_border<P>(image: &Image<P>, sigma: f32, border: Border<P>) -> Image<P>
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    if let Border::Replicate = border {
+        return gaussian_blur_f32(image, sigma);
+    }
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return image.clone();
+    }
+
+    // Padding by the kernel radius means that every input read when computing
+    // the central region lies inside the padded image.
+    let kernel = gaussian_kernel_f32(sigma);
+    let radius = (kernel.len() / 2) as u32;
+    let padded = pad(image, radius, radius, radius, radius, border);
+    let blurred = separable_filter_equal(&padded, &kernel);
+
+    Image::<P>::from_fn(width, height, |x, y| *blurred.get_pixel(x + radius, y + radius))
+}
+
 /// Returns 2d correlation of view with the outer product of the 1d
 /// kernels `h_kernel` and `v_kernel`.
+///
+/// Single-channel images use SIMD instructions for the same combinations of subpixel
+/// and kernel types as [`Kernel::filter`](struct.Kernel.html#method.filter).
 pub fn separable_filter<P, K>(image: &Image<P>, h_kernel: &[K], v_kernel: &[K]) -> Image<P>
 where
     P: Pixel + 'static,
-    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
-    K: Num + Copy,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
 {
     let h = horizontal_filter(image, h_kernel);
     vertical_filter(&h, v_kernel)
 }
 
+/// Writes the result of [`separable_filter`](fn.separable_filter.html) to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn separable_filter_into<P, K>(image: &Image<P>, h_kernel: &[K], v_kernel: &[K], out: &mut Image<P>)
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    horizontal_filter_into(image, h_kernel, out);
+    vertical_filter_mut(out, v_kernel);
+}
+
+/// Applies [`separable_filter`](fn.separable_filter.html) to an image in place.
+pub fn separable_filter_mut<P, K>(image: &mut Image<P>, h_kernel: &[K], v_kernel: &[K])
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    horizontal_filter_mut(image, h_kernel);
+    vertical_filter_mut(image, v_kernel);
+}
+
 /// Returns 2d correlation of an image with the outer product of the 1d
 /// kernel filter with itself.
 pub fn separable_filter_equal<P, K>(image: &Image<P>, kernel: &[K]) -> Image<P>
 where
     P: Pixel + 'static,
-    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
-    K: Num + Copy,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
 {
     separable_filter(image, kernel, kernel)
 }
 
+/// Returns 2d correlation of an image with a kernel. Intermediate calculations are
+/// performed at type K, and the results clamped to subpixel type S. Pads by continuity.
+///
+/// See [`Kernel::filter`](struct.Kernel.html#method.filter) for the combinations of pixel and
+/// kernel types that use SIMD instructions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::filter::{filter_clamped, Kernel};
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6;
+///     7, 8, 9);
+///
+/// // Negative values are clamped to zero.
+/// let filtered = filter_clamped::<_, _, u8>(&image, &Kernel::laplacian());
+/// assert_pixels_eq!(
+///     filtered,
+///     gray_image!(
+///         4, 3, 2;
+///         1, 0, 0;
+///         0, 0, 0));
+/// # }
+/// ```
+pub fn filter_clamped<P, K, S>(image: &Image<P>, kernel: &Kernel<K>) -> Image<ChannelMap<P, S>>
+where
+    P::Subpixel: ValueInto<K> + MaybeSync,
+    S: Clamp<K> + Primitive + MaybeSend + 'static,
+    P: WithChannel<S> + 'static,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = Image::<ChannelMap<P, S>>::new(width, height);
+    filter_clamped_into(image, kernel, &mut out);
+    out
+}
+
+/// Writes the result of [`filter_clamped`](fn.filter_clamped.html) to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn filter_clamped_into<P, K, S>(image: &Image<P>, kernel: &Kernel<K>, out: &mut Image<ChannelMap<P, S>>)
+where
+    P::Subpixel: ValueInto<K> + MaybeSync,
+    S: Clamp<K> + Primitive + MaybeSend + 'static,
+    P: WithChannel<S> + 'static,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    let (width, height) = image.dimensions();
+    let source = Source { raw: image, width, height };
+
+    for_each_row(out, |y, row| {
+        let mut acc = vec![K::zero(); width as usize * P::channel_count() as usize];
+        let mut f = |channel: &mut S, a: K| *channel = S::clamp(a);
+        kernel.filter_row::<P, _, ChannelMap<P, S>>(&source, y, row, &mut acc, &mut f);
+    });
+}
+
+/// Returns 2d correlation of an image with a kernel, reading pixels outside the image
+/// according to `border`. Intermediate calculations are performed at type K, and the
+/// results clamped to subpixel type S.
+///
+/// With `Border::Replicate` this gives the same results as
+/// [`filter_clamped`](fn.filter_clamped.html).
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::filter::{filter_clamped_with_border, Kernel};
+/// use imageproc::view::Border;
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let kernel = Kernel::new(&[1, 1, 1], 3, 1);
+/// let filtered = filter_clamped_with_border::<_, _, u8>(&image, &kernel, Border::Constant(Luma([0])));
+/// assert_pixels_eq!(
+///     filtered,
+///     gray_image!(
+///         3, 6, 5;
+///         9, 15, 11));
+/// # }
+/// ```
+pub fn filter_clamped_with_border<P, K, S>(
+    image: &Image<P>,
+    kernel: &Kernel<K>,
+    border: Border<P>,
+) -> Image<ChannelMap<P, S>>
+where
+    P::Subpixel: ValueInto<K> + MaybeSync,
+    S: Clamp<K> + Primitive + MaybeSend + 'static,
+    P: WithChannel<S> + 'static,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    if let Border::Replicate = border {
+        return filter_clamped(image, kernel);
+    }
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Image::<ChannelMap<P, S>>::new(width, height);
+    }
+
+    let (left, top) = kernel.anchor;
+    let (right, bottom) = (kernel.width - 1 - left, kernel.height - 1 - top);
+    let padded = pad(image, left, right, top, bottom, border);
+    let filtered = filter_clamped(&padded, kernel);
+
+    Image::<ChannelMap<P, S>>::from_fn(width, height, |x, y| *filtered.get_pixel(x + left, y + top))
+}
+
 /// Returns 2d correlation of an image with a 3x3 row-major kernel. Intermediate calculations are
 /// performed at type K, and the results clamped to subpixel type S. Pads by continuity.
+///
+/// See [`Kernel::filter`](struct.Kernel.html#method.filter) for the combinations of pixel and
+/// kernel types that use SIMD instructions.
 pub fn filter3x3<P, K, S>(image: &Image<P>, kernel: &[K]) -> Image<ChannelMap<P, S>>
 where
-    P::Subpixel: ValueInto<K>,
-    S: Clamp<K> + Primitive + 'static,
+    P::Subpixel: ValueInto<K> + MaybeSync,
+    S: Clamp<K> + Primitive + MaybeSend + 'static,
     P: WithChannel<S> + 'static,
-    K: Num + Copy,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    filter_clamped(image, &Kernel::new(kernel, 3, 3))
+}
+
+/// Writes the result of [`filter3x3`](fn.filter3x3.html) to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn filter3x3_into<P, K, S>(image: &Image<P>, kernel: &[K], out: &mut Image<ChannelMap<P, S>>)
+where
+    P::Subpixel: ValueInto<K> + MaybeSync,
+    S: Clamp<K> + Primitive + MaybeSend + 'static,
+    P: WithChannel<S> + 'static,
+    K: Num + Copy + MaybeSync + 'static,
 {
-    let kernel = Kernel::new(kernel, 3, 3);
-    kernel.filter(image, |channel, acc| *channel = S::clamp(acc))
+    filter_clamped_into(image, &Kernel::new(kernel, 3, 3), out)
 }
 
 ///	Returns horizontal correlations between an image and a 1d kernel.
 /// Pads by continuity. Intermediate calculations are performed at
 /// type K.
+///
+/// Single-channel images use SIMD instructions for the same combinations of subpixel
+/// and kernel types as [`Kernel::filter`](struct.Kernel.html#method.filter).
 pub fn horizontal_filter<P, K>(image: &Image<P>, kernel: &[K]) -> Image<P>
 where
     P: Pixel + 'static,
-    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
-    K: Num + Copy,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
 {
-    // Don't replace this with a call to Kernel::filter without
-    // checking the benchmark results. At the time of writing this
-    // specialised implementation is faster.
     let (width, height) = image.dimensions();
     let mut out = Image::<P>::new(width, height);
-    let zero = K::zero();
-    let mut acc = vec![zero; P::channel_count() as usize];
-    let k_width = kernel.len() as i32;
+    horizontal_filter_into(image, kernel, &mut out);
+    out
+}
 
-    // Typically the image side will be much larger than the kernel length.
-    // In that case we can remove a lot of bounds checks for most pixels.
-    if k_width >= width as i32 {
-        for y in 0..height {
-            for x in 0..width {
-                for (i, k) in kernel.iter().enumerate() {
-                    let x_unchecked = (x as i32) + i as i32 - k_width / 2;
-                    let x_p = max(0, min(x_unchecked, width as i32 - 1)) as u32;
-                    let p = unsafe { image.unsafe_get_pixel(x_p, y) };
-                    accumulate(&mut acc, &p, *k);
-                }
+/// Writes the result of [`horizontal_filter`](fn.horizontal_filter.html) to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn horizontal_filter_into<P, K>(image: &Image<P>, kernel: &[K], out: &mut Image<P>)
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    let width = image.width();
+    let row_len = width as usize * P::channel_count() as usize;
+    let raw: &[P::Subpixel] = image;
+
+    for_each_row(out, |y, row| {
+        let start = y as usize * row_len;
+        horizontal_filter_row::<P, K>(&raw[start..start + row_len], width, kernel, row);
+    });
+}
 
-                let out_channels = out.get_pixel_mut(x, y).channels_mut();
-                for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                    *c = <P as Pixel>::Subpixel::clamp(*a);
-                    *a = zero;
-                }
-            }
-        }
+/// Applies [`horizontal_filter`](fn.horizontal_filter.html) to an image in place.
+///
+/// Rows are processed serially, and only a single row of the input is copied at a time.
+pub fn horizontal_filter_mut<P, K>(image: &mut Image<P>, kernel: &[K])
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    let (width, height) = image.dimensions();
+    let row_len = width as usize * P::channel_count() as usize;
+    if row_len == 0 || height == 0 {
+        return;
+    }
 
-        return out;
+    let raw: &mut [P::Subpixel] = &mut *image;
+    let mut source = vec![raw[0]; row_len];
+
+    for row in raw.chunks_mut(row_len) {
+        source.copy_from_slice(row);
+        horizontal_filter_row::<P, K>(&source, width, kernel, row);
     }
+}
 
+// Writes the horizontal correlation of a single image row with kernel to out.
+fn horizontal_filter_row<P, K>(source: &[P::Subpixel], width: u32, kernel: &[K], out: &mut [P::Subpixel])
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
+    K: Num + Copy + 'static,
+{
+    // Don't replace this with a call to Kernel::filter without
+    // checking the benchmark results. At the time of writing this
+    // specialised implementation is faster.
+    let num_channels = P::channel_count() as usize;
+    let k_width = kernel.len() as i32;
     let half_k = k_width / 2;
-
-    for y in 0..height {
-        // Left margin - need to check lower bound only
-        for x in 0..half_k {
+    let mut acc = vec![K::zero(); num_channels];
+
+    if num_channels == 1 && width > 0 && is_vectorised::<P::Subpixel, K>(kernel) {
+        // Pad the row by continuity so that every output is an interior output.
+        let padded: Vec<P::Subpixel> = (0..width as i32 + k_width - 1)
+            .map(|j| source[max(0, min(j - half_k, width as i32 - 1)) as usize])
+            .collect();
+        let computed = correlate_rows_vectorised(&[&padded[..]], kernel, kernel.len(), out);
+        for (x, out_channels) in out.chunks_mut(num_channels).enumerate().skip(computed) {
             for (i, k) in kernel.iter().enumerate() {
-                let x_unchecked = (x as i32) + i as i32 - k_width / 2;
-                let x_p = max(0, x_unchecked) as u32;
-                let p = unsafe { image.unsafe_get_pixel(x_p, y) };
-                accumulate(&mut acc, &p, *k);
-            }
-
-            let out_channels = out.get_pixel_mut(x as u32, y).channels_mut();
-            for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                *c = <P as Pixel>::Subpixel::clamp(*a);
-                *a = zero;
+                accumulate(&mut acc, P::from_slice(&padded[x + i..x + i + 1]), *k);
             }
+            flush(&mut acc, out_channels);
         }
+        return;
+    }
+
+    let mut out_channels = out.chunks_mut(num_channels);
 
-        // Neither margin - don't need bounds check on either side
-        for x in half_k..(width as i32 - half_k) {
+    // Typically the image side will be much larger than the kernel length.
+    // In that case we can remove a lot of bounds checks for most pixels.
+    if k_width >= width as i32 {
+        for x in 0..width as i32 {
             for (i, k) in kernel.iter().enumerate() {
-                let x_unchecked = (x as i32) + i as i32 - k_width / 2;
-                let x_p = x_unchecked as u32;
-                let p = unsafe { image.unsafe_get_pixel(x_p, y) };
-                accumulate(&mut acc, &p, *k);
+                let x_unchecked = x + i as i32 - half_k;
+                let x_p = max(0, min(x_unchecked, width as i32 - 1)) as u32;
+                let p = unsafe { pixel_at::<P>(source, width, x_p, 0) };
+                accumulate(&mut acc, p, *k);
             }
+            flush(&mut acc, out_channels.next().unwrap());
+        }
+        return;
+    }
 
-            let out_channels = out.get_pixel_mut(x as u32, y).channels_mut();
-            for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                *c = <P as Pixel>::Subpixel::clamp(*a);
-                *a = zero;
-            }
+    // Left margin - need to check lower bound only
+    for x in 0..half_k {
+        for (i, k) in kernel.iter().enumerate() {
+            let x_unchecked = x + i as i32 - half_k;
+            let x_p = max(0, x_unchecked) as u32;
+            let p = unsafe { pixel_at::<P>(source, width, x_p, 0) };
+            accumulate(&mut acc, p, *k);
         }
+        flush(&mut acc, out_channels.next().unwrap());
+    }
 
-        // Right margin - need to check upper bound only
-        for x in (width as i32 - half_k)..(width as i32) {
-            for (i, k) in kernel.iter().enumerate() {
-                let x_unchecked = (x as i32) + i as i32 - k_width / 2;
-                let x_p = min(x_unchecked, width as i32 - 1) as u32;
-                let p = unsafe { image.unsafe_get_pixel(x_p, y) };
-                accumulate(&mut acc, &p, *k);
-            }
+    // Neither margin - don't need bounds check on either side
+    for x in half_k..(width as i32 - half_k) {
+        for (i, k) in kernel.iter().enumerate() {
+            let x_p = (x + i as i32 - half_k) as u32;
+            let p = unsafe { pixel_at::<P>(source, width, x_p, 0) };
+            accumulate(&mut acc, p, *k);
+        }
+        flush(&mut acc, out_channels.next().unwrap());
+    }
 
-            let out_channels = out.get_pixel_mut(x as u32, y).channels_mut();
-            for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                *c = <P as Pixel>::Subpixel::clamp(*a);
-                *a = zero;
-            }
+    // Right margin - need to check upper bound only
+    for x in (width as i32 - half_k)..(width as i32) {
+        for (i, k) in kernel.iter().enumerate() {
+            let x_unchecked = x + i as i32 - half_k;
+            let x_p = min(x_unchecked, width as i32 - 1) as u32;
+            let p = unsafe { pixel_at::<P>(source, width, x_p, 0) };
+            accumulate(&mut acc, p, *k);
         }
+        flush(&mut acc, out_channels.next().unwrap());
     }
-
-    out
 }
 
 ///	Returns horizontal correlations between an image and a 1d kernel.
 /// Pads by continuity.
+///
+/// Single-channel images use SIMD instructions for the same combinations of subpixel
+/// and kernel types as [`Kernel::filter`](struct.Kernel.html#method.filter).
 pub fn vertical_filter<P, K>(image: &Image<P>, kernel: &[K]) -> Image<P>
 where
     P: Pixel + 'static,
-    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
-    K: Num + Copy,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = Image::<P>::new(width, height);
+    vertical_filter_into(image, kernel, &mut out);
+    out
+}
+
+/// Writes the result of [`vertical_filter`](fn.vertical_filter.html) to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn vertical_filter_into<P, K>(image: &Image<P>, kernel: &[K], out: &mut Image<P>)
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K> + MaybeSend + MaybeSync,
+    K: Num + Copy + MaybeSync + 'static,
 {
     // Don't replace this with a call to Kernel::filter without
     // checking the benchmark results. At the time of writing this
     // specialised implementation is faster.
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
     let (width, height) = image.dimensions();
-    let mut out = Image::<P>::new(width, height);
-    let zero = K::zero();
-    let mut acc = vec![zero; P::channel_count() as usize];
-    let k_height = kernel.len() as i32;
-
-    // Typically the image side will be much larger than the kernel length.
-    // In that case we can remove a lot of bounds checks for most pixels.
-    if k_height >= height as i32 {
-        for y in 0..height {
-            for x in 0..width {
-                for (i, k) in kernel.iter().enumerate() {
-                    let y_unchecked = (y as i32) + i as i32 - k_height / 2;
-                    let y_p = max(0, min(y_unchecked, height as i32 - 1)) as u32;
-                    let p = unsafe { image.unsafe_get_pixel(x, y_p) };
-                    accumulate(&mut acc, &p, *k);
-                }
-
-                let out_channels = out.get_pixel_mut(x, y).channels_mut();
-                for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                    *c = <P as Pixel>::Subpixel::clamp(*a);
-                    *a = zero;
-                }
-            }
-        }
-
-        return out;
-    }
-
-    let half_k = k_height / 2;
-
-    // Top margin - need to check lower bound only
-    for y in 0..half_k {
-        for x in 0..width {
-            for (i, k) in kernel.iter().enumerate() {
-                let y_unchecked = (y as i32) + i as i32 - k_height / 2;
-                let y_p = max(0, y_unchecked) as u32;
-                let p = unsafe { image.unsafe_get_pixel(x, y_p) };
-                accumulate(&mut acc, &p, *k);
-            }
+    let row_len = width as usize * P::channel_count() as usize;
+    let raw: &[P::Subpixel] = image;
+    let half_k = kernel.len() as i32 / 2;
+
+    for_each_row(out, |y, row| {
+        // The source row for each kernel entry is the same for every pixel in
+        // this output row, so we only need to clamp it to the image once.
+        let sources: Vec<&[P::Subpixel]> = (0..kernel.len() as i32)
+            .map(|i| {
+                let y_p = max(0, min(y as i32 + i - half_k, height as i32 - 1)) as usize;
+                &raw[y_p * row_len..(y_p + 1) * row_len]
+            })
+            .collect();
+        vertical_filter_row::<P, K>(&sources, width, kernel, row);
+    });
+}
 
-            let out_channels = out.get_pixel_mut(x, y as u32).channels_mut();
-            for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                *c = <P as Pixel>::Subpixel::clamp(*a);
-                *a = zero;
-            }
-        }
+/// Applies [`vertical_filter`](fn.vertical_filter.html) to an image in place.
+///
+/// Rows are processed serially, and only `kernel.len() / 2 + 1` rows of the input
+/// are copied at a time.
+pub fn vertical_filter_mut<P, K>(image: &mut Image<P>, kernel: &[K])
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
+    K: Num + Copy + 'static,
+{
+    let (width, height) = image.dimensions();
+    let row_len = width as usize * P::channel_count() as usize;
+    if row_len == 0 || height == 0 {
+        return;
     }
 
-    // Neither margin - don't need bounds check on either side
-    for y in half_k..(height as i32 - half_k) {
-        for x in 0..width {
-            for (i, k) in kernel.iter().enumerate() {
-                let y_unchecked = (y as i32) + i as i32 - k_height / 2;
-                let y_p = y_unchecked as u32;
-                let p = unsafe { image.unsafe_get_pixel(x, y_p) };
-                accumulate(&mut acc, &p, *k);
-            }
-
-            let out_channels = out.get_pixel_mut(x, y as u32).channels_mut();
-            for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                *c = <P as Pixel>::Subpixel::clamp(*a);
-                *a = zero;
-            }
-        }
+    // Outputs in row y depend on input rows y - half_k to y + half_k. When computing
+    // row y the rows below it are still unmodified, and the original values of the
+    // other rows are kept in a ring buffer, with row r stored in slot r % ring_len.
+    let half_k = kernel.len() as i32 / 2;
+    let ring_len = half_k as usize + 1;
+    let raw: &mut [P::Subpixel] = &mut *image;
+    let mut ring = vec![raw[0]; ring_len * row_len];
+
+    for y in 0..height as usize {
+        let start = y * row_len;
+        let slot = (y % ring_len) * row_len;
+        ring[slot..slot + row_len].copy_from_slice(&raw[start..start + row_len]);
+
+        let (above, below) = raw.split_at_mut(start + row_len);
+        let row = &mut above[start..];
+        let sources: Vec<&[P::Subpixel]> = (0..kernel.len() as i32)
+            .map(|i| {
+                let y_p = max(0, min(y as i32 + i - half_k, height as i32 - 1)) as usize;
+                if y_p <= y {
+                    let slot = (y_p % ring_len) * row_len;
+                    &ring[slot..slot + row_len]
+                } else {
+                    let offset = (y_p - y - 1) * row_len;
+                    &below[offset..offset + row_len]
+                }
+            })
+            .collect();
+        vertical_filter_row::<P, K>(&sources, width, kernel, row);
     }
+}
 
-    // Right margin - need to check upper bound only
-    for y in (height as i32 - half_k)..(height as i32) {
-        for x in 0..width {
-            for (i, k) in kernel.iter().enumerate() {
-                let y_unchecked = (y as i32) + i as i32 - k_height / 2;
-                let y_p = min(y_unchecked, height as i32 - 1) as u32;
-                let p = unsafe { image.unsafe_get_pixel(x, y_p) };
-                accumulate(&mut acc, &p, *k);
-            }
-
-            let out_channels = out.get_pixel_mut(x, y as u32).channels_mut();
-            for (a, c) in acc.iter_mut().zip(out_channels.iter_mut()) {
-                *c = <P as Pixel>::Subpixel::clamp(*a);
-                *a = zero;
-            }
+// Writes the correlation of kernel with the given source rows to out.
+fn vertical_filter_row<P, K>(sources: &[&[P::Subpixel]], width: u32, kernel: &[K], out: &mut [P::Subpixel])
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<K> + Clamp<K>,
+    K: Num + Copy + 'static,
+{
+    let num_channels = P::channel_count() as usize;
+    let mut acc = vec![K::zero(); num_channels];
+
+    let computed = if num_channels == 1 && is_vectorised::<P::Subpixel, K>(kernel) {
+        correlate_rows_vectorised(sources, kernel, 1, out)
+    } else {
+        0
+    };
+
+    for (x, out_channels) in out.chunks_mut(num_channels).enumerate().skip(computed) {
+        for (source, k) in sources.iter().zip(kernel.iter()) {
+            let p = unsafe { pixel_at::<P>(source, width, x as u32, 0) };
+            accumulate(&mut acc, p, *k);
         }
+        flush(&mut acc, out_channels);
     }
-
-    out
 }
 
 fn accumulate<P, K>(acc: &mut [K], pixel: &P, weight: K)
@@ -420,14 +1453,57 @@ where
     }
 }
 
+// Adds weight times the pixel at x + shift in source_row, clamped to the row, to the
+// accumulators for each x in xs. acc holds the channels of the pixels in xs.
+fn accumulate_shifted<S, K>(acc: &mut [K], source_row: &[S], xs: (usize, usize), shift: isize, num_channels: usize, weight: K)
+where
+    S: ValueInto<K> + Copy,
+    K: Num + Copy,
+{
+    let (x_start, x_end) = (xs.0 as isize, xs.1 as isize);
+    if x_start >= x_end {
+        return;
+    }
+    let width = (source_row.len() / num_channels) as isize;
+    let add = |acc: &mut [K], pixels: &[S]| {
+        for (a, p) in acc.iter_mut().zip(pixels) {
+            *a = *a + cast(*p) * weight;
+        }
+    };
+    let first = &source_row[..num_channels];
+    let last = &source_row[(width as usize - 1) * num_channels..];
+    let offset = |x: isize| (x - x_start) as usize * num_channels;
+
+    // The pixels for which x + shift lies within the row.
+    let inner_start = min(max(x_start, -shift), x_end);
+    let inner_end = min(max(width - shift, inner_start), x_end);
+
+    for x in x_start..inner_start {
+        add(&mut acc[offset(x)..offset(x + 1)], first);
+    }
+    if inner_start < inner_end {
+        add(
+            &mut acc[offset(inner_start)..offset(inner_end)],
+            &source_row[(inner_start + shift) as usize * num_channels..(inner_end + shift) as usize * num_channels],
+        );
+    }
+    for x in inner_end..x_end {
+        add(&mut acc[offset(x)..offset(x + 1)], last);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use utils::{gray_bench_image, rgb_bench_image};
+    use utils::{gray_bench_image, pixel_diff_summary, rgb_bench_image};
+    use property_testing::GrayTestImage;
+    use quickcheck::{quickcheck, TestResult};
     use image::{GenericImage, GrayImage, ImageBuffer, Luma, Rgb};
     use definitions::{Clamp, Image};
     use image::imageops::blur;
     use test::{Bencher, black_box};
+    use view::get_pixel_with_border;
+    use map::map_subpixels;
     use std::cmp::{min, max};
 
     #[test]
@@ -449,6 +1525,108 @@ mod test {
         assert_pixels_eq!(box_filter(&image, 1, 1), expected);
     }
 
+    // Computes the mean along each row and then along each column, clamping
+    // coordinates to the image and truncating after each pass.
+    fn box_filter_reference(image: &GrayImage, x_radius: u32, y_radius: u32) -> GrayImage {
+        let (width, height) = image.dimensions();
+        let clamp = |v: i32, len: u32| max(0, min(v, len as i32 - 1)) as u32;
+        let mean = |sum: u32, radius: u32| (sum / (2 * radius + 1)) as u8;
+        let rows = ImageBuffer::from_fn(width, height, |x, y| {
+            let r = x_radius as i32;
+            let sum = (-r..r + 1).map(|d| image.get_pixel(clamp(x as i32 + d, width), y)[0] as u32).sum();
+            Luma([mean(sum, x_radius)])
+        });
+        ImageBuffer::from_fn(width, height, |x, y| {
+            let r = y_radius as i32;
+            let sum = (-r..r + 1).map(|d| rows.get_pixel(x, clamp(y as i32 + d, height))[0] as u32).sum();
+            Luma([mean(sum, y_radius)])
+        })
+    }
+
+    #[test]
+    fn test_box_filter_matches_reference_implementation() {
+        fn prop(image: GrayTestImage, x_radius: u8, y_radius: u8) -> TestResult {
+            let (x_radius, y_radius) = (x_radius as u32 % 8, y_radius as u32 % 8);
+            let expected = box_filter_reference(&image.0, x_radius, y_radius);
+            let actual = box_filter(&image.0, x_radius, y_radius);
+            match pixel_diff_summary(&actual, &expected) {
+                None => TestResult::passed(),
+                Some(err) => TestResult::error(err),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage, u8, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_mean_filter_matches_reference_implementation() {
+        fn prop(image: GrayTestImage, x_radius: u8, y_radius: u8) -> TestResult {
+            let (x_radius, y_radius) = (x_radius as u32 % 8, y_radius as u32 % 8);
+            let image = image.0;
+            let (width, height) = image.dimensions();
+            let clamp = |v: i32, len: u32| max(0, min(v, len as i32 - 1)) as u32;
+            let area = ((2 * x_radius + 1) * (2 * y_radius + 1)) as f64;
+            let (rx, ry) = (x_radius as i32, y_radius as i32);
+            let expected = ImageBuffer::from_fn(width, height, |x, y| {
+                let mut sum = 0u32;
+                for dy in -ry..ry + 1 {
+                    for dx in -rx..rx + 1 {
+                        sum += image.get_pixel(clamp(x as i32 + dx, width), clamp(y as i32 + dy, height))[0] as u32;
+                    }
+                }
+                Luma([(sum as f64 / area) as f32])
+            });
+            let actual = mean_filter(&image, x_radius, y_radius);
+            match pixel_diff_summary(&actual, &expected) {
+                None => TestResult::passed(),
+                Some(err) => TestResult::error(err),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage, u8, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_mean_filter_radius_larger_than_image() {
+        let image = rgb_image!(
+            [0, 9, 3], [4, 9, 3];
+            [8, 9, 3], [0, 9, 3]);
+        let filtered = mean_filter(&image, 3, 2);
+
+        // Sum each padded window directly.
+        for (x, y, p) in filtered.enumerate_pixels() {
+            let mut sum = 0;
+            for dy in -2i32..3 {
+                for dx in -3i32..4 {
+                    let px = max(0, min(x as i32 + dx, 1)) as u32;
+                    let py = max(0, min(y as i32 + dy, 1)) as u32;
+                    sum += image.get_pixel(px, py)[0] as u32;
+                }
+            }
+            assert!((p[0] - sum as f32 / 35.0).abs() < 1e-5);
+            assert!((p[1] - 9.0).abs() < 1e-5);
+            assert!((p[2] - 3.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_mean_filter_u16() {
+        let image = gray_image!(type: u16,
+            60000, 65535, 1000);
+        let expected = gray_image!(type: f32,
+            (3.0 * 60000.0 + 65535.0 + 1000.0) / 5.0,
+            (2.0 * 60000.0 + 65535.0 + 2.0 * 1000.0) / 5.0,
+            (60000.0 + 65535.0 + 3.0 * 1000.0) / 5.0);
+        assert_pixels_eq_within!(mean_filter(&image, 2, 0), expected, 1e-2);
+    }
+
+    #[bench]
+    fn bench_mean_filter(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let filtered = mean_filter(&image, 7, 7);
+            black_box(filtered);
+        });
+    }
+
     #[bench]
     fn bench_box_filter(b: &mut Bencher) {
         let image = gray_bench_image(500, 500);
@@ -495,6 +1673,170 @@ mod test {
         assert_pixels_eq!(filtered, expected);
     }
 
+    #[test]
+    fn test_gaussian_blur_f32_with_border() {
+        let image = gray_image!(
+            0, 0, 255, 255, 0, 40;
+            0, 90, 255, 30, 0, 7;
+            10, 0, 255, 255, 200, 3);
+
+        let replicate = gaussian_blur_f32_with_border(&image, 1.2, Border::Replicate);
+        assert_pixels_eq!(replicate, gaussian_blur_f32(&image, 1.2));
+
+        // Check each border mode against a blur of an explicitly padded image.
+        let sigma = 0.8;
+        let radius = 2i64;
+        for border in vec![Border::Reflect, Border::Wrap, Border::Constant(Luma([50u8]))] {
+            let padded = ImageBuffer::from_fn(10, 7, |x, y| {
+                get_pixel_with_border(&image, x as i64 - radius, y as i64 - radius, border)
+            });
+            let expected = gaussian_blur_f32(&padded, sigma);
+            let actual = gaussian_blur_f32_with_border(&image, sigma, border);
+            for (x, y, p) in actual.enumerate_pixels() {
+                assert_eq!(*p, *expected.get_pixel(x + radius as u32, y + radius as u32), "{:?}", border);
+            }
+        }
+
+        let empty = GrayImage::new(0, 3);
+        assert_eq!(gaussian_blur_f32_with_border(&empty, 1.0, Border::Reflect).dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn test_filter_clamped_with_border() {
+        let image = gray_image!(
+            0, 0, 255, 255, 0, 40;
+            0, 90, 255, 30, 0, 7;
+            10, 0, 255, 255, 200, 3);
+        let kernel = Kernel::new(&[1i32, -2, 0, 3, 1, -1], 3, 2);
+
+        let replicate = filter_clamped_with_border::<_, _, u8>(&image, &kernel, Border::Replicate);
+        assert_pixels_eq!(replicate, filter_clamped::<_, _, u8>(&image, &kernel));
+
+        // Check each border mode against a filter of an explicitly padded image,
+        // including for a kernel whose anchor is not at its centre.
+        for kernel in vec![kernel.clone(), kernel.clone().with_anchor(2, 0)] {
+            let (ax, ay) = kernel.anchor();
+            for border in vec![Border::Reflect, Border::Wrap, Border::Constant(Luma([50u8]))] {
+                let padded = pad(&image, 3, 3, 3, 3, border);
+                let expected = filter_clamped::<_, _, u8>(&padded, &kernel);
+                let actual = filter_clamped_with_border::<_, _, u8>(&image, &kernel, border);
+                for (x, y, p) in actual.enumerate_pixels() {
+                    assert_eq!(*p, *expected.get_pixel(x + 3, y + 3), "{:?} {:?}", border, (ax, ay));
+                }
+            }
+        }
+
+        let empty = GrayImage::new(0, 3);
+        assert_eq!(filter_clamped_with_border::<_, _, u8>(&empty, &kernel, Border::Wrap).dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn test_gaussian_blur_f32_supports_high_bit_depth_and_float_images() {
+        let image = gray_image!(
+            0, 0, 255, 255, 0;
+            0, 90, 255, 30, 0;
+            10, 0, 255, 255, 200);
+        // Results for the u8 image are truncated after each of the two 1d filters.
+        let blurred = gaussian_blur_f32(&image, 1.0);
+
+        let float = ImageBuffer::from_fn(5, 3, |x, y| Luma([image.get_pixel(x, y)[0] as f32]));
+        let blurred_float = gaussian_blur_f32(&float, 1.0);
+        for (b, f) in blurred.iter().zip(blurred_float.iter()) {
+            assert!((*b as f32 - *f).abs() < 2.0);
+        }
+
+        let wide = ImageBuffer::from_fn(5, 3, |x, y| Luma([image.get_pixel(x, y)[0] as u16 * 257]));
+        let blurred_wide = gaussian_blur_f32(&wide, 1.0);
+        for (b, w) in blurred.iter().zip(blurred_wide.iter()) {
+            assert!((*b as f32 * 257.0 - *w as f32).abs() < 2.0 * 257.0);
+        }
+
+        let rgb = ImageBuffer::from_fn(5, 3, |x, y| Rgb([float.get_pixel(x, y)[0], 1.0, 0.0]));
+        let blurred_rgb = gaussian_blur_f32(&rgb, 1.0);
+        for (c, f) in blurred_rgb.pixels().zip(blurred_float.pixels()) {
+            assert_eq!(c[0], f[0]);
+        }
+    }
+
+    #[test]
+    fn test_separable_filter_mut_and_into_match_separable_filter() {
+        fn prop(image: GrayTestImage, h_len: u8, v_len: u8) -> TestResult {
+            let image = image.0;
+            let h_kernel: Vec<f32> = (0..h_len % 6 + 1).map(|i| 0.3 * i as f32 - 0.4).collect();
+            let v_kernel: Vec<f32> = (0..v_len % 6 + 1).map(|i| 0.7 - 0.2 * i as f32).collect();
+            let expected = separable_filter(&image, &h_kernel, &v_kernel);
+
+            let mut into = GrayImage::new(image.width(), image.height());
+            separable_filter_into(&image, &h_kernel, &v_kernel, &mut into);
+            if let Some(err) = pixel_diff_summary(&into, &expected) {
+                return TestResult::error(err);
+            }
+
+            let mut in_place = image.clone();
+            separable_filter_mut(&mut in_place, &h_kernel, &v_kernel);
+            if let Some(err) = pixel_diff_summary(&in_place, &expected) {
+                return TestResult::error(err);
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(GrayTestImage, u8, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_vertical_filter_mut_multichannel_even_kernel() {
+        let image = rgb_image!(
+            [1, 2, 3], [4, 5, 6];
+            [7, 8, 9], [10, 11, 12];
+            [13, 14, 15], [16, 17, 18];
+            [19, 20, 21], [22, 23, 24]);
+        let kernel = [1i32, 2, 3, 4];
+
+        let mut in_place = image.clone();
+        vertical_filter_mut(&mut in_place, &kernel);
+        assert_pixels_eq!(in_place, vertical_filter(&image, &kernel));
+    }
+
+    #[test]
+    fn test_horizontal_filter_mut_multichannel_even_kernel() {
+        let image = rgb_image!(
+            [1, 2, 3], [4, 5, 6], [7, 8, 9], [10, 11, 12];
+            [13, 14, 15], [16, 17, 18], [19, 20, 21], [22, 23, 24]);
+        let kernel = [1i32, 2, 3, 4];
+
+        let mut in_place = image.clone();
+        horizontal_filter_mut(&mut in_place, &kernel);
+        assert_pixels_eq!(in_place, horizontal_filter(&image, &kernel));
+    }
+
+    #[test]
+    fn test_box_filter_into_matches_box_filter() {
+        let image = gray_image!(
+            1, 20, 3, 40;
+            50, 6, 70, 8;
+            9, 100, 11, 120);
+        let mut out = GrayImage::new(4, 3);
+        box_filter_into(&image, 2, 1, &mut out);
+        assert_pixels_eq!(out, box_filter(&image, 2, 1));
+    }
+
+    #[test]
+    fn test_filter3x3_into_reuses_output_buffer() {
+        let kernel = [-1i32, 0, 1, -2, 0, 2, -1, 0, 1];
+        let mut out = Image::<Luma<i16>>::new(3, 2);
+        for image in &[gray_image!(1, 2, 3; 4, 5, 6), gray_image!(9, 0, 7; 1, 50, 2)] {
+            filter3x3_into::<_, _, i16>(image, &kernel, &mut out);
+            assert_pixels_eq!(out, filter3x3::<_, _, i16>(image, &kernel));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_horizontal_filter_into_rejects_mismatched_output() {
+        let image = gray_image!(1, 2, 3);
+        let mut out = GrayImage::new(2, 1);
+        horizontal_filter_into(&image, &[1i32], &mut out);
+    }
+
     #[bench]
     fn bench_separable_filter(b: &mut Bencher) {
         let image = gray_bench_image(300, 300);
@@ -530,7 +1872,7 @@ mod test {
                     acc += color as f32 * weight;
                 }
 
-                let clamped = u8::clamp(acc);
+                let clamped = <u8 as Clamp<_>>::clamp(acc);
                 out.put_pixel(x, y, Luma([clamped]));
             }
         }
@@ -562,7 +1904,7 @@ mod test {
                     acc += color as f32 * weight;
                 }
 
-                let clamped = u8::clamp(acc);
+                let clamped = <u8 as Clamp<_>>::clamp(acc);
                 out.put_pixel(x, y, Luma([clamped]));
             }
         }
@@ -608,6 +1950,38 @@ mod test {
         vertical_filter_reference
     );
 
+    #[test]
+    fn test_separable_filters_match_reference_implementations_on_wide_images() {
+        // Wide enough that most outputs are computed by the vectorised loops.
+        let image = gray_bench_image(37, 9);
+        for kernel_length in 1..9 {
+            let kernel: Vec<f32> = (0..kernel_length).map(|i| i as f32 % 1.35 - 0.3).collect();
+            assert_pixels_eq!(
+                horizontal_filter(&image, &kernel),
+                horizontal_filter_reference(&image, &kernel)
+            );
+            assert_pixels_eq!(
+                vertical_filter(&image, &kernel),
+                vertical_filter_reference(&image, &kernel)
+            );
+        }
+    }
+
+    #[test]
+    fn test_separable_filters_with_i32_kernel_on_wide_images() {
+        let image = gray_bench_image(37, 9);
+        let kernel = vec![-1i32, 2, 3, -2];
+        let kernel_f32: Vec<f32> = kernel.iter().map(|k| *k as f32).collect();
+        assert_pixels_eq!(
+            horizontal_filter(&image, &kernel),
+            horizontal_filter_reference(&image, &kernel_f32)
+        );
+        assert_pixels_eq!(
+            vertical_filter(&image, &kernel),
+            vertical_filter_reference(&image, &kernel_f32)
+        );
+    }
+
     #[test]
     fn test_horizontal_filter() {
         let image = gray_image!(
@@ -723,6 +2097,250 @@ mod test {
         });
     }
 
+    #[bench]
+    fn bench_filter3x3_rgb_i32_filter(b: &mut Bencher) {
+        let image = rgb_bench_image(500, 500);
+        let kernel: Vec<i32> = vec![
+            -1, 0, 1,
+            -2, 0, 2,
+            -1, 0, 1];
+
+        b.iter(|| {
+            let filtered: ImageBuffer<Rgb<i16>, Vec<i16>> =
+                filter3x3::<_, _, i16>(&image, &kernel);
+            black_box(filtered);
+        });
+    }
+
+    // Straightforward implementation of Kernel::filter for single-channel images,
+    // used to validate the vectorised implementation against.
+    fn kernel_filter_reference<T, K>(image: &Image<Luma<T>>, kernel: &[K], k_width: u32, k_height: u32) -> Vec<K>
+    where
+        T: Primitive + 'static + ValueInto<K>,
+        K: Num + Copy,
+    {
+        let (width, height) = image.dimensions();
+        let mut out = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let mut acc = K::zero();
+                for k_y in 0..k_height {
+                    let y_p = max(0, min(y as i32 + k_y as i32 - k_height as i32 / 2, height as i32 - 1));
+                    for k_x in 0..k_width {
+                        let x_p = max(0, min(x as i32 + k_x as i32 - k_width as i32 / 2, width as i32 - 1));
+                        let p: K = cast(image.get_pixel(x_p as u32, y_p as u32)[0]);
+                        acc = acc + p * kernel[(k_y * k_width + k_x) as usize];
+                    }
+                }
+                out.push(acc);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_kernel_filter_single_channel_matches_reference() {
+        let kernel_i32: Vec<i32> = (0..25).map(|i| i * 3 - 37).collect();
+        let kernel_f32: Vec<f32> = kernel_i32.iter().map(|&k| k as f32 * 0.21).collect();
+
+        for &(width, height) in &[(1, 1), (4, 3), (9, 2), (37, 11)] {
+            let image = gray_bench_image(width, height);
+            let image_f32: Image<Luma<f32>> = ImageBuffer::from_fn(width, height, |x, y| {
+                Luma([image.get_pixel(x, y)[0] as f32 * 0.5 - 7.0])
+            });
+
+            for &size in &[3, 5] {
+                let kernel_i32 = &kernel_i32[..size * size];
+                let kernel_f32 = &kernel_f32[..size * size];
+                let (w, h) = (size as u32, size as u32);
+
+                let actual: Image<Luma<i32>> = Kernel::new(kernel_i32, w, h).filter(&image, |c, a| *c = a);
+                assert_eq!(actual.into_raw(), kernel_filter_reference(&image, kernel_i32, w, h));
+
+                let actual: Image<Luma<f32>> = Kernel::new(kernel_f32, w, h).filter(&image, |c, a| *c = a);
+                assert_eq!(actual.into_raw(), kernel_filter_reference(&image, kernel_f32, w, h));
+
+                let actual: Image<Luma<f32>> = Kernel::new(kernel_f32, w, h).filter(&image_f32, |c, a| *c = a);
+                assert_eq!(actual.into_raw(), kernel_filter_reference(&image_f32, kernel_f32, w, h));
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernel_filter_with_anchor() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        let kernel = Kernel::new(&[1, 10], 2, 1).with_anchor(0, 0);
+
+        let filtered: Image<Luma<i32>> = kernel.filter(&image, |c, a| *c = a);
+        let expected = gray_image!(type: i32,
+            21, 32, 33;
+            54, 65, 66);
+        assert_pixels_eq!(filtered, expected);
+
+        let wide = gray_bench_image(20, 3);
+        let kernel = Kernel::new(&[1, 2, 3, 4, 5, 6], 3, 2).with_anchor(2, 1);
+        let filtered: Image<Luma<i32>> = kernel.filter(&wide, |c, a| *c = a);
+        let expected: Image<Luma<i32>> = Kernel::new(&[1, 2, 3, 0, 0, 4, 5, 6, 0, 0, 0, 0, 0, 0, 0], 5, 3)
+            .filter(&wide, |c, a| *c = a);
+        assert_pixels_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_kernel_convolve_matches_filter_with_flipped_kernel() {
+        let image = gray_bench_image(13, 7);
+        let kernel = Kernel::new(&[1, -2, 3, 7, 5, -6], 3, 2).with_anchor(2, 0);
+        let flipped = kernel.flipped();
+
+        assert_eq!(flipped.data(), &[-6, 5, 7, 3, -2, 1]);
+        assert_eq!(flipped.anchor(), (0, 1));
+        assert_eq!(flipped.flipped(), kernel);
+
+        let convolved: Image<Luma<i32>> = kernel.convolve(&image, |c, a| *c = a);
+        let expected: Image<Luma<i32>> = flipped.filter(&image, |c, a| *c = a);
+        assert_pixels_eq!(convolved, expected);
+
+        // Convolution and correlation agree for symmetric kernels.
+        let laplacian = Kernel::laplacian();
+        let convolved: Image<Luma<i32>> = laplacian.convolve(&image, |c, a| *c = a);
+        let filtered: Image<Luma<i32>> = laplacian.filter(&image, |c, a| *c = a);
+        assert_pixels_eq!(convolved, filtered);
+    }
+
+    #[test]
+    fn test_kernel_filter_strided_subsamples_filter() {
+        let kernel: Vec<i32> = (0..15).map(|i| i * 5 - 31).collect();
+        let kernel = Kernel::new(&kernel, 5, 3);
+
+        for &(width, height) in &[(1, 1), (4, 3), (9, 2), (37, 11)] {
+            let image = rgb_bench_image(width, height);
+            let full: Image<Rgb<i32>> = kernel.filter(&image, |c, a| *c = a);
+            for &stride in &[1, 2, 3, 5] {
+                let strided: Image<Rgb<i32>> = kernel.filter_strided(&image, stride, |c, a| *c = a);
+                let expected = ImageBuffer::from_fn(strided.width(), strided.height(), |x, y| {
+                    *full.get_pixel(x * stride, y * stride)
+                });
+                assert_eq!(strided.dimensions(), ((width + stride - 1) / stride, (height + stride - 1) / stride));
+                assert_pixels_eq!(strided, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernel_filter_dilated_matches_filter_with_zero_filled_kernel() {
+        let kernel: Vec<i32> = (0..6).map(|i| i * 7 - 20).collect();
+
+        for &(width, height) in &[(1, 1), (4, 3), (9, 2), (37, 11)] {
+            let image = gray_bench_image(width, height);
+            for &dilation in &[1u32, 2, 3] {
+                // Spread the 3x2 kernel over rows 0 and dilation of a square kernel
+                // of side 2 * dilation + 1, so that both kernels have the same anchor.
+                let side = 2 * dilation + 1;
+                let mut spread = vec![0i32; (side * side) as usize];
+                for k_y in 0..2 {
+                    for k_x in 0..3 {
+                        spread[(k_y * dilation * side + k_x * dilation) as usize] = kernel[(k_y * 3 + k_x) as usize];
+                    }
+                }
+                let expected: Image<Luma<i32>> = Kernel::new(&spread, side, side).filter(&image, |c, a| *c = a);
+                let actual: Image<Luma<i32>> = Kernel::new(&kernel, 3, 2).filter_dilated(&image, dilation, |c, a| *c = a);
+                assert_pixels_eq!(actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kernel_filter_spaced() {
+        let image = gray_image!(
+            1, 2, 3, 4, 5;
+            6, 7, 8, 9, 10;
+            11, 12, 13, 14, 15;
+            16, 17, 18, 19, 20);
+        let kernel = Kernel::new(&[1, 0, 1], 1, 3);
+
+        let actual: Image<Luma<i32>> = kernel.filter_spaced(&image, 3, 2, |c, a| *c = a);
+        let expected = gray_image!(type: i32,
+            12, 18;
+            22, 28);
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_kernel_filter_fft_matches_filter_f64() {
+        let image = rgb_bench_image(100, 90);
+        let image: Image<Rgb<f64>> = map_subpixels(&image, |p| p as f64 / 255.0);
+        let kernel: Vec<f64> = (0..61 * 55).map(|i| ((i * 7) % 11) as f64 / 10.0 - 0.5).collect();
+        let kernel = Kernel::new(&kernel, 61, 55).with_anchor(10, 30);
+
+        // filter_spaced always computes its outputs directly.
+        let expected: Image<Rgb<f64>> = kernel.filter_spaced(&image, 1, 1, |c, a| *c = a);
+        let actual: Image<Rgb<f64>> = kernel.filter_fft(&image, |c, a| *c = a);
+        assert_pixels_eq_within!(actual, expected, 1e-12);
+    }
+
+    #[test]
+    fn test_kernel_filter_large_kernel_matches_direct_filter() {
+        let image = rgb_bench_image(100, 90);
+        let image: Image<Rgb<f64>> = map_subpixels(&image, |p| p as f64 / 255.0);
+        let kernel: Vec<f64> = (0..61 * 55).map(|i| ((i * 7) % 11) as f64 / 10.0 - 0.5).collect();
+        let kernel = Kernel::new(&kernel, 61, 55).with_anchor(10, 30);
+        // Check that the Fourier transform is used.
+        assert!(fft_is_faster((160, 144), (61, 55)));
+
+        let expected: Image<Rgb<f64>> = kernel.filter_spaced(&image, 1, 1, |c, a| *c = a);
+        let actual: Image<Rgb<f64>> = kernel.filter(&image, |c, a| *c = a);
+        assert_pixels_eq_within!(actual, expected, 1e-12);
+    }
+
+    #[test]
+    fn test_kernel_filter_fft_matches_filter_f32() {
+        let image = gray_bench_image(100, 90);
+        let image: Image<Luma<f32>> = map_subpixels(&image, |p| p as f32 / 255.0);
+        let kernel: Vec<f32> = (0..31 * 25).map(|i| ((i * 7) % 11) as f32 / 10.0 - 0.5).collect();
+        let kernel = Kernel::new(&kernel, 31, 25);
+
+        // filter_spaced always computes its outputs directly.
+        let expected: Image<Luma<f32>> = kernel.filter_spaced(&image, 1, 1, |c, a| *c = a);
+        let actual: Image<Luma<f32>> = kernel.filter_fft(&image, |c, a| *c = a);
+        assert_pixels_eq_within!(actual, expected, 1e-5);
+    }
+
+    #[test]
+    fn test_kernel_filter_fft_integer_kernel_matches_filter() {
+        let image = gray_bench_image(20, 15);
+        let kernel: Vec<i32> = (0..35).map(|i| (i * 7) % 11 - 5).collect();
+        let kernel = Kernel::new(&kernel, 7, 5).with_anchor(1, 3);
+
+        let expected: Image<Luma<i32>> = kernel.filter(&image, |c, a| *c = a);
+        let actual: Image<Luma<i32>> = kernel.filter_fft(&image, |c, a| *c = a);
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[bench]
+    fn bench_kernel_filter_dilated(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        let kernel: Vec<f32> = (0..25).map(|i| i as f32 / 25.0).collect();
+        let kernel = Kernel::new(&kernel, 5, 5);
+
+        b.iter(|| {
+            let filtered: Image<Luma<u8>> = kernel.filter_dilated(&image, 2, |c, a| *c = <u8 as Clamp<_>>::clamp(a));
+            black_box(filtered);
+        });
+    }
+
+    #[bench]
+    fn bench_filter5x5_f32_filter(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        let kernel: Vec<f32> = (0..25).map(|i| i as f32 / 25.0).collect();
+        let kernel = Kernel::new(&kernel, 5, 5);
+
+        b.iter(|| {
+            let filtered: Image<Luma<u8>> = kernel.filter(&image, |c, a| *c = <u8 as Clamp<_>>::clamp(a));
+            black_box(filtered);
+        });
+    }
+
     /// Baseline implementation of Gaussian blur is that provided by image::imageops.
     /// We can also use this to validate correctnes of any implementations we add here.
     fn gaussian_baseline_rgb<I>(image: &I, stdev: f32) -> Image<Rgb<u8>>
@@ -0,0 +1,1491 @@
+//! Geometric transformations of images: projective and affine warps,
+//! rotations, translations and resizing.
+
+use image::{Pixel, GenericImage, GenericImageView, ImageBuffer, Luma};
+use definitions::{Clamp, Image, MaybeSend, MaybeSync};
+use error::{check_dimensions, check_non_empty, ImageProcError, Result};
+use math::cast;
+use parallel::for_each_row;
+use conv::ValueInto;
+use std::cmp::{max, min};
+use std::f32;
+use std::ops::Mul;
+
+/// A projective transformation of the plane, stored as a row-major 3x3 matrix in
+/// homogeneous coordinates together with its inverse.
+///
+/// Affine transformations are the projections whose matrix has bottom row `[0, 0, 1]`.
+/// Projections can be composed by multiplication: `a * b` applies `b` and then `a`.
+///
+/// # Examples
+/// ```
+/// use imageproc::geometric_transformations::Projection;
+///
+/// let scale_then_shift = Projection::translate(1.0, 2.0) * Projection::scale(2.0, 2.0);
+/// assert_eq!(scale_then_shift * (1.0, 1.0), (3.0, 4.0));
+/// assert_eq!(scale_then_shift.invert() * (3.0, 4.0), (1.0, 1.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Projection {
+    transform: [f32; 9],
+    inverse: [f32; 9],
+}
+
+impl Projection {
+    /// Creates a projection from a row-major 3x3 matrix in homogeneous coordinates,
+    /// or returns `None` if the matrix is not invertible.
+    pub fn from_matrix(transform: [f32; 9]) -> Option<Projection> {
+        try_inverse(&transform).map(|inverse| Projection { transform, inverse })
+    }
+
+    /// The row-major 3x3 matrix of this projection in homogeneous coordinates.
+    pub fn matrix(&self) -> [f32; 9] {
+        self.transform
+    }
+
+    /// The inverse of this projection.
+    pub fn invert(self) -> Projection {
+        Projection { transform: self.inverse, inverse: self.transform }
+    }
+
+    /// A translation by `(tx, ty)`.
+    pub fn translate(tx: f32, ty: f32) -> Projection {
+        Projection {
+            transform: [
+                1.0, 0.0, tx,
+                0.0, 1.0, ty,
+                0.0, 0.0, 1.0,
+            ],
+            inverse: [
+                1.0, 0.0, -tx,
+                0.0, 1.0, -ty,
+                0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    /// A clockwise rotation by `theta` radians about the origin. Rotations are
+    /// clockwise because image coordinates increase from top left to bottom right.
+    pub fn rotate(theta: f32) -> Projection {
+        let (s, c) = theta.sin_cos();
+        Projection {
+            transform: [
+                c, -s, 0.0,
+                s, c, 0.0,
+                0.0, 0.0, 1.0,
+            ],
+            inverse: [
+                c, s, 0.0,
+                -s, c, 0.0,
+                0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    /// A clockwise rotation by `theta` radians about `center`.
+    pub fn rotate_about(center: (f32, f32), theta: f32) -> Projection {
+        Projection::translate(center.0, center.1)
+            * Projection::rotate(theta)
+            * Projection::translate(-center.0, -center.1)
+    }
+
+    /// Scales horizontally by `sx` and vertically by `sy`.
+    ///
+    /// # Panics
+    /// If `sx` or `sy` is zero. See [`try_scale`](#method.try_scale) for a version that
+    /// returns an error instead.
+    pub fn scale(sx: f32, sy: f32) -> Projection {
+        assert!(sx != 0.0 && sy != 0.0, "scale factors must be non-zero");
+        Projection {
+            transform: [
+                sx, 0.0, 0.0,
+                0.0, sy, 0.0,
+                0.0, 0.0, 1.0,
+            ],
+            inverse: [
+                1.0 / sx, 0.0, 0.0,
+                0.0, 1.0 / sy, 0.0,
+                0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    /// Scales horizontally by `sx` and vertically by `sy`.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if `sx` or `sy` is zero.
+    pub fn try_scale(sx: f32, sy: f32) -> Result<Projection> {
+        if sx == 0.0 || sy == 0.0 {
+            return Err(ImageProcError::InvalidOptions(
+                format!("scale factors must be non-zero, found ({}, {})", sx, sy)
+            ));
+        }
+        Ok(Projection::scale(sx, sy))
+    }
+
+    /// Maps `(x, y)` to `(x + kx * y, y + ky * x)`.
+    ///
+    /// # Panics
+    /// If `kx * ky == 1`, in which case the shear is not invertible. See
+    /// [`try_shear`](#method.try_shear) for a version that returns an error instead.
+    pub fn shear(kx: f32, ky: f32) -> Projection {
+        Projection::try_shear(kx, ky).expect("shear must be invertible")
+    }
+
+    /// Maps `(x, y)` to `(x + kx * y, y + ky * x)`.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if `kx * ky == 1`, in which case the
+    /// shear is not invertible.
+    pub fn try_shear(kx: f32, ky: f32) -> Result<Projection> {
+        Projection::from_matrix([
+            1.0, kx, 0.0,
+            ky, 1.0, 0.0,
+            0.0, 0.0, 1.0,
+        ]).ok_or_else(|| ImageProcError::InvalidOptions(
+            format!("shear ({}, {}) is not invertible", kx, ky)
+        ))
+    }
+}
+
+/// Applies `rhs` and then `self`.
+impl Mul<Projection> for Projection {
+    type Output = Projection;
+
+    fn mul(self, rhs: Projection) -> Projection {
+        Projection {
+            transform: mul3x3(&self.transform, &rhs.transform),
+            inverse: mul3x3(&rhs.inverse, &self.inverse),
+        }
+    }
+}
+
+/// Maps a point. Points sent to infinity are mapped to `(f32::INFINITY, f32::INFINITY)`.
+impl Mul<(f32, f32)> for Projection {
+    type Output = (f32, f32);
+
+    fn mul(self, rhs: (f32, f32)) -> (f32, f32) {
+        apply(&self.transform, rhs.0, rhs.1)
+    }
+}
+
+fn mul3x3(a: &[f32; 9], b: &[f32; 9]) -> [f32; 9] {
+    let mut c = [0f32; 9];
+    for row in 0..3 {
+        for col in 0..3 {
+            c[3 * row + col] = (0..3).map(|k| a[3 * row + k] * b[3 * k + col]).sum();
+        }
+    }
+    c
+}
+
+fn apply(t: &[f32; 9], x: f32, y: f32) -> (f32, f32) {
+    let px = t[0] * x + t[1] * y + t[2];
+    let py = t[3] * x + t[4] * y + t[5];
+    let w = t[6] * x + t[7] * y + t[8];
+    if w == 1.0 {
+        (px, py)
+    } else if w == 0.0 {
+        // Points mapped to infinity lie outside every image.
+        (::std::f32::INFINITY, ::std::f32::INFINITY)
+    } else {
+        (px / w, py / w)
+    }
+}
+
+fn try_inverse(t: &[f32; 9]) -> Option<[f32; 9]> {
+    let (
+        t00, t01, t02,
+        t10, t11, t12,
+        t20, t21, t22
+    ) = (
+        t[0], t[1], t[2],
+        t[3], t[4], t[5],
+        t[6], t[7], t[8]
+    );
+
+    let m00 = t11 * t22 - t12 * t21;
+    let m01 = t10 * t22 - t12 * t20;
+    let m02 = t10 * t21 - t11 * t20;
+
+    let det = t00 * m00 - t01 * m01 + t02 * m02;
+
+    if det == 0.0 {
+        return None;
+    }
+
+    let m10 = t01 * t22 - t02 * t21;
+    let m11 = t00 * t22 - t02 * t20;
+    let m12 = t00 * t21 - t01 * t20;
+    let m20 = t01 * t12 - t02 * t11;
+    let m21 = t00 * t12 - t02 * t10;
+    let m22 = t00 * t11 - t01 * t10;
+
+    Some([
+         m00 / det, -m10 / det,  m20 / det,
+        -m01 / det,  m11 / det, -m21 / det,
+         m02 / det, -m12 / det,  m22 / det
+    ])
+}
+
+/// How to handle pixels whose pre-image lies between input pixels.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Interpolation {
+    /// Choose the nearest pixel to the pre-image of the
+    /// output pixel.
+    Nearest,
+    /// Bilinearly interpolate between the four pixels
+    /// closest to the pre-image of the output pixel.
+    Bilinear,
+    /// Bicubically interpolate between the sixteen pixels closest to the
+    /// pre-image of the output pixel, using the Catmull-Rom cubic.
+    Bicubic,
+}
+
+/// Warps an image by a projection. Each output pixel takes the value of the input
+/// at its pre-image under `projection`, sampled using `interpolation`.
+///
+/// The output image has the same dimensions as the input. Output pixels whose
+/// pre-image lies outside the input image, or too close to its boundary for all
+/// the pixels used by `interpolation` to exist, are set to `default`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::geometric_transformations::{warp, Interpolation, Projection};
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let shifted = warp(&image, &Projection::translate(1.0, 0.0), Interpolation::Nearest, Luma([0]));
+///
+/// assert_pixels_eq!(
+///     shifted,
+///     gray_image!(
+///         0, 1, 2;
+///         0, 4, 5));
+/// # }
+/// ```
+pub fn warp<P>(
+    image: &Image<P>,
+    projection: &Projection,
+    interpolation: Interpolation,
+    default: P,
+) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+    warp_into(image, projection, interpolation, default, &mut out);
+    out
+}
+
+/// Writes the result of warping `image` by `projection` to `out`, as in
+/// [`warp`](fn.warp.html). The output may have different dimensions to the input.
+pub fn warp_into<P>(
+    image: &Image<P>,
+    projection: &Projection,
+    interpolation: Interpolation,
+    default: P,
+    out: &mut Image<P>,
+)
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let inverse = projection.inverse;
+    warp_inner(image, |x, y| apply(&inverse, x as f32, y as f32), interpolation, default, out);
+}
+
+/// Warps an image using an arbitrary mapping from output to input coordinates.
+/// Output pixel `(x, y)` takes the value of the input at `mapping(x, y)`, sampled
+/// using `interpolation`.
+///
+/// The output image has the same dimensions as the input. Output pixels whose
+/// pre-image lies outside the input image, or too close to its boundary for all
+/// the pixels used by `interpolation` to exist, are set to `default`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::geometric_transformations::{warp_with, Interpolation};
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// // Reflect about the vertical line through the middle column.
+/// let reflected = warp_with(&image, |x, y| (2.0 - x, y), Interpolation::Nearest, Luma([0]));
+///
+/// assert_pixels_eq!(
+///     reflected,
+///     gray_image!(
+///         3, 2, 1;
+///         6, 5, 4));
+/// # }
+/// ```
+pub fn warp_with<P, F>(
+    image: &Image<P>,
+    mapping: F,
+    interpolation: Interpolation,
+    default: P,
+) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+    F: Fn(f32, f32) -> (f32, f32) + MaybeSync + MaybeSend,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+    warp_inner(image, |x, y| mapping(x as f32, y as f32), interpolation, default, &mut out);
+    out
+}
+
+/// Warps an image using per-pixel displacements. Output pixel `(x, y)` takes the value
+/// of the input at `(x + dx(x, y), y + dy(x, y))`, sampled using `interpolation`. This
+/// allows warps that are expensive to compute, such as lens distortion corrections,
+/// to be computed once and applied to many images.
+///
+/// The output image has the same dimensions as the displacement fields. Output pixels whose
+/// pre-image lies outside the input image, or too close to its boundary for all
+/// the pixels used by `interpolation` to exist, are set to `default`.
+///
+/// # Panics
+/// If `dx` and `dy` have different dimensions. See [`try_remap`](fn.try_remap.html)
+/// for a version that returns an error instead.
+pub fn remap<P>(
+    image: &Image<P>,
+    dx: &Image<Luma<f32>>,
+    dy: &Image<Luma<f32>>,
+    interpolation: Interpolation,
+    default: P,
+) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    assert_eq!(dx.dimensions(), dy.dimensions(), "displacement fields must have the same dimensions");
+    let (width, height) = dx.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+    warp_inner(
+        image,
+        |x, y| unsafe {
+            (
+                x as f32 + dx.unsafe_get_pixel(x, y)[0],
+                y as f32 + dy.unsafe_get_pixel(x, y)[0],
+            )
+        },
+        interpolation,
+        default,
+        &mut out,
+    );
+    out
+}
+
+/// Warps an image using per-pixel displacements, as for [`remap`](fn.remap.html).
+///
+/// Returns `ImageProcError::DimensionMismatch` if `dx` and `dy` have different dimensions.
+pub fn try_remap<P>(
+    image: &Image<P>,
+    dx: &Image<Luma<f32>>,
+    dy: &Image<Luma<f32>>,
+    interpolation: Interpolation,
+    default: P,
+) -> Result<Image<P>>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    check_dimensions(dx.dimensions(), dy.dimensions())?;
+    Ok(remap(image, dx, dy, interpolation, default))
+}
+
+// Writes to each output pixel (x, y) the input sampled at mapping(x, y).
+fn warp_inner<P, F>(
+    image: &Image<P>,
+    mapping: F,
+    interpolation: Interpolation,
+    default: P,
+    out: &mut Image<P>,
+)
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+    F: Fn(u32, u32) -> (f32, f32) + MaybeSync + MaybeSend,
+{
+    let num_channels = P::channel_count() as usize;
+
+    for_each_row(out, |y, row| {
+        for (x, out_channels) in row.chunks_mut(num_channels).enumerate() {
+            let (px, py) = mapping(x as u32, y);
+
+            let pix = match interpolation {
+                Interpolation::Nearest => interpolate_nearest(image, px, py, default),
+                Interpolation::Bilinear => interpolate_bilinear(image, px, py, default),
+                Interpolation::Bicubic => interpolate_bicubic(image, px, py, default),
+            };
+            out_channels.copy_from_slice(pix.channels());
+        }
+    });
+}
+
+/// Rotate an image clockwise about provided center by theta radians.
+/// The output image has the same dimensions as the input. Output pixels
+/// whose pre-image lies outside the input image are set to default.
+///
+/// Rotations by multiples of 90 degrees that swap the image's width and height
+/// and don't resample any pixels are provided by [`rotate90`](fn.rotate90.html),
+/// [`rotate180`](fn.rotate180.html) and [`rotate270`](fn.rotate270.html).
+pub fn rotate<P>(
+    image: &Image<P>,
+    center: (f32, f32),
+    theta: f32,
+    interpolation: Interpolation,
+    default: P,
+) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    match interpolation {
+        Interpolation::Nearest => rotate_nearest(image, center, theta, default),
+        Interpolation::Bilinear => rotate_bilinear(image, center, theta, default),
+        Interpolation::Bicubic => warp(image, &Projection::rotate_about(center, theta), interpolation, default),
+    }
+}
+
+/// Rotate an image clockwise about its center by theta radians.
+/// The output image has the same dimensions as the input. Output pixels
+/// whose pre-image lies outside the input image are set to default.
+pub fn rotate_about_center<P>(
+    image: &Image<P>,
+    theta: f32,
+    interpolation: Interpolation,
+    default: P,
+) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let center = (image.width() as f32 / 2f32, image.height() as f32 / 2f32);
+    rotate(image, center, theta, interpolation, default)
+}
+
+/// Rotates an image clockwise by 90 degrees. The output has width equal to the input's
+/// height and height equal to its width, and every pixel is copied without resampling.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::geometric_transformations::rotate90;
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// assert_pixels_eq!(
+///     rotate90(&image),
+///     gray_image!(
+///         4, 1;
+///         5, 2;
+///         6, 3));
+/// # }
+/// ```
+pub fn rotate90<P>(image: &Image<P>) -> Image<P>
+where
+    P: Pixel + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(height, width);
+    for y in 0..width {
+        for x in 0..height {
+            let p = unsafe { image.unsafe_get_pixel(y, height - 1 - x) };
+            unsafe { out.unsafe_put_pixel(x, y, p) };
+        }
+    }
+    out
+}
+
+/// Rotates an image by 180 degrees. Every pixel is copied without resampling.
+pub fn rotate180<P>(image: &Image<P>) -> Image<P>
+where
+    P: Pixel + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let p = unsafe { image.unsafe_get_pixel(width - 1 - x, height - 1 - y) };
+            unsafe { out.unsafe_put_pixel(x, y, p) };
+        }
+    }
+    out
+}
+
+/// Rotates an image clockwise by 270 degrees, i.e. anticlockwise by 90 degrees. The output
+/// has width equal to the input's height and height equal to its width, and every pixel
+/// is copied without resampling.
+pub fn rotate270<P>(image: &Image<P>) -> Image<P>
+where
+    P: Pixel + 'static,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(height, width);
+    for y in 0..width {
+        for x in 0..height {
+            let p = unsafe { image.unsafe_get_pixel(width - 1 - y, x) };
+            unsafe { out.unsafe_put_pixel(x, y, p) };
+        }
+    }
+    out
+}
+
+fn rotate_nearest<P>(image: &Image<P>, center: (f32, f32), theta: f32, default: P) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: MaybeSend + MaybeSync,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+
+    let num_channels = P::channel_count() as usize;
+    let cos_theta = theta.cos();
+    let sin_theta = theta.sin();
+    let center_x = center.0;
+    let center_y = center.1;
+
+    for_each_row(&mut out, |y, row| {
+        let dy = y as f32 - center_y;
+        let mut px = center_x + sin_theta * dy - cos_theta * center_x;
+        let mut py = center_y + cos_theta * dy + sin_theta * center_x;
+
+        for out_channels in row.chunks_mut(num_channels) {
+            let pix = interpolate_nearest(image, px, py, default);
+            out_channels.copy_from_slice(pix.channels());
+
+            px += cos_theta;
+            py -= sin_theta;
+        }
+    });
+
+    out
+}
+
+fn rotate_bilinear<P>(image: &Image<P>, center: (f32, f32), theta: f32, default: P) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+
+    let num_channels = P::channel_count() as usize;
+    let cos_theta = theta.cos();
+    let sin_theta = theta.sin();
+    let center_x = center.0;
+    let center_y = center.1;
+
+    for_each_row(&mut out, |y, row| {
+        let dy = y as f32 - center_y;
+        let mut px = center_x + sin_theta * dy - cos_theta * center_x;
+        let mut py = center_y + cos_theta * dy + sin_theta * center_x;
+
+        for out_channels in row.chunks_mut(num_channels) {
+            let pix = interpolate_bilinear(image, px, py, default);
+            out_channels.copy_from_slice(pix.channels());
+
+            px += cos_theta;
+            py -= sin_theta;
+        }
+    });
+
+    out
+}
+
+/// Translates the input image by t. Note that image coordinates increase from
+/// top left to bottom right. Output pixels whose pre-image are not in the input
+/// image are set to the boundary pixel in the input image nearest to their pre-image.
+// TODO: it's possibly confusing that this has different behaviour to
+// TODO: attempting the equivalent transformation via the warp function
+pub fn translate<P>(image: &Image<P>, t: (i32, i32)) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: MaybeSend + MaybeSync,
+{
+    use std::cmp;
+
+    let (width, height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+
+    let w = width as i32;
+    let h = height as i32;
+    let num_channels = P::channel_count() as usize;
+
+    for_each_row(&mut out, |y, row| {
+        let y_in = cmp::max(0, cmp::min(y as i32 - t.1, h - 1));
+        for (x, out_channels) in row.chunks_mut(num_channels).enumerate() {
+            let x_in = cmp::max(0, cmp::min(x as i32 - t.0, w - 1));
+            // (x_in, y_in) is guaranteed to be in bounds
+            let p = unsafe { image.unsafe_get_pixel(x_in as u32, y_in as u32) };
+            out_channels.copy_from_slice(p.channels());
+        }
+    });
+
+    out
+}
+
+/// Filter used to compute output pixels when [`resize`](fn.resize.html)-ing an image.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ResizeFilter {
+    /// Copy the input pixel containing the pre-image of the center of each output pixel.
+    Nearest,
+    /// Linearly interpolate between input pixels, i.e. filter with a triangle.
+    Bilinear,
+    /// Filter with the Catmull-Rom cubic.
+    Bicubic,
+    /// Filter with the three lobed Lanczos window, `sinc(x) * sinc(x / 3)`.
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    // Radius of the filter's support, in input pixels when upsampling.
+    fn support(&self) -> f32 {
+        match *self {
+            ResizeFilter::Nearest => 0.5,
+            ResizeFilter::Bilinear => 1.0,
+            ResizeFilter::Bicubic => 2.0,
+            ResizeFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match *self {
+            ResizeFilter::Nearest => if x < 0.5 { 1.0 } else { 0.0 },
+            ResizeFilter::Bilinear => (1.0 - x).max(0.0),
+            ResizeFilter::Bicubic => {
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            ResizeFilter::Lanczos3 => if x < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 },
+        }
+    }
+}
+
+// The normalised sinc function, which is exactly zero at non-zero integers.
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    // Reducing to [-0.5, 0.5] before calling sin avoids rounding errors in PI * x.
+    let n = x.round();
+    let s = (f32::consts::PI * (x - n)).sin();
+    let s = if n % 2.0 == 0.0 { s } else { -s };
+    s / (f32::consts::PI * x)
+}
+
+/// Resizes an image to the given dimensions, using `filter` to compute each output pixel
+/// from the input pixels near its pre-image. Pads by continuity.
+///
+/// The image is resampled by a horizontal and then a vertical 1d pass. When shrinking
+/// a dimension each filter is stretched to cover all the input pixels that map to an
+/// output pixel, so that the output is not aliased.
+///
+/// # Panics
+/// If `image` is empty and the requested dimensions are not. See
+/// [`try_resize`](fn.try_resize.html) for a version that returns an error instead.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::geometric_transformations::{resize, ResizeFilter};
+///
+/// let image = gray_image!(
+///     1, 2;
+///     3, 4);
+///
+/// assert_pixels_eq!(
+///     resize(&image, 4, 2, ResizeFilter::Nearest),
+///     gray_image!(
+///         1, 1, 2, 2;
+///         3, 3, 4, 4));
+/// # }
+/// ```
+pub fn resize<P>(image: &Image<P>, width: u32, height: u32, filter: ResizeFilter) -> Image<P>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    let (in_width, in_height) = image.dimensions();
+    let mut out = ImageBuffer::new(width, height);
+    if width == 0 || height == 0 {
+        return out;
+    }
+    assert!(in_width > 0 && in_height > 0, "cannot resize an empty image to a non-empty one");
+
+    let num_channels = P::channel_count() as usize;
+    let x_taps = resize_taps(in_width, width, filter);
+    let y_taps = resize_taps(in_height, height, filter);
+
+    // The horizontal pass, with width `width` and height `in_height`.
+    let row_len = width as usize * num_channels;
+    let mut horizontal = vec![0f32; row_len * in_height as usize];
+    for (y, row) in horizontal.chunks_mut(row_len).enumerate() {
+        for (taps, channels) in x_taps.iter().zip(row.chunks_mut(num_channels)) {
+            for &(x, weight) in taps {
+                let p = unsafe { image.unsafe_get_pixel(x, y as u32) };
+                for (c, v) in channels.iter_mut().zip(p.channels()) {
+                    let v: f32 = cast(*v);
+                    *c += weight * v;
+                }
+            }
+        }
+    }
+
+    for_each_row(&mut out, |y, row| {
+        let taps = &y_taps[y as usize];
+        for (i, c) in row.iter_mut().enumerate() {
+            let value: f32 = taps.iter().map(|&(y_in, weight)| weight * horizontal[y_in as usize * row_len + i]).sum();
+            *c = P::Subpixel::clamp(value);
+        }
+    });
+
+    out
+}
+
+/// Resizes an image to the given dimensions, as for [`resize`](fn.resize.html).
+///
+/// Returns `ImageProcError::EmptyImage` if `image` is empty and the requested dimensions
+/// are not.
+pub fn try_resize<P>(image: &Image<P>, width: u32, height: u32, filter: ResizeFilter) -> Result<Image<P>>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    if width > 0 && height > 0 {
+        check_non_empty(image.dimensions())?;
+    }
+    Ok(resize(image, width, height, filter))
+}
+
+// For each output coordinate when resizing a dimension of length in_len to out_len,
+// the input coordinates used to compute it and their weights. Pads by continuity.
+fn resize_taps(in_len: u32, out_len: u32, filter: ResizeFilter) -> Vec<Vec<(u32, f32)>> {
+    let scale = in_len as f32 / out_len as f32;
+    // Stretch the filter when downsampling, so that it covers every input pixel.
+    let filter_scale = scale.max(1.0);
+    let support = filter.support() * filter_scale;
+
+    (0..out_len)
+        .map(|o| {
+            let center = (o as f32 + 0.5) * scale;
+            if filter == ResizeFilter::Nearest {
+                return vec![(min(center as u32, in_len - 1), 1.0)];
+            }
+
+            // Input pixel i covers [i, i + 1), so has center i + 0.5.
+            let center = center - 0.5;
+            let first = (center - support).ceil() as i64;
+            let last = (center + support).floor() as i64;
+            let mut taps: Vec<(u32, f32)> = (first..last + 1)
+                .map(|i| {
+                    let weight = filter.weight((i as f32 - center) / filter_scale);
+                    (max(0, min(i, in_len as i64 - 1)) as u32, weight)
+                })
+                .collect();
+
+            let sum: f32 = taps.iter().map(|t| t.1).sum();
+            for t in &mut taps {
+                t.1 /= sum;
+            }
+            taps
+        })
+        .collect()
+}
+
+fn blend<P>(
+    top_left: P,
+    top_right: P,
+    bottom_left: P,
+    bottom_right: P,
+    right_weight: f32,
+    bottom_weight: f32,
+) -> P
+where
+    P: Pixel,
+    P::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let top = top_left.map2(&top_right, |u, v| {
+        P::Subpixel::clamp((1f32 - right_weight) * cast(u) + right_weight * cast(v))
+    });
+
+    let bottom = bottom_left.map2(&bottom_right, |u, v| {
+        P::Subpixel::clamp((1f32 - right_weight) * cast(u) + right_weight * cast(v))
+    });
+
+    top.map2(&bottom, |u, v| {
+        P::Subpixel::clamp((1f32 - bottom_weight) * cast(u) + bottom_weight * cast(v))
+    })
+}
+
+/// Samples `image` at `(x, y)` by bilinearly interpolating between the four
+/// pixels closest to it.
+///
+/// Returns `default` if any of these four pixels lies outside the image. In particular,
+/// this is the case for points in the last row or column of the image.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::geometric_transformations::interpolate_bilinear;
+///
+/// let image = gray_image!(
+///     10, 20, 30;
+///     50, 60, 70);
+///
+/// assert_eq!(interpolate_bilinear(&image, 0.5, 0.5, Luma([0])), Luma([35]));
+/// assert_eq!(interpolate_bilinear(&image, 1.25, 0.0, Luma([0])), Luma([22]));
+///
+/// // Out of bounds
+/// assert_eq!(interpolate_bilinear(&image, 2.5, 0.0, Luma([0])), Luma([0]));
+/// # }
+/// ```
+pub fn interpolate_bilinear<P>(image: &Image<P>, x: f32, y: f32, default: P) -> P
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let left = x.floor();
+    let right = left + 1f32;
+    let top = y.floor();
+    let bottom = top + 1f32;
+
+    let right_weight = x - left;
+    let bottom_weight = y - top;
+
+    // default if out of bound
+    let (width, height) = image.dimensions();
+    if left < 0f32 || right >= width as f32 || top < 0f32 || bottom >= height as f32 {
+        default
+    } else {
+        let (tl, tr, bl, br) = unsafe {
+            (
+                image.unsafe_get_pixel(left as u32, top as u32),
+                image.unsafe_get_pixel(right as u32, top as u32),
+                image.unsafe_get_pixel(left as u32, bottom as u32),
+                image.unsafe_get_pixel(right as u32, bottom as u32),
+            )
+        };
+        blend(tl, tr, bl, br, right_weight, bottom_weight)
+    }
+}
+
+/// Samples `image` at `(x, y)` by bicubically interpolating between the sixteen
+/// pixels closest to it, using the Catmull-Rom cubic.
+///
+/// Returns `default` if any of these sixteen pixels lies outside the image.
+/// As the Catmull-Rom cubic overshoots, results are clamped to the range of
+/// the pixel's subpixel type.
+pub fn interpolate_bicubic<P>(image: &Image<P>, x: f32, y: f32, default: P) -> P
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let left = x.floor() - 1f32;
+    let top = y.floor() - 1f32;
+
+    // default if any of the sixteen pixels used is out of bounds
+    let (width, height) = image.dimensions();
+    if left < 0f32 || left + 3f32 >= width as f32 || top < 0f32 || top + 3f32 >= height as f32 {
+        return default;
+    }
+
+    let x_weights = cubic_weights(x - x.floor());
+    let y_weights = cubic_weights(y - y.floor());
+    let (left, top) = (left as u32, top as u32);
+
+    let mut out = default;
+    for (c, channel) in out.channels_mut().iter_mut().enumerate() {
+        let mut value = 0f32;
+        for (dy, wy) in y_weights.iter().enumerate() {
+            for (dx, wx) in x_weights.iter().enumerate() {
+                let p = unsafe { image.unsafe_get_pixel(left + dx as u32, top + dy as u32) };
+                value += wx * wy * cast(p.channels()[c]);
+            }
+        }
+        *channel = P::Subpixel::clamp(value);
+    }
+    out
+}
+
+// Weights of the Catmull-Rom cubic for the four pixels at offsets -1, 0, 1 and 2
+// from a pixel, when sampling at fractional offset t from that pixel.
+fn cubic_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2f32 * t2 - t),
+        0.5 * (3f32 * t3 - 5f32 * t2 + 2f32),
+        0.5 * (-3f32 * t3 + 4f32 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+/// Samples `image` at `(x, y)` by taking the value of the nearest pixel.
+///
+/// Returns `default` if the nearest pixel lies outside the image.
+pub fn interpolate_nearest<P: Pixel + 'static>(image: &Image<P>, x: f32, y: f32, default: P) -> P {
+    let rx = x.round();
+    let ry = y.round();
+
+    // default if out of bound
+    let (width, height) = image.dimensions();
+    if rx < 0f32 || rx >= width as f32 || ry < 0f32 || ry >= height as f32 {
+        default
+    } else {
+        unsafe { image.unsafe_get_pixel(rx as u32, ry as u32) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::{gray_bench_image, rgb_bench_image};
+    use image::{GrayImage, ImageBuffer, Luma, Rgb};
+    use test;
+
+    #[test]
+    fn test_rotate_nearest_zero_radians() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12);
+
+        let rotated = rotate_nearest(&image, (1f32, 0f32), 0f32, Luma([99u8]));
+        assert_pixels_eq!(rotated, image);
+    }
+
+    #[test]
+    fn text_rotate_nearest_quarter_turn_clockwise() {
+        use std::f32;
+
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12);
+
+        let expected = gray_image!(
+            11, 01, 99;
+            12, 02, 99);
+
+        let rotated = rotate_nearest(&image, (1f32, 0f32), f32::consts::PI / 2f32, Luma([99u8]));
+        assert_pixels_eq!(rotated, expected);
+    }
+
+    #[test]
+    fn text_rotate_nearest_half_turn_anticlockwise() {
+        use std::f32;
+
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12);
+
+        let expected = gray_image!(
+            12, 11, 10;
+            02, 01, 00);
+
+        let rotated = rotate_nearest(&image, (1f32, 0.5f32), -f32::consts::PI, Luma([99u8]));
+        assert_pixels_eq!(rotated, expected);
+    }
+
+    #[bench]
+    fn bench_rotate_nearest(b: &mut test::Bencher) {
+        let image = GrayImage::from_pixel(200, 200, Luma([15u8]));
+        b.iter(|| {
+            let rotated = rotate_nearest(&image, (3f32, 3f32), 1f32, Luma([0u8]));
+            test::black_box(rotated);
+        });
+    }
+
+    #[bench]
+    fn bench_rotate_bilinear(b: &mut test::Bencher) {
+        let image = GrayImage::from_pixel(200, 200, Luma([15u8]));
+        b.iter(|| {
+            let rotated = rotate_bilinear(&image, (3f32, 3f32), 1f32, Luma([0u8]));
+            test::black_box(rotated);
+        });
+    }
+
+    #[test]
+    fn test_rotate_multiples_of_90_degrees() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12);
+
+        let expected = gray_image!(
+            10, 00;
+            11, 01;
+            12, 02);
+        assert_pixels_eq!(rotate90(&image), expected);
+
+        let expected = gray_image!(
+            12, 11, 10;
+            02, 01, 00);
+        assert_pixels_eq!(rotate180(&image), expected);
+
+        let expected = gray_image!(
+            02, 12;
+            01, 11;
+            00, 10);
+        assert_pixels_eq!(rotate270(&image), expected);
+    }
+
+    #[test]
+    fn test_rotate90_compositions() {
+        let image = gray_bench_image(7, 4);
+        assert_pixels_eq!(rotate90(&rotate90(&image)), rotate180(&image));
+        assert_pixels_eq!(rotate90(&rotate180(&image)), rotate270(&image));
+        assert_pixels_eq!(rotate90(&rotate270(&image)), image);
+    }
+
+    #[test]
+    fn test_rotate90_empty_image() {
+        let image = GrayImage::new(0, 3);
+        assert_eq!(rotate90(&image).dimensions(), (3, 0));
+        assert_eq!(rotate270(&image).dimensions(), (3, 0));
+    }
+
+    #[test]
+    fn test_resize_to_same_size_is_identity() {
+        let image = rgb_bench_image(9, 7);
+        for &filter in &[ResizeFilter::Nearest, ResizeFilter::Bilinear, ResizeFilter::Bicubic, ResizeFilter::Lanczos3] {
+            assert_pixels_eq!(resize(&image, 9, 7, filter), image);
+        }
+    }
+
+    #[test]
+    fn test_resize_bilinear_downsample() {
+        let image = gray_image!(0, 0, 80, 80);
+
+        // Shrinking by a factor of two stretches the triangle to radius two,
+        // so the weights of pixels at distance 0.5 and 1.5 are 3/8 and 1/8.
+        let expected = gray_image!(10, 70);
+        assert_pixels_eq!(resize(&image, 2, 1, ResizeFilter::Bilinear), expected);
+    }
+
+    #[test]
+    fn test_resize_bilinear_upsample() {
+        let image = gray_image!(0, 80);
+
+        // Output pixel centers have pre-images -0.25, 0.25, 0.75 and 1.25
+        // relative to the center of the first input pixel.
+        let expected = gray_image!(0, 20, 60, 80);
+        assert_pixels_eq!(resize(&image, 4, 1, ResizeFilter::Bilinear), expected);
+    }
+
+    #[test]
+    fn test_resize_preserves_constant_images() {
+        let image = GrayImage::from_pixel(13, 9, Luma([100u8]));
+        for &filter in &[ResizeFilter::Nearest, ResizeFilter::Bilinear, ResizeFilter::Bicubic, ResizeFilter::Lanczos3] {
+            for &(width, height) in &[(5, 4), (20, 30), (13, 1)] {
+                let resized = resize(&image, width, height, filter);
+                assert_eq!(resized.dimensions(), (width, height));
+                assert_pixels_eq_within!(resized, GrayImage::from_pixel(width, height, Luma([100u8])), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize_to_empty_image() {
+        let image = gray_bench_image(4, 4);
+        assert_eq!(resize(&image, 0, 3, ResizeFilter::Bicubic).dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn test_sinc_is_zero_at_non_zero_integers() {
+        assert_eq!(sinc(0.0), 1.0);
+        for i in 1..6 {
+            assert_eq!(sinc(i as f32), 0.0);
+            assert_eq!(sinc(-i as f32), 0.0);
+        }
+    }
+
+    macro_rules! bench_resize {
+        ($name:ident, $filter:expr, $width:expr, $height:expr) => {
+            #[bench]
+            fn $name(b: &mut test::Bencher) {
+                let image = rgb_bench_image(200, 200);
+                b.iter(|| {
+                    let resized = resize(&image, $width, $height, $filter);
+                    test::black_box(resized);
+                });
+            }
+        }
+    }
+
+    bench_resize!(bench_resize_bilinear_upsample, ResizeFilter::Bilinear, 300, 300);
+    bench_resize!(bench_resize_lanczos3_upsample, ResizeFilter::Lanczos3, 300, 300);
+    bench_resize!(bench_resize_lanczos3_downsample, ResizeFilter::Lanczos3, 70, 70);
+
+    #[test]
+    fn test_translate_positive_x_positive_y() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12;
+            20, 21, 22);
+
+        let expected = gray_image!(
+            00, 00, 01;
+            00, 00, 01;
+            10, 10, 11);
+
+        let translated = translate(&image, (1, 1));
+        assert_pixels_eq!(translated, expected);
+    }
+
+    #[test]
+    fn test_translate_positive_x_negative_y() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12;
+            20, 21, 22);
+
+        let expected = gray_image!(
+            10, 10, 11;
+            20, 20, 21;
+            20, 20, 21);
+
+        let translated = translate(&image, (1, -1));
+        assert_pixels_eq!(translated, expected);
+    }
+
+    #[test]
+    fn test_translate_large_x_large_y() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12;
+            20, 21, 22);
+
+        let expected = gray_image!(
+            00, 00, 00;
+            00, 00, 00;
+            00, 00, 00);
+
+        // Translating by more than the image width and height
+        let translated = translate(&image, (5, 5));
+        assert_pixels_eq!(translated, expected);
+    }
+
+    #[bench]
+    fn bench_translate(b: &mut test::Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let translated = translate(&image, (30, 30));
+            test::black_box(translated);
+        });
+    }
+
+    #[test]
+    fn test_warp_translation() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12;
+            20, 21, 22);
+
+        let expected = gray_image!(
+            00, 00, 00;
+            00, 00, 01;
+            00, 10, 11);
+
+        let translated = warp(&image, &Projection::translate(1.0, 1.0), Interpolation::Nearest, Luma([0]));
+        assert_pixels_eq!(translated, expected);
+    }
+
+    #[test]
+    fn test_warp_into_output_dimensions() {
+        let image = gray_image!(
+            1, 2;
+            3, 4);
+
+        let expected = gray_image!(
+            1, 1, 2, 2;
+            1, 1, 2, 2;
+            3, 3, 4, 4;
+            3, 3, 4, 4);
+
+        // The pre-image of (x, y) is (x / 2 - 0.25, y / 2 - 0.25), which
+        // rounds to the nearest pixel of the input.
+        let upscale = Projection::translate(0.5, 0.5) * Projection::scale(2.0, 2.0);
+        let mut out = GrayImage::new(4, 4);
+        warp_into(&image, &upscale, Interpolation::Nearest, Luma([99]), &mut out);
+        assert_pixels_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_warp_with_matches_warp() {
+        let image = gray_bench_image(15, 12);
+        let projection = Projection::rotate_about((7.0, 6.0), 0.4) * Projection::scale(1.2, 0.9);
+        let inverse = projection.invert();
+
+        for &interpolation in &[Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic] {
+            let expected = warp(&image, &projection, interpolation, Luma([3]));
+            let actual = warp_with(&image, |x, y| inverse * (x, y), interpolation, Luma([3]));
+            assert_pixels_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_remap() {
+        let image = gray_image!(
+            00, 01, 02;
+            10, 11, 12);
+
+        let dx = gray_image!(type: f32,
+            1.0, 1.0;
+            0.0, -1.0;
+            0.0, 5.0);
+        let dy = gray_image!(type: f32,
+            0.0, 1.0;
+            -1.0, 0.0;
+            -1.0, 0.0);
+
+        // The output has the dimensions of the displacement fields.
+        let expected = gray_image!(
+            01, 12;
+            00, 10;
+            10, 99);
+        assert_pixels_eq!(remap(&image, &dx, &dy, Interpolation::Nearest, Luma([99])), expected);
+    }
+
+    #[test]
+    fn test_remap_zero_displacement_is_identity() {
+        let image = gray_bench_image(6, 5);
+        let zero = ImageBuffer::from_pixel(6, 5, Luma([0f32]));
+        assert_pixels_eq!(remap(&image, &zero, &zero, Interpolation::Nearest, Luma([0])), image);
+
+        // Bilinear interpolation needs the pixels to the right and below each pre-image,
+        // so the last row and column are set to the default.
+        let expected = ImageBuffer::from_fn(6, 5, |x, y| if x < 5 && y < 4 { *image.get_pixel(x, y) } else { Luma([0]) });
+        assert_pixels_eq!(remap(&image, &zero, &zero, Interpolation::Bilinear, Luma([0])), expected);
+    }
+
+    #[test]
+    fn test_projection_from_singular_matrix() {
+        assert!(Projection::from_matrix([
+            1.0, 2.0, 0.0,
+            2.0, 4.0, 0.0,
+            0.0, 0.0, 1.0,
+        ]).is_none());
+    }
+
+    #[test]
+    fn test_projection_try_constructors() {
+        assert_eq!(Projection::try_scale(2.0, 3.0), Ok(Projection::scale(2.0, 3.0)));
+        assert!(Projection::try_scale(0.0, 3.0).is_err());
+        assert!(Projection::try_scale(2.0, 0.0).is_err());
+
+        assert_eq!(Projection::try_shear(0.5, 0.0), Ok(Projection::shear(0.5, 0.0)));
+        assert!(Projection::try_shear(2.0, 0.5).is_err());
+    }
+
+    #[test]
+    fn test_try_remap_dimension_mismatch() {
+        let image = gray_bench_image(6, 5);
+        let dx = ImageBuffer::from_pixel(6, 5, Luma([0f32]));
+        let dy = ImageBuffer::from_pixel(5, 6, Luma([0f32]));
+        assert_eq!(
+            try_remap(&image, &dx, &dy, Interpolation::Nearest, Luma([0])).unwrap_err(),
+            ImageProcError::DimensionMismatch { expected: (6, 5), actual: (5, 6) }
+        );
+        assert_pixels_eq!(
+            try_remap(&image, &dx, &dx, Interpolation::Nearest, Luma([0])).unwrap(),
+            image
+        );
+    }
+
+    #[test]
+    fn test_try_resize_empty_image() {
+        let empty = GrayImage::new(0, 4);
+        assert_eq!(
+            try_resize(&empty, 2, 2, ResizeFilter::Nearest).unwrap_err(),
+            ImageProcError::EmptyImage
+        );
+        assert_eq!(try_resize(&empty, 0, 2, ResizeFilter::Nearest).unwrap().dimensions(), (0, 2));
+
+        let image = gray_bench_image(3, 4);
+        assert_pixels_eq!(
+            try_resize(&image, 6, 2, ResizeFilter::Bilinear).unwrap(),
+            resize(&image, 6, 2, ResizeFilter::Bilinear)
+        );
+    }
+
+    #[test]
+    fn test_projection_constructors() {
+        let close = |p: (f32, f32), q: (f32, f32)| (p.0 - q.0).abs() < 1e-5 && (p.1 - q.1).abs() < 1e-5;
+
+        assert_eq!(Projection::translate(2.0, -3.0) * (1.0, 1.0), (3.0, -2.0));
+        assert_eq!(Projection::scale(2.0, 0.5) * (3.0, 4.0), (6.0, 2.0));
+        assert_eq!(Projection::shear(1.0, 0.0) * (3.0, 4.0), (7.0, 4.0));
+
+        // A quarter turn clockwise, given that y increases downwards.
+        let quarter_turn = Projection::rotate(::std::f32::consts::PI / 2.0);
+        assert!(close(quarter_turn * (1.0, 0.0), (0.0, 1.0)));
+
+        let about = Projection::rotate_about((2.0, 3.0), ::std::f32::consts::PI / 2.0);
+        assert!(close(about * (2.0, 3.0), (2.0, 3.0)));
+        assert!(close(about * (3.0, 3.0), (2.0, 4.0)));
+    }
+
+    #[test]
+    fn test_projection_composition_and_inverse() {
+        let close = |p: (f32, f32), q: (f32, f32)| (p.0 - q.0).abs() < 1e-4 && (p.1 - q.1).abs() < 1e-4;
+
+        let a = Projection::from_matrix([
+            1.0, 0.2, 3.0,
+            -0.4, 2.0, 1.0,
+            0.01, 0.02, 1.0,
+        ]).unwrap();
+        let b = Projection::rotate_about((5.0, 1.0), 0.7) * Projection::shear(0.3, -0.2);
+
+        for &p in &[(0.0, 0.0), (4.0, -2.0), (10.0, 7.5)] {
+            assert!(close((a * b) * p, a * (b * p)));
+            assert!(close((a * b).invert() * ((a * b) * p), p));
+        }
+    }
+
+    #[test]
+    fn test_warp_bicubic_reproduces_input_at_pixel_centres() {
+        let image = gray_bench_image(8, 6);
+
+        let shifted = warp(&image, &Projection::translate(-1.0, 0.0), Interpolation::Bicubic, Luma([0]));
+        // Sampling at (x + 1, y) uses pixels x to x + 3 and y - 1 to y + 2.
+        let expected = ImageBuffer::from_fn(8, 6, |x, y| {
+            if x + 3 < 8 && y >= 1 && y + 2 < 6 {
+                *image.get_pixel(x + 1, y)
+            } else {
+                Luma([0])
+            }
+        });
+        assert_pixels_eq!(shifted, expected);
+    }
+
+    #[test]
+    fn test_warp_bicubic_preserves_linear_gradients() {
+        let image = GrayImage::from_fn(8, 8, |x, y| Luma([(10 * x + 5 * y) as u8]));
+
+        let shifted = warp(&image, &Projection::translate(-0.5, -0.5), Interpolation::Bicubic, Luma([0]));
+        // The Catmull-Rom cubic interpolates linear functions exactly, up to
+        // the truncation of outputs to integers.
+        for y in 1..5 {
+            for x in 1..5 {
+                let expected = 10.0 * (x as f32 + 0.5) + 5.0 * (y as f32 + 0.5);
+                let actual = shifted.get_pixel(x, y)[0] as f32;
+                assert!((actual - expected).abs() < 1.0, "({}, {}): {} != {}", x, y, actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotate_bicubic_matches_warp() {
+        let image = gray_bench_image(20, 20);
+        let rotated = rotate(&image, (9.5, 9.5), 0.3, Interpolation::Bicubic, Luma([7]));
+        let warped = warp(&image, &Projection::rotate_about((9.5, 9.5), 0.3), Interpolation::Bicubic, Luma([7]));
+        assert_pixels_eq!(rotated, warped);
+    }
+
+    #[test]
+    fn test_interpolate_nearest() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        assert_eq!(interpolate_nearest(&image, -0.4, 0.3, Luma([0])), Luma([1]));
+        assert_eq!(interpolate_nearest(&image, 1.6, 0.6, Luma([0])), Luma([6]));
+        assert_eq!(interpolate_nearest(&image, 2.6, 1.0, Luma([0])), Luma([0]));
+        assert_eq!(interpolate_nearest(&image, 0.0, -0.6, Luma([0])), Luma([0]));
+    }
+
+    #[test]
+    fn test_interpolate_bilinear_rgb() {
+        let image: Image<Rgb<u8>> = ImageBuffer::from_fn(2, 2, |x, y| Rgb([(100 * x) as u8, (100 * y) as u8, 50]));
+        assert_eq!(interpolate_bilinear(&image, 0.25, 0.75, Rgb([0, 0, 0])), Rgb([25, 75, 50]));
+        assert_eq!(interpolate_bilinear(&image, 0.0, 0.0, Rgb([0, 0, 0])), Rgb([0, 0, 50]));
+        assert_eq!(interpolate_bilinear(&image, -0.1, 0.0, Rgb([1, 2, 3])), Rgb([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_interpolate_bicubic_reproduces_input_at_pixels() {
+        let image = gray_bench_image(6, 6);
+        for y in 0..6 {
+            for x in 0..6 {
+                let expected = if x >= 1 && x + 2 < 6 && y >= 1 && y + 2 < 6 {
+                    *image.get_pixel(x, y)
+                } else {
+                    Luma([0])
+                };
+                assert_eq!(interpolate_bicubic(&image, x as f32, y as f32, Luma([0])), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_warp_u16_and_f32_images() {
+        let projection = Projection::translate(0.5, 0.0);
+
+        let image = gray_image!(type: u16,
+            60000, 10000, 65535;
+            0,     20000, 30000);
+        let warped = warp(&image, &projection, Interpolation::Bilinear, Luma([7]));
+        let expected = gray_image!(type: u16,
+            7, 35000, 37767;
+            7, 7,     7);
+        assert_pixels_eq!(warped, expected);
+        assert_eq!(interpolate_bilinear(&image, 0.5, 0.5, Luma([7])), Luma([22500]));
+
+        let image = gray_image!(type: f32,
+            -1.5, 2.0, 0.25;
+            0.5,  4.0, 1.0);
+        assert_eq!(interpolate_bilinear(&image, 1.5, 0.0, Luma([0.0])), Luma([1.125]));
+        assert_eq!(interpolate_bicubic(&image, 1.5, 0.0, Luma([0.0])), Luma([0.0]));
+        assert_eq!(interpolate_nearest(&image, 0.2, 0.0, Luma([0.0])), Luma([-1.5]));
+    }
+
+    macro_rules! bench_warp {
+        ($name:ident, $interpolation:expr) => {
+            #[bench]
+            fn $name(b: &mut test::Bencher) {
+                let image = GrayImage::from_pixel(200, 200, Luma([15u8]));
+                let projection = Projection::rotate_about((100.0, 100.0), 0.3) * Projection::scale(1.1, 0.9);
+                b.iter(|| {
+                    let transformed = warp(&image, &projection, $interpolation, Luma([0]));
+                    test::black_box(transformed);
+                });
+            }
+        }
+    }
+
+    bench_warp!(bench_warp_nearest, Interpolation::Nearest);
+    bench_warp!(bench_warp_bilinear, Interpolation::Bilinear);
+    bench_warp!(bench_warp_bicubic, Interpolation::Bicubic);
+}
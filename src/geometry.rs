@@ -0,0 +1,1472 @@
+//! Geometric primitives: line segments, polygons and point-in-polygon tests, convex
+//! hulls, minimal bounding shapes and ellipse fitting, and estimation of the
+//! transformation between matched sets of points.
+//!
+//! Points are `(x, y)` pairs in image coordinates, with `y` increasing downwards.
+//! Polygons are given by their vertices as an open path, i.e. the first and last
+//! vertices should not be equal. An implicit edge joins the last vertex to the first.
+
+use error::{ImageProcError, Result};
+use geometric_transformations::Projection;
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Range};
+use rect::RectF;
+use std::cmp::Ordering;
+
+/// A line segment between two points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineSegment {
+    /// First endpoint of the segment.
+    pub start: (f32, f32),
+    /// Second endpoint of the segment.
+    pub end: (f32, f32),
+}
+
+impl LineSegment {
+    /// A segment from `start` to `end`.
+    pub fn new(start: (f32, f32), end: (f32, f32)) -> LineSegment {
+        LineSegment { start, end }
+    }
+
+    /// Length of the segment.
+    pub fn length(&self) -> f32 {
+        let (dx, dy) = sub(self.end, self.start);
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    /// Midpoint of the segment.
+    pub fn midpoint(&self) -> (f32, f32) {
+        ((self.start.0 + self.end.0) / 2.0, (self.start.1 + self.end.1) / 2.0)
+    }
+
+    /// The point on the segment closest to `point`.
+    pub fn closest_point(&self, point: (f32, f32)) -> (f32, f32) {
+        let d = sub(self.end, self.start);
+        let length_squared = dot(d, d);
+        if length_squared == 0.0 {
+            return self.start;
+        }
+        let t = (dot(sub(point, self.start), d) / length_squared).max(0.0).min(1.0);
+        (self.start.0 + t * d.0, self.start.1 + t * d.1)
+    }
+
+    /// Euclidean distance from `point` to the closest point on the segment.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::geometry::LineSegment;
+    ///
+    /// let segment = LineSegment::new((0.0, 0.0), (4.0, 0.0));
+    /// assert_eq!(segment.distance_to_point((2.0, 3.0)), 3.0);
+    /// assert_eq!(segment.distance_to_point((7.0, 4.0)), 5.0);
+    /// ```
+    pub fn distance_to_point(&self, point: (f32, f32)) -> f32 {
+        let (dx, dy) = sub(point, self.closest_point(point));
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    /// Returns the point at which self and other intersect, or `None` if they don't
+    /// intersect or are parallel. Collinear overlapping segments intersect in more than
+    /// one point, so also return `None`; use [`intersects`](#method.intersects) to
+    /// detect these.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::geometry::LineSegment;
+    ///
+    /// let a = LineSegment::new((0.0, 0.0), (4.0, 4.0));
+    /// let b = LineSegment::new((0.0, 4.0), (4.0, 0.0));
+    /// assert_eq!(a.intersection(&b), Some((2.0, 2.0)));
+    ///
+    /// let c = LineSegment::new((5.0, 0.0), (6.0, 0.0));
+    /// assert_eq!(b.intersection(&c), None);
+    /// ```
+    pub fn intersection(&self, other: &LineSegment) -> Option<(f32, f32)> {
+        let r = sub(self.end, self.start);
+        let s = sub(other.end, other.start);
+        let denominator = cross(r, s);
+        if denominator == 0.0 {
+            return None;
+        }
+        let q = sub(other.start, self.start);
+        let t = cross(q, s) / denominator;
+        let u = cross(q, r) / denominator;
+        if t < 0.0 || t > 1.0 || u < 0.0 || u > 1.0 {
+            return None;
+        }
+        Some((self.start.0 + t * r.0, self.start.1 + t * r.1))
+    }
+
+    /// Whether self and other share at least one point. Unlike
+    /// [`intersection`](#method.intersection) this is true for collinear overlapping segments.
+    pub fn intersects(&self, other: &LineSegment) -> bool {
+        let (p, q) = (self.start, self.end);
+        let (a, b) = (other.start, other.end);
+        let d1 = orientation(a, b, p);
+        let d2 = orientation(a, b, q);
+        let d3 = orientation(p, q, a);
+        let d4 = orientation(p, q, b);
+
+        if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+            && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+        {
+            return true;
+        }
+
+        (d1 == 0.0 && in_bounding_box(a, b, p))
+            || (d2 == 0.0 && in_bounding_box(a, b, q))
+            || (d3 == 0.0 && in_bounding_box(p, q, a))
+            || (d4 == 0.0 && in_bounding_box(p, q, b))
+    }
+}
+
+/// Rule deciding which points lie inside a possibly self-intersecting polygon.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside if a ray from it crosses the polygon's boundary an odd number of times.
+    EvenOdd,
+    /// A point is inside if the polygon winds around it a non-zero number of times.
+    NonZero,
+}
+
+/// The number of times `polygon` winds around `point`. This is positive if the
+/// polygon's vertices run clockwise as displayed, i.e. with `y` increasing downwards,
+/// and negative if they run anticlockwise.
+///
+/// Points on the boundary of the polygon are counted as inside edges that they
+/// lie to the left or top of, and outside those they lie to the right or bottom of.
+pub fn winding_number(point: (f32, f32), polygon: &[(f32, f32)]) -> i32 {
+    let mut winding = 0;
+    for (a, b) in edges(polygon) {
+        if a.1 <= point.1 {
+            if b.1 > point.1 && orientation(a, b, point) > 0.0 {
+                winding += 1;
+            }
+        } else if b.1 <= point.1 && orientation(a, b, point) < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+/// Whether `point` lies inside `polygon` under the given fill rule. Points on the boundary
+/// are treated as in [`winding_number`](fn.winding_number.html).
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::{point_in_polygon, FillRule};
+///
+/// // A pentagram, whose central pentagon is wound around twice.
+/// let star = [(5.0, 0.0), (8.0, 10.0), (0.0, 4.0), (10.0, 4.0), (2.0, 10.0)];
+///
+/// assert!(point_in_polygon((5.0, 2.0), &star, FillRule::EvenOdd));
+/// assert!(!point_in_polygon((5.0, 5.0), &star, FillRule::EvenOdd));
+/// assert!(point_in_polygon((5.0, 5.0), &star, FillRule::NonZero));
+/// assert!(!point_in_polygon((9.0, 9.0), &star, FillRule::NonZero));
+/// ```
+pub fn point_in_polygon(point: (f32, f32), polygon: &[(f32, f32)], rule: FillRule) -> bool {
+    match rule {
+        FillRule::NonZero => winding_number(point, polygon) != 0,
+        FillRule::EvenOdd => {
+            let mut inside = false;
+            for (a, b) in edges(polygon) {
+                if (a.1 <= point.1) != (b.1 <= point.1) {
+                    let x = a.0 + (point.1 - a.1) * (b.0 - a.0) / (b.1 - a.1);
+                    if point.0 < x {
+                        inside = !inside;
+                    }
+                }
+            }
+            inside
+        }
+    }
+}
+
+/// A polygon, given by its vertices.
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::Polygon;
+/// use imageproc::rect::RectF;
+///
+/// // A triangle whose vertices run clockwise as displayed.
+/// let triangle = Polygon::new(vec![(0.0, 0.0), (12.0, 0.0), (0.0, 9.0)]);
+/// assert_eq!(triangle.signed_area(), 54.0);
+/// assert_eq!(triangle.perimeter(), 36.0);
+/// assert_eq!(triangle.centroid(), Some((4.0, 3.0)));
+/// assert_eq!(triangle.bounding_rect(), Some(RectF::new(0.0, 0.0, 12.0, 9.0)));
+///
+/// // Clipping keeps the part of the triangle inside the rect, which is
+/// // a square with its bottom right corner cut off.
+/// let clipped = triangle.clip_to_rect(RectF::new(0.0, 0.0, 6.0, 6.0));
+/// assert_eq!(clipped.vertices().len(), 5);
+/// assert_eq!(clipped.area(), 36.0 - 1.5);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<(f32, f32)>,
+}
+
+impl Polygon {
+    /// A polygon with the given vertices, as an open path.
+    pub fn new(vertices: Vec<(f32, f32)>) -> Polygon {
+        Polygon { vertices }
+    }
+
+    /// The vertices of the polygon.
+    pub fn vertices(&self) -> &[(f32, f32)] {
+        &self.vertices
+    }
+
+    /// Whether the polygon has no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// The edges of the polygon, including the edge from its last vertex to its first.
+    pub fn edges<'a>(&'a self) -> impl Iterator<Item = LineSegment> + 'a {
+        edges(&self.vertices).map(|(a, b)| LineSegment::new(a, b))
+    }
+
+    /// Signed area of the polygon. This is positive if the vertices run clockwise as
+    /// displayed, i.e. with `y` increasing downwards, and negative if they run anticlockwise.
+    /// The signed areas of the regions bounded by a self-intersecting polygon are added
+    /// with weights given by their winding numbers.
+    pub fn signed_area(&self) -> f32 {
+        edges(&self.vertices).map(|(a, b)| cross(a, b)).sum::<f32>() / 2.0
+    }
+
+    /// Absolute value of the [`signed_area`](#method.signed_area).
+    pub fn area(&self) -> f32 {
+        self.signed_area().abs()
+    }
+
+    /// Total length of the polygon's edges.
+    pub fn perimeter(&self) -> f32 {
+        self.edges().map(|e| e.length()).sum()
+    }
+
+    /// The centroid of the region bounded by the polygon, or `None` if
+    /// the polygon has zero area.
+    pub fn centroid(&self) -> Option<(f32, f32)> {
+        let mut area = 0.0;
+        let (mut cx, mut cy) = (0.0, 0.0);
+        for (a, b) in edges(&self.vertices) {
+            let c = cross(a, b);
+            area += c;
+            cx += (a.0 + b.0) * c;
+            cy += (a.1 + b.1) * c;
+        }
+        if area == 0.0 {
+            return None;
+        }
+        Some((cx / (3.0 * area), cy / (3.0 * area)))
+    }
+
+    /// The smallest axis-aligned rect containing the polygon, or `None` if
+    /// this has zero width or height.
+    pub fn bounding_rect(&self) -> Option<RectF> {
+        let min_x = self.vertices.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+        let max_x = self.vertices.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = self.vertices.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+        let max_y = self.vertices.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+        if !(max_x > min_x && max_y > min_y) {
+            return None;
+        }
+        Some(RectF::new(min_x, min_y, max_x - min_x, max_y - min_y))
+    }
+
+    /// Whether `point` lies inside the polygon under the given fill rule.
+    /// See [`point_in_polygon`](fn.point_in_polygon.html).
+    pub fn contains(&self, point: (f32, f32), rule: FillRule) -> bool {
+        point_in_polygon(point, &self.vertices, rule)
+    }
+
+    /// Returns the part of self lying inside the convex polygon `clip`, which may have
+    /// vertices running in either direction. Uses Sutherland-Hodgman clipping, so if self is
+    /// not convex the result may include zero-area edges along the boundary of `clip`.
+    ///
+    /// The result is empty if self and `clip` do not overlap, or if `clip` has zero area.
+    pub fn clip_convex(&self, clip: &Polygon) -> Polygon {
+        // Flip the orientation test for anticlockwise clip polygons.
+        let sign = match clip.signed_area() {
+            a if a > 0.0 => 1.0,
+            a if a < 0.0 => -1.0,
+            _ => return Polygon::new(vec![]),
+        };
+        let mut output = self.vertices.clone();
+        for (a, b) in edges(&clip.vertices) {
+            if output.is_empty() {
+                break;
+            }
+            let input = output;
+            output = Vec::with_capacity(input.len() + 1);
+            for (p, q) in edges(&input) {
+                let (op, oq) = (sign * orientation(a, b, p), sign * orientation(a, b, q));
+                if op >= 0.0 {
+                    output.push(p);
+                }
+                if (op >= 0.0) != (oq >= 0.0) {
+                    let t = op / (op - oq);
+                    output.push((p.0 + t * (q.0 - p.0), p.1 + t * (q.1 - p.1)));
+                }
+            }
+        }
+        Polygon::new(output)
+    }
+
+    /// Returns the part of self lying inside `rect`. See [`clip_convex`](#method.clip_convex).
+    pub fn clip_to_rect(&self, rect: RectF) -> Polygon {
+        self.clip_convex(&Polygon::from(rect))
+    }
+}
+
+impl From<Vec<(f32, f32)>> for Polygon {
+    fn from(vertices: Vec<(f32, f32)>) -> Polygon {
+        Polygon::new(vertices)
+    }
+}
+
+impl From<RectF> for Polygon {
+    /// The corners of the rect, clockwise from its top left.
+    fn from(rect: RectF) -> Polygon {
+        let (l, t, r, b) = (rect.left(), rect.top(), rect.right(), rect.bottom());
+        Polygon::new(vec![(l, t), (r, t), (r, b), (l, b)])
+    }
+}
+
+/// An ellipse, given by its centre, the lengths of its semi-axes and the orientation of its
+/// major axis. See [`fit_ellipse`](fn.fit_ellipse.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ellipse {
+    /// Centre of the ellipse.
+    pub center: (f32, f32),
+    /// Half the length of the longest diameter.
+    pub semi_major: f32,
+    /// Half the length of the shortest diameter.
+    pub semi_minor: f32,
+    /// Angle in radians from the positive `x` axis to the major axis, in the range
+    /// (-pi / 2, pi / 2]. As `y` increases downwards, positive angles are clockwise
+    /// as displayed.
+    pub angle: f32,
+}
+
+impl Ellipse {
+    /// Area of the ellipse.
+    pub fn area(&self) -> f32 {
+        ::std::f32::consts::PI * self.semi_major * self.semi_minor
+    }
+
+    /// Whether a point lies inside or on the boundary of the ellipse.
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        let (dx, dy) = (point.0 - self.center.0, point.1 - self.center.1);
+        let (sin, cos) = self.angle.sin_cos();
+        let (u, v) = (dx * cos + dy * sin, dy * cos - dx * sin);
+        (u / self.semi_major).powi(2) + (v / self.semi_minor).powi(2) <= 1.0
+    }
+}
+
+/// A rectangle with arbitrary orientation, as returned by
+/// [`min_area_rect`](fn.min_area_rect.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RotatedRect {
+    /// Centre of the rectangle.
+    pub center: (f32, f32),
+    /// Length of the sides parallel to the direction given by `angle`.
+    pub width: f32,
+    /// Length of the sides perpendicular to the direction given by `angle`.
+    pub height: f32,
+    /// Angle in radians from the positive `x` axis to the sides of length `width`, in the
+    /// range (-pi / 2, pi / 2]. As `y` increases downwards, positive angles are clockwise
+    /// as displayed.
+    pub angle: f32,
+}
+
+impl RotatedRect {
+    /// Area of the rectangle.
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    /// The corners of the rectangle, clockwise as displayed. When `angle` is zero
+    /// the first corner is the top left.
+    pub fn corners(&self) -> [(f32, f32); 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        let (hw, hh) = (self.width / 2.0, self.height / 2.0);
+        let (c, u, v) = (self.center, (cos * hw, sin * hw), (-sin * hh, cos * hh));
+        [
+            (c.0 - u.0 - v.0, c.1 - u.1 - v.1),
+            (c.0 + u.0 - v.0, c.1 + u.1 - v.1),
+            (c.0 + u.0 + v.0, c.1 + u.1 + v.1),
+            (c.0 - u.0 + v.0, c.1 - u.1 + v.1),
+        ]
+    }
+}
+
+impl From<RotatedRect> for Polygon {
+    /// The [`corners`](struct.RotatedRect.html#method.corners) of the rect.
+    fn from(rect: RotatedRect) -> Polygon {
+        Polygon::new(rect.corners().to_vec())
+    }
+}
+
+/// A circle, given by its centre and radius.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Circle {
+    /// Centre of the circle.
+    pub center: (f32, f32),
+    /// Radius of the circle.
+    pub radius: f32,
+}
+
+impl Circle {
+    /// Area of the circle.
+    pub fn area(&self) -> f32 {
+        ::std::f32::consts::PI * self.radius * self.radius
+    }
+
+    /// Whether a point lies inside or on the boundary of the circle.
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        let (dx, dy) = sub(point, self.center);
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+}
+
+/// Returns the convex hull of a set of points, computed using Andrew's monotone chain
+/// algorithm.
+///
+/// The vertices of the hull run clockwise as displayed, starting from the point with
+/// smallest `x` (and smallest `y` among those), so the result has non-negative
+/// [`signed_area`](struct.Polygon.html#method.signed_area). Duplicate points and points
+/// lying on the hull's edges are omitted. If all points are collinear the hull contains
+/// just the two extreme points, and it is empty if `points` is.
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::convex_hull;
+///
+/// let points = [(0.0, 0.0), (4.0, 0.0), (2.0, 1.0), (4.0, 4.0), (2.0, 4.0), (0.0, 4.0)];
+/// assert_eq!(
+///     convex_hull(&points).vertices(),
+///     &[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+/// ```
+pub fn convex_hull(points: &[(f32, f32)]) -> Polygon {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(Ordering::Equal)
+            .then(a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    });
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return Polygon::new(sorted);
+    }
+
+    // The upper chain from left to right, then the lower chain from right to left,
+    // keeping only clockwise turns.
+    let mut hull: Vec<(f32, f32)> = Vec::with_capacity(sorted.len() + 1);
+    for &p in &sorted {
+        while hull.len() >= 2 && orientation(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    let upper_len = hull.len() + 1;
+    for &p in sorted.iter().rev().skip(1) {
+        while hull.len() >= upper_len && orientation(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    // The last point pushed is the first point of the upper chain.
+    hull.pop();
+    Polygon::new(hull)
+}
+
+/// Returns the rectangle of smallest area containing the given points, or `None`
+/// if `points` is empty.
+///
+/// The rectangle has a side lying along an edge of the [`convex_hull`](fn.convex_hull.html)
+/// of the points, and is found by rotating calipers around the hull, so this takes
+/// O(n log n) time. If the points are collinear the result has zero height.
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::min_area_rect;
+///
+/// // A diamond, which is a square rotated by 45 degrees.
+/// let points = [(2.0, 0.0), (4.0, 2.0), (2.0, 4.0), (0.0, 2.0), (2.0, 2.0)];
+/// let rect = min_area_rect(&points).unwrap();
+///
+/// assert!((rect.area() - 8.0).abs() < 1e-5);
+/// assert!((rect.angle.abs() - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+/// assert!((rect.center.0 - 2.0).abs() < 1e-5 && (rect.center.1 - 2.0).abs() < 1e-5);
+/// ```
+pub fn min_area_rect(points: &[(f32, f32)]) -> Option<RotatedRect> {
+    let hull = convex_hull(points);
+    let hull = hull.vertices();
+    let n = hull.len();
+    if n == 0 {
+        return None;
+    }
+    if n == 1 {
+        return Some(RotatedRect { center: hull[0], width: 0.0, height: 0.0, angle: 0.0 });
+    }
+
+    let at = |i: usize| hull[i % n];
+    let mut best: Option<(f32, RotatedRect)> = None;
+    // Indices of the hull points furthest along, furthest from and least far along
+    // each edge. These only move forwards as the edge rotates, so are stored unreduced.
+    let (mut far, mut top, mut near) = (1, 1, 1);
+    for i in 0..n {
+        let (a, b) = (hull[i], at(i + 1));
+        let length = LineSegment::new(a, b).length();
+        let u = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        // Points towards the interior of the hull.
+        let v = (-u.1, u.0);
+
+        far = far.max(i + 1);
+        while dot(at(far + 1), u) > dot(at(far), u) {
+            far += 1;
+        }
+        top = top.max(far);
+        while dot(at(top + 1), v) > dot(at(top), v) {
+            top += 1;
+        }
+        near = near.max(top);
+        while dot(at(near + 1), u) < dot(at(near), u) {
+            near += 1;
+        }
+
+        let (min_u, max_u) = (dot(at(near), u), dot(at(far), u));
+        let (min_v, max_v) = (dot(a, v), dot(at(top), v));
+        let area = (max_u - min_u) * (max_v - min_v);
+        if best.map_or(true, |(best_area, _)| area < best_area) {
+            let (mid_u, mid_v) = ((min_u + max_u) / 2.0, (min_v + max_v) / 2.0);
+            let mut angle = u.1.atan2(u.0);
+            if angle <= -::std::f32::consts::FRAC_PI_2 {
+                angle += ::std::f32::consts::PI;
+            } else if angle > ::std::f32::consts::FRAC_PI_2 {
+                angle -= ::std::f32::consts::PI;
+            }
+            let rect = RotatedRect {
+                center: (u.0 * mid_u + v.0 * mid_v, u.1 * mid_u + v.1 * mid_v),
+                width: max_u - min_u,
+                height: max_v - min_v,
+                angle,
+            };
+            best = Some((area, rect));
+        }
+    }
+    best.map(|(_, rect)| rect)
+}
+
+/// Returns the circle of smallest radius containing the given points, or `None` if
+/// `points` is empty.
+///
+/// Uses Welzl's algorithm on the vertices of the [`convex_hull`](fn.convex_hull.html)
+/// of the points, taken in a fixed pseudo-random order, so the result is deterministic.
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::min_enclosing_circle;
+///
+/// let points = [(0.0, 0.0), (6.0, 0.0), (3.0, 1.0), (3.0, -2.0)];
+/// let circle = min_enclosing_circle(&points).unwrap();
+///
+/// assert!((circle.center.0 - 3.0).abs() < 1e-5 && circle.center.1.abs() < 1e-5);
+/// assert!((circle.radius - 3.0).abs() < 1e-5);
+/// ```
+pub fn min_enclosing_circle(points: &[(f32, f32)]) -> Option<Circle> {
+    let hull = convex_hull(points);
+    let mut points: Vec<(f64, f64)> = hull.vertices().iter().map(|p| (p.0 as f64, p.1 as f64)).collect();
+    if points.is_empty() {
+        return None;
+    }
+    let seed_array: &[_] = &[0];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+    rng.shuffle(&mut points);
+
+    let mut circle = (points[0], 0.0);
+    for i in 1..points.len() {
+        if enclosed(circle, points[i]) {
+            continue;
+        }
+        circle = (points[i], 0.0);
+        for j in 0..i {
+            if enclosed(circle, points[j]) {
+                continue;
+            }
+            circle = diametral_circle(points[i], points[j]);
+            for k in 0..j {
+                if !enclosed(circle, points[k]) {
+                    circle = circumcircle(points[i], points[j], points[k]);
+                }
+            }
+        }
+    }
+
+    let ((x, y), radius) = circle;
+    Some(Circle { center: (x as f32, y as f32), radius: radius as f32 })
+}
+
+/// Whether p lies in the circle with given centre and radius, allowing for rounding errors.
+fn enclosed(circle: ((f64, f64), f64), p: (f64, f64)) -> bool {
+    let ((cx, cy), r) = circle;
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt() <= r * (1.0 + 1e-9) + 1e-9
+}
+
+fn diametral_circle(a: (f64, f64), b: (f64, f64)) -> ((f64, f64), f64) {
+    let center = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    (center, ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt() / 2.0)
+}
+
+/// The circle through a, b and c, or the smallest circle containing them if they
+/// are collinear.
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> ((f64, f64), f64) {
+    let (bx, by) = (b.0 - a.0, b.1 - a.1);
+    let (cx, cy) = (c.0 - a.0, c.1 - a.1);
+    let d = 2.0 * (bx * cy - by * cx);
+    if d == 0.0 {
+        let circles = [diametral_circle(a, b), diametral_circle(a, c), diametral_circle(b, c)];
+        return circles.iter().cloned().fold(circles[0], |max, circle| if circle.1 > max.1 { circle } else { max });
+    }
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    let (ux, uy) = ((cy * b2 - by * c2) / d, (bx * c2 - cx * b2) / d);
+    ((a.0 + ux, a.1 + uy), (ux * ux + uy * uy).sqrt())
+}
+
+/// Fits an ellipse to a set of points by direct least squares, or returns `None` if there
+/// are fewer than five points or no ellipse fits them.
+///
+/// Minimises the algebraic distance from the points to a general conic, subject to a
+/// constraint that ensures the conic is an ellipse, using the method of [Fitzgibbon et al.]
+/// in the numerically stable form given by [Halir and Flusser]. Points need not be ordered,
+/// and may cover only part of the ellipse, so this can be applied to the points of a
+/// [`Contour`](../contours/struct.Contour.html) or the boundary of a connected component.
+/// For filled regions, the [`ellipse`](../moments/struct.Moments.html#method.ellipse) of
+/// their moments is cheaper.
+///
+/// [Fitzgibbon et al.]: https://doi.org/10.1109/34.765658
+/// [Halir and Flusser]: https://autotrace.sourceforge.net/WSCG98.pdf
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::fit_ellipse;
+///
+/// // Points on an ellipse centred at (10, 5) with semi-axes 4 and 2 along x and y.
+/// let points: Vec<(f32, f32)> = (0..12)
+///     .map(|i| (i as f32 * 0.5).sin_cos())
+///     .map(|(sin, cos)| (10.0 + 4.0 * cos, 5.0 + 2.0 * sin))
+///     .collect();
+///
+/// let ellipse = fit_ellipse(&points).unwrap();
+/// assert!((ellipse.center.0 - 10.0).abs() < 1e-3 && (ellipse.center.1 - 5.0).abs() < 1e-3);
+/// assert!((ellipse.semi_major - 4.0).abs() < 1e-3 && (ellipse.semi_minor - 2.0).abs() < 1e-3);
+/// assert!(ellipse.angle.abs() < 1e-3);
+/// ```
+pub fn fit_ellipse(points: &[(f32, f32)]) -> Option<Ellipse> {
+    if points.len() < 5 {
+        return None;
+    }
+    let points: Vec<(f64, f64)> = points.iter().map(|p| (p.0 as f64, p.1 as f64)).collect();
+    let (Similarity { scale: s, tx, ty }, points) = normalise(&points)?;
+
+    // Scatter matrices of the quadratic terms [x^2, xy, y^2] and linear terms [x, y, 1]
+    // of the conic a x^2 + b xy + c y^2 + d x + e y + f = 0.
+    let mut s1 = [0f64; 9];
+    let mut s2 = [0f64; 9];
+    let mut s3 = [0f64; 9];
+    for &(x, y) in &points {
+        let quadratic = [x * x, x * y, y * y];
+        let linear = [x, y, 1.0];
+        for i in 0..3 {
+            for j in 0..3 {
+                s1[3 * i + j] += quadratic[i] * quadratic[j];
+                s2[3 * i + j] += quadratic[i] * linear[j];
+                s3[3 * i + j] += linear[i] * linear[j];
+            }
+        }
+    }
+
+    // The linear coefficients minimising the algebraic distance for given quadratic
+    // coefficients q are t * q.
+    let mut t = [0f64; 9];
+    for col in 0..3 {
+        let rhs = [-s2[3 * col], -s2[3 * col + 1], -s2[3 * col + 2]];
+        let x = solve(&s3, &rhs, 3)?;
+        for row in 0..3 {
+            t[3 * row + col] = x[row];
+        }
+    }
+    let mut reduced = s1;
+    for i in 0..3 {
+        for j in 0..3 {
+            reduced[3 * i + j] += (0..3).map(|k| s2[3 * i + k] * t[3 * k + j]).sum::<f64>();
+        }
+    }
+    // Premultiply by the inverse of the constraint matrix for 4ac - b^2.
+    let m = [
+        reduced[6] / 2.0, reduced[7] / 2.0, reduced[8] / 2.0,
+        -reduced[3], -reduced[4], -reduced[5],
+        reduced[0] / 2.0, reduced[1] / 2.0, reduced[2] / 2.0,
+    ];
+
+    // Exactly one eigenvector of m satisfies the ellipse constraint.
+    let trace = m[0] + m[4] + m[8];
+    let minors = m[0] * m[4] - m[1] * m[3] + m[0] * m[8] - m[2] * m[6] + m[4] * m[8] - m[5] * m[7];
+    let det = m[0] * (m[4] * m[8] - m[5] * m[7]) - m[1] * (m[3] * m[8] - m[5] * m[6])
+        + m[2] * (m[3] * m[7] - m[4] * m[6]);
+    let quadratic = real_cubic_roots(-trace, minors, -det)
+        .into_iter()
+        .filter_map(|l| eigenvector3x3(&m, l))
+        .map(|q| (q, 4.0 * q[0] * q[2] - q[1] * q[1]))
+        .filter(|&(_, constraint)| constraint > 0.0)
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))?
+        .0;
+    let linear: Vec<f64> = (0..3).map(|i| (0..3).map(|k| t[3 * i + k] * quadratic[k]).sum()).collect();
+
+    let (a, b, c) = (quadratic[0], quadratic[1], quadratic[2]);
+    let (d, e, f) = (linear[0], linear[1], linear[2]);
+    let denominator = 4.0 * a * c - b * b;
+    let (x0, y0) = ((b * e - 2.0 * c * d) / denominator, (b * d - 2.0 * a * e) / denominator);
+    // Value of the conic at its centre, which must have the opposite sign to a and c.
+    let f0 = f + (d * x0 + e * y0) / 2.0;
+    let (a, b, c, f0) = if f0 > 0.0 { (-a, -b, -c, -f0) } else { (a, b, c, f0) };
+
+    let mean = (a + c) / 2.0;
+    let spread = (((a - c) / 2.0).powi(2) + b * b / 4.0).sqrt();
+    let (semi_major, semi_minor) = ((-f0 / (mean - spread)).sqrt() / s, (-f0 / (mean + spread)).sqrt() / s);
+    if !(semi_minor > 0.0 && semi_major.is_finite()) {
+        return None;
+    }
+    // The major axis is the eigenvector of [a, b / 2; b / 2, c] with the smaller eigenvalue.
+    let mut angle = 0.5 * b.atan2(a - c) + ::std::f64::consts::FRAC_PI_2;
+    if angle > ::std::f64::consts::FRAC_PI_2 {
+        angle -= ::std::f64::consts::PI;
+    }
+    Some(Ellipse {
+        center: (((x0 - tx) / s) as f32, ((y0 - ty) / s) as f32),
+        semi_major: semi_major as f32,
+        semi_minor: semi_minor as f32,
+        angle: angle as f32,
+    })
+}
+
+/// The real roots of x^3 + a x^2 + b x + c.
+fn real_cubic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substitute x = t - a / 3 to get t^3 + p t + q.
+    let shift = a / 3.0;
+    let p = b - a * shift;
+    let q = 2.0 * shift.powi(3) - b * shift + c;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+    if discriminant >= 0.0 {
+        let root = discriminant.sqrt();
+        vec![(-q / 2.0 + root).cbrt() + (-q / 2.0 - root).cbrt() - shift]
+    } else {
+        let r = (-p / 3.0).sqrt();
+        let phi = (-q / (2.0 * r.powi(3))).max(-1.0).min(1.0).acos();
+        (0..3)
+            .map(|k| 2.0 * r * ((phi - 2.0 * ::std::f64::consts::PI * k as f64) / 3.0).cos() - shift)
+            .collect()
+    }
+}
+
+/// An eigenvector of the 3x3 matrix m with eigenvalue l, or None if m - lI has rank
+/// less than two.
+fn eigenvector3x3(m: &[f64; 9], l: f64) -> Option<[f64; 3]> {
+    let rows = [
+        [m[0] - l, m[1], m[2]],
+        [m[3], m[4] - l, m[5]],
+        [m[6], m[7], m[8] - l],
+    ];
+    // The eigenvector is orthogonal to every row, so is parallel to the largest
+    // cross product of a pair of them.
+    let cross3 = |u: [f64; 3], v: [f64; 3]| {
+        [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]]
+    };
+    let norm = |v: &[f64; 3]| v.iter().map(|x| x * x).sum::<f64>();
+    let candidates = [cross3(rows[0], rows[1]), cross3(rows[0], rows[2]), cross3(rows[1], rows[2])];
+    let best = candidates
+        .iter()
+        .cloned()
+        .max_by(|u, v| norm(u).partial_cmp(&norm(v)).unwrap_or(Ordering::Equal))?;
+    if norm(&best) > 0.0 {
+        Some(best)
+    } else {
+        None
+    }
+}
+
+/// A type of transformation fitted by [`fit_transform`](fn.fit_transform.html)
+/// and [`estimate_transform`](fn.estimate_transform.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransformModel {
+    /// An affine transformation, with six degrees of freedom.
+    Affine,
+    /// A projective transformation, or homography, with eight degrees of freedom.
+    Projective,
+}
+
+impl TransformModel {
+    /// The number of point correspondences needed to determine a transformation of this type.
+    pub fn min_correspondences(&self) -> usize {
+        match *self {
+            TransformModel::Affine => 3,
+            TransformModel::Projective => 4,
+        }
+    }
+}
+
+/// A pair of points `(p, q)`, where `p` is a point in one image and `q` is the
+/// corresponding point in another.
+pub type Correspondence = ((f32, f32), (f32, f32));
+
+/// Options for [`estimate_transform`](fn.estimate_transform.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RansacOptions {
+    /// The type of transformation to estimate.
+    pub model: TransformModel,
+    /// Number of random samples of correspondences to fit transformations to.
+    pub max_iterations: usize,
+    /// A correspondence `(p, q)` is an inlier for a transformation `t` if the distance
+    /// from `t * p` to `q` is at most this. Must be non-negative.
+    pub inlier_threshold: f32,
+}
+
+/// A transformation returned by [`estimate_transform`](fn.estimate_transform.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformEstimate {
+    /// The estimated transformation.
+    pub projection: Projection,
+    /// Indices of the correspondences that are inliers for `projection`, in increasing order.
+    pub inliers: Vec<usize>,
+}
+
+/// Returns the transformation of type `model` that best maps the first point of each
+/// correspondence to the second, or `None` if there are too few correspondences or their
+/// points are degenerate, e.g. all collinear.
+///
+/// Affine transformations minimise the sum of squared distances between the mapped and
+/// target points. Projective transformations minimise the algebraic error of the direct
+/// linear transform, after normalising both point sets to have centroid zero and mean
+/// distance `sqrt(2)` from it. Both fit exactly when given exactly
+/// [`min_correspondences`](enum.TransformModel.html#method.min_correspondences) correspondences.
+///
+/// # Examples
+/// ```
+/// use imageproc::geometry::{fit_transform, TransformModel};
+///
+/// // Points scaled by two and then shifted one to the right.
+/// let correspondences = [
+///     ((0.0, 0.0), (1.0, 0.0)),
+///     ((1.0, 0.0), (3.0, 0.0)),
+///     ((0.0, 1.0), (1.0, 2.0)),
+/// ];
+/// let affine = fit_transform(&correspondences, TransformModel::Affine).unwrap();
+///
+/// let (x, y) = affine * (2.0, 2.0);
+/// assert!((x - 5.0).abs() < 1e-4 && (y - 4.0).abs() < 1e-4);
+/// ```
+pub fn fit_transform(correspondences: &[Correspondence], model: TransformModel) -> Option<Projection> {
+    if correspondences.len() < model.min_correspondences() {
+        return None;
+    }
+
+    let from: Vec<(f64, f64)> = correspondences.iter().map(|c| ((c.0).0 as f64, (c.0).1 as f64)).collect();
+    let to: Vec<(f64, f64)> = correspondences.iter().map(|c| ((c.1).0 as f64, (c.1).1 as f64)).collect();
+    let (from_norm, from) = normalise(&from)?;
+    let (to_norm, to) = normalise(&to)?;
+
+    let normalised = match model {
+        TransformModel::Affine => fit_affine(&from, &to)?,
+        TransformModel::Projective => fit_projective(&from, &to)?,
+    };
+
+    // Undo the normalisations, i.e. compute to_norm^-1 * normalised * from_norm.
+    let Similarity { scale: s, tx, ty } = to_norm;
+    let to_norm_inv = [1.0 / s, 0.0, -tx / s, 0.0, 1.0 / s, -ty / s, 0.0, 0.0, 1.0];
+    let Similarity { scale: s, tx, ty } = from_norm;
+    let from_norm = [s, 0.0, tx, 0.0, s, ty, 0.0, 0.0, 1.0];
+    let m = mul3x3_f64(&mul3x3_f64(&to_norm_inv, &normalised), &from_norm);
+
+    if m[8].abs() < 1e-12 {
+        return None;
+    }
+    let mut matrix = [0f32; 9];
+    for (t, v) in matrix.iter_mut().zip(m.iter()) {
+        *t = (v / m[8]) as f32;
+    }
+    Projection::from_matrix(matrix)
+}
+
+/// Estimates the transformation of type `options.model` mapping the first point of each
+/// correspondence to the second using [RANSAC](https://en.wikipedia.org/wiki/Random_sample_consensus),
+/// so that the estimate is not affected by incorrect correspondences.
+///
+/// Transformations are fitted to `options.max_iterations` random samples of the minimal
+/// number of correspondences, and the one with the most inliers is refined by fitting it
+/// to all its inliers using [`fit_transform`](fn.fit_transform.html). The refined transformation
+/// is returned if it has at least as many inliers. The result depends only on the inputs and `seed`.
+///
+/// Returns `Ok(None)` if every sample was degenerate.
+///
+/// Returns `ImageProcError::InvalidOptions` if there are fewer correspondences than
+/// the model requires, `max_iterations` is 0, or `inlier_threshold` is negative or not finite.
+pub fn estimate_transform(
+    correspondences: &[Correspondence],
+    options: RansacOptions,
+    seed: usize,
+) -> Result<Option<TransformEstimate>> {
+    let sample_size = options.model.min_correspondences();
+    let mut errors = vec![];
+    if correspondences.len() < sample_size {
+        errors.push(format!(
+            "need at least {} correspondences, found {}",
+            sample_size,
+            correspondences.len()
+        ));
+    }
+    if options.max_iterations == 0 {
+        errors.push("max_iterations must be non-zero".to_owned());
+    }
+    if !(options.inlier_threshold >= 0.0 && options.inlier_threshold.is_finite()) {
+        errors.push("inlier_threshold must be non-negative and finite".to_owned());
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+
+    let seed_array: &[_] = &[seed];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+    let range = Range::new(0, correspondences.len());
+
+    let mut best: Option<TransformEstimate> = None;
+    let mut indices = Vec::with_capacity(sample_size);
+    for _ in 0..options.max_iterations {
+        indices.clear();
+        while indices.len() < sample_size {
+            let i = range.ind_sample(&mut rng);
+            if !indices.contains(&i) {
+                indices.push(i);
+            }
+        }
+        let sample: Vec<_> = indices.iter().map(|&i| correspondences[i]).collect();
+
+        if let Some(projection) = fit_transform(&sample, options.model) {
+            let inliers = inliers(correspondences, &projection, options.inlier_threshold);
+            if best.as_ref().map_or(true, |b| inliers.len() > b.inliers.len()) {
+                best = Some(TransformEstimate { projection, inliers });
+            }
+        }
+    }
+
+    Ok(best.map(|best| {
+        let consensus: Vec<_> = best.inliers.iter().map(|&i| correspondences[i]).collect();
+        match fit_transform(&consensus, options.model) {
+            Some(projection) => {
+                let inliers = inliers(correspondences, &projection, options.inlier_threshold);
+                if inliers.len() >= best.inliers.len() {
+                    TransformEstimate { projection, inliers }
+                } else {
+                    best
+                }
+            }
+            None => best,
+        }
+    }))
+}
+
+fn inliers(correspondences: &[Correspondence], projection: &Projection, threshold: f32) -> Vec<usize> {
+    correspondences
+        .iter()
+        .enumerate()
+        .filter(|&(_, &(p, q))| {
+            let (x, y) = *projection * p;
+            (x - q.0).hypot(y - q.1) <= threshold
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// The similarity (x, y) -> (scale * x + tx, scale * y + ty).
+#[derive(Copy, Clone, Debug)]
+struct Similarity {
+    scale: f64,
+    tx: f64,
+    ty: f64,
+}
+
+// Returns the similarity mapping points to points with centroid zero and
+// mean distance sqrt(2) from it, and the mapped points.
+// Returns None if all the points are equal.
+fn normalise(points: &[(f64, f64)]) -> Option<(Similarity, Vec<(f64, f64)>)> {
+    let n = points.len() as f64;
+    let cx = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let cy = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let mean_distance = points.iter().map(|p| (p.0 - cx).hypot(p.1 - cy)).sum::<f64>() / n;
+    if mean_distance == 0.0 {
+        return None;
+    }
+    let s = 2f64.sqrt() / mean_distance;
+    let normalised = points.iter().map(|p| (s * (p.0 - cx), s * (p.1 - cy))).collect();
+    Some((Similarity { scale: s, tx: -s * cx, ty: -s * cy }, normalised))
+}
+
+fn fit_affine(from: &[(f64, f64)], to: &[(f64, f64)]) -> Option<[f64; 9]> {
+    // The rows of x' = ax + by + c and y' = dx + ey + f share their coefficients,
+    // so both are solved using the same normal equations.
+    let mut ata = [0f64; 9];
+    let mut atx = [0f64; 3];
+    let mut aty = [0f64; 3];
+    for (p, q) in from.iter().zip(to) {
+        let row = [p.0, p.1, 1.0];
+        for i in 0..3 {
+            for j in 0..3 {
+                ata[3 * i + j] += row[i] * row[j];
+            }
+            atx[i] += row[i] * q.0;
+            aty[i] += row[i] * q.1;
+        }
+    }
+    let x = solve(&ata, &atx, 3)?;
+    let y = solve(&ata, &aty, 3)?;
+    Some([x[0], x[1], x[2], y[0], y[1], y[2], 0.0, 0.0, 1.0])
+}
+
+fn fit_projective(from: &[(f64, f64)], to: &[(f64, f64)]) -> Option<[f64; 9]> {
+    // Fixing the bottom right entry to 1, each correspondence gives two
+    // equations linear in the remaining eight entries.
+    let mut ata = [0f64; 64];
+    let mut atb = [0f64; 8];
+    for (p, q) in from.iter().zip(to) {
+        let (x, y) = *p;
+        let (u, v) = *q;
+        let rows = [
+            ([x, y, 1.0, 0.0, 0.0, 0.0, -x * u, -y * u], u),
+            ([0.0, 0.0, 0.0, x, y, 1.0, -x * v, -y * v], v),
+        ];
+        for &(ref row, b) in &rows {
+            for i in 0..8 {
+                for j in 0..8 {
+                    ata[8 * i + j] += row[i] * row[j];
+                }
+                atb[i] += row[i] * b;
+            }
+        }
+    }
+    let h = solve(&ata, &atb, 8)?;
+    Some([h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7], 1.0])
+}
+
+// Solves the n by n system a * x = b using Gaussian elimination with partial
+// pivoting, or returns None if a is singular.
+fn solve(a: &[f64], b: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut a = a.to_vec();
+    let mut x = b.to_vec();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| {
+            a[i * n + col].abs().partial_cmp(&a[j * n + col].abs()).unwrap_or(Ordering::Equal)
+        })?;
+        let pivot_value = a[pivot * n + col].abs();
+        if pivot_value < 1e-9 || pivot_value.is_nan() {
+            return None;
+        }
+        for k in 0..n {
+            a.swap(col * n + k, pivot * n + k);
+        }
+        x.swap(col, pivot);
+
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for k in col..n {
+                a[row * n + k] -= factor * a[col * n + k];
+            }
+            x[row] -= factor * x[col];
+        }
+    }
+    for col in (0..n).rev() {
+        let sum: f64 = (col + 1..n).map(|k| a[col * n + k] * x[k]).sum();
+        x[col] = (x[col] - sum) / a[col * n + col];
+    }
+    Some(x)
+}
+
+fn mul3x3_f64(a: &[f64; 9], b: &[f64; 9]) -> [f64; 9] {
+    let mut c = [0f64; 9];
+    for row in 0..3 {
+        for col in 0..3 {
+            c[3 * row + col] = (0..3).map(|k| a[3 * row + k] * b[3 * k + col]).sum();
+        }
+    }
+    c
+}
+
+/// Twice the signed area of the triangle (a, b, p). Positive if the path from a
+/// through b to p turns clockwise as displayed.
+fn orientation(a: (f32, f32), b: (f32, f32), p: (f32, f32)) -> f32 {
+    cross(sub(b, a), sub(p, a))
+}
+
+/// The edges of a polygon, including the implicit closing edge.
+fn edges<'a>(polygon: &'a [(f32, f32)]) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + 'a {
+    let n = polygon.len();
+    (0..n).map(move |i| (polygon[i], polygon[(i + 1) % n]))
+}
+
+/// Whether p lies in the axis-aligned bounding box of a and b.
+fn in_bounding_box(a: (f32, f32), b: (f32, f32), p: (f32, f32)) -> bool {
+    a.0.min(b.0) <= p.0 && p.0 <= a.0.max(b.0) && a.1.min(b.1) <= p.1 && p.1 <= a.1.max(b.1)
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn dot(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn cross(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn homography() -> Projection {
+        Projection::from_matrix([
+            1.2, 0.1, 5.0,
+            -0.2, 0.9, 3.0,
+            0.001, 0.002, 1.0,
+        ]).unwrap()
+    }
+
+    fn assert_maps_close(actual: &Projection, expected: &Projection, points: &[(f32, f32)]) {
+        for &p in points {
+            let (a, e) = (*actual * p, *expected * p);
+            assert!((a.0 - e.0).abs() < 1e-2 && (a.1 - e.1).abs() < 1e-2, "{:?}: {:?} != {:?}", p, a, e);
+        }
+    }
+
+    #[test]
+    fn test_fit_transform_exact() {
+        let h = homography();
+        let points = [(0.0, 0.0), (100.0, 0.0), (0.0, 80.0), (90.0, 110.0)];
+        let correspondences: Vec<_> = points.iter().map(|&p| (p, h * p)).collect();
+
+        let fitted = fit_transform(&correspondences, TransformModel::Projective).unwrap();
+        assert_maps_close(&fitted, &h, &[(50.0, 50.0), (-20.0, 130.0)]);
+
+        let affine = Projection::translate(3.0, -7.0) * Projection::rotate(0.4) * Projection::scale(1.5, 0.8);
+        let correspondences: Vec<_> = points[..3].iter().map(|&p| (p, affine * p)).collect();
+        let fitted = fit_transform(&correspondences, TransformModel::Affine).unwrap();
+        assert_maps_close(&fitted, &affine, &[(50.0, 50.0), (-20.0, 130.0)]);
+    }
+
+    #[test]
+    fn test_fit_transform_degenerate() {
+        let collinear = [((0.0, 0.0), (1.0, 1.0)), ((1.0, 1.0), (2.0, 5.0)), ((2.0, 2.0), (4.0, 0.0))];
+        assert_eq!(fit_transform(&collinear, TransformModel::Affine), None);
+        assert_eq!(fit_transform(&collinear[..2], TransformModel::Affine), None);
+
+        let repeated = [((1.0, 2.0), (3.0, 4.0)); 5];
+        assert_eq!(fit_transform(&repeated, TransformModel::Projective), None);
+    }
+
+    #[test]
+    fn test_estimate_transform_ignores_outliers() {
+        let h = homography();
+        let mut correspondences = vec![];
+        for y in 0..4 {
+            for x in 0..5 {
+                let p = (x as f32 * 30.0, y as f32 * 40.0);
+                correspondences.push((p, h * p));
+            }
+        }
+        let outliers = [(10.0, 10.0), (50.0, 70.0), (100.0, 20.0), (80.0, 90.0), (20.0, 110.0)];
+        for (i, &p) in outliers.iter().enumerate() {
+            let (x, y) = h * p;
+            correspondences.push((p, (x + 20.0 + 5.0 * i as f32, y - 15.0)));
+        }
+
+        let options = RansacOptions {
+            model: TransformModel::Projective,
+            max_iterations: 100,
+            inlier_threshold: 1.0,
+        };
+        let estimate = estimate_transform(&correspondences, options, 7).unwrap().unwrap();
+        assert_eq!(estimate.inliers, (0..20).collect::<Vec<_>>());
+        assert_maps_close(&estimate.projection, &h, &[(60.0, 60.0), (150.0, 0.0)]);
+
+        // The result depends only on the inputs and seed.
+        assert_eq!(estimate_transform(&correspondences, options, 7).unwrap().unwrap(), estimate);
+    }
+
+    #[test]
+    fn test_estimate_transform_invalid_options() {
+        let options = RansacOptions {
+            model: TransformModel::Affine,
+            max_iterations: 0,
+            inlier_threshold: -1.0,
+        };
+        let correspondences = [((0.0, 0.0), (0.0, 0.0)), ((1.0, 0.0), (1.0, 0.0))];
+        assert_eq!(
+            estimate_transform(&correspondences, options, 0),
+            Err(ImageProcError::InvalidOptions(
+                "need at least 3 correspondences, found 2, \
+                 max_iterations must be non-zero, \
+                 inlier_threshold must be non-negative and finite"
+                    .to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_estimate_transform_degenerate_samples() {
+        let options = RansacOptions {
+            model: TransformModel::Affine,
+            max_iterations: 10,
+            inlier_threshold: 1.0,
+        };
+        let collinear: Vec<_> = (0..5).map(|i| ((i as f32, i as f32), (0.0, i as f32))).collect();
+        assert_eq!(estimate_transform(&collinear, options, 0), Ok(None));
+    }
+
+    #[test]
+    fn test_polygon_orientation_and_degenerate() {
+        let clockwise = Polygon::new(vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)]);
+        let anticlockwise = Polygon::new(clockwise.vertices().iter().rev().cloned().collect());
+        assert_eq!(clockwise.signed_area(), 2.0);
+        assert_eq!(anticlockwise.signed_area(), -2.0);
+        assert_eq!(anticlockwise.area(), 2.0);
+        assert_eq!(clockwise.centroid(), Some((1.0, 0.5)));
+        assert_eq!(anticlockwise.centroid(), Some((1.0, 0.5)));
+        assert_eq!(clockwise.perimeter(), 6.0);
+
+        let empty = Polygon::new(vec![]);
+        assert_eq!(empty.signed_area(), 0.0);
+        assert_eq!(empty.centroid(), None);
+        assert_eq!(empty.bounding_rect(), None);
+        let line = Polygon::new(vec![(0.0, 0.0), (3.0, 0.0)]);
+        assert_eq!(line.perimeter(), 6.0);
+        assert_eq!(line.bounding_rect(), None);
+    }
+
+    #[test]
+    fn test_polygon_clip_convex() {
+        let square = Polygon::from(RectF::new(0.0, 0.0, 2.0, 2.0));
+        let shifted = Polygon::from(RectF::new(1.0, 1.0, 2.0, 2.0));
+        let reversed = Polygon::new(shifted.vertices().iter().rev().cloned().collect());
+
+        assert_eq!(square.clip_convex(&shifted).area(), 1.0);
+        assert_eq!(square.clip_convex(&reversed).area(), 1.0);
+        assert_eq!(square.clip_convex(&shifted).bounding_rect(), Some(RectF::new(1.0, 1.0, 1.0, 1.0)));
+
+        // Disjoint.
+        assert!(square.clip_to_rect(RectF::new(5.0, 5.0, 1.0, 1.0)).is_empty());
+        // Degenerate clip polygon.
+        assert!(square.clip_convex(&Polygon::new(vec![(0.0, 0.0), (1.0, 1.0)])).is_empty());
+    }
+
+    #[test]
+    fn test_polygon_clip_non_convex_subject() {
+        // An L shape, clipped to its lower half.
+        let l_shape = Polygon::new(vec![
+            (0.0, 0.0), (1.0, 0.0), (1.0, 2.0), (3.0, 2.0), (3.0, 3.0), (0.0, 3.0),
+        ]);
+        assert_eq!(l_shape.area(), 5.0);
+        let clipped = l_shape.clip_to_rect(RectF::new(0.0, 1.5, 3.0, 1.5));
+        assert_eq!(clipped.area(), 3.5);
+        assert!(clipped.contains((2.0, 2.5), FillRule::NonZero));
+        assert!(!clipped.contains((2.0, 1.75), FillRule::NonZero));
+    }
+
+    #[test]
+    fn test_closest_point() {
+        let segment = LineSegment::new((1.0, 1.0), (5.0, 1.0));
+        assert_eq!(segment.closest_point((3.0, -2.0)), (3.0, 1.0));
+        assert_eq!(segment.closest_point((0.0, 0.0)), (1.0, 1.0));
+        assert_eq!(segment.closest_point((9.0, 3.0)), (5.0, 1.0));
+
+        let point = LineSegment::new((2.0, 2.0), (2.0, 2.0));
+        assert_eq!(point.closest_point((5.0, 6.0)), (2.0, 2.0));
+        assert_eq!(point.distance_to_point((5.0, 6.0)), 5.0);
+        assert_eq!(point.length(), 0.0);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = LineSegment::new((0.0, 0.0), (2.0, 0.0));
+        // Touching at an endpoint.
+        let b = LineSegment::new((2.0, 0.0), (3.0, 5.0));
+        assert_eq!(a.intersection(&b), Some((2.0, 0.0)));
+        assert!(a.intersects(&b));
+        // Would intersect if extended.
+        let c = LineSegment::new((3.0, -1.0), (3.0, 1.0));
+        assert_eq!(a.intersection(&c), None);
+        assert!(!a.intersects(&c));
+        // Parallel.
+        let d = LineSegment::new((0.0, 1.0), (2.0, 1.0));
+        assert_eq!(a.intersection(&d), None);
+        assert!(!a.intersects(&d));
+    }
+
+    #[test]
+    fn test_intersects_collinear() {
+        let a = LineSegment::new((0.0, 0.0), (2.0, 2.0));
+        assert!(a.intersects(&LineSegment::new((1.0, 1.0), (3.0, 3.0))));
+        assert!(a.intersects(&LineSegment::new((2.0, 2.0), (3.0, 3.0))));
+        assert!(!a.intersects(&LineSegment::new((2.5, 2.5), (3.0, 3.0))));
+        assert_eq!(a.intersection(&LineSegment::new((1.0, 1.0), (3.0, 3.0))), None);
+    }
+
+    #[test]
+    fn test_winding_number_orientation() {
+        let clockwise = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let anticlockwise = [(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 0.0)];
+        assert_eq!(winding_number((1.0, 1.0), &clockwise), 1);
+        assert_eq!(winding_number((1.0, 1.0), &anticlockwise), -1);
+        assert_eq!(winding_number((3.0, 1.0), &clockwise), 0);
+    }
+
+    #[test]
+    fn test_point_in_polygon_boundary() {
+        let square = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        for &rule in &[FillRule::EvenOdd, FillRule::NonZero] {
+            assert!(point_in_polygon((0.0, 0.0), &square, rule));
+            assert!(point_in_polygon((0.0, 1.0), &square, rule));
+            assert!(!point_in_polygon((2.0, 1.0), &square, rule));
+            assert!(!point_in_polygon((1.0, 2.0), &square, rule));
+            assert!(!point_in_polygon((1.0, 1.0), &[], rule));
+        }
+    }
+
+    #[test]
+    fn test_point_in_polygon_fill_rules() {
+        // Two overlapping squares traced in the same direction.
+        let doubled = [
+            (0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0),
+            (0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0),
+        ];
+        assert_eq!(winding_number((2.0, 2.0), &doubled), 2);
+        assert!(point_in_polygon((2.0, 2.0), &doubled, FillRule::NonZero));
+        assert!(!point_in_polygon((2.0, 2.0), &doubled, FillRule::EvenOdd));
+    }
+
+    #[test]
+    fn test_convex_hull() {
+        let points = [
+            (1.0, 1.0), (0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0),
+            (1.0, 0.0), (2.0, 2.0), (0.5, 1.5),
+        ];
+        let hull = convex_hull(&points);
+        assert_eq!(hull.vertices(), &[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+        assert_eq!(hull.signed_area(), 4.0);
+
+        let collinear = [(2.0, 2.0), (0.0, 0.0), (1.0, 1.0), (3.0, 3.0)];
+        assert_eq!(convex_hull(&collinear).vertices(), &[(0.0, 0.0), (3.0, 3.0)]);
+        assert_eq!(convex_hull(&[(1.0, 2.0), (1.0, 2.0)]).vertices(), &[(1.0, 2.0)]);
+        assert!(convex_hull(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_min_area_rect_recovers_rotated_rect() {
+        let rect = RotatedRect { center: (5.0, 5.0), width: 4.0, height: 2.0, angle: 0.3 };
+        let mut points = rect.corners().to_vec();
+        points.push((5.0, 5.0));
+        points.push((6.0, 5.5));
+        let found = min_area_rect(&points).unwrap();
+        assert!((found.area() - 8.0).abs() < 1e-4);
+        assert!((found.angle - 0.3).abs() < 1e-4);
+        assert!((found.width - 4.0).abs() < 1e-4 && (found.height - 2.0).abs() < 1e-4);
+        assert!((found.center.0 - 5.0).abs() < 1e-4 && (found.center.1 - 5.0).abs() < 1e-4);
+        assert!(Polygon::from(found).signed_area() > 0.0);
+    }
+
+    #[test]
+    fn test_min_area_rect_degenerate() {
+        assert_eq!(min_area_rect(&[]), None);
+        assert_eq!(
+            min_area_rect(&[(3.0, 4.0)]),
+            Some(RotatedRect { center: (3.0, 4.0), width: 0.0, height: 0.0, angle: 0.0 })
+        );
+        let line = min_area_rect(&[(0.0, 1.0), (4.0, 1.0), (2.0, 1.0)]).unwrap();
+        assert_eq!((line.center, line.width, line.height, line.angle), ((2.0, 1.0), 4.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_min_enclosing_circle() {
+        // The circle is determined by three points of an acute triangle.
+        let points = [(0.0, 0.0), (4.0, 0.0), (2.0, 3.0), (2.0, 1.0), (1.0, 0.5)];
+        let circle = min_enclosing_circle(&points).unwrap();
+        assert!((circle.center.0 - 2.0).abs() < 1e-5);
+        assert!((circle.center.1 - 5.0 / 6.0).abs() < 1e-5);
+        assert!((circle.radius - 13.0 / 6.0).abs() < 1e-5);
+
+        // For an obtuse triangle it is determined by the longest side.
+        let circle = min_enclosing_circle(&[(0.0, 0.0), (4.0, 0.0), (2.0, 1.0)]).unwrap();
+        assert_eq!(circle, Circle { center: (2.0, 0.0), radius: 2.0 });
+        assert!(circle.contains((2.0, 1.0)));
+
+        assert_eq!(min_enclosing_circle(&[]), None);
+        assert_eq!(
+            min_enclosing_circle(&[(1.0, 1.0)]),
+            Some(Circle { center: (1.0, 1.0), radius: 0.0 })
+        );
+    }
+
+    fn ellipse_points(ellipse: Ellipse, start: f32, end: f32, count: usize) -> Vec<(f32, f32)> {
+        let (sin, cos) = ellipse.angle.sin_cos();
+        (0..count)
+            .map(|i| start + (end - start) * i as f32 / count as f32)
+            .map(|t| (ellipse.semi_major * t.cos(), ellipse.semi_minor * t.sin()))
+            .map(|(u, v)| (ellipse.center.0 + u * cos - v * sin, ellipse.center.1 + u * sin + v * cos))
+            .collect()
+    }
+
+    fn assert_ellipses_close(actual: Ellipse, expected: Ellipse, tolerance: f32) {
+        let close = (actual.center.0 - expected.center.0).abs() < tolerance
+            && (actual.center.1 - expected.center.1).abs() < tolerance
+            && (actual.semi_major - expected.semi_major).abs() < tolerance
+            && (actual.semi_minor - expected.semi_minor).abs() < tolerance
+            && (actual.angle - expected.angle).abs() < tolerance;
+        assert!(close, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn test_fit_ellipse_rotated() {
+        for &angle in &[-1.2, -0.3, 0.0, 0.7, 1.5] {
+            let expected = Ellipse { center: (40.0, 25.0), semi_major: 12.0, semi_minor: 5.0, angle };
+            let points = ellipse_points(expected, 0.0, 2.0 * ::std::f32::consts::PI, 30);
+            assert_ellipses_close(fit_ellipse(&points).unwrap(), expected, 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_fit_ellipse_partial_arc() {
+        let expected = Ellipse { center: (-3.0, 8.0), semi_major: 6.0, semi_minor: 4.0, angle: 0.4 };
+        let points = ellipse_points(expected, 1.0, 3.0, 8);
+        assert_ellipses_close(fit_ellipse(&points).unwrap(), expected, 1e-2);
+    }
+
+    #[test]
+    fn test_fit_ellipse_noisy_points() {
+        let expected = Ellipse { center: (20.0, 10.0), semi_major: 8.0, semi_minor: 3.0, angle: 0.0 };
+        let mut points = ellipse_points(expected, 0.0, 2.0 * ::std::f32::consts::PI, 40);
+        for (i, p) in points.iter_mut().enumerate() {
+            p.0 += if i % 2 == 0 { 0.3 } else { -0.3 };
+        }
+        assert_ellipses_close(fit_ellipse(&points).unwrap(), expected, 0.1);
+    }
+
+    #[test]
+    fn test_fit_ellipse_degenerate() {
+        let square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        assert_eq!(fit_ellipse(&square), None);
+        let line: Vec<_> = (0..10).map(|i| (i as f32, 2.0 * i as f32)).collect();
+        assert_eq!(fit_ellipse(&line), None);
+        assert_eq!(fit_ellipse(&[(1.0, 1.0); 6]), None);
+    }
+}
@@ -0,0 +1,666 @@
+//! [GrabCut] interactive foreground segmentation.
+//!
+//! The user marks a rectangle around an object, or scribbles over parts of the foreground
+//! and background, and the remaining pixels are labelled by alternately fitting Gaussian
+//! mixture models to the colors of the foreground and background and finding the
+//! segmentation that best fits these models using a minimum graph cut. The cut also
+//! penalises boundaries between pixels of similar color, so segments follow image edges.
+//!
+//! Segmentations are given by masks using the values [`BACKGROUND`](constant.BACKGROUND.html),
+//! [`PROBABLE_BACKGROUND`](constant.PROBABLE_BACKGROUND.html),
+//! [`PROBABLE_FOREGROUND`](constant.PROBABLE_FOREGROUND.html) and
+//! [`FOREGROUND`](constant.FOREGROUND.html). Definite labels are fixed by the user, and
+//! probable labels are updated by [`grabcut`](fn.grabcut.html).
+//!
+//! [GrabCut]: https://doi.org/10.1145/1015706.1015720
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use bag_of_words::{build_vocabulary, Initialization, KMeansOptions};
+use error::{check_dimensions, check_non_empty, ImageProcError, Result};
+use rect::Rect;
+use region_labelling::Connectivity;
+use std::collections::VecDeque;
+use std::f64;
+use std::usize;
+
+/// Mask value for pixels that are known to belong to the background.
+pub const BACKGROUND: u8 = 0;
+
+/// Mask value for pixels that are currently labelled as background, but may change.
+pub const PROBABLE_BACKGROUND: u8 = 64;
+
+/// Mask value for pixels that are currently labelled as foreground, but may change.
+pub const PROBABLE_FOREGROUND: u8 = 192;
+
+/// Mask value for pixels that are known to belong to the foreground.
+pub const FOREGROUND: u8 = 255;
+
+/// Options for [`grabcut`](fn.grabcut.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GrabCutOptions {
+    /// Number of rounds of fitting color models and cutting the graph. Must be at least 1.
+    pub iterations: u32,
+    /// Number of Gaussians in the color model of each of the foreground and background.
+    /// Must be at least 1.
+    pub components: usize,
+    /// Weight of the penalty for neighbouring pixels of similar color having different
+    /// labels, relative to the fit of each pixel to the color models. Larger values give
+    /// smoother segmentations. Must not be negative.
+    pub smoothness: f32,
+    /// Which pixels are neighbours.
+    pub connectivity: Connectivity,
+}
+
+impl Default for GrabCutOptions {
+    fn default() -> Self {
+        GrabCutOptions {
+            iterations: 5,
+            components: 5,
+            smoothness: 50.0,
+            connectivity: Connectivity::Eight,
+        }
+    }
+}
+
+impl GrabCutOptions {
+    /// Returns an error describing every invalid option.
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.iterations == 0 {
+            errors.push("iterations must be at least 1");
+        }
+        if self.components == 0 {
+            errors.push("components must be at least 1");
+        }
+        if self.smoothness < 0.0 || self.smoothness.is_nan() {
+            errors.push("smoothness must not be negative");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Segments the object inside `rect` from the rest of `image`, and returns the resulting
+/// mask.
+///
+/// Pixels outside `rect` are labelled [`BACKGROUND`](constant.BACKGROUND.html), and pixels
+/// inside it are initially labelled [`PROBABLE_FOREGROUND`](constant.PROBABLE_FOREGROUND.html)
+/// and then refined by [`grabcut`](fn.grabcut.html). The mask can be edited and passed
+/// to `grabcut` again to correct any mistakes.
+///
+/// Returns `ImageProcError::EmptyImage` if `image` is empty, `ImageProcError::OutOfBounds`
+/// if `rect` does not overlap `image`, and `ImageProcError::InvalidOptions` if `rect`
+/// covers the whole image or any option is invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{Rgb, RgbImage};
+/// use imageproc::grabcut::{grabcut_rect, GrabCutOptions, PROBABLE_BACKGROUND, PROBABLE_FOREGROUND};
+/// use imageproc::rect::Rect;
+///
+/// // A red square on a mottled green background.
+/// let image = RgbImage::from_fn(30, 30, |x, y| {
+///     if x >= 10 && x < 20 && y >= 10 && y < 20 {
+///         Rgb([200, 30, 30])
+///     } else {
+///         Rgb([30, 150 + ((x * 7 + y * 3) % 40) as u8, 40])
+///     }
+/// });
+///
+/// // The rect loosely surrounds the square.
+/// let mask = grabcut_rect(&image, Rect::at(6, 5).of_size(18, 20), GrabCutOptions::default()).unwrap();
+///
+/// assert_eq!(mask.get_pixel(15, 15)[0], PROBABLE_FOREGROUND);
+/// assert_eq!(mask.get_pixel(8, 8)[0], PROBABLE_BACKGROUND);
+/// # }
+/// ```
+pub fn grabcut_rect(image: &RgbImage, rect: Rect, options: GrabCutOptions) -> Result<GrayImage> {
+    check_non_empty(image.dimensions())?;
+    let (width, height) = image.dimensions();
+    let inside = rect.clamp_to_image(width, height).ok_or(ImageProcError::OutOfBounds {
+        region: rect,
+        image: (width, height),
+    })?;
+    let mut mask = GrayImage::new(width, height);
+    for (x, y) in inside.points() {
+        mask.put_pixel(x as u32, y as u32, Luma([PROBABLE_FOREGROUND]));
+    }
+    grabcut(image, &mut mask, options)?;
+    Ok(mask)
+}
+
+/// Updates the probable labels in `mask` to segment the foreground of `image`.
+///
+/// Pixels labelled [`FOREGROUND`](constant.FOREGROUND.html) or
+/// [`BACKGROUND`](constant.BACKGROUND.html) keep their labels, and are used along with the
+/// current probable labels to fit the initial color models. Each iteration assigns every
+/// pixel to a Gaussian in the model of its current label, refits the models, and then
+/// relabels each probable pixel as [`PROBABLE_FOREGROUND`](constant.PROBABLE_FOREGROUND.html)
+/// or [`PROBABLE_BACKGROUND`](constant.PROBABLE_BACKGROUND.html) using the minimum cut of a
+/// graph whose edges penalise poor fits to the models and label changes between similarly
+/// colored neighbours. The result depends only on the inputs.
+///
+/// For segmentation from scribbles, label the scribbled pixels with definite values and
+/// the rest of the image with probable values guessed from the scribbles.
+///
+/// Returns `ImageProcError::EmptyImage` if `image` is empty,
+/// `ImageProcError::DimensionMismatch` if `mask` and `image` have different dimensions,
+/// and `ImageProcError::InvalidOptions` if any option is invalid, `mask` contains any
+/// other values, or `mask` does not label at least one pixel as each of foreground and
+/// background.
+pub fn grabcut(image: &RgbImage, mask: &mut GrayImage, options: GrabCutOptions) -> Result<()> {
+    check_non_empty(image.dimensions())?;
+    check_dimensions(image.dimensions(), mask.dimensions())?;
+    options.validate()?;
+    if mask.iter().any(|&v| v != BACKGROUND && v != PROBABLE_BACKGROUND && v != PROBABLE_FOREGROUND && v != FOREGROUND) {
+        return Err(ImageProcError::InvalidOptions(
+            "mask values must be BACKGROUND, PROBABLE_BACKGROUND, PROBABLE_FOREGROUND or FOREGROUND".into(),
+        ));
+    }
+    let is_foreground = |v: u8| v == FOREGROUND || v == PROBABLE_FOREGROUND;
+    if mask.iter().all(|&v| is_foreground(v)) || !mask.iter().any(|&v| is_foreground(v)) {
+        return Err(ImageProcError::InvalidOptions(
+            "mask must label at least one pixel as each of foreground and background".into(),
+        ));
+    }
+    if mask.iter().all(|&v| v == FOREGROUND || v == BACKGROUND) {
+        return Ok(());
+    }
+
+    let (width, height) = image.dimensions();
+    let colors: Vec<[f64; 3]> = image.pixels().map(|p| to_f64(*p)).collect();
+    let neighbours = Neighbours::new(image, options);
+    let n = colors.len();
+    let (source, sink) = (n, n + 1);
+    // Exceeds the total weight of the edges between any pixel and its neighbours, each
+    // of which is at most the smoothness.
+    let fixed = 1.0 + options.smoothness as f64 * 2.0 * neighbours.offsets.len() as f64;
+
+    let (foreground, background) = split_by_label(&colors, mask);
+    let mut foreground_model = Gmm::initialize(&foreground, options.components)?;
+    let mut background_model = Gmm::initialize(&background, options.components)?;
+
+    for _ in 0..options.iterations {
+        let (foreground, background) = split_by_label(&colors, mask);
+        foreground_model = foreground_model.refit(&foreground);
+        background_model = background_model.refit(&background);
+
+        let mut graph = FlowGraph::new(n + 2);
+        for (i, (color, &label)) in colors.iter().zip(mask.iter()).enumerate() {
+            // The source side of the cut is the foreground. Only the difference between
+            // the costs of each label matters, so the smaller cost is subtracted from both.
+            let (to_source, to_sink) = match label {
+                FOREGROUND => (fixed, 0.0),
+                BACKGROUND => (0.0, fixed),
+                _ => {
+                    let foreground_cost = -foreground_model.log_likelihood(color);
+                    let background_cost = -background_model.log_likelihood(color);
+                    ((background_cost - foreground_cost).max(0.0), (foreground_cost - background_cost).max(0.0))
+                }
+            };
+            if to_source > 0.0 {
+                graph.add_edges(source, i, to_source, 0.0);
+            }
+            if to_sink > 0.0 {
+                graph.add_edges(i, sink, to_sink, 0.0);
+            }
+        }
+        neighbours.add_edges(&mut graph, width, height);
+
+        let source_side = graph.min_cut(source, sink);
+        for (i, label) in mask.iter_mut().enumerate() {
+            if *label == PROBABLE_FOREGROUND || *label == PROBABLE_BACKGROUND {
+                *label = if source_side[i] { PROBABLE_FOREGROUND } else { PROBABLE_BACKGROUND };
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the colors of the pixels labelled as foreground and as background.
+fn split_by_label(colors: &[[f64; 3]], mask: &GrayImage) -> (Vec<[f64; 3]>, Vec<[f64; 3]>) {
+    let mut foreground = vec![];
+    let mut background = vec![];
+    for (color, &label) in colors.iter().zip(mask.iter()) {
+        if label == FOREGROUND || label == PROBABLE_FOREGROUND {
+            foreground.push(*color);
+        } else {
+            background.push(*color);
+        }
+    }
+    (foreground, background)
+}
+
+fn to_f64(color: Rgb<u8>) -> [f64; 3] {
+    [color[0] as f64, color[1] as f64, color[2] as f64]
+}
+
+/// The weights of the edges between neighbouring pixels, which do not change between
+/// iterations.
+struct Neighbours {
+    /// Half of the neighbour offsets, so that each pair of neighbours appears once.
+    offsets: Vec<(i32, i32)>,
+    /// The weight of the edge from each pixel to its neighbour at each offset, in
+    /// row-major order of pixels.
+    weights: Vec<f64>,
+}
+
+impl Neighbours {
+    fn new(image: &RgbImage, options: GrabCutOptions) -> Neighbours {
+        let offsets = match options.connectivity {
+            Connectivity::Four => vec![(1, 0), (0, 1)],
+            Connectivity::Eight => vec![(1, 0), (-1, 1), (0, 1), (1, 1)],
+        };
+        let (width, height) = image.dimensions();
+        let mut squared_distances = Vec::with_capacity(offsets.len() * (width * height) as usize);
+        for (x, y, p) in image.enumerate_pixels() {
+            for &(dx, dy) in &offsets {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                squared_distances.push(if nx >= 0 && nx < width as i32 && ny < height as i32 {
+                    let (a, b) = (to_f64(*p), to_f64(*image.get_pixel(nx as u32, ny as u32)));
+                    Some((0..3).map(|c| (a[c] - b[c]) * (a[c] - b[c])).sum::<f64>())
+                } else {
+                    None
+                });
+            }
+        }
+
+        // Scale color differences by their mean over the image, as in the GrabCut paper.
+        let (sum, count) = squared_distances
+            .iter()
+            .filter_map(|&d| d)
+            .fold((0.0, 0), |(sum, count), d| (sum + d, count + 1));
+        let beta = if sum > 0.0 { count as f64 / (2.0 * sum) } else { 0.0 };
+        let smoothness = options.smoothness as f64;
+        let lengths: Vec<f64> = offsets.iter().map(|&(dx, dy)| ((dx * dx + dy * dy) as f64).sqrt()).collect();
+        let weights = squared_distances
+            .iter()
+            .enumerate()
+            .map(|(i, d)| match *d {
+                Some(d) => smoothness * (-beta * d).exp() / lengths[i % offsets.len()],
+                None => 0.0,
+            })
+            .collect();
+        Neighbours { offsets, weights }
+    }
+
+    fn add_edges(&self, graph: &mut FlowGraph, width: u32, height: u32) {
+        let k = self.offsets.len();
+        for y in 0..height {
+            for x in 0..width {
+                let i = (y * width + x) as usize;
+                for (j, &(dx, dy)) in self.offsets.iter().enumerate() {
+                    let weight = self.weights[i * k + j];
+                    if weight > 0.0 {
+                        let neighbour = ((y as i32 + dy) * width as i32 + x as i32 + dx) as usize;
+                        graph.add_edges(i, neighbour, weight, weight);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A Gaussian mixture model of colors.
+struct Gmm {
+    components: Vec<Gaussian>,
+}
+
+struct Gaussian {
+    mean: [f64; 3],
+    /// Inverse of the covariance matrix, in row-major order.
+    inverse: [f64; 9],
+    /// Log of the component's weight divided by the square root of the determinant
+    /// of its covariance. The constant factor of the normal density is omitted.
+    log_scale: f64,
+}
+
+impl Gaussian {
+    fn log_density(&self, color: &[f64; 3]) -> f64 {
+        let d = [color[0] - self.mean[0], color[1] - self.mean[1], color[2] - self.mean[2]];
+        let mut mahalanobis = 0.0;
+        for i in 0..3 {
+            for j in 0..3 {
+                mahalanobis += d[i] * self.inverse[3 * i + j] * d[j];
+            }
+        }
+        self.log_scale - 0.5 * mahalanobis
+    }
+}
+
+impl Gmm {
+    /// Fits a model to colors clustered by k-means.
+    fn initialize(colors: &[[f64; 3]], components: usize) -> Result<Gmm> {
+        let points: Vec<[f32; 3]> = colors.iter().map(|c| [c[0] as f32, c[1] as f32, c[2] as f32]).collect();
+        let options = KMeansOptions {
+            num_words: components.min(points.len()),
+            max_iterations: 10,
+            initialization: Initialization::PlusPlus,
+            batch_size: None,
+        };
+        let vocabulary = build_vocabulary(&points, options, 0)?;
+        let assignments: Vec<usize> = points.iter().map(|p| vocabulary.nearest_word(p).0).collect();
+        Ok(Gmm::fit(colors, &assignments, vocabulary.num_words()))
+    }
+
+    /// Assigns each color to its most likely component, and fits a new model to the result.
+    fn refit(&self, colors: &[[f64; 3]]) -> Gmm {
+        let assignments: Vec<usize> = colors
+            .iter()
+            .map(|color| {
+                let densities = self.components.iter().map(|g| g.log_density(color));
+                densities
+                    .enumerate()
+                    .fold((0, f64::NEG_INFINITY), |best, (i, d)| if d > best.1 { (i, d) } else { best })
+                    .0
+            })
+            .collect();
+        Gmm::fit(colors, &assignments, self.components.len())
+    }
+
+    /// Fits a Gaussian to the colors assigned to each of `k` components. Components to
+    /// which no colors are assigned are dropped.
+    fn fit(colors: &[[f64; 3]], assignments: &[usize], k: usize) -> Gmm {
+        let mut counts = vec![0usize; k];
+        let mut sums = vec![[0f64; 3]; k];
+        let mut products = vec![[0f64; 9]; k];
+        for (color, &a) in colors.iter().zip(assignments) {
+            counts[a] += 1;
+            for i in 0..3 {
+                sums[a][i] += color[i];
+                for j in 0..3 {
+                    products[a][3 * i + j] += color[i] * color[j];
+                }
+            }
+        }
+
+        let total = colors.len() as f64;
+        let mut components = Vec::with_capacity(k);
+        for a in 0..k {
+            if counts[a] == 0 {
+                continue;
+            }
+            let count = counts[a] as f64;
+            let mean = [sums[a][0] / count, sums[a][1] / count, sums[a][2] / count];
+            let mut covariance = [0f64; 9];
+            for i in 0..3 {
+                for j in 0..3 {
+                    covariance[3 * i + j] = products[a][3 * i + j] / count - mean[i] * mean[j];
+                }
+            }
+            let mut det = determinant(&covariance);
+            if det <= f64::EPSILON {
+                // Regularise components of a single color, or of colors in a plane.
+                for i in 0..3 {
+                    covariance[4 * i] += 0.01;
+                }
+                det = determinant(&covariance);
+            }
+            components.push(Gaussian {
+                mean,
+                inverse: inverse(&covariance, det),
+                log_scale: (count / total).ln() - 0.5 * det.ln(),
+            });
+        }
+        Gmm { components }
+    }
+
+    /// The log of the (unnormalised) density of the model at `color`.
+    fn log_likelihood(&self, color: &[f64; 3]) -> f64 {
+        let densities: Vec<f64> = self.components.iter().map(|g| g.log_density(color)).collect();
+        let max = densities.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        max + densities.iter().map(|d| (d - max).exp()).sum::<f64>().ln()
+    }
+}
+
+fn determinant(m: &[f64; 9]) -> f64 {
+    m[0] * (m[4] * m[8] - m[5] * m[7]) - m[1] * (m[3] * m[8] - m[5] * m[6]) + m[2] * (m[3] * m[7] - m[4] * m[6])
+}
+
+fn inverse(m: &[f64; 9], det: f64) -> [f64; 9] {
+    [
+        (m[4] * m[8] - m[5] * m[7]) / det,
+        (m[2] * m[7] - m[1] * m[8]) / det,
+        (m[1] * m[5] - m[2] * m[4]) / det,
+        (m[5] * m[6] - m[3] * m[8]) / det,
+        (m[0] * m[8] - m[2] * m[6]) / det,
+        (m[2] * m[3] - m[0] * m[5]) / det,
+        (m[3] * m[7] - m[4] * m[6]) / det,
+        (m[1] * m[6] - m[0] * m[7]) / det,
+        (m[0] * m[4] - m[1] * m[3]) / det,
+    ]
+}
+
+const NONE: usize = usize::MAX;
+
+/// Residual capacities below this are treated as zero.
+const EPSILON: f64 = 1e-9;
+
+/// A directed graph with edge capacities, for finding minimum cuts using Dinic's algorithm.
+/// Edges are stored in pairs, so that the reverse of edge `e` is edge `e ^ 1`.
+struct FlowGraph {
+    /// The first edge leaving each node, or `NONE`.
+    first: Vec<usize>,
+    /// The next edge leaving the same node as each edge, or `NONE`.
+    next: Vec<usize>,
+    to: Vec<usize>,
+    capacity: Vec<f64>,
+}
+
+impl FlowGraph {
+    fn new(num_nodes: usize) -> FlowGraph {
+        FlowGraph {
+            first: vec![NONE; num_nodes],
+            next: vec![],
+            to: vec![],
+            capacity: vec![],
+        }
+    }
+
+    /// Adds an edge from `a` to `b` and an edge from `b` to `a` with the given capacities.
+    fn add_edges(&mut self, a: usize, b: usize, forward: f64, backward: f64) {
+        for &(from, to, capacity) in &[(a, b, forward), (b, a, backward)] {
+            self.next.push(self.first[from]);
+            self.first[from] = self.to.len();
+            self.to.push(to);
+            self.capacity.push(capacity);
+        }
+    }
+
+    /// Returns whether each node is on the source side of a minimum cut between `source`
+    /// and `sink`. Leaves the graph holding the residual capacities of a maximum flow.
+    fn min_cut(&mut self, source: usize, sink: usize) -> Vec<bool> {
+        let mut level = vec![NONE; self.first.len()];
+        let mut current = vec![NONE; self.first.len()];
+        let mut path: Vec<usize> = vec![];
+        loop {
+            self.compute_levels(source, &mut level);
+            if level[sink] == NONE {
+                return level.iter().map(|&l| l != NONE).collect();
+            }
+
+            // Find a blocking flow by repeated depth-first search. current[u] is the first
+            // edge leaving u that may still lie on an augmenting path.
+            current.copy_from_slice(&self.first);
+            path.clear();
+            let mut u = source;
+            loop {
+                if u == sink {
+                    let bottleneck = path.iter().map(|&e| self.capacity[e]).fold(f64::INFINITY, f64::min);
+                    let mut saturated = path.len();
+                    for (i, &e) in path.iter().enumerate() {
+                        self.capacity[e] -= bottleneck;
+                        self.capacity[e ^ 1] += bottleneck;
+                        if self.capacity[e] <= EPSILON && saturated == path.len() {
+                            saturated = i;
+                        }
+                    }
+                    // Continue searching from the start of the first saturated edge.
+                    path.truncate(saturated);
+                    u = path.last().map_or(source, |&e| self.to[e]);
+                    continue;
+                }
+
+                let mut e = current[u];
+                while e != NONE && !(self.capacity[e] > EPSILON && level[self.to[e]] == level[u] + 1) {
+                    e = self.next[e];
+                }
+                current[u] = e;
+                if e != NONE {
+                    path.push(e);
+                    u = self.to[e];
+                } else if u == source {
+                    break;
+                } else {
+                    // No augmenting path passes through u.
+                    level[u] = NONE;
+                    let e = path.pop().unwrap();
+                    u = self.to[e ^ 1];
+                }
+            }
+        }
+    }
+
+    /// Sets the level of each node to its distance from `source` in the residual graph,
+    /// or `NONE` if it is unreachable.
+    fn compute_levels(&self, source: usize, level: &mut [usize]) {
+        for l in level.iter_mut() {
+            *l = NONE;
+        }
+        level[source] = 0;
+        let mut queue = VecDeque::new();
+        queue.push_back(source);
+        while let Some(u) = queue.pop_front() {
+            let mut e = self.first[u];
+            while e != NONE {
+                let v = self.to[e];
+                if self.capacity[e] > EPSILON && level[v] == NONE {
+                    level[v] = level[u] + 1;
+                    queue.push_back(v);
+                }
+                e = self.next[e];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test::{Bencher, black_box};
+
+    // A red disk of radius 8 centred at (16, 14), on a background of blue and green stripes.
+    fn disk_image() -> RgbImage {
+        RgbImage::from_fn(32, 28, |x, y| {
+            let (dx, dy) = (x as f32 - 16.0, y as f32 - 14.0);
+            if dx * dx + dy * dy <= 64.0 {
+                Rgb([210, 40 + (x % 3) as u8 * 5, 30])
+            } else if (x / 4) % 2 == 0 {
+                Rgb([20, 60, 200])
+            } else {
+                Rgb([30, 180, 60])
+            }
+        })
+    }
+
+    fn in_disk(x: u32, y: u32) -> bool {
+        let (dx, dy) = (x as f32 - 16.0, y as f32 - 14.0);
+        dx * dx + dy * dy <= 64.0
+    }
+
+    #[test]
+    fn test_grabcut_rect_segments_disk() {
+        let image = disk_image();
+        for &connectivity in &[Connectivity::Four, Connectivity::Eight] {
+            let options = GrabCutOptions { connectivity, ..GrabCutOptions::default() };
+            let mask = grabcut_rect(&image, Rect::at(5, 3).of_size(23, 22), options).unwrap();
+            for (x, y, p) in mask.enumerate_pixels() {
+                let expected = if in_disk(x, y) { PROBABLE_FOREGROUND } else if x < 5 || y < 3 || x > 27 || y > 24 {
+                    BACKGROUND
+                } else {
+                    PROBABLE_BACKGROUND
+                };
+                assert_eq!(p[0], expected, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_grabcut_from_scribbles() {
+        let image = disk_image();
+        let mut mask = GrayImage::from_pixel(32, 28, Luma([PROBABLE_BACKGROUND]));
+        for x in 12..20 {
+            mask.put_pixel(x, 14, Luma([FOREGROUND]));
+        }
+        for y in 0..28 {
+            mask.put_pixel(0, y, Luma([BACKGROUND]));
+            mask.put_pixel(6, y, Luma([BACKGROUND]));
+        }
+        grabcut(&image, &mut mask, GrabCutOptions::default()).unwrap();
+        for (x, y, p) in mask.enumerate_pixels() {
+            if p[0] == FOREGROUND || p[0] == BACKGROUND {
+                continue;
+            }
+            let expected = if in_disk(x, y) { PROBABLE_FOREGROUND } else { PROBABLE_BACKGROUND };
+            assert_eq!(p[0], expected, "({}, {})", x, y);
+        }
+        assert_eq!(mask.get_pixel(15, 14)[0], FOREGROUND);
+        assert_eq!(mask.get_pixel(6, 3)[0], BACKGROUND);
+    }
+
+    #[test]
+    fn test_grabcut_invalid_inputs() {
+        let image = disk_image();
+        let options = GrabCutOptions::default();
+
+        let mut all_foreground = GrayImage::from_pixel(32, 28, Luma([PROBABLE_FOREGROUND]));
+        assert!(grabcut(&image, &mut all_foreground, options).is_err());
+        let mut invalid_value = GrayImage::from_pixel(32, 28, Luma([PROBABLE_FOREGROUND]));
+        invalid_value.put_pixel(0, 0, Luma([1]));
+        assert!(grabcut(&image, &mut invalid_value, options).is_err());
+        let mut wrong_size = GrayImage::new(3, 3);
+        assert_eq!(
+            grabcut(&image, &mut wrong_size, options),
+            Err(ImageProcError::DimensionMismatch { expected: (32, 28), actual: (3, 3) })
+        );
+
+        let whole_image = Rect::at(-1, -1).of_size(40, 40);
+        assert!(grabcut_rect(&image, whole_image, options).is_err());
+        let outside = Rect::at(40, 0).of_size(5, 5);
+        assert_eq!(
+            grabcut_rect(&image, outside, options).err(),
+            Some(ImageProcError::OutOfBounds { region: outside, image: (32, 28) })
+        );
+        let no_iterations = GrabCutOptions { iterations: 0, ..options };
+        assert!(grabcut_rect(&image, Rect::at(5, 3).of_size(23, 22), no_iterations).is_err());
+    }
+
+    #[test]
+    fn test_min_cut() {
+        // The minimum cut separates {source, 0} from {1, sink}, with capacity 4.
+        let (source, sink) = (2, 3);
+        let mut graph = FlowGraph::new(4);
+        graph.add_edges(source, 0, 5.0, 0.0);
+        graph.add_edges(source, 1, 1.0, 0.0);
+        graph.add_edges(0, 1, 2.0, 2.0);
+        graph.add_edges(0, sink, 1.0, 0.0);
+        graph.add_edges(1, sink, 4.0, 0.0);
+        assert_eq!(graph.min_cut(source, sink), vec![true, false, true, false]);
+        // Every edge from the source side to the sink side is saturated.
+        assert_eq!(graph.capacity[2], 0.0);
+        assert_eq!(graph.capacity[4], 0.0);
+        assert_eq!(graph.capacity[6], 0.0);
+    }
+
+    #[bench]
+    fn bench_grabcut_rect(b: &mut Bencher) {
+        let image = disk_image();
+        let rect = Rect::at(5, 3).of_size(23, 22);
+        b.iter(|| black_box(grabcut_rect(&image, rect, GrabCutOptions::default()).unwrap()));
+    }
+}
@@ -1,10 +1,12 @@
 //! Functions for computing gradients of image intensities.
 
 use image::{GenericImage, GenericImageView, GrayImage, Luma, Pixel};
-use definitions::{HasBlack, Image};
+use conv::ValueInto;
+use definitions::{HasBlack, Image, MaybeSync};
 use filter::filter3x3;
 use itertools::multizip;
 use map::{WithChannel, ChannelMap};
+use math::cast;
 
 /// Sobel filter for detecting vertical gradients.
 ///
@@ -38,6 +40,22 @@ pub static HORIZONTAL_PREWITT: [i32; 9] = [
     -1, 0, 1,
     -1, 0, 1];
 
+/// Scharr filter for detecting vertical gradients.
+///
+/// Used by the [`vertical_scharr`](fn.vertical_scharr.html) function.
+pub static VERTICAL_SCHARR: [i32; 9] = [
+    -3, -10, -3,
+     0,   0,  0,
+     3,  10,  3];
+
+/// Scharr filter for detecting horizontal gradients.
+///
+/// Used by the [`horizontal_scharr`](fn.horizontal_scharr.html) function.
+pub static HORIZONTAL_SCHARR: [i32; 9] = [
+     -3, 0,  3,
+    -10, 0, 10,
+     -3, 0,  3];
+
 /// Convolves an image with the [`HORIZONTAL_SOBEL`](static.HORIZONTAL_SOBEL.html)
 /// kernel to detect horizontal gradients.
 pub fn horizontal_sobel(image: &GrayImage) -> Image<Luma<i16>> {
@@ -62,6 +80,20 @@ pub fn vertical_prewitt(image: &GrayImage) -> Image<Luma<i16>> {
     filter3x3(image, &VERTICAL_PREWITT)
 }
 
+/// Convolves an image with the [`HORIZONTAL_SCHARR`](static.HORIZONTAL_SCHARR.html)
+/// kernel to detect horizontal gradients.
+///
+/// The Scharr kernels have better rotational symmetry than the Sobel kernels.
+pub fn horizontal_scharr(image: &GrayImage) -> Image<Luma<i16>> {
+    filter3x3(image, &HORIZONTAL_SCHARR)
+}
+
+/// Convolves an image with the [`VERTICAL_SCHARR`](static.VERTICAL_SCHARR.html)
+/// kernel to detect vertical gradients.
+pub fn vertical_scharr(image: &GrayImage) -> Image<Luma<i16>> {
+    filter3x3(image, &VERTICAL_SCHARR)
+}
+
 /// Returns the magnitudes of gradients in an image using Sobel filters.
 pub fn sobel_gradients(image: &GrayImage) -> Image<Luma<u16>> {
     gradients(image, &HORIZONTAL_SOBEL, &VERTICAL_SOBEL, |p| p)
@@ -148,6 +180,239 @@ pub fn prewitt_gradients(image: &GrayImage) -> Image<Luma<u16>> {
     gradients(image, &HORIZONTAL_PREWITT, &VERTICAL_PREWITT, |p| p)
 }
 
+/// Returns the magnitudes of gradients in an image using Scharr filters.
+pub fn scharr_gradients(image: &GrayImage) -> Image<Luma<u16>> {
+    gradients(image, &HORIZONTAL_SCHARR, &VERTICAL_SCHARR, |p| p)
+}
+
+/// Convolves an image with the [`HORIZONTAL_SOBEL`](static.HORIZONTAL_SOBEL.html)
+/// kernel, performing all calculations at type `f32`.
+///
+/// Unlike [`horizontal_sobel`](fn.horizontal_sobel.html) this supports images with any number
+/// of channels and any subpixel type convertible to `f32`, including 16 bit and floating
+/// point images, and never saturates.
+pub fn horizontal_sobel_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    filter3x3(image, &kernel_f32(&HORIZONTAL_SOBEL))
+}
+
+/// Convolves an image with the [`VERTICAL_SOBEL`](static.VERTICAL_SOBEL.html)
+/// kernel, performing all calculations at type `f32`.
+///
+/// See [`horizontal_sobel_f32`](fn.horizontal_sobel_f32.html) for details.
+pub fn vertical_sobel_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    filter3x3(image, &kernel_f32(&VERTICAL_SOBEL))
+}
+
+/// Convolves an image with the [`HORIZONTAL_PREWITT`](static.HORIZONTAL_PREWITT.html)
+/// kernel, performing all calculations at type `f32`.
+///
+/// See [`horizontal_sobel_f32`](fn.horizontal_sobel_f32.html) for details.
+pub fn horizontal_prewitt_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    filter3x3(image, &kernel_f32(&HORIZONTAL_PREWITT))
+}
+
+/// Convolves an image with the [`VERTICAL_PREWITT`](static.VERTICAL_PREWITT.html)
+/// kernel, performing all calculations at type `f32`.
+///
+/// See [`horizontal_sobel_f32`](fn.horizontal_sobel_f32.html) for details.
+pub fn vertical_prewitt_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    filter3x3(image, &kernel_f32(&VERTICAL_PREWITT))
+}
+
+/// Convolves an image with the [`HORIZONTAL_SCHARR`](static.HORIZONTAL_SCHARR.html)
+/// kernel, performing all calculations at type `f32`.
+///
+/// See [`horizontal_sobel_f32`](fn.horizontal_sobel_f32.html) for details.
+pub fn horizontal_scharr_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    filter3x3(image, &kernel_f32(&HORIZONTAL_SCHARR))
+}
+
+/// Convolves an image with the [`VERTICAL_SCHARR`](static.VERTICAL_SCHARR.html)
+/// kernel, performing all calculations at type `f32`.
+///
+/// See [`horizontal_sobel_f32`](fn.horizontal_sobel_f32.html) for details.
+pub fn vertical_scharr_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    filter3x3(image, &kernel_f32(&VERTICAL_SCHARR))
+}
+
+/// Returns the per-channel magnitudes of gradients in an image using Sobel filters,
+/// performing all calculations at type `f32`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::gradients::sobel_gradients_f32;
+///
+/// // A 16 bit image whose gradients would saturate sobel_gradients.
+/// let image = gray_image!(type: u16,
+///     0, 0, 60000;
+///     0, 0, 60000;
+///     0, 0, 60000);
+///
+/// let expected = gray_image!(type: f32,
+///     0.0, 240000.0, 240000.0;
+///     0.0, 240000.0, 240000.0;
+///     0.0, 240000.0, 240000.0);
+///
+/// assert_pixels_eq!(sobel_gradients_f32(&image), expected);
+/// # }
+/// ```
+pub fn sobel_gradients_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    gradients_f32(image, &HORIZONTAL_SOBEL, &VERTICAL_SOBEL)
+}
+
+/// Returns the per-channel magnitudes of gradients in an image using Prewitt filters,
+/// performing all calculations at type `f32`.
+pub fn prewitt_gradients_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    gradients_f32(image, &HORIZONTAL_PREWITT, &VERTICAL_PREWITT)
+}
+
+/// Returns the per-channel magnitudes of gradients in an image using Scharr filters,
+/// performing all calculations at type `f32`.
+pub fn scharr_gradients_f32<P>(image: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    gradients_f32(image, &HORIZONTAL_SCHARR, &VERTICAL_SCHARR)
+}
+
+/// Combines per-channel horizontal and vertical gradients into per-channel
+/// gradient magnitudes `sqrt(dx^2 + dy^2)`.
+///
+/// The inputs are typically the outputs of a pair of horizontal and vertical
+/// filters, e.g. [`horizontal_sobel_f32`](fn.horizontal_sobel_f32.html) and
+/// [`vertical_sobel_f32`](fn.vertical_sobel_f32.html).
+///
+/// # Panics
+///
+/// If `horizontal` and `vertical` do not have the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::gradients::gradient_magnitude;
+///
+/// let dx = gray_image!(type: i16, 3, -6);
+/// let dy = gray_image!(type: i16, 4, 8);
+///
+/// assert_pixels_eq!(
+///     gradient_magnitude(&dx, &dy),
+///     gray_image!(type: f32, 5.0, 10.0)
+/// );
+/// # }
+/// ```
+pub fn gradient_magnitude<P>(horizontal: &Image<P>, vertical: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32>,
+{
+    combine_gradients(horizontal, vertical, |h, v| h.hypot(v))
+}
+
+/// Combines per-channel horizontal and vertical gradients into per-channel
+/// gradient orientations `atan2(dy, dx)`, in radians.
+///
+/// Orientations lie in the range `[-pi, pi]`. Angles increase clockwise
+/// from the positive x-axis, as the y-axis points down the image.
+///
+/// # Panics
+///
+/// If `horizontal` and `vertical` do not have the same dimensions.
+pub fn gradient_orientation<P>(horizontal: &Image<P>, vertical: &Image<P>) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32>,
+{
+    combine_gradients(horizontal, vertical, |h, v| v.atan2(h))
+}
+
+fn combine_gradients<P, F>(
+    horizontal: &Image<P>,
+    vertical: &Image<P>,
+    f: F,
+) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32>,
+    F: Fn(f32, f32) -> f32,
+{
+    assert_eq!(horizontal.dimensions(), vertical.dimensions());
+    let (width, height) = horizontal.dimensions();
+    let mut out = Image::<ChannelMap<P, f32>>::new(width, height);
+
+    for (o, h, v) in multizip((out.iter_mut(), horizontal.iter(), vertical.iter())) {
+        *o = f(cast(*h), cast(*v));
+    }
+
+    out
+}
+
+fn gradients_f32<P>(
+    image: &Image<P>,
+    horizontal_kernel: &[i32; 9],
+    vertical_kernel: &[i32; 9],
+) -> Image<ChannelMap<P, f32>>
+where
+    P: WithChannel<f32> + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    let horizontal: Image<ChannelMap<P, f32>> = filter3x3(image, &kernel_f32(horizontal_kernel));
+    let mut out: Image<ChannelMap<P, f32>> = filter3x3(image, &kernel_f32(vertical_kernel));
+
+    for (o, h) in out.iter_mut().zip(horizontal.iter()) {
+        *o = (h.powi(2) + o.powi(2)).sqrt();
+    }
+
+    out
+}
+
+fn kernel_f32(kernel: &[i32; 9]) -> [f32; 9] {
+    let mut out = [0f32; 9];
+    for (o, k) in out.iter_mut().zip(kernel.iter()) {
+        *o = *k as f32;
+    }
+    out
+}
+
 // TODO: Returns directions as well as magnitudes.
 // TODO: Support filtering without allocating a fresh image - filtering functions could
 // TODO: take some kind of pixel-sink. This would allow us to compute gradient magnitudes
@@ -179,7 +444,7 @@ where
                 let mut p = ChannelMap::<P, u16>::black();
 
                 for (h, v, p) in multizip((h.channels(), v.channels(), p.channels_mut())) {
-                    *p = gradient_magnitude(*h as f32, *v as f32);
+                    *p = magnitude(*h as f32, *v as f32);
                 }
 
                 out.unsafe_put_pixel(x, y, f(p));
@@ -191,7 +456,7 @@ where
 }
 
 #[inline]
-fn gradient_magnitude(dx: f32, dy: f32) -> u16 {
+fn magnitude(dx: f32, dy: f32) -> u16 {
     (dx.powi(2) + dy.powi(2)).sqrt() as u16
 }
 
@@ -212,6 +477,38 @@ mod test {
         assert_pixels_eq!(vertical_prewitt(&image), expected);
     }
 
+    #[test]
+    fn test_sobel_gradients_f32_matches_sobel_gradients() {
+        let image = gray_image!(
+            3, 20, 1, 90;
+            6, 5, 140, 4;
+            9, 255, 7, 0);
+
+        let expected = sobel_gradients(&image);
+        let actual = sobel_gradients_f32(&image);
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert_eq!(*e, *a as u16);
+        }
+    }
+
+    #[test]
+    fn test_gradients_f32_support_high_bit_depth() {
+        let image = gray_image!(type: u16,
+            0, 50000, 65535;
+            0, 50000, 65535;
+            0, 50000, 65535);
+        let as_float = ImageBuffer::from_fn(3, 3, |x, y| Luma([image.get_pixel(x, y)[0] as f32]));
+
+        let expected = gray_image!(type: f32,
+            200000.0, 262140.0, 62140.0;
+            200000.0, 262140.0, 62140.0;
+            200000.0, 262140.0, 62140.0);
+
+        assert_pixels_eq!(horizontal_sobel_f32(&image), expected);
+        assert_pixels_eq!(horizontal_sobel_f32(&as_float), expected);
+        assert_pixels_eq!(vertical_prewitt_f32(&image), ImageBuffer::from_pixel(3, 3, Luma([0f32])));
+    }
+
     #[test]
     fn test_horizontal_sobel_gradient_image() {
         let image = gray_image!(
@@ -276,6 +573,80 @@ mod test {
         assert_pixels_eq!(filtered, expected);
     }
 
+    #[test]
+    fn test_horizontal_scharr_gradient_image() {
+        let image = gray_image!(
+            3, 2, 1;
+            6, 5, 4;
+            9, 8, 7);
+
+        let expected = gray_image!(type: i16,
+            -16, -32, -16;
+            -16, -32, -16;
+            -16, -32, -16);
+
+        let filtered = horizontal_scharr(&image);
+        assert_pixels_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_vertical_scharr_gradient_image() {
+        let image = gray_image!(
+            3, 6, 9;
+            2, 5, 8;
+            1, 4, 7);
+
+        let expected = gray_image!(type: i16,
+            -16, -16, -16;
+            -32, -32, -32;
+            -16, -16, -16);
+
+        let filtered = vertical_scharr(&image);
+        assert_pixels_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_scharr_gradients_f32_matches_scharr_gradients() {
+        let image = gray_image!(
+            3, 20, 1, 90;
+            6, 5, 140, 4;
+            9, 255, 7, 0);
+
+        let expected = scharr_gradients(&image);
+        let actual = scharr_gradients_f32(&image);
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert_eq!(*e, *a as u16);
+        }
+    }
+
+    #[test]
+    fn test_gradient_magnitude_and_orientation_rgb() {
+        use std::f32::consts::PI;
+
+        let dx = rgb_image!(type: f32, [1.0, 0.0, -2.0], [0.0, 3.0, 0.0]);
+        let dy = rgb_image!(type: f32, [1.0, 2.0, 0.0], [0.0, -3.0, 0.0]);
+
+        let magnitude = gradient_magnitude(&dx, &dy);
+        let expected = rgb_image!(type: f32, [2f32.sqrt(), 2.0, 2.0], [0.0, 18f32.sqrt(), 0.0]);
+        assert_pixels_eq_within!(magnitude, expected, 1e-6);
+
+        let orientation = gradient_orientation(&dx, &dy);
+        let expected = rgb_image!(type: f32, [PI / 4.0, PI / 2.0, PI], [0.0, -PI / 4.0, 0.0]);
+        assert_pixels_eq_within!(orientation, expected, 1e-6);
+    }
+
+    #[test]
+    fn test_gradient_magnitude_matches_sobel_gradients_f32() {
+        let image = gray_image!(
+            3, 20, 1, 90;
+            6, 5, 140, 4;
+            9, 255, 7, 0);
+
+        let expected = sobel_gradients_f32(&image);
+        let actual = gradient_magnitude(&horizontal_sobel(&image), &vertical_sobel(&image));
+        assert_pixels_eq_within!(actual, expected, 1e-3);
+    }
+
     #[bench]
     fn bench_sobel_gradients(b: &mut Bencher) {
         let image = gray_bench_image(500, 500);
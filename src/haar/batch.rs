@@ -0,0 +1,328 @@
+//! Evaluation of many Haar-like features in the same window.
+
+use super::{offset_in_bounds, HaarFeature, Sign};
+use definitions::Image;
+use error::{ImageProcError, Result};
+use image::Luma;
+use rect::Rect;
+
+/// A set of Haar-like features prepared for evaluation together in many windows, e.g.
+/// the features of a boosted cascade.
+///
+/// Each feature is reduced to the reads it makes from the integral image and the weight
+/// of each read. Reads shared between blocks are merged, and features are evaluated in the
+/// order of their first read with the reads of each feature sorted in memory order. Bounds
+/// are checked once per window for the whole set rather than once per feature, and the
+/// inner loop over reads is branch-free.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::haar::{HaarFeature, HaarFeatureBatch};
+/// use imageproc::integral_image::integral_image;
+///
+/// let image = gray_image!(
+///     1, 2, 3, 4;
+///     5, 6, 7, 8);
+/// let integral = integral_image(&image);
+///
+/// let features: Vec<HaarFeature> = HaarFeature::enumerate(2, 2).collect();
+/// let batch = HaarFeatureBatch::new(&features);
+///
+/// let values = batch.evaluate_at(&integral, 2, 0).unwrap();
+/// for (feature, value) in features.iter().zip(values) {
+///     assert_eq!(feature.evaluate_at(&integral, 2, 0), Ok(value));
+/// }
+///
+/// // The features do not fit in a window starting in the last column.
+/// assert!(batch.evaluate_at(&integral, 3, 0).is_err());
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HaarFeatureBatch {
+    // The position in the input of each feature, in evaluation order.
+    indices: Vec<usize>,
+    // The reads of the feature at position i in evaluation order are
+    // reads[starts[i]..starts[i + 1]].
+    starts: Vec<usize>,
+    reads: Vec<Read>,
+    // The largest extent of any feature.
+    extent: (u32, u32),
+}
+
+impl HaarFeatureBatch {
+    /// Prepares `features` for evaluation. Values are returned in the order of `features`.
+    ///
+    /// As for [`HaarFeature::evaluate_at`](struct.HaarFeature.html#method.evaluate_at), tilted
+    /// features must be evaluated on a rotated integral image, so a batch should contain
+    /// either only tilted or only upright features.
+    pub fn new(features: &[HaarFeature]) -> HaarFeatureBatch {
+        let mut reads: Vec<_> = features
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (i, reads(f)))
+            .collect();
+        reads.sort_by_key(|r| r.1.first().map(|&(x, y, _)| (y, x)));
+
+        let mut batch = HaarFeatureBatch {
+            indices: Vec::with_capacity(features.len()),
+            starts: vec![0],
+            reads: vec![],
+            extent: (0, 0),
+        };
+        for (index, feature_reads) in reads {
+            batch.indices.push(index);
+            batch.reads.extend(feature_reads.into_iter().map(|(dx, dy, weight)| Read { dx, dy, weight }));
+            batch.starts.push(batch.reads.len());
+        }
+        for feature in features {
+            let (right, bottom) = feature.extent();
+            batch.extent = (batch.extent.0.max(right), batch.extent.1.max(bottom));
+        }
+        batch
+    }
+
+    /// The number of features in this batch.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// True if this batch contains no features.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Evaluates every feature in the window with top left corner (x, y), returning
+    /// the values in the order that the features were provided to `new`.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if any feature does not lie within the
+    /// image that `integral` was computed from.
+    pub fn evaluate_at(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> Result<Vec<i32>> {
+        let mut values = vec![0; self.len()];
+        self.evaluate_at_into(integral, x, y, &mut values)?;
+        Ok(values)
+    }
+
+    /// Writes the values of every feature in the window with top left corner (x, y) to `values`,
+    /// as for [`evaluate_at`](#method.evaluate_at). This avoids allocating when scanning many windows.
+    ///
+    /// # Panics
+    /// If the length of `values` is not the number of features in this batch.
+    pub fn evaluate_at_into(&self, integral: &Image<Luma<u32>>, x: u32, y: u32, values: &mut [i32]) -> Result<()> {
+        assert_eq!(values.len(), self.len(), "values must have one entry per feature");
+        if self.is_empty() {
+            return Ok(());
+        }
+        let (width, height) = integral.dimensions();
+        let (right, bottom) = self.extent;
+        if !offset_in_bounds(x, y, (right, bottom), (width, height)) {
+            return Err(ImageProcError::OutOfBounds {
+                region: Rect::at(x as i32, y as i32).of_size(right, bottom),
+                image: (width.saturating_sub(1), height.saturating_sub(1)),
+            });
+        }
+
+        let raw: &[u32] = integral;
+        let stride = width as usize;
+        let origin = y as usize * stride + x as usize;
+        for (&index, bounds) in self.indices.iter().zip(self.starts.windows(2)) {
+            let mut sum = 0i32;
+            for read in &self.reads[bounds[0]..bounds[1]] {
+                let offset = origin + read.dy as usize * stride + read.dx as usize;
+                // Safe as every read lies within the extent checked above.
+                sum += read.weight * unsafe { *raw.get_unchecked(offset) } as i32;
+            }
+            values[index] = sum;
+        }
+        Ok(())
+    }
+}
+
+// A weighted read from an integral image, relative to the top left of a window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Read {
+    dx: u32,
+    dy: u32,
+    weight: i32,
+}
+
+/// Evaluates every feature in `features` in the window with top left corner (x, y),
+/// returning the values in the order of `features`.
+///
+/// This is equivalent to calling [`HaarFeature::evaluate_at`](struct.HaarFeature.html#method.evaluate_at)
+/// on each feature, but checks bounds once for all features. When evaluating the same features
+/// in many windows, create a [`HaarFeatureBatch`](struct.HaarFeatureBatch.html) once instead.
+///
+/// Returns `ImageProcError::OutOfBounds` if any feature does not lie within the
+/// image that `integral` was computed from.
+pub fn evaluate_all(features: &[HaarFeature], integral: &Image<Luma<u32>>, x: u32, y: u32) -> Result<Vec<i32>> {
+    HaarFeatureBatch::new(features).evaluate_at(integral, x, y)
+}
+
+// The (x, y, weight) of each read from the integral image made when evaluating `feature`
+// in a window at the origin, in row-major order with repeated locations merged.
+pub(super) fn reads(feature: &HaarFeature) -> Vec<(u32, u32, i32)> {
+    let (block_width, block_height) = (feature.block_width() as u32, feature.block_height() as u32);
+    let sign = if feature.sign == Sign::Positive { 1 } else { -1 };
+    let mut reads = vec![];
+
+    for h in 0..feature.blocks_high() as u32 {
+        for w in 0..feature.blocks_wide() as u32 {
+            let s = if (w + h) % 2 == 0 { sign } else { -sign };
+            if feature.tilted {
+                // As in evaluate_tilted_at_unchecked.
+                let (top_x, top_y) = feature.top_corner();
+                let bx = top_x + w * block_width - h * block_height;
+                let by = top_y + w * block_width + h * block_height;
+                reads.push((bx, by, s));
+                reads.push((bx - block_height, by + block_height, -s));
+                reads.push((bx + block_width, by + block_width, -s));
+                reads.push((bx + block_width - block_height, by + block_width + block_height, s));
+            } else {
+                let x0 = feature.left as u32 + w * block_width;
+                let y0 = feature.top as u32 + h * block_height;
+                let (x1, y1) = (x0 + block_width, y0 + block_height);
+                reads.push((x0, y0, s));
+                reads.push((x1, y0, -s));
+                reads.push((x0, y1, -s));
+                reads.push((x1, y1, s));
+            }
+        }
+    }
+
+    reads.sort_by_key(|&(x, y, _)| (y, x));
+    let mut merged: Vec<(u32, u32, i32)> = vec![];
+    for (x, y, weight) in reads {
+        match merged.last_mut() {
+            Some(last) if (last.0, last.1) == (x, y) => last.2 += weight,
+            _ => merged.push((x, y, weight)),
+        }
+    }
+    merged.retain(|r| r.2 != 0);
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::HaarFeatureType;
+    use integral_image::{integral_image, rotated_integral_image};
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_batch_matches_individual_evaluation() {
+        let integral = integral_image(&gray_bench_image(9, 8));
+        let features: Vec<HaarFeature> = HaarFeature::enumerate(6, 5).collect();
+        let batch = HaarFeatureBatch::new(&features);
+        assert_eq!(batch.len(), features.len());
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let values = batch.evaluate_at(&integral, x, y).unwrap();
+                for (feature, value) in features.iter().zip(values) {
+                    assert_eq!(feature.evaluate_at(&integral, x, y), Ok(value), "{:?} at ({}, {})", feature, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_individual_evaluation_tilted() {
+        let integral = rotated_integral_image(&gray_bench_image(12, 11));
+        let mut features = vec![];
+        for &feature_type in &[HaarFeatureType::TwoRegionHorizontal, HaarFeatureType::ThreeRegionVertical, HaarFeatureType::FourRegion] {
+            for &(block_width, block_height) in &[(1, 1), (2, 1), (1, 2)] {
+                features.push(HaarFeature::tilted(feature_type, block_width, block_height, 1, 0));
+            }
+        }
+
+        let values = evaluate_all(&features, &integral, 1, 2).unwrap();
+        for (feature, value) in features.iter().zip(values) {
+            assert_eq!(feature.evaluate_at(&integral, 1, 2), Ok(value), "{:?}", feature);
+        }
+    }
+
+    #[test]
+    fn test_batch_reads_are_merged() {
+        // The four region feature of 1x1 blocks reads each of a 3x3 grid of corners once.
+        let feature = HaarFeature::try_new(HaarFeatureType::FourRegion, 1, 1, 0, 0).unwrap();
+        assert_eq!(
+            reads(&feature),
+            vec![
+                (0, 0, 1), (1, 0, -2), (2, 0, 1),
+                (0, 1, -2), (1, 1, 4), (2, 1, -2),
+                (0, 2, 1), (1, 2, -2), (2, 2, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_out_of_bounds() {
+        let integral = integral_image(&gray_bench_image(5, 5));
+        let features = vec![
+            HaarFeature::try_new(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0).unwrap(),
+            HaarFeature::try_new(HaarFeatureType::TwoRegionVertical, 1, 2, 1, 0).unwrap(),
+        ];
+        let batch = HaarFeatureBatch::new(&features);
+
+        assert!(batch.evaluate_at(&integral, 3, 1).is_ok());
+        assert_eq!(
+            batch.evaluate_at(&integral, 3, 2),
+            Err(ImageProcError::OutOfBounds {
+                region: Rect::at(3, 2).of_size(2, 4),
+                image: (5, 5)
+            })
+        );
+        assert!(batch.evaluate_at(&integral, u32::max_value() - 1, 0).is_err());
+        assert!(batch.evaluate_at(&integral, 0, u32::max_value()).is_err());
+
+        let empty = HaarFeatureBatch::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.evaluate_at(&integral, 10, 10), Ok(vec![]));
+    }
+
+    fn bench_features() -> Vec<HaarFeature> {
+        HaarFeature::enumerate(24, 24).step_by(100).take(1000).collect()
+    }
+
+    #[bench]
+    fn bench_batch_evaluate_at(b: &mut Bencher) {
+        // Evaluating 1000 features in each of 100 windows.
+        let features = bench_features();
+        let integral = integral_image(&gray_bench_image(34, 34));
+        let batch = HaarFeatureBatch::new(&features);
+        let mut values = vec![0; features.len()];
+
+        b.iter(|| {
+            for y in 0..10 {
+                for x in 0..10 {
+                    batch.evaluate_at_into(&integral, x, y, &mut values).unwrap();
+                    black_box(&values);
+                }
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_individual_evaluate_at(b: &mut Bencher) {
+        // As for bench_batch_evaluate_at, evaluating each feature separately.
+        let features = bench_features();
+        let integral = integral_image(&gray_bench_image(34, 34));
+        let mut values = vec![0; features.len()];
+
+        b.iter(|| {
+            for y in 0..10 {
+                for x in 0..10 {
+                    for (value, feature) in values.iter_mut().zip(&features) {
+                        *value = feature.evaluate_at(&integral, x, y).unwrap();
+                    }
+                    black_box(&values);
+                }
+            }
+        });
+    }
+}
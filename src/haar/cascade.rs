@@ -0,0 +1,528 @@
+//! Boosted cascades of Haar-like features, as used by the
+//! [Viola-Jones object detector](https://en.wikipedia.org/wiki/Viola%E2%80%93Jones_object_detection_framework).
+
+use super::{offset_in_bounds, HaarFeature, Sign};
+use definitions::Image;
+use error::{ImageProcError, Result};
+use image::{GenericImageView, Luma};
+use rect::Rect;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+
+/// A decision stump on the value of a single Haar-like feature.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WeakClassifier {
+    /// The feature to evaluate, positioned relative to the top left of the detection window.
+    pub feature: HaarFeature,
+    /// Feature values less than this threshold produce `below`, and all others produce `above`.
+    pub threshold: f32,
+    /// Output when the feature value is less than `threshold`.
+    pub below: f32,
+    /// Output when the feature value is at least `threshold`.
+    pub above: f32,
+}
+
+impl WeakClassifier {
+    /// Returns the output of this classifier for the given feature value.
+    pub fn classify(&self, feature_value: f32) -> f32 {
+        if feature_value < self.threshold { self.below } else { self.above }
+    }
+}
+
+/// A stage of a cascade. A window passes the stage if the sum of the outputs of
+/// its weak classifiers is at least `threshold`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stage {
+    /// Weak classifiers whose outputs are summed.
+    pub classifiers: Vec<WeakClassifier>,
+    /// Minimum total output for a window to pass this stage.
+    pub threshold: f32,
+}
+
+/// A sequence of stages, each of which must be passed for a window to be accepted.
+/// Most windows are rejected by the first few stages, making evaluation cheap.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawCascade"))]
+pub struct Cascade {
+    window_width: u32,
+    window_height: u32,
+    stages: Vec<Stage>,
+}
+
+// The fields of a serialized cascade, which are checked by `Cascade::new` before
+// a `Cascade` is deserialized.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawCascade {
+    window_width: u32,
+    window_height: u32,
+    stages: Vec<Stage>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawCascade> for Cascade {
+    type Error = ImageProcError;
+
+    fn try_from(raw: RawCascade) -> Result<Cascade> {
+        Cascade::new(raw.window_width, raw.window_height, raw.stages)
+    }
+}
+
+impl Cascade {
+    /// Creates a cascade for detection windows of the given size.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if the window is empty, any feature
+    /// does not fit within the window, or any feature is tilted.
+    pub fn new(window_width: u32, window_height: u32, stages: Vec<Stage>) -> Result<Cascade> {
+        if window_width == 0 || window_height == 0 {
+            return Err(ImageProcError::InvalidOptions(
+                format!("window must be non-empty, got {}x{}", window_width, window_height)
+            ));
+        }
+        for stage in &stages {
+            for classifier in &stage.classifiers {
+                if classifier.feature.is_tilted() {
+                    return Err(ImageProcError::InvalidOptions(format!(
+                        "tilted feature {:?} is not supported", classifier.feature
+                    )));
+                }
+                let (right, bottom) = classifier.feature.extent();
+                if right > window_width || bottom > window_height {
+                    return Err(ImageProcError::InvalidOptions(format!(
+                        "feature {:?} does not fit in a {}x{} window",
+                        classifier.feature, window_width, window_height
+                    )));
+                }
+            }
+        }
+        Ok(Cascade { window_width, window_height, stages })
+    }
+
+    /// Width and height of the detection window at scale 1.
+    pub fn window_size(&self) -> (u32, u32) {
+        (self.window_width, self.window_height)
+    }
+
+    /// The stages of this cascade.
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Returns true if the window with top left corner (x, y) at the given scale passes every
+    /// stage, where `integral` is the integral image of the image to search.
+    ///
+    /// Features are scaled rather than the image, and feature values are normalised by the
+    /// ratio of their scaled and unscaled areas so that thresholds remain valid at every scale.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if the scaled window does not lie within the image.
+    ///
+    /// # Panics
+    /// If `scale` is not positive.
+    pub fn classify(&self, integral: &Image<Luma<u32>>, x: u32, y: u32, scale: f32) -> Result<bool> {
+        assert!(scale > 0.0, "scale must be positive, got {}", scale);
+        let scaled = ScaledCascade::new(self, scale);
+        let (width, height) = image_size(integral);
+        let fits = |start: u32, extent: u32, size: u32| start.checked_add(extent).map_or(false, |end| end <= size);
+        if !fits(x, scaled.extent.0, width) || !fits(y, scaled.extent.1, height) {
+            return Err(ImageProcError::OutOfBounds {
+                region: Rect::at(x as i32, y as i32).of_size(scaled.extent.0, scaled.extent.1),
+                image: (width.saturating_sub(1), height.saturating_sub(1)),
+            });
+        }
+        Ok(unsafe { scaled.classify(integral, x, y) })
+    }
+}
+
+/// Options for [`detect_objects`](fn.detect_objects.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DetectionOptions {
+    /// Smallest scale at which to search, relative to the cascade's window size.
+    pub initial_scale: f32,
+    /// Ratio between consecutive scales. Must be greater than 1.
+    pub scale_factor: f32,
+    /// Distance between window positions at scale 1, in pixels. At scale `s` windows
+    /// are `max(1, round(step * s))` pixels apart.
+    pub step: f32,
+}
+
+impl Default for DetectionOptions {
+    fn default() -> Self {
+        DetectionOptions {
+            initial_scale: 1.0,
+            scale_factor: 1.25,
+            step: 1.0,
+        }
+    }
+}
+
+impl DetectionOptions {
+    /// Returns an error describing every invalid option.
+    pub(super) fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.initial_scale <= 0.0 || self.initial_scale.is_nan() {
+            errors.push("initial_scale must be positive");
+        }
+        if self.scale_factor <= 1.0 || self.scale_factor.is_nan() {
+            errors.push("scale_factor must be greater than 1");
+        }
+        if self.step <= 0.0 || self.step.is_nan() {
+            errors.push("step must be positive");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Scans a cascade over an image at increasing scales and returns the bounding box
+/// of every accepted window, where `integral` is the integral image of the image to search.
+///
+/// Scales start at `options.initial_scale` and increase by `options.scale_factor` until
+/// the scaled window no longer fits in the image. Overlapping detections of the same object
+/// are all returned.
+///
+/// Returns `ImageProcError::InvalidOptions` if `initial_scale` or `step` is not positive,
+/// or `scale_factor` is not greater than 1.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::haar::{
+///     detect_objects, enumerate_haar_features, Cascade, DetectionOptions, Stage, WeakClassifier
+/// };
+/// use imageproc::integral_image::integral_image;
+/// use imageproc::rect::Rect;
+///
+/// // The only features for a 2x1 window are the differences between the left
+/// // and right pixels, with either sign. The first is left minus right.
+/// let feature = enumerate_haar_features(2, 1)[0];
+///
+/// // Accept windows whose left pixel is brighter than their right.
+/// let classifier = WeakClassifier { feature, threshold: 1.0, below: 0.0, above: 1.0 };
+/// let stage = Stage { classifiers: vec![classifier], threshold: 1.0 };
+/// let cascade = Cascade::new(2, 1, vec![stage]).unwrap();
+///
+/// let image = gray_image!(0, 9, 0, 0);
+/// let integral = integral_image(&image);
+/// let options = DetectionOptions { scale_factor: 2.0, ..DetectionOptions::default() };
+///
+/// assert_eq!(
+///     detect_objects(&integral, &cascade, options).unwrap(),
+///     vec![Rect::at(1, 0).of_size(2, 1)]
+/// );
+/// # }
+/// ```
+pub fn detect_objects(
+    integral: &Image<Luma<u32>>,
+    cascade: &Cascade,
+    options: DetectionOptions,
+) -> Result<Vec<Rect>> {
+    options.validate()?;
+
+    let (width, height) = image_size(integral);
+    let mut detections = vec![];
+    let mut scale = options.initial_scale;
+
+    loop {
+        let scaled = ScaledCascade::new(cascade, scale);
+        let (extent_width, extent_height) = scaled.extent;
+        if extent_width > width || extent_height > height {
+            break;
+        }
+        let step = ((options.step * scale).round() as u32).max(1);
+        let mut y = 0;
+        while y + extent_height <= height {
+            let mut x = 0;
+            while x + extent_width <= width {
+                if unsafe { scaled.classify(integral, x, y) } {
+                    detections.push(Rect::at(x as i32, y as i32).of_size(scaled.window.0, scaled.window.1));
+                }
+                x += step;
+            }
+            y += step;
+        }
+        scale *= options.scale_factor;
+    }
+
+    Ok(detections)
+}
+
+/// Width and height of the image that `integral` was computed from.
+fn image_size(integral: &Image<Luma<u32>>) -> (u32, u32) {
+    (integral.width().saturating_sub(1), integral.height().saturating_sub(1))
+}
+
+/// A cascade whose features have been scaled for evaluation at a single scale.
+struct ScaledCascade<'a> {
+    cascade: &'a Cascade,
+    /// Scaled features, in the order in which they appear in the cascade.
+    features: Vec<ScaledHaarFeature>,
+    /// Scaled window size.
+    window: (u32, u32),
+    /// Width and height of the region read when classifying a window. Rounding
+    /// may cause scaled features to extend slightly beyond the scaled window.
+    extent: (u32, u32),
+}
+
+impl<'a> ScaledCascade<'a> {
+    fn new(cascade: &'a Cascade, scale: f32) -> ScaledCascade<'a> {
+        let window = (
+            ((cascade.window_width as f32 * scale).round() as u32).max(1),
+            ((cascade.window_height as f32 * scale).round() as u32).max(1),
+        );
+        let features: Vec<ScaledHaarFeature> = cascade
+            .stages
+            .iter()
+            .flat_map(|s| s.classifiers.iter().map(|c| ScaledHaarFeature::new(&c.feature, scale)))
+            .collect();
+        let extent = features.iter().fold(window, |(w, h), f| {
+            let (right, bottom) = f.extent();
+            (w.max(right), h.max(bottom))
+        });
+        ScaledCascade { cascade, features, window, extent }
+    }
+
+    /// Requires the extent of the cascade at (x, y) to lie within the image.
+    unsafe fn classify(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> bool {
+        let mut features = self.features.iter();
+        for stage in &self.cascade.stages {
+            let mut total = 0.0;
+            for (classifier, feature) in stage.classifiers.iter().zip(&mut features) {
+                total += classifier.classify(feature.evaluate_at_unchecked(integral, x, y));
+            }
+            if total < stage.threshold {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A Haar-like feature with each block scaled to the nearest whole number of pixels,
+/// created by [`HaarFeature::scale`](struct.HaarFeature.html#method.scale).
+///
+/// Values are multiplied by the ratio of unscaled to scaled block area, so that the value
+/// of a scaled feature on an image scaled by the same factor approximately equals the
+/// value of the original feature on the original image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScaledHaarFeature {
+    left: u32,
+    top: u32,
+    block_width: u32,
+    block_height: u32,
+    blocks_wide: u32,
+    blocks_high: u32,
+    positive: bool,
+    /// Ratio of unscaled to scaled block area.
+    normalisation: f32,
+}
+
+impl ScaledHaarFeature {
+    /// Requires `feature` not to be tilted.
+    pub(crate) fn new(feature: &HaarFeature, scale: f32) -> ScaledHaarFeature {
+        let scale_length = |v: u8| ((v as f32 * scale).round() as u32).max(1);
+        let (block_width, block_height) = (scale_length(feature.block_width()), scale_length(feature.block_height()));
+        ScaledHaarFeature {
+            left: (feature.left as f32 * scale).round() as u32,
+            top: (feature.top as f32 * scale).round() as u32,
+            block_width,
+            block_height,
+            blocks_wide: feature.blocks_wide() as u32,
+            blocks_high: feature.blocks_high() as u32,
+            positive: feature.sign == Sign::Positive,
+            normalisation: (feature.block_width() as f32 * feature.block_height() as f32)
+                / (block_width * block_height) as f32,
+        }
+    }
+
+    /// Width and height of the region from the window origin to the
+    /// bottom right of this feature.
+    pub fn extent(&self) -> (u32, u32) {
+        (
+            self.left + self.blocks_wide * self.block_width,
+            self.top + self.blocks_high * self.block_height,
+        )
+    }
+
+    /// Evaluates the scaled feature in the window with top left corner (x, y).
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if the feature does not lie within
+    /// the image that `integral` was computed from.
+    pub fn evaluate_at(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> Result<f32> {
+        let (width, height) = integral.dimensions();
+        let (right, bottom) = self.extent();
+        if !offset_in_bounds(x, y, (right, bottom), (width, height)) {
+            return Err(ImageProcError::OutOfBounds {
+                region: Rect::at(x.saturating_add(self.left) as i32, y.saturating_add(self.top) as i32)
+                    .of_size(right - self.left, bottom - self.top),
+                image: (width.saturating_sub(1), height.saturating_sub(1)),
+            });
+        }
+        Ok(unsafe { self.evaluate_at_unchecked(integral, x, y) })
+    }
+
+    /// Evaluates the scaled feature in the window with top left corner (x, y),
+    /// without checking that the feature lies within the image.
+    ///
+    /// # Safety
+    /// `evaluate_at` must return `Ok` for this feature, integral image and window.
+    pub unsafe fn evaluate_at_unchecked(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> f32 {
+        let read = |x, y| integral.unsafe_get_pixel(x, y)[0] as i64;
+        let mut sum = 0i64;
+        for by in 0..self.blocks_high {
+            let top = y + self.top + by * self.block_height;
+            let bottom = top + self.block_height;
+            for bx in 0..self.blocks_wide {
+                let left = x + self.left + bx * self.block_width;
+                let right = left + self.block_width;
+                let block = read(right, bottom) - read(right, top) - read(left, bottom) + read(left, top);
+                if (bx + by) % 2 == 0 { sum += block } else { sum -= block }
+            }
+        }
+        let value = sum as f32 * self.normalisation;
+        if self.positive { value } else { -value }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{HaarFeatureType, Size};
+    use integral_image::integral_image;
+    use image::{GrayImage, Luma};
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn feature(feature_type: HaarFeatureType, block_size: (u8, u8), left: u8, top: u8) -> HaarFeature {
+        HaarFeature {
+            sign: Sign::Positive,
+            feature_type,
+            block_size: Size::new(block_size.0, block_size.1),
+            left,
+            top,
+            tilted: false,
+        }
+    }
+
+    /// Accepts windows whose left half is brighter than their right half.
+    fn left_bright_cascade() -> Cascade {
+        let classifier = WeakClassifier {
+            feature: feature(HaarFeatureType::TwoRegionHorizontal, (2, 4), 0, 0),
+            threshold: 100.0,
+            below: -1.0,
+            above: 1.0,
+        };
+        Cascade::new(4, 4, vec![Stage { classifiers: vec![classifier], threshold: 0.0 }]).unwrap()
+    }
+
+    #[test]
+    fn test_cascade_new_rejects_oversized_feature() {
+        let classifier = WeakClassifier {
+            feature: feature(HaarFeatureType::ThreeRegionVertical, (1, 2), 0, 0),
+            threshold: 0.0,
+            below: 0.0,
+            above: 0.0,
+        };
+        let stages = vec![Stage { classifiers: vec![classifier], threshold: 0.0 }];
+        assert!(Cascade::new(3, 6, stages.clone()).is_ok());
+        assert!(Cascade::new(3, 5, stages).is_err());
+        assert!(Cascade::new(0, 5, vec![]).is_err());
+
+        let tilted = WeakClassifier {
+            feature: HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0),
+            ..classifier
+        };
+        assert!(Cascade::new(3, 6, vec![Stage { classifiers: vec![tilted], threshold: 0.0 }]).is_err());
+    }
+
+    #[test]
+    fn test_scaled_feature_matches_unscaled() {
+        let image = gray_bench_image(12, 10);
+        let integral = integral_image(&image);
+        let f = feature(HaarFeatureType::FourRegion, (2, 3), 1, 2);
+        let scaled = ScaledHaarFeature::new(&f, 1.0);
+        assert_eq!(unsafe { scaled.evaluate_at_unchecked(&integral, 0, 0) }, f.evaluate(&integral).unwrap() as f32);
+    }
+
+    #[test]
+    fn test_scaled_feature_evaluate_at_out_of_bounds() {
+        let integral = integral_image(&gray_bench_image(12, 10));
+        let scaled = feature(HaarFeatureType::FourRegion, (2, 3), 1, 2).scale(1.0).unwrap();
+        assert!(scaled.evaluate_at(&integral, 7, 2).is_ok());
+        assert!(scaled.evaluate_at(&integral, 8, 2).is_err());
+        assert!(scaled.evaluate_at(&integral, u32::max_value() - 1, 0).is_err());
+        assert!(scaled.evaluate_at(&integral, 0, u32::max_value()).is_err());
+    }
+
+    #[test]
+    fn test_scaled_feature_normalisation() {
+        // Doubling the size of both image and feature leaves the normalised value unchanged.
+        let small = gray_image!(
+            9, 9, 1, 1;
+            9, 9, 1, 1);
+        let large = GrayImage::from_fn(8, 4, |x, y| *small.get_pixel(x / 2, y / 2));
+        let f = feature(HaarFeatureType::TwoRegionHorizontal, (2, 2), 0, 0);
+        let at_one = unsafe { ScaledHaarFeature::new(&f, 1.0).evaluate_at_unchecked(&integral_image(&small), 0, 0) };
+        let at_two = unsafe { ScaledHaarFeature::new(&f, 2.0).evaluate_at_unchecked(&integral_image(&large), 0, 0) };
+        assert_eq!(at_one, 32.0);
+        assert_eq!(at_two, 32.0);
+    }
+
+    #[test]
+    fn test_classify() {
+        let cascade = left_bright_cascade();
+        let image = GrayImage::from_fn(8, 4, |x, _| Luma([if x < 2 { 200 } else { 0 }]));
+        let integral = integral_image(&image);
+        assert_eq!(cascade.classify(&integral, 0, 0, 1.0), Ok(true));
+        assert_eq!(cascade.classify(&integral, 2, 0, 1.0), Ok(false));
+        assert!(cascade.classify(&integral, 5, 0, 1.0).is_err());
+        assert!(cascade.classify(&integral, u32::max_value(), 0, 1.0).is_err());
+        assert!(cascade.classify(&integral, 0, u32::max_value(), 1.0).is_err());
+    }
+
+    #[test]
+    fn test_detect_objects_multiple_scales() {
+        // A vertical edge at x = 4.
+        let image = GrayImage::from_fn(16, 8, |x, _| Luma([if x < 4 { 200 } else { 0 }]));
+        let integral = integral_image(&image);
+        let options = DetectionOptions { scale_factor: 2.0, step: 2.0, ..DetectionOptions::default() };
+        let detections = detect_objects(&integral, &left_bright_cascade(), options).unwrap();
+        // Windows are 2 pixels apart at scale 1, and 4 pixels apart at scale 2.
+        assert_eq!(
+            detections,
+            vec![
+                Rect::at(2, 0).of_size(4, 4),
+                Rect::at(2, 2).of_size(4, 4),
+                Rect::at(2, 4).of_size(4, 4),
+                Rect::at(0, 0).of_size(8, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_objects_invalid_options() {
+        let integral = integral_image(&GrayImage::new(4, 4));
+        let options = DetectionOptions { initial_scale: 0.0, scale_factor: 1.0, step: 0.0 };
+        assert_eq!(
+            detect_objects(&integral, &left_bright_cascade(), options),
+            Err(ImageProcError::InvalidOptions(
+                "initial_scale must be positive, scale_factor must be greater than 1, \
+                 step must be positive".to_owned()
+            ))
+        );
+    }
+
+    #[bench]
+    fn bench_detect_objects(b: &mut Bencher) {
+        let integral = integral_image(&gray_bench_image(200, 200));
+        let cascade = left_bright_cascade();
+        b.iter(|| black_box(detect_objects(&integral, &cascade, DetectionOptions::default()).unwrap()));
+    }
+}
@@ -0,0 +1,1553 @@
+//! Functions for creating and evaluating [Haar-like features].
+//!
+//! [Haar-like features]: https://en.wikipedia.org/wiki/Haar-like_features
+//!
+//! Features can be combined into boosted cascades and scanned over an image
+//! to detect objects using [`detect_objects`](fn.detect_objects.html).
+
+use definitions::{HasBlack, HasWhite, Image};
+use error::{ImageProcError, Result};
+use integral_image::window_mean_and_variance;
+use rect::Rect;
+use simd;
+use image::{GenericImage, ImageBuffer, Luma, Primitive};
+use itertools::Itertools;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+mod batch;
+pub use self::batch::{HaarFeatureBatch, evaluate_all};
+
+mod cascade;
+pub use self::cascade::{
+    Cascade,
+    DetectionOptions,
+    ScaledHaarFeature,
+    Stage,
+    WeakClassifier,
+    detect_objects
+};
+
+pub mod opencv;
+pub mod train;
+
+/// A [Haar-like feature].
+///
+/// [Haar-like feature]: https://en.wikipedia.org/wiki/Haar-like_features
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawHaarFeature"))]
+pub struct HaarFeature {
+    sign: Sign,
+    feature_type: HaarFeatureType,
+    block_size: Size<Pixels>,
+    left: u8,
+    top: u8,
+    // Tilted features are rotated by 45 degrees and evaluated on rotated integral images.
+    tilted: bool,
+}
+
+// The fields of a serialized feature, which are checked by `HaarFeature::validate`
+// before a `HaarFeature` is deserialized.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawHaarFeature {
+    sign: Sign,
+    feature_type: HaarFeatureType,
+    block_size: Size<Pixels>,
+    left: u8,
+    top: u8,
+    tilted: bool,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawHaarFeature> for HaarFeature {
+    type Error = ImageProcError;
+
+    fn try_from(raw: RawHaarFeature) -> Result<HaarFeature> {
+        HaarFeature {
+            sign: raw.sign,
+            feature_type: raw.feature_type,
+            block_size: raw.block_size,
+            left: raw.left,
+            top: raw.top,
+            tilted: raw.tilted,
+        }.validate()
+    }
+}
+
+/// Whether the top left region in a Haar-like feature is counted
+/// with positive or negative sign.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Sign {
+    /// Top left region is counted with a positive sign.
+    Positive,
+    /// Top left region is counted with a negative sign.
+    Negative
+}
+
+/// The type of a Haar-like feature determines the number of regions it contains and their orientation.
+/// The diagrams in the comments for each variant use the symbols (*, &) to represent either
+/// (+, -) or (-, +), depending on which `Sign` the feature type is used with.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HaarFeatureType {
+    /// Two horizontally-adjacent regions of equal width.
+    /// <pre>
+    ///      -----------
+    ///     |  *  |  &  |
+    ///      -----------
+    /// </pre>
+    TwoRegionHorizontal,
+    /// Three horizontally-adjacent regions of equal width.
+    /// <pre>
+    ///      -----------------
+    ///     |  *  |  &  |  *  |
+    ///      -----------------
+    /// </pre>
+    ThreeRegionHorizontal,
+    /// Two vertically-adjacent regions of equal height.
+    /// <pre>
+    ///      -----
+    ///     |  *  |
+    ///      -----
+    ///     |  &  |
+    ///      -----
+    /// </pre>
+    TwoRegionVertical,
+    /// Three vertically-adjacent regions of equal height.
+    /// <pre>
+    ///      -----
+    ///     |  *  |
+    ///      -----
+    ///     |  &  |
+    ///      -----
+    ///     |  *  |
+    ///      -----
+    /// </pre>
+    ThreeRegionVertical,
+    /// Four regions arranged in a two-by-two grid. The two columns
+    /// have equal width and the two rows have equal height.
+    /// <pre>
+    ///      -----------
+    ///     |  *  |  &  | 
+    ///      -----------
+    ///     |  &  |  *  |
+    ///      -----------
+    /// </pre>
+    FourRegion
+}
+
+impl HaarFeatureType {
+    // The width and height of Haar-like feature, in blocks.
+    fn shape(&self) -> Size<Blocks> {
+        match *self {
+            HaarFeatureType::TwoRegionHorizontal => Size::new(2, 1),
+            HaarFeatureType::ThreeRegionHorizontal => Size::new(3, 1),
+            HaarFeatureType::TwoRegionVertical => Size::new(1, 2),
+            HaarFeatureType::ThreeRegionVertical => Size::new(1, 3),
+            HaarFeatureType::FourRegion => Size::new(2, 2)
+        }
+    }
+}
+
+impl HaarFeature {
+    /// Returns an iterator over every valid Haar-like feature for an image with the given
+    /// width and height, i.e. every feature of each type, block size, position and sign
+    /// that lies wholly within the image.
+    ///
+    /// The number of features yielded is
+    /// [`number_of_haar_features(frame_width, frame_height)`](fn.number_of_haar_features.html).
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::haar::{number_of_haar_features, HaarFeature, HaarFeatureType};
+    ///
+    /// assert_eq!(HaarFeature::enumerate(24, 24).count() as u32, number_of_haar_features(24, 24));
+    ///
+    /// // Training only needs one sign of each feature.
+    /// let four_region = HaarFeature::enumerate(4, 4)
+    ///     .filter(|f| f.is_positive() && f.feature_type() == HaarFeatureType::FourRegion)
+    ///     .count();
+    /// assert_eq!(four_region, 9 + 3 + 3 + 1);
+    /// ```
+    pub fn enumerate(frame_width: u8, frame_height: u8) -> impl Iterator<Item = HaarFeature> {
+        let frame_size = Size::new(frame_width, frame_height);
+        FEATURE_TYPES.iter().cloned().flat_map(move |feature_type| {
+            block_sizes(feature_type.shape(), frame_size).into_iter().flat_map(move |block_size| {
+                let size = feature_size(feature_type, block_size);
+                feature_positions(size, frame_size).into_iter().flat_map(move |(left, top)| {
+                    [Sign::Positive, Sign::Negative]
+                        .iter()
+                        .map(move |&sign| HaarFeature { sign, feature_type, block_size, left, top, tilted: false })
+                })
+            })
+        })
+    }
+
+    /// Returns an upright feature of the given type whose blocks are `block_width` by
+    /// `block_height` pixels, with top left corner (`left`, `top`). The top left region of
+    /// the feature is counted with a positive sign.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if either block dimension is zero, or the
+    /// feature does not fit in a 255x255 window.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::{HaarFeature, HaarFeatureType};
+    /// use imageproc::integral_image::integral_image;
+    ///
+    /// let image = gray_image!(
+    ///     1, 2, 3;
+    ///     4, 5, 6);
+    /// let integral = integral_image(&image);
+    ///
+    /// let feature = HaarFeature::try_new(HaarFeatureType::TwoRegionVertical, 2, 1, 1, 0).unwrap();
+    /// assert_eq!(feature.evaluate(&integral), Ok((2 + 3) - (5 + 6)));
+    ///
+    /// assert!(HaarFeature::try_new(HaarFeatureType::TwoRegionVertical, 0, 1, 1, 0).is_err());
+    /// assert!(HaarFeature::try_new(HaarFeatureType::ThreeRegionHorizontal, 90, 1, 0, 0).is_err());
+    /// # }
+    /// ```
+    pub fn try_new(
+        feature_type: HaarFeatureType,
+        block_width: u8,
+        block_height: u8,
+        left: u8,
+        top: u8,
+    ) -> Result<HaarFeature> {
+        let feature = HaarFeature {
+            sign: Sign::Positive,
+            feature_type,
+            block_size: Size::new(block_width, block_height),
+            left,
+            top,
+            tilted: false,
+        };
+        feature.validate()
+    }
+
+    /// Returns a feature of the given type rotated clockwise by 45 degrees, as in the extended
+    /// set of Haar-like features introduced by [Lienhart and Maydt]. The top left region of the
+    /// feature is counted with a positive sign.
+    ///
+    /// Rows of blocks run down and to the right from the top corner of the feature, and columns of
+    /// blocks run down and to the left. Each block is a square of `block_width` by `block_height`
+    /// diagonal steps, containing `2 * block_width * block_height` pixels. If the feature is `w`
+    /// steps wide and `h` steps high in total then it is read from a square of side `w + h`
+    /// with top left corner (`left`, `top`).
+    ///
+    /// Tilted features must be evaluated on a [rotated integral image].
+    ///
+    /// [Lienhart and Maydt]: http://www.lienhart.de/Prof._Dr._Rainer_Lienhart/Source_Code_files/ICIP2002.pdf
+    /// [rotated integral image]: ../integral_image/fn.rotated_integral_image.html
+    ///
+    /// # Panics
+    /// If either block dimension is zero, or the feature does not fit in a 255x255 window.
+    /// See [`try_tilted`](#method.try_tilted) for a version that returns an error instead.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::{HaarFeature, HaarFeatureType};
+    /// use imageproc::integral_image::rotated_integral_image;
+    ///
+    /// let image = gray_image!(
+    ///     1, 2, 3;
+    ///     4, 5, 6;
+    ///     7, 8, 9);
+    /// let integral = rotated_integral_image(&image);
+    ///
+    /// // The top block contains pixels 1 and 4, and the block below and
+    /// // to its right contains pixels 5 and 8.
+    /// let feature = HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0);
+    /// assert_eq!(feature.evaluate(&integral), Ok((1 + 4) - (5 + 8)));
+    /// # }
+    /// ```
+    pub fn tilted(feature_type: HaarFeatureType, block_width: u8, block_height: u8, left: u8, top: u8) -> HaarFeature {
+        match HaarFeature::try_tilted(feature_type, block_width, block_height, left, top) {
+            Ok(feature) => feature,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Returns a tilted feature as for [`tilted`](#method.tilted), or
+    /// `ImageProcError::InvalidOptions` if either block dimension is zero or
+    /// the feature does not fit in a 255x255 window.
+    pub fn try_tilted(
+        feature_type: HaarFeatureType,
+        block_width: u8,
+        block_height: u8,
+        left: u8,
+        top: u8,
+    ) -> Result<HaarFeature> {
+        let feature = HaarFeature {
+            sign: Sign::Positive,
+            feature_type,
+            block_size: Size::new(block_width, block_height),
+            left,
+            top,
+            tilted: true,
+        };
+        feature.validate()
+    }
+
+    // Returns self if its blocks are non-empty and it fits in a 255x255 window.
+    fn validate(self) -> Result<HaarFeature> {
+        if self.block_width() == 0 || self.block_height() == 0 {
+            return Err(ImageProcError::InvalidOptions("block size must be non-zero".to_owned()));
+        }
+        let (right, bottom) = self.extent();
+        if right > 255 || bottom > 255 {
+            return Err(ImageProcError::InvalidOptions(
+                format!("feature {:?} does not fit in a 255x255 window", self)
+            ));
+        }
+        Ok(self)
+    }
+
+    /// True if this feature is rotated by 45 degrees.
+    pub fn is_tilted(&self) -> bool {
+        self.tilted
+    }
+
+    /// The type of this feature.
+    pub fn feature_type(&self) -> HaarFeatureType {
+        self.feature_type
+    }
+
+    /// True if the top left region of this feature is counted with a positive sign.
+    pub fn is_positive(&self) -> bool {
+        self.sign == Sign::Positive
+    }
+
+    /// Scales this feature by `factor`, rounding the position and the size of each block
+    /// to the nearest whole number of pixels. Blocks are at least one pixel in size.
+    ///
+    /// The value of the scaled feature is normalised by the ratio of unscaled to scaled
+    /// block area, so a feature scaled by `s` gives approximately the same value on an
+    /// image scaled by `s` as the original feature does on the original image. This allows
+    /// detection at multiple scales without building an image pyramid.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if `factor` is not positive and finite
+    /// or the feature is tilted.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::HaarFeature;
+    /// use imageproc::integral_image::integral_image;
+    ///
+    /// let image = gray_image!(9, 1);
+    /// let doubled = gray_image!(
+    ///     9, 9, 1, 1;
+    ///     9, 9, 1, 1);
+    ///
+    /// // The left pixel minus the right pixel, in a 2x1 window.
+    /// let feature = HaarFeature::enumerate(2, 1).next().unwrap();
+    /// let scaled = feature.scale(2.0).unwrap();
+    ///
+    /// assert_eq!(scaled.extent(), (4, 2));
+    /// assert_eq!(feature.evaluate(&integral_image(&image)), Ok(8));
+    /// assert_eq!(scaled.evaluate_at(&integral_image(&doubled), 0, 0), Ok(8.0));
+    /// # }
+    /// ```
+    pub fn scale(&self, factor: f32) -> Result<ScaledHaarFeature> {
+        if !(factor > 0.0 && factor.is_finite()) {
+            return Err(ImageProcError::InvalidOptions(
+                format!("scale factor must be positive and finite, got {}", factor)
+            ));
+        }
+        if self.tilted {
+            return Err(ImageProcError::InvalidOptions(
+                "scale does not support tilted features".to_owned()
+            ));
+        }
+        Ok(ScaledHaarFeature::new(self, factor))
+    }
+
+    /// Evaluates the Haar-like feature on an integral image, or on a rotated
+    /// integral image if the feature is tilted.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if the feature does not lie within
+    /// the image that `integral` was computed from.
+    pub fn evaluate(&self, integral: &Image<Luma<u32>>) -> Result<i32> {
+        self.evaluate_at(integral, 0, 0)
+    }
+
+    /// Evaluates the Haar-like feature on an integral image, without checking
+    /// that the feature lies within the image.
+    ///
+    /// # Safety
+    /// The feature must lie within the image that `integral` was computed from,
+    /// i.e. `evaluate` must return `Ok` for this feature and integral image.
+    pub unsafe fn evaluate_unchecked(&self, integral: &Image<Luma<u32>>) -> i32 {
+        self.evaluate_at_unchecked(integral, 0, 0)
+    }
+
+    /// Evaluates the Haar-like feature in the window with top left corner (x, y), i.e. with
+    /// the feature's position measured from (x, y) rather than from the image origin. This
+    /// allows a single feature to be evaluated at every position of a sliding window.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if the feature does not lie within
+    /// the image that `integral` was computed from.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::HaarFeature;
+    /// use imageproc::integral_image::integral_image;
+    ///
+    /// let image = gray_image!(
+    ///     1, 2, 3, 4;
+    ///     5, 6, 7, 8);
+    /// let integral = integral_image(&image);
+    ///
+    /// // The feature in a 2x1 window computing the left pixel minus the right pixel.
+    /// let feature = HaarFeature::enumerate(2, 1).next().unwrap();
+    ///
+    /// assert_eq!(feature.evaluate_at(&integral, 0, 0), Ok(1 - 2));
+    /// assert_eq!(feature.evaluate_at(&integral, 2, 1), Ok(7 - 8));
+    /// assert!(feature.evaluate_at(&integral, 3, 1).is_err());
+    /// # }
+    /// ```
+    pub fn evaluate_at(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> Result<i32> {
+        let (width, height) = integral.dimensions();
+        let (right, bottom) = self.extent();
+        if !offset_in_bounds(x, y, (right, bottom), (width, height)) {
+            return Err(ImageProcError::OutOfBounds {
+                region: Rect::at(x.saturating_add(self.left as u32) as i32, y.saturating_add(self.top as u32) as i32)
+                    .of_size(right - self.left as u32, bottom - self.top as u32),
+                image: (width.saturating_sub(1), height.saturating_sub(1)),
+            });
+        }
+        Ok(unsafe { self.evaluate_at_unchecked(integral, x, y) })
+    }
+
+    /// Evaluates the Haar-like feature in the windows with top left corners `(x, y)` for
+    /// `x` in `0..values.len()`, writing the value in each window to `values`. This gives the
+    /// same values as calling [`evaluate_at`](#method.evaluate_at) for each window, but
+    /// evaluates several windows at once using vectorised instructions where available.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if the feature does not lie within the image
+    /// that `integral` was computed from in the rightmost window, and leaves `values` unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::HaarFeature;
+    /// use imageproc::integral_image::integral_image;
+    ///
+    /// let image = gray_image!(
+    ///     1, 2, 4, 8;
+    ///     5, 6, 7, 8);
+    /// let integral = integral_image(&image);
+    ///
+    /// // The feature in a 2x1 window computing the left pixel minus the right pixel.
+    /// let feature = HaarFeature::enumerate(2, 1).next().unwrap();
+    ///
+    /// let mut values = [0; 3];
+    /// feature.evaluate_row(&integral, 0, &mut values).unwrap();
+    /// assert_eq!(values, [1 - 2, 2 - 4, 4 - 8]);
+    ///
+    /// assert!(feature.evaluate_row(&integral, 0, &mut [0; 4]).is_err());
+    /// # }
+    /// ```
+    pub fn evaluate_row(&self, integral: &Image<Luma<u32>>, y: u32, values: &mut [i32]) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        // The feature lies within the image in every window if it does in the rightmost.
+        self.evaluate_at(integral, values.len() as u32 - 1, y)?;
+
+        let stride = integral.width() as usize;
+        let raw: &[u32] = integral;
+        let reads = batch::reads(self);
+        let rows: Vec<&[u32]> = reads
+            .iter()
+            .map(|&(dx, dy, _)| {
+                let start = (y + dy) as usize * stride + dx as usize;
+                &raw[start..start + values.len()]
+            })
+            .collect();
+        let weights: Vec<i32> = reads.iter().map(|r| r.2).collect();
+
+        let n = simd::weighted_sum_u32_i32(&rows, &weights, values);
+        for (x, value) in values.iter_mut().enumerate().skip(n) {
+            *value = rows.iter().zip(&weights).map(|(row, &w)| w * row[x] as i32).sum();
+        }
+        Ok(())
+    }
+
+    /// Evaluates the Haar-like feature in `window` after normalising the window's intensities
+    /// to have mean 0 and standard deviation 1, so that the result is unaffected by changes
+    /// in brightness and contrast. Windows of constant intensity have value 0.
+    ///
+    /// The feature's position is measured from the top left of `window`, and `integral` and
+    /// `integral_squared` are the integral images of the intensities of the image and of their
+    /// squares. The sums in `integral_squared` may have any type, e.g. `u64` or `f64` to avoid
+    /// overflow for large images.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if `window` does not lie within the image,
+    /// `ImageProcError::DimensionMismatch` if the integral images have different sizes, and
+    /// `ImageProcError::InvalidOptions` if the feature is tilted or does not fit in `window`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::HaarFeature;
+    /// use imageproc::integral_image::{integral_image, integral_squared_image};
+    /// use imageproc::rect::Rect;
+    ///
+    /// let image = gray_image!(
+    ///     10, 20, 100, 140);
+    ///
+    /// // The left pixel minus the right pixel, in a 2x1 window.
+    /// let feature = HaarFeature::enumerate(2, 1).next().unwrap();
+    /// let normalized = |x| feature.evaluate_normalized(
+    ///     &integral_image(&image),
+    ///     &integral_squared_image(&image),
+    ///     Rect::at(x, 0).of_size(2, 1)
+    /// );
+    ///
+    /// // The two windows differ in brightness and contrast, but have the same normalised value.
+    /// assert_eq!(normalized(0), Ok(-2.0));
+    /// assert_eq!(normalized(2), Ok(-2.0));
+    /// # }
+    /// ```
+    pub fn evaluate_normalized<T>(
+        &self,
+        integral: &Image<Luma<u32>>,
+        integral_squared: &Image<Luma<T>>,
+        window: Rect,
+    ) -> Result<f32>
+    where
+        T: Primitive + 'static,
+    {
+        let (mean, variance) = window_mean_and_variance(integral, integral_squared, window)?;
+        if self.tilted {
+            return Err(ImageProcError::InvalidOptions(
+                "evaluate_normalized does not support tilted features".to_owned()
+            ));
+        }
+        let (right, bottom) = self.extent();
+        if right > window.width() || bottom > window.height() {
+            return Err(ImageProcError::InvalidOptions(format!(
+                "feature {:?} does not fit in a {}x{} window",
+                self, window.width(), window.height()
+            )));
+        }
+
+        let value = unsafe { self.evaluate_at_unchecked(integral, window.left() as u32, window.top() as u32) };
+        // Subtracting the mean from every pixel changes the value of the feature by the mean
+        // times the difference between the areas of its positive and negative regions.
+        let std_dev = variance.sqrt();
+        let centered = value as f64 - mean * self.signed_area() as f64;
+        Ok(if std_dev > 0.0 { (centered / std_dev) as f32 } else { 0.0 })
+    }
+
+    /// The area of the positive regions of this feature minus the area of its negative regions.
+    fn signed_area(&self) -> i32 {
+        // Blocks alternate in sign, so the excess is a single block if there are an odd number of them.
+        let excess = (self.blocks_wide() as i32 * self.blocks_high() as i32) % 2;
+        let area = excess * self.block_width() as i32 * self.block_height() as i32;
+        if self.sign == Sign::Positive { area } else { -area }
+    }
+
+    /// Evaluates the Haar-like feature in the window with top left corner (x, y),
+    /// without checking that the feature lies within the image.
+    ///
+    /// # Safety
+    /// The feature must lie within the image that `integral` was computed from,
+    /// i.e. `evaluate_at` must return `Ok` for this feature, integral image and window.
+    pub unsafe fn evaluate_at_unchecked(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> i32 {
+        if self.tilted {
+            return self.evaluate_tilted_at_unchecked(integral, x, y);
+        }
+        // The corners of each block are lettered. Not all letters are evaluated for each feature type.
+        // A   B   C   D
+        //
+        // E   F   G   H
+        //
+        // I   J   K   L
+        //
+        // M   N   O
+        //
+        // Each row of corners is read from a single row of the integral image, starting at
+        // the left column of the feature.
+        let stride = integral.width() as usize;
+        let (block_width, block_height) = (self.block_width() as usize, self.block_height() as usize);
+        let left = x as usize + self.left as usize;
+        let raw: &[u32] = integral;
+        let corners = |r: usize| {
+            let start = (y as usize + self.top as usize + r * block_height) * stride + left;
+            raw.get_unchecked(start..)
+        };
+        let at = |row: &[u32], c: usize| *row.get_unchecked(c * block_width) as i32;
+
+        let sum = match self.feature_type {
+            HaarFeatureType::TwoRegionHorizontal => {
+                let (r0, r1) = (corners(0), corners(1));
+
+                at(r0, 0) - 2 * at(r0, 1) + at(r0, 2)
+                    - at(r1, 0) + 2 * at(r1, 1) - at(r1, 2)
+            },
+
+            HaarFeatureType::ThreeRegionHorizontal => {
+                let (r0, r1) = (corners(0), corners(1));
+
+                at(r0, 0) - 2 * at(r0, 1) + 2 * at(r0, 2) - at(r0, 3)
+                    - at(r1, 0) + 2 * at(r1, 1) - 2 * at(r1, 2) + at(r1, 3)
+            },
+
+            HaarFeatureType::TwoRegionVertical => {
+                let (r0, r1, r2) = (corners(0), corners(1), corners(2));
+
+                at(r0, 0) - at(r0, 1)
+                    - 2 * at(r1, 0) + 2 * at(r1, 1)
+                    + at(r2, 0) - at(r2, 1)
+            },
+
+            HaarFeatureType::ThreeRegionVertical => {
+                let (r0, r1, r2, r3) = (corners(0), corners(1), corners(2), corners(3));
+
+                at(r0, 0) - at(r0, 1)
+                    - 2 * at(r1, 0) + 2 * at(r1, 1)
+                    + 2 * at(r2, 0) - 2 * at(r2, 1)
+                    - at(r3, 0) + at(r3, 1)
+            },
+
+            HaarFeatureType::FourRegion => {
+                let (r0, r1, r2) = (corners(0), corners(1), corners(2));
+
+                at(r0, 0) - 2 * at(r0, 1) + at(r0, 2)
+                    - 2 * at(r1, 0) + 4 * at(r1, 1) - 2 * at(r1, 2)
+                    + at(r2, 0) - 2 * at(r2, 1) + at(r2, 2)
+            }
+        };
+
+        let mul = if self.sign == Sign::Positive { 1i32 } else { -1i32 };
+        sum * mul
+    }
+
+    /// Evaluates a tilted feature on a rotated integral image. Requires the
+    /// feature to lie within the image.
+    unsafe fn evaluate_tilted_at_unchecked(&self, integral: &Image<Luma<u32>>, x: u32, y: u32) -> i32 {
+        let (width, height) = (self.block_width() as u32, self.block_height() as u32);
+        let (top_x, top_y) = self.top_corner();
+        let stride = integral.width() as usize;
+        let raw: &[u32] = integral;
+        let read = |x: u32, y: u32| *raw.get_unchecked(y as usize * stride + x as usize) as i32;
+
+        let mut sum = 0;
+        for h in 0..self.blocks_high() as u32 {
+            for w in 0..self.blocks_wide() as u32 {
+                // The top corner of block (w, h), as an index into the rotated integral image.
+                let bx = x + top_x + w * width - h * height;
+                let by = y + top_y + w * width + h * height;
+                let block = read(bx, by)
+                    - read(bx - height, by + height)
+                    - read(bx + width, by + width)
+                    + read(bx + width - height, by + width + height);
+                if (w + h) % 2 == 0 { sum += block } else { sum -= block }
+            }
+        }
+
+        let mul = if self.sign == Sign::Positive { 1i32 } else { -1i32 };
+        sum * mul
+    }
+
+    /// The top corner of a tilted feature, as an index into a rotated integral image.
+    /// This is one pixel to the right of the top pixel of the feature.
+    fn top_corner(&self) -> (u32, u32) {
+        (self.left as u32 + self.blocks_high() as u32 * self.block_height() as u32, self.top as u32)
+    }
+
+    /// The coordinates one past the right and bottom of the region read when evaluating
+    /// this feature, relative to the integral image.
+    fn extent(&self) -> (u32, u32) {
+        let width = self.blocks_wide() as u32 * self.block_width() as u32;
+        let height = self.blocks_high() as u32 * self.block_height() as u32;
+        if self.tilted {
+            (self.left as u32 + width + height, self.top as u32 + width + height)
+        } else {
+            (self.left as u32 + width, self.top as u32 + height)
+        }
+    }
+
+    /// Width of this feature in blocks.
+    fn blocks_wide(&self) -> u8 {
+        self.feature_type.shape().width
+    }
+
+    /// Height of this feature in blocks.
+    fn blocks_high(&self) -> u8 {
+        self.feature_type.shape().height
+    }
+
+    /// Width of each block in pixels.
+    fn block_width(&self) -> u8 {
+        self.block_size.width
+    }
+
+    /// Height of each block in pixels.
+    fn block_height(&self) -> u8 {
+        self.block_size.height
+    }
+}
+
+/// True if (x + dx, y + dy) lies within an image of the given dimensions. Unlike comparing
+/// `x + dx` with `width` directly this cannot overflow, so it is safe to use before unchecked reads.
+fn offset_in_bounds(x: u32, y: u32, (dx, dy): (u32, u32), (width, height): (u32, u32)) -> bool {
+    match (x.checked_add(dx), y.checked_add(dy)) {
+        (Some(px), Some(py)) => px < width && py < height,
+        _ => false,
+    }
+}
+
+// The total width and height of a feature with the given type and block size.
+fn feature_size(feature_type: HaarFeatureType, block_size: Size<Pixels>) -> Size<Pixels> {
+    let shape = feature_type.shape();
+    Size::new(shape.width * block_size.width, shape.height * block_size.height)
+}
+
+/// Returns a vector of all valid Haar-like features for an image with given width and height.
+///
+/// See [`HaarFeature::enumerate`](struct.HaarFeature.html#method.enumerate) for a lazy version.
+pub fn enumerate_haar_features(frame_width: u8, frame_height: u8) -> Vec<HaarFeature> {
+    HaarFeature::enumerate(frame_width, frame_height).collect()
+}
+
+const FEATURE_TYPES: [HaarFeatureType; 5] = [
+    HaarFeatureType::TwoRegionHorizontal,
+    HaarFeatureType::ThreeRegionHorizontal,
+    HaarFeatureType::TwoRegionVertical,
+    HaarFeatureType::ThreeRegionVertical,
+    HaarFeatureType::FourRegion
+];
+
+// Indicates that a size size is measured in pixels, e.g. the width of an individual block within a Haar-like feature.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Pixels(u8);
+
+// Indicates that a size is measured in blocks, e.g. the width of a Haar-like feature in blocks.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Blocks(u8);
+
+// A Size, measured either in pixels (T = Pixels) or in blocks (T = Blocks)
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Size<T> {
+    width: u8,
+    height: u8,
+    units: PhantomData<T>
+}
+
+impl<T> Size<T> {
+    fn new(width: u8, height: u8) -> Size<T> {
+        Size { 
+            width: width,
+            height: height,
+            units: PhantomData
+        }
+    }
+}
+
+// Returns the valid block sizes for a feature of shape `feature_shape` in a frame of size `frame_size`.
+fn block_sizes(feature_shape: Size<Blocks>, frame_size: Size<Pixels>) -> Vec<Size<Pixels>> {
+    (1..frame_size.width / feature_shape.width + 1)
+        .cartesian_product(1..frame_size.height / feature_shape.height + 1)
+        .map(|(w, h)| Size::new(w, h))
+        .collect()
+}
+
+// Returns the start positions for an interval of length `inner` for which the
+// interval is wholly contained within an interval of length `outer`.
+fn start_positions(inner: u8, outer: u8) -> Range<u8> {
+    let upper = if inner > outer { 0 } else { outer - inner + 1};
+    0..upper
+}
+
+// Returns all valid (left, top) coordinates for a feature of the given total size
+fn feature_positions(feature_size: Size<Pixels>, frame_size: Size<Pixels>) -> Vec<(u8, u8)> {
+    start_positions(feature_size.width, frame_size.width)
+        .cartesian_product(start_positions(feature_size.height, frame_size.height))
+        .collect()
+}
+
+/// Returns the number of distinct Haar-like features for an image of the given dimensions.
+/// 
+/// Includes positive and negative, two and three region, vertical and horizontal features,
+/// as well as positive and negative four region features.
+///
+/// Consider a `k`-region horizontal feature in an image of height `1` and width `w`. The largest valid block size
+/// for such a feature is `M = floor(w / k)`, and for a block size `s` there are `(w + 1) - 2 * s`
+/// valid locations for the leftmost column of this feature.
+/// Summing over `s` gives `M * (w + 1) - k * [(M * (M + 1)) / 2]`.
+/// 
+/// An equivalent argument applies vertically.
+pub fn number_of_haar_features(width: u32, height: u32) -> u32 {
+    let num_positive_features = 
+        // Two-region horizontal
+        num_features(width, 2) * num_features(height, 1)
+        // Three-region horizontal
+        + num_features(width, 3) * num_features(height, 1)
+        // Two-region vertical
+        + num_features(width, 1) * num_features(height, 2)
+        // Three-region vertical
+        + num_features(width, 1) * num_features(height, 3)
+        // Four-region
+        + num_features(width, 2) * num_features(height, 2);
+
+    num_positive_features * 2
+}
+
+fn num_features(image_side: u32, num_blocks: u32) -> u32 {
+    let m = image_side / num_blocks;
+    m * (image_side + 1) - num_blocks * ((m * (m + 1)) / 2)
+}
+
+/// Draws the given Haar-like feature on an image, drawing pixels
+/// with a positive sign white and those with a negative sign black.
+///
+/// # Panics
+/// If the feature does not lie within the image. See
+/// [`try_draw_haar_feature`](fn.try_draw_haar_feature.html) for a version that
+/// returns an error instead.
+pub fn draw_haar_feature<I>(image: &I, feature: HaarFeature) -> Image<I::Pixel>
+    where I: GenericImage,
+          I::Pixel: HasBlack + HasWhite + 'static
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    draw_haar_feature_mut(&mut out, feature);
+    out
+}
+
+/// Draws the given Haar-like feature on an image, as for
+/// [`draw_haar_feature`](fn.draw_haar_feature.html).
+///
+/// Returns `ImageProcError::OutOfBounds` if the feature does not lie within the image.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::GrayImage;
+/// use imageproc::haar::{try_draw_haar_feature, HaarFeature, HaarFeatureType};
+///
+/// let feature = HaarFeature::try_new(HaarFeatureType::TwoRegionHorizontal, 1, 2, 1, 0).unwrap();
+///
+/// let drawn = try_draw_haar_feature(&GrayImage::new(3, 2), feature).unwrap();
+/// assert_pixels_eq!(drawn, gray_image!(
+///     0, 255, 0;
+///     0, 255, 0));
+///
+/// assert!(try_draw_haar_feature(&GrayImage::new(2, 2), feature).is_err());
+/// # }
+/// ```
+pub fn try_draw_haar_feature<I>(image: &I, feature: HaarFeature) -> Result<Image<I::Pixel>>
+    where I: GenericImage,
+          I::Pixel: HasBlack + HasWhite + 'static
+{
+    check_feature_in_bounds(feature, image.dimensions())?;
+    Ok(draw_haar_feature(image, feature))
+}
+
+/// Draws the given Haar-like feature on an image in place, drawing pixels
+/// with a positive sign white and those with a negative sign black.
+///
+/// # Panics
+/// If the feature does not lie within the image. See
+/// [`try_draw_haar_feature_mut`](fn.try_draw_haar_feature_mut.html) for a version
+/// that returns an error instead.
+pub fn draw_haar_feature_mut<I>(image: &mut I, feature: HaarFeature)
+    where I: GenericImage,
+          I::Pixel: HasBlack + HasWhite
+{
+    let parity_shift = if feature.sign == Sign::Positive { 0 } else { 1 };
+
+    if feature.tilted {
+        draw_tilted_haar_feature_mut(image, feature, parity_shift as u32);
+        return;
+    }
+
+    for w in 0..feature.blocks_wide() {
+        for h in 0..feature.blocks_high() {
+            let parity = (w + h + parity_shift) % 2;
+            let color = if parity == 0 { I::Pixel::white() } else { I::Pixel::black() };
+            for x in 0..feature.block_width() {
+                for y in 0..feature.block_height() {
+                    let px = feature.left + w * feature.block_width() + x;
+                    let py = feature.top + h * feature.block_height() + y;
+                    image.put_pixel(px as u32, py as u32, color);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the given Haar-like feature on an image in place, as for
+/// [`draw_haar_feature_mut`](fn.draw_haar_feature_mut.html).
+///
+/// Returns `ImageProcError::OutOfBounds`, leaving the image unchanged, if the feature
+/// does not lie within the image.
+pub fn try_draw_haar_feature_mut<I>(image: &mut I, feature: HaarFeature) -> Result<()>
+    where I: GenericImage,
+          I::Pixel: HasBlack + HasWhite
+{
+    check_feature_in_bounds(feature, image.dimensions())?;
+    draw_haar_feature_mut(image, feature);
+    Ok(())
+}
+
+fn check_feature_in_bounds(feature: HaarFeature, image: (u32, u32)) -> Result<()> {
+    let (right, bottom) = feature.extent();
+    if right > image.0 || bottom > image.1 {
+        let (left, top) = (feature.left as u32, feature.top as u32);
+        return Err(ImageProcError::OutOfBounds {
+            region: Rect::at(left as i32, top as i32).of_size(right - left, bottom - top),
+            image,
+        });
+    }
+    Ok(())
+}
+
+fn draw_tilted_haar_feature_mut<I>(image: &mut I, feature: HaarFeature, parity_shift: u32)
+    where I: GenericImage,
+          I::Pixel: HasBlack + HasWhite
+{
+    let (top_x, top_y) = feature.top_corner();
+    let (right, bottom) = feature.extent();
+    let (width, height) = (2 * feature.block_width() as i32, 2 * feature.block_height() as i32);
+    for py in feature.top as u32..bottom {
+        for px in feature.left as u32..right {
+            // Distances along the feature's rows and columns from its top pixel, in half diagonal steps.
+            let along = (px + py) as i32 + 1 - (top_x + top_y) as i32;
+            let down = (py as i32 - top_y as i32) - (px as i32 - top_x as i32 + 1);
+            if along < 0 || down < 0 {
+                continue;
+            }
+            let (w, h) = ((along / width) as u32, (down / height) as u32);
+            if w < feature.blocks_wide() as u32 && h < feature.blocks_high() as u32 {
+                let parity = (w + h + parity_shift) % 2;
+                let color = if parity == 0 { I::Pixel::white() } else { I::Pixel::black() };
+                image.put_pixel(px, py, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GenericImageView, GrayImage};
+    use integral_image::{integral_image, integral_squared_image, rotated_integral_image, sum_image_pixels};
+    use utils::gray_bench_image;
+    use test;
+
+    #[test]
+    fn test_block_sizes() {
+        assert_eq!(
+            block_sizes(HaarFeatureType::TwoRegionHorizontal.shape(), Size::new(1, 1)), 
+            vec![]);
+
+        assert_eq!(
+            block_sizes(HaarFeatureType::TwoRegionHorizontal.shape(), Size::new(2, 1)), 
+            vec![Size::new(1, 1)]);
+
+        assert_eq!(
+            block_sizes(HaarFeatureType::TwoRegionHorizontal.shape(), Size::new(5, 1)),
+            vec![Size::new(1, 1), Size::new(2, 1)]);
+
+        assert_eq!(
+            block_sizes(HaarFeatureType::TwoRegionVertical.shape(), Size::new(1, 2)),
+            vec![Size::new(1, 1)]);
+    }
+
+    #[test]
+    fn test_feature_positions() {
+        assert_eq!(feature_positions(Size::new(2, 3), Size::new(2, 2)), 
+            vec![]);
+
+        assert_eq!(feature_positions(Size::new(2, 2), Size::new(2, 2)), 
+            vec![(0, 0)]);
+
+        assert_eq!(feature_positions(Size::new(2, 2), Size::new(3, 2)), 
+            vec![(0, 0), (1, 0)]);
+
+        assert_eq!(feature_positions(Size::new(2, 2), Size::new(3, 3)), 
+            vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_number_of_haar_features() {
+        for h in 0..6 {
+            for w in 0..6 {
+                let features = enumerate_haar_features(w, h);
+                let actual = features.len() as u32;
+                let expected = number_of_haar_features(w as u32, h as u32);
+                assert_eq!(actual, expected, "w = {}, h = {}", w, h);
+            }
+        }
+    }
+
+    #[test]
+    fn test_enumerate_yields_distinct_features_within_frame() {
+        for h in 0..7 {
+            for w in 0..7 {
+                let features: Vec<HaarFeature> = HaarFeature::enumerate(w, h).collect();
+                assert_eq!(features.len() as u32, number_of_haar_features(w as u32, h as u32));
+                for (i, f) in features.iter().enumerate() {
+                    let (right, bottom) = f.extent();
+                    assert!(right <= w as u32 && bottom <= h as u32, "{:?} outside {}x{}", f, w, h);
+                    assert!(!features[i + 1..].contains(f), "{:?} repeated", f);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_two_region_horizontal() {
+        let image = gray_image!(
+            1u8,     2u8, 3u8,     4u8,     5u8;
+                 /***+++++++++*****---------***/
+            6u8, /**/7u8, 8u8,/**/ 9u8, 0u8;/**/
+            9u8, /**/8u8, 7u8,/**/ 6u8, 5u8;/**/
+            4u8, /**/3u8, 2u8,/**/ 1u8, 0u8;/**/
+                 /***+++++++++*****---------***/
+            6u8,     5u8, 4u8,     2u8, 1u8     );
+
+        let integral = integral_image(&image);
+        let feature = HaarFeature { 
+            sign: Sign::Positive,
+            feature_type: HaarFeatureType::TwoRegionHorizontal,
+            block_size: Size::new(2, 3),
+            left: 1,
+            top: 1,
+            tilted: false
+        };
+        assert_eq!(feature.evaluate(&integral).unwrap(), 14i32);
+    }
+
+    #[test]
+    fn test_three_region_vertical() {
+        let image = gray_image!(
+        /*****************/
+        /*-*/1u8, 2u8,/*-*/ 3u8, 4u8, 5u8;
+        /*****************/
+        /*+*/6u8, 7u8,/*+*/ 8u8, 9u8, 0u8;
+        /*****************/
+        /*-*/9u8, 8u8,/*-*/ 7u8, 6u8, 5u8;
+        /*****************/
+             4u8, 3u8,      2u8, 1u8, 0u8;
+             6u8, 5u8,      4u8, 2u8, 1u8);
+
+        let integral = integral_image(&image);
+        let feature = HaarFeature { 
+            sign: Sign::Negative,
+            feature_type: HaarFeatureType::ThreeRegionVertical,
+            block_size: Size::new(2, 1),
+            left: 0,
+            top: 0,
+            tilted: false
+        };
+        assert_eq!(feature.evaluate(&integral).unwrap(), -7i32);
+    }
+
+    #[test]
+    fn test_four_region() {
+        let image = gray_image!(
+            /*****************************/
+        1u8,/**/2u8, 3u8,/**/ 4u8, 5u8;/**/
+        6u8,/**/7u8, 8u8,/**/ 9u8, 0u8;/**/ 
+            /*****************************/
+        9u8,/**/8u8, 7u8,/**/ 6u8, 5u8;/**/
+        4u8,/**/3u8, 2u8,/**/ 1u8, 0u8;/**/
+            /*****************************/
+        6u8,    5u8, 4u8,     2u8, 1u8);
+
+        let integral = integral_image(&image);
+        let feature = HaarFeature { 
+            sign: Sign::Positive,
+            feature_type: HaarFeatureType::FourRegion,
+            block_size: Size::new(2, 2),
+            left: 1,
+            top: 0,
+            tilted: false
+        };
+
+        assert_eq!(feature.evaluate(&integral).unwrap(), -6i32);
+    }
+
+    // Reference implementation of Haar-like feature evaluation, to validate faster implementations against.
+    fn reference_evaluate(feature: HaarFeature, integral: &Image<Luma<u32>>) -> i32 {
+        let parity_shift = if feature.sign == Sign::Positive { 0 } else { 1 };
+
+        let mut sum = 0i32;
+
+        for w in 0..feature.blocks_wide() {
+            let left = feature.left + feature.block_width() * w;
+            let right = left + feature.block_width() - 1;
+
+            for h in 0..feature.blocks_high() {
+                let top = feature.top + feature.block_height() * h;
+                let bottom = top + feature.block_height() - 1;
+                let parity = (w + h + parity_shift) & 1;
+                let multiplier = 1 - 2 * (parity as i32);
+
+                let block_sum = sum_image_pixels(integral, left as u32, top as u32, right as u32, bottom as u32) as i32;
+                sum += multiplier * block_sum;
+            }
+        }
+
+        sum
+    }
+
+    #[test]
+    fn test_evaluate_out_of_bounds() {
+        let integral = integral_image(&gray_bench_image(4, 3));
+        let feature = HaarFeature {
+            sign: Sign::Positive,
+            feature_type: HaarFeatureType::ThreeRegionHorizontal,
+            block_size: Size::new(1, 2),
+            left: 2,
+            top: 1,
+            tilted: false
+        };
+        assert_eq!(
+            feature.evaluate(&integral),
+            Err(ImageProcError::OutOfBounds {
+                region: Rect::at(2, 1).of_size(3, 2),
+                image: (4, 3)
+            })
+        );
+    }
+
+    #[test]
+    fn test_haar_evaluate_against_reference_implementation() {
+        for w in 0..6 {
+            for h in 0..6 {
+                let features = enumerate_haar_features(w, h);
+                let image = gray_bench_image(w as u32, h as u32);
+                let integral = integral_image(&image);
+
+                for feature in features {
+                    let actual = feature.evaluate(&integral).unwrap();
+                    let expected = reference_evaluate(feature, &integral);
+                    assert_eq!(actual, expected, "w = {}, h = {}", w, h);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_draw_haar_feature_two_region_horizontal() {
+        let image = gray_image!(
+            1u8,     2u8, 3u8,     4u8, 5u8;
+                 /***+++++++++*****---------***/
+            6u8, /**/7u8, 8u8,/**/ 9u8, 0u8;/**/
+            9u8, /**/8u8, 7u8,/**/ 6u8, 5u8;/**/
+            4u8, /**/3u8, 2u8,/**/ 1u8, 0u8;/**/
+                 /***+++++++++*****---------***/
+            6u8,     5u8, 4u8,     2u8, 1u8);
+
+        let feature = HaarFeature { 
+            sign: Sign::Positive,
+            feature_type: HaarFeatureType::TwoRegionHorizontal,
+            block_size: Size::new(2, 3),
+            left: 1,
+            top: 1,
+            tilted: false
+        };
+        let actual = draw_haar_feature(&image, feature);
+
+        let expected = gray_image!(
+            1u8,     2u8,  3u8,        4u8,     5u8;
+                 /***+++++++++++++*****---------***/
+            6u8, /**/255u8, 255u8,/**/ 0u8, 0u8;/**/
+            9u8, /**/255u8, 255u8,/**/ 0u8, 0u8;/**/
+            4u8, /**/255u8, 255u8,/**/ 0u8, 0u8;/**/
+                 /***+++++++++++++*****---------***/
+            6u8,     5u8,   4u8,       2u8,     1u8);
+
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_draw_haar_feature_four_region() {
+        let image = gray_image!(
+            /*****************************/
+        1u8,/**/2u8, 3u8,/**/ 4u8, 5u8;/**/
+        6u8,/**/7u8, 8u8,/**/ 9u8, 0u8;/**/ 
+            /*****************************/
+        9u8,/**/8u8, 7u8,/**/ 6u8, 5u8;/**/
+        4u8,/**/3u8, 2u8,/**/ 1u8, 0u8;/**/
+            /*****************************/
+        6u8,    5u8, 4u8,     2u8, 1u8);
+
+        let feature = HaarFeature { 
+            sign: Sign::Positive,
+            feature_type: HaarFeatureType::FourRegion,
+            block_size: Size::new(2, 2),
+            left: 1,
+            top: 0,
+            tilted: false
+        };
+
+        let actual = draw_haar_feature(&image, feature);
+
+        let expected = gray_image!(
+            /*************************************/
+        1u8,/**/255u8, 255u8,/**/ 0u8,   0u8;  /**/
+        6u8,/**/255u8, 255u8,/**/ 0u8,   0u8;  /**/
+            /*************************************/
+        9u8,/**/0u8,   0u8,  /**/ 255u8, 255u8;/**/
+        4u8,/**/0u8,   0u8,  /**/ 255u8, 255u8;/**/
+            /*************************************/
+        6u8,    5u8,   4u8,       2u8,   1u8);
+
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_evaluate_at_matches_evaluate_on_window() {
+        let image = gray_bench_image(9, 8);
+        let integral = integral_image(&image);
+        for feature in HaarFeature::enumerate(4, 3) {
+            for y in 0..6 {
+                for x in 0..6 {
+                    let window = integral_image(&image.view(x, y, 4, 3).to_image());
+                    assert_eq!(feature.evaluate_at(&integral, x, y), feature.evaluate(&window));
+                }
+            }
+        }
+        // A 2x1 feature at the top left of the window.
+        let feature = HaarFeature::enumerate(4, 3).next().unwrap();
+        assert_eq!(
+            feature.evaluate_at(&integral, 8, 0),
+            Err(ImageProcError::OutOfBounds {
+                region: Rect::at(8, 0).of_size(2, 1),
+                image: (9, 8)
+            })
+        );
+        assert!(feature.evaluate_at(&integral, u32::max_value() - 1, 0).is_err());
+        assert!(feature.evaluate_at(&integral, 0, u32::max_value()).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_row_matches_evaluate_at() {
+        let image = gray_bench_image(23, 6);
+        let integral = integral_image(&image);
+        for feature in HaarFeature::enumerate(4, 3) {
+            let (right, _) = feature.extent();
+            for y in 0..4 {
+                // Rows both shorter and longer than a vector register.
+                for len in 1..24 - right {
+                    let mut values = vec![0; len as usize];
+                    feature.evaluate_row(&integral, y, &mut values).unwrap();
+                    for (x, &value) in values.iter().enumerate() {
+                        assert_eq!(Ok(value), feature.evaluate_at(&integral, x as u32, y));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_row_out_of_bounds() {
+        let integral = integral_image(&gray_bench_image(9, 8));
+        // A 2x1 feature at the top left of the window.
+        let feature = HaarFeature::enumerate(4, 3).next().unwrap();
+        let mut values = vec![7; 9];
+        assert_eq!(
+            feature.evaluate_row(&integral, 0, &mut values),
+            Err(ImageProcError::OutOfBounds {
+                region: Rect::at(8, 0).of_size(2, 1),
+                image: (9, 8)
+            })
+        );
+        assert_eq!(values, vec![7; 9]);
+        assert!(feature.evaluate_row(&integral, 8, &mut values[..1]).is_err());
+        assert_eq!(feature.evaluate_row(&integral, 8, &mut []), Ok(()));
+    }
+
+    // Reference implementation of tilted feature evaluation, summing the pixels in each block directly.
+    fn reference_evaluate_tilted(feature: HaarFeature, image: &GrayImage, x: u32, y: u32) -> i32 {
+        let (top_x, top_y) = feature.top_corner();
+        let (width, height) = (feature.block_width() as u32, feature.block_height() as u32);
+        let mut sum = 0i32;
+        for h in 0..feature.blocks_high() as u32 {
+            for w in 0..feature.blocks_wide() as u32 {
+                // Each diagonal step contains two vertically adjacent pixels.
+                let (bx, by) = (x + top_x - 1 + w * width - h * height, y + top_y + w * width + h * height);
+                let mut block = 0;
+                for j in 0..height {
+                    for i in 0..width {
+                        let (px, py) = (bx + i - j, by + i + j);
+                        block += image.get_pixel(px, py)[0] as i32 + image.get_pixel(px, py + 1)[0] as i32;
+                    }
+                }
+                if (w + h) % 2 == 0 { sum += block } else { sum -= block }
+            }
+        }
+        if feature.is_positive() { sum } else { -sum }
+    }
+
+    #[test]
+    fn test_tilted_evaluate_against_reference_implementation() {
+        let image = gray_bench_image(12, 11);
+        let integral = rotated_integral_image(&image);
+        for &feature_type in FEATURE_TYPES.iter() {
+            for &(block_width, block_height) in &[(1, 1), (2, 1), (1, 2), (2, 3)] {
+                let feature = HaarFeature::tilted(feature_type, block_width, block_height, 1, 0);
+                let (right, bottom) = feature.extent();
+                for y in 0..12 - bottom {
+                    for x in 0..13 - right {
+                        let expected = reference_evaluate_tilted(feature, &image, x, y);
+                        assert_eq!(feature.evaluate_at(&integral, x, y), Ok(expected), "{:?} at ({}, {})", feature, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tilted_evaluate_row_matches_evaluate_at() {
+        let image = gray_bench_image(21, 5);
+        let integral = rotated_integral_image(&image);
+        for &feature_type in FEATURE_TYPES.iter() {
+            let feature = HaarFeature::tilted(feature_type, 1, 1, 1, 0);
+            let (right, bottom) = feature.extent();
+            for y in 0..6 - bottom {
+                let mut values = vec![0; (22 - right) as usize];
+                feature.evaluate_row(&integral, y, &mut values).unwrap();
+                for (x, &value) in values.iter().enumerate() {
+                    assert_eq!(Ok(value), feature.evaluate_at(&integral, x as u32, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tilted_evaluate_out_of_bounds() {
+        let feature = HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0);
+        assert!(feature.is_tilted());
+        assert!(feature.evaluate(&rotated_integral_image(&GrayImage::new(3, 3))).is_ok());
+        assert_eq!(
+            feature.evaluate(&rotated_integral_image(&GrayImage::new(2, 3))),
+            Err(ImageProcError::OutOfBounds {
+                region: Rect::at(0, 0).of_size(3, 3),
+                image: (2, 3)
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_constructors_validate_geometry() {
+        let upright = HaarFeature::try_new(HaarFeatureType::FourRegion, 3, 2, 5, 7).unwrap();
+        assert!(!upright.is_tilted() && upright.is_positive());
+        assert!(HaarFeature::enumerate(11, 11).any(|f| f == upright));
+
+        assert!(HaarFeature::try_new(HaarFeatureType::FourRegion, 1, 0, 0, 0).is_err());
+        assert!(HaarFeature::try_new(HaarFeatureType::ThreeRegionVertical, 1, 80, 0, 16).is_err());
+        assert!(HaarFeature::try_new(HaarFeatureType::ThreeRegionVertical, 1, 80, 0, 15).is_ok());
+
+        assert_eq!(
+            HaarFeature::try_tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 2, 0),
+            Ok(HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 2, 0))
+        );
+        assert!(HaarFeature::try_tilted(HaarFeatureType::TwoRegionHorizontal, 0, 1, 0, 0).is_err());
+        assert!(HaarFeature::try_tilted(HaarFeatureType::FourRegion, 64, 64, 0, 0).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tilted_panics_on_empty_blocks() {
+        HaarFeature::tilted(HaarFeatureType::FourRegion, 0, 1, 0, 0);
+    }
+
+    #[test]
+    fn test_try_draw_haar_feature_mut_out_of_bounds() {
+        let mut image = GrayImage::from_pixel(4, 3, Luma([7]));
+        let feature = HaarFeature::try_new(HaarFeatureType::ThreeRegionHorizontal, 1, 2, 2, 1).unwrap();
+        assert_eq!(
+            try_draw_haar_feature_mut(&mut image, feature),
+            Err(ImageProcError::OutOfBounds {
+                region: Rect::at(2, 1).of_size(3, 2),
+                image: (4, 3)
+            })
+        );
+        assert!(image.iter().all(|p| *p == 7));
+
+        let feature = HaarFeature::try_new(HaarFeatureType::TwoRegionHorizontal, 1, 2, 2, 1).unwrap();
+        assert_eq!(try_draw_haar_feature_mut(&mut image, feature), Ok(()));
+        assert_eq!(image.get_pixel(2, 2)[0], 255);
+        assert_eq!(image.get_pixel(3, 1)[0], 0);
+    }
+
+    #[test]
+    fn test_draw_haar_feature_tilted() {
+        let image = gray_image!(
+            1, 2, 3, 4;
+            5, 6, 7, 8;
+            9, 8, 7, 6;
+            5, 4, 3, 2);
+
+        let feature = HaarFeature::tilted(HaarFeatureType::TwoRegionVertical, 1, 1, 1, 0);
+        let actual = draw_haar_feature(&image, feature);
+
+        let expected = gray_image!(
+            1, 2,   255, 4;
+            5, 0,   255, 8;
+            9, 0,   7,   6;
+            5, 4,   3,   2);
+
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_evaluate_normalized() {
+        let image = gray_bench_image(10, 9);
+        // The same image with different brightness and contrast.
+        let adjusted = GrayImage::from_fn(10, 9, |x, y| Luma([20 + 3 * image.get_pixel(x, y)[0]]));
+        let window = Rect::at(2, 3).of_size(6, 5);
+        for feature in HaarFeature::enumerate(6, 5) {
+            let expected = feature.evaluate_normalized(&integral_image(&image), &integral_squared_image(&image), window).unwrap();
+            let actual = feature.evaluate_normalized(&integral_image(&adjusted), &integral_squared_image(&adjusted), window).unwrap();
+            assert!((actual - expected).abs() < 1e-4, "{:?}: {} != {}", feature, actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_normalized_reference() {
+        let image = gray_image!(
+            1, 2, 3, 4, 5;
+            9, 7, 5, 3, 1);
+        let integral = integral_image(&image);
+        let integral_squared = integral_squared_image(&image);
+        let window = Rect::at(1, 0).of_size(3, 2);
+
+        // Normalise the window's pixels directly.
+        let pixels: Vec<f64> = [2, 3, 4, 7, 5, 3].iter().map(|&p| p as f64).collect();
+        let mean = pixels.iter().sum::<f64>() / 6.0;
+        let std_dev = (pixels.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / 6.0).sqrt();
+        let n: Vec<f64> = pixels.iter().map(|p| (p - mean) / std_dev).collect();
+
+        // A three region horizontal feature covering the whole window.
+        let feature = HaarFeature {
+            sign: Sign::Negative,
+            feature_type: HaarFeatureType::ThreeRegionHorizontal,
+            block_size: Size::new(1, 2),
+            left: 0,
+            top: 0,
+            tilted: false
+        };
+        let expected = -((n[0] + n[3]) - (n[1] + n[4]) + (n[2] + n[5]));
+        let actual = feature.evaluate_normalized(&integral, &integral_squared, window).unwrap();
+        assert!((actual as f64 - expected).abs() < 1e-6, "{} != {}", actual, expected);
+
+        assert!(feature.evaluate_normalized(&integral, &integral_squared, Rect::at(1, 0).of_size(2, 2)).is_err());
+        let tilted = HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0);
+        assert!(tilted.evaluate_normalized(&integral, &integral_squared, window).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_normalized_constant_window() {
+        let image = GrayImage::from_pixel(4, 4, Luma([50u8]));
+        let feature = HaarFeature::enumerate(3, 3).nth(5).unwrap();
+        let window = Rect::at(1, 1).of_size(3, 3);
+        assert_eq!(
+            feature.evaluate_normalized(&integral_image(&image), &integral_squared_image(&image), window),
+            Ok(0.0)
+        );
+    }
+
+    #[test]
+    fn test_scale() {
+        let image = gray_bench_image(30, 20);
+        let integral = integral_image(&image);
+        for feature in HaarFeature::enumerate(6, 5).step_by(7) {
+            let unscaled = feature.scale(1.0).unwrap();
+            assert_eq!(unscaled.extent(), feature.extent());
+            assert_eq!(unscaled.evaluate_at(&integral, 3, 2), Ok(feature.evaluate_at(&integral, 3, 2).unwrap() as f32));
+
+            let scaled = feature.scale(3.0).unwrap();
+            let (right, bottom) = feature.extent();
+            assert_eq!(scaled.extent(), (3 * right, 3 * bottom));
+            assert!(scaled.evaluate_at(&integral, 30 - 3 * right, 0).is_ok());
+            assert!(scaled.evaluate_at(&integral, 31 - 3 * right, 0).is_err());
+        }
+
+        let feature = HaarFeature::enumerate(2, 2).next().unwrap();
+        assert!(feature.scale(0.0).is_err());
+        assert!(feature.scale(::std::f32::NAN).is_err());
+        let tilted = HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0);
+        assert!(tilted.scale(2.0).is_err());
+    }
+
+    #[test]
+    fn test_haar_feature_is_small() {
+        assert!(::std::mem::size_of::<HaarFeature>() <= 8);
+    }
+
+    #[bench]
+    fn bench_evaluate_at_all_windows(b: &mut test::Bencher) {
+        // Sliding a 24x24 window over a 100x100 image, evaluating 100 features per window.
+        let features: Vec<HaarFeature> = HaarFeature::enumerate(24, 24).step_by(1000).take(100).collect();
+        let integral = integral_image(&gray_bench_image(100, 100));
+
+        b.iter(|| {
+            let mut total = 0i32;
+            for y in 0..77 {
+                for x in 0..77 {
+                    for feature in &features {
+                        total = total.wrapping_add(unsafe { feature.evaluate_at_unchecked(&integral, x, y) });
+                    }
+                }
+            }
+            test::black_box(total);
+        });
+    }
+
+    #[bench]
+    fn bench_evaluate_all_features_10x10(b: &mut test::Bencher) {
+        // 10050 features in total
+        let features = enumerate_haar_features(10, 10);
+        let image = gray_bench_image(10, 10);
+        let integral = integral_image(&image);
+
+        b.iter(|| {
+            for feature in &features {
+                let x = feature.evaluate(&integral).unwrap();
+                test::black_box(x);
+            }
+        });
+    }
+}
@@ -0,0 +1,897 @@
+//! Loading of pretrained cascades in the XML format used by [OpenCV], such as the
+//! `haarcascade_frontalface_default.xml` and `haarcascade_eye.xml` files distributed with it.
+//!
+//! Both the format written by `opencv_traincascade` and the older format written by
+//! `opencv_haartraining` are supported.
+//!
+//! [OpenCV]: https://opencv.org
+
+use super::DetectionOptions;
+use definitions::Image;
+use error::{ImageProcError, Result};
+use image::{GenericImageView, GrayImage, Luma, Primitive};
+use integral_image::{integral_image, integral_squared_image_as, rotated_integral_image};
+use rect::Rect;
+use std::str::FromStr;
+
+/// OpenCV lowers each stage threshold by this amount when loading a cascade,
+/// to allow for rounding errors in the sums of classifier outputs.
+const STAGE_THRESHOLD_EPSILON: f64 = 1e-5;
+
+/// A boosted cascade of Haar-like features loaded from an OpenCV XML file.
+///
+/// OpenCV features are weighted sums of two or three possibly overlapping rectangles,
+/// and feature values are normalised by the standard deviation of the intensities in each
+/// window. As neither of these is supported by [`Cascade`](../struct.Cascade.html),
+/// OpenCV cascades are evaluated separately.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenCvCascade {
+    window_width: u32,
+    window_height: u32,
+    features: Vec<Feature>,
+    stages: Vec<TreeStage>,
+}
+
+/// A rectangle in a feature, with left, top, width and height measured
+/// in pixels relative to the top left of the detection window.
+///
+/// The rectangles of tilted features are rotated clockwise by 45 degrees. Their left and
+/// top give the top corner of the rectangle as an index into a rotated integral image,
+/// and they extend `width` diagonal steps down and to the right and `height` diagonal
+/// steps down and to the left.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct WeightedRect {
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    weight: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Feature {
+    rects: Vec<WeightedRect>,
+    tilted: bool,
+}
+
+/// Where to go after a decision tree node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Child {
+    /// Index of the next node in the tree.
+    Node(usize),
+    /// Output of the tree.
+    Leaf(f32),
+}
+
+/// A decision tree node, choosing `left` if the normalised value of
+/// `feature` is less than `threshold` and `right` otherwise.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Node {
+    feature: usize,
+    threshold: f32,
+    left: Child,
+    right: Child,
+}
+
+/// A stage of the cascade, passed if the sum of the outputs of its trees
+/// is at least `threshold`. Evaluation of each tree starts at its first node.
+#[derive(Clone, Debug, PartialEq)]
+struct TreeStage {
+    trees: Vec<Vec<Node>>,
+    threshold: f32,
+}
+
+impl OpenCvCascade {
+    /// Parses a cascade from the contents of an OpenCV XML file.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if `xml` is not a valid cascade of Haar-like features.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::haar::DetectionOptions;
+    /// use imageproc::haar::opencv::OpenCvCascade;
+    /// use imageproc::rect::Rect;
+    /// use image::{GrayImage, Luma};
+    ///
+    /// // Pretrained cascades can be read using std::fs::read_to_string.
+    /// // This one accepts 6x6 windows whose centre is brighter on the left than the right.
+    /// let xml = r#"
+    ///     <opencv_storage>
+    ///     <cascade type_id="opencv-cascade-classifier">
+    ///       <stageType>BOOST</stageType>
+    ///       <featureType>HAAR</featureType>
+    ///       <height>6</height>
+    ///       <width>6</width>
+    ///       <stages>
+    ///         <_>
+    ///           <stageThreshold>0.</stageThreshold>
+    ///           <weakClassifiers>
+    ///             <_>
+    ///               <internalNodes>0 -1 0 0.8</internalNodes>
+    ///               <leafValues>-1. 1.</leafValues></_></weakClassifiers></_></stages>
+    ///       <features>
+    ///         <_>
+    ///           <rects>
+    ///             <_>1 1 4 4 -1.</_>
+    ///             <_>1 1 2 4 2.</_></rects></_></features></cascade>
+    ///     </opencv_storage>"#;
+    ///
+    /// let cascade = OpenCvCascade::from_xml(xml).unwrap();
+    /// assert_eq!(cascade.window_size(), (6, 6));
+    ///
+    /// // A bright vertical bar, two pixels wide.
+    /// let image = GrayImage::from_fn(12, 6, |x, _| Luma([if x == 4 || x == 5 { 200 } else { 10 }]));
+    /// assert_eq!(
+    ///     cascade.detect(&image, DetectionOptions::default()).unwrap(),
+    ///     vec![Rect::at(3, 0).of_size(6, 6)]
+    /// );
+    /// # }
+    /// ```
+    pub fn from_xml(xml: &str) -> Result<OpenCvCascade> {
+        let document = parse_xml(xml)?;
+        // Cascades are stored in a single element inside the root <opencv_storage> element.
+        let root = if document.name == "opencv_storage" {
+            document.children.first().ok_or_else(|| invalid("empty <opencv_storage>"))?
+        } else {
+            &document
+        };
+        if root.find("size").is_some() {
+            parse_haartraining_cascade(root)
+        } else {
+            parse_traincascade_cascade(root)
+        }
+    }
+
+    /// Width and height of the detection window at scale 1.
+    pub fn window_size(&self) -> (u32, u32) {
+        (self.window_width, self.window_height)
+    }
+
+    /// The number of stages in this cascade.
+    pub fn num_stages(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Scans this cascade over an image at increasing scales and returns the bounding box
+    /// of every accepted window.
+    ///
+    /// Scales are chosen as for [`detect_objects`](../fn.detect_objects.html). Overlapping
+    /// detections of the same object are all returned.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if `initial_scale` or `step` is not positive,
+    /// or `scale_factor` is not greater than 1.
+    pub fn detect(&self, image: &GrayImage, options: DetectionOptions) -> Result<Vec<Rect>> {
+        options.validate()?;
+
+        let integral = integral_image(image);
+        let squared: Image<Luma<f64>> = integral_squared_image_as(image);
+        // Only read by tilted features.
+        let rotated = if self.features.iter().any(|f| f.tilted) {
+            rotated_integral_image(image)
+        } else {
+            Image::new(0, 0)
+        };
+        let (width, height) = image.dimensions();
+        let mut detections = vec![];
+        let mut scale = options.initial_scale;
+
+        loop {
+            let scaled = ScaledCascade::new(self, scale);
+            let (extent_width, extent_height) = scaled.extent;
+            if extent_width > width || extent_height > height {
+                break;
+            }
+            let step = ((options.step * scale).round() as u32).max(1);
+            let mut y = 0;
+            while y + extent_height <= height {
+                let mut x = 0;
+                while x + extent_width <= width {
+                    if unsafe { scaled.classify(&integral, &squared, &rotated, x, y) } {
+                        detections.push(Rect::at(x as i32, y as i32).of_size(scaled.window.0, scaled.window.1));
+                    }
+                    x += step;
+                }
+                y += step;
+            }
+            scale *= options.scale_factor;
+        }
+
+        Ok(detections)
+    }
+
+    /// Checks that every feature lies within the window and that every tree is well formed.
+    fn new(
+        window_width: u32,
+        window_height: u32,
+        features: Vec<Feature>,
+        stages: Vec<TreeStage>,
+    ) -> Result<OpenCvCascade> {
+        // Feature values are normalised using the window with a one pixel border removed.
+        if window_width < 3 || window_height < 3 {
+            return Err(invalid(format!("window must be at least 3x3, got {}x{}", window_width, window_height)));
+        }
+        for feature in &features {
+            if feature.rects.is_empty() {
+                return Err(invalid("feature has no rectangles"));
+            }
+            for r in &feature.rects {
+                let fits = if feature.tilted {
+                    r.left >= r.height && r.left + r.width <= window_width && r.top + r.width + r.height <= window_height
+                } else {
+                    r.left + r.width <= window_width && r.top + r.height <= window_height
+                };
+                if r.width == 0 || r.height == 0 || !fits {
+                    return Err(invalid(format!(
+                        "rectangle {:?} is empty or does not fit in a {}x{} window",
+                        r, window_width, window_height
+                    )));
+                }
+            }
+        }
+        for stage in &stages {
+            for tree in &stage.trees {
+                if tree.is_empty() {
+                    return Err(invalid("decision tree has no nodes"));
+                }
+                for (i, node) in tree.iter().enumerate() {
+                    if node.feature >= features.len() {
+                        return Err(invalid(format!("feature index {} out of range", node.feature)));
+                    }
+                    for child in &[node.left, node.right] {
+                        if let Child::Node(c) = *child {
+                            // Requiring children to follow their parents rules out cycles.
+                            if c <= i || c >= tree.len() {
+                                return Err(invalid(format!("invalid child index {} for node {}", c, i)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(OpenCvCascade { window_width, window_height, features, stages })
+    }
+}
+
+/// Parses a cascade in the format written by `opencv_traincascade`, in which
+/// features are listed separately from the stages that use them.
+fn parse_traincascade_cascade(root: &Element) -> Result<OpenCvCascade> {
+    let stage_type = root.child("stageType")?.text.trim();
+    if stage_type != "BOOST" {
+        return Err(invalid(format!("unsupported stage type {}", stage_type)));
+    }
+    let feature_type = root.child("featureType")?.text.trim();
+    if feature_type != "HAAR" {
+        return Err(invalid(format!("unsupported feature type {}", feature_type)));
+    }
+    let width = root.child("width")?.number()?;
+    let height = root.child("height")?.number()?;
+
+    let features = root
+        .child("features")?
+        .children
+        .iter()
+        .map(parse_feature)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut stages = vec![];
+    for stage in &root.child("stages")?.children {
+        let mut trees = vec![];
+        for classifier in &stage.child("weakClassifiers")?.children {
+            let values: Vec<&str> = classifier.child("internalNodes")?.text.split_whitespace().collect();
+            let leaves: Vec<f32> = classifier.child("leafValues")?.numbers()?;
+            if values.is_empty() || values.len() % 4 != 0 {
+                return Err(invalid("<internalNodes> must contain four values per node"));
+            }
+            // Non-positive child indices refer to leaves.
+            let child = |index: i32| {
+                if index > 0 {
+                    Ok(Child::Node(index as usize))
+                } else {
+                    leaves
+                        .get(-index as usize)
+                        .map(|&value| Child::Leaf(value))
+                        .ok_or_else(|| invalid(format!("leaf index {} out of range", -index)))
+                }
+            };
+            let mut nodes = vec![];
+            for node in values.chunks(4) {
+                nodes.push(Node {
+                    left: child(parse_number(node[0])?)?,
+                    right: child(parse_number(node[1])?)?,
+                    feature: parse_number(node[2])?,
+                    threshold: parse_number(node[3])?,
+                });
+            }
+            trees.push(nodes);
+        }
+        let threshold = stage.child("stageThreshold")?.number()?;
+        stages.push(TreeStage { trees, threshold });
+    }
+
+    OpenCvCascade::new(width, height, features, stages)
+}
+
+/// Parses a cascade in the format written by `opencv_haartraining`, in which
+/// each tree node contains its own feature.
+fn parse_haartraining_cascade(root: &Element) -> Result<OpenCvCascade> {
+    let size: Vec<u32> = root.child("size")?.numbers()?;
+    if size.len() != 2 {
+        return Err(invalid("<size> must contain a width and height"));
+    }
+
+    let mut features = vec![];
+    let mut stages = vec![];
+    for stage in &root.child("stages")?.children {
+        let mut trees = vec![];
+        for tree in &stage.child("trees")?.children {
+            let mut nodes = vec![];
+            for node in &tree.children {
+                features.push(parse_feature(node.child("feature")?)?);
+                nodes.push(Node {
+                    feature: features.len() - 1,
+                    threshold: node.child("threshold")?.number()?,
+                    left: haartraining_child(node, "left_val", "left_node")?,
+                    right: haartraining_child(node, "right_val", "right_node")?,
+                });
+            }
+            trees.push(nodes);
+        }
+        let threshold = stage.child("stage_threshold")?.number()?;
+        stages.push(TreeStage { trees, threshold });
+    }
+
+    OpenCvCascade::new(size[0], size[1], features, stages)
+}
+
+/// Reads a child of a tree node, which is given either as a leaf value or as a node index.
+fn haartraining_child(node: &Element, leaf: &str, index: &str) -> Result<Child> {
+    match (node.find(leaf), node.find(index)) {
+        (Some(value), None) => Ok(Child::Leaf(value.number()?)),
+        (None, Some(index)) => Ok(Child::Node(index.number()?)),
+        _ => Err(invalid(format!("tree node must contain exactly one of <{}> and <{}>", leaf, index))),
+    }
+}
+
+fn parse_feature(feature: &Element) -> Result<Feature> {
+    let tilted = match feature.find("tilted") {
+        Some(tilted) => tilted.number::<i32>()? != 0,
+        None => false,
+    };
+    let mut rects = vec![];
+    for rect in &feature.child("rects")?.children {
+        let values: Vec<&str> = rect.text.split_whitespace().collect();
+        if values.len() != 5 {
+            return Err(invalid(format!("rectangle must contain five values, got '{}'", rect.text.trim())));
+        }
+        rects.push(WeightedRect {
+            left: parse_number(values[0])?,
+            top: parse_number(values[1])?,
+            width: parse_number(values[2])?,
+            height: parse_number(values[3])?,
+            weight: parse_number(values[4])?,
+        });
+    }
+    Ok(Feature { rects, tilted })
+}
+
+fn parse_number<T: FromStr>(token: &str) -> Result<T> {
+    token.parse().map_err(|_| invalid(format!("invalid number '{}'", token)))
+}
+
+fn invalid<S: AsRef<str>>(message: S) -> ImageProcError {
+    ImageProcError::InvalidOptions(format!("invalid OpenCV cascade: {}", message.as_ref()))
+}
+
+/// A rectangle with its weight, scaled for evaluation at a single scale.
+struct ScaledRect {
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    weight: f64,
+    tilted: bool,
+}
+
+impl ScaledRect {
+    fn new(rect: &WeightedRect, tilted: bool, scale: f32) -> ScaledRect {
+        let scale_length = |v: u32| (v as f32 * scale).round() as u32;
+        ScaledRect {
+            left: scale_length(rect.left),
+            top: scale_length(rect.top),
+            width: scale_length(rect.width).max(1),
+            height: scale_length(rect.height).max(1),
+            weight: rect.weight as f64,
+            tilted,
+        }
+    }
+
+    /// The number of pixels in this rectangle.
+    fn area(&self) -> f64 {
+        let area = self.width as f64 * self.height as f64;
+        if self.tilted { 2.0 * area } else { area }
+    }
+
+    /// Width and height of the region from the window origin to the
+    /// bottom right of the integral image entries read for this rectangle.
+    fn extent(&self) -> (u32, u32) {
+        if self.tilted {
+            (self.left + self.width, self.top + self.width + self.height)
+        } else {
+            (self.left + self.width, self.top + self.height)
+        }
+    }
+
+    /// Sums the pixels in this rectangle, where `integral` is a rotated integral image
+    /// if the rectangle is tilted. Requires this rectangle at (x, y) to lie within the image.
+    unsafe fn sum<T>(&self, integral: &Image<Luma<T>>, x: u32, y: u32) -> f64
+    where
+        T: Primitive + Into<f64> + 'static,
+    {
+        let read = |x, y| integral.unsafe_get_pixel(x, y)[0].into();
+        let (left, top, width, height) = (x + self.left, y + self.top, self.width, self.height);
+        if self.tilted {
+            read(left, top) - read(left - height, top + height) - read(left + width, top + width)
+                + read(left + width - height, top + width + height)
+        } else {
+            read(left + width, top + height) - read(left + width, top) - read(left, top + height) + read(left, top)
+        }
+    }
+}
+
+/// A cascade whose features have been scaled for evaluation at a single scale.
+struct ScaledCascade<'a> {
+    cascade: &'a OpenCvCascade,
+    /// Scaled features, with weights divided by the area of `normalisation`.
+    features: Vec<Vec<ScaledRect>>,
+    /// The region whose standard deviation is used to normalise feature values.
+    normalisation: ScaledRect,
+    /// Scaled window size.
+    window: (u32, u32),
+    /// Width and height of the region read when classifying a window.
+    extent: (u32, u32),
+}
+
+impl<'a> ScaledCascade<'a> {
+    fn new(cascade: &'a OpenCvCascade, scale: f32) -> ScaledCascade<'a> {
+        let window = (
+            ((cascade.window_width as f32 * scale).round() as u32).max(1),
+            ((cascade.window_height as f32 * scale).round() as u32).max(1),
+        );
+        let normalisation = ScaledRect::new(
+            &WeightedRect {
+                left: 1,
+                top: 1,
+                width: cascade.window_width - 2,
+                height: cascade.window_height - 2,
+                weight: 1.0,
+            },
+            false,
+            scale,
+        );
+        let inverse_area = 1.0 / normalisation.area();
+
+        let features: Vec<Vec<ScaledRect>> = cascade
+            .features
+            .iter()
+            .map(|feature| {
+                let mut rects: Vec<ScaledRect> = feature.rects.iter().map(|r| ScaledRect::new(r, feature.tilted, scale)).collect();
+                // Rounding changes the relative areas of the rectangles, so as in OpenCV the weight of
+                // the first is recomputed to keep the response to a region of constant intensity zero.
+                if rects.len() > 1 {
+                    let rest: f64 = rects[1..].iter().map(|r| r.weight * r.area()).sum();
+                    rects[0].weight = -rest / rects[0].area();
+                }
+                for r in &mut rects {
+                    r.weight *= inverse_area;
+                }
+                rects
+            })
+            .collect();
+
+        let extent = features.iter().flat_map(|f| f.iter()).chain(Some(&normalisation)).fold(window, |(w, h), r| {
+            let (right, bottom) = r.extent();
+            (w.max(right), h.max(bottom))
+        });
+        ScaledCascade { cascade, features, normalisation, window, extent }
+    }
+
+    /// Requires the extent of the cascade at (x, y) to lie within the image.
+    unsafe fn classify(
+        &self,
+        integral: &Image<Luma<u32>>,
+        squared: &Image<Luma<f64>>,
+        rotated: &Image<Luma<u32>>,
+        x: u32,
+        y: u32,
+    ) -> bool {
+        let inverse_area = 1.0 / self.normalisation.area();
+        let mean = self.normalisation.sum(integral, x, y) * inverse_area;
+        let variance = self.normalisation.sum(squared, x, y) * inverse_area - mean * mean;
+        let std_dev = if variance > 0.0 { variance.sqrt() } else { 1.0 };
+
+        for stage in &self.cascade.stages {
+            let mut total = 0.0;
+            for tree in &stage.trees {
+                let mut node = &tree[0];
+                loop {
+                    let value: f64 = self.features[node.feature]
+                        .iter()
+                        .map(|r| r.weight * r.sum(if r.tilted { rotated } else { integral }, x, y))
+                        .sum();
+                    let child = if value < node.threshold as f64 * std_dev { node.left } else { node.right };
+                    match child {
+                        Child::Node(index) => node = &tree[index],
+                        Child::Leaf(output) => {
+                            total += output as f64;
+                            break;
+                        }
+                    }
+                }
+            }
+            if total < stage.threshold as f64 - STAGE_THRESHOLD_EPSILON {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// An element of an XML document. Attributes are discarded.
+#[derive(Debug)]
+struct Element {
+    name: String,
+    /// The concatenated text directly inside this element.
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn find(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn child(&self, name: &str) -> Result<&Element> {
+        self.find(name).ok_or_else(|| invalid(format!("missing <{}> in <{}>", name, self.name)))
+    }
+
+    fn number<T: FromStr>(&self) -> Result<T> {
+        parse_number(self.text.trim())
+    }
+
+    fn numbers<T: FromStr>(&self) -> Result<Vec<T>> {
+        self.text.split_whitespace().map(parse_number).collect()
+    }
+}
+
+/// Parses the subset of XML used by OpenCV: nested elements containing text, with
+/// attributes, comments, processing instructions and declarations ignored.
+fn parse_xml(xml: &str) -> Result<Element> {
+    let mut parser = XmlParser { input: xml, position: 0 };
+    parser.skip_markup()?;
+    let root = parser.element()?;
+    parser.skip_markup()?;
+    if !parser.rest().is_empty() {
+        return Err(invalid("unexpected content after the root element"));
+    }
+    Ok(root)
+}
+
+struct XmlParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    /// Skips whitespace, comments, processing instructions and declarations.
+    fn skip_markup(&mut self) -> Result<()> {
+        loop {
+            self.position = self.input.len() - self.rest().trim_start().len();
+            if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<()> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.position += i + end.len();
+                Ok(())
+            }
+            None => Err(invalid(format!("unterminated XML markup, expected '{}'", end))),
+        }
+    }
+
+    /// Parses the element starting at the current position.
+    fn element(&mut self) -> Result<Element> {
+        if !self.rest().starts_with('<') {
+            return Err(invalid("expected an XML element"));
+        }
+        self.position += 1;
+        let name_length = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or_else(|| self.rest().len());
+        if name_length == 0 {
+            return Err(invalid("XML element has no name"));
+        }
+        let name = self.rest()[..name_length].to_string();
+        let tag_length = self.rest().find('>').ok_or_else(|| invalid(format!("unterminated tag <{}", name)))?;
+        let self_closing = self.rest()[..tag_length].ends_with('/');
+        self.position += tag_length + 1;
+
+        let mut element = Element { name, text: String::new(), children: vec![] };
+        if self_closing {
+            return Ok(element);
+        }
+        loop {
+            let text_length = self
+                .rest()
+                .find('<')
+                .ok_or_else(|| invalid(format!("unterminated element <{}>", element.name)))?;
+            element.text.push_str(&self.rest()[..text_length]);
+            self.position += text_length;
+
+            if self.rest().starts_with("</") {
+                let end = self.rest().find('>').ok_or_else(|| invalid("unterminated closing tag"))?;
+                if self.rest()[2..end].trim() != element.name {
+                    return Err(invalid(format!("mismatched closing tag for <{}>", element.name)));
+                }
+                self.position += end + 1;
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else {
+                element.children.push(self.element()?);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    const TRAINCASCADE_XML: &'static str = r#"<?xml version="1.0"?>
+<opencv_storage>
+<cascade type_id="opencv-cascade-classifier"><stageType>BOOST</stageType>
+  <featureType>HAAR</featureType>
+  <height>6</height>
+  <width>6</width>
+  <stageParams>
+    <maxWeakCount>1</maxWeakCount></stageParams>
+  <featureParams>
+    <maxCatCount>0</maxCatCount></featureParams>
+  <stageNum>1</stageNum>
+  <stages>
+    <!-- stage 0 -->
+    <_>
+      <maxWeakCount>1</maxWeakCount>
+      <stageThreshold>0.</stageThreshold>
+      <weakClassifiers>
+        <_>
+          <internalNodes>
+            0 -1 0 8.0000001192092896e-01</internalNodes>
+          <leafValues>
+            -1. 1.</leafValues></_></weakClassifiers></_></stages>
+  <features>
+    <_>
+      <rects>
+        <_>
+          1 1 4 4 -1.</_>
+        <_>
+          1 1 2 4 2.</_></rects></_></features></cascade>
+</opencv_storage>
+"#;
+
+    const HAARTRAINING_XML: &'static str = r#"<?xml version="1.0"?>
+<opencv_storage>
+<test_cascade type_id="opencv-haar-classifier">
+  <size>6 6</size>
+  <stages>
+    <_>
+      <!-- stage 0 -->
+      <trees>
+        <_>
+          <!-- tree 0 -->
+          <_>
+            <!-- root node -->
+            <feature>
+              <rects>
+                <_>1 1 4 4 -1.</_>
+                <_>1 1 2 4 2.</_></rects>
+              <tilted>0</tilted></feature>
+            <threshold>8.0000001192092896e-01</threshold>
+            <left_val>-1.</left_val>
+            <right_val>1.</right_val></_></_></trees>
+      <stage_threshold>0.</stage_threshold>
+      <parent>-1</parent>
+      <next>-1</next></_></stages></test_cascade>
+</opencv_storage>
+"#;
+
+    fn expected_cascade() -> OpenCvCascade {
+        OpenCvCascade {
+            window_width: 6,
+            window_height: 6,
+            features: vec![Feature {
+                rects: vec![
+                    WeightedRect { left: 1, top: 1, width: 4, height: 4, weight: -1.0 },
+                    WeightedRect { left: 1, top: 1, width: 2, height: 4, weight: 2.0 },
+                ],
+                tilted: false,
+            }],
+            stages: vec![TreeStage {
+                trees: vec![vec![Node { feature: 0, threshold: 0.8, left: Child::Leaf(-1.0), right: Child::Leaf(1.0) }]],
+                threshold: 0.0,
+            }],
+        }
+    }
+
+    // A vertical bar two pixels wide at x = 4 and 5.
+    fn bar_image(background: u8, foreground: u8) -> GrayImage {
+        GrayImage::from_fn(12, 6, |x, _| Luma([if x == 4 || x == 5 { foreground } else { background }]))
+    }
+
+    #[test]
+    fn test_parse_traincascade_format() {
+        assert_eq!(OpenCvCascade::from_xml(TRAINCASCADE_XML), Ok(expected_cascade()));
+    }
+
+    #[test]
+    fn test_parse_haartraining_format() {
+        assert_eq!(OpenCvCascade::from_xml(HAARTRAINING_XML), Ok(expected_cascade()));
+    }
+
+    #[test]
+    fn test_parse_haartraining_tree() {
+        let xml = r#"
+            <tree_cascade>
+              <size>6 6</size>
+              <stages><_>
+                <trees><_>
+                  <_>
+                    <feature><rects><_>1 1 4 4 -1.</_><_>1 1 2 4 2.</_></rects></feature>
+                    <threshold>0.</threshold>
+                    <left_val>-1.</left_val>
+                    <right_node>1</right_node></_>
+                  <_>
+                    <feature><rects><_>1 1 4 4 -1.</_><_>1 1 4 2 2.</_></rects></feature>
+                    <threshold>0.5</threshold>
+                    <left_val>-0.5</left_val>
+                    <right_val>2.</right_val></_></_></trees>
+                <stage_threshold>-0.8</stage_threshold></_></stages>
+            </tree_cascade>"#;
+        let cascade = OpenCvCascade::from_xml(xml).unwrap();
+        assert_eq!(cascade.features.len(), 2);
+        assert_eq!(
+            cascade.stages[0].trees[0],
+            vec![
+                Node { feature: 0, threshold: 0.0, left: Child::Leaf(-1.0), right: Child::Node(1) },
+                Node { feature: 1, threshold: 0.5, left: Child::Leaf(-0.5), right: Child::Leaf(2.0) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // Tilted rectangles must have room to extend down and to the left of their top corner.
+        let tilted = HAARTRAINING_XML.replace("<tilted>0</tilted>", "<tilted>1</tilted>");
+        assert!(OpenCvCascade::from_xml(&tilted).is_err());
+
+        let lbp = TRAINCASCADE_XML.replace("HAAR", "LBP");
+        assert!(OpenCvCascade::from_xml(&lbp).is_err());
+
+        let outside_window = TRAINCASCADE_XML.replace("1 1 4 4 -1.", "1 1 6 4 -1.");
+        assert!(OpenCvCascade::from_xml(&outside_window).is_err());
+
+        let missing_feature = TRAINCASCADE_XML.replace("0 -1 0 8", "0 -1 1 8");
+        assert!(OpenCvCascade::from_xml(&missing_feature).is_err());
+
+        let missing_leaf = TRAINCASCADE_XML.replace("0 -1 0 8", "0 -2 0 8");
+        assert!(OpenCvCascade::from_xml(&missing_leaf).is_err());
+
+        let truncated = &TRAINCASCADE_XML[..TRAINCASCADE_XML.len() / 2];
+        assert!(OpenCvCascade::from_xml(truncated).is_err());
+
+        assert_eq!(
+            OpenCvCascade::from_xml("<a><b></a>"),
+            Err(ImageProcError::InvalidOptions("invalid OpenCV cascade: mismatched closing tag for <b>".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_parse_tilted_feature() {
+        let xml = HAARTRAINING_XML
+            .replace("1 1 4 4 -1.", "3 0 2 2 -1.")
+            .replace("1 1 2 4 2.", "3 0 1 2 2.")
+            .replace("<tilted>0</tilted>", "<tilted>1</tilted>");
+        let cascade = OpenCvCascade::from_xml(&xml).unwrap();
+        assert!(cascade.features[0].tilted);
+    }
+
+    #[test]
+    fn test_tilted_rect_sum() {
+        let image = gray_image!(
+            1, 2, 3, 4;
+            5, 6, 7, 8;
+            9, 8, 7, 6;
+            5, 4, 3, 2);
+        let rotated = rotated_integral_image(&image);
+        // The two pixels below the top corner, and the two pixels below and to their left.
+        let rect = WeightedRect { left: 2, top: 0, width: 1, height: 2, weight: 1.0 };
+        let scaled = ScaledRect::new(&rect, true, 1.0);
+        assert_eq!(scaled.area(), 4.0);
+        assert_eq!(scaled.extent(), (3, 3));
+        assert_eq!(unsafe { scaled.sum(&rotated, 0, 0) }, (2 + 6 + 5 + 9) as f64);
+        assert_eq!(unsafe { scaled.sum(&rotated, 1, 1) }, (7 + 7 + 8 + 4) as f64);
+    }
+
+    #[test]
+    fn test_parse_xml() {
+        let root = parse_xml("<?xml version=\"1.0\"?><!-- c --><a x=\"1\"> 1 <b/><c>2<!-- 3 --></c> 4 </a>").unwrap();
+        assert_eq!(root.name, "a");
+        assert_eq!(root.text, " 1  4 ");
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.child("b").unwrap().children.len(), 0);
+        assert_eq!(root.child("c").unwrap().number::<u32>(), Ok(2));
+        assert!(root.child("d").is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        let cascade = expected_cascade();
+        let options = DetectionOptions::default();
+        let expected = vec![Rect::at(3, 0).of_size(6, 6)];
+        assert_eq!(cascade.detect(&bar_image(0, 255), options), Ok(expected.clone()));
+        // Feature values are normalised for brightness and contrast.
+        assert_eq!(cascade.detect(&bar_image(100, 140), options), Ok(expected));
+        assert_eq!(cascade.detect(&bar_image(140, 140), options), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_detect_scaled() {
+        // The bar image at twice the size.
+        let image = GrayImage::from_fn(24, 12, |x, _| Luma([if x >= 8 && x < 12 { 255 } else { 0 }]));
+        let options = DetectionOptions { initial_scale: 2.0, step: 0.5, ..DetectionOptions::default() };
+        assert_eq!(
+            expected_cascade().detect(&image, options),
+            Ok(vec![Rect::at(6, 0).of_size(12, 12)])
+        );
+    }
+
+    #[test]
+    fn test_detect_invalid_options() {
+        let options = DetectionOptions { scale_factor: 1.0, ..DetectionOptions::default() };
+        assert!(expected_cascade().detect(&bar_image(0, 255), options).is_err());
+    }
+
+    #[bench]
+    fn bench_parse_traincascade_format(b: &mut Bencher) {
+        b.iter(|| black_box(OpenCvCascade::from_xml(TRAINCASCADE_XML).unwrap()));
+    }
+
+    #[bench]
+    fn bench_detect(b: &mut Bencher) {
+        let cascade = expected_cascade();
+        let image = gray_bench_image(200, 200);
+        b.iter(|| black_box(cascade.detect(&image, DetectionOptions::default()).unwrap()));
+    }
+}
@@ -0,0 +1,424 @@
+//! Training of Haar-like feature cascades by [AdaBoost](https://en.wikipedia.org/wiki/AdaBoost),
+//! following [Viola and Jones](https://doi.org/10.1023/B:VISI.0000013087.49260.fb).
+//!
+//! Training samples are integral images of windows with the same size as the detection
+//! window, e.g. as produced by [`training::integral_images`](../../training/fn.integral_images.html).
+
+use super::{Cascade, HaarFeature, Stage, WeakClassifier};
+use definitions::Image;
+use error::{ImageProcError, Result};
+use image::Luma;
+use std::cmp::Ordering;
+
+/// Options for [`train_cascade`](fn.train_cascade.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CascadeTrainingOptions {
+    /// Maximum number of stages in the cascade.
+    pub max_stages: usize,
+    /// Maximum number of weak classifiers in each stage.
+    pub max_classifiers_per_stage: usize,
+    /// Minimum fraction of positive samples that each stage must accept.
+    pub min_hit_rate: f32,
+    /// Weak classifiers are added to a stage until it accepts at most this fraction
+    /// of the negative samples that reach it, or it reaches `max_classifiers_per_stage`.
+    pub max_false_alarm_rate: f32,
+}
+
+impl Default for CascadeTrainingOptions {
+    fn default() -> Self {
+        CascadeTrainingOptions {
+            max_stages: 10,
+            max_classifiers_per_stage: 50,
+            min_hit_rate: 0.995,
+            max_false_alarm_rate: 0.5,
+        }
+    }
+}
+
+/// Trains a cascade from the integral images of positive and negative sample windows,
+/// choosing each weak classifier from `features` by discrete AdaBoost.
+///
+/// Each stage is trained on all positives and on the negatives accepted by every
+/// previous stage. After each weak classifier is added the stage threshold is lowered
+/// until the stage accepts at least `min_hit_rate` of the positives. Training stops
+/// early if every negative has been rejected, or if no feature classifies the remaining
+/// samples better than chance.
+///
+/// Every feature is evaluated on every sample for each stage, so training time is
+/// proportional to `features.len()` times the number of samples. For large windows,
+/// consider training on a random subset of the features returned by
+/// [`HaarFeature::enumerate`](../struct.HaarFeature.html#method.enumerate). As each weak
+/// classifier can use either polarity, only features with a positive sign are needed.
+///
+/// Returns `ImageProcError::InvalidOptions` if there are no positives, negatives
+/// or features, the samples have different sizes, a feature does not fit in the
+/// samples or is tilted, or the rates are not in the range (0, 1].
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::haar::enumerate_haar_features;
+/// use imageproc::haar::train::{train_cascade, CascadeTrainingOptions};
+/// use imageproc::training::integral_images;
+///
+/// // Positives are brighter on the left than the right, negatives the reverse.
+/// let positives: Vec<GrayImage> = (0..10)
+///     .map(|i| GrayImage::from_fn(4, 4, |x, _| Luma([if x < 2 { 200 + i } else { 50 }])))
+///     .collect();
+/// let negatives: Vec<GrayImage> = (0..10)
+///     .map(|i| GrayImage::from_fn(4, 4, |x, _| Luma([if x < 2 { 50 } else { 200 + i }])))
+///     .collect();
+///
+/// let cascade = train_cascade(
+///     &integral_images(&positives),
+///     &integral_images(&negatives),
+///     &enumerate_haar_features(4, 4),
+///     CascadeTrainingOptions::default()
+/// ).unwrap();
+///
+/// assert_eq!(cascade.window_size(), (4, 4));
+/// assert_eq!(cascade.stages().len(), 1);
+/// # }
+/// ```
+pub fn train_cascade(
+    positives: &[Image<Luma<u32>>],
+    negatives: &[Image<Luma<u32>>],
+    features: &[HaarFeature],
+    options: CascadeTrainingOptions,
+) -> Result<Cascade> {
+    let (window_width, window_height) = validate(positives, negatives, features, options)?;
+
+    let mut stages = vec![];
+    let mut remaining: Vec<&Image<Luma<u32>>> = negatives.iter().collect();
+
+    while stages.len() < options.max_stages && !remaining.is_empty() {
+        let samples: Vec<&Image<Luma<u32>>> = positives.iter().chain(remaining.iter().cloned()).collect();
+        let stage = match train_stage(&samples, positives.len(), features, options) {
+            Some(stage) => stage,
+            None => break,
+        };
+        remaining.retain(|n| stage_accepts(&stage, n));
+        stages.push(stage);
+    }
+
+    Cascade::new(window_width, window_height, stages)
+}
+
+/// Checks inputs and returns the window size.
+fn validate(
+    positives: &[Image<Luma<u32>>],
+    negatives: &[Image<Luma<u32>>],
+    features: &[HaarFeature],
+    options: CascadeTrainingOptions,
+) -> Result<(u32, u32)> {
+    let mut errors = vec![];
+    if positives.is_empty() {
+        errors.push("positives must be non-empty".to_owned());
+    }
+    if negatives.is_empty() {
+        errors.push("negatives must be non-empty".to_owned());
+    }
+    if features.is_empty() {
+        errors.push("features must be non-empty".to_owned());
+    }
+    if !(options.min_hit_rate > 0.0 && options.min_hit_rate <= 1.0) {
+        errors.push(format!("min_hit_rate must be in (0, 1], got {}", options.min_hit_rate));
+    }
+    if !(options.max_false_alarm_rate > 0.0 && options.max_false_alarm_rate <= 1.0) {
+        errors.push(format!("max_false_alarm_rate must be in (0, 1], got {}", options.max_false_alarm_rate));
+    }
+
+    let size = positives.first().map(|p| p.dimensions()).unwrap_or((1, 1));
+    if positives.iter().chain(negatives).any(|s| s.dimensions() != size) {
+        errors.push("samples must all have the same size".to_owned());
+    }
+    let (window_width, window_height) = (size.0.saturating_sub(1), size.1.saturating_sub(1));
+    if features.iter().any(|f| f.extent().0 > window_width || f.extent().1 > window_height) {
+        errors.push(format!("features must fit in a {}x{} window", window_width, window_height));
+    }
+    if features.iter().any(|f| f.is_tilted()) {
+        errors.push("tilted features are not supported".to_owned());
+    }
+
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+    Ok((window_width, window_height))
+}
+
+/// Trains a stage on `samples`, the first `num_positives` of which are positive.
+/// Returns `None` if no feature is better than chance.
+fn train_stage(
+    samples: &[&Image<Luma<u32>>],
+    num_positives: usize,
+    features: &[HaarFeature],
+    options: CascadeTrainingOptions,
+) -> Option<Stage> {
+    let table = FeatureTable::new(features, samples);
+    let num_negatives = samples.len() - num_positives;
+    let is_positive = |i: usize| i < num_positives;
+
+    let mut weights: Vec<f64> = (0..samples.len())
+        .map(|i| if is_positive(i) { 0.5 / num_positives as f64 } else { 0.5 / num_negatives as f64 })
+        .collect();
+    let mut scores = vec![0f32; samples.len()];
+    let mut stage = Stage { classifiers: vec![], threshold: 0.0 };
+
+    while stage.classifiers.len() < options.max_classifiers_per_stage {
+        let total: f64 = weights.iter().sum();
+        for w in weights.iter_mut() {
+            *w /= total;
+        }
+
+        let stump = best_stump(&table, &weights, num_positives);
+        if stump.error >= 0.5 {
+            break;
+        }
+        // Avoid infinite weights for features that separate the samples perfectly.
+        let error = stump.error.max(1e-10);
+        let beta = error / (1.0 - error);
+        let alpha = (1.0 / beta).ln() as f32;
+
+        let classifier = WeakClassifier {
+            feature: features[stump.feature],
+            threshold: stump.threshold,
+            below: if stump.positives_below { alpha } else { 0.0 },
+            above: if stump.positives_below { 0.0 } else { alpha },
+        };
+        for (i, (score, weight)) in scores.iter_mut().zip(weights.iter_mut()).enumerate() {
+            let output = classifier.classify(table.value(stump.feature, i) as f32);
+            *score += output;
+            if (output > 0.0) == is_positive(i) {
+                *weight *= beta;
+            }
+        }
+        stage.classifiers.push(classifier);
+
+        stage.threshold = hit_rate_threshold(&scores[..num_positives], options.min_hit_rate);
+        let false_alarms = scores[num_positives..].iter().filter(|&&s| s >= stage.threshold).count();
+        if false_alarms as f32 <= options.max_false_alarm_rate * num_negatives as f32 {
+            break;
+        }
+    }
+
+    if stage.classifiers.is_empty() { None } else { Some(stage) }
+}
+
+/// The largest threshold for which at least `min_hit_rate` of `scores` are at least the threshold.
+fn hit_rate_threshold(scores: &[f32], min_hit_rate: f32) -> f32 {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let misses = ((1.0 - min_hit_rate) * sorted.len() as f32).floor() as usize;
+    sorted[misses.min(sorted.len() - 1)]
+}
+
+fn stage_accepts(stage: &Stage, integral: &Image<Luma<u32>>) -> bool {
+    let total: f32 = stage
+        .classifiers
+        .iter()
+        .map(|c| c.classify(unsafe { c.feature.evaluate_unchecked(integral) } as f32))
+        .sum();
+    total >= stage.threshold
+}
+
+/// The values of every feature on every sample, and the order of the samples
+/// sorted by each feature's value.
+struct FeatureTable {
+    num_samples: usize,
+    values: Vec<i32>,
+    sorted: Vec<u32>,
+}
+
+impl FeatureTable {
+    /// Requires every feature to fit in every sample.
+    fn new(features: &[HaarFeature], samples: &[&Image<Luma<u32>>]) -> FeatureTable {
+        let num_samples = samples.len();
+        let mut values = Vec::with_capacity(features.len() * num_samples);
+        let mut sorted = Vec::with_capacity(features.len() * num_samples);
+        for feature in features {
+            let start = values.len();
+            values.extend(samples.iter().map(|s| unsafe { feature.evaluate_unchecked(s) }));
+            let row = &values[start..];
+            let mut order: Vec<u32> = (0..num_samples as u32).collect();
+            order.sort_by_key(|&i| row[i as usize]);
+            sorted.extend(order);
+        }
+        FeatureTable { num_samples, values, sorted }
+    }
+
+    fn value(&self, feature: usize, sample: usize) -> i32 {
+        self.values[feature * self.num_samples + sample]
+    }
+
+    fn sorted(&self, feature: usize) -> &[u32] {
+        &self.sorted[feature * self.num_samples..(feature + 1) * self.num_samples]
+    }
+}
+
+/// A threshold on a single feature, and its weighted error.
+struct Stump {
+    feature: usize,
+    threshold: f32,
+    positives_below: bool,
+    error: f64,
+}
+
+/// Finds the stump with the least weighted error.
+fn best_stump(table: &FeatureTable, weights: &[f64], num_positives: usize) -> Stump {
+    let positive_total: f64 = weights[..num_positives].iter().sum();
+    let negative_total: f64 = weights[num_positives..].iter().sum();
+    let mut best = Stump { feature: 0, threshold: 0.0, positives_below: false, error: f64::INFINITY };
+
+    for feature in 0..table.values.len() / table.num_samples.max(1) {
+        let order = table.sorted(feature);
+        let (mut positive_below, mut negative_below) = (0.0, 0.0);
+        // Consider a split before each distinct value, and one after all values.
+        for k in 0..order.len() + 1 {
+            let value = |k: usize| table.value(feature, order[k] as usize);
+            if k == order.len() || k == 0 || value(k) != value(k - 1) {
+                let positives_above_error = positive_below + negative_total - negative_below;
+                let positives_below_error = negative_below + positive_total - positive_below;
+                let threshold = match k {
+                    0 => value(0) as f32,
+                    k if k == order.len() => value(k - 1) as f32 + 1.0,
+                    _ => 0.5 * (value(k - 1) + value(k)) as f32,
+                };
+                if positives_above_error < best.error {
+                    best = Stump { feature, threshold, positives_below: false, error: positives_above_error };
+                }
+                if positives_below_error < best.error {
+                    best = Stump { feature, threshold, positives_below: true, error: positives_below_error };
+                }
+            }
+            if k < order.len() {
+                let i = order[k] as usize;
+                if i < num_positives { positive_below += weights[i] } else { negative_below += weights[i] }
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{detect_objects, enumerate_haar_features, DetectionOptions, HaarFeatureType};
+    use image::GrayImage;
+    use integral_image::integral_image;
+    use rand::{SeedableRng, StdRng};
+    use rand::distributions::{IndependentSample, Range};
+    use rect::Rect;
+    use training::integral_images;
+    use test::{Bencher, black_box};
+
+    /// Noisy 6x6 patches containing a bright horizontal bar between two dark ones if
+    /// `positive`, or uniform noise otherwise.
+    fn patches(count: usize, positive: bool, seed: usize) -> Vec<GrayImage> {
+        let seed_array: &[_] = &[seed];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+        let noise = Range::new(0u8, 60);
+        let level = Range::new(0u8, 196);
+        (0..count)
+            .map(|_| {
+                let base = level.ind_sample(&mut rng);
+                GrayImage::from_fn(6, 6, |_, y| {
+                    let bar = if positive && y >= 2 && y < 4 { 180 } else if positive { 0 } else { base };
+                    Luma([bar.saturating_add(noise.ind_sample(&mut rng))])
+                })
+            })
+            .collect()
+    }
+
+    fn positive_sign_features(width: u8, height: u8) -> Vec<HaarFeature> {
+        HaarFeature::enumerate(width, height).filter(|f| f.is_positive()).collect()
+    }
+
+    #[test]
+    fn test_train_cascade_separates_samples() {
+        let positives = integral_images(&patches(50, true, 1));
+        let negatives = integral_images(&patches(200, false, 2));
+        let options = CascadeTrainingOptions { max_stages: 3, ..CascadeTrainingOptions::default() };
+        let cascade = train_cascade(&positives, &negatives, &positive_sign_features(6, 6), options).unwrap();
+        assert_eq!(cascade.window_size(), (6, 6));
+        assert!(!cascade.stages().is_empty());
+
+        // Evaluate on fresh samples.
+        let accepted = |samples: &[Image<Luma<u32>>]| {
+            samples.iter().filter(|s| cascade.classify(s, 0, 0, 1.0).unwrap()).count()
+        };
+        assert!(accepted(&integral_images(&patches(100, true, 3))) >= 95);
+        assert!(accepted(&integral_images(&patches(100, false, 4))) <= 10);
+    }
+
+    #[test]
+    fn test_trained_cascade_detects_object() {
+        let positives = integral_images(&patches(50, true, 1));
+        let negatives = integral_images(&patches(200, false, 2));
+        let cascade = train_cascade(
+            &positives,
+            &negatives,
+            &positive_sign_features(6, 6),
+            CascadeTrainingOptions::default(),
+        ).unwrap();
+
+        let mut image = GrayImage::new(20, 20);
+        for x in 8..14 {
+            for y in 10..12 {
+                image.put_pixel(x, y, Luma([180]));
+            }
+        }
+        let options = DetectionOptions { scale_factor: 10.0, ..DetectionOptions::default() };
+        let detections = detect_objects(&integral_image(&image), &cascade, options).unwrap();
+        assert!(detections.contains(&Rect::at(8, 8).of_size(6, 6)));
+    }
+
+    #[test]
+    fn test_best_stump() {
+        // One feature, with positives at values 3 and 4 and negatives at 1 and 7.
+        let table = FeatureTable { num_samples: 4, values: vec![3, 4, 1, 7], sorted: vec![2, 0, 1, 3] };
+        let stump = best_stump(&table, &[0.3, 0.3, 0.1, 0.3], 2);
+        assert_eq!(stump.threshold, 5.5);
+        assert_eq!(stump.positives_below, true);
+        assert!((stump.error - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hit_rate_threshold() {
+        let scores = [5.0, 1.0, 3.0, 2.0];
+        assert_eq!(hit_rate_threshold(&scores, 1.0), 1.0);
+        assert_eq!(hit_rate_threshold(&scores, 0.75), 2.0);
+        assert_eq!(hit_rate_threshold(&scores, 0.5), 3.0);
+    }
+
+    #[test]
+    fn test_train_cascade_invalid() {
+        let positives = integral_images(&patches(2, true, 1));
+        let negatives = integral_images(&[GrayImage::new(4, 4)]);
+        let options = CascadeTrainingOptions { min_hit_rate: 0.0, ..CascadeTrainingOptions::default() };
+        assert_eq!(
+            train_cascade(&positives, &negatives, &enumerate_haar_features(7, 1), options),
+            Err(ImageProcError::InvalidOptions(
+                "min_hit_rate must be in (0, 1], got 0, samples must all have the same size, \
+                 features must fit in a 6x6 window".to_owned()
+            ))
+        );
+        let tilted = [HaarFeature::tilted(HaarFeatureType::TwoRegionHorizontal, 1, 1, 0, 0)];
+        assert_eq!(
+            train_cascade(&positives, &positives, &tilted, CascadeTrainingOptions::default()),
+            Err(ImageProcError::InvalidOptions("tilted features are not supported".to_owned()))
+        );
+    }
+
+    #[bench]
+    fn bench_train_cascade(b: &mut Bencher) {
+        let positives = integral_images(&patches(50, true, 1));
+        let negatives = integral_images(&patches(100, false, 2));
+        let features = positive_sign_features(6, 6);
+        let options = CascadeTrainingOptions { max_stages: 2, ..CascadeTrainingOptions::default() };
+        b.iter(|| black_box(train_cascade(&positives, &negatives, &features, options).unwrap()));
+    }
+}
@@ -0,0 +1,279 @@
+//! Perceptual hashes, for detecting duplicate and near-duplicate images.
+//!
+//! Each hash is a 64-bit fingerprint of a grayscale image that changes little under
+//! rescaling, mild blurring or small changes in brightness. Two images can be compared
+//! by taking the [Hamming distance](fn.hamming_distance.html) between their hashes -
+//! small distances indicate similar images.
+
+use image::{GenericImageView, GrayImage};
+use std::f32;
+
+/// Returns the [average hash] of an image.
+///
+/// The image is shrunk to 8x8 pixels and each bit of the hash is set if the corresponding
+/// pixel is brighter than the mean of all 64 pixels. Bits are ordered row-major, with the
+/// most significant bit corresponding to the top left pixel.
+///
+/// [average hash]: http://www.hackerfactor.com/blog/index.php?/archives/432-Looks-Like-It.html
+///
+/// # Panics
+/// If `image` is empty.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::hash::{average_hash, hamming_distance};
+///
+/// // Left half dark, right half bright.
+/// let image = GrayImage::from_fn(16, 16, |x, _| if x < 8 { Luma([20u8]) } else { Luma([200u8]) });
+///
+/// // Each row of the shrunk image contributes the byte 0b00001111.
+/// assert_eq!(average_hash(&image), 0x0f0f_0f0f_0f0f_0f0f);
+///
+/// // Brightening the image doesn't change its hash.
+/// let brighter = GrayImage::from_fn(16, 16, |x, _| if x < 8 { Luma([50u8]) } else { Luma([250u8]) });
+/// assert_eq!(hamming_distance(average_hash(&image), average_hash(&brighter)), 0);
+/// # }
+/// ```
+pub fn average_hash(image: &GrayImage) -> u64 {
+    let pixels = shrink(image, 8, 8);
+    let mean = pixels.iter().sum::<f32>() / pixels.len() as f32;
+    bits_from_predicate(pixels.iter().map(|p| *p > mean))
+}
+
+/// Returns the [difference hash] of an image.
+///
+/// The image is shrunk to 9x8 pixels and each bit of the hash is set if a pixel is
+/// brighter than its right-hand neighbour. Bits are ordered row-major, with the most
+/// significant bit corresponding to the top left pixel.
+///
+/// [difference hash]: http://www.hackerfactor.com/blog/index.php?/archives/529-Kind-of-Like-That.html
+///
+/// # Panics
+/// If `image` is empty.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::hash::difference_hash;
+///
+/// // Intensity decreases from left to right, so every pixel is
+/// // brighter than its right-hand neighbour.
+/// let image = GrayImage::from_fn(18, 8, |x, _| Luma([255 - 10 * x as u8]));
+/// assert_eq!(difference_hash(&image), u64::max_value());
+/// # }
+/// ```
+pub fn difference_hash(image: &GrayImage) -> u64 {
+    let pixels = shrink(image, 9, 8);
+    let gradients = (0..8).flat_map(|y| (0..8).map(move |x| (x, y)))
+        .map(|(x, y)| pixels[y * 9 + x] > pixels[y * 9 + x + 1]);
+    bits_from_predicate(gradients)
+}
+
+/// Returns the DCT-based [perceptual hash] of an image.
+///
+/// The image is shrunk to 32x32 pixels and its two dimensional discrete cosine transform
+/// computed. Each bit of the hash is set if the corresponding coefficient in the lowest 8x8
+/// frequencies is greater than the median of these coefficients, excluding the DC term.
+/// Bits are ordered row-major, with the most significant bit corresponding to the DC term.
+///
+/// This hash is more robust than [`average_hash`](fn.average_hash.html) and
+/// [`difference_hash`](fn.difference_hash.html) to gamma correction and other
+/// non-linear changes in intensity, but is slower to compute.
+///
+/// [perceptual hash]: http://www.hackerfactor.com/blog/index.php?/archives/432-Looks-Like-It.html
+///
+/// # Panics
+/// If `image` is empty.
+pub fn perceptual_hash(image: &GrayImage) -> u64 {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let pixels = shrink(image, SIZE as u32, SIZE as u32);
+
+    // Precompute cos((2x + 1) * u * pi / 2N) for the frequencies we need.
+    let mut basis = vec![0f32; LOW * SIZE];
+    for u in 0..LOW {
+        for x in 0..SIZE {
+            let angle = (2 * x + 1) as f32 * u as f32 * f32::consts::PI / (2 * SIZE) as f32;
+            basis[u * SIZE + x] = angle.cos();
+        }
+    }
+
+    // The DCT is separable, so transform the rows first and then the columns.
+    // Only the lowest LOW frequencies in each direction are ever used.
+    let mut rows = vec![0f32; SIZE * LOW];
+    for y in 0..SIZE {
+        for u in 0..LOW {
+            rows[y * LOW + u] = (0..SIZE).map(|x| pixels[y * SIZE + x] * basis[u * SIZE + x]).sum();
+        }
+    }
+
+    let mut coefficients = vec![0f32; LOW * LOW];
+    for v in 0..LOW {
+        for u in 0..LOW {
+            coefficients[v * LOW + u] = (0..SIZE).map(|y| rows[y * LOW + u] * basis[v * SIZE + y]).sum();
+        }
+    }
+
+    // There are an odd number of coefficients excluding the DC term, so the median is
+    // the middle one. Exactly half of the remaining coefficients are greater than it.
+    let mut sorted: Vec<f32> = coefficients.iter().skip(1).cloned().collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = sorted[sorted.len() / 2];
+
+    bits_from_predicate(coefficients.iter().map(|c| *c > median))
+}
+
+/// Returns the number of bits that differ between two hashes.
+///
+/// As a rough guide, hashes of the same image typically differ in at most
+/// a handful of bits, whereas hashes of unrelated images differ in about half.
+pub fn hamming_distance(left: u64, right: u64) -> u32 {
+    (left ^ right).count_ones()
+}
+
+/// Packs up to 64 booleans into a u64, with the first value in the most significant bit.
+fn bits_from_predicate<I: Iterator<Item = bool>>(bits: I) -> u64 {
+    bits.fold(0u64, |acc, bit| (acc << 1) | bit as u64)
+}
+
+/// Shrinks an image to the given dimensions by averaging over the
+/// block of input pixels corresponding to each output pixel. Returns
+/// the result as a row-major vector of intensities.
+fn shrink(image: &GrayImage, width: u32, height: u32) -> Vec<f32> {
+    let (in_width, in_height) = image.dimensions();
+    assert!(in_width > 0 && in_height > 0, "image must be non-empty");
+
+    let mut out = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        let (top, bottom) = block_bounds(y, height, in_height);
+        for x in 0..width {
+            let (left, right) = block_bounds(x, width, in_width);
+            let mut sum = 0u32;
+            for py in top..bottom {
+                for px in left..right {
+                    sum += unsafe { image.unsafe_get_pixel(px, py)[0] } as u32;
+                }
+            }
+            out.push(sum as f32 / ((right - left) * (bottom - top)) as f32);
+        }
+    }
+    out
+}
+
+/// Returns the half-open range of input coordinates that contribute to the output
+/// coordinate `i` when shrinking a dimension of length `input_len` to `output_len`.
+/// The range always contains at least one element, so this also handles enlargement.
+fn block_bounds(i: u32, output_len: u32, input_len: u32) -> (u32, u32) {
+    let start = (i as u64 * input_len as u64 / output_len as u64) as u32;
+    let end = ((i as u64 + 1) * input_len as u64 / output_len as u64) as u32;
+    (start, end.max(start + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Luma;
+    use noise::gaussian_noise;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn textured_image(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let fx = x as f32 / width as f32;
+            let fy = y as f32 / height as f32;
+            let v = 128.0 + 60.0 * (6.0 * fx).sin() + 60.0 * (4.0 * fy + 2.0 * fx).cos();
+            Luma([v as u8])
+        })
+    }
+
+    #[test]
+    fn test_shrink_exact_multiple() {
+        let image = gray_image!(
+            1, 3, 5, 7;
+            1, 3, 5, 7);
+        assert_eq!(shrink(&image, 2, 1), vec![2.0, 6.0]);
+    }
+
+    #[test]
+    fn test_shrink_enlarges_small_images() {
+        let image = gray_image!(
+            1, 2;
+            3, 4);
+        assert_eq!(shrink(&image, 4, 2), vec![1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_average_hash_rejects_empty_image() {
+        average_hash(&GrayImage::new(0, 0));
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::max_value()), 64);
+    }
+
+    #[test]
+    fn test_hashes_stable_under_resizing() {
+        let small = textured_image(64, 48);
+        let large = textured_image(256, 192);
+
+        assert!(hamming_distance(average_hash(&small), average_hash(&large)) <= 4);
+        assert!(hamming_distance(difference_hash(&small), difference_hash(&large)) <= 4);
+        assert!(hamming_distance(perceptual_hash(&small), perceptual_hash(&large)) <= 4);
+    }
+
+    #[test]
+    fn test_hashes_stable_under_noise() {
+        let image = textured_image(128, 128);
+        let noisy = gaussian_noise(&image, 0.0, 5.0, 1);
+
+        assert!(hamming_distance(average_hash(&image), average_hash(&noisy)) <= 6);
+        assert!(hamming_distance(difference_hash(&image), difference_hash(&noisy)) <= 6);
+        assert!(hamming_distance(perceptual_hash(&image), perceptual_hash(&noisy)) <= 6);
+    }
+
+    #[test]
+    fn test_perceptual_hash_known_value() {
+        let hash = perceptual_hash(&textured_image(64, 64));
+        assert_eq!(hash, 0xcad5_802a_852f_973f);
+        // The median is one of the 63 coefficients other than the DC term,
+        // and 31 of the others are greater than it.
+        assert_eq!((hash & !(1 << 63)).count_ones(), 31);
+    }
+
+    #[test]
+    fn test_perceptual_hash_distinguishes_different_images() {
+        let image = textured_image(128, 128);
+        let flipped = GrayImage::from_fn(128, 128, |x, y| *image.get_pixel(y, x));
+        assert!(hamming_distance(perceptual_hash(&image), perceptual_hash(&flipped)) > 10);
+    }
+
+    #[bench]
+    fn bench_average_hash(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| black_box(average_hash(&image)));
+    }
+
+    #[bench]
+    fn bench_difference_hash(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| black_box(difference_hash(&image)));
+    }
+
+    #[bench]
+    fn bench_perceptual_hash(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| black_box(perceptual_hash(&image)));
+    }
+}
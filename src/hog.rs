@@ -4,12 +4,15 @@
 use image::{GenericImage, GrayImage, ImageBuffer, Luma};
 use gradients::{horizontal_sobel, vertical_sobel};
 use definitions::{Clamp, Image};
+use error::{ImageProcError, Result};
 use math::l2_norm;
-use num::Zero;
+pub use multiarray::{Array3d, View3d};
 use std::f32;
+use std::result;
 
 /// Parameters for HoG descriptors.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HogOptions {
     /// Number of gradient orientation bins.
     pub orientations: usize,
@@ -60,18 +63,18 @@ pub struct HogSpec {
 }
 
 impl HogSpec {
-    /// Returns an error message if image dimensions aren't compatible with the provided options.
-    pub fn from_options(width: u32, height: u32, options: HogOptions) -> Result<HogSpec, String> {
+    /// Returns `ImageProcError::InvalidOptions` if image dimensions aren't compatible with the provided options.
+    pub fn from_options(width: u32, height: u32, options: HogOptions) -> Result<HogSpec> {
         let (cells_wide, cells_high) = try!(Self::checked_cell_dimensions(
             width as usize,
             height as usize,
             options,
-        ));
+        ).map_err(ImageProcError::InvalidOptions));
         let (blocks_wide, blocks_high) = try!(Self::checked_block_dimensions(
             cells_wide,
             cells_high,
             options,
-        ));
+        ).map_err(ImageProcError::InvalidOptions));
         Ok(HogSpec {
             options: options,
             cells_wide: cells_wide,
@@ -90,7 +93,7 @@ impl HogSpec {
         width: usize,
         height: usize,
         options: HogOptions,
-    ) -> Result<(usize, usize), String> {
+    ) -> result::Result<(usize, usize), String> {
         let mut errors: Vec<String> = vec![];
         if width % options.cell_side != 0 {
             errors.push(format!(
@@ -118,7 +121,7 @@ impl HogSpec {
         cells_wide: usize,
         cells_high: usize,
         options: HogOptions,
-    ) -> Result<(usize, usize), String> {
+    ) -> result::Result<(usize, usize), String> {
         let mut errors: Vec<String> = vec![];
         if (cells_wide - options.block_side) % options.block_stride != 0 {
             errors.push(format!(
@@ -205,24 +208,30 @@ fn num_blocks(num_cells: usize, block_side: usize, block_stride: usize) -> usize
     (num_cells + block_stride - block_side) / block_stride
 }
 
-/// Computes the HoG descriptor of an image, or None if the provided
-/// options are incompatible with the image size.
+/// Computes the HoG descriptor of an image, or returns `ImageProcError::InvalidOptions`
+/// if the provided options are incompatible with the image size.
 // TODO: support color images by taking the channel with maximum gradient at each point
-pub fn hog(image: &GrayImage, options: HogOptions) -> Result<Vec<f32>, String> {
-    match HogSpec::from_options(image.width(), image.height(), options) {
-        Err(e) => Err(e),
-        Ok(spec) => {
-            let mut grid: Array3d<f32> = cell_histograms(image, spec);
-            let grid_view = grid.view_mut();
-            let descriptor = hog_descriptor_from_hist_grid(grid_view, spec);
-            Ok(descriptor)
-        }
-    }
+pub fn hog(image: &GrayImage, options: HogOptions) -> Result<Vec<f32>> {
+    hog_blocks(image, options).map(Array3d::into_vec)
+}
+
+/// Computes the normalised block descriptors making up the HoG descriptor of an image,
+/// or returns `ImageProcError::InvalidOptions` if the provided options are incompatible
+/// with the image size.
+///
+/// The innermost dimension of the result is position within a block descriptor,
+/// then horizontal block location, then vertical block location. The data of the
+/// result is the output of [`hog`](fn.hog.html).
+pub fn hog_blocks(image: &GrayImage, options: HogOptions) -> Result<Array3d<f32>> {
+    let spec = HogSpec::from_options(image.width(), image.height(), options)?;
+    let mut grid: Array3d<f32> = cell_histograms(image, spec);
+    let grid_view = grid.view_mut();
+    Ok(hog_descriptor_from_hist_grid(grid_view, spec))
 }
 
-/// Computes the HoG descriptor of an image. Assumes that the spec and grid
+/// Computes the HoG block descriptors of an image. Assumes that the spec and grid
 /// dimensions are consistent.
-fn hog_descriptor_from_hist_grid(grid: View3d<f32>, spec: HogSpec) -> Vec<f32> {
+fn hog_descriptor_from_hist_grid(grid: View3d<f32>, spec: HogSpec) -> Array3d<f32> {
 
     let mut descriptor = Array3d::new(spec.block_grid_lengths());
     {
@@ -259,7 +268,7 @@ fn hog_descriptor_from_hist_grid(grid: View3d<f32>, spec: HogSpec) -> Vec<f32> {
         }
     }
 
-    descriptor.data
+    descriptor
 }
 
 /// L2 norm of the block descriptor at given location within an image descriptor.
@@ -336,7 +345,7 @@ pub fn cell_histograms(image: &GrayImage, spec: HogSpec) -> Array3d<f32> {
 
 /// True if the given outer two indices into a view are within bounds.
 fn contains_outer<T>(view: &View3d<T>, u: usize, v: usize) -> bool {
-    u < view.lengths[1] && v < view.lengths[2]
+    u < view.lengths()[1] && v < view.lengths()[2]
 }
 
 /// Width of an orientation histogram bin in radians.
@@ -395,15 +404,15 @@ impl Interpolation {
 /// horizontal location of the cell, then vertical location of the cell.
 /// Note that we ignore block-level aggregation or normalisation here.
 /// Each rendered star has side length `star_side`, so the image will have
-/// width grid.lengths[1] * `star_side` and height grid.lengths[2] * `star_side`.
+/// width grid.lengths()[1] * `star_side` and height grid.lengths()[2] * `star_side`.
 pub fn render_hist_grid(star_side: u32, grid: &View3d<f32>, signed: bool) -> Image<Luma<u8>> {
-    let width = grid.lengths[1] as u32 * star_side;
-    let height = grid.lengths[2] as u32 * star_side;
+    let width = grid.lengths()[1] as u32 * star_side;
+    let height = grid.lengths()[2] as u32 * star_side;
     let mut out = ImageBuffer::new(width, height);
 
-    for y in 0..grid.lengths[2] {
+    for y in 0..grid.lengths()[2] {
         let y_window = y as u32 * star_side;
-        for x in 0..grid.lengths[1] {
+        for x in 0..grid.lengths()[1] {
             let x_window = x as u32 * star_side;
             let mut window = out.sub_image(x_window, y_window, star_side, star_side);
             let hist = grid.inner_slice(x, y);
@@ -414,6 +423,51 @@ pub fn render_hist_grid(star_side: u32, grid: &View3d<f32>, signed: bool) -> Ima
     out
 }
 
+/// Draws the dominant orientation of each cell of a grid of orientation histograms onto an
+/// image in place, e.g. to overlay the output of [`cell_histograms`](fn.cell_histograms.html)
+/// on the image it was computed from.
+///
+/// The dimensions of `grid` are orientation bucket, horizontal location of the cell, then
+/// vertical location of the cell. For each cell whose histogram is not all zeros, a line
+/// segment of length `cell_side` is drawn through the centre of the cell perpendicular to
+/// the direction of its largest bucket, i.e. along the dominant edge in the cell. Ties are
+/// resolved in favour of the first bucket. Segments are clipped to the image.
+pub fn draw_dominant_orientations_mut<I>(image: &mut I, grid: &Array3d<f32>, cell_side: u32, signed: bool, color: I::Pixel)
+where
+    I: GenericImage,
+    I::Pixel: 'static,
+{
+    use drawing::draw_line_segment_mut;
+
+    let lengths = grid.lengths();
+    let (orientations, cells_wide, cells_high) = (lengths[0], lengths[1], lengths[2]);
+    let range = if signed { 2f32 * f32::consts::PI } else { f32::consts::PI };
+    let half_side = cell_side as f32 / 2f32;
+
+    for y in 0..cells_high {
+        for x in 0..cells_wide {
+            let hist = grid.inner_slice(x, y);
+            let mut dominant = 0;
+            for bucket in 1..orientations {
+                if hist[bucket] > hist[dominant] {
+                    dominant = bucket;
+                }
+            }
+            if hist[dominant] <= 0f32 || hist[dominant].is_nan() {
+                continue;
+            }
+            let edge = range * dominant as f32 / orientations as f32 + f32::consts::FRAC_PI_2;
+            let (dx, dy) = (half_side * edge.cos(), half_side * edge.sin());
+            let centre_x = (x as f32 + 0.5) * cell_side as f32;
+            let centre_y = (y as f32 + 0.5) * cell_side as f32;
+            // Round endpoints so that axis-aligned segments aren't perturbed by rounding errors.
+            let start = ((centre_x - dx).round(), (centre_y - dy).round());
+            let end = ((centre_x + dx).round(), (centre_y + dy).round());
+            draw_line_segment_mut(image, start, end, color);
+        }
+    }
+}
+
 /// Draws a ray from the center of an image in place, in a direction theta radians
 /// clockwise from the y axis (recall that image coordinates increase from
 /// top left to bottom right).
@@ -449,92 +503,23 @@ where
     for bucket in 0..hist.len() {
         if signed {
             let dir = (2f32 * f32::consts::PI * bucket as f32) / orientations;
-            let intensity = u8::clamp(hist[bucket]);
+            let intensity = <u8 as Clamp<_>>::clamp(hist[bucket]);
             draw_ray_mut(image, dir, Luma([intensity]));
         } else {
             let dir = (f32::consts::PI * bucket as f32) / orientations;
-            let intensity = u8::clamp(hist[bucket]);
+            let intensity = <u8 as Clamp<_>>::clamp(hist[bucket]);
             draw_ray_mut(image, dir, Luma([intensity]));
             draw_ray_mut(image, dir + f32::consts::PI, Luma([intensity]));
         }
     }
 }
 
-/// A 3d array that owns its data.
-pub struct Array3d<T> {
-    /// The owned data.
-    data: Vec<T>,
-    /// Lengths of the dimensions, from innermost (i.e. fastest-varying) to outermost.
-    lengths: [usize; 3],
-}
-
-/// A view into a 3d array.
-pub struct View3d<'a, T: 'a> {
-    /// The underlying data.
-    data: &'a mut [T],
-    /// Lengths of the dimensions, from innermost (i.e. fastest-varying) to outermost.
-    lengths: [usize; 3],
-}
-
-impl<T: Zero + Clone> Array3d<T> {
-    /// Allocates a new Array3d with the given dimensions.
-    fn new(lengths: [usize; 3]) -> Array3d<T> {
-        let data = vec![Zero::zero(); data_length(lengths)];
-        Array3d {
-            data: data,
-            lengths: lengths,
-        }
-    }
-
-    /// Provides a 3d view of the data.
-    pub fn view_mut(&mut self) -> View3d<T> {
-        View3d::from_raw(&mut self.data, self.lengths)
-    }
-}
-
-impl<'a, T> View3d<'a, T> {
-    /// Constructs index from existing data and the lengths of the desired dimensions.
-    fn from_raw(data: &'a mut [T], lengths: [usize; 3]) -> View3d<'a, T> {
-        View3d {
-            data: data,
-            lengths: lengths,
-        }
-    }
-
-    /// A mutable reference from a 3d index.
-    fn at_mut(&mut self, indices: [usize; 3]) -> &mut T {
-        &mut self.data[self.offset(indices)]
-    }
-
-    /// All entries with the given outer dimensions. As the first dimension
-    /// is fastest varying, this is a contiguous slice.
-    fn inner_slice(&self, x1: usize, x2: usize) -> &[T] {
-        let offset = self.offset([0, x1, x2]);
-        &self.data[offset..offset + self.lengths[0]]
-    }
-
-    /// All entries with the given outer dimensions. As the first dimension
-    /// is fastest varying, this is a contiguous slice.
-    fn inner_slice_mut(&mut self, x1: usize, x2: usize) -> &mut [T] {
-        let offset = self.offset([0, x1, x2]);
-        &mut self.data[offset..offset + self.lengths[0]]
-    }
-
-    fn offset(&self, indices: [usize; 3]) -> usize {
-        indices[2] * self.lengths[1] * self.lengths[0] + indices[1] * self.lengths[0] + indices[0]
-    }
-}
-
-/// Length of array needed for the given dimensions.
-fn data_length(lengths: [usize; 3]) -> usize {
-    lengths[0] * lengths[1] * lengths[2]
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
     use utils::gray_bench_image;
     use test;
+    use image::{GrayImage, Luma};
 
     #[test]
     fn test_num_blocks() {
@@ -591,7 +576,7 @@ mod test {
 			block stride 2 does not evenly divide (cells high 7 - block side 4)";
         assert_eq!(
             HogSpec::from_options(21, 21, opts),
-            Err(expected.to_owned())
+            Err(ImageProcError::InvalidOptions(expected.to_owned()))
         );
     }
 
@@ -673,7 +658,7 @@ mod test {
             copy(&[6f32, 1f32, 4f32], br);
         }
 
-        let descriptor = hog_descriptor_from_hist_grid(view, spec);
+        let descriptor = hog_descriptor_from_hist_grid(view, spec).into_vec();
         assert_eq!(descriptor.len(), 16);
 
         let counts = [1, 3, 2, 3, 5, 0, 3, 7, 2, 3, 0, 1, 3, 7, 6, 1];
@@ -722,6 +707,27 @@ mod test {
         test::black_box(desc_unsigned.unwrap());
     }
 
+    #[test]
+    fn test_draw_dominant_orientations() {
+        // Two cells with four unsigned orientation buckets. The dominant gradient of the
+        // left cell is horizontal and the dominant gradient of the right cell is vertical.
+        let grid = Array3d::from_vec(vec![5f32, 1f32, 0f32, 0f32, 0f32, 1f32, 3f32, 0f32], [4, 2, 1]);
+
+        let mut canvas = GrayImage::new(8, 4);
+        draw_dominant_orientations_mut(&mut canvas, &grid, 4, false, Luma([255]));
+        let expected = gray_image!(
+            0,   0, 255,   0,   0,   0,   0,   0;
+            0,   0, 255,   0,   0,   0,   0,   0;
+            0,   0, 255,   0, 255, 255, 255, 255;
+            0,   0, 255,   0,   0,   0,   0,   0);
+        assert_pixels_eq!(canvas, expected);
+
+        // Cells with empty histograms are not drawn.
+        let mut canvas = GrayImage::new(8, 4);
+        draw_dominant_orientations_mut(&mut canvas, &Array3d::new([4, 2, 1]), 4, false, Luma([255]));
+        assert_pixels_eq!(canvas, GrayImage::new(8, 4));
+    }
+
     #[bench]
     fn bench_hog(b: &mut test::Bencher) {
         let image = gray_bench_image(88, 88);
@@ -1,24 +1,28 @@
-//! Line detection via the [Hough transform].
+//! Line and circle detection via the [Hough transform].
 //!
 //! [Hough transform]: https://en.wikipedia.org/wiki/Hough_transform
 
 use image::{GenericImage, GenericImageView, GrayImage, Luma, ImageBuffer, Pixel};
 use drawing::draw_line_segment_mut;
 use definitions::Image;
+use geometry::LineSegment;
+use rand::{Rng, SeedableRng, StdRng};
 use suppress::suppress_non_maximum;
 use std::f32;
 
-/// A detected line, in polar coordinates.
+/// A detected line, in polar coordinates. The line is the set of points `(x, y)` with
+/// `x * cos(angle) + y * sin(angle) == r`.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct PolarLine {
-    /// Distance of the line from the origin (top-left of the image), in pixels.
+    /// Signed distance of the line from the origin (top-left of the image), in pixels.
     pub r: f32,
-    /// Clockwise angle in degrees between the x-axis and the line.
-    pub angle_in_degrees: u32,
+    /// Clockwise angle in degrees between the x-axis and the normal to the line,
+    /// in the range `[0, 180)`.
+    pub angle_in_degrees: f32,
 }
 
 /// Options for Hough line detection.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct LineDetectionOptions {
     /// Number of votes required to be detected as a line.
     pub vote_threshold: u32,
@@ -27,44 +31,97 @@ pub struct LineDetectionOptions {
     /// block centred on them of side length `2 * suppression_radius + 1`
     /// are returned. Set to `0` if you don't want to apply non-maxima suppression.
     pub suppression_radius: u32,
+    /// Height in degrees of each accumulator bucket. Detected lines have angles which
+    /// are multiples of this value. Must be positive.
+    pub angle_resolution_in_degrees: f32,
 }
 
 /// Detects lines in a binary input image using the Hough transform.
 ///
 /// Points are considered to be in the foreground (and thus vote for lines)
-/// if their intensity is non-zero.
+/// if their intensity is non-zero. Each foreground point votes once for each
+/// angle, in the bucket of width 1 pixel containing the distance from the origin
+/// of the line through the point at that angle. When non-maxima suppression compares
+/// buckets with equal numbers of votes, it favours the bucket whose votes lie closest
+/// to its centre.
+///
+/// Lines are returned in order of increasing angle, then increasing `r`.
 ///
 /// See ./examples/hough.rs for example usage.
+///
+/// # Panics
+/// If `options.angle_resolution_in_degrees` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::hough::{detect_lines, LineDetectionOptions, PolarLine};
+///
+/// let image = gray_image!(
+///     0, 0, 0, 0, 0;
+///     0, 0, 0, 0, 0;
+///     1, 1, 1, 1, 1;
+///     0, 0, 0, 0, 0);
+///
+/// let options = LineDetectionOptions {
+///     vote_threshold: 5,
+///     suppression_radius: 20,
+///     angle_resolution_in_degrees: 0.5,
+/// };
+///
+/// assert_eq!(
+///     detect_lines(&image, options),
+///     vec![PolarLine { r: 2.0, angle_in_degrees: 90.0 }]);
+/// # }
+/// ```
 pub fn detect_lines(image: &GrayImage, options: LineDetectionOptions) -> Vec<PolarLine> {
-    let (width, height) = image.dimensions();
-
-    // The maximum possible radius is the diagonal of the image.
-    let rmax = ((width * width + height * height) as f64).sqrt() as i32;
-
-    // Measure angles in degrees, and use bins of width 1 pixel and height 1 degree.
-    let mut acc: ImageBuffer<Luma<u32>, Vec<u32>> = ImageBuffer::new(rmax as u32, 180u32);
+    let resolution = options.angle_resolution_in_degrees;
+    assert!(resolution > 0.0, "angle_resolution_in_degrees must be positive");
 
-    let cos_lut: Vec<f32> = (0..180u32).map(|m| degrees_to_radians(m).cos()).collect();
-    let sin_lut: Vec<f32> = (0..180u32).map(|m| degrees_to_radians(m).sin()).collect();
+    let (width, height) = image.dimensions();
+    let num_angles = (180.0 / resolution).ceil() as u32;
+
+    // The maximum possible distance from the origin is the diagonal of the image,
+    // and the distance is negative for some angles greater than 90 degrees.
+    let rmax = ((width as f64).hypot(height as f64)).ceil() as i32;
+
+    // Use bins of width 1 pixel and height `resolution` degrees, with a column
+    // per integer distance from -rmax to rmax.
+    //
+    // Short lines vote equally for several neighbouring angles, so each bin stores its
+    // vote count in the upper 32 bits and a score in the lower 32 bits measuring how close
+    // its votes fall to the centre of the bin. Non-maxima suppression then favours the
+    // bins which best fit the points voting for them.
+    let mut acc: ImageBuffer<Luma<u64>, Vec<u64>> = ImageBuffer::new(2 * rmax as u32 + 1, num_angles);
+    let max_closeness = max_closeness(width, height);
+
+    let angle = |m: u32| m as f32 * resolution;
+    let cos_lut: Vec<f32> = (0..num_angles).map(|m| degrees_to_radians(angle(m)).cos()).collect();
+    let sin_lut: Vec<f32> = (0..num_angles).map(|m| degrees_to_radians(angle(m)).sin()).collect();
 
     for y in 0..height {
         for x in 0..width {
             let p = unsafe { image.unsafe_get_pixel(x, y)[0] };
 
             if p > 0 {
-                for m in 0..180u32 {
+                for m in 0..num_angles {
                     let fy = y as f32;
                     let fx = x as f32;
 
-                    let r = unsafe {
-                        (fx * *cos_lut.get_unchecked(m as usize) +
-                             fy * *sin_lut.get_unchecked(m as usize)) as i32
+                    let exact_r = unsafe {
+                        fx * *cos_lut.get_unchecked(m as usize) +
+                            fy * *sin_lut.get_unchecked(m as usize)
                     };
+                    let (r, vote) = weighted_vote(exact_r, max_closeness);
 
-                    if r < rmax && r >= 0 {
+                    if r <= rmax && r >= -rmax {
+                        let column = (r + rmax) as u32;
                         unsafe {
-                            let vote_incr = acc.unsafe_get_pixel(r as u32, m)[0] + 1;
-                            acc.unsafe_put_pixel(r as u32, m, Luma([vote_incr]));
+                            let vote_incr = acc.unsafe_get_pixel(column, m)[0] + vote;
+                            acc.unsafe_put_pixel(column, m, Luma([vote_incr]));
                         }
                     }
                 }
@@ -77,12 +134,12 @@ pub fn detect_lines(image: &GrayImage, options: LineDetectionOptions) -> Vec<Pol
     let mut lines = Vec::new();
 
     for m in 0..acc_sup.height() {
-        for r in 0..acc_sup.width() {
-            let votes = unsafe { acc_sup.unsafe_get_pixel(r, m)[0] };
-            if votes >= options.vote_threshold {
+        for column in 0..acc_sup.width() {
+            let votes = unsafe { (acc_sup.unsafe_get_pixel(column, m)[0] >> 32) as u32 };
+            if votes >= options.vote_threshold && votes > 0 {
                 let line = PolarLine {
-                    r: r as f32,
-                    angle_in_degrees: m,
+                    r: column as f32 - rmax as f32,
+                    angle_in_degrees: angle(m),
                 };
                 lines.push(line);
             }
@@ -92,6 +149,364 @@ pub fn detect_lines(image: &GrayImage, options: LineDetectionOptions) -> Vec<Pol
     lines
 }
 
+/// Returns the accumulator bucket for a vote for the line at distance `exact_r` from the
+/// origin, and the amount to add to the bucket. The upper 32 bits of the amount count
+/// the vote and the lower 32 bits score how close `exact_r` is to the centre of the bucket,
+/// from 0 up to `max_closeness`.
+fn weighted_vote(exact_r: f32, max_closeness: f32) -> (i32, u64) {
+    let r = exact_r.round() as i32;
+    let residual = exact_r - r as f32;
+    let closeness = ((0.25 - residual * residual) * 4.0 * max_closeness) as u64;
+    (r, (1 << 32) + closeness)
+}
+
+/// The largest closeness score of a single vote from an image of the given size. Each pixel
+/// votes at most once for each bucket, so limiting the score of a single vote to
+/// 2^32 / (width * height) ensures that scores never carry into the vote count.
+fn max_closeness(width: u32, height: u32) -> f32 {
+    (u32::max_value() as u64 / (width as u64 * height as u64).max(1)).min(65535) as f32
+}
+
+/// Options for probabilistic Hough line segment detection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineSegmentDetectionOptions {
+    /// Number of votes required for a line to be searched for segments.
+    pub vote_threshold: u32,
+    /// Segments shorter than this, in pixels, are discarded.
+    pub min_line_length: u32,
+    /// Largest number of consecutive background pixels allowed between two
+    /// foreground pixels of the same segment.
+    pub max_line_gap: u32,
+    /// Height in degrees of each accumulator bucket. Must be positive.
+    pub angle_resolution_in_degrees: f32,
+}
+
+/// Detects line segments in a binary input image using the progressive probabilistic
+/// Hough transform of Matas, Galambos and Kittler.
+///
+/// Foreground points (those with non-zero intensity) are processed in a random order,
+/// each voting for lines through it as in [`detect_lines`](fn.detect_lines.html). When a
+/// point takes the votes of some line to `options.vote_threshold`, the line is followed
+/// in both directions from the point through foreground pixels, allowing gaps of up to
+/// `options.max_line_gap` pixels. The foreground pixels passed are removed from further
+/// consideration, and if the resulting segment is at least `options.min_line_length` pixels
+/// long then it is returned and the votes from its pixels are removed from the accumulator.
+///
+/// Segment endpoints are foreground pixels. The order of the segments, and for ambiguous
+/// inputs the segments themselves, depend on `seed`.
+///
+/// # Panics
+/// If `options.angle_resolution_in_degrees` is not positive.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::draw_line_segment_mut;
+/// use imageproc::geometry::LineSegment;
+/// use imageproc::hough::{detect_line_segments, LineSegmentDetectionOptions};
+///
+/// let mut image = GrayImage::new(40, 30);
+/// draw_line_segment_mut(&mut image, (5.0, 20.0), (25.0, 20.0), Luma([255]));
+///
+/// let options = LineSegmentDetectionOptions {
+///     vote_threshold: 10,
+///     min_line_length: 10,
+///     max_line_gap: 2,
+///     angle_resolution_in_degrees: 1.0,
+/// };
+///
+/// let segments = detect_line_segments(&image, options, 1);
+/// assert_eq!(segments.len(), 1);
+///
+/// // The endpoints may be returned in either order.
+/// let segment = segments[0];
+/// let (start, end) = if segment.start.0 < segment.end.0 {
+///     (segment.start, segment.end)
+/// } else {
+///     (segment.end, segment.start)
+/// };
+/// assert_eq!((start, end), ((5.0, 20.0), (25.0, 20.0)));
+/// # }
+/// ```
+pub fn detect_line_segments(
+    image: &GrayImage,
+    options: LineSegmentDetectionOptions,
+    seed: usize,
+) -> Vec<LineSegment> {
+    let resolution = options.angle_resolution_in_degrees;
+    assert!(resolution > 0.0, "angle_resolution_in_degrees must be positive");
+
+    let (width, height) = image.dimensions();
+    let num_angles = (180.0 / resolution).ceil() as usize;
+    let rmax = ((width as f64).hypot(height as f64)).ceil() as i32;
+    let num_distances = 2 * rmax as usize + 1;
+    let max_closeness = max_closeness(width, height);
+
+    let angles: Vec<f32> = (0..num_angles).map(|m| degrees_to_radians(m as f32 * resolution)).collect();
+    let cos_lut: Vec<f32> = angles.iter().map(|a| a.cos()).collect();
+    let sin_lut: Vec<f32> = angles.iter().map(|a| a.sin()).collect();
+    let vote = |x: i32, y: i32, m: usize| {
+        let (r, vote) = weighted_vote(x as f32 * cos_lut[m] + y as f32 * sin_lut[m], max_closeness);
+        (m * num_distances + (r + rmax) as usize, vote)
+    };
+
+    let index = |x: i32, y: i32| (y as u32 * width + x as u32) as usize;
+    let in_bounds = |x: i32, y: i32| x >= 0 && y >= 0 && x < width as i32 && y < height as i32;
+
+    let mut points: Vec<(i32, i32)> = image
+        .enumerate_pixels()
+        .filter(|&(_, _, p)| p[0] > 0)
+        .map(|(x, y, _)| (x as i32, y as i32))
+        .collect();
+    let seed_array: &[_] = &[seed];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+    rng.shuffle(&mut points);
+
+    // Foreground pixels not yet assigned to a segment, and those which have voted.
+    let mut remaining: Vec<bool> = image.iter().map(|p| *p > 0).collect();
+    let mut voted = vec![false; remaining.len()];
+    // Votes are weighted as in detect_lines, so that the line chosen through
+    // a point best fits the points voting for it.
+    let mut acc = vec![0u64; num_angles * num_distances];
+    let mut segments = Vec::new();
+
+    for &(x, y) in &points {
+        if !remaining[index(x, y)] {
+            continue;
+        }
+
+        let mut best_votes = 0;
+        let mut best_angle = 0;
+        for m in 0..num_angles {
+            let (bucket, v) = vote(x, y, m);
+            acc[bucket] += v;
+            if acc[bucket] > best_votes {
+                best_votes = acc[bucket];
+                best_angle = m;
+            }
+        }
+        voted[index(x, y)] = true;
+
+        if ((best_votes >> 32) as u32) < options.vote_threshold {
+            continue;
+        }
+
+        // Step along the line one pixel at a time in its major direction.
+        let (dx, dy) = (-sin_lut[best_angle], cos_lut[best_angle]);
+        let scale = f32::max(dx.abs(), dy.abs());
+        let (dx, dy) = (dx / scale, dy / scale);
+        let step = |k: i32, sign: f32| {
+            (
+                (x as f32 + sign * k as f32 * dx).round() as i32,
+                (y as f32 + sign * k as f32 * dy).round() as i32,
+            )
+        };
+
+        let mut ends = [(x, y), (x, y)];
+        for (end, &sign) in ends.iter_mut().zip(&[1.0, -1.0]) {
+            let mut gap = 0;
+            for k in 1.. {
+                let (px, py) = step(k, sign);
+                if !in_bounds(px, py) {
+                    break;
+                }
+                if remaining[index(px, py)] {
+                    *end = (px, py);
+                    gap = 0;
+                } else {
+                    gap += 1;
+                    if gap > options.max_line_gap {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let length = ((ends[0].0 - ends[1].0) as f32).hypot((ends[0].1 - ends[1].1) as f32);
+        let is_segment = length >= options.min_line_length as f32;
+
+        for (end, &sign) in ends.iter().zip(&[1.0, -1.0]) {
+            for k in 0.. {
+                let (px, py) = step(k, sign);
+                let i = index(px, py);
+                if remaining[i] {
+                    if is_segment && voted[i] {
+                        for m in 0..num_angles {
+                            let (bucket, v) = vote(px, py, m);
+                            acc[bucket] -= v;
+                        }
+                    }
+                    remaining[i] = false;
+                }
+                if (px, py) == *end {
+                    break;
+                }
+            }
+        }
+
+        if is_segment {
+            segments.push(LineSegment::new(
+                (ends[1].0 as f32, ends[1].1 as f32),
+                (ends[0].0 as f32, ends[0].1 as f32),
+            ));
+        }
+    }
+
+    segments
+}
+
+/// A detected circle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Circle {
+    /// Location of the centre of the circle.
+    pub center: (u32, u32),
+    /// Radius of the circle, in pixels.
+    pub radius: u32,
+    /// Number of foreground pixels lying on the circle.
+    pub votes: u32,
+}
+
+/// Options for Hough circle detection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CircleDetectionOptions {
+    /// Smallest radius to search for. Must be at least 1.
+    pub min_radius: u32,
+    /// Largest radius to search for. Must be at least `min_radius`.
+    pub max_radius: u32,
+    /// Fraction of the pixels on a circle which must be in the foreground for it to be
+    /// detected. A fraction rather than a number of votes is used so that circles of
+    /// different radii can be compared.
+    pub vote_fraction: f32,
+    /// Circles are only returned if no circle with a higher fraction of votes has a
+    /// centre within this many pixels horizontally and vertically of theirs, and a radius
+    /// within this many pixels of theirs.
+    pub suppression_radius: u32,
+}
+
+/// Detects circles in a binary input image using the Hough transform.
+///
+/// Each foreground point (one with non-zero intensity) votes, for each radius between
+/// `options.min_radius` and `options.max_radius` inclusive, for every centre at that
+/// distance from it. Only centres within the image are considered. The pixels on a circle
+/// are those drawn by [`draw_hollow_circle_mut`](../drawing/fn.draw_hollow_circle_mut.html).
+///
+/// Circles are returned in decreasing order of the fraction of their pixels which vote for
+/// them, with ties broken by radius, then by centre.
+///
+/// Performs `O(N * R^2)` operations, where `N` is the number of foreground pixels and `R`
+/// is `options.max_radius`.
+///
+/// # Panics
+/// If `options.min_radius` is 0 or greater than `options.max_radius`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::draw_hollow_circle_mut;
+/// use imageproc::hough::{detect_circles, Circle, CircleDetectionOptions};
+///
+/// let mut image = GrayImage::new(50, 40);
+/// draw_hollow_circle_mut(&mut image, (20, 18), 12, Luma([255]));
+///
+/// let options = CircleDetectionOptions {
+///     min_radius: 5,
+///     max_radius: 15,
+///     vote_fraction: 0.8,
+///     suppression_radius: 3,
+/// };
+///
+/// let circles = detect_circles(&image, options);
+/// assert_eq!(circles.len(), 1);
+/// assert_eq!(circles[0].center, (20, 18));
+/// assert_eq!(circles[0].radius, 12);
+/// # }
+/// ```
+pub fn detect_circles(image: &GrayImage, options: CircleDetectionOptions) -> Vec<Circle> {
+    assert!(options.min_radius > 0, "min_radius must be at least 1");
+    assert!(options.max_radius >= options.min_radius, "max_radius must be at least min_radius");
+
+    let (width, height) = image.dimensions();
+    let points: Vec<(i32, i32)> = image
+        .enumerate_pixels()
+        .filter(|&(_, _, p)| p[0] > 0)
+        .map(|(x, y, _)| (x as i32, y as i32))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for radius in options.min_radius..options.max_radius + 1 {
+        let offsets = circle_offsets(radius as i32);
+        let mut acc: ImageBuffer<Luma<u32>, Vec<u32>> = ImageBuffer::new(width, height);
+        for &(x, y) in &points {
+            for &(dx, dy) in &offsets {
+                let (cx, cy) = (x - dx, y - dy);
+                if cx >= 0 && cy >= 0 && cx < width as i32 && cy < height as i32 {
+                    let votes = acc.get_pixel_mut(cx as u32, cy as u32);
+                    votes[0] += 1;
+                }
+            }
+        }
+
+        let min_votes = (options.vote_fraction * offsets.len() as f32).ceil() as u32;
+        let acc = suppress_non_maximum(&acc, options.suppression_radius);
+        for (x, y, votes) in acc.enumerate_pixels() {
+            if votes[0] >= min_votes && votes[0] > 0 {
+                let circle = Circle { center: (x, y), radius, votes: votes[0] };
+                candidates.push((votes[0] as f32 / offsets.len() as f32, circle));
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0).unwrap()
+            .then(a.1.radius.cmp(&b.1.radius))
+            .then((a.1.center.1, a.1.center.0).cmp(&(b.1.center.1, b.1.center.0)))
+    });
+
+    // Suppression across radii.
+    let near = |a: u32, b: u32| (a as i64 - b as i64).abs() <= options.suppression_radius as i64;
+    let mut circles: Vec<Circle> = Vec::new();
+    for (_, candidate) in candidates {
+        let suppressed = circles.iter().any(|c| {
+            near(c.center.0, candidate.center.0)
+                && near(c.center.1, candidate.center.1)
+                && near(c.radius, candidate.radius)
+        });
+        if !suppressed {
+            circles.push(candidate);
+        }
+    }
+    circles
+}
+
+/// Offsets from its centre of the distinct pixels drawn by `draw_hollow_circle_mut`.
+fn circle_offsets(radius: i32) -> Vec<(i32, i32)> {
+    let mut offsets = Vec::new();
+    let mut x = radius;
+    let mut y = 0i32;
+    let mut err = 0i32;
+    while x >= y {
+        offsets.extend_from_slice(&[
+            (x, y), (y, x), (-y, x), (-x, y),
+            (-x, -y), (-y, -x), (y, -x), (x, -y),
+        ]);
+        y += 1;
+        err += 1 + 2 * y;
+        if 2 * (err - x) + 1 > 0 {
+            x -= 1;
+            err += 1 - 2 * x;
+        }
+    }
+    offsets.sort();
+    offsets.dedup();
+    offsets
+}
+
 /// Draws each element of `lines` on `image` in the provided `color`.
 ///
 /// See ./examples/hough.rs for example usage.
@@ -134,32 +549,33 @@ fn intersection_points(
     image_height: u32,
 ) -> Option<((f32, f32), (f32, f32))> {
     let r = line.r;
-    let m = line.angle_in_degrees;
     let w = image_width as f32;
     let h = image_height as f32;
 
+    let theta = degrees_to_radians(line.angle_in_degrees);
+    let sin = theta.sin();
+    let cos = theta.cos();
+
     // Vertical line
-    if m == 0 {
-        return if r < h {
-            Some(((r, 0.0), (r, h)))
+    if sin.abs() < 1e-6 {
+        let x = r / cos;
+        return if x >= 0.0 && x < w {
+            Some(((x, 0.0), (x, h)))
         } else {
             None
         };
     }
 
     // Horizontal line
-    if m == 90 {
-        return if r < w {
-            Some(((0.0, r), (w, r)))
+    if cos.abs() < 1e-6 {
+        let y = r / sin;
+        return if y >= 0.0 && y < h {
+            Some(((0.0, y), (w, y)))
         } else {
             None
         };
     }
 
-    let theta = degrees_to_radians(m);
-    let sin = theta.sin();
-    let cos = theta.cos();
-
     let right_y = (r - w * cos) / sin;
     let left_y = r / sin;
     let bottom_x = (r - h * sin) / cos;
@@ -201,14 +617,15 @@ fn intersection_points(
     None
 }
 
-fn degrees_to_radians(degrees: u32) -> f32 {
-    degrees as f32 * f32::consts::PI / 180.0
+fn degrees_to_radians(degrees: f32) -> f32 {
+    degrees * f32::consts::PI / 180.0
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use image::{GrayImage, ImageBuffer, Luma};
+    use drawing::draw_hollow_circle_mut;
     use test::{Bencher, black_box};
 
     fn separated_horizontal_line_segment() -> GrayImage {
@@ -229,6 +646,7 @@ mod test {
         let options = LineDetectionOptions {
             vote_threshold: 11,
             suppression_radius: 0,
+            angle_resolution_in_degrees: 1.0,
         };
         let detected = detect_lines(&image, options);
         assert_eq!(detected.len(), 0);
@@ -240,12 +658,197 @@ mod test {
         let options = LineDetectionOptions {
             vote_threshold: 10,
             suppression_radius: 8,
+            angle_resolution_in_degrees: 1.0,
         };
         let detected = detect_lines(&image, options);
         assert_eq!(detected.len(), 1);
         let line = detected[0];
-        assert_eq!(line.r, 1f32);
-        assert_eq!(line.angle_in_degrees, 90);
+        assert_eq!(line.r, 2f32);
+        assert_eq!(line.angle_in_degrees, 90f32);
+    }
+
+    #[test]
+    fn detect_lines_negative_distance() {
+        // The anti-diagonal x + y == 4 has angle 45 degrees and r == 4 / sqrt(2), and the
+        // diagonal x == y has angle 135 degrees and r == 0. Lines just above 135 degrees
+        // pass through the image with negative r.
+        let mut image = GrayImage::new(5, 5);
+        for i in 0..5 {
+            image.put_pixel(i, 4 - i, Luma([255u8]));
+            image.put_pixel(i, i, Luma([255u8]));
+        }
+        let options = LineDetectionOptions {
+            vote_threshold: 5,
+            suppression_radius: 2,
+            angle_resolution_in_degrees: 1.0,
+        };
+        let detected = detect_lines(&image, options);
+        assert_eq!(detected, vec![
+            PolarLine { r: 3.0, angle_in_degrees: 45.0 },
+            PolarLine { r: 0.0, angle_in_degrees: 135.0 },
+        ]);
+
+        // The line x - y == 2 has r == -sqrt(2).
+        let mut image = GrayImage::new(40, 40);
+        for i in 0..38 {
+            image.put_pixel(i + 2, i, Luma([255u8]));
+        }
+        let options = LineDetectionOptions { vote_threshold: 30, ..options };
+        let detected = detect_lines(&image, options);
+        assert_eq!(detected, vec![PolarLine { r: -1.0, angle_in_degrees: 135.0 }]);
+    }
+
+    #[test]
+    fn detect_lines_angle_resolution() {
+        let image = separated_horizontal_line_segment();
+        let options = LineDetectionOptions {
+            vote_threshold: 10,
+            suppression_radius: 8,
+            angle_resolution_in_degrees: 7.5,
+        };
+        let detected = detect_lines(&image, options);
+        assert_eq!(detected, vec![PolarLine { r: 2.0, angle_in_degrees: 90.0 }]);
+    }
+
+    #[test]
+    fn detect_lines_many_votes_per_bin() {
+        // Every pixel votes for the line x = 0, enough times for unscaled
+        // closeness scores to overflow into the vote count.
+        let image = GrayImage::from_pixel(1, 70_000, Luma([1u8]));
+        let options = |vote_threshold| LineDetectionOptions {
+            vote_threshold,
+            suppression_radius: 0,
+            angle_resolution_in_degrees: 90.0,
+        };
+        assert_eq!(
+            detect_lines(&image, options(70_000)),
+            vec![PolarLine { r: 0.0, angle_in_degrees: 0.0 }]
+        );
+        assert_eq!(detect_lines(&image, options(70_001)), vec![]);
+    }
+
+    #[test]
+    fn draw_polar_lines_negative_distance() {
+        let image = GrayImage::new(4, 4);
+        let lines = [
+            PolarLine { r: -1.0, angle_in_degrees: 135.0 },
+            PolarLine { r: 1.0, angle_in_degrees: 0.0 },
+        ];
+        let drawn = draw_polar_lines(&image, &lines, Luma([1u8]));
+        let expected = gray_image!(
+            0, 1, 0, 0;
+            0, 1, 1, 0;
+            0, 1, 0, 1;
+            0, 1, 0, 0);
+        assert_pixels_eq!(drawn, expected);
+    }
+
+    fn sorted_endpoints(segments: &[LineSegment]) -> Vec<((f32, f32), (f32, f32))> {
+        let mut endpoints: Vec<((f32, f32), (f32, f32))> = segments
+            .iter()
+            .map(|s| {
+                if (s.start.0, s.start.1) <= (s.end.0, s.end.1) { (s.start, s.end) } else { (s.end, s.start) }
+            })
+            .collect();
+        endpoints.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        endpoints
+    }
+
+    fn segment_options(min_line_length: u32, max_line_gap: u32) -> LineSegmentDetectionOptions {
+        LineSegmentDetectionOptions {
+            vote_threshold: 12,
+            min_line_length,
+            max_line_gap,
+            angle_resolution_in_degrees: 1.0,
+        }
+    }
+
+    #[test]
+    fn detect_line_segments_horizontal_and_vertical() {
+        let mut image = GrayImage::new(50, 40);
+        for x in 5..31 {
+            image.put_pixel(x, 10, Luma([255u8]));
+        }
+        for y in 5..36 {
+            image.put_pixel(40, y, Luma([255u8]));
+        }
+        for seed in 0..10 {
+            let segments = detect_line_segments(&image, segment_options(10, 2), seed);
+            assert_eq!(
+                sorted_endpoints(&segments),
+                vec![((5.0, 10.0), (30.0, 10.0)), ((40.0, 5.0), (40.0, 35.0))]);
+        }
+    }
+
+    #[test]
+    fn detect_line_segments_gaps() {
+        // Two diagonal runs separated by a gap of three pixels.
+        let mut image = GrayImage::new(40, 40);
+        for i in (2..14).chain(17..30) {
+            image.put_pixel(i, i, Luma([255u8]));
+        }
+        let joined = detect_line_segments(&image, segment_options(5, 3), 7);
+        assert_eq!(sorted_endpoints(&joined), vec![((2.0, 2.0), (29.0, 29.0))]);
+
+        let split = detect_line_segments(&image, segment_options(5, 2), 7);
+        assert_eq!(
+            sorted_endpoints(&split),
+            vec![((2.0, 2.0), (13.0, 13.0)), ((17.0, 17.0), (29.0, 29.0))]);
+
+        // Both runs are too short.
+        let short = detect_line_segments(&image, segment_options(20, 2), 7);
+        assert!(short.is_empty());
+    }
+
+    #[test]
+    fn detect_line_segments_empty_image() {
+        assert!(detect_line_segments(&GrayImage::new(0, 0), segment_options(1, 1), 0).is_empty());
+        assert!(detect_line_segments(&GrayImage::new(10, 10), segment_options(1, 1), 0).is_empty());
+    }
+
+    #[test]
+    fn circle_offsets_match_draw_hollow_circle() {
+        for radius in 1..12 {
+            let center = (radius + 1) as i32;
+            let side = 2 * radius + 3;
+            let mut image = GrayImage::new(side, side);
+            draw_hollow_circle_mut(&mut image, (center, center), radius as i32, Luma([1u8]));
+            let mut drawn: Vec<(i32, i32)> = image
+                .enumerate_pixels()
+                .filter(|&(_, _, p)| p[0] > 0)
+                .map(|(x, y, _)| (x as i32 - center, y as i32 - center))
+                .collect();
+            drawn.sort();
+            assert_eq!(circle_offsets(radius as i32), drawn);
+        }
+    }
+
+    #[test]
+    fn detect_circles_multiple_radii() {
+        let mut image = GrayImage::new(60, 50);
+        draw_hollow_circle_mut(&mut image, (15, 15), 8, Luma([255u8]));
+        draw_hollow_circle_mut(&mut image, (40, 30), 14, Luma([255u8]));
+        // Concentric with the first circle, but with a gap.
+        draw_hollow_circle_mut(&mut image, (15, 15), 4, Luma([255u8]));
+        for x in 11..20 {
+            image.put_pixel(x, 11, Luma([0u8]));
+        }
+
+        let options = CircleDetectionOptions {
+            min_radius: 3,
+            max_radius: 16,
+            vote_fraction: 0.9,
+            suppression_radius: 2,
+        };
+        let circles = detect_circles(&image, options);
+        let found: Vec<((u32, u32), u32)> = circles.iter().map(|c| (c.center, c.radius)).collect();
+        assert_eq!(found, vec![((15, 15), 8), ((40, 30), 14)]);
+        assert!(circles.iter().all(|c| c.votes == circle_offsets(c.radius as i32).len() as u32));
+
+        let options = CircleDetectionOptions { vote_fraction: 0.7, ..options };
+        let circles = detect_circles(&image, options);
+        let found: Vec<((u32, u32), u32)> = circles.iter().map(|c| (c.center, c.radius)).collect();
+        assert_eq!(found, vec![((15, 15), 8), ((40, 30), 14), ((15, 15), 4)]);
     }
 
     // TODO: This is an exact duplicate of a function in tbe regionlabelling tests.
@@ -265,6 +868,7 @@ mod test {
         let options = LineDetectionOptions {
             vote_threshold: 10,
             suppression_radius: 3,
+            angle_resolution_in_degrees: 1.0,
         };
 
         b.iter(|| {
@@ -272,4 +876,40 @@ mod test {
             black_box(lines);
         });
     }
+
+    #[bench]
+    fn bench_detect_line_segments(b: &mut Bencher) {
+        let image = chessboard(200, 200);
+
+        let options = LineSegmentDetectionOptions {
+            vote_threshold: 10,
+            min_line_length: 20,
+            max_line_gap: 2,
+            angle_resolution_in_degrees: 1.0,
+        };
+
+        b.iter(|| {
+            let segments = detect_line_segments(&image, options, 1);
+            black_box(segments);
+        });
+    }
+
+    #[bench]
+    fn bench_detect_circles(b: &mut Bencher) {
+        let mut image = GrayImage::new(100, 100);
+        draw_hollow_circle_mut(&mut image, (40, 50), 25, Luma([255u8]));
+        draw_hollow_circle_mut(&mut image, (70, 30), 12, Luma([255u8]));
+
+        let options = CircleDetectionOptions {
+            min_radius: 10,
+            max_radius: 30,
+            vote_fraction: 0.8,
+            suppression_radius: 3,
+        };
+
+        b.iter(|| {
+            let circles = detect_circles(&image, options);
+            black_box(circles);
+        });
+    }
 }
@@ -1,9 +1,14 @@
 //! Functions for computing [integral images](https://en.wikipedia.org/wiki/Summed_area_table)
 //! and running sums of rows and columns.
 
-use image::{Luma, GrayImage, GenericImageView, Pixel};
-use definitions::Image;
+use image::{Luma, GrayImage, GenericImageView, Pixel, Primitive};
+use definitions::{Image, MaybeSend, MaybeSync};
+use error::{check_dimensions, ImageProcError, Result};
 use map::{ChannelMap, WithChannel};
+use parallel::for_each_row;
+use rect::Rect;
+use simd::{self, cast_slice, cast_slice_mut, is_type};
+use std::mem;
 
 /// Computes the 2d running sum of an image. Channels are summed independently.
 ///
@@ -48,7 +53,7 @@ use map::{ChannelMap, WithChannel};
 /// ```
 pub fn integral_image<P>(image: &Image<P>) -> Image<ChannelMap<P, u32>>
 where
-    P: Pixel<Subpixel = u8> + WithChannel<u32> + 'static
+    P: Pixel<Subpixel = u8> + WithChannel<u32> + MaybeSync + 'static
 {
     integral_image_impl(image, false)
 }
@@ -86,50 +91,223 @@ where
 /// ```
 pub fn integral_squared_image<P>(image: &Image<P>) -> Image<ChannelMap<P, u32>>
 where
-    P: Pixel<Subpixel = u8> + WithChannel<u32> + 'static
+    P: Pixel<Subpixel = u8> + WithChannel<u32> + MaybeSync + 'static
 {
     integral_image_impl(image, true)
 }
 
-/// Implementation of `integral_image` and `integral_squared_image`.
-fn integral_image_impl<P>(image: &Image<P>, square: bool) -> Image<ChannelMap<P, u32>>
+/// Computes the running sum of an image over triangles bounded by lines at 45 degrees.
+/// Channels are summed independently.
+///
+/// A rotated integral image R has width and height one greater than its source image F,
+/// and is defined by R(x, y) = sum of F(x', y') for y' < y, |x' - x + 1| < y - y', i.e.
+/// each pixel in the rotated integral image contains the sum of the pixel intensities of
+/// input pixel (x - 1, y - 1) and of all input pixels above it and within 45 degrees of the
+/// vertical through it.
+///
+/// Rotated integral images allow the sum of the pixel intensities in a rectangle rotated
+/// by 45 degrees to be computed in constant time, and are used to evaluate tilted
+/// [Haar-like features](../haar/struct.HaarFeature.html#method.tilted).
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::integral_image::rotated_integral_image;
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let integral = gray_image!(type: u32,
+///     0,  0,  0,  0;
+///     0,  1,  2,  3;
+///     1,  7, 11, 11);
+///
+/// assert_pixels_eq!(rotated_integral_image(&image), integral);
+/// # }
+/// ```
+pub fn rotated_integral_image<P>(image: &Image<P>) -> Image<ChannelMap<P, u32>>
 where
     P: Pixel<Subpixel = u8> + WithChannel<u32> + 'static
 {
-    // TODO: Make faster, add a new IntegralImage type
-    // TODO: to make it harder to make off-by-one errors when computing sums of regions.
+    let (in_width, in_height) = image.dimensions();
+    let mut out = Image::<ChannelMap<P, u32>>::new(in_width + 1, in_height + 1);
+
+    if in_width == 0 || in_height == 0 {
+        return out;
+    }
+
+    // Triangles whose apex lies outside the image may still contain image pixels, so sums
+    // are computed for `in_height` additional columns on either side of the output.
+    let channels = P::channel_count() as usize;
+    let padding = in_height as usize;
+    let padded_width = in_width as usize + 1 + 2 * padding;
+    let pixel = |x: usize, y: u32, c: usize| -> u32 {
+        // x is a padded column index for the output, so x - padding - 1 is the input column.
+        if x > padding && x <= padding + in_width as usize {
+            image.get_pixel((x - padding - 1) as u32, y).channels()[c] as u32
+        } else {
+            0
+        }
+    };
+
+    // Sums for the current row and the two rows above it.
+    let mut two_above = vec![0u32; padded_width * channels];
+    let mut above = vec![0u32; padded_width * channels];
+    let mut current = vec![0u32; padded_width * channels];
+
+    for y in 1..in_height + 1 {
+        for x in 0..padded_width {
+            for c in 0..channels {
+                let left = if x > 0 { above[(x - 1) * channels + c] } else { 0 };
+                let right = if x + 1 < padded_width { above[(x + 1) * channels + c] } else { 0 };
+                let mut sum = left + right + pixel(x, y - 1, c);
+                if y > 1 {
+                    sum += pixel(x, y - 2, c);
+                }
+                current[x * channels + c] = sum - two_above[x * channels + c];
+            }
+        }
+        for x in 0..in_width + 1 {
+            let start = (x as usize + padding) * channels;
+            out.get_pixel_mut(x, y).channels_mut().copy_from_slice(&current[start..start + channels]);
+        }
+        mem::swap(&mut two_above, &mut above);
+        mem::swap(&mut above, &mut current);
+    }
+
+    out
+}
+
+/// Computes the 2d running sum of an image, as for [`integral_image`](fn.integral_image.html),
+/// with sums of type `T`. Channels are summed independently.
+///
+/// The sums in an integral image with `u32` channels overflow for images with more than
+/// 2^24 pixels, and those in an integral image of squared intensities overflow for images with
+/// more than 2^16 pixels. Using `u64` or `f64` sums avoids this for all practical image sizes.
+///
+/// Unlike `integral_image`, this function accepts images of any subpixel type that can be
+/// converted to `T`, e.g. `u16` images with `u64` sums or `f32` images with `f64` sums.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::integral_image::{integral_image_as, sum_image_pixels};
+/// use imageproc::definitions::Image;
+/// use image::Luma;
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let integral: Image<Luma<f64>> = integral_image_as(&image);
+/// assert_eq!(sum_image_pixels(&integral, 1, 0, 2, 1), 2.0 + 3.0 + 5.0 + 6.0);
+/// # }
+/// ```
+pub fn integral_image_as<T, P>(image: &Image<P>) -> Image<ChannelMap<P, T>>
+where
+    P: Pixel + WithChannel<T> + MaybeSync + 'static,
+    P::Subpixel: MaybeSync,
+    T: Primitive + From<P::Subpixel> + MaybeSend + 'static
+{
+    integral_image_impl(image, false)
+}
+
+/// Computes the 2d running sum of the squares of the intensities in an image, as for
+/// [`integral_squared_image`](fn.integral_squared_image.html), with sums of type `T`.
+/// Channels are summed independently.
+///
+/// See [`integral_image_as`](fn.integral_image_as.html) for when to use this function.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::integral_image::{integral_squared_image_as, sum_image_pixels};
+/// use imageproc::definitions::Image;
+/// use image::{GrayImage, Luma};
+///
+/// // The sum of the squared intensities is too large to fit in a u32.
+/// let image = GrayImage::from_pixel(300, 300, Luma([255u8]));
+///
+/// let integral: Image<Luma<u64>> = integral_squared_image_as(&image);
+/// assert_eq!(sum_image_pixels(&integral, 0, 0, 299, 299), 255 * 255 * 300 * 300);
+/// # }
+/// ```
+pub fn integral_squared_image_as<T, P>(image: &Image<P>) -> Image<ChannelMap<P, T>>
+where
+    P: Pixel + WithChannel<T> + MaybeSync + 'static,
+    P::Subpixel: MaybeSync,
+    T: Primitive + From<P::Subpixel> + MaybeSend + 'static
+{
+    integral_image_impl(image, true)
+}
+
+/// Implementation of the integral image functions.
+fn integral_image_impl<P, T>(image: &Image<P>, square: bool) -> Image<ChannelMap<P, T>>
+where
+    P: Pixel + WithChannel<T> + MaybeSync + 'static,
+    P::Subpixel: MaybeSync,
+    T: Primitive + From<P::Subpixel> + MaybeSend + 'static
+{
     let (in_width, in_height) = image.dimensions();
     let out_width = in_width + 1;
     let out_height = in_height + 1;
 
-    let mut out = Image::<ChannelMap<P, u32>>::new(out_width, out_height);
+    let mut out = Image::<ChannelMap<P, T>>::new(out_width, out_height);
 
     if in_width == 0 || in_height == 0 {
         return out;
     }
 
-    for y in 1..out_height {
-        let mut sum = vec![0u32; P::channel_count() as usize];
-        for x in 1..out_width {
-            unsafe {
-                for c in 0..P::channel_count() {
-                    let pix = image.unsafe_get_pixel(x - 1, y - 1).channels()[c as usize] as u32;
-                    if square {
-                        sum[c as usize] += pix * pix;
-                    } else {
-                        sum[c as usize] += pix;
-                    }
-                }
+    let channels = P::channel_count() as usize;
+    let in_row_len = in_width as usize * channels;
+    let out_row_len = out_width as usize * channels;
 
-                let above = out.unsafe_get_pixel(x, y - 1);
-                // For some reason there's no unsafe_get_pixel_mut, so to update the existing
-                // pixel here we need to use the method with bounds checking
-                let mut current = out.get_pixel_mut(x, y);
-                for c in 0..P::channel_count() {
-                    current.channels_mut()[c as usize] = above.channels()[c as usize] + sum[c as usize];
-                }
+    // Each row of the output first holds the running sums along the input row above
+    // it. Rows are independent, so this pass can be run in parallel.
+    let input: &[P::Subpixel] = image;
+    for_each_row(&mut out, |y, row| {
+        if y == 0 {
+            return;
+        }
+        let start = (y as usize - 1) * in_row_len;
+        let in_row = &input[start..start + in_row_len];
+
+        let mut sum = vec![T::zero(); channels];
+        let mut start = 0;
+        if channels == 1 && is_type::<P::Subpixel, u8>() && is_type::<T, u32>() {
+            let sums = cast_slice_mut::<T, u32>(&mut row[1..]);
+            start = simd::prefix_sum_u8_u32(cast_slice(in_row), square, 0, sums);
+            if start > 0 {
+                sum[0] = row[start];
             }
         }
+        let in_row = &in_row[start * channels..];
+        for (current, pixel) in row[(start + 1) * channels..].chunks_mut(channels).zip(in_row.chunks(channels)) {
+            for c in 0..channels {
+                let value: T = pixel[c].into();
+                sum[c] = sum[c] + if square { value * value } else { value };
+                current[c] = sum[c];
+            }
+        }
+    });
+
+    // Accumulate the row sums down each column.
+    for y in 1..out_height as usize {
+        let (above, rest) = out.split_at_mut(y * out_row_len);
+        let above = &above[(y - 1) * out_row_len..];
+        let current = &mut rest[..out_row_len];
+        for (current, above) in current.iter_mut().zip(above) {
+            *current = *above + *current;
+        }
     }
 
     out
@@ -139,20 +317,21 @@ where
 /// integral image of F.
 ///
 /// See the [`integral_image`](fn.integral_image.html) documentation for examples.
-pub fn sum_image_pixels(
-    integral_image: &Image<Luma<u32>>,
+pub fn sum_image_pixels<T>(
+    integral_image: &Image<Luma<T>>,
     left: u32,
     top: u32,
     right: u32,
     bottom: u32,
-) -> u32 {
+) -> T
+where
+    T: Primitive + 'static
+{
     // TODO: better type-safety. It's too easy to pass the original image in here by mistake.
     // TODO: it's also hard to see what the four u32s mean at the call site - use a Rect instead.
-    let sum = integral_image.get_pixel(right + 1, bottom + 1)[0] as i32 -
-        integral_image.get_pixel(right + 1, top)[0] as i32 -
-        integral_image.get_pixel(left, bottom + 1)[0] as i32 +
-        integral_image.get_pixel(left, top)[0] as i32;
-    sum as u32
+    // The positive terms are summed first so that unsigned sums cannot underflow.
+    (integral_image.get_pixel(right + 1, bottom + 1)[0] + integral_image.get_pixel(left, top)[0])
+        - (integral_image.get_pixel(right + 1, top)[0] + integral_image.get_pixel(left, bottom + 1)[0])
 }
 
 /// Computes the variance of [left, right] * [top, bottom] in F, where `integral_image` is the
@@ -202,6 +381,313 @@ pub fn variance(
     (sum_sq as f64 - (sum as f64).powi(2) / n) / n
 }
 
+/// Computes the mean and variance of the pixel intensities in `rect`, where `integral_image`
+/// is the integral image of an image and `integral_squared_image` is the integral image of the
+/// squares of its intensities. This is used to normalise sliding windows for changes in
+/// brightness and contrast, e.g. when evaluating
+/// [Haar-like features](../haar/struct.HaarFeature.html#method.evaluate_normalized).
+///
+/// Returns `ImageProcError::DimensionMismatch` if the integral images have different sizes,
+/// and `ImageProcError::OutOfBounds` if `rect` does not lie within the image.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::integral_image::{integral_image, integral_squared_image, window_mean_and_variance};
+/// use imageproc::rect::Rect;
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let integral = integral_image(&image);
+/// let integral_squared = integral_squared_image(&image);
+///
+/// // The right two columns.
+/// let rect = Rect::at(1, 0).of_size(2, 2);
+/// assert_eq!(window_mean_and_variance(&integral, &integral_squared, rect), Ok((4.0, 2.5)));
+/// assert!(window_mean_and_variance(&integral, &integral_squared, rect.translate(1, 0)).is_err());
+/// # }
+/// ```
+pub fn window_mean_and_variance<T, U>(
+    integral_image: &Image<Luma<T>>,
+    integral_squared_image: &Image<Luma<U>>,
+    rect: Rect,
+) -> Result<(f64, f64)>
+where
+    T: Primitive + 'static,
+    U: Primitive + 'static
+{
+    check_dimensions(integral_image.dimensions(), integral_squared_image.dimensions())?;
+    let (width, height) = integral_image.dimensions();
+    let (width, height) = (width.saturating_sub(1), height.saturating_sub(1));
+    if rect.left() < 0 || rect.top() < 0 || rect.right() as i64 >= width as i64 || rect.bottom() as i64 >= height as i64 {
+        return Err(ImageProcError::OutOfBounds { region: rect, image: (width, height) });
+    }
+
+    let (left, top, right, bottom) = (rect.left() as u32, rect.top() as u32, rect.right() as u32, rect.bottom() as u32);
+    let n = rect.area() as f64;
+    let sum = sum_image_pixels(integral_image, left, top, right, bottom).to_f64().unwrap();
+    let sum_sq = sum_image_pixels(integral_squared_image, left, top, right, bottom).to_f64().unwrap();
+    let mean = sum / n;
+    // Rounding errors can make the variance of a constant region very slightly negative.
+    let variance = (sum_sq / n - mean * mean).max(0.0);
+    Ok((mean, variance))
+}
+
+/// An integral image of an 8bpp grayscale image that owns its sums and can be updated
+/// incrementally when part of the source image changes.
+///
+/// Updating a region only recomputes the sums below and to the right of its top-left
+/// corner, which is much cheaper than recomputing the whole integral image when processing
+/// video in which only a small part of each frame changes. Sums are stored as `u64`, so
+/// cannot overflow for any image that fits in memory.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::integral_image::IntegralImage;
+/// use imageproc::rect::Rect;
+///
+/// let mut image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let mut integral = IntegralImage::new(&image);
+/// let right_columns = Rect::at(1, 0).of_size(2, 2);
+/// assert_eq!(integral.sum_image_pixels(right_columns), Ok(2 + 3 + 5 + 6));
+///
+/// image.put_pixel(2, 1, Luma([16]));
+/// integral.update_region(&image, Rect::at(2, 1).of_size(1, 1)).unwrap();
+/// assert_eq!(integral.sum_image_pixels(right_columns), Ok(2 + 3 + 5 + 16));
+/// assert_eq!(integral, IntegralImage::new(&image));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntegralImage {
+    width: u32,
+    height: u32,
+    /// The (width + 1) * (height + 1) sums, in row-major order.
+    sums: Vec<u64>,
+}
+
+impl IntegralImage {
+    /// Computes the integral image of an image.
+    pub fn new(image: &GrayImage) -> IntegralImage {
+        let (width, height) = image.dimensions();
+        let sums = vec![0u64; (width as usize + 1) * (height as usize + 1)];
+        let mut integral = IntegralImage { width, height, sums };
+        integral.recompute(image, 0, 0);
+        integral
+    }
+
+    /// The (width, height) of the image this was computed from.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the sum of the pixels in `rect`.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if `rect` does not lie within the image.
+    pub fn sum_image_pixels(&self, rect: Rect) -> Result<u64> {
+        self.check_bounds(rect)?;
+        let (left, top) = (rect.left() as usize, rect.top() as usize);
+        let (right, bottom) = (rect.right() as usize + 1, rect.bottom() as usize + 1);
+        let at = |x: usize, y: usize| self.sums[y * (self.width as usize + 1) + x];
+        // The positive terms are summed first so that the sums cannot underflow.
+        Ok((at(right, bottom) + at(left, top)) - (at(right, top) + at(left, bottom)))
+    }
+
+    /// Updates the sums after the pixels of `source` in `rect` have changed. All pixels
+    /// of `source` outside `rect` must be unchanged since this integral image was computed
+    /// or last updated.
+    ///
+    /// Returns `ImageProcError::DimensionMismatch` if `source` does not have the same
+    /// dimensions as the original image, and `ImageProcError::OutOfBounds` if `rect`
+    /// does not lie within the image.
+    pub fn update_region(&mut self, source: &GrayImage, rect: Rect) -> Result<()> {
+        check_dimensions((self.width, self.height), source.dimensions())?;
+        self.check_bounds(rect)?;
+        self.recompute(source, rect.left() as u32, rect.top() as u32);
+        Ok(())
+    }
+
+    /// Recomputes the sums for all pixels of `source` at or below and to the right
+    /// of `(left, top)`.
+    fn recompute(&mut self, source: &GrayImage, left: u32, top: u32) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let stride = width + 1;
+        let left = left as usize;
+        for y in top as usize..height {
+            let (above, rest) = self.sums.split_at_mut((y + 1) * stride);
+            let above = &above[y * stride..];
+            let current = &mut rest[..stride];
+            // The sum of the pixels in this row to the left of the updated region
+            // is unchanged.
+            let mut row_sum = current[left] - above[left];
+            for x in left..width {
+                row_sum += unsafe { source.unsafe_get_pixel(x as u32, y as u32)[0] } as u64;
+                current[x + 1] = above[x + 1] + row_sum;
+            }
+        }
+    }
+
+    fn check_bounds(&self, rect: Rect) -> Result<()> {
+        if rect.left() < 0 || rect.top() < 0
+            || rect.right() as i64 >= self.width as i64 || rect.bottom() as i64 >= self.height as i64 {
+            return Err(ImageProcError::OutOfBounds { region: rect, image: (self.width, self.height) });
+        }
+        Ok(())
+    }
+}
+
+/// Per-bin integral images of an image's intensity histogram, allowing the histogram of
+/// any rectangular region to be computed in time proportional to the number of bins rather
+/// than the size of the region. This speeds up algorithms that compare the histograms of
+/// many overlapping windows, such as matching histograms of
+/// [local binary patterns](../local_binary_patterns/index.html).
+///
+/// Intensities are split into `num_bins` bins of (almost) equal width, with intensity `i`
+/// in bin `i * num_bins / 256`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::integral_image::IntegralHistogram;
+/// use imageproc::rect::Rect;
+///
+/// let image = gray_image!(
+///       0, 100, 200;
+///      50, 150, 250);
+///
+/// let histogram = IntegralHistogram::new(&image, 2).unwrap();
+///
+/// // The right two columns contain one dark pixel and three bright pixels.
+/// let rect = Rect::at(1, 0).of_size(2, 2);
+/// assert_eq!(histogram.histogram(rect), Ok(vec![1, 3]));
+/// assert!(histogram.histogram(rect.translate(1, 0)).is_err());
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntegralHistogram {
+    width: u32,
+    height: u32,
+    num_bins: usize,
+    /// Counts for each position of the (width + 1) * (height + 1) integral images,
+    /// with the bin varying fastest.
+    counts: Vec<u32>,
+}
+
+impl IntegralHistogram {
+    /// Computes the integral histogram of an image.
+    ///
+    /// Returns `ImageProcError::InvalidOptions` if `num_bins` is not in the range [1, 256].
+    pub fn new(image: &GrayImage, num_bins: usize) -> Result<IntegralHistogram> {
+        if num_bins == 0 || num_bins > 256 {
+            return Err(ImageProcError::InvalidOptions(format!("num_bins must be in the range [1, 256], got {}", num_bins)));
+        }
+        let (width, height) = image.dimensions();
+        let stride = (width as usize + 1) * num_bins;
+        let mut counts = vec![0u32; stride * (height as usize + 1)];
+
+        for y in 0..height as usize {
+            let (above, rest) = counts.split_at_mut((y + 1) * stride);
+            let above = &above[y * stride..];
+            let current = &mut rest[..stride];
+            // Running counts along the row, plus the counts for all rows above.
+            let mut row = vec![0u32; num_bins];
+            for x in 0..width as usize {
+                let intensity = unsafe { image.unsafe_get_pixel(x as u32, y as u32)[0] };
+                row[intensity as usize * num_bins / 256] += 1;
+                let start = (x + 1) * num_bins;
+                for b in 0..num_bins {
+                    current[start + b] = above[start + b] + row[b];
+                }
+            }
+        }
+
+        Ok(IntegralHistogram { width, height, num_bins, counts })
+    }
+
+    /// The number of bins in each histogram.
+    pub fn num_bins(&self) -> usize {
+        self.num_bins
+    }
+
+    /// The (width, height) of the image this was computed from.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The bin containing the given intensity.
+    pub fn bin(&self, intensity: u8) -> usize {
+        intensity as usize * self.num_bins / 256
+    }
+
+    /// Returns the number of pixels in `rect` whose intensity lies in each bin.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if `rect` does not lie within the image.
+    pub fn histogram(&self, rect: Rect) -> Result<Vec<u32>> {
+        let mut out = vec![0; self.num_bins];
+        self.histogram_into(rect, &mut out)?;
+        Ok(out)
+    }
+
+    /// Writes the histogram of the pixels in `rect` into `out`. See
+    /// [`histogram`](#method.histogram).
+    ///
+    /// # Panics
+    /// If `out.len()` is not equal to `num_bins()`.
+    pub fn histogram_into(&self, rect: Rect, out: &mut [u32]) -> Result<()> {
+        assert_eq!(out.len(), self.num_bins, "output length must equal the number of bins");
+        if rect.left() < 0 || rect.top() < 0
+            || rect.right() as i64 >= self.width as i64 || rect.bottom() as i64 >= self.height as i64 {
+            return Err(ImageProcError::OutOfBounds { region: rect, image: (self.width, self.height) });
+        }
+        let (left, top) = (rect.left() as usize, rect.top() as usize);
+        let (right, bottom) = (rect.right() as usize + 1, rect.bottom() as usize + 1);
+        let at = |x: usize, y: usize| ((y * (self.width as usize + 1)) + x) * self.num_bins;
+        let (tl, tr, bl, br) = (at(left, top), at(right, top), at(left, bottom), at(right, bottom));
+        for b in 0..self.num_bins {
+            // The positive terms are summed first so that the counts cannot underflow.
+            out[b] = (self.counts[br + b] + self.counts[tl + b]) - (self.counts[tr + b] + self.counts[bl + b]);
+        }
+        Ok(())
+    }
+
+    /// Returns an approximation to the median intensity of the pixels in `rect`: the middle
+    /// intensity of the bin containing the median. With 256 bins this is the exact median,
+    /// taking the lower of the two middle values for regions with an even number of pixels.
+    ///
+    /// Returns `ImageProcError::OutOfBounds` if `rect` does not lie within the image.
+    pub fn approximate_median(&self, rect: Rect) -> Result<u8> {
+        let histogram = self.histogram(rect)?;
+        let half = (rect.area() as u32 + 1) / 2;
+        let mut cumulative = 0;
+        let mut bin = 0;
+        for (b, count) in histogram.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= half {
+                bin = b;
+                break;
+            }
+        }
+        // The smallest intensity in a bin b is ceil(256 * b / num_bins).
+        let lowest = |b: usize| (256 * b + self.num_bins - 1) / self.num_bins;
+        Ok(((lowest(bin) + lowest(bin + 1) - 1) / 2) as u8)
+    }
+}
+
 /// Computes the running sum of one row of image, padded
 /// at the beginning and end. The padding is by continuity.
 /// Takes a reference to buffer so that this can be reused
@@ -251,9 +737,17 @@ pub fn row_running_sum(image: &GrayImage, row: u32, buffer: &mut [u32], padding:
             *buffer.get_unchecked_mut(x as usize) = sum;
         }
 
-        for x in 0..width {
-            sum += image.unsafe_get_pixel(x, row)[0] as u32;
-            *buffer.get_unchecked_mut((x + padding) as usize) = sum;
+        let raw: &[u8] = image;
+        let start = row as usize * width as usize;
+        let pixels = &raw[start..start + width as usize];
+        let sums = &mut buffer[padding as usize..(padding + width) as usize];
+        let n = simd::prefix_sum_u8_u32(pixels, false, sum, sums);
+        if n > 0 {
+            sum = sums[n - 1];
+        }
+        for (s, p) in sums[n..].iter_mut().zip(&pixels[n..]) {
+            sum += *p as u32;
+            *s = sum;
         }
 
         for x in 0..padding {
@@ -330,7 +824,7 @@ mod test {
     use super::*;
     use property_testing::GrayTestImage;
     use utils::{gray_bench_image, pixel_diff_summary, rgb_bench_image};
-    use image::{GenericImage, ImageBuffer, Luma};
+    use image::{GenericImage, ImageBuffer, Luma, Rgb};
     use quickcheck::{quickcheck, TestResult};
     use definitions::Image;
     use test;
@@ -439,6 +933,267 @@ mod test {
         quickcheck(prop as fn(GrayTestImage) -> TestResult);
     }
 
+    #[test]
+    fn test_integral_image_as_matches_integral_image() {
+        let image = rgb_bench_image(20, 15);
+        let expected = integral_image(&image);
+        let actual: Image<Rgb<u64>> = integral_image_as(&image);
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert_eq!(*e as u64, *a);
+        }
+
+        let expected = integral_squared_image(&image);
+        let actual: Image<Rgb<f64>> = integral_squared_image_as(&image);
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert_eq!(*e as f64, *a);
+        }
+    }
+
+    #[test]
+    fn test_integral_image_as_u16_and_f32() {
+        let image = gray_image!(type: u16,
+            1000, 60000;
+            65535, 2);
+        let integral: Image<Luma<u64>> = integral_image_as(&image);
+        assert_eq!(integral.get_pixel(2, 2)[0], 1000 + 60000 + 65535 + 2);
+        let squared: Image<Luma<u64>> = integral_squared_image_as(&image);
+        assert_eq!(sum_image_pixels(&squared, 0, 1, 1, 1), 65535 * 65535 + 4);
+
+        let image = gray_image!(type: f32,
+            0.5, -1.25;
+            2.0, 0.25);
+        let integral: Image<Luma<f64>> = integral_image_as(&image);
+        assert_eq!(sum_image_pixels(&integral, 1, 0, 1, 1), -1.0);
+        let squared: Image<Luma<f64>> = integral_squared_image_as(&image);
+        assert_eq!(sum_image_pixels(&squared, 0, 0, 0, 1), 4.25);
+    }
+
+    #[test]
+    fn test_integral_squared_image_as_large_image() {
+        // The sum of the squared intensities of this image exceeds u32::MAX.
+        let image = GrayImage::from_pixel(400, 400, Luma([255u8]));
+        let integral: Image<Luma<u64>> = integral_squared_image_as(&image);
+        assert_eq!(integral.get_pixel(400, 400)[0], 255 * 255 * 400 * 400);
+        assert_eq!(sum_image_pixels(&integral, 100, 200, 399, 399), 255 * 255 * 300 * 200);
+    }
+
+    #[bench]
+    fn bench_integral_squared_image_as_f64(b: &mut test::Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let integral: Image<Luma<f64>> = integral_squared_image_as(&image);
+            test::black_box(integral);
+        });
+    }
+
+    #[test]
+    fn test_window_mean_and_variance() {
+        let image = gray_bench_image(9, 7);
+        let integral = integral_image(&image);
+        let integral_squared: Image<Luma<f64>> = integral_squared_image_as(&image);
+
+        let rect = Rect::at(2, 1).of_size(5, 4);
+        let (mean, var) = window_mean_and_variance(&integral, &integral_squared, rect).unwrap();
+        let expected_var = variance(&integral, &integral_squared_image(&image), 2, 1, 6, 4);
+        let expected_mean = sum_image_pixels(&integral, 2, 1, 6, 4) as f64 / 20.0;
+        assert_eq!(mean, expected_mean);
+        assert!((var - expected_var).abs() < 1e-9);
+
+        let whole = Rect::at(0, 0).of_size(9, 7);
+        assert!(window_mean_and_variance(&integral, &integral_squared, whole).is_ok());
+        assert_eq!(
+            window_mean_and_variance(&integral, &integral_squared, whole.translate(-1, 0)),
+            Err(ImageProcError::OutOfBounds { region: whole.translate(-1, 0), image: (9, 7) })
+        );
+        assert_eq!(
+            window_mean_and_variance(&integral, &integral_squared, Rect::at(0, 0).of_size(9, 8)),
+            Err(ImageProcError::OutOfBounds { region: Rect::at(0, 0).of_size(9, 8), image: (9, 7) })
+        );
+
+        let smaller = integral_squared_image(&gray_bench_image(9, 6));
+        assert!(window_mean_and_variance(&integral, &smaller, rect).is_err());
+    }
+
+    #[test]
+    fn test_window_mean_and_variance_constant() {
+        let image = GrayImage::from_pixel(5, 5, Luma([77u8]));
+        let integral = integral_image(&image);
+        let integral_squared = integral_squared_image(&image);
+        let rect = Rect::at(1, 1).of_size(3, 3);
+        assert_eq!(window_mean_and_variance(&integral, &integral_squared, rect), Ok((77.0, 0.0)));
+    }
+
+    /// Simple implementation of rotated_integral_image to validate faster versions against.
+    fn rotated_integral_image_ref<I>(image: &I) -> Image<Luma<u32>>
+    where
+        I: GenericImage<Pixel = Luma<u8>>,
+    {
+        let (in_width, in_height) = image.dimensions();
+        let (out_width, out_height) = (in_width + 1, in_height + 1);
+        let mut out = ImageBuffer::from_pixel(out_width, out_height, Luma([0u32]));
+
+        for y in 1..out_height {
+            for x in 0..out_width {
+                let mut sum = 0u32;
+
+                for iy in 0..y {
+                    for ix in 0..in_width {
+                        if (ix as i32 - x as i32 + 1).abs() < (y - iy) as i32 {
+                            sum += image.get_pixel(ix, iy)[0] as u32;
+                        }
+                    }
+                }
+
+                out.put_pixel(x, y, Luma([sum]));
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    fn test_rotated_integral_image_matches_reference_implementation() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let expected = rotated_integral_image_ref(&image.0);
+            let actual = rotated_integral_image(&image.0);
+            match pixel_diff_summary(&actual, &expected) {
+                None => TestResult::passed(),
+                Some(err) => TestResult::error(err),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_rotated_integral_image_rgb() {
+        let image = rgb_image!(
+            [1, 11, 21], [2, 12, 22], [3, 13, 23];
+            [4, 14, 24], [5, 15, 25], [6, 16, 26]);
+
+        let expected = rgb_image!(type: u32,
+            [0,  0,  0], [0,  0,  0], [ 0,  0,  0], [ 0,  0,  0];
+            [0,  0,  0], [1, 11, 21], [ 2, 12, 22], [ 3, 13, 23];
+            [1, 11, 21], [7, 37, 67], [11, 51, 91], [11, 41, 71]);
+
+        assert_pixels_eq!(rotated_integral_image(&image), expected);
+    }
+
+    #[bench]
+    fn bench_rotated_integral_image_gray(b: &mut test::Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let integral = rotated_integral_image(&image);
+            test::black_box(integral);
+        });
+    }
+
+    #[test]
+    fn test_integral_image_struct_matches_integral_image_as() {
+        let image = gray_bench_image(23, 17);
+        let integral = IntegralImage::new(&image);
+        let expected: Image<Luma<u64>> = integral_image_as(&image);
+        for &(left, top, width, height) in &[(0, 0, 23, 17), (3, 4, 5, 6), (22, 16, 1, 1), (10, 0, 13, 9)] {
+            let rect = Rect::at(left as i32, top as i32).of_size(width, height);
+            let sum = sum_image_pixels(&expected, left, top, left + width - 1, top + height - 1);
+            assert_eq!(integral.sum_image_pixels(rect), Ok(sum));
+        }
+    }
+
+    #[test]
+    fn test_integral_image_update_region() {
+        let mut image = gray_bench_image(23, 17);
+        let mut integral = IntegralImage::new(&image);
+        for &(left, top, width, height) in &[(0, 0, 23, 17), (3, 4, 5, 6), (22, 16, 1, 1), (10, 0, 13, 9), (0, 12, 4, 5)] {
+            for y in top..top + height {
+                for x in left..left + width {
+                    let p = image.get_pixel(x, y)[0];
+                    image.put_pixel(x, y, Luma([p.wrapping_mul(7).wrapping_add(x as u8)]));
+                }
+            }
+            let rect = Rect::at(left as i32, top as i32).of_size(width, height);
+            integral.update_region(&image, rect).unwrap();
+            assert_eq!(integral, IntegralImage::new(&image));
+        }
+    }
+
+    #[test]
+    fn test_integral_image_invalid_inputs() {
+        let image = gray_bench_image(5, 4);
+        let mut integral = IntegralImage::new(&image);
+        let rect = Rect::at(3, 0).of_size(3, 2);
+        assert_eq!(integral.sum_image_pixels(rect), Err(ImageProcError::OutOfBounds { region: rect, image: (5, 4) }));
+        assert!(integral.update_region(&image, rect).is_err());
+        assert_eq!(
+            integral.update_region(&gray_bench_image(4, 5), Rect::at(0, 0).of_size(1, 1)),
+            Err(ImageProcError::DimensionMismatch { expected: (5, 4), actual: (4, 5) })
+        );
+        assert_eq!(IntegralImage::new(&GrayImage::new(0, 3)).dimensions(), (0, 3));
+    }
+
+    #[test]
+    fn test_integral_histogram_matches_reference_implementation() {
+        let image = gray_bench_image(23, 17);
+        for &num_bins in &[1, 7, 16, 256] {
+            let integral = IntegralHistogram::new(&image, num_bins).unwrap();
+            for &(left, top, width, height) in &[(0, 0, 23, 17), (3, 4, 5, 6), (22, 16, 1, 1), (10, 0, 13, 9)] {
+                let rect = Rect::at(left as i32, top as i32).of_size(width, height);
+                let mut expected = vec![0; num_bins];
+                let mut values = vec![];
+                for y in top..top + height {
+                    for x in left..left + width {
+                        let p = image.get_pixel(x, y)[0];
+                        expected[integral.bin(p)] += 1;
+                        values.push(p);
+                    }
+                }
+                assert_eq!(integral.histogram(rect), Ok(expected));
+
+                if num_bins == 256 {
+                    values.sort();
+                    let median = values[(values.len() - 1) / 2];
+                    assert_eq!(integral.approximate_median(rect), Ok(median));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_integral_histogram_approximate_median() {
+        let image = gray_image!(10, 20, 200, 210, 220);
+        let integral = IntegralHistogram::new(&image, 4).unwrap();
+        // The median, 200, is in the bin [192, 255].
+        assert_eq!(integral.approximate_median(Rect::at(0, 0).of_size(5, 1)), Ok(223));
+        let integral = IntegralHistogram::new(&image, 3).unwrap();
+        // The median of the first two pixels, 10, is in the bin [0, 85].
+        assert_eq!(integral.approximate_median(Rect::at(0, 0).of_size(2, 1)), Ok(42));
+    }
+
+    #[test]
+    fn test_integral_histogram_invalid_inputs() {
+        let image = gray_bench_image(5, 5);
+        assert!(IntegralHistogram::new(&image, 0).is_err());
+        assert!(IntegralHistogram::new(&image, 257).is_err());
+        let integral = IntegralHistogram::new(&image, 8).unwrap();
+        let rect = Rect::at(-1, 0).of_size(2, 2);
+        assert_eq!(integral.histogram(rect), Err(ImageProcError::OutOfBounds { region: rect, image: (5, 5) }));
+        assert!(integral.histogram(Rect::at(4, 4).of_size(2, 1)).is_err());
+    }
+
+    #[bench]
+    fn bench_integral_histogram_windows(b: &mut test::Bencher) {
+        let image = gray_bench_image(200, 200);
+        let integral = IntegralHistogram::new(&image, 16).unwrap();
+        let mut out = vec![0; 16];
+        b.iter(|| {
+            for y in 0..180 {
+                for x in 0..180 {
+                    integral.histogram_into(Rect::at(x, y).of_size(20, 20), &mut out).unwrap();
+                }
+            }
+            test::black_box(&out);
+        });
+    }
+
     #[bench]
     fn bench_row_running_sum(b: &mut test::Bencher) {
         let image = gray_bench_image(1000, 1);
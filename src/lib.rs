@@ -1,5 +1,72 @@
 //! An image processing library, based on the
 //! [image](https://github.com/PistonDevelopers/image) crate.
+//!
+//! # Features
+//!
+//! * `rayon` - processes the rows of an image in parallel in the filtering functions
+//!   in [`filter`](filter/index.html) and [`gradients`](gradients/index.html), the
+//!   transformations in [`geometric_transformations`](geometric_transformations/index.html),
+//!   the functions in [`map`](map/index.html), [`dilate`](morphology/fn.dilate.html) and
+//!   [`erode`](morphology/fn.erode.html) (and hence the rest of the
+//!   [`morphology`](morphology/index.html) module), `L1` distance transforms,
+//!   histogram equalisation and the [`integral_image`](integral_image/index.html)
+//!   functions. Disabled by default.
+//!
+//!   These functions bound their pixel types and closures by
+//!   [`MaybeSend`](definitions/trait.MaybeSend.html) and
+//!   [`MaybeSync`](definitions/trait.MaybeSync.html), which are `Send` and `Sync` when this
+//!   feature is enabled and are implemented by every type otherwise.
+//!
+//!   Enabling this feature never changes any outputs. Each parallelised loop computes every
+//!   output row independently of every other, performing the same operations in the
+//!   same order as when running serially, so results do not depend on the number of
+//!   threads used or how rows are scheduled between them.
+//!
+//! * `ndarray` - adds the [`ndarray_interop`](ndarray_interop/index.html) module, for
+//!   converting between images and [`ndarray`](https://docs.rs/ndarray) arrays without
+//!   copying. Disabled by default.
+//!
+//! * `nalgebra` - adds the [`nalgebra_interop`](nalgebra_interop/index.html) module, for
+//!   converting points and transforms to and from [`nalgebra`](https://docs.rs/nalgebra)
+//!   types and warping images by projective transformations given as `Matrix3`s.
+//!   Disabled by default.
+//!
+//! * `serde` - implements `Serialize` and `Deserialize` from [`serde`](https://serde.rs) for
+//!   Haar-like features and cascades, corners, binary descriptors and their matches,
+//!   bag of words vocabularies, `HogOptions` and `Rect`, so that trained models and extracted
+//!   features can be saved and loaded. Disabled by default.
+//!
+//! # Pixel depths
+//!
+//! Many functions only accept 8 bit grayscale images. The core operations also support
+//! images with `u16` and `f32` channels, performing intermediate calculations at a type
+//! wide enough to avoid overflow and loss of precision:
+//!
+//! * filtering - [`gaussian_blur_f32`](filter/fn.gaussian_blur_f32.html),
+//!   [`separable_filter`](filter/fn.separable_filter.html),
+//!   [`filter_clamped`](filter/fn.filter_clamped.html) and
+//!   [`Kernel::filter`](filter/struct.Kernel.html#method.filter) compute at type `f32`, and
+//!   [`mean_filter`](filter/fn.mean_filter.html) sums `u16` images exactly.
+//! * gradients - the `_f32` functions in [`gradients`](gradients/index.html).
+//! * thresholding - [`binarize`](contrast/fn.binarize.html), with levels from
+//!   [`otsu_level_u16`](contrast/fn.otsu_level_u16.html) or
+//!   [`otsu_level_f32`](contrast/fn.otsu_level_f32.html).
+//! * integral images - [`integral_image_as`](integral_image/fn.integral_image_as.html) and
+//!   [`integral_squared_image_as`](integral_image/fn.integral_squared_image_as.html), e.g.
+//!   with `u64` sums for `u16` images and `f64` sums for `f32` images.
+//! * warps - the functions in [`geometric_transformations`](geometric_transformations/index.html).
+//!
+//! # WebAssembly
+//!
+//! The crate builds for `wasm32` targets with its default features. The `rayon` feature
+//! requires threads, so should not be enabled for the browser. Nothing in the crate reads
+//! the clock or seeds random number generators from the environment: functions that need
+//! randomness take an explicit seed.
+//!
+//! WebAssembly has no runtime CPU feature detection, so the SIMD paths used by
+//! [`Kernel::filter`](filter/struct.Kernel.html#method.filter) and the functions built on it
+//! are only compiled in when the `simd128` target feature is enabled, e.g. by building with
+//! `RUSTFLAGS="-C target-feature=+simd128"`. Results are identical either way.
 #![deny(missing_docs)]
 #![cfg_attr(test, feature(test))]
 
@@ -12,34 +79,88 @@ extern crate num;
 extern crate quickcheck;
 extern crate rand;
 extern crate rusttype;
+#[cfg(feature = "rayon")]
 extern crate rayon;
+#[cfg(feature = "ndarray")]
+extern crate ndarray;
+#[cfg(feature = "nalgebra")]
+extern crate nalgebra;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
 #[macro_use]
 pub mod utils;
 pub mod affine;
+pub mod augment;
+pub mod background;
+pub mod bag_of_words;
+pub mod bench_utils;
+pub mod binary_descriptors;
+pub mod blobs;
+pub mod color;
+pub mod composite;
+pub mod contours;
 pub mod contrast;
 pub mod corners;
 pub mod definitions;
+pub mod denoise;
+pub mod dense;
+pub mod deskew;
+pub mod diffusion;
 pub mod distance_transform;
 pub mod drawing;
 pub mod edges;
+pub mod error;
+pub mod evaluation;
+mod fft;
 pub mod filter;
+pub mod geometric_transformations;
+pub mod geometry;
+pub mod grabcut;
 pub mod gradients;
 pub mod haar;
+pub mod hash;
 pub mod hog;
 pub mod hough;
 pub mod integral_image;
 pub mod local_binary_patterns;
 pub mod map;
 pub mod math;
+pub mod moments;
 pub mod morphology;
+pub mod mser;
+pub mod motion;
+pub mod multiarray;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra_interop;
+#[cfg(feature = "ndarray")]
+pub mod ndarray_interop;
 pub mod noise;
+pub mod optflow;
+mod parallel;
+pub mod pipeline;
 pub mod pixelops;
 pub mod property_testing;
+pub mod pyramid;
+pub mod qr;
+pub mod quantize;
+pub mod radon;
 pub mod rect;
 pub mod region_labelling;
 pub mod seam_carving;
+pub mod segmentation;
+pub mod shape_matching;
+mod simd;
+pub mod snakes;
 pub mod stats;
+pub mod stereo;
+pub mod structure_tensor;
 pub mod suppress;
 pub mod template_matching;
+pub mod threshold;
+pub mod tiles;
+pub mod training;
+pub mod tracking;
 pub mod union_find;
+pub mod view;
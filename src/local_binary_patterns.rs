@@ -1,6 +1,8 @@
 //! Functions for computing [local binary patterns](https://en.wikipedia.org/wiki/Local_binary_patterns).
 
-use image::{GenericImage, Luma};
+use image::{GenericImage, GrayImage, Luma};
+use error::{ImageProcError, Result};
+use multiarray::Array3d;
 use std::cmp;
 
 /// Computes the basic local binary pattern of a pixel, or None
@@ -647,6 +649,134 @@ pub static MIN_SHIFT: [u8; 256] = [
 255, // 255
 ];
 
+/// How [`local_binary_pattern_histograms`](fn.local_binary_pattern_histograms.html) groups
+/// patterns into histogram bins.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PatternMapping {
+    /// One bin for each of the 256 patterns.
+    Basic,
+    /// One bin for each of the 58 uniform patterns (those with at most two bit transitions),
+    /// in increasing order, and a final bin shared by all non-uniform patterns.
+    Uniform,
+    /// Patterns are grouped by [`UNIFORM_REPRESENTATIVE_2`](static.UNIFORM_REPRESENTATIVE_2.html)
+    /// into 10 bins, which are invariant to rotation. Bins are in increasing order of
+    /// representative.
+    RotationInvariantUniform,
+}
+
+impl PatternMapping {
+    /// Number of histogram bins.
+    pub fn num_bins(&self) -> usize {
+        match *self {
+            PatternMapping::Basic => 256,
+            PatternMapping::Uniform => 59,
+            PatternMapping::RotationInvariantUniform => 10,
+        }
+    }
+
+    /// Returns the histogram bin of each pattern.
+    pub(crate) fn bins(&self) -> [usize; 256] {
+        let mut bins = [0usize; 256];
+        match *self {
+            PatternMapping::Basic => {
+                for (pattern, bin) in bins.iter_mut().enumerate() {
+                    *bin = pattern;
+                }
+            }
+            PatternMapping::Uniform => {
+                let mut next = 0;
+                for (pattern, bin) in bins.iter_mut().enumerate() {
+                    if count_transitions(pattern as u8) <= 2 {
+                        *bin = next;
+                        next += 1;
+                    } else {
+                        *bin = 58;
+                    }
+                }
+            }
+            PatternMapping::RotationInvariantUniform => {
+                let mut representatives: Vec<u8> = UNIFORM_REPRESENTATIVE_2.to_vec();
+                representatives.sort();
+                representatives.dedup();
+                for (pattern, bin) in bins.iter_mut().enumerate() {
+                    let representative = UNIFORM_REPRESENTATIVE_2[pattern];
+                    *bin = representatives.binary_search(&representative).unwrap();
+                }
+            }
+        }
+        bins
+    }
+}
+
+/// Computes a histogram of local binary patterns for each cell of a grid of non-overlapping
+/// `cell_side` x `cell_side` cells covering the image, normalised to sum to 1. Concatenating
+/// the histograms gives the spatially enhanced descriptor commonly used for face recognition.
+///
+/// Pixels on the image boundary don't have a local binary pattern so aren't counted, and
+/// pixels on the right or bottom of the image that can't be covered by a whole number of
+/// cells are ignored. Cells without any patterns have histograms of all zeros.
+///
+/// The innermost dimension of the result is histogram bin, then horizontal location of the
+/// cell, then vertical location of the cell.
+///
+/// Returns `ImageProcError::InvalidOptions` if `cell_side` is 0.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::GrayImage;
+/// use imageproc::local_binary_patterns::{local_binary_pattern_histograms, PatternMapping};
+///
+/// let image = GrayImage::new(20, 9);
+/// let histograms = local_binary_pattern_histograms(&image, 4, PatternMapping::Uniform).unwrap();
+///
+/// // 5 cells wide and 2 cells high, with 59 bins per cell.
+/// assert_eq!(histograms.lengths(), [59, 5, 2]);
+/// // Every pattern in a constant image is zero.
+/// assert_eq!(histograms.inner_slice(2, 1)[0], 1.0);
+/// # }
+/// ```
+pub fn local_binary_pattern_histograms(
+    image: &GrayImage,
+    cell_side: u32,
+    mapping: PatternMapping,
+) -> Result<Array3d<f32>> {
+    if cell_side == 0 {
+        return Err(ImageProcError::InvalidOptions("cell_side must be positive".to_owned()));
+    }
+    let (width, height) = image.dimensions();
+    let (cells_wide, cells_high) = (width / cell_side, height / cell_side);
+    let bins = mapping.bins();
+
+    let mut histograms = Array3d::new([mapping.num_bins(), cells_wide as usize, cells_high as usize]);
+    {
+        let mut view = histograms.view_mut();
+        for cy in 0..cells_high {
+            for cx in 0..cells_wide {
+                let histogram = view.inner_slice_mut(cx as usize, cy as usize);
+                let mut count = 0;
+                for y in cy * cell_side..(cy + 1) * cell_side {
+                    for x in cx * cell_side..(cx + 1) * cell_side {
+                        if let Some(pattern) = local_binary_pattern(image, x, y) {
+                            histogram[bins[pattern as usize]] += 1.0;
+                            count += 1;
+                        }
+                    }
+                }
+                if count > 0 {
+                    for h in histogram.iter_mut() {
+                        *h /= count as f32;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(histograms)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -663,6 +793,52 @@ mod test {
         assert_eq!(UNIFORM_REPRESENTATIVE_2[c], 0b10101010);
     }
 
+    #[test]
+    fn test_pattern_mapping_bins() {
+        for &mapping in &[PatternMapping::Basic, PatternMapping::Uniform, PatternMapping::RotationInvariantUniform] {
+            let bins = mapping.bins();
+            let mut used: Vec<usize> = bins.to_vec();
+            used.sort();
+            used.dedup();
+            assert_eq!(used, (0..mapping.num_bins()).collect::<Vec<usize>>());
+        }
+        let uniform = PatternMapping::Uniform.bins();
+        assert_eq!((uniform[0], uniform[1], uniform[2], uniform[3], uniform[4]), (0, 1, 2, 3, 4));
+        assert_eq!(uniform[0b00000101], 58);
+        assert_eq!(uniform[255], 57);
+    }
+
+    #[test]
+    fn test_local_binary_pattern_histograms() {
+        let image = gray_image!(
+            1, 1, 1, 1, 1, 1, 1;
+            1, 5, 1, 1, 1, 1, 1;
+            1, 1, 1, 1, 1, 9, 1;
+            1, 1, 1, 1, 1, 1, 1);
+
+        let histograms = local_binary_pattern_histograms(&image, 3, PatternMapping::Basic).unwrap();
+        assert_eq!(histograms.lengths(), [256, 2, 1]);
+        // The left cell contains the patterns of pixels (1, 1) to (2, 2), and the right cell
+        // those of pixels (3, 1) to (5, 2). Bright pixels are brighter than all neighbours,
+        // and their neighbours are brighter than none.
+        let left = histograms.inner_slice(0, 0);
+        assert_eq!((left[255], left[0]), (0.25, 0.75));
+        let right = histograms.inner_slice(1, 0);
+        assert_eq!((right[255], right[0]), (1.0 / 6.0, 5.0 / 6.0));
+
+        let rotation_invariant = local_binary_pattern_histograms(&image, 3, PatternMapping::RotationInvariantUniform).unwrap();
+        assert_eq!(rotation_invariant.inner_slice(0, 0)[9], 0.25);
+
+        assert!(local_binary_pattern_histograms(&image, 0, PatternMapping::Basic).is_err());
+        assert_eq!(local_binary_pattern_histograms(&image, 5, PatternMapping::Basic).unwrap().lengths(), [256, 1, 0]);
+    }
+
+    #[bench]
+    fn bench_local_binary_pattern_histograms(b: &mut Bencher) {
+        let image = GrayImage::from_fn(100, 100, |x, y| Luma([(x * 7 + y * 13) as u8]));
+        b.iter(|| black_box(local_binary_pattern_histograms(&image, 10, PatternMapping::Uniform).unwrap()));
+    }
+
     #[bench]
     fn bench_local_binary_pattern(b: &mut Bencher) {
         let image = GrayImage::from_fn(100, 100, |x, y| Luma([x as u8 % 2 + y as u8 % 2]));
@@ -2,7 +2,8 @@
 
 use image::{GenericImage, ImageBuffer, Luma, Pixel, Primitive, Rgb, Rgba};
 
-use definitions::Image;
+use definitions::{Image, MaybeSend, MaybeSync};
+use parallel::for_each_row;
 
 /// The type obtained by replacing the channel type of a given `Pixel` type.
 /// The output type must have the same name of channels as the input type, or
@@ -64,28 +65,75 @@ where
 /// ```
 pub fn map_subpixels<I, P, F, S>(image: &I, f: F) -> Image<ChannelMap<P, S>>
 where
-    I: GenericImage<Pixel = P>,
+    I: GenericImage<Pixel = P> + MaybeSync,
     P: WithChannel<S> + 'static,
-    S: Primitive + 'static,
-    F: Fn(P::Subpixel) -> S,
+    S: Primitive + MaybeSend + 'static,
+    F: Fn(P::Subpixel) -> S + MaybeSync,
 {
     let (width, height) = image.dimensions();
     let mut out: ImageBuffer<ChannelMap<P, S>, Vec<S>> = ImageBuffer::new(width, height);
+    map_subpixels_into(image, f, &mut out);
+    out
+}
+
+/// Applies `f` to each subpixel of the input image, writing the results to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn map_subpixels_into<I, P, F, S>(image: &I, f: F, out: &mut Image<ChannelMap<P, S>>)
+where
+    I: GenericImage<Pixel = P> + MaybeSync,
+    P: WithChannel<S> + 'static,
+    S: Primitive + MaybeSend + 'static,
+    F: Fn(P::Subpixel) -> S + MaybeSync,
+{
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    let num_channels = P::channel_count() as usize;
 
-    for y in 0..height {
-        for x in 0..width {
-            let out_channels = out.get_pixel_mut(x, y).channels_mut();
-            for c in 0..P::channel_count() {
-                out_channels[c as usize] = f(unsafe {
-                    *image.unsafe_get_pixel(x, y).channels().get_unchecked(
-                        c as usize,
-                    )
-                });
+    for_each_row(out, |y, row| {
+        for (x, out_channels) in row.chunks_mut(num_channels).enumerate() {
+            let pix = unsafe { image.unsafe_get_pixel(x as u32, y) };
+            for (o, c) in out_channels.iter_mut().zip(pix.channels()) {
+                *o = f(*c);
             }
         }
-    }
+    });
+}
 
-    out
+/// Applies `f` to each subpixel of an image in place.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::map::map_subpixels_mut;
+///
+/// let mut image = gray_image!(
+///     1, 2;
+///     3, 4);
+///
+/// map_subpixels_mut(&mut image, |x| 10 * x);
+///
+/// assert_pixels_eq!(
+///     image,
+///     gray_image!(
+///         10, 20;
+///         30, 40));
+/// # }
+/// ```
+pub fn map_subpixels_mut<P, F>(image: &mut Image<P>, f: F)
+where
+    P: Pixel + 'static,
+    P::Subpixel: MaybeSend,
+    F: Fn(P::Subpixel) -> P::Subpixel + MaybeSync,
+{
+    for_each_row(image, |_, row| {
+        for c in row.iter_mut() {
+            *c = f(*c);
+        }
+    });
 }
 
 /// Applies `f` to the color of each pixel in the input image.
@@ -114,24 +162,79 @@ where
 /// ```
 pub fn map_colors<I, P, Q, F>(image: &I, f: F) -> Image<Q>
 where
-    I: GenericImage<Pixel = P>,
+    I: GenericImage<Pixel = P> + MaybeSync,
     P: Pixel,
     Q: Pixel + 'static,
-    F: Fn(P) -> Q,
+    Q::Subpixel: MaybeSend,
+    F: Fn(P) -> Q + MaybeSync,
 {
     let (width, height) = image.dimensions();
     let mut out: ImageBuffer<Q, Vec<Q::Subpixel>> = ImageBuffer::new(width, height);
+    map_colors_into(image, f, &mut out);
+    out
+}
 
-    for y in 0..height {
-        for x in 0..width {
-            unsafe {
-                let pix = image.unsafe_get_pixel(x, y);
-                out.unsafe_put_pixel(x, y, f(pix));
-            }
+/// Applies `f` to the color of each pixel in the input image, writing the results to `out`.
+///
+/// # Panics
+/// If `out` and `image` have different dimensions.
+pub fn map_colors_into<I, P, Q, F>(image: &I, f: F, out: &mut Image<Q>)
+where
+    I: GenericImage<Pixel = P> + MaybeSync,
+    P: Pixel,
+    Q: Pixel + 'static,
+    Q::Subpixel: MaybeSend,
+    F: Fn(P) -> Q + MaybeSync,
+{
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    let num_channels = Q::channel_count() as usize;
+
+    for_each_row(out, |y, row| {
+        for (x, out_channels) in row.chunks_mut(num_channels).enumerate() {
+            let pix = unsafe { image.unsafe_get_pixel(x as u32, y) };
+            out_channels.copy_from_slice(f(pix).channels());
         }
-    }
+    });
+}
 
-    out
+/// Applies `f` to the color of each pixel of an image in place.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::map::map_colors_mut;
+///
+/// let mut image = gray_image!(
+///     1, 2;
+///     3, 4);
+///
+/// map_colors_mut(&mut image, |p| Luma([5 - p[0]]));
+///
+/// assert_pixels_eq!(
+///     image,
+///     gray_image!(
+///         4, 3;
+///         2, 1));
+/// # }
+/// ```
+pub fn map_colors_mut<P, F>(image: &mut Image<P>, f: F)
+where
+    P: Pixel + 'static,
+    P::Subpixel: MaybeSend,
+    F: Fn(P) -> P + MaybeSync,
+{
+    let num_channels = P::channel_count() as usize;
+
+    for_each_row(image, |_, row| {
+        for channels in row.chunks_mut(num_channels) {
+            let pix = f(*P::from_slice(channels));
+            channels.copy_from_slice(pix.channels());
+        }
+    });
 }
 
 /// Applies `f` to the colors of the pixels in the input images.
@@ -169,27 +272,28 @@ where
 /// ```
 pub fn map_colors2<I, J, P, Q, R, F>(image1: &I, image2: &J, f: F) -> Image<R>
 where
-    I: GenericImage<Pixel = P>,
-    J: GenericImage<Pixel = Q>,
+    I: GenericImage<Pixel = P> + MaybeSync,
+    J: GenericImage<Pixel = Q> + MaybeSync,
     P: Pixel,
     Q: Pixel,
     R: Pixel + 'static,
-    F: Fn(P, Q) -> R,
+    R::Subpixel: MaybeSend,
+    F: Fn(P, Q) -> R + MaybeSync,
 {
     assert_eq!(image1.dimensions(), image2.dimensions());
 
     let (width, height) = image1.dimensions();
     let mut out: ImageBuffer<R, Vec<R::Subpixel>> = ImageBuffer::new(width, height);
+    let num_channels = R::channel_count() as usize;
 
-    for y in 0..height {
-        for x in 0..width {
-            unsafe {
-                let p = image1.unsafe_get_pixel(x, y);
-                let q = image2.unsafe_get_pixel(x, y);
-                out.unsafe_put_pixel(x, y, f(p, q));
-            }
+    for_each_row(&mut out, |y, row| {
+        for (x, out_channels) in row.chunks_mut(num_channels).enumerate() {
+            let (p, q) = unsafe {
+                (image1.unsafe_get_pixel(x as u32, y), image2.unsafe_get_pixel(x as u32, y))
+            };
+            out_channels.copy_from_slice(f(p, q).channels());
         }
-    }
+    });
 
     out
 }
@@ -222,22 +326,23 @@ where
 /// ```
 pub fn map_pixels<I, P, Q, F>(image: &I, f: F) -> Image<Q>
 where
-    I: GenericImage<Pixel = P>,
+    I: GenericImage<Pixel = P> + MaybeSync,
     P: Pixel,
     Q: Pixel + 'static,
-    F: Fn(u32, u32, P) -> Q,
+    Q::Subpixel: MaybeSend,
+    F: Fn(u32, u32, P) -> Q + MaybeSync,
 {
     let (width, height) = image.dimensions();
     let mut out: ImageBuffer<Q, Vec<Q::Subpixel>> = ImageBuffer::new(width, height);
+    let num_channels = Q::channel_count() as usize;
 
-    for y in 0..height {
-        for x in 0..width {
-            unsafe {
-                let pix = image.unsafe_get_pixel(x, y);
-                out.unsafe_put_pixel(x, y, f(x, y, pix));
-            }
+    for_each_row(&mut out, |y, row| {
+        for (x, out_channels) in row.chunks_mut(num_channels).enumerate() {
+            let x = x as u32;
+            let pix = unsafe { image.unsafe_get_pixel(x, y) };
+            out_channels.copy_from_slice(f(x, y, pix).channels());
         }
-    }
+    });
 
     out
 }
@@ -267,8 +372,8 @@ where
 /// ```
 pub fn red_channel<I, C>(image: &I) -> Image<Luma<C>>
 where
-    I: GenericImage<Pixel = Rgb<C>>,
-    C: Primitive + 'static,
+    I: GenericImage<Pixel = Rgb<C>> + MaybeSync,
+    C: Primitive + MaybeSend + 'static,
 {
     map_colors(image, |p| Luma([p[0]]))
 }
@@ -298,8 +403,8 @@ where
 /// ```
 pub fn as_red_channel<I, C>(image: &I) -> Image<Rgb<C>>
 where
-    I: GenericImage<Pixel = Luma<C>>,
-    C: Primitive + 'static,
+    I: GenericImage<Pixel = Luma<C>> + MaybeSync,
+    C: Primitive + MaybeSend + 'static,
 {
     map_colors(image, |p| {
         let mut cs = [C::zero(); 3];
@@ -333,8 +438,8 @@ where
 /// ```
 pub fn green_channel<I, C>(image: &I) -> Image<Luma<C>>
 where
-    I: GenericImage<Pixel = Rgb<C>>,
-    C: Primitive + 'static,
+    I: GenericImage<Pixel = Rgb<C>> + MaybeSync,
+    C: Primitive + MaybeSend + 'static,
 {
     map_colors(image, |p| Luma([p[1]]))
 }
@@ -364,8 +469,8 @@ where
 /// ```
 pub fn as_green_channel<I, C>(image: &I) -> Image<Rgb<C>>
 where
-    I: GenericImage<Pixel = Luma<C>>,
-    C: Primitive + 'static,
+    I: GenericImage<Pixel = Luma<C>> + MaybeSync,
+    C: Primitive + MaybeSend + 'static,
 {
     map_colors(image, |p| {
         let mut cs = [C::zero(); 3];
@@ -399,8 +504,8 @@ where
 /// ```
 pub fn blue_channel<I, C>(image: &I) -> Image<Luma<C>>
 where
-    I: GenericImage<Pixel = Rgb<C>>,
-    C: Primitive + 'static,
+    I: GenericImage<Pixel = Rgb<C>> + MaybeSync,
+    C: Primitive + MaybeSend + 'static,
 {
     map_colors(image, |p| Luma([p[2]]))
 }
@@ -430,8 +535,8 @@ where
 /// ```
 pub fn as_blue_channel<I, C>(image: &I) -> Image<Rgb<C>>
 where
-    I: GenericImage<Pixel = Luma<C>>,
-    C: Primitive + 'static,
+    I: GenericImage<Pixel = Luma<C>> + MaybeSync,
+    C: Primitive + MaybeSend + 'static,
 {
     map_colors(image, |p| {
         let mut cs = [C::zero(); 3];
@@ -7,6 +7,11 @@ pub fn l2_norm(xs: &[f32]) -> f32 {
     xs.iter().fold(0f32, |acc, x| acc + x * x).sqrt()
 }
 
+/// Squared Euclidean distance between two vectors of the same length.
+pub fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
 /// Helper for a conversion that we know can't fail.
 pub fn cast<T, U>(x: T) -> U
 where
@@ -0,0 +1,475 @@
+//! [Image moments](https://en.wikipedia.org/wiki/Image_moment) of whole images and of
+//! labelled regions, and the moment invariants of Hu.
+
+use image::{GenericImageView, GrayImage, Luma, Primitive};
+use conv::ValueInto;
+use definitions::Image;
+use geometry::Ellipse;
+use math::cast;
+
+/// Raw spatial moments of an image or region, up to order three. Moment `mpq` is the sum
+/// of `w * x^p * y^q` over all pixels `(x, y)`, where `w` is the weight of the pixel.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Moments {
+    /// Total weight, i.e. the area of a binary region.
+    pub m00: f64,
+    /// First order moment in `x`.
+    pub m10: f64,
+    /// First order moment in `y`.
+    pub m01: f64,
+    /// Second order moment in `x`.
+    pub m20: f64,
+    /// Second order mixed moment.
+    pub m11: f64,
+    /// Second order moment in `y`.
+    pub m02: f64,
+    /// Third order moment in `x`.
+    pub m30: f64,
+    /// Third order mixed moment, second order in `x`.
+    pub m21: f64,
+    /// Third order mixed moment, second order in `y`.
+    pub m12: f64,
+    /// Third order moment in `y`.
+    pub m03: f64,
+}
+
+/// Central moments of order two and three, i.e. moments about the centroid of a region.
+/// Central moments of order zero and one are not stored, as `mu00 = m00` and
+/// `mu10 = mu01 = 0`.
+///
+/// Also used for normalized central moments, which are invariant to scaling.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CentralMoments {
+    /// Second order central moment in `x`.
+    pub mu20: f64,
+    /// Second order mixed central moment.
+    pub mu11: f64,
+    /// Second order central moment in `y`.
+    pub mu02: f64,
+    /// Third order central moment in `x`.
+    pub mu30: f64,
+    /// Third order mixed central moment, second order in `x`.
+    pub mu21: f64,
+    /// Third order mixed central moment, second order in `y`.
+    pub mu12: f64,
+    /// Third order central moment in `y`.
+    pub mu03: f64,
+}
+
+impl Moments {
+    /// Computes the moments of a grayscale image, weighting each pixel by its intensity.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::moments::Moments;
+    ///
+    /// let image = gray_image!(
+    ///     0, 0, 0, 0;
+    ///     0, 2, 2, 0;
+    ///     0, 0, 0, 0);
+    ///
+    /// let moments = Moments::of_image(&image);
+    /// assert_eq!(moments.m00, 4.0);
+    /// assert_eq!(moments.centroid(), Some((1.5, 1.0)));
+    /// # }
+    /// ```
+    pub fn of_image<I, T>(image: &I) -> Moments
+    where
+        I: GenericImageView<Pixel = Luma<T>>,
+        T: Primitive + ValueInto<f64> + 'static,
+    {
+        let mut moments = Moments::default();
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let w: f64 = cast(image.get_pixel(x, y)[0]);
+                if w != 0.0 {
+                    moments.add(x, y, w);
+                }
+            }
+        }
+        moments
+    }
+
+    /// Computes the moments of the region of pixels in an image with non-zero intensity,
+    /// giving each pixel in the region weight 1.
+    pub fn of_binary(image: &GrayImage) -> Moments {
+        let mut moments = Moments::default();
+        for (x, y, p) in image.enumerate_pixels() {
+            if p[0] != 0 {
+                moments.add(x, y, 1.0);
+            }
+        }
+        moments
+    }
+
+    /// Computes the moments of each labelled region in an image, such as the output of
+    /// [`connected_components`](../region_labelling/fn.connected_components.html). Each
+    /// pixel in a region has weight 1.
+    ///
+    /// The result has one entry per label from 0 to the largest label in the image, so
+    /// entry 0 holds the moments of the background.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use image::Luma;
+    /// use imageproc::moments::Moments;
+    /// use imageproc::region_labelling::{connected_components, Connectivity};
+    ///
+    /// let image = gray_image!(
+    ///     255, 255, 0,   0;
+    ///       0,   0, 0, 255;
+    ///       0,   0, 0, 255);
+    ///
+    /// let labels = connected_components(&image, Connectivity::Eight, Luma([0u8]));
+    /// let moments = Moments::of_components(&labels);
+    ///
+    /// assert_eq!(moments.len(), 3);
+    /// assert_eq!(moments[1].centroid(), Some((0.5, 0.0)));
+    /// assert_eq!(moments[2].centroid(), Some((3.0, 1.5)));
+    /// # }
+    /// ```
+    pub fn of_components(labels: &Image<Luma<u32>>) -> Vec<Moments> {
+        let num_labels = labels.pixels().map(|p| p[0]).max().map_or(0, |l| l as usize + 1);
+        let mut moments = vec![Moments::default(); num_labels];
+        for (x, y, p) in labels.enumerate_pixels() {
+            moments[p[0] as usize].add(x, y, 1.0);
+        }
+        moments
+    }
+
+    /// Computes the moments of a set of pixels, giving each pixel weight 1.
+    pub fn of_points(points: &[(u32, u32)]) -> Moments {
+        let mut moments = Moments::default();
+        for &(x, y) in points {
+            moments.add(x, y, 1.0);
+        }
+        moments
+    }
+
+    fn add(&mut self, x: u32, y: u32, w: f64) {
+        let (x, y) = (x as f64, y as f64);
+        let (wx, wy) = (w * x, w * y);
+        self.m00 += w;
+        self.m10 += wx;
+        self.m01 += wy;
+        self.m20 += wx * x;
+        self.m11 += wx * y;
+        self.m02 += wy * y;
+        self.m30 += wx * x * x;
+        self.m21 += wx * x * y;
+        self.m12 += wx * y * y;
+        self.m03 += wy * y * y;
+    }
+
+    /// The centroid of the region, or `None` if it has zero weight.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        if self.m00 == 0.0 {
+            return None;
+        }
+        Some((self.m10 / self.m00, self.m01 / self.m00))
+    }
+
+    /// The central moments of the region, or `None` if it has zero weight. These are
+    /// invariant to translation.
+    pub fn central(&self) -> Option<CentralMoments> {
+        let (cx, cy) = self.centroid()?;
+        Some(CentralMoments {
+            mu20: self.m20 - cx * self.m10,
+            mu11: self.m11 - cx * self.m01,
+            mu02: self.m02 - cy * self.m01,
+            mu30: self.m30 - 3.0 * cx * self.m20 + 2.0 * cx * cx * self.m10,
+            mu21: self.m21 - 2.0 * cx * self.m11 - cy * self.m20 + 2.0 * cx * cx * self.m01,
+            mu12: self.m12 - 2.0 * cy * self.m11 - cx * self.m02 + 2.0 * cy * cy * self.m10,
+            mu03: self.m03 - 3.0 * cy * self.m02 + 2.0 * cy * cy * self.m01,
+        })
+    }
+
+    /// The normalized central moments of the region, or `None` if it has zero weight.
+    /// The normalized central moment `nupq` is `mupq / m00^(1 + (p + q) / 2)`. These are
+    /// invariant to translation and scaling.
+    pub fn normalized_central(&self) -> Option<CentralMoments> {
+        let mu = self.central()?;
+        let second = self.m00 * self.m00;
+        let third = second * self.m00.sqrt();
+        Some(CentralMoments {
+            mu20: mu.mu20 / second,
+            mu11: mu.mu11 / second,
+            mu02: mu.mu02 / second,
+            mu30: mu.mu30 / third,
+            mu21: mu.mu21 / third,
+            mu12: mu.mu12 / third,
+            mu03: mu.mu03 / third,
+        })
+    }
+
+    /// The ellipse with the same centroid and second order central moments as the region,
+    /// or `None` if it has zero weight. For a binary region this is a summary of its
+    /// position, size, elongation and orientation. The semi-axes are twice the standard
+    /// deviations of the region along its principal axes, so that a filled ellipse is its
+    /// own equivalent ellipse, up to discretisation.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::moments::Moments;
+    ///
+    /// let image = gray_image!(
+    ///     0,   0,   0,   0,   0;
+    ///     0, 255, 255, 255,   0;
+    ///     0,   0,   0,   0,   0);
+    ///
+    /// let ellipse = Moments::of_binary(&image).ellipse().unwrap();
+    /// assert_eq!(ellipse.center, (2.0, 1.0));
+    /// assert_eq!(ellipse.angle, 0.0);
+    /// assert!(ellipse.semi_minor < 1e-3);
+    /// assert!((ellipse.semi_major - 1.633).abs() < 1e-3);
+    /// # }
+    /// ```
+    pub fn ellipse(&self) -> Option<Ellipse> {
+        let (cx, cy) = self.centroid()?;
+        let mu = self.central()?;
+        let (c20, c11, c02) = (mu.mu20 / self.m00, mu.mu11 / self.m00, mu.mu02 / self.m00);
+        let mean = (c20 + c02) / 2.0;
+        let spread = (((c20 - c02) / 2.0).powi(2) + c11 * c11).sqrt();
+        let angle = 0.5 * (2.0 * c11).atan2(c20 - c02);
+        Some(Ellipse {
+            center: (cx as f32, cy as f32),
+            semi_major: (2.0 * (mean + spread).max(0.0).sqrt()) as f32,
+            semi_minor: (2.0 * (mean - spread).max(0.0).sqrt()) as f32,
+            angle: angle as f32,
+        })
+    }
+
+    /// The seven [Hu moment invariants] of the region, or `None` if it has zero weight.
+    ///
+    /// The invariants are unchanged by translation, scaling and rotation of the region, up
+    /// to discretisation error, so are useful as simple shape descriptors. The seventh
+    /// invariant changes sign under reflection.
+    ///
+    /// [Hu moment invariants]: https://en.wikipedia.org/wiki/Image_moment#Rotation_invariants
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::moments::Moments;
+    ///
+    /// let horizontal = gray_image!(
+    ///     0,   0,   0,   0;
+    ///     0, 255, 255, 255;
+    ///     0,   0,   0,   0);
+    ///
+    /// let vertical = gray_image!(
+    ///     0, 255, 0;
+    ///     0, 255, 0;
+    ///     0, 255, 0;
+    ///     0,   0, 0);
+    ///
+    /// let h = Moments::of_binary(&horizontal).hu().unwrap();
+    /// let v = Moments::of_binary(&vertical).hu().unwrap();
+    /// for i in 0..7 {
+    ///     assert!((h[i] - v[i]).abs() < 1e-12);
+    /// }
+    /// # }
+    /// ```
+    pub fn hu(&self) -> Option<[f64; 7]> {
+        let nu = self.normalized_central()?;
+        let (n20, n11, n02) = (nu.mu20, nu.mu11, nu.mu02);
+        let (n30, n21, n12, n03) = (nu.mu30, nu.mu21, nu.mu12, nu.mu03);
+
+        let (a, b) = (n30 + n12, n21 + n03);
+        let (c, d) = (n30 - 3.0 * n12, 3.0 * n21 - n03);
+        Some([
+            n20 + n02,
+            (n20 - n02).powi(2) + 4.0 * n11 * n11,
+            c * c + d * d,
+            a * a + b * b,
+            c * a * (a * a - 3.0 * b * b) + d * b * (3.0 * a * a - b * b),
+            (n20 - n02) * (a * a - b * b) + 4.0 * n11 * a * b,
+            d * a * (a * a - 3.0 * b * b) - c * b * (3.0 * a * a - b * b),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::ImageBuffer;
+    use geometric_transformations::{rotate_about_center, Interpolation};
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn near(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    // An L shaped region with its corner at (left, top), scaled by `scale`.
+    fn l_shape(width: u32, height: u32, left: u32, top: u32, scale: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            if x < left || y < top {
+                return Luma([0]);
+            }
+            let (u, v) = ((x - left) / scale, (y - top) / scale);
+            if (u < 3 && v < 12) || (u < 8 && v >= 9 && v < 12) { Luma([255]) } else { Luma([0]) }
+        })
+    }
+
+    #[test]
+    fn test_rectangle_moments() {
+        let image = GrayImage::from_fn(10, 10, |x, y| {
+            if x >= 2 && x < 6 && y >= 3 && y < 5 { Luma([255]) } else { Luma([0]) }
+        });
+        let moments = Moments::of_binary(&image);
+        assert_eq!(moments.m00, 8.0);
+        assert_eq!(moments.centroid(), Some((3.5, 3.5)));
+
+        let mu = moments.central().unwrap();
+        // Sum of (x - 3.5)^2 over x in 2..6, times two rows.
+        assert!(near(mu.mu20, 10.0, 1e-9));
+        assert!(near(mu.mu02, 2.0, 1e-9));
+        // Symmetric shapes have zero odd order central moments.
+        for &m in &[mu.mu11, mu.mu30, mu.mu21, mu.mu12, mu.mu03] {
+            assert!(near(m, 0.0, 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_of_image_weights_by_intensity() {
+        let image = gray_image!(1, 0, 3);
+        let moments = Moments::of_image(&image);
+        assert_eq!(moments.m00, 4.0);
+        assert_eq!(moments.m10, 6.0);
+        assert_eq!(moments.m20, 12.0);
+        assert_eq!(moments.m30, 24.0);
+        assert_eq!(moments.centroid(), Some((1.5, 0.0)));
+
+        let floats: Image<Luma<f32>> = ImageBuffer::from_raw(3, 1, vec![1.0, 0.0, 3.0]).unwrap();
+        assert_eq!(Moments::of_image(&floats), moments);
+    }
+
+    #[test]
+    fn test_empty_region() {
+        let moments = Moments::of_binary(&GrayImage::new(4, 4));
+        assert_eq!(moments.centroid(), None);
+        assert_eq!(moments.central(), None);
+        assert_eq!(moments.hu(), None);
+    }
+
+    #[test]
+    fn test_central_moments_translation_invariant() {
+        let a = Moments::of_binary(&l_shape(40, 40, 0, 0, 1)).central().unwrap();
+        let b = Moments::of_binary(&l_shape(40, 40, 17, 11, 1)).central().unwrap();
+        for &(p, q) in &[(a.mu20, b.mu20), (a.mu11, b.mu11), (a.mu02, b.mu02), (a.mu30, b.mu30),
+                         (a.mu21, b.mu21), (a.mu12, b.mu12), (a.mu03, b.mu03)] {
+            assert!(near(p, q, 1e-6), "{} {}", p, q);
+        }
+    }
+
+    #[test]
+    fn test_hu_invariants() {
+        let original = l_shape(40, 40, 5, 5, 1);
+        let hu = Moments::of_binary(&original).hu().unwrap();
+
+        // Exact under rotation by a right angle.
+        let rotated = GrayImage::from_fn(40, 40, |x, y| *original.get_pixel(y, 39 - x));
+        let rotated_hu = Moments::of_binary(&rotated).hu().unwrap();
+        for i in 0..7 {
+            assert!(near(hu[i], rotated_hu[i], 1e-12), "{} {} {}", i, hu[i], rotated_hu[i]);
+        }
+
+        // Reflection changes the sign of the seventh invariant only.
+        let reflected = GrayImage::from_fn(40, 40, |x, y| *original.get_pixel(39 - x, y));
+        let reflected_hu = Moments::of_binary(&reflected).hu().unwrap();
+        for i in 0..6 {
+            assert!(near(hu[i], reflected_hu[i], 1e-12));
+        }
+        assert!(near(hu[6], -reflected_hu[6], 1e-12));
+
+        // Approximately unchanged by scaling and arbitrary rotations.
+        let scaled = Moments::of_binary(&l_shape(80, 80, 4, 4, 3)).hu().unwrap();
+        assert!(near(hu[0], scaled[0], 0.02 * hu[0]), "{} {}", hu[0], scaled[0]);
+        assert!(near(hu[1], scaled[1], 0.05 * hu[1]), "{} {}", hu[1], scaled[1]);
+
+        let big = l_shape(100, 100, 30, 20, 3);
+        let turned = rotate_about_center(&big, 0.6, Interpolation::Nearest, Luma([0]));
+        let a = Moments::of_binary(&big).hu().unwrap();
+        let b = Moments::of_binary(&turned).hu().unwrap();
+        assert!(near(a[0], b[0], 0.02 * a[0]), "{} {}", a[0], b[0]);
+        assert!(near(a[1], b[1], 0.05 * a[1]), "{} {}", a[1], b[1]);
+    }
+
+    #[test]
+    fn test_hu_of_disk() {
+        let disk = GrayImage::from_fn(101, 101, |x, y| {
+            let (dx, dy) = (x as f64 - 50.0, y as f64 - 50.0);
+            if dx * dx + dy * dy <= 40.0 * 40.0 { Luma([255]) } else { Luma([0]) }
+        });
+        let hu = Moments::of_binary(&disk).hu().unwrap();
+        // The first invariant of a disk is 1 / 2pi, and the others vanish.
+        assert!(near(hu[0], 1.0 / (2.0 * ::std::f64::consts::PI), 1e-3), "{}", hu[0]);
+        for i in 1..7 {
+            assert!(near(hu[i], 0.0, 1e-6), "{} {}", i, hu[i]);
+        }
+    }
+
+    #[test]
+    fn test_ellipse_of_filled_ellipse() {
+        // A filled ellipse with semi-axes 30 and 12, rotated by 0.5 radians.
+        let (sin, cos) = 0.5f64.sin_cos();
+        let image = GrayImage::from_fn(100, 100, |x, y| {
+            let (dx, dy) = (x as f64 - 50.0, y as f64 - 45.0);
+            let (u, v) = (dx * cos + dy * sin, dy * cos - dx * sin);
+            if (u / 30.0).powi(2) + (v / 12.0).powi(2) <= 1.0 { Luma([255]) } else { Luma([0]) }
+        });
+        let ellipse = Moments::of_binary(&image).ellipse().unwrap();
+        assert!((ellipse.center.0 - 50.0).abs() < 0.01 && (ellipse.center.1 - 45.0).abs() < 0.01);
+        assert!((ellipse.semi_major - 30.0).abs() < 0.3, "{}", ellipse.semi_major);
+        assert!((ellipse.semi_minor - 12.0).abs() < 0.3, "{}", ellipse.semi_minor);
+        assert!((ellipse.angle - 0.5).abs() < 0.01, "{}", ellipse.angle);
+        assert!((ellipse.area() - Moments::of_binary(&image).m00 as f32).abs() < 10.0);
+    }
+
+    #[test]
+    fn test_of_points_matches_of_binary() {
+        let image = l_shape(20, 20, 3, 2, 1);
+        let points: Vec<(u32, u32)> = image.enumerate_pixels().filter(|&(_, _, p)| p[0] != 0).map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(Moments::of_points(&points), Moments::of_binary(&image));
+    }
+
+    #[test]
+    fn test_of_components_matches_of_binary() {
+        let a = l_shape(60, 30, 0, 0, 1);
+        let labels: Image<Luma<u32>> = ImageBuffer::from_fn(60, 30, |x, y| {
+            if a.get_pixel(x, y)[0] != 0 { Luma([2]) } else { Luma([0]) }
+        });
+        let moments = Moments::of_components(&labels);
+        assert_eq!(moments.len(), 3);
+        assert_eq!(moments[1], Moments::default());
+        assert_eq!(moments[2], Moments::of_binary(&a));
+        assert_eq!(moments[0].m00, 60.0 * 30.0 - moments[2].m00);
+    }
+
+    #[bench]
+    fn bench_moments_of_image(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let moments = Moments::of_image(&image);
+            black_box(moments.hu());
+        });
+    }
+}
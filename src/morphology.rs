@@ -3,8 +3,12 @@
 //! [morphological operators]: http://homepages.inf.ed.ac.uk/rbf/HIPR2/morops.htm
 
 use std::u8;
-use image::GrayImage;
-use distance_transform::{DistanceFrom, distance_transform_impl, distance_transform_mut, Norm};
+use std::cmp::{max, min};
+use image::{GrayImage, Luma};
+use distance_transform::{DistanceFrom, threshold_distance_impl, Norm};
+use region_labelling::Connectivity;
+use segmentation::neighbour_offsets;
+use std::collections::VecDeque;
 
 /// Sets all pixels within distance `k` of a foreground pixel to white.
 ///
@@ -63,10 +67,15 @@ pub fn dilate(image: &GrayImage, norm: Norm, k: u8) -> GrayImage {
 ///
 /// See the [`dilate`](fn.dilate.html) documentation for examples.
 pub fn dilate_mut(image: &mut GrayImage, norm: Norm, k: u8) {
-    distance_transform_mut(image, norm);
-    for p in image.iter_mut() {
-        *p = if *p <= k { 255 } else { 0 };
-    }
+    threshold_distance_impl(image, norm, DistanceFrom::Foreground, k, 255, 0);
+}
+
+/// Writes the dilation of `image` to `out`, which must have the same dimensions.
+///
+/// See the [`dilate`](fn.dilate.html) documentation for details.
+pub fn dilate_into(image: &GrayImage, norm: Norm, k: u8, out: &mut GrayImage) {
+    copy_into(image, out);
+    dilate_mut(out, norm, k);
 }
 
 /// Sets all pixels within distance `k` of a background pixel to black.
@@ -141,10 +150,15 @@ pub fn erode(image: &GrayImage, norm: Norm, k: u8) -> GrayImage {
 ///
 /// See the [`erode`](fn.erode.html) documentation for examples.
 pub fn erode_mut(image: &mut GrayImage, norm: Norm, k: u8) {
-    distance_transform_impl(image, norm, DistanceFrom::Background);
-    for p in image.iter_mut() {
-        *p = if *p <= k { 0 } else { 255 };
-    }
+    threshold_distance_impl(image, norm, DistanceFrom::Background, k, 0, 255);
+}
+
+/// Writes the erosion of `image` to `out`, which must have the same dimensions.
+///
+/// See the [`erode`](fn.erode.html) documentation for details.
+pub fn erode_into(image: &GrayImage, norm: Norm, k: u8, out: &mut GrayImage) {
+    copy_into(image, out);
+    erode_mut(out, norm, k);
 }
 
 /// Erosion followed by dilation.
@@ -214,6 +228,14 @@ pub fn open_mut(image: &mut GrayImage, norm: Norm, k: u8) {
     dilate_mut(image, norm, k);
 }
 
+/// Writes the opening of `image` to `out`, which must have the same dimensions.
+///
+/// See the [`open`](fn.open.html) documentation for details.
+pub fn open_into(image: &GrayImage, norm: Norm, k: u8, out: &mut GrayImage) {
+    copy_into(image, out);
+    open_mut(out, norm, k);
+}
+
 /// Dilation followed by erosion.
 ///
 /// See the [`erode`](fn.erode.html) and [`dilate`](fn.dilate.html)
@@ -319,12 +341,856 @@ pub fn close_mut(image: &mut GrayImage, norm: Norm, k: u8) {
     erode_mut(image, norm, k);
 }
 
+/// Writes the closing of `image` to `out`, which must have the same dimensions.
+///
+/// See the [`close`](fn.close.html) documentation for details.
+pub fn close_into(image: &GrayImage, norm: Norm, k: u8, out: &mut GrayImage) {
+    copy_into(image, out);
+    close_mut(out, norm, k);
+}
+
+/// A structuring element for the grayscale morphological operators, defined
+/// as a set of offsets from its centre.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::Mask;
+///
+/// // A horizontal line of length three, centred on its middle pixel.
+/// let line = Mask::from_image(&gray_image!(255, 255, 255), 1, 0);
+/// assert_eq!(line.offsets(), &[(-1, 0), (0, 0), (1, 0)]);
+///
+/// // The pixels within L1 distance 1 of the centre.
+/// let diamond = Mask::diamond(1);
+/// assert_eq!(diamond.offsets(), &[(0, -1), (-1, 0), (0, 0), (1, 0), (0, 1)]);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mask {
+    // Offsets in row-major order.
+    offsets: Vec<(i32, i32)>,
+}
+
+impl Mask {
+    /// Creates a mask containing the offsets from `(center_x, center_y)` of all
+    /// pixels in `image` with non-zero intensity.
+    ///
+    /// The centre need not lie within the image.
+    pub fn from_image(image: &GrayImage, center_x: i32, center_y: i32) -> Mask {
+        let offsets = image
+            .enumerate_pixels()
+            .filter(|&(_, _, p)| p[0] != 0)
+            .map(|(x, y, _)| (x as i32 - center_x, y as i32 - center_y))
+            .collect();
+        Mask { offsets }
+    }
+
+    /// A square of side `2 * radius + 1`, i.e. all offsets within
+    /// [`Norm::LInf`](../distance_transform/enum.Norm.html) distance `radius` of the centre.
+    pub fn square(radius: u8) -> Mask {
+        Mask::from_predicate(radius, |_, _| true)
+    }
+
+    /// All offsets within [`Norm::L1`](../distance_transform/enum.Norm.html) distance
+    /// `radius` of the centre.
+    pub fn diamond(radius: u8) -> Mask {
+        Mask::from_predicate(radius, |dx, dy| dx.abs() + dy.abs() <= radius as i32)
+    }
+
+    /// All offsets within Euclidean distance `radius` of the centre.
+    pub fn disk(radius: u8) -> Mask {
+        let r = radius as i32;
+        Mask::from_predicate(radius, |dx, dy| dx * dx + dy * dy <= r * r)
+    }
+
+    fn from_predicate<F: Fn(i32, i32) -> bool>(radius: u8, f: F) -> Mask {
+        let r = radius as i32;
+        let mut offsets = vec![];
+        for dy in -r..r + 1 {
+            for dx in -r..r + 1 {
+                if f(dx, dy) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        Mask { offsets }
+    }
+
+    /// The offsets in this mask, in row-major order.
+    pub fn offsets(&self) -> &[(i32, i32)] {
+        &self.offsets
+    }
+}
+
+/// Grayscale dilation: sets each pixel `(x, y)` to the maximum intensity of the pixels
+/// `(x - dx, y - dy)` in the image, for `(dx, dy)` in `mask`.
+///
+/// Positions outside the image are ignored, and pixels with no such positions in the
+/// image are set to 0. For binary images and masks given by [`Mask::diamond`](struct.Mask.html#method.diamond)
+/// and [`Mask::square`](struct.Mask.html#method.square) this matches
+/// [`dilate`](fn.dilate.html) with norms `L1` and `LInf`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::{grayscale_dilate, Mask};
+///
+/// let image = gray_image!(
+///     10, 20, 10, 10;
+///     10, 10, 10, 80;
+///     10, 10, 10, 10);
+///
+/// let dilated = gray_image!(
+///     20, 20, 80, 80;
+///     20, 20, 80, 80;
+///     10, 10, 80, 80);
+///
+/// assert_pixels_eq!(grayscale_dilate(&image, &Mask::square(1)), dilated);
+/// # }
+/// ```
+pub fn grayscale_dilate(image: &GrayImage, mask: &Mask) -> GrayImage {
+    let reflected: Vec<(i32, i32)> = mask.offsets.iter().map(|&(dx, dy)| (-dx, -dy)).collect();
+    grayscale_morphology(image, &reflected, 0, max)
+}
+
+/// Grayscale erosion: sets each pixel `(x, y)` to the minimum intensity of the pixels
+/// `(x + dx, y + dy)` in the image, for `(dx, dy)` in `mask`.
+///
+/// Positions outside the image are ignored, and pixels with no such positions in the
+/// image are set to 255. For binary images and masks given by [`Mask::diamond`](struct.Mask.html#method.diamond)
+/// and [`Mask::square`](struct.Mask.html#method.square) this matches
+/// [`erode`](fn.erode.html) with norms `L1` and `LInf`, except at the image boundary.
+pub fn grayscale_erode(image: &GrayImage, mask: &Mask) -> GrayImage {
+    grayscale_morphology(image, &mask.offsets, 255, min)
+}
+
+/// Grayscale erosion followed by grayscale dilation, using the same mask.
+///
+/// See [`grayscale_erode`](fn.grayscale_erode.html) and [`grayscale_dilate`](fn.grayscale_dilate.html).
+pub fn grayscale_open(image: &GrayImage, mask: &Mask) -> GrayImage {
+    grayscale_dilate(&grayscale_erode(image, mask), mask)
+}
+
+/// Grayscale dilation followed by grayscale erosion, using the same mask.
+///
+/// See [`grayscale_erode`](fn.grayscale_erode.html) and [`grayscale_dilate`](fn.grayscale_dilate.html).
+pub fn grayscale_close(image: &GrayImage, mask: &Mask) -> GrayImage {
+    grayscale_erode(&grayscale_dilate(image, mask), mask)
+}
+
+/// The white top-hat transform: the difference between an image and its
+/// [`grayscale_open`](fn.grayscale_open.html)ing. Highlights bright features
+/// smaller than the mask.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::{top_hat, Mask};
+///
+/// // A bright spot and a larger bright region.
+/// let image = gray_image!(
+///     10, 10, 10, 10, 70, 70, 70;
+///     10, 90, 10, 10, 70, 70, 70;
+///     10, 10, 10, 10, 70, 70, 70);
+///
+/// let spot = gray_image!(
+///      0,  0,  0,  0,  0,  0,  0;
+///      0, 80,  0,  0,  0,  0,  0;
+///      0,  0,  0,  0,  0,  0,  0);
+///
+/// assert_pixels_eq!(top_hat(&image, &Mask::square(1)), spot);
+/// # }
+/// ```
+pub fn top_hat(image: &GrayImage, mask: &Mask) -> GrayImage {
+    let opened = grayscale_open(image, mask);
+    difference(image, &opened)
+}
+
+/// The black top-hat transform: the difference between the
+/// [`grayscale_close`](fn.grayscale_close.html)ing of an image and the image.
+/// Highlights dark features smaller than the mask.
+pub fn black_hat(image: &GrayImage, mask: &Mask) -> GrayImage {
+    let closed = grayscale_close(image, mask);
+    difference(&closed, image)
+}
+
+/// The morphological gradient: the difference between the
+/// [`grayscale_dilate`](fn.grayscale_dilate.html)ion and the
+/// [`grayscale_erode`](fn.grayscale_erode.html)ion of an image.
+///
+/// This is the sum of the [`internal_gradient`](fn.internal_gradient.html) and the
+/// [`external_gradient`](fn.external_gradient.html), and gives a cheap estimate of
+/// edge strength that is less sensitive to noise than derivative filters, e.g. as
+/// the input to a watershed segmentation. Edges are as wide as the mask.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::{external_gradient, internal_gradient, morphological_gradient, Mask};
+///
+/// let image = gray_image!(
+///     10, 10, 10, 80, 80;
+///     10, 10, 10, 80, 80);
+///
+/// let mask = Mask::square(1);
+///
+/// assert_pixels_eq!(
+///     morphological_gradient(&image, &mask),
+///     gray_image!(
+///         0, 0, 70, 70, 0;
+///         0, 0, 70, 70, 0));
+///
+/// // The internal gradient marks the edge pixels of the bright region...
+/// assert_pixels_eq!(
+///     internal_gradient(&image, &mask),
+///     gray_image!(
+///         0, 0, 0, 70, 0;
+///         0, 0, 0, 70, 0));
+///
+/// // ...and the external gradient the pixels just outside it.
+/// assert_pixels_eq!(
+///     external_gradient(&image, &mask),
+///     gray_image!(
+///         0, 0, 70, 0, 0;
+///         0, 0, 70, 0, 0));
+/// # }
+/// ```
+pub fn morphological_gradient(image: &GrayImage, mask: &Mask) -> GrayImage {
+    let dilated = grayscale_dilate(image, mask);
+    let eroded = grayscale_erode(image, mask);
+    difference(&dilated, &eroded)
+}
+
+/// The internal morphological gradient: the difference between an image and its
+/// [`grayscale_erode`](fn.grayscale_erode.html)ion. Highlights the pixels on the
+/// inside of the boundaries of bright regions.
+///
+/// See [`morphological_gradient`](fn.morphological_gradient.html) for examples.
+pub fn internal_gradient(image: &GrayImage, mask: &Mask) -> GrayImage {
+    let eroded = grayscale_erode(image, mask);
+    difference(image, &eroded)
+}
+
+/// The external morphological gradient: the difference between the
+/// [`grayscale_dilate`](fn.grayscale_dilate.html)ion of an image and the image.
+/// Highlights the pixels on the outside of the boundaries of bright regions.
+///
+/// See [`morphological_gradient`](fn.morphological_gradient.html) for examples.
+pub fn external_gradient(image: &GrayImage, mask: &Mask) -> GrayImage {
+    let dilated = grayscale_dilate(image, mask);
+    difference(&dilated, image)
+}
+
+/// The [hit-or-miss transform]: sets each pixel `(x, y)` to 255 if the pixels
+/// `(x + dx, y + dy)` have non-zero intensity for all `(dx, dy)` in `foreground` and have
+/// intensity 0 for all `(dx, dy)` in `background`, and to 0 otherwise.
+///
+/// Offsets in neither mask are ignored, so can be used as "don't care" positions.
+/// Positions outside the image are treated as background. If the masks share an offset
+/// then the output is entirely black.
+///
+/// [hit-or-miss transform]: https://en.wikipedia.org/wiki/Hit-or-miss_transform
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::{hit_or_miss, Mask};
+///
+/// // Finds the right hand ends of horizontal lines.
+/// let foreground = Mask::from_image(&gray_image!(255, 255, 0), 1, 0);
+/// let background = Mask::from_image(&gray_image!(0, 0, 255), 1, 0);
+///
+/// let image = gray_image!(
+///       0, 255, 255, 255,   0;
+///       0,   0,   0,   0,   0;
+///     255, 255,   0, 255, 255);
+///
+/// assert_pixels_eq!(
+///     hit_or_miss(&image, &foreground, &background),
+///     gray_image!(
+///       0,   0,   0, 255,   0;
+///       0,   0,   0,   0,   0;
+///       0, 255,   0,   0, 255));
+/// # }
+/// ```
+pub fn hit_or_miss(image: &GrayImage, foreground: &Mask, background: &Mask) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let mut out = GrayImage::from_pixel(width, height, Luma([255]));
+    if width == 0 || height == 0 {
+        return out;
+    }
+    let w = width as usize;
+    let raw: &[u8] = image;
+    for (y, out_row) in out.chunks_mut(w).enumerate() {
+        for &(mask, hit_value) in &[(foreground, true), (background, false)] {
+            for &(dx, dy) in &mask.offsets {
+                let py = y as i64 + dy as i64;
+                let source_row = if py >= 0 && py < height as i64 {
+                    Some(&raw[py as usize * w..(py as usize + 1) * w])
+                } else {
+                    None
+                };
+                for (x, o) in out_row.iter_mut().enumerate() {
+                    let px = x as i64 + dx as i64;
+                    let is_foreground = match source_row {
+                        Some(row) if px >= 0 && px < w as i64 => row[px as usize] != 0,
+                        _ => false,
+                    };
+                    if is_foreground != hit_value {
+                        *o = 0;
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Geodesic dilation of `marker` under `mask`: the pointwise minimum of `mask` and the
+/// dilation of `marker` by a single step to each pixel's neighbours.
+///
+/// # Panics
+/// If `marker` and `mask` do not have the same dimensions.
+pub fn geodesic_dilate(marker: &GrayImage, mask: &GrayImage, connectivity: Connectivity) -> GrayImage {
+    assert_dimensions_match!(marker, mask);
+    let dilated = grayscale_dilate(marker, &unit_mask(connectivity));
+    pointwise(&dilated, mask, min)
+}
+
+/// Geodesic erosion of `marker` over `mask`: the pointwise maximum of `mask` and the
+/// erosion of `marker` by a single step to each pixel's neighbours.
+///
+/// # Panics
+/// If `marker` and `mask` do not have the same dimensions.
+pub fn geodesic_erode(marker: &GrayImage, mask: &GrayImage, connectivity: Connectivity) -> GrayImage {
+    assert_dimensions_match!(marker, mask);
+    let eroded = grayscale_erode(marker, &unit_mask(connectivity));
+    pointwise(&eroded, mask, max)
+}
+
+/// Morphological reconstruction by dilation: the result of repeatedly applying
+/// [`geodesic_dilate`](fn.geodesic_dilate.html) to `marker` under `mask` until it stops
+/// changing.
+///
+/// Each connected component of each threshold of `mask` is recovered exactly if
+/// it contains a pixel at which `marker` reaches the threshold, and removed otherwise.
+/// Pixels at which `marker` exceeds `mask` are first clipped to `mask`.
+///
+/// Uses the hybrid algorithm from Vincent's "Morphological grayscale reconstruction in
+/// image analysis: applications and efficient algorithms", 1993.
+///
+/// # Panics
+/// If `marker` and `mask` do not have the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::reconstruct_by_dilation;
+/// use imageproc::region_labelling::Connectivity;
+///
+/// let mask = gray_image!(
+///     0, 9, 9, 0, 7, 7;
+///     0, 9, 5, 0, 7, 7;
+///     0, 0, 0, 0, 0, 0);
+///
+/// // Marks the left hand object.
+/// let marker = gray_image!(
+///     0, 0, 0, 0, 0, 0;
+///     0, 6, 0, 0, 0, 0;
+///     0, 0, 0, 0, 0, 0);
+///
+/// assert_pixels_eq!(
+///     reconstruct_by_dilation(&marker, &mask, Connectivity::Four),
+///     gray_image!(
+///         0, 6, 6, 0, 0, 0;
+///         0, 6, 5, 0, 0, 0;
+///         0, 0, 0, 0, 0, 0));
+/// # }
+/// ```
+pub fn reconstruct_by_dilation(marker: &GrayImage, mask: &GrayImage, connectivity: Connectivity) -> GrayImage {
+    assert_dimensions_match!(marker, mask);
+    let (width, height) = mask.dimensions();
+    let mut values: Vec<u8> = marker.iter().zip(mask.iter()).map(|(m, b)| min(*m, *b)).collect();
+    reconstruct(&mut values, mask, width, height, connectivity);
+    GrayImage::from_raw(width, height, values).unwrap()
+}
+
+/// Morphological reconstruction by erosion: the result of repeatedly applying
+/// [`geodesic_erode`](fn.geodesic_erode.html) to `marker` over `mask` until it stops
+/// changing.
+///
+/// This is the dual of [`reconstruct_by_dilation`](fn.reconstruct_by_dilation.html), and
+/// fills dark regions of `mask` that `marker` does not reach into. Pixels at which
+/// `marker` is below `mask` are first clipped to `mask`.
+///
+/// # Panics
+/// If `marker` and `mask` do not have the same dimensions.
+pub fn reconstruct_by_erosion(marker: &GrayImage, mask: &GrayImage, connectivity: Connectivity) -> GrayImage {
+    assert_dimensions_match!(marker, mask);
+    let (width, height) = mask.dimensions();
+    let inverted_mask: Vec<u8> = mask.iter().map(|p| u8::MAX - p).collect();
+    let mut values: Vec<u8> = marker.iter().zip(mask.iter()).map(|(m, b)| u8::MAX - max(*m, *b)).collect();
+    reconstruct(&mut values, &inverted_mask, width, height, connectivity);
+    for v in &mut values {
+        *v = u8::MAX - *v;
+    }
+    GrayImage::from_raw(width, height, values).unwrap()
+}
+
+/// The h-maxima transform: suppresses every regional maximum of `image` whose height
+/// above the lowest pass to a higher region is at most `h`, and lowers all other regional
+/// maxima by `h`.
+///
+/// Computed by [`reconstruct_by_dilation`](fn.reconstruct_by_dilation.html) of `image - h`
+/// under `image`. The [`regional_maxima`](fn.regional_maxima.html) of the result are the
+/// significant peaks of `image`, so make robust markers for
+/// [`watershed`](../segmentation/fn.watershed.html) segmentation.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::{h_maxima, regional_maxima};
+/// use imageproc::region_labelling::Connectivity;
+///
+/// // A peak of height 6 and a peak of height 2 above the pass between them.
+/// let image = gray_image!(
+///     1, 9, 3, 5, 1);
+///
+/// assert_pixels_eq!(
+///     regional_maxima(&image, Connectivity::Four),
+///     gray_image!(
+///         0, 255, 0, 255, 0));
+///
+/// let suppressed = h_maxima(&image, 4, Connectivity::Four);
+/// assert_pixels_eq!(suppressed, gray_image!(1, 5, 3, 3, 1));
+/// assert_pixels_eq!(
+///     regional_maxima(&suppressed, Connectivity::Four),
+///     gray_image!(
+///         0, 255, 0, 0, 0));
+/// # }
+/// ```
+pub fn h_maxima(image: &GrayImage, h: u8, connectivity: Connectivity) -> GrayImage {
+    let mut lowered = image.clone();
+    for p in lowered.iter_mut() {
+        *p = p.saturating_sub(h);
+    }
+    reconstruct_by_dilation(&lowered, image, connectivity)
+}
+
+/// The h-minima transform: fills every regional minimum of `image` whose depth below the
+/// lowest pass to a lower region is at most `h`, and raises all other regional minima by `h`.
+///
+/// This is the dual of [`h_maxima`](fn.h_maxima.html), computed by
+/// [`reconstruct_by_erosion`](fn.reconstruct_by_erosion.html) of `image + h` over `image`.
+/// The [`regional_minima`](fn.regional_minima.html) of the result are the significant
+/// basins of `image`.
+pub fn h_minima(image: &GrayImage, h: u8, connectivity: Connectivity) -> GrayImage {
+    let mut raised = image.clone();
+    for p in raised.iter_mut() {
+        *p = p.saturating_add(h);
+    }
+    reconstruct_by_erosion(&raised, image, connectivity)
+}
+
+/// Returns an image that is 255 on the regional maxima of `image` and 0 elsewhere.
+///
+/// A regional maximum is a connected set of pixels of equal intensity whose neighbours
+/// all have lower intensities. Every pixel of an image of constant intensity is in its
+/// single regional maximum.
+pub fn regional_maxima(image: &GrayImage, connectivity: Connectivity) -> GrayImage {
+    regional_extrema(image, connectivity, |neighbour, value| neighbour > value)
+}
+
+/// Returns an image that is 255 on the regional minima of `image` and 0 elsewhere.
+///
+/// A regional minimum is a connected set of pixels of equal intensity whose neighbours
+/// all have higher intensities. Every pixel of an image of constant intensity is in its
+/// single regional minimum.
+pub fn regional_minima(image: &GrayImage, connectivity: Connectivity) -> GrayImage {
+    regional_extrema(image, connectivity, |neighbour, value| neighbour < value)
+}
+
+// The mask containing the centre and its neighbours for the given connectivity.
+fn unit_mask(connectivity: Connectivity) -> Mask {
+    match connectivity {
+        Connectivity::Four => Mask::diamond(1),
+        Connectivity::Eight => Mask::square(1),
+    }
+}
+
+fn pointwise<F>(image: &GrayImage, other: &GrayImage, f: F) -> GrayImage
+where
+    F: Fn(u8, u8) -> u8,
+{
+    let mut out = image.clone();
+    for (o, p) in out.iter_mut().zip(other.iter()) {
+        *o = f(*o, *p);
+    }
+    out
+}
+
+// Reconstructs values under mask in place, where values is at most mask everywhere.
+// Two raster scans propagate values in most images, and pixels from which values may
+// still spread are then processed using a queue.
+fn reconstruct(values: &mut [u8], mask: &[u8], width: u32, height: u32, connectivity: Connectivity) {
+    let offsets = neighbour_offsets(connectivity);
+    // The neighbours preceding each pixel in raster order, and those following it.
+    let before: Vec<(i32, i32)> = offsets.iter().cloned().filter(|&(dx, dy)| dy < 0 || (dy == 0 && dx < 0)).collect();
+    let after: Vec<(i32, i32)> = before.iter().map(|&(dx, dy)| (-dx, -dy)).collect();
+    let (w, h) = (width as i32, height as i32);
+    let neighbour = |x: i32, y: i32, dx: i32, dy: i32| {
+        let (nx, ny) = (x + dx, y + dy);
+        if nx >= 0 && ny >= 0 && nx < w && ny < h {
+            Some((ny * w + nx) as usize)
+        } else {
+            None
+        }
+    };
+
+    for y in 0..h {
+        for x in 0..w {
+            let i = (y * w + x) as usize;
+            let mut value = values[i];
+            for &(dx, dy) in &before {
+                if let Some(j) = neighbour(x, y, dx, dy) {
+                    value = max(value, values[j]);
+                }
+            }
+            values[i] = min(value, mask[i]);
+        }
+    }
+
+    let mut queue = VecDeque::new();
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            let i = (y * w + x) as usize;
+            let mut value = values[i];
+            for &(dx, dy) in &after {
+                if let Some(j) = neighbour(x, y, dx, dy) {
+                    value = max(value, values[j]);
+                }
+            }
+            let value = min(value, mask[i]);
+            values[i] = value;
+            let spreads = after.iter().any(|&(dx, dy)| match neighbour(x, y, dx, dy) {
+                Some(j) => values[j] < value && values[j] < mask[j],
+                None => false,
+            });
+            if spreads {
+                queue.push_back((x, y));
+            }
+        }
+    }
+
+    while let Some((x, y)) = queue.pop_front() {
+        let value = values[(y * w + x) as usize];
+        for &(dx, dy) in offsets {
+            if let Some(j) = neighbour(x, y, dx, dy) {
+                if values[j] < value && values[j] < mask[j] {
+                    values[j] = min(value, mask[j]);
+                    queue.push_back((x + dx, y + dy));
+                }
+            }
+        }
+    }
+}
+
+// Sets the pixels of each plateau of image to 255 if no neighbour of the plateau
+// exceeds it, and to 0 otherwise.
+fn regional_extrema<F>(image: &GrayImage, connectivity: Connectivity, exceeds: F) -> GrayImage
+where
+    F: Fn(u8, u8) -> bool,
+{
+    let (width, height) = image.dimensions();
+    let (w, h) = (width as i32, height as i32);
+    let offsets = neighbour_offsets(connectivity);
+    let pixels: &[u8] = image;
+    let mut out = vec![0u8; pixels.len()];
+    let mut visited = vec![false; pixels.len()];
+    let mut plateau = vec![];
+
+    for start in 0..pixels.len() {
+        if visited[start] {
+            continue;
+        }
+        let value = pixels[start];
+        visited[start] = true;
+        plateau.clear();
+        plateau.push(start);
+        let mut extremal = true;
+        let mut next = 0;
+        while next < plateau.len() {
+            let (x, y) = ((plateau[next] % width as usize) as i32, (plateau[next] / width as usize) as i32);
+            next += 1;
+            for &(dx, dy) in offsets {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= w || ny >= h {
+                    continue;
+                }
+                let j = (ny * w + nx) as usize;
+                if pixels[j] == value {
+                    if !visited[j] {
+                        visited[j] = true;
+                        plateau.push(j);
+                    }
+                } else if exceeds(pixels[j], value) {
+                    extremal = false;
+                }
+            }
+        }
+        if extremal {
+            for &i in &plateau {
+                out[i] = 255;
+            }
+        }
+    }
+    GrayImage::from_raw(width, height, out).unwrap()
+}
+
+/// The algorithm used by [`thin_with_method`](fn.thin_with_method.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThinningMethod {
+    /// The algorithm of Zhang and Suen, from "A fast parallel algorithm for thinning
+    /// digital patterns", 1984.
+    ZhangSuen,
+    /// The algorithm of Guo and Hall, from "Parallel thinning with two-subiteration
+    /// algorithms", 1989. This tends to preserve diagonal lines and the ends of strokes
+    /// better than Zhang-Suen.
+    GuoHall,
+}
+
+/// Thins the foreground of a binary image to a skeleton of lines one pixel wide, using the
+/// Zhang-Suen algorithm.
+///
+/// Equivalent to calling [`thin_with_method`](fn.thin_with_method.html) with
+/// `ThinningMethod::ZhangSuen`.
+pub fn thin(image: &GrayImage) -> GrayImage {
+    thin_with_method(image, ThinningMethod::ZhangSuen)
+}
+
+/// Thins the foreground of a binary image to a skeleton of lines one pixel wide.
+///
+/// Pixels with non-zero intensity are treated as foreground, and pixels outside the image
+/// as background. Foreground pixels on the boundary of each shape are repeatedly removed
+/// until no more can be removed without disconnecting a shape or shortening a line. The
+/// output has value 255 on the skeleton and 0 elsewhere.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::morphology::{thin_with_method, ThinningMethod};
+///
+/// let image = gray_image!(
+///       0,   0,   0,   0,   0,   0,   0;
+///       0, 255, 255, 255, 255, 255,   0;
+///       0, 255, 255, 255, 255, 255,   0;
+///       0, 255, 255, 255, 255, 255,   0;
+///       0,   0,   0,   0,   0,   0,   0);
+///
+/// assert_pixels_eq!(
+///     thin_with_method(&image, ThinningMethod::ZhangSuen),
+///     gray_image!(
+///         0,   0,   0,   0,   0,   0,   0;
+///         0,   0,   0,   0,   0,   0,   0;
+///         0,   0, 255, 255,   0,   0,   0;
+///         0,   0,   0,   0,   0,   0,   0;
+///         0,   0,   0,   0,   0,   0,   0));
+///
+/// assert_pixels_eq!(
+///     thin_with_method(&image, ThinningMethod::GuoHall),
+///     gray_image!(
+///         0,   0,   0,   0,   0,   0,   0;
+///         0,   0,   0,   0,   0,   0,   0;
+///         0,   0, 255, 255, 255,   0,   0;
+///         0,   0,   0,   0,   0,   0,   0;
+///         0,   0,   0,   0,   0,   0,   0));
+/// # }
+/// ```
+pub fn thin_with_method(image: &GrayImage, method: ThinningMethod) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let mut foreground: Vec<bool> = image.iter().map(|p| *p != 0).collect();
+    let mut removed = vec![];
+
+    loop {
+        let mut changed = false;
+        for &first in &[true, false] {
+            removed.clear();
+            for y in 0..height {
+                for x in 0..width {
+                    if !foreground[(y * width + x) as usize] {
+                        continue;
+                    }
+                    let n = neighbours(&foreground, width, height, x, y);
+                    let remove = match method {
+                        ThinningMethod::ZhangSuen => zhang_suen_removes(n, first),
+                        ThinningMethod::GuoHall => guo_hall_removes(n, first),
+                    };
+                    if remove {
+                        removed.push((y * width + x) as usize);
+                    }
+                }
+            }
+            for &i in &removed {
+                foreground[i] = false;
+            }
+            changed |= !removed.is_empty();
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut out = GrayImage::new(width, height);
+    for (o, f) in out.iter_mut().zip(foreground) {
+        if f {
+            *o = 255;
+        }
+    }
+    out
+}
+
+// The neighbours of (x, y), clockwise from the pixel above, i.e. the pixels
+// P2 to P9 in the usual notation for thinning algorithms.
+fn neighbours(foreground: &[bool], width: u32, height: u32, x: u32, y: u32) -> [bool; 8] {
+    let at = |dx: i32, dy: i32| {
+        let (px, py) = (x as i32 + dx, y as i32 + dy);
+        px >= 0 && py >= 0 && px < width as i32 && py < height as i32
+            && foreground[(py as u32 * width + px as u32) as usize]
+    };
+    [at(0, -1), at(1, -1), at(1, 0), at(1, 1), at(0, 1), at(-1, 1), at(-1, 0), at(-1, -1)]
+}
+
+fn zhang_suen_removes(n: [bool; 8], first: bool) -> bool {
+    let (p2, p4, p6, p8) = (n[0], n[2], n[4], n[6]);
+    let count = n.iter().filter(|&&p| p).count();
+    let transitions = (0..8).filter(|&i| !n[i] && n[(i + 1) % 8]).count();
+    let (a, b) = if first {
+        (p2 && p4 && p6, p4 && p6 && p8)
+    } else {
+        (p2 && p4 && p8, p2 && p6 && p8)
+    };
+    count >= 2 && count <= 6 && transitions == 1 && !a && !b
+}
+
+fn guo_hall_removes(n: [bool; 8], first: bool) -> bool {
+    let (p2, p3, p4, p5) = (n[0], n[1], n[2], n[3]);
+    let (p6, p7, p8, p9) = (n[4], n[5], n[6], n[7]);
+    let c = (!p2 && (p3 || p4)) as u8 + (!p4 && (p5 || p6)) as u8
+        + (!p6 && (p7 || p8)) as u8 + (!p8 && (p9 || p2)) as u8;
+    let n1 = (p9 || p2) as u8 + (p3 || p4) as u8 + (p5 || p6) as u8 + (p7 || p8) as u8;
+    let n2 = (p2 || p3) as u8 + (p4 || p5) as u8 + (p6 || p7) as u8 + (p8 || p9) as u8;
+    let m = if first { (p6 || p7 || !p9) && p8 } else { (p2 || p3 || !p5) && p4 };
+    let n = min(n1, n2);
+    c == 1 && n >= 2 && n <= 3 && !m
+}
+
+// Computes f of the pixels (x + dx, y + dy) for each offset within the image,
+// or empty if there are no such pixels.
+fn grayscale_morphology<F>(image: &GrayImage, offsets: &[(i32, i32)], empty: u8, f: F) -> GrayImage
+where
+    F: Fn(u8, u8) -> u8,
+{
+    let (width, height) = image.dimensions();
+    let mut out = GrayImage::from_pixel(width, height, Luma([empty]));
+    if width == 0 || height == 0 {
+        return out;
+    }
+    let w = width as i64;
+    let raw: &[u8] = image;
+    // Whether each pixel in the current output row has been set from any offset yet.
+    let mut found = vec![false; width as usize];
+    for (y, out_row) in out.chunks_mut(width as usize).enumerate() {
+        for flag in found.iter_mut() {
+            *flag = false;
+        }
+        for &(dx, dy) in offsets {
+            let py = y as i64 + dy as i64;
+            if py < 0 || py >= height as i64 {
+                continue;
+            }
+            // The output pixels x for which x + dx lies within the image.
+            let (start, end) = (max(0, -(dx as i64)), min(w, w - dx as i64));
+            if start >= end {
+                continue;
+            }
+            let row_start = py as usize * width as usize;
+            let source = &raw[row_start + (start + dx as i64) as usize..row_start + (end + dx as i64) as usize];
+            let (start, end) = (start as usize, end as usize);
+            for ((o, flag), p) in out_row[start..end].iter_mut().zip(&mut found[start..end]).zip(source) {
+                *o = if *flag { f(*o, *p) } else { *p };
+                *flag = true;
+            }
+        }
+    }
+    out
+}
+
+fn difference(larger: &GrayImage, smaller: &GrayImage) -> GrayImage {
+    let mut out = larger.clone();
+    for (o, s) in out.iter_mut().zip(smaller.iter()) {
+        *o = o.saturating_sub(*s);
+    }
+    out
+}
+
+fn copy_into(image: &GrayImage, out: &mut GrayImage) {
+    assert_eq!(out.dimensions(), image.dimensions(), "output dimensions must match input");
+    out.copy_from_slice(image);
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use image::{GrayImage, Luma};
     use test::*;
     use std::cmp::{max, min};
+    use utils::gray_bench_image;
+
+    #[test]
+    fn test_into_variants_match_allocating_variants() {
+        let image = gray_image!(
+              0,   0,   0,   0,   0;
+              0, 255, 255,   0,   0;
+              0, 255,   0,   0, 255;
+              0,   0,   0,   0,   0);
+        let mut out = GrayImage::new(5, 4);
+
+        for &norm in &[Norm::L1, Norm::LInf] {
+            dilate_into(&image, norm, 1, &mut out);
+            assert_pixels_eq!(out, dilate(&image, norm, 1));
+            erode_into(&image, norm, 1, &mut out);
+            assert_pixels_eq!(out, erode(&image, norm, 1));
+            open_into(&image, norm, 1, &mut out);
+            assert_pixels_eq!(out, open(&image, norm, 1));
+            close_into(&image, norm, 1, &mut out);
+            assert_pixels_eq!(out, close(&image, norm, 1));
+        }
+    }
 
     #[test]
     fn test_dilate_point_l1_1() {
@@ -466,6 +1332,324 @@ mod test {
         })
     }
 
+    #[test]
+    fn test_mask_shapes() {
+        assert_eq!(Mask::square(1).offsets().len(), 9);
+        assert_eq!(Mask::diamond(2).offsets().len(), 13);
+        assert_eq!(
+            Mask::disk(2).offsets(),
+            &[
+                (0, -2),
+                (-1, -1), (0, -1), (1, -1),
+                (-2, 0), (-1, 0), (0, 0), (1, 0), (2, 0),
+                (-1, 1), (0, 1), (1, 1),
+                (0, 2)
+            ]
+        );
+        assert_eq!(Mask::disk(3).offsets().len(), 29);
+        assert_eq!(Mask::from_image(&gray_image!(0, 1; 1, 0), 5, 5).offsets(), &[(-4, -5), (-5, -4)]);
+    }
+
+    #[test]
+    fn test_grayscale_dilate_matches_binary_dilate() {
+        let image = gray_image!(
+              0,   0,   0,   0,   0,   0;
+              0, 255,   0,   0,   0,   0;
+              0,   0,   0,   0,   0, 255;
+              0,   0,   0, 255,   0,   0;
+              0,   0,   0,   0,   0,   0);
+
+        for k in 0..3 {
+            assert_pixels_eq!(grayscale_dilate(&image, &Mask::diamond(k)), dilate(&image, Norm::L1, k));
+            assert_pixels_eq!(grayscale_dilate(&image, &Mask::square(k)), dilate(&image, Norm::LInf, k));
+        }
+    }
+
+    #[test]
+    fn test_grayscale_erode_matches_binary_erode_away_from_boundary() {
+        let image = GrayImage::from_fn(12, 10, |x, y| {
+            if x > 1 && x < 10 && y > 1 && y < 8 && (x, y) != (5, 4) { Luma([255]) } else { Luma([0]) }
+        });
+        for k in 1..3 {
+            assert_pixels_eq!(grayscale_erode(&image, &Mask::diamond(k)), erode(&image, Norm::L1, k));
+            assert_pixels_eq!(grayscale_erode(&image, &Mask::square(k)), erode(&image, Norm::LInf, k));
+        }
+    }
+
+    #[test]
+    fn test_grayscale_morphology_with_asymmetric_mask() {
+        // A mask containing the centre and the pixel to its right.
+        let mask = Mask::from_image(&gray_image!(255, 255), 0, 0);
+        let image = gray_image!(1, 5, 2, 9, 3);
+
+        assert_pixels_eq!(grayscale_erode(&image, &mask), gray_image!(1, 2, 2, 3, 3));
+        assert_pixels_eq!(grayscale_dilate(&image, &mask), gray_image!(1, 5, 5, 9, 9));
+        // Opening is anti-extensive and closing is extensive.
+        let opened = grayscale_open(&image, &mask);
+        let closed = grayscale_close(&image, &mask);
+        assert_pixels_eq!(opened, gray_image!(1, 2, 2, 3, 3));
+        assert_pixels_eq!(closed, gray_image!(1, 5, 5, 9, 9));
+    }
+
+    #[test]
+    fn test_top_hat_and_black_hat() {
+        let image = gray_image!(
+            50, 50, 50, 50, 50, 50, 50, 50, 50;
+            50, 50, 50, 50, 50, 50, 50, 50, 50;
+            50, 50, 90, 50, 50, 50, 10, 50, 50;
+            50, 50, 50, 50, 50, 50, 50, 50, 50;
+            50, 50, 50, 50, 50, 50, 50, 50, 50);
+        let mask = Mask::square(1);
+
+        let top = top_hat(&image, &mask);
+        let black = black_hat(&image, &mask);
+        for (x, y, p) in top.enumerate_pixels() {
+            assert_eq!(p[0], if (x, y) == (2, 2) { 40 } else { 0 });
+        }
+        for (x, y, p) in black.enumerate_pixels() {
+            assert_eq!(p[0], if (x, y) == (6, 2) { 40 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_morphological_gradients_sum() {
+        let image = gray_bench_image(30, 20);
+        for mask in &[Mask::square(1), Mask::diamond(2), Mask::disk(3)] {
+            let gradient = morphological_gradient(&image, mask);
+            let internal = internal_gradient(&image, mask);
+            let external = external_gradient(&image, mask);
+            for ((g, i), e) in gradient.iter().zip(internal.iter()).zip(external.iter()) {
+                assert_eq!(*g as u16, *i as u16 + *e as u16);
+            }
+        }
+    }
+
+    #[test]
+    fn test_morphological_gradient_constant_image() {
+        let image = GrayImage::from_pixel(6, 5, Luma([120]));
+        let gradient = morphological_gradient(&image, &Mask::disk(2));
+        assert!(gradient.iter().all(|p| *p == 0));
+    }
+
+    fn bar() -> GrayImage {
+        GrayImage::from_fn(14, 6, |x, y| {
+            if x >= 1 && x <= 12 && y >= 1 && y <= 4 { Luma([255]) } else { Luma([0]) }
+        })
+    }
+
+    #[test]
+    fn test_thin_bar_to_line() {
+        for &method in &[ThinningMethod::ZhangSuen, ThinningMethod::GuoHall] {
+            let thinned = thin_with_method(&bar(), method);
+            let columns: Vec<u32> = thinned.enumerate_pixels()
+                .filter(|&(_, _, p)| p[0] == 255)
+                .map(|(x, y, _)| { assert_eq!(y, 2); x })
+                .collect();
+            assert!(columns.len() >= 8, "{:?}", columns);
+            assert!(columns.windows(2).all(|w| w[1] == w[0] + 1), "{:?}", columns);
+        }
+    }
+
+    #[test]
+    fn test_thin_preserves_thin_lines() {
+        let line = GrayImage::from_fn(6, 6, |x, y| if x == y { Luma([255]) } else { Luma([0]) });
+        for &method in &[ThinningMethod::ZhangSuen, ThinningMethod::GuoHall] {
+            assert_pixels_eq!(thin_with_method(&line, method), line);
+        }
+    }
+
+    #[test]
+    fn test_thin_preserves_topology() {
+        use region_labelling::{connected_components, Connectivity};
+        let ring = GrayImage::from_fn(15, 15, |x, y| {
+            let d = max((x as i32 - 7).abs(), (y as i32 - 7).abs());
+            if d >= 2 && d <= 4 { Luma([255]) } else { Luma([0]) }
+        });
+        for &method in &[ThinningMethod::ZhangSuen, ThinningMethod::GuoHall] {
+            let thinned = thin_with_method(&ring, method);
+            let count = |image: &GrayImage, conn, background| {
+                let labels = connected_components(image, conn, Luma([background]));
+                labels.pixels().map(|p| p[0]).max().unwrap()
+            };
+            // One foreground component, and the hole inside the ring remains.
+            assert_eq!(count(&thinned, Connectivity::Eight, 0), 1);
+            assert_eq!(count(&thinned, Connectivity::Four, 255), 2);
+            assert!(thinned.pixels().filter(|p| p[0] == 255).count() < 40);
+        }
+    }
+
+    #[test]
+    fn test_thin_is_idempotent_subset() {
+        use bench_utils::binary_image;
+        let image = dilate(&binary_image(40, 30, 0.1, 3), Norm::LInf, 1);
+        for &method in &[ThinningMethod::ZhangSuen, ThinningMethod::GuoHall] {
+            let thinned = thin_with_method(&image, method);
+            for (t, i) in thinned.iter().zip(image.iter()) {
+                assert!(*t == 0 || *i != 0);
+            }
+            assert_pixels_eq!(thin_with_method(&thinned, method), thinned);
+        }
+    }
+
+    #[test]
+    fn test_hit_or_miss_matches_brute_force() {
+        use bench_utils::binary_image;
+        let image = binary_image(20, 15, 0.5, 1);
+        let foreground = Mask::from_image(&gray_image!(255, 0; 0, 255), 0, 0);
+        let background = Mask::from_image(&gray_image!(0, 255; 0, 0), 0, 0);
+        let result = hit_or_miss(&image, &foreground, &background);
+        let at = |x: i32, y: i32| x >= 0 && y >= 0 && x < 20 && y < 15 && image.get_pixel(x as u32, y as u32)[0] != 0;
+        for (x, y, p) in result.enumerate_pixels() {
+            let (x, y) = (x as i32, y as i32);
+            let expected = at(x, y) && at(x + 1, y + 1) && !at(x + 1, y);
+            assert_eq!(p[0], if expected { 255 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_hit_or_miss_overlapping_masks() {
+        let image = GrayImage::from_pixel(4, 4, Luma([255]));
+        let result = hit_or_miss(&image, &Mask::square(1), &Mask::diamond(0));
+        assert!(result.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_hit_or_miss_finds_skeleton_end_points() {
+        // A skeleton pixel with exactly one neighbour, in each of the eight directions.
+        let directions = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+        let masks: Vec<(Mask, Mask)> = directions.iter().map(|&d| {
+            let foreground = Mask { offsets: vec![(0, 0), d] };
+            let background = Mask::square(1).offsets().iter()
+                .cloned()
+                .filter(|&o| o != (0, 0) && o != d)
+                .collect();
+            (foreground, Mask { offsets: background })
+        }).collect();
+
+        let skeleton = thin(&bar());
+        let mut end_points = vec![];
+        for &(ref foreground, ref background) in &masks {
+            let hits = hit_or_miss(&skeleton, foreground, background);
+            end_points.extend(hits.enumerate_pixels().filter(|&(_, _, p)| p[0] == 255).map(|(x, y, _)| (x, y)));
+        }
+        end_points.sort();
+        assert_eq!(end_points, vec![(3, 2), (10, 2)]);
+    }
+
+    // Applies f to marker until the result stops changing.
+    fn iterate_to_stability<F>(marker: &GrayImage, f: F) -> GrayImage
+    where
+        F: Fn(&GrayImage) -> GrayImage,
+    {
+        let mut current = marker.clone();
+        loop {
+            let next = f(&current);
+            if next.iter().eq(current.iter()) {
+                return next;
+            }
+            current = next;
+        }
+    }
+
+    #[test]
+    fn test_reconstruction_matches_iterated_geodesic_operations() {
+        use utils::random_image;
+        use region_labelling::Connectivity;
+        for &connectivity in &[Connectivity::Four, Connectivity::Eight] {
+            for seed in 0..3 {
+                let mask: GrayImage = random_image(17, 13, seed);
+                let mut point = GrayImage::new(17, 13);
+                point.put_pixel(8, 6, Luma([255]));
+                for marker in &[random_image(17, 13, seed + 10), point] {
+                    let dilated = iterate_to_stability(&pointwise(marker, &mask, min), |m| {
+                        geodesic_dilate(m, &mask, connectivity)
+                    });
+                    assert_pixels_eq!(reconstruct_by_dilation(marker, &mask, connectivity), dilated);
+
+                    let eroded = iterate_to_stability(&pointwise(marker, &mask, max), |m| {
+                        geodesic_erode(m, &mask, connectivity)
+                    });
+                    assert_pixels_eq!(reconstruct_by_erosion(marker, &mask, connectivity), eroded);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_h_minima_fills_shallow_basins() {
+        use region_labelling::Connectivity;
+        // A basin of depth 7 on the left, and one of depth 3 on the right.
+        let image = gray_image!(
+            9, 9, 9, 9, 9, 9, 9;
+            9, 2, 9, 9, 7, 7, 9;
+            9, 9, 9, 9, 7, 6, 9;
+            9, 9, 9, 9, 9, 9, 9);
+
+        let filled = gray_image!(
+            9, 9, 9, 9, 9, 9, 9;
+            9, 5, 9, 9, 9, 9, 9;
+            9, 9, 9, 9, 9, 9, 9;
+            9, 9, 9, 9, 9, 9, 9);
+
+        let minima = gray_image!(
+            0,   0, 0, 0, 0,   0, 0;
+            0, 255, 0, 0, 0,   0, 0;
+            0,   0, 0, 0, 0, 255, 0;
+            0,   0, 0, 0, 0,   0, 0);
+
+        for &connectivity in &[Connectivity::Four, Connectivity::Eight] {
+            assert_pixels_eq!(regional_minima(&image, connectivity), minima);
+            assert_pixels_eq!(h_minima(&image, 3, connectivity), filled);
+        }
+    }
+
+    #[test]
+    fn test_regional_maxima() {
+        use region_labelling::Connectivity;
+        let image = gray_image!(
+            5, 5, 1, 4;
+            1, 1, 1, 4;
+            3, 1, 2, 1);
+
+        // The 2 in the bottom row is only a maximum when diagonal neighbours are ignored.
+        assert_pixels_eq!(
+            regional_maxima(&image, Connectivity::Four),
+            gray_image!(
+                255, 255, 0, 255;
+                  0,   0, 0, 255;
+                255,   0, 255, 0));
+        assert_pixels_eq!(
+            regional_maxima(&image, Connectivity::Eight),
+            gray_image!(
+                255, 255, 0, 255;
+                  0,   0, 0, 255;
+                255,   0, 0,   0));
+
+        let constant = GrayImage::from_pixel(3, 2, Luma([7]));
+        let all = GrayImage::from_pixel(3, 2, Luma([255]));
+        assert_pixels_eq!(regional_maxima(&constant, Connectivity::Four), all);
+        assert_pixels_eq!(regional_minima(&constant, Connectivity::Eight), all);
+    }
+
+    #[bench]
+    fn bench_thin(b: &mut Bencher) {
+        let image = square();
+        b.iter(|| {
+            let thinned = thin(&image);
+            black_box(thinned);
+        })
+    }
+
+    #[bench]
+    fn bench_grayscale_dilate_disk_3(b: &mut Bencher) {
+        let image = square();
+        let mask = Mask::disk(3);
+        b.iter(|| {
+            let dilated = grayscale_dilate(&image, &mask);
+            black_box(dilated);
+        })
+    }
+
     #[bench]
     fn bench_dilate_l1_5(b: &mut Bencher) {
         let image = square();
@@ -483,4 +1667,15 @@ mod test {
             black_box(dilated);
         })
     }
+
+    #[bench]
+    fn bench_h_maxima(b: &mut Bencher) {
+        use region_labelling::Connectivity;
+        use utils::gray_bench_image;
+        let image = gray_bench_image(200, 200);
+        b.iter(|| {
+            let suppressed = h_maxima(&image, 20, Connectivity::Eight);
+            black_box(suppressed);
+        })
+    }
 }
@@ -0,0 +1,320 @@
+//! Motion detection by differencing consecutive video frames.
+//!
+//! The individual steps - differencing, thresholding, cleanup and blob extraction -
+//! are available separately, and [`detect_motion`](fn.detect_motion.html) chains
+//! them together for the common case of finding moving objects in a grayscale video.
+
+use image::{GrayImage, Luma};
+use distance_transform::Norm;
+use error::{check_dimensions, Result};
+use morphology::{close_mut, open_mut};
+use rect::Rect;
+use region_labelling::{connected_components, Connectivity};
+
+/// Returns the absolute difference between two images at each pixel.
+///
+/// Returns `ImageProcError::DimensionMismatch` if `first` and `second` do not have
+/// the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::motion::absolute_difference;
+///
+/// let first = gray_image!(
+///     10, 80;
+///     50, 90);
+///
+/// let second = gray_image!(
+///     20, 80;
+///     40, 0);
+///
+/// let difference = gray_image!(
+///     10, 0;
+///     10, 90);
+///
+/// assert_pixels_eq!(absolute_difference(&first, &second).unwrap(), difference);
+/// # }
+/// ```
+pub fn absolute_difference(first: &GrayImage, second: &GrayImage) -> Result<GrayImage> {
+    check_dimensions(first.dimensions(), second.dimensions())?;
+    let mut out = first.clone();
+    for (o, s) in out.iter_mut().zip(second.iter()) {
+        *o = if *o > *s { *o - *s } else { *s - *o };
+    }
+    Ok(out)
+}
+
+/// Returns a mask of the pixels whose intensity changes by more than `threshold`
+/// between `previous` and `current`. Changed pixels are set to 255 and all others to 0.
+///
+/// A moving object produces changes both where it is in `current` and where
+/// it was in `previous`. Use [`three_frame_difference`](fn.three_frame_difference.html)
+/// to find only the object's current location.
+///
+/// Returns `ImageProcError::DimensionMismatch` if `previous` and `current` do not have
+/// the same dimensions.
+pub fn two_frame_difference(previous: &GrayImage, current: &GrayImage, threshold: u8) -> Result<GrayImage> {
+    let mut out = absolute_difference(previous, current)?;
+    for p in out.iter_mut() {
+        *p = if *p > threshold { 255 } else { 0 };
+    }
+    Ok(out)
+}
+
+/// Returns a mask of the pixels in `current` that differ by more than `threshold` from
+/// both `previous` and `next`. Changed pixels are set to 255 and all others to 0.
+///
+/// Unlike [`two_frame_difference`](fn.two_frame_difference.html), this does not
+/// mark the regions that a moving object has just uncovered or is about to cover.
+///
+/// Returns `ImageProcError::DimensionMismatch` if the three frames do not all have
+/// the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::motion::three_frame_difference;
+///
+/// // A bright pixel moving one step right in each frame.
+/// let previous = gray_image!(
+///     200, 0, 0, 0);
+/// let current = gray_image!(
+///     0, 200, 0, 0);
+/// let next = gray_image!(
+///     0, 0, 200, 0);
+///
+/// assert_pixels_eq!(
+///     three_frame_difference(&previous, &current, &next, 50).unwrap(),
+///     gray_image!(0, 255, 0, 0));
+/// # }
+/// ```
+pub fn three_frame_difference(
+    previous: &GrayImage,
+    current: &GrayImage,
+    next: &GrayImage,
+    threshold: u8,
+) -> Result<GrayImage> {
+    let mut out = two_frame_difference(previous, current, threshold)?;
+    let forward = two_frame_difference(current, next, threshold)?;
+    for (o, f) in out.iter_mut().zip(forward.iter()) {
+        *o = (*o).min(*f);
+    }
+    Ok(out)
+}
+
+/// Returns the bounding boxes of the connected foreground regions of `mask`
+/// which contain at least `min_area` pixels. Pixels with non-zero intensity
+/// are treated as foreground.
+///
+/// Boxes are returned in the order in which their regions are first encountered
+/// when scanning the mask in row-major order.
+pub fn blob_bounding_boxes(mask: &GrayImage, connectivity: Connectivity, min_area: u32) -> Vec<Rect> {
+    let binary = GrayImage::from_fn(mask.width(), mask.height(), |x, y| {
+        Luma([if mask.get_pixel(x, y)[0] > 0 { 255 } else { 0 }])
+    });
+    let labels = connected_components(&binary, connectivity, Luma([0u8]));
+
+    // (left, top, right, bottom, area) for each label, with label 0 unused.
+    let mut bounds: Vec<(u32, u32, u32, u32, u32)> = vec![];
+    for (x, y, p) in labels.enumerate_pixels() {
+        let label = p[0] as usize;
+        if label == 0 {
+            continue;
+        }
+        if label >= bounds.len() {
+            bounds.resize(label + 1, (u32::max_value(), u32::max_value(), 0, 0, 0));
+        }
+        let b = &mut bounds[label];
+        b.0 = b.0.min(x);
+        b.1 = b.1.min(y);
+        b.2 = b.2.max(x);
+        b.3 = b.3.max(y);
+        b.4 += 1;
+    }
+
+    bounds
+        .into_iter()
+        .filter(|b| b.4 > 0 && b.4 >= min_area)
+        .map(|(left, top, right, bottom, _)| {
+            Rect::at(left as i32, top as i32).of_size(right - left + 1, bottom - top + 1)
+        })
+        .collect()
+}
+
+/// Options for [`detect_motion`](fn.detect_motion.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MotionDetectionOptions {
+    /// Pixels whose intensity changes by more than this are treated as moving.
+    pub threshold: u8,
+    /// Radius of the morphological opening used to remove isolated changed pixels
+    /// caused by noise. No opening is performed if this is 0.
+    pub opening_radius: u8,
+    /// Radius of the morphological closing used to merge nearby fragments of
+    /// the same moving object. No closing is performed if this is 0.
+    pub closing_radius: u8,
+    /// Connectivity used when grouping moving pixels into objects.
+    pub connectivity: Connectivity,
+    /// Moving regions containing fewer pixels than this are discarded.
+    pub min_area: u32,
+}
+
+impl Default for MotionDetectionOptions {
+    fn default() -> Self {
+        MotionDetectionOptions {
+            threshold: 25,
+            opening_radius: 1,
+            closing_radius: 2,
+            connectivity: Connectivity::Eight,
+            min_area: 20,
+        }
+    }
+}
+
+/// Returns bounding boxes of the objects moving in `current`, using three-frame differencing.
+///
+/// This computes [`three_frame_difference`](fn.three_frame_difference.html), cleans up the
+/// resulting mask with a morphological opening followed by a closing, and returns the
+/// [`blob_bounding_boxes`](fn.blob_bounding_boxes.html) of the result.
+///
+/// Returns `ImageProcError::DimensionMismatch` if the three frames do not all have
+/// the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::draw_filled_rect_mut;
+/// use imageproc::motion::{detect_motion, MotionDetectionOptions};
+/// use imageproc::rect::Rect;
+///
+/// // A 10x10 square moving 12 pixels right in each frame.
+/// let frame = |x| {
+///     let mut image = GrayImage::new(60, 30);
+///     draw_filled_rect_mut(&mut image, Rect::at(x, 10).of_size(10, 10), Luma([255u8]));
+///     image
+/// };
+///
+/// let moving = detect_motion(&frame(5), &frame(17), &frame(29), MotionDetectionOptions::default()).unwrap();
+/// assert_eq!(moving, vec![Rect::at(17, 10).of_size(10, 10)]);
+/// # }
+/// ```
+pub fn detect_motion(
+    previous: &GrayImage,
+    current: &GrayImage,
+    next: &GrayImage,
+    options: MotionDetectionOptions,
+) -> Result<Vec<Rect>> {
+    let mut mask = three_frame_difference(previous, current, next, options.threshold)?;
+    if options.opening_radius > 0 {
+        open_mut(&mut mask, Norm::LInf, options.opening_radius);
+    }
+    if options.closing_radius > 0 {
+        close_mut(&mut mask, Norm::LInf, options.closing_radius);
+    }
+    Ok(blob_bounding_boxes(&mask, options.connectivity, options.min_area))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use drawing::draw_filled_rect_mut;
+    use error::ImageProcError;
+    use noise::gaussian_noise;
+    use test::{Bencher, black_box};
+
+    fn frame_with_squares(squares: &[(i32, i32)]) -> GrayImage {
+        let mut image = GrayImage::from_pixel(80, 60, Luma([50u8]));
+        for &(x, y) in squares {
+            draw_filled_rect_mut(&mut image, Rect::at(x, y).of_size(8, 8), Luma([200u8]));
+        }
+        image
+    }
+
+    #[test]
+    fn test_two_frame_difference() {
+        let previous = gray_image!(
+            10, 80, 20;
+            50, 90, 70);
+
+        let current = gray_image!(
+            15, 10, 20;
+            50, 90, 0);
+
+        let expected = gray_image!(
+            0, 255, 0;
+            0,   0, 255);
+
+        assert_pixels_eq!(two_frame_difference(&previous, &current, 10).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_mismatched_frames_are_rejected() {
+        let (small, large) = (GrayImage::new(2, 2), GrayImage::new(2, 3));
+        let mismatch = ImageProcError::DimensionMismatch { expected: (2, 2), actual: (2, 3) };
+        assert_eq!(absolute_difference(&small, &large).unwrap_err(), mismatch);
+        assert_eq!(three_frame_difference(&small, &small, &large, 10).unwrap_err(), mismatch);
+        assert_eq!(
+            detect_motion(&large, &small, &small, MotionDetectionOptions::default()),
+            Err(ImageProcError::DimensionMismatch { expected: (2, 3), actual: (2, 2) }));
+    }
+
+    #[test]
+    fn test_blob_bounding_boxes() {
+        let mask = gray_image!(
+            255, 255,   0,   0,   0;
+              0, 255,   0,   0, 255;
+              0,   0,   0,   0,   0;
+              0,   0, 255, 255,   0);
+
+        assert_eq!(
+            blob_bounding_boxes(&mask, Connectivity::Four, 1),
+            vec![
+                Rect::at(0, 0).of_size(2, 2),
+                Rect::at(4, 1).of_size(1, 1),
+                Rect::at(2, 3).of_size(2, 1),
+            ]);
+
+        assert_eq!(
+            blob_bounding_boxes(&mask, Connectivity::Four, 2),
+            vec![Rect::at(0, 0).of_size(2, 2), Rect::at(2, 3).of_size(2, 1)]);
+    }
+
+    #[test]
+    fn test_blob_bounding_boxes_empty_mask() {
+        assert_eq!(blob_bounding_boxes(&GrayImage::new(5, 5), Connectivity::Eight, 1), vec![]);
+    }
+
+    #[test]
+    fn test_detect_motion_ignores_static_objects_and_noise() {
+        // One square is static and one moves diagonally.
+        let previous = gaussian_noise(&frame_with_squares(&[(5, 5), (30, 20)]), 0.0, 3.0, 1);
+        let current = gaussian_noise(&frame_with_squares(&[(5, 5), (40, 30)]), 0.0, 3.0, 2);
+        let next = gaussian_noise(&frame_with_squares(&[(5, 5), (50, 40)]), 0.0, 3.0, 3);
+
+        let moving = detect_motion(&previous, &current, &next, MotionDetectionOptions::default()).unwrap();
+        assert_eq!(moving, vec![Rect::at(40, 30).of_size(8, 8)]);
+    }
+
+    #[test]
+    fn test_detect_motion_no_motion() {
+        let frame = frame_with_squares(&[(10, 10)]);
+        assert_eq!(detect_motion(&frame, &frame, &frame, MotionDetectionOptions::default()), Ok(vec![]));
+    }
+
+    #[bench]
+    fn bench_detect_motion(b: &mut Bencher) {
+        let previous = frame_with_squares(&[(5, 5), (30, 20)]);
+        let current = frame_with_squares(&[(5, 5), (40, 30)]);
+        let next = frame_with_squares(&[(5, 5), (50, 40)]);
+        b.iter(|| black_box(detect_motion(&previous, &current, &next, MotionDetectionOptions::default())));
+    }
+}
@@ -0,0 +1,485 @@
+//! [Maximally stable extremal regions](https://en.wikipedia.org/wiki/Maximally_stable_extremal_regions).
+
+use image::GrayImage;
+use error::{check_non_empty, ImageProcError, Result};
+use geometry::Ellipse;
+use moments::Moments;
+use union_find::DisjointSetForest;
+
+/// Options for [`mser`](fn.mser.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MserOptions {
+    /// Intensity step used to measure the stability of a region. The variation of a region
+    /// at threshold `t` is measured against the region containing it at threshold `t + delta`.
+    pub delta: u8,
+    /// Smallest number of pixels in a detected region.
+    pub min_area: u32,
+    /// Largest number of pixels in a detected region.
+    pub max_area: u32,
+    /// Largest variation of a detected region, i.e. the relative growth in area when the
+    /// threshold is increased by `delta`.
+    pub max_variation: f32,
+    /// A region is discarded if it is nested in a larger detected region whose area is less
+    /// than `1 + min_diversity` times its own, as the two are then near duplicates.
+    /// Zero keeps all nested regions.
+    pub min_diversity: f32,
+}
+
+impl Default for MserOptions {
+    fn default() -> Self {
+        MserOptions {
+            delta: 5,
+            min_area: 60,
+            max_area: 14400,
+            max_variation: 0.25,
+            min_diversity: 0.2,
+        }
+    }
+}
+
+impl MserOptions {
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.delta == 0 {
+            errors.push("delta must be positive".to_owned());
+        }
+        if self.min_area == 0 || self.min_area > self.max_area {
+            errors.push(format!(
+                "min_area must be positive and at most max_area, got min_area {} and max_area {}",
+                self.min_area, self.max_area
+            ));
+        }
+        if self.max_variation < 0.0 || self.max_variation.is_nan() {
+            errors.push(format!("max_variation must be non-negative, got {}", self.max_variation));
+        }
+        if !(self.min_diversity >= 0.0 && self.min_diversity < 1.0) {
+            errors.push(format!("min_diversity must be in the range [0, 1), got {}", self.min_diversity));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ImageProcError::InvalidOptions(errors.join(", ")))
+        }
+    }
+}
+
+/// A maximally stable extremal region.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MserRegion {
+    /// The pixels in the region, ordered by row and then by column.
+    pub pixels: Vec<(u32, u32)>,
+    /// The ellipse with the same first and second order moments as the region.
+    /// See [`Moments::ellipse`](../moments/struct.Moments.html#method.ellipse).
+    pub ellipse: Ellipse,
+    /// True if the region is darker than its boundary, i.e. all its pixels have intensity
+    /// at most `level` and its neighbours have intensity greater than `level`. False if the
+    /// region is brighter than its boundary.
+    pub dark: bool,
+    /// The threshold at which the region is extremal.
+    pub level: u8,
+    /// The variation of the region. Smaller values are more stable.
+    pub variation: f32,
+}
+
+/// Detects [maximally stable extremal regions](https://en.wikipedia.org/wiki/Maximally_stable_extremal_regions)
+/// (MSERs) in an image, as described in Matas et al. "Robust Wide Baseline Stereo from
+/// Maximally Stable Extremal Regions".
+///
+/// An extremal region is a 4-connected component of the pixels with intensity at most
+/// (for dark regions) or at least (for bright regions) some threshold. A region is maximally
+/// stable if its variation, the relative growth in its area when the threshold is relaxed by
+/// `options.delta`, is a local minimum over the thresholds at which it exists.
+///
+/// Dark regions are returned before bright regions, and the regions of each kind are
+/// ordered by increasing `level` for dark regions and decreasing `level` for bright regions.
+/// Regions may be nested within each other, and the region covering the whole image is
+/// never returned.
+///
+/// Returns `ImageProcError::EmptyImage` if the image is empty and
+/// `ImageProcError::InvalidOptions` if the options are invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::mser::{mser, MserOptions};
+///
+/// // A dark 10x6 rectangle on a bright background.
+/// let image = GrayImage::from_fn(30, 20, |x, y| {
+///     if x >= 5 && x < 15 && y >= 7 && y < 13 { Luma([20]) } else { Luma([200]) }
+/// });
+///
+/// let options = MserOptions { min_area: 10, ..Default::default() };
+/// let regions = mser(&image, options).unwrap();
+///
+/// // Dark regions come first. The background is also detected as a bright region.
+/// assert_eq!(regions.len(), 2);
+/// assert!(regions[0].dark);
+/// assert_eq!(regions[0].pixels.len(), 60);
+/// assert_eq!(regions[0].ellipse.center, (9.5, 9.5));
+/// # }
+/// ```
+pub fn mser(image: &GrayImage, options: MserOptions) -> Result<Vec<MserRegion>> {
+    check_non_empty(image.dimensions())?;
+    options.validate()?;
+    let (width, height) = image.dimensions();
+    let pixels: &[u8] = image;
+
+    let mut regions = extremal_regions(pixels, width, height, &options, true);
+    let inverted: Vec<u8> = pixels.iter().map(|p| 255 - p).collect();
+    regions.extend(extremal_regions(&inverted, width, height, &options, false));
+    Ok(regions)
+}
+
+const NONE: usize = ::std::usize::MAX;
+
+/// A node in the component tree, i.e. an extremal region at the smallest threshold
+/// at which it exists.
+struct Node {
+    level: u8,
+    area: u32,
+    parent: usize,
+    children: Vec<usize>,
+}
+
+/// Finds the maximally stable dark regions in an image, reporting them as bright
+/// regions if `dark` is false.
+fn extremal_regions(pixels: &[u8], width: u32, height: u32, options: &MserOptions, dark: bool) -> Vec<MserRegion> {
+    let (w, h) = (width as usize, height as usize);
+    let (nodes, pixel_node) = component_tree(pixels, w, h);
+
+    // Variation of a region at threshold t, measured against the largest ancestor whose
+    // level is at most t + delta.
+    let variation_at = |node: usize, t: u32| {
+        let limit = t + options.delta as u32;
+        let mut top = node;
+        while nodes[top].parent != NONE && nodes[nodes[top].parent].level as u32 <= limit {
+            top = nodes[top].parent;
+        }
+        (nodes[top].area - nodes[node].area) as f32 / nodes[node].area as f32
+    };
+    // A node is the same region for all thresholds from its level to one below the level of
+    // its parent, and its variation increases over this range. We record the smallest and
+    // largest values.
+    let variation: Vec<f32> = (0..nodes.len()).map(|i| variation_at(i, nodes[i].level as u32)).collect();
+    let end_variation: Vec<f32> = (0..nodes.len())
+        .map(|i| match nodes[i].parent {
+            NONE => 0.0,
+            parent => variation_at(i, nodes[parent].level as u32 - 1),
+        })
+        .collect();
+
+    // A region is maximally stable if its variation is a local minimum over thresholds,
+    // i.e. smaller than the variation of each child at the previous threshold and no
+    // larger than the variation at the next threshold. The region covering the whole
+    // image is excluded.
+    let mut stable: Vec<bool> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            node.parent != NONE
+                && node.children.iter().all(|&c| variation[i] < end_variation[c])
+                && (nodes[node.parent].level > node.level + 1 || variation[i] <= variation[node.parent])
+                && node.area >= options.min_area
+                && node.area <= options.max_area
+                && variation[i] <= options.max_variation
+        })
+        .collect();
+
+    // Remove regions that are too similar to a larger stable region containing them.
+    // Ancestors have larger areas, so are processed first.
+    let mut order: Vec<usize> = (0..nodes.len()).filter(|&i| stable[i]).collect();
+    order.sort_by(|&a, &b| nodes[b].area.cmp(&nodes[a].area).then(a.cmp(&b)));
+    for &i in &order {
+        let mut ancestor = nodes[i].parent;
+        while ancestor != NONE && !stable[ancestor] {
+            ancestor = nodes[ancestor].parent;
+        }
+        if ancestor != NONE {
+            let outer = nodes[ancestor].area as f32;
+            if (outer - nodes[i].area as f32) / outer < options.min_diversity {
+                stable[i] = false;
+            }
+        }
+    }
+
+    // Group pixels by the node that created them.
+    let mut node_pixels = vec![vec![]; nodes.len()];
+    for (p, &node) in pixel_node.iter().enumerate() {
+        node_pixels[node].push(p);
+    }
+
+    // Nodes are created in order of increasing level.
+    let mut regions = vec![];
+    for i in (0..nodes.len()).filter(|&i| stable[i]) {
+        let mut indices = vec![];
+        let mut stack = vec![i];
+        while let Some(n) = stack.pop() {
+            indices.extend_from_slice(&node_pixels[n]);
+            stack.extend_from_slice(&nodes[n].children);
+        }
+        indices.sort();
+        let points: Vec<(u32, u32)> = indices.iter().map(|&p| ((p % w) as u32, (p / w) as u32)).collect();
+        let ellipse = Moments::of_points(&points).ellipse().unwrap();
+        regions.push(MserRegion {
+            pixels: points,
+            ellipse,
+            dark,
+            level: if dark { nodes[i].level } else { 255 - nodes[i].level },
+            variation: variation[i],
+        });
+    }
+    regions
+}
+
+/// Builds the tree of 4-connected components of the sets of pixels with intensity at most
+/// each threshold, adding pixels in order of increasing intensity. A node is created for
+/// each component whose pixels change at a threshold. Returns the nodes, in order of
+/// increasing level, and the node created when each pixel was added.
+fn component_tree(pixels: &[u8], w: usize, h: usize) -> (Vec<Node>, Vec<usize>) {
+    // Counting sort of pixel indices by intensity.
+    let mut starts = [0usize; 257];
+    for &p in pixels {
+        starts[p as usize + 1] += 1;
+    }
+    for i in 1..257 {
+        starts[i] += starts[i - 1];
+    }
+    let mut sorted = vec![0; pixels.len()];
+    {
+        let mut next = starts;
+        for (i, &p) in pixels.iter().enumerate() {
+            sorted[next[p as usize]] = i;
+            next[p as usize] += 1;
+        }
+    }
+
+    let mut forest = DisjointSetForest::<usize>::new(pixels.len());
+    let mut area = vec![1u32; pixels.len()];
+    // The most recently created node of each component, indexed by its root.
+    let mut root_node = vec![NONE; pixels.len()];
+    let mut pixel_node = vec![NONE; pixels.len()];
+    let mut nodes: Vec<Node> = vec![];
+    let mut children = vec![];
+
+    let neighbours = |p: usize| {
+        let (x, y) = (p % w, p / w);
+        let mut n = [NONE; 4];
+        if x > 0 {
+            n[0] = p - 1;
+        }
+        if x + 1 < w {
+            n[1] = p + 1;
+        }
+        if y > 0 {
+            n[2] = p - w;
+        }
+        if y + 1 < h {
+            n[3] = p + w;
+        }
+        n
+    };
+
+    for level in 0..256 {
+        let current = &sorted[starts[level]..starts[level + 1]];
+        if current.is_empty() {
+            continue;
+        }
+        let level = level as u8;
+
+        // Components from lower levels that are merged into components at this level.
+        children.clear();
+        for &p in current {
+            for &q in neighbours(p).iter() {
+                if q != NONE && pixels[q] < level {
+                    let root = forest.root(q);
+                    children.push((p, root_node[root]));
+                }
+            }
+        }
+
+        for &p in current {
+            for &q in neighbours(p).iter() {
+                if q != NONE && pixels[q] <= level {
+                    let (rp, rq) = (forest.root(p), forest.root(q));
+                    if forest.union_sets(rp, rq) {
+                        let merged = forest.root(rp);
+                        area[merged] = area[rp] + area[rq];
+                    }
+                }
+            }
+        }
+
+        for &p in current {
+            let root = forest.root(p);
+            let existing = root_node[root];
+            if existing == NONE || nodes[existing].level != level {
+                root_node[root] = nodes.len();
+                nodes.push(Node {
+                    level,
+                    area: area[root],
+                    parent: NONE,
+                    children: vec![],
+                });
+            }
+            pixel_node[p] = root_node[root];
+        }
+
+        for &(p, child) in &children {
+            if nodes[child].parent == NONE {
+                let parent = pixel_node[p];
+                nodes[child].parent = parent;
+                nodes[parent].children.push(child);
+            }
+        }
+    }
+
+    (nodes, pixel_node)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Luma;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn options(min_area: u32) -> MserOptions {
+        MserOptions {
+            min_area,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mser_constant_image() {
+        let image = GrayImage::from_pixel(20, 20, Luma([100]));
+        assert!(mser(&image, options(1)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mser_dark_and_bright_squares() {
+        let image = GrayImage::from_fn(40, 20, |x, y| {
+            if y >= 5 && y < 15 && x >= 3 && x < 13 {
+                Luma([10])
+            } else if y >= 6 && y < 14 && x >= 25 && x < 33 {
+                Luma([240])
+            } else {
+                Luma([128])
+            }
+        });
+        let regions = mser(&image, options(10)).unwrap();
+        let sizes: Vec<(bool, usize)> = regions.iter().map(|r| (r.dark, r.pixels.len())).collect();
+        // Each square, and the background together with the other square.
+        assert_eq!(sizes, vec![(true, 100), (true, 736), (false, 64), (false, 700)]);
+
+        let dark = &regions[0];
+        assert_eq!(dark.pixels[0], (3, 5));
+        assert_eq!(dark.ellipse.center, (7.5, 9.5));
+        assert_eq!(dark.variation, 0.0);
+        assert_eq!(dark.level, 10);
+
+        let bright = &regions[2];
+        assert_eq!(bright.ellipse.center, (28.5, 9.5));
+        assert_eq!(bright.level, 240);
+    }
+
+    #[test]
+    fn test_mser_area_limits() {
+        let image = GrayImage::from_fn(30, 30, |x, y| {
+            if x >= 10 && x < 20 && y >= 10 && y < 20 { Luma([0]) } else { Luma([255]) }
+        });
+        // The bright background has 800 pixels, so is excluded by each maximum area.
+        let limits = |min_area, max_area| MserOptions {
+            min_area,
+            max_area,
+            ..Default::default()
+        };
+        assert_eq!(mser(&image, limits(100, 100)).unwrap().len(), 1);
+        assert!(mser(&image, limits(101, 799)).unwrap().is_empty());
+        assert!(mser(&image, limits(10, 99)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mser_nested_regions_and_diversity() {
+        // A dark square inside a larger mid-gray square, both well separated in intensity.
+        let image = GrayImage::from_fn(40, 40, |x, y| {
+            if x >= 15 && x < 25 && y >= 15 && y < 25 {
+                Luma([0])
+            } else if x >= 5 && x < 35 && y >= 5 && y < 35 {
+                Luma([100])
+            } else {
+                Luma([250])
+            }
+        });
+        let dark: Vec<usize> = mser(&image, options(10))
+            .unwrap()
+            .iter()
+            .filter(|r| r.dark)
+            .map(|r| r.pixels.len())
+            .collect();
+        assert_eq!(dark, vec![100, 900]);
+
+        // The inner square has a ninth of the area of the outer square, so is removed
+        // when the minimum diversity is above 8 / 9.
+        let strict = MserOptions {
+            min_area: 10,
+            min_diversity: 0.95,
+            ..Default::default()
+        };
+        let dark: Vec<usize> = mser(&image, strict)
+            .unwrap()
+            .iter()
+            .filter(|r| r.dark)
+            .map(|r| r.pixels.len())
+            .collect();
+        assert_eq!(dark, vec![900]);
+    }
+
+    #[test]
+    fn test_mser_regions_are_extremal() {
+        let image = gray_bench_image(60, 60);
+        let regions = mser(&image, options(5)).unwrap();
+        for region in &regions {
+            let inside = |x: u32, y: u32| region.pixels.binary_search_by(|&(px, py)| (py, px).cmp(&(y, x))).is_ok();
+            for &(x, y) in &region.pixels {
+                let p = image.get_pixel(x, y)[0];
+                assert!(if region.dark { p <= region.level } else { p >= region.level });
+                let (x, y) = (x as i64, y as i64);
+                for &(nx, ny) in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if nx < 0 || ny < 0 || nx >= 60 || ny >= 60 || inside(nx as u32, ny as u32) {
+                        continue;
+                    }
+                    let q = image.get_pixel(nx as u32, ny as u32)[0];
+                    assert!(if region.dark { q > region.level } else { q < region.level });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mser_invalid_inputs() {
+        let image = gray_bench_image(10, 10);
+        assert_eq!(mser(&GrayImage::new(0, 5), MserOptions::default()), Err(ImageProcError::EmptyImage));
+        for options in &[
+            MserOptions { delta: 0, ..Default::default() },
+            MserOptions { min_area: 0, ..Default::default() },
+            MserOptions { min_area: 10, max_area: 5, ..Default::default() },
+            MserOptions { max_variation: -1.0, ..Default::default() },
+            MserOptions { min_diversity: 1.0, ..Default::default() },
+        ] {
+            assert!(mser(&image, *options).is_err());
+        }
+    }
+
+    #[bench]
+    fn bench_mser(b: &mut Bencher) {
+        let image = gray_bench_image(200, 200);
+        b.iter(|| {
+            let regions = mser(&image, MserOptions::default()).unwrap();
+            black_box(regions);
+        });
+    }
+}
@@ -0,0 +1,430 @@
+//! Owned 3d arrays and strided views into them, for stacks of feature channels.
+//!
+//! Dimensions are ordered from innermost to outermost. Arrays built from images
+//! use the same layout as an `ImageBuffer`: dimension 0 is the channel, dimension 1
+//! is the x-coordinate and dimension 2 is the y-coordinate, so that the channel
+//! values at each location are stored contiguously.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # #[macro_use]
+//! # extern crate imageproc;
+//! # fn main() {
+//! use imageproc::multiarray::Array3d;
+//!
+//! let red = gray_image!(1, 2; 3, 4);
+//! let green = gray_image!(5, 6; 7, 8);
+//! let mut array = Array3d::from_channels(&[red, green]).unwrap();
+//! assert_eq!(array.lengths(), [2, 2, 2]);
+//! assert_eq!(array.inner_slice(1, 0), &[2, 6]);
+//!
+//! // A view of the second column only.
+//! let mut view = array.view_mut();
+//! let column = view.slice_axis(1, 1..2);
+//! assert_eq!(column.lengths(), [2, 1, 2]);
+//! assert_eq!(*column.get([0, 0, 1]), 4);
+//!
+//! let doubled = array.map(|v| 2 * v);
+//! let channels = doubled.to_channels();
+//! assert_pixels_eq!(channels[1], gray_image!(10, 12; 14, 16));
+//! # }
+//! ```
+
+use image::{GenericImageView, ImageBuffer, Luma, Primitive};
+use definitions::Image;
+use error::{check_dimensions, Result};
+use num::Zero;
+use std::ops::Range;
+
+/// A 3d array that owns its data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Array3d<T> {
+    /// The owned data.
+    data: Vec<T>,
+    /// Lengths of the dimensions, from innermost (i.e. fastest-varying) to outermost.
+    lengths: [usize; 3],
+}
+
+/// A mutable view into a 3d array, whose entries need not be contiguous.
+#[derive(Debug)]
+pub struct View3d<'a, T: 'a> {
+    /// The underlying data, starting at the entry with index `[0, 0, 0]`.
+    data: &'a mut [T],
+    /// Lengths of the dimensions, from innermost (i.e. fastest-varying) to outermost.
+    lengths: [usize; 3],
+    /// Distance in `data` between entries adjacent along each dimension.
+    strides: [usize; 3],
+}
+
+impl<T: Zero + Clone> Array3d<T> {
+    /// Allocates a new Array3d with the given dimensions.
+    pub fn new(lengths: [usize; 3]) -> Array3d<T> {
+        let data = vec![Zero::zero(); data_length(lengths)];
+        Array3d {
+            data,
+            lengths,
+        }
+    }
+}
+
+impl<T> Array3d<T> {
+    /// Constructs an array from its data, in order of increasing offset.
+    ///
+    /// # Panics
+    /// If `data.len()` is not equal to the product of `lengths`.
+    pub fn from_vec(data: Vec<T>, lengths: [usize; 3]) -> Array3d<T> {
+        assert_eq!(data.len(), data_length(lengths), "data length must match dimensions");
+        Array3d { data, lengths }
+    }
+
+    /// Lengths of the dimensions, from innermost to outermost.
+    pub fn lengths(&self) -> [usize; 3] {
+        self.lengths
+    }
+
+    /// The underlying data.
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Consumes the array, returning its data.
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    /// A reference to the entry at the given index.
+    pub fn get(&self, indices: [usize; 3]) -> &T {
+        check_indices(indices, self.lengths);
+        &self.data[offset(indices, contiguous_strides(self.lengths))]
+    }
+
+    /// All entries with the given outer dimensions. As the first dimension
+    /// is fastest varying, this is a contiguous slice.
+    pub fn inner_slice(&self, x1: usize, x2: usize) -> &[T] {
+        check_indices([0, x1, x2], [1, self.lengths[1], self.lengths[2]]);
+        let start = offset([0, x1, x2], contiguous_strides(self.lengths));
+        &self.data[start..start + self.lengths[0]]
+    }
+
+    /// Provides a 3d view of the data.
+    pub fn view_mut(&mut self) -> View3d<'_, T> {
+        View3d::from_raw(&mut self.data, self.lengths)
+    }
+
+    /// Applies `f` to each entry.
+    pub fn map<U, F>(&self, f: F) -> Array3d<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        Array3d {
+            data: self.data.iter().map(f).collect(),
+            lengths: self.lengths,
+        }
+    }
+
+    /// Applies `f` to each pair of corresponding entries of self and other.
+    ///
+    /// # Panics
+    /// If self and other have different dimensions.
+    pub fn zip_map<U, V, F>(&self, other: &Array3d<U>, mut f: F) -> Array3d<V>
+    where
+        F: FnMut(&T, &U) -> V,
+    {
+        assert_eq!(self.lengths, other.lengths, "arrays must have the same dimensions");
+        Array3d {
+            data: self.data.iter().zip(other.data.iter()).map(|(a, b)| f(a, b)).collect(),
+            lengths: self.lengths,
+        }
+    }
+}
+
+impl<T: Primitive + 'static> Array3d<T> {
+    /// Stacks images into an array, with dimension 0 indexing the images. Returns
+    /// `ImageProcError::DimensionMismatch` if the images do not all have the same
+    /// dimensions.
+    pub fn from_channels(channels: &[Image<Luma<T>>]) -> Result<Array3d<T>> {
+        let (width, height) = match channels.first() {
+            Some(c) => c.dimensions(),
+            None => return Ok(Array3d::from_vec(vec![], [0, 0, 0])),
+        };
+        for c in channels {
+            check_dimensions((width, height), c.dimensions())?;
+        }
+        let mut data = Vec::with_capacity(channels.len() * width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                data.extend(channels.iter().map(|c| unsafe { c.unsafe_get_pixel(x, y)[0] }));
+            }
+        }
+        Ok(Array3d::from_vec(data, [channels.len(), width as usize, height as usize]))
+    }
+
+    /// The image of entries with the given index in dimension 0.
+    pub fn channel(&self, c: usize) -> Image<Luma<T>> {
+        assert!(c < self.lengths[0], "channel {} out of bounds", c);
+        let width = self.lengths[1] as u32;
+        let height = self.lengths[2] as u32;
+        ImageBuffer::from_fn(width, height, |x, y| Luma([*self.get([c, x as usize, y as usize])]))
+    }
+
+    /// Splits the array into an image for each index in dimension 0. This is
+    /// the inverse of [`from_channels`](#method.from_channels).
+    pub fn to_channels(&self) -> Vec<Image<Luma<T>>> {
+        (0..self.lengths[0]).map(|c| self.channel(c)).collect()
+    }
+
+    /// Applies `f` to the image for each index in dimension 0, and stacks the results.
+    /// `f` is given the index of each channel along with its image.
+    ///
+    /// # Panics
+    /// If `f` returns images of differing dimensions.
+    pub fn map_channels<U, F>(&self, mut f: F) -> Array3d<U>
+    where
+        U: Primitive + 'static,
+        F: FnMut(usize, &Image<Luma<T>>) -> Image<Luma<U>>,
+    {
+        let mapped: Vec<Image<Luma<U>>> = self.to_channels()
+            .iter()
+            .enumerate()
+            .map(|(c, image)| f(c, image))
+            .collect();
+        Array3d::from_channels(&mapped).expect("mapped channels must have equal dimensions")
+    }
+}
+
+impl<'a, T> View3d<'a, T> {
+    /// Constructs a view of contiguous data, given the lengths of its dimensions.
+    ///
+    /// # Panics
+    /// If `data.len()` is less than the product of `lengths`.
+    pub fn from_raw(data: &'a mut [T], lengths: [usize; 3]) -> View3d<'a, T> {
+        assert!(data.len() >= data_length(lengths), "data too short for dimensions");
+        View3d {
+            data,
+            lengths,
+            strides: contiguous_strides(lengths),
+        }
+    }
+
+    /// Lengths of the dimensions, from innermost to outermost.
+    pub fn lengths(&self) -> [usize; 3] {
+        self.lengths
+    }
+
+    /// Distance in the underlying data between entries adjacent along each dimension.
+    pub fn strides(&self) -> [usize; 3] {
+        self.strides
+    }
+
+    /// A reference to the entry at the given index.
+    pub fn get(&self, indices: [usize; 3]) -> &T {
+        check_indices(indices, self.lengths);
+        &self.data[offset(indices, self.strides)]
+    }
+
+    /// A mutable reference from a 3d index.
+    pub fn at_mut(&mut self, indices: [usize; 3]) -> &mut T {
+        check_indices(indices, self.lengths);
+        &mut self.data[offset(indices, self.strides)]
+    }
+
+    /// All entries with the given outer dimensions, which must be contiguous.
+    ///
+    /// # Panics
+    /// If the innermost dimension of this view does not have stride 1.
+    pub fn inner_slice(&self, x1: usize, x2: usize) -> &[T] {
+        let start = self.inner_slice_start(x1, x2);
+        &self.data[start..start + self.lengths[0]]
+    }
+
+    /// All entries with the given outer dimensions, which must be contiguous.
+    ///
+    /// # Panics
+    /// If the innermost dimension of this view does not have stride 1.
+    pub fn inner_slice_mut(&mut self, x1: usize, x2: usize) -> &mut [T] {
+        let start = self.inner_slice_start(x1, x2);
+        &mut self.data[start..start + self.lengths[0]]
+    }
+
+    /// A view of the entries whose index along `axis` lies in `range`.
+    ///
+    /// # Panics
+    /// If `axis > 2` or `range` is not contained in `0..self.lengths()[axis]`.
+    pub fn slice_axis(&mut self, axis: usize, range: Range<usize>) -> View3d<'_, T> {
+        assert!(axis < 3, "axis must be 0, 1 or 2");
+        assert!(
+            range.start <= range.end && range.end <= self.lengths[axis],
+            "range {:?} out of bounds for axis of length {}", range, self.lengths[axis]
+        );
+        let mut lengths = self.lengths;
+        lengths[axis] = range.end - range.start;
+        let start = if data_length(lengths) == 0 { 0 } else { range.start * self.strides[axis] };
+        View3d {
+            data: &mut self.data[start..],
+            lengths,
+            strides: self.strides,
+        }
+    }
+
+    /// Swaps two dimensions of the view. This doesn't move any data.
+    pub fn swap_axes(mut self, a: usize, b: usize) -> View3d<'a, T> {
+        self.lengths.swap(a, b);
+        self.strides.swap(a, b);
+        self
+    }
+
+    /// Applies `f` to each entry of the view in place.
+    pub fn apply<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        for x2 in 0..self.lengths[2] {
+            for x1 in 0..self.lengths[1] {
+                for x0 in 0..self.lengths[0] {
+                    f(&mut self.data[offset([x0, x1, x2], self.strides)]);
+                }
+            }
+        }
+    }
+
+    /// Copies the entries of the view into a new, contiguous array.
+    pub fn to_array(&self) -> Array3d<T>
+    where
+        T: Clone,
+    {
+        let mut data = Vec::with_capacity(data_length(self.lengths));
+        for x2 in 0..self.lengths[2] {
+            for x1 in 0..self.lengths[1] {
+                for x0 in 0..self.lengths[0] {
+                    data.push(self.data[offset([x0, x1, x2], self.strides)].clone());
+                }
+            }
+        }
+        Array3d::from_vec(data, self.lengths)
+    }
+
+    fn inner_slice_start(&self, x1: usize, x2: usize) -> usize {
+        assert!(
+            self.strides[0] == 1 || self.lengths[0] <= 1,
+            "inner dimension of view is not contiguous"
+        );
+        check_indices([0, x1, x2], [1, self.lengths[1], self.lengths[2]]);
+        offset([0, x1, x2], self.strides)
+    }
+}
+
+/// Length of array needed for the given dimensions.
+fn data_length(lengths: [usize; 3]) -> usize {
+    lengths[0] * lengths[1] * lengths[2]
+}
+
+fn contiguous_strides(lengths: [usize; 3]) -> [usize; 3] {
+    [1, lengths[0], lengths[0] * lengths[1]]
+}
+
+fn offset(indices: [usize; 3], strides: [usize; 3]) -> usize {
+    indices[0] * strides[0] + indices[1] * strides[1] + indices[2] * strides[2]
+}
+
+#[inline]
+fn check_indices(indices: [usize; 3], lengths: [usize; 3]) {
+    debug_assert!(
+        indices[0] < lengths[0] && indices[1] < lengths[1] && indices[2] < lengths[2],
+        "index {:?} out of bounds for dimensions {:?}", indices, lengths
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::ImageProcError;
+    use image::GrayImage;
+
+    fn numbered(lengths: [usize; 3]) -> Array3d<u32> {
+        Array3d::from_vec((0..data_length(lengths) as u32).collect(), lengths)
+    }
+
+    #[test]
+    fn test_slice_axis() {
+        let mut array = numbered([2, 3, 4]);
+        let mut view = array.view_mut();
+
+        let mut rows = view.slice_axis(2, 1..3);
+        assert_eq!(rows.lengths(), [2, 3, 2]);
+        assert_eq!(*rows.get([1, 2, 1]), 1 + 2 * 2 + 2 * 6);
+
+        let columns = rows.slice_axis(1, 2..3);
+        assert_eq!(columns.to_array(), Array3d::from_vec(vec![10, 11, 16, 17], [2, 1, 2]));
+
+        let mut channel = view.slice_axis(0, 1..2);
+        assert_eq!(channel.strides(), [1, 2, 6]);
+        channel.apply(|v| *v = 0);
+        assert_eq!(array.data().iter().skip(1).step_by(2).sum::<u32>(), 0);
+    }
+
+    #[test]
+    fn test_slice_axis_empty() {
+        let mut array = numbered([2, 3, 4]);
+        let mut view = array.view_mut();
+        let empty = view.slice_axis(2, 4..4);
+        assert_eq!(empty.to_array().lengths(), [2, 3, 0]);
+    }
+
+    #[test]
+    fn test_swap_axes() {
+        let mut array = numbered([2, 3, 1]);
+        let swapped = array.view_mut().swap_axes(0, 1);
+        assert_eq!(swapped.lengths(), [3, 2, 1]);
+        assert_eq!(swapped.to_array().into_vec(), vec![0, 2, 4, 1, 3, 5]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_inner_slice_non_contiguous() {
+        let mut array = numbered([2, 3, 1]);
+        let swapped = array.view_mut().swap_axes(0, 1);
+        swapped.inner_slice(0, 0);
+    }
+
+    #[test]
+    fn test_map_and_zip_map() {
+        let a = numbered([2, 2, 1]);
+        let b = a.map(|v| *v as f32 / 2.0);
+        assert_eq!(b.data(), &[0.0, 0.5, 1.0, 1.5]);
+        let c = a.zip_map(&b, |x, y| *x as f32 + y);
+        assert_eq!(c.data(), &[0.0, 1.5, 3.0, 4.5]);
+    }
+
+    #[test]
+    fn test_channels_round_trip() {
+        let a = gray_image!(1, 2, 3; 4, 5, 6);
+        let b = gray_image!(7, 8, 9; 10, 11, 12);
+        let array = Array3d::from_channels(&[a.clone(), b.clone()]).unwrap();
+        assert_eq!(array.lengths(), [2, 3, 2]);
+        assert_eq!(array.data()[..6], [1, 7, 2, 8, 3, 9]);
+
+        let channels = array.to_channels();
+        assert_eq!(channels.len(), 2);
+        assert_pixels_eq!(channels[0], a);
+        assert_pixels_eq!(channels[1], b);
+    }
+
+    #[test]
+    fn test_from_channels_dimension_mismatch() {
+        let result = Array3d::from_channels(&[GrayImage::new(2, 3), GrayImage::new(3, 2)]);
+        assert_eq!(
+            result,
+            Err(ImageProcError::DimensionMismatch { expected: (2, 3), actual: (3, 2) })
+        );
+        assert_eq!(Array3d::<u8>::from_channels(&[]).unwrap().lengths(), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_map_channels() {
+        let array = Array3d::from_channels(&[gray_image!(1, 2), gray_image!(3, 4)]).unwrap();
+        let mapped = array.map_channels(|c, image| {
+            ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+                Luma([image.get_pixel(x, y)[0] as u16 * 10 + c as u16])
+            })
+        });
+        assert_eq!(mapped.data(), &[10, 31, 20, 41]);
+    }
+}
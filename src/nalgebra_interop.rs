@@ -0,0 +1,228 @@
+//! Conversions between this crate's point and transform types and
+//! [`nalgebra`](https://docs.rs/nalgebra) types, and projective warps using nalgebra matrices.
+//!
+//! Requires the `nalgebra` feature.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # #[macro_use]
+//! # extern crate imageproc;
+//! # extern crate nalgebra;
+//! # fn main() {
+//! use imageproc::geometric_transformations::Interpolation;
+//! use imageproc::nalgebra_interop::warp_projective;
+//! use nalgebra::Matrix3;
+//!
+//! let image = gray_image!(
+//!     1, 2, 3;
+//!     4, 5, 6);
+//!
+//! // Translate one pixel to the right.
+//! let translation = Matrix3::new(
+//!     1.0, 0.0, 1.0,
+//!     0.0, 1.0, 0.0,
+//!     0.0, 0.0, 1.0);
+//!
+//! assert_pixels_eq!(
+//!     warp_projective(&image, &translation, Interpolation::Nearest).unwrap(),
+//!     gray_image!(
+//!         0, 1, 2;
+//!         0, 4, 5));
+//! # }
+//! ```
+
+use image::Pixel;
+use nalgebra::{Matrix3, Point2, Scalar};
+#[allow(deprecated)]
+use affine::Affine2;
+use geometric_transformations::{warp, Interpolation, Projection};
+use conv::ValueInto;
+use corners::Corner;
+use definitions::{Clamp, HasBlack, Image, MaybeSend, MaybeSync};
+use drawing::Point;
+
+/// Returns the projection given by a 3x3 matrix in homogeneous coordinates,
+/// or `None` if the matrix is not invertible.
+pub fn projection_from_matrix(matrix: &Matrix3<f32>) -> Option<Projection> {
+    let m = matrix;
+    Projection::from_matrix([
+        m[(0, 0)], m[(0, 1)], m[(0, 2)],
+        m[(1, 0)], m[(1, 1)], m[(1, 2)],
+        m[(2, 0)], m[(2, 1)], m[(2, 2)],
+    ])
+}
+
+#[allow(deprecated)]
+impl From<Matrix3<f32>> for Affine2 {
+    /// The matrix is not checked to be affine. Warps using the result apply
+    /// the projective transformation given by the matrix.
+    fn from(matrix: Matrix3<f32>) -> Affine2 {
+        let m = matrix;
+        Affine2::from_matrix_unchecked([
+            m[(0, 0)], m[(0, 1)], m[(0, 2)],
+            m[(1, 0)], m[(1, 1)], m[(1, 2)],
+            m[(2, 0)], m[(2, 1)], m[(2, 2)],
+        ])
+    }
+}
+
+#[allow(deprecated)]
+impl From<Affine2> for Matrix3<f32> {
+    fn from(affine: Affine2) -> Matrix3<f32> {
+        Matrix3::from_row_slice(&affine.matrix())
+    }
+}
+
+impl From<Projection> for Matrix3<f32> {
+    fn from(projection: Projection) -> Matrix3<f32> {
+        Matrix3::from_row_slice(&projection.matrix())
+    }
+}
+
+impl<T: Scalar + Eq> From<Point<T>> for Point2<T> {
+    fn from(point: Point<T>) -> Point2<T> {
+        Point2::new(point.x, point.y)
+    }
+}
+
+impl<T: Scalar + Eq> From<Point2<T>> for Point<T> {
+    fn from(point: Point2<T>) -> Point<T> {
+        Point::new(point.x, point.y)
+    }
+}
+
+impl From<Corner> for Point2<f32> {
+    fn from(corner: Corner) -> Point2<f32> {
+        Point2::new(corner.x as f32, corner.y as f32)
+    }
+}
+
+/// Applies the projective transformation given by a 3x3 matrix in homogeneous
+/// coordinates to an image, or returns `None` if the matrix is not invertible.
+/// The output image has the same dimensions as the input. Output pixels
+/// whose pre-image lies outside the input image are set to black.
+pub fn warp_projective<P>(
+    image: &Image<P>,
+    projection: &Matrix3<f32>,
+    interpolation: Interpolation,
+) -> Option<Image<P>>
+where
+    P: Pixel + HasBlack + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    warp_projective_with_default(image, projection, P::black(), interpolation)
+}
+
+/// Applies the projective transformation given by a 3x3 matrix in homogeneous
+/// coordinates to an image, or returns `None` if the matrix is not invertible.
+/// The output image has the same dimensions as the input. Output pixels
+/// whose pre-image lies outside the input image are set to default.
+pub fn warp_projective_with_default<P>(
+    image: &Image<P>,
+    projection: &Matrix3<f32>,
+    default: P,
+    interpolation: Interpolation,
+) -> Option<Image<P>>
+where
+    P: Pixel + MaybeSync + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    projection_from_matrix(projection).map(|p| warp(image, &p, interpolation, default))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_affine_matrix3_round_trip() {
+        let m = Matrix3::new(
+            1.0, 2.0, 3.0,
+            4.0, 5.0, 6.0,
+            7.0, 8.0, 9.0);
+        let affine = Affine2::from(m);
+        assert_eq!(affine.matrix(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        assert_eq!(Matrix3::from(affine), m);
+    }
+
+    #[test]
+    fn test_projection_matrix3_round_trip() {
+        let m = Matrix3::new(
+            1.0, 2.0, 3.0,
+            4.0, 5.0, 6.0,
+            7.0, 8.0, 10.0);
+        let projection = projection_from_matrix(&m).unwrap();
+        assert_eq!(projection.matrix(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 10.0]);
+        assert_eq!(Matrix3::from(projection), m);
+
+        let singular = Matrix3::new(
+            1.0, 2.0, 3.0,
+            4.0, 5.0, 6.0,
+            7.0, 8.0, 9.0);
+        assert!(projection_from_matrix(&singular).is_none());
+    }
+
+    #[test]
+    fn test_point_conversions() {
+        let p: Point2<i32> = Point::new(3, -4).into();
+        assert_eq!(p, Point2::new(3, -4));
+        assert_eq!(Point::from(p), Point::new(3, -4));
+
+        let c: Point2<f32> = Corner::new(5, 7, 1.0).into();
+        assert_eq!(c, Point2::new(5.0, 7.0));
+    }
+
+    #[test]
+    fn test_warp_projective_matches_warp() {
+        let image = gray_image!(
+            1, 2, 3, 4;
+            5, 6, 7, 8;
+            9, 10, 11, 12);
+        let m = Matrix3::new(
+            0.0, -1.0, 3.0,
+            1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0);
+
+        let expected = warp(&image, &projection_from_matrix(&m).unwrap(), Interpolation::Nearest, Luma([0]));
+        let actual = warp_projective(&image, &m, Interpolation::Nearest).unwrap();
+        assert_pixels_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_warp_projective_divides_by_w() {
+        let image = gray_image!(
+            1, 2, 3, 4;
+            5, 6, 7, 8;
+            9, 10, 11, 12;
+            13, 14, 15, 16);
+
+        // Maps (x, y) to (2x, 2y) / (1 + x), so the pre-image of (x', y') is
+        // (x', y') / (2 - x').
+        let m = Matrix3::new(
+            2.0, 0.0, 0.0,
+            0.0, 2.0, 0.0,
+            1.0, 0.0, 1.0);
+
+        let warped = warp_projective(&image, &m, Interpolation::Nearest).unwrap();
+        // (0, y') has pre-image (0, y' / 2).
+        assert_eq!(warped.get_pixel(0, 2), &Luma([5]));
+        // (1, y') has pre-image (1, y').
+        assert_eq!(warped.get_pixel(1, 3), &Luma([14]));
+        // (2, y') has pre-image at infinity, and x' > 2 maps to negative x.
+        assert_eq!(warped.get_pixel(2, 0), &Luma([0]));
+        assert_eq!(warped.get_pixel(3, 1), &Luma([0]));
+    }
+
+    #[test]
+    fn test_warp_projective_singular_matrix() {
+        let image = gray_image!(1, 2; 3, 4);
+        let m = Matrix3::new(
+            1.0, 2.0, 0.0,
+            2.0, 4.0, 0.0,
+            0.0, 0.0, 1.0);
+        assert!(warp_projective(&image, &m, Interpolation::Bilinear).is_none());
+    }
+}
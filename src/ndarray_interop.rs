@@ -0,0 +1,262 @@
+//! Conversions between images and [`ndarray`](https://docs.rs/ndarray) arrays.
+//!
+//! Requires the `ndarray` feature.
+//!
+//! Images are viewed as arrays with shape `(height, width)` for single-channel images
+//! and `(height, width, channels)` in general, so that `array[[y, x, c]]` is channel
+//! `c` of the pixel at `(x, y)`. This matches the storage order of an `Image`, so
+//! converting or viewing an image as an array never copies any data. Converting an
+//! array into an image only copies if the array is not in standard (row-major) layout.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # #[macro_use]
+//! # extern crate imageproc;
+//! # extern crate ndarray;
+//! # fn main() {
+//! use imageproc::ndarray_interop::{array2_into_image, image_as_array2, image_into_array2};
+//!
+//! let image = gray_image!(
+//!     1, 2, 3;
+//!     4, 5, 6);
+//!
+//! assert_eq!(image_as_array2(&image)[[1, 0]], 4);
+//!
+//! let mut array = image_into_array2(image);
+//! array.map_inplace(|p| *p *= 2);
+//! let doubled = array2_into_image(array);
+//!
+//! assert_pixels_eq!(
+//!     doubled,
+//!     gray_image!(
+//!         2,  4,  6;
+//!         8, 10, 12));
+//! # }
+//! ```
+
+use image::{ImageBuffer, Luma, Pixel, Primitive};
+use ndarray::{Array, Array2, Array3, ArrayBase, ArrayView2, ArrayView3, ArrayViewMut2, ArrayViewMut3, Data, Dimension};
+use definitions::Image;
+use std::mem;
+
+/// Returns a view of a grayscale image as an array with shape `(height, width)`.
+pub fn image_as_array2<T: Primitive + 'static>(image: &Image<Luma<T>>) -> ArrayView2<'_, T> {
+    let shape = (image.height() as usize, image.width() as usize);
+    ArrayView2::from_shape(shape, image).unwrap()
+}
+
+/// Returns a mutable view of a grayscale image as an array with shape `(height, width)`.
+pub fn image_as_array2_mut<T: Primitive + 'static>(image: &mut Image<Luma<T>>) -> ArrayViewMut2<'_, T> {
+    let shape = (image.height() as usize, image.width() as usize);
+    ArrayViewMut2::from_shape(shape, image).unwrap()
+}
+
+/// Returns a view of an image as an array with shape `(height, width, channels)`.
+pub fn image_as_array3<P: Pixel + 'static>(image: &Image<P>) -> ArrayView3<'_, P::Subpixel> {
+    ArrayView3::from_shape(shape3(image), image).unwrap()
+}
+
+/// Returns a mutable view of an image as an array with shape `(height, width, channels)`.
+pub fn image_as_array3_mut<P: Pixel + 'static>(image: &mut Image<P>) -> ArrayViewMut3<'_, P::Subpixel> {
+    let shape = shape3(image);
+    ArrayViewMut3::from_shape(shape, image).unwrap()
+}
+
+/// Converts a grayscale image into an array with shape `(height, width)`, without copying.
+pub fn image_into_array2<T: Primitive + 'static>(image: Image<Luma<T>>) -> Array2<T> {
+    let shape = (image.height() as usize, image.width() as usize);
+    Array2::from_shape_vec(shape, image.into_raw()).unwrap()
+}
+
+/// Converts an image into an array with shape `(height, width, channels)`, without copying.
+pub fn image_into_array3<P: Pixel + 'static>(image: Image<P>) -> Array3<P::Subpixel> {
+    let shape = shape3(&image);
+    Array3::from_shape_vec(shape, image.into_raw()).unwrap()
+}
+
+/// Converts an array with shape `(height, width)` into a grayscale image.
+///
+/// This does not copy if the array is in standard layout.
+///
+/// # Panics
+/// If either dimension of the array does not fit in a `u32`.
+pub fn array2_into_image<T: Primitive + 'static>(array: Array2<T>) -> Image<Luma<T>> {
+    let (height, width) = array.dim();
+    ImageBuffer::from_raw(to_u32(width), to_u32(height), into_row_major_vec(array)).unwrap()
+}
+
+/// Converts an array with shape `(height, width, channels)` into an image, or returns
+/// `None` if `channels` is not the number of channels of `P`.
+///
+/// This does not copy if the array is in standard layout.
+///
+/// # Panics
+/// If the width or height of the array does not fit in a `u32`.
+pub fn array3_into_image<P: Pixel + 'static>(array: Array3<P::Subpixel>) -> Option<Image<P>> {
+    let (height, width, channels) = array.dim();
+    if channels != P::channel_count() as usize {
+        return None;
+    }
+    ImageBuffer::from_raw(to_u32(width), to_u32(height), into_row_major_vec(array))
+}
+
+/// Returns a view of an array with shape `(height, width)` as a grayscale image, or `None`
+/// if the array is not in standard layout.
+///
+/// # Panics
+/// If either dimension of the array does not fit in a `u32`.
+pub fn array2_as_image<T, S>(array: &ArrayBase<S, ::ndarray::Ix2>) -> Option<ImageBuffer<Luma<T>, &[T]>>
+where
+    T: Primitive + 'static,
+    S: Data<Elem = T>,
+{
+    let (height, width) = array.dim();
+    array
+        .as_slice()
+        .and_then(|data| ImageBuffer::from_raw(to_u32(width), to_u32(height), data))
+}
+
+/// Returns a view of an array with shape `(height, width, channels)` as an image, or `None`
+/// if the array is not in standard layout or `channels` is not the number of channels of `P`.
+///
+/// # Panics
+/// If the width or height of the array does not fit in a `u32`.
+pub fn array3_as_image<P, S>(array: &ArrayBase<S, ::ndarray::Ix3>) -> Option<ImageBuffer<P, &[P::Subpixel]>>
+where
+    P: Pixel + 'static,
+    S: Data<Elem = P::Subpixel>,
+{
+    let (height, width, channels) = array.dim();
+    if channels != P::channel_count() as usize {
+        return None;
+    }
+    array
+        .as_slice()
+        .and_then(|data| ImageBuffer::from_raw(to_u32(width), to_u32(height), data))
+}
+
+fn shape3<P: Pixel + 'static>(image: &Image<P>) -> (usize, usize, usize) {
+    (image.height() as usize, image.width() as usize, P::channel_count() as usize)
+}
+
+fn to_u32(n: usize) -> u32 {
+    assert!(n <= u32::MAX as usize, "array dimensions must fit in a u32");
+    n as u32
+}
+
+/// Returns the elements of `array` in row-major order, reusing its storage if possible.
+fn into_row_major_vec<T: Clone, D: Dimension>(array: Array<T, D>) -> Vec<T> {
+    if !array.is_standard_layout() {
+        return array.iter().cloned().collect();
+    }
+    // The elements are contiguous and in order, but may not span the whole
+    // of the underlying storage if the array has been sliced.
+    let len = array.len();
+    let first = array.as_ptr() as usize;
+    let mut data = array.into_raw_vec();
+    let size = mem::size_of::<T>();
+    let offset = (first - data.as_ptr() as usize).checked_div(size).unwrap_or(0);
+    if offset == 0 && data.len() == len {
+        return data;
+    }
+    data.drain(offset..offset + len).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use ndarray::{s, Axis};
+
+    #[test]
+    fn test_image_as_array3_indexing() {
+        let image = rgb_image!(
+            [1, 2, 3], [4, 5, 6];
+            [7, 8, 9], [10, 11, 12]);
+
+        let array = image_as_array3(&image);
+        assert_eq!(array.dim(), (2, 2, 3));
+        assert_eq!(array[[1, 0, 2]], 9);
+        assert_eq!(array[[0, 1, 0]], 4);
+    }
+
+    #[test]
+    fn test_image_as_array_mut_writes_through() {
+        let mut image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        image_as_array2_mut(&mut image)[[0, 2]] = 30;
+
+        let mut rgb = RgbImage::new(2, 1);
+        image_as_array3_mut(&mut rgb).index_axis_mut(Axis(2), 1).fill(7);
+
+        assert_pixels_eq!(image, gray_image!(1, 2, 30; 4, 5, 6));
+        assert_eq!(rgb.get_pixel(1, 0), &Rgb([0, 7, 0]));
+    }
+
+    #[test]
+    fn test_image_into_array_does_not_copy() {
+        let image = rgb_image!([1, 2, 3], [4, 5, 6]);
+        let ptr = image.as_ptr();
+        let array = image_into_array3(image);
+        assert_eq!(array.as_ptr(), ptr);
+
+        let round_trip: RgbImage = array3_into_image(array).unwrap();
+        assert_eq!(round_trip.as_ptr(), ptr);
+        assert_pixels_eq!(round_trip, rgb_image!([1, 2, 3], [4, 5, 6]));
+    }
+
+    #[test]
+    fn test_array2_into_image_non_standard_layout() {
+        let array = image_into_array2(gray_image!(
+            1, 2, 3;
+            4, 5, 6));
+        let image = array2_into_image(array.reversed_axes());
+        assert_pixels_eq!(
+            image,
+            gray_image!(
+                1, 4;
+                2, 5;
+                3, 6));
+    }
+
+    #[test]
+    fn test_array2_into_image_sliced_array() {
+        let mut array = image_into_array2(gray_image!(
+            1, 2, 3;
+            4, 5, 6;
+            7, 8, 9));
+        array.slice_collapse(s![1.., ..]);
+        let image = array2_into_image(array);
+        assert_pixels_eq!(
+            image,
+            gray_image!(
+                4, 5, 6;
+                7, 8, 9));
+    }
+
+    #[test]
+    fn test_array3_into_image_rejects_wrong_channel_count() {
+        let array = Array3::<u8>::zeros((2, 2, 4));
+        let image: Option<RgbImage> = array3_into_image(array);
+        assert!(image.is_none());
+    }
+
+    #[test]
+    fn test_array_as_image() {
+        let array = image_into_array2(gray_image!(
+            1, 2, 3;
+            4, 5, 6));
+
+        let view = array2_as_image(&array).unwrap();
+        assert_eq!(view.dimensions(), (3, 2));
+        assert_eq!(view.get_pixel(2, 1)[0], 6);
+
+        assert!(array2_as_image(&array.t()).is_none());
+
+        let rgb = image_into_array3(rgb_image!([1, 2, 3], [4, 5, 6]));
+        let view: ImageBuffer<Rgb<u8>, &[u8]> = array3_as_image(&rgb).unwrap();
+        assert_eq!(view.get_pixel(1, 0), &Rgb([4, 5, 6]));
+    }
+}
@@ -9,6 +9,9 @@ use math::cast;
 
 /// Adds independent additive Gaussian noise to all channels
 /// of an image, with the given mean and standard deviation.
+///
+/// The noise is fully determined by `seed`, so calling this function twice with
+/// the same arguments gives the same output.
 pub fn gaussian_noise<I>(image: &I, mean: f64, stddev: f64, seed: usize) -> Image<I::Pixel>
 where
     I: GenericImage,
@@ -51,6 +54,9 @@ where
 
 /// Converts pixels to black or white at the given `rate` (between 0.0 and 1.0).
 /// Black and white occur with equal probability.
+///
+/// The noise is fully determined by `seed`, so calling this function twice with
+/// the same arguments gives the same output.
 pub fn salt_and_pepper_noise<I>(image: &I, rate: f64, seed: usize) -> Image<I::Pixel>
 where
     I: GenericImage,
@@ -96,9 +102,50 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
-    use image::GrayImage;
+    use image::{GrayImage, Luma};
+    use utils::gray_bench_image;
     use test::{Bencher, black_box};
 
+    #[test]
+    fn test_gaussian_noise_is_deterministic() {
+        let image = gray_bench_image(20, 20);
+        let first = gaussian_noise(&image, 0.0, 20.0, 7);
+        let second = gaussian_noise(&image, 0.0, 20.0, 7);
+        assert_pixels_eq!(first, second);
+        assert!(first.pixels().zip(image.pixels()).any(|(p, q)| p != q));
+
+        let other_seed = gaussian_noise(&image, 0.0, 20.0, 8);
+        assert_ne!(first.into_raw(), other_seed.into_raw());
+    }
+
+    #[test]
+    fn test_gaussian_noise_zero_stddev_adds_mean() {
+        let image = GrayImage::from_pixel(5, 4, Luma([100u8]));
+        let noisy = gaussian_noise(&image, 20.0, 0.0, 1);
+        assert_pixels_eq!(noisy, GrayImage::from_pixel(5, 4, Luma([120u8])));
+    }
+
+    #[test]
+    fn test_salt_and_pepper_noise_is_deterministic() {
+        let image = gray_bench_image(20, 20);
+        let first = salt_and_pepper_noise(&image, 0.3, 3);
+        let second = salt_and_pepper_noise(&image, 0.3, 3);
+        assert_pixels_eq!(first, second);
+    }
+
+    #[test]
+    fn test_salt_and_pepper_noise_rates() {
+        let image = GrayImage::from_pixel(10, 10, Luma([100u8]));
+
+        let unchanged = salt_and_pepper_noise(&image, 0.0, 1);
+        assert_pixels_eq!(unchanged, image);
+
+        let all_noise = salt_and_pepper_noise(&image, 1.0, 1);
+        assert!(all_noise.pixels().all(|p| p[0] == 0 || p[0] == 255));
+        assert!(all_noise.pixels().any(|p| p[0] == 0));
+        assert!(all_noise.pixels().any(|p| p[0] == 255));
+    }
+
     #[bench]
     fn bench_gaussian_noise_mut(b: &mut Bencher) {
         let mut image = GrayImage::new(100, 100);
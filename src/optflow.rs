@@ -0,0 +1,829 @@
+//! [Optical flow](https://en.wikipedia.org/wiki/Optical_flow): estimating the motion of
+//! points from one video frame to the next.
+//!
+//! Sparse flow tracks a set of points, typically found using a corner detector such as
+//! [`corners_fast9`](../corners/fn.corners_fast9.html), using
+//! [`lucas_kanade`](fn.lucas_kanade.html). Dense flow estimates the motion of every pixel,
+//! using [`farneback`](fn.farneback.html).
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use color::hsv_to_rgb;
+use definitions::Image;
+use error::{check_dimensions, check_non_empty, ImageProcError, Result};
+use map::map_subpixels;
+use multiarray::Array3d;
+use pyramid::{gaussian_pyramid, Pyramid, PyramidOptions};
+use std::cmp::min;
+use std::f32;
+
+/// Options for [`lucas_kanade`](fn.lucas_kanade.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LucasKanadeOptions {
+    /// Each point is tracked by matching the (2 * `window_radius` + 1) square window
+    /// centered on it. Must be at least 1.
+    pub window_radius: u32,
+    /// Number of pyramid levels used, including the full size frame. Each extra level
+    /// roughly doubles the largest motion that can be tracked. Must be at least 1.
+    pub pyramid_levels: u32,
+    /// Maximum number of refinement steps at each pyramid level. Must be at least 1.
+    pub max_iterations: u32,
+    /// Refinement at a pyramid level stops once a step moves the point by less than this
+    /// many pixels. Must be positive.
+    pub epsilon: f32,
+    /// Points are lost if the smallest eigenvalue of the gradient matrix of their window,
+    /// divided by the number of pixels in the window, is less than this. This rejects
+    /// points in flat regions or on straight edges, whose motion cannot be determined.
+    /// Must not be negative.
+    pub min_eigenvalue: f32,
+}
+
+impl Default for LucasKanadeOptions {
+    fn default() -> Self {
+        LucasKanadeOptions {
+            window_radius: 7,
+            pyramid_levels: 4,
+            max_iterations: 30,
+            epsilon: 0.01,
+            min_eigenvalue: 0.01,
+        }
+    }
+}
+
+impl LucasKanadeOptions {
+    /// Returns an error describing every invalid option.
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.window_radius == 0 {
+            errors.push("window_radius must be at least 1");
+        }
+        if self.pyramid_levels == 0 {
+            errors.push("pyramid_levels must be at least 1");
+        }
+        if self.max_iterations == 0 {
+            errors.push("max_iterations must be at least 1");
+        }
+        if self.epsilon <= 0.0 || self.epsilon.is_nan() {
+            errors.push("epsilon must be positive");
+        }
+        if self.min_eigenvalue < 0.0 || self.min_eigenvalue.is_nan() {
+            errors.push("min_eigenvalue must not be negative");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// The result of tracking a single point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrackedPoint {
+    /// Position of the point in the current frame. If the point was lost then this
+    /// is its position in the previous frame.
+    pub position: (f32, f32),
+    /// False if the point was lost, either because its window had too little texture or
+    /// because it moved outside the frame.
+    pub found: bool,
+    /// Mean absolute difference between the intensities of the window around the point in
+    /// the previous frame and the window around its new position in the current frame.
+    /// Large values indicate that the point was tracked poorly. Infinite if the point was lost.
+    pub error: f32,
+}
+
+/// Tracks points from `previous` to `current` using the pyramidal [Lucas-Kanade] method.
+///
+/// Each point is tracked by finding the displacement that minimises the squared
+/// difference between the window around it in `previous` and the displaced window in
+/// `current`. Displacements are estimated on the smallest level of a Gaussian pyramid of
+/// each frame, and then refined on each larger level in turn, so that motions larger
+/// than the window can be tracked. Positions may be fractional; pixel centres have
+/// whole number coordinates.
+///
+/// The result has one entry per point, in the same order as `points`.
+///
+/// Returns `ImageProcError::EmptyImage` if `previous` is empty,
+/// `ImageProcError::DimensionMismatch` if the frames have different dimensions, and
+/// `ImageProcError::InvalidOptions` if any option is invalid.
+///
+/// [Lucas-Kanade]: https://en.wikipedia.org/wiki/Lucas%E2%80%93Kanade_method
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::optflow::{lucas_kanade, LucasKanadeOptions};
+///
+/// // A smooth blob that moves 3 pixels right and 2 pixels down.
+/// let blob = |cx: f32, cy: f32| GrayImage::from_fn(40, 40, |x, y| {
+///     let d = (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+///     Luma([(200.0 * (-d / 50.0).exp()) as u8])
+/// });
+/// let previous = blob(18.0, 20.0);
+/// let current = blob(21.0, 22.0);
+///
+/// let tracked = lucas_kanade(&previous, &current, &[(18.0, 20.0)], LucasKanadeOptions::default()).unwrap();
+///
+/// assert!(tracked[0].found);
+/// assert!((tracked[0].position.0 - 21.0).abs() < 0.2);
+/// assert!((tracked[0].position.1 - 22.0).abs() < 0.2);
+/// # }
+/// ```
+pub fn lucas_kanade(
+    previous: &GrayImage,
+    current: &GrayImage,
+    points: &[(f32, f32)],
+    options: LucasKanadeOptions,
+) -> Result<Vec<TrackedPoint>> {
+    check_non_empty(previous.dimensions())?;
+    check_dimensions(previous.dimensions(), current.dimensions())?;
+    options.validate()?;
+
+    let pyramid_options = PyramidOptions {
+        max_levels: options.pyramid_levels,
+        min_size: options.window_radius + 1,
+        ..PyramidOptions::default()
+    };
+    let previous: Image<Luma<f32>> = map_subpixels(previous, |c| c as f32);
+    let current: Image<Luma<f32>> = map_subpixels(current, |c| c as f32);
+    let previous = gaussian_pyramid(&previous, pyramid_options)?;
+    let current = gaussian_pyramid(&current, pyramid_options)?;
+
+    Ok(points.iter().map(|&p| track_point(&previous, &current, p, &options)).collect())
+}
+
+fn track_point(
+    previous: &Pyramid<Luma<f32>>,
+    current: &Pyramid<Luma<f32>>,
+    point: (f32, f32),
+    options: &LucasKanadeOptions,
+) -> TrackedPoint {
+    let lost = TrackedPoint { position: point, found: false, error: f32::INFINITY };
+    let r = options.window_radius as i32;
+    // Displacement of the point, in the coordinates of the current level.
+    let mut displacement = (0f32, 0f32);
+
+    for level in (0..previous.num_levels()).rev() {
+        let (before, after) = (&previous.levels()[level], &current.levels()[level]);
+        let scale = previous.scale(level);
+        let (px, py) = ((point.0 + 0.5) * scale - 0.5, (point.1 + 0.5) * scale - 0.5);
+
+        // Intensity and gradient at each window position in the previous frame.
+        let mut window = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+        let (mut gxx, mut gxy, mut gyy) = (0f32, 0f32, 0f32);
+        for dy in -r..r + 1 {
+            for dx in -r..r + 1 {
+                let (x, y) = (px + dx as f32, py + dy as f32);
+                let ix = (sample(before, x + 1.0, y) - sample(before, x - 1.0, y)) / 2.0;
+                let iy = (sample(before, x, y + 1.0) - sample(before, x, y - 1.0)) / 2.0;
+                gxx += ix * ix;
+                gxy += ix * iy;
+                gyy += iy * iy;
+                window.push((x, y, sample(before, x, y), ix, iy));
+            }
+        }
+
+        let n = window.len() as f32;
+        let min_eigenvalue = (gxx + gyy - ((gxx - gyy).powi(2) + 4.0 * gxy * gxy).sqrt()) / 2.0;
+        let det = gxx * gyy - gxy * gxy;
+        if min_eigenvalue / n < options.min_eigenvalue || det <= 0.0 {
+            return lost;
+        }
+
+        let mut step = (0f32, 0f32);
+        for _ in 0..options.max_iterations {
+            let (mut bx, mut by) = (0f32, 0f32);
+            for &(x, y, value, ix, iy) in &window {
+                let diff = value - sample(after, x + displacement.0 + step.0, y + displacement.1 + step.1);
+                bx += diff * ix;
+                by += diff * iy;
+            }
+            let eta = ((gyy * bx - gxy * by) / det, (gxx * by - gxy * bx) / det);
+            step = (step.0 + eta.0, step.1 + eta.1);
+            if eta.0 * eta.0 + eta.1 * eta.1 < options.epsilon * options.epsilon {
+                break;
+            }
+        }
+
+        displacement = (displacement.0 + step.0, displacement.1 + step.1);
+        if level > 0 {
+            let ratio = previous.scale(level - 1) / scale;
+            displacement = (displacement.0 * ratio, displacement.1 * ratio);
+        }
+    }
+
+    let (before, after) = (&previous.levels()[0], &current.levels()[0]);
+    let (width, height) = before.dimensions();
+    let position = (point.0 + displacement.0, point.1 + displacement.1);
+    if !(position.0 >= 0.0 && position.1 >= 0.0
+        && position.0 <= (width - 1) as f32 && position.1 <= (height - 1) as f32) {
+        return lost;
+    }
+
+    let mut error = 0f32;
+    for dy in -r..r + 1 {
+        for dx in -r..r + 1 {
+            let (x, y) = (point.0 + dx as f32, point.1 + dy as f32);
+            error += (sample(before, x, y) - sample(after, x + displacement.0, y + displacement.1)).abs();
+        }
+    }
+    TrackedPoint { position, found: true, error: error / ((2 * r + 1) * (2 * r + 1)) as f32 }
+}
+
+/// Bilinearly interpolates the intensity at a position, clamping positions outside the image
+/// to its boundary.
+fn sample(image: &Image<Luma<f32>>, x: f32, y: f32) -> f32 {
+    let (width, height) = image.dimensions();
+    let x = x.max(0.0).min((width - 1) as f32);
+    let y = y.max(0.0).min((height - 1) as f32);
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let (right, bottom) = ((left + 1).min(width - 1), (top + 1).min(height - 1));
+    let (fx, fy) = (x - left as f32, y - top as f32);
+    let at = |x, y| image.get_pixel(x, y)[0];
+    let upper = (1.0 - fx) * at(left, top) + fx * at(right, top);
+    let lower = (1.0 - fx) * at(left, bottom) + fx * at(right, bottom);
+    (1.0 - fy) * upper + fy * lower
+}
+
+/// Options for [`farneback`](fn.farneback.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FarnebackOptions {
+    /// Number of pyramid levels used, including the full size frames. Each extra level
+    /// roughly doubles the largest motion that can be estimated. Must be at least 1.
+    pub pyramid_levels: u32,
+    /// Standard deviation of the Gaussian weighting used when approximating the
+    /// neighbourhood of each pixel by a quadratic polynomial. The neighbourhood has radius
+    /// `ceil(2 * poly_sigma)`. Larger values give smoother but less detailed flow. Must be
+    /// positive.
+    pub poly_sigma: f32,
+    /// The flow at each pixel is estimated from the (2 * `window_radius` + 1) square window
+    /// centered on it. Larger values are more robust to noise and track faster motion, but
+    /// blur motion boundaries. Must be at least 1.
+    pub window_radius: u32,
+    /// Number of refinement steps at each pyramid level. Must be at least 1.
+    pub iterations: u32,
+}
+
+impl Default for FarnebackOptions {
+    fn default() -> Self {
+        FarnebackOptions {
+            pyramid_levels: 3,
+            poly_sigma: 1.5,
+            window_radius: 7,
+            iterations: 3,
+        }
+    }
+}
+
+impl FarnebackOptions {
+    /// Returns an error describing every invalid option.
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.pyramid_levels == 0 {
+            errors.push("pyramid_levels must be at least 1");
+        }
+        if self.poly_sigma <= 0.0 || self.poly_sigma.is_nan() {
+            errors.push("poly_sigma must be positive");
+        }
+        if self.window_radius == 0 {
+            errors.push("window_radius must be at least 1");
+        }
+        if self.iterations == 0 {
+            errors.push("iterations must be at least 1");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Estimates the motion of every pixel from `previous` to `current`, using the dense
+/// optical flow algorithm of Gunnar Farnebäck, from "Two-frame motion estimation based on
+/// polynomial expansion", 2003.
+///
+/// The neighbourhood of each pixel in each frame is approximated by a quadratic polynomial,
+/// and the displacement at each pixel is the one that best maps the polynomials of
+/// `previous` onto those of `current` over a window around the pixel. As for
+/// [`lucas_kanade`](fn.lucas_kanade.html), displacements are estimated on a Gaussian
+/// pyramid of each frame, from the smallest level to the full size frames.
+///
+/// The result has lengths `[2, width, height]`: entry `[0, x, y]` is the horizontal
+/// displacement of pixel `(x, y)` and entry `[1, x, y]` is its vertical displacement, so that
+/// the pixel moves to `(x + dx, y + dy)`. Use [`flow_to_color`](fn.flow_to_color.html) to
+/// visualise the flow.
+///
+/// Returns `ImageProcError::EmptyImage` if `previous` is empty,
+/// `ImageProcError::DimensionMismatch` if the frames have different dimensions, and
+/// `ImageProcError::InvalidOptions` if any option is invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::optflow::{farneback, FarnebackOptions};
+///
+/// // A smooth pattern that moves 2 pixels right and 1 pixel up.
+/// let pattern = |dx: f32, dy: f32| GrayImage::from_fn(48, 48, |x, y| {
+///     let (x, y) = (x as f32 - dx, y as f32 - dy);
+///     Luma([(128.0 + 60.0 * (x / 6.0).sin() * (y / 8.0).cos()) as u8])
+/// });
+/// let flow = farneback(&pattern(0.0, 0.0), &pattern(2.0, -1.0), FarnebackOptions::default()).unwrap();
+///
+/// assert_eq!(flow.lengths(), [2, 48, 48]);
+/// assert!((flow.get([0, 24, 24]) - 2.0).abs() < 0.2);
+/// assert!((flow.get([1, 24, 24]) + 1.0).abs() < 0.2);
+/// # }
+/// ```
+pub fn farneback(previous: &GrayImage, current: &GrayImage, options: FarnebackOptions) -> Result<Array3d<f32>> {
+    check_non_empty(previous.dimensions())?;
+    check_dimensions(previous.dimensions(), current.dimensions())?;
+    options.validate()?;
+
+    let pyramid_options = PyramidOptions {
+        max_levels: options.pyramid_levels,
+        min_size: 2 * options.window_radius + 1,
+        ..PyramidOptions::default()
+    };
+    let previous: Image<Luma<f32>> = map_subpixels(previous, |c| c as f32);
+    let current: Image<Luma<f32>> = map_subpixels(current, |c| c as f32);
+    let previous = gaussian_pyramid(&previous, pyramid_options)?;
+    let current = gaussian_pyramid(&current, pyramid_options)?;
+
+    let expansion = PolynomialExpansion::new(options.poly_sigma);
+    let mut flow: Vec<(f32, f32)> = vec![];
+    let mut flow_size = (0, 0);
+
+    for level in (0..previous.num_levels()).rev() {
+        let (width, height) = previous.levels()[level].dimensions();
+        flow = if flow.is_empty() {
+            vec![(0.0, 0.0); (width * height) as usize]
+        } else {
+            let ratio = previous.scale(level + 1) / previous.scale(level);
+            upsample_flow(&flow, flow_size, (width, height), ratio)
+        };
+        flow_size = (width, height);
+
+        let before = expansion.expand(&previous.levels()[level]);
+        let after = expansion.expand(&current.levels()[level]);
+        for _ in 0..options.iterations {
+            flow = update_flow(&before, &after, &flow, width, height, options.window_radius);
+        }
+    }
+
+    let (width, height) = flow_size;
+    let mut data = Vec::with_capacity(flow.len() * 2);
+    for &(dx, dy) in &flow {
+        data.push(dx);
+        data.push(dy);
+    }
+    Ok(Array3d::from_vec(data, [2, width as usize, height as usize]))
+}
+
+/// Visualises a flow field, such as the output of [`farneback`](fn.farneback.html), as an
+/// RGB image. The hue of each pixel gives the direction of its motion, with red for motion
+/// to the right, and the brightness gives its speed. Pixels that do not move are black.
+///
+/// Speeds are divided by `max_magnitude` and clamped to 1, or divided by the largest speed
+/// in the flow if `max_magnitude` is `None`. Passing the same `max_magnitude` for each frame
+/// of a video gives consistent brightness between frames.
+///
+/// # Panics
+/// If `flow` does not have two channels.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::multiarray::Array3d;
+/// use imageproc::optflow::flow_to_color;
+///
+/// // Three pixels, moving right, down and not at all.
+/// let flow = Array3d::from_vec(vec![2.0, 0.0, 0.0, 1.0, 0.0, 0.0], [2, 3, 1]);
+/// let colors = flow_to_color(&flow, None);
+///
+/// assert_eq!(*colors.get_pixel(0, 0), Rgb([255, 0, 0]));
+/// assert_eq!(*colors.get_pixel(1, 0), Rgb([64, 128, 0]));
+/// assert_eq!(*colors.get_pixel(2, 0), Rgb([0, 0, 0]));
+/// # }
+/// ```
+pub fn flow_to_color(flow: &Array3d<f32>, max_magnitude: Option<f32>) -> RgbImage {
+    let lengths = flow.lengths();
+    assert_eq!(lengths[0], 2, "flow must have two channels");
+    let (width, height) = (lengths[1], lengths[2]);
+    let magnitude = |v: &[f32]| (v[0] * v[0] + v[1] * v[1]).sqrt();
+    let max_magnitude = max_magnitude.unwrap_or_else(|| {
+        let mut max = 0f32;
+        for y in 0..height {
+            for x in 0..width {
+                max = max.max(magnitude(flow.inner_slice(x, y)));
+            }
+        }
+        max
+    });
+
+    RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let v = flow.inner_slice(x as usize, y as usize);
+        if max_magnitude <= 0.0 {
+            return Rgb([0, 0, 0]);
+        }
+        let hue = v[1].atan2(v[0]).to_degrees();
+        let value = (magnitude(v) / max_magnitude).min(1.0);
+        hsv_to_rgb([hue, 1.0, value])
+    })
+}
+
+/// Computes the coefficients of the quadratic polynomial `x^T A x + b^T x + c` that best
+/// approximates the neighbourhood of each pixel, weighted by a Gaussian.
+struct PolynomialExpansion {
+    radius: i32,
+    weights: Vec<f32>,
+    // Sums over the 2d Gaussian of the products of pairs of basis functions.
+    m0: f32,
+    m2: f32,
+    m4: f32,
+    m22: f32,
+}
+
+/// The coefficients `[a11, a22, a12, b1, b2]` of the polynomial at each pixel, where
+/// `A = [[a11, a12], [a12, a22]]`.
+type Coefficients = Vec<[f32; 5]>;
+
+impl PolynomialExpansion {
+    fn new(sigma: f32) -> PolynomialExpansion {
+        let radius = (2.0 * sigma).ceil() as i32;
+        let weights: Vec<f32> = (-radius..radius + 1)
+            .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let sum = |p: i32| -> f32 {
+            (-radius..radius + 1).zip(&weights).map(|(i, w)| w * (i as f32).powi(p)).sum()
+        };
+        let (s0, s2, s4) = (sum(0), sum(2), sum(4));
+        PolynomialExpansion { radius, weights, m0: s0 * s0, m2: s2 * s0, m4: s4 * s0, m22: s2 * s2 }
+    }
+
+    fn expand(&self, image: &Image<Luma<f32>>) -> Coefficients {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        let r = self.radius;
+        let pixels: &[f32] = image;
+        let index = |x: i32, y: i32| (y.max(0).min(height - 1) * width + x.max(0).min(width - 1)) as usize;
+
+        // Weighted sums of f, x * f and x^2 * f along each row.
+        let mut rows = vec![[0f32; 3]; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let mut sums = [0f32; 3];
+                for (i, w) in (-r..r + 1).zip(&self.weights) {
+                    let v = w * pixels[index(x + i, y)];
+                    let i = i as f32;
+                    sums[0] += v;
+                    sums[1] += i * v;
+                    sums[2] += i * i * v;
+                }
+                rows[(y * width + x) as usize] = sums;
+            }
+        }
+
+        let (m0, m2, m4, m22) = (self.m0, self.m2, self.m4, self.m22);
+        let mut coefficients = Vec::with_capacity(rows.len());
+        for y in 0..height {
+            for x in 0..width {
+                // Weighted sums of f, x * f, y * f, x^2 * f, y^2 * f and x * y * f.
+                let (mut s, mut sx, mut sy, mut sxx, mut syy, mut sxy) = (0f32, 0f32, 0f32, 0f32, 0f32, 0f32);
+                for (j, w) in (-r..r + 1).zip(&self.weights) {
+                    let row = rows[index(x, y + j)];
+                    let j = j as f32;
+                    s += w * row[0];
+                    sx += w * row[1];
+                    sy += w * j * row[0];
+                    sxx += w * row[2];
+                    syy += w * j * j * row[0];
+                    sxy += w * j * row[1];
+                }
+                // The basis functions x, y and xy are orthogonal to all others, but 1, x^2
+                // and y^2 are not, so their coefficients are found by solving a 3x3 system.
+                let trace = (sxx + syy - 2.0 * m2 * s / m0) / (m4 + m22 - 2.0 * m2 * m2 / m0);
+                let difference = (sxx - syy) / (m4 - m22);
+                coefficients.push([
+                    (trace + difference) / 2.0,
+                    (trace - difference) / 2.0,
+                    sxy / m22 / 2.0,
+                    sx / m2,
+                    sy / m2,
+                ]);
+            }
+        }
+        coefficients
+    }
+}
+
+/// Returns the refined flow at each pixel, given the polynomial expansions of both frames
+/// and the current estimate of the flow.
+fn update_flow(
+    before: &Coefficients,
+    after: &Coefficients,
+    flow: &[(f32, f32)],
+    width: u32,
+    height: u32,
+    window_radius: u32,
+) -> Vec<(f32, f32)> {
+    // The entries g11, g12, g22 of A^T A and h1, h2 of A^T b for each pixel, where
+    // A is the mean of the quadratic terms of both frames and b is the change in the linear
+    // terms, adjusted for the current flow. The flow solves (sum A^T A) d = sum A^T b.
+    let mut matrices = vec![[0f32; 5]; flow.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let (dx, dy) = flow[i];
+            let (tx, ty) = (x as f32 + dx, y as f32 + dy);
+            if tx < 0.0 || ty < 0.0 || tx > (width - 1) as f32 || ty > (height - 1) as f32 {
+                continue;
+            }
+            let p = before[i];
+            let q = interpolate_coefficients(after, width, height, tx, ty);
+            let (a11, a22, a12) = ((p[0] + q[0]) / 2.0, (p[1] + q[1]) / 2.0, (p[2] + q[2]) / 2.0);
+            let b1 = (p[3] - q[3]) / 2.0 + a11 * dx + a12 * dy;
+            let b2 = (p[4] - q[4]) / 2.0 + a12 * dx + a22 * dy;
+            matrices[i] = [
+                a11 * a11 + a12 * a12,
+                a12 * (a11 + a22),
+                a12 * a12 + a22 * a22,
+                a11 * b1 + a12 * b2,
+                a12 * b1 + a22 * b2,
+            ];
+        }
+    }
+
+    let summed = box_sum(&matrices, width, height, window_radius);
+    summed
+        .iter()
+        .map(|g| {
+            // The small constant regularises windows without enough texture towards zero flow.
+            let det = g[0] * g[2] - g[1] * g[1] + 1e-3;
+            ((g[2] * g[3] - g[1] * g[4]) / det, (g[0] * g[4] - g[1] * g[3]) / det)
+        })
+        .collect()
+}
+
+fn interpolate_coefficients(coefficients: &Coefficients, width: u32, height: u32, x: f32, y: f32) -> [f32; 5] {
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let (right, bottom) = ((left + 1).min(width - 1), (top + 1).min(height - 1));
+    let (fx, fy) = (x - left as f32, y - top as f32);
+    let at = |x: u32, y: u32| coefficients[(y * width + x) as usize];
+    let (tl, tr, bl, br) = (at(left, top), at(right, top), at(left, bottom), at(right, bottom));
+    let mut out = [0f32; 5];
+    for c in 0..5 {
+        out[c] = (1.0 - fy) * ((1.0 - fx) * tl[c] + fx * tr[c]) + fy * ((1.0 - fx) * bl[c] + fx * br[c]);
+    }
+    out
+}
+
+/// Sums each value over the (2 * `radius` + 1) square window centered on it, truncated at
+/// the image boundary.
+fn box_sum(values: &[[f32; 5]], width: u32, height: u32, radius: u32) -> Vec<[f32; 5]> {
+    let (width, height, radius) = (width as usize, height as usize, radius as usize);
+    let sum_line = |input: &[[f32; 5]], output: &mut [[f32; 5]], len: usize, stride: usize, start: usize| {
+        for i in 0..len {
+            let (lo, hi) = (i.saturating_sub(radius), min(len - 1, i + radius));
+            let mut sum = [0f32; 5];
+            for j in lo..hi + 1 {
+                for c in 0..5 {
+                    sum[c] += input[start + j * stride][c];
+                }
+            }
+            output[start + i * stride] = sum;
+        }
+    };
+    let mut horizontal = vec![[0f32; 5]; values.len()];
+    for y in 0..height {
+        sum_line(values, &mut horizontal, width, 1, y * width);
+    }
+    let mut out = vec![[0f32; 5]; values.len()];
+    for x in 0..width {
+        sum_line(&horizontal, &mut out, height, width, x);
+    }
+    out
+}
+
+/// Resizes a flow field using bilinear interpolation, scaling displacements by the ratio
+/// of the sizes.
+fn upsample_flow(flow: &[(f32, f32)], from: (u32, u32), to: (u32, u32), ratio: f32) -> Vec<(f32, f32)> {
+    let mut out = Vec::with_capacity((to.0 * to.1) as usize);
+    for y in 0..to.1 {
+        for x in 0..to.0 {
+            let sx = ((x as f32 + 0.5) * ratio - 0.5).max(0.0).min((from.0 - 1) as f32);
+            let sy = ((y as f32 + 0.5) * ratio - 0.5).max(0.0).min((from.1 - 1) as f32);
+            let (left, top) = (sx.floor() as u32, sy.floor() as u32);
+            let (right, bottom) = ((left + 1).min(from.0 - 1), (top + 1).min(from.1 - 1));
+            let (fx, fy) = (sx - left as f32, sy - top as f32);
+            let at = |x: u32, y: u32| flow[(y * from.0 + x) as usize];
+            let (tl, tr, bl, br) = (at(left, top), at(right, top), at(left, bottom), at(right, bottom));
+            let lerp = |a: f32, b: f32, c: f32, d: f32| {
+                (1.0 - fy) * ((1.0 - fx) * a + fx * b) + fy * ((1.0 - fx) * c + fx * d)
+            };
+            out.push((
+                lerp(tl.0, tr.0, bl.0, br.0) / ratio,
+                lerp(tl.1, tr.1, bl.1, br.1) / ratio,
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use corners::corners_fast9;
+    use test::{Bencher, black_box};
+
+    // A smooth texture, shifted by (dx, dy).
+    fn texture(width: u32, height: u32, dx: f32, dy: f32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f32 - dx, y as f32 - dy);
+            let v = 128.0 + 50.0 * (x / 5.0).sin() * (y / 7.0).cos()
+                + 40.0 * ((x + 2.0 * y) / 11.0).sin();
+            Luma([v.round() as u8])
+        })
+    }
+
+    fn grid(width: u32, height: u32, border: u32, step: u32) -> Vec<(f32, f32)> {
+        let mut points = vec![];
+        for y in (0..(height - 2 * border) / step + 1).map(|i| border + i * step) {
+            for x in (0..(width - 2 * border) / step + 1).map(|i| border + i * step) {
+                points.push((x as f32, y as f32));
+            }
+        }
+        points
+    }
+
+    fn assert_tracked(previous: &GrayImage, current: &GrayImage, points: &[(f32, f32)], shift: (f32, f32), options: LucasKanadeOptions) {
+        let tracked = lucas_kanade(previous, current, points, options).unwrap();
+        assert_eq!(tracked.len(), points.len());
+        for (p, t) in points.iter().zip(&tracked) {
+            assert!(t.found, "{:?} lost", p);
+            let (ex, ey) = (p.0 + shift.0, p.1 + shift.1);
+            assert!((t.position.0 - ex).abs() < 0.25 && (t.position.1 - ey).abs() < 0.25,
+                "{:?} tracked to {:?}, expected ({}, {})", p, t.position, ex, ey);
+            assert!(t.error < 5.0, "{:?} error {}", p, t.error);
+        }
+    }
+
+    #[test]
+    fn test_lucas_kanade_subpixel_motion() {
+        let previous = texture(64, 64, 0.0, 0.0);
+        let current = texture(64, 64, 1.4, -0.7);
+        let points = grid(64, 64, 12, 8);
+        assert_tracked(&previous, &current, &points, (1.4, -0.7), LucasKanadeOptions::default());
+    }
+
+    #[test]
+    fn test_lucas_kanade_large_motion_needs_pyramid() {
+        let previous = texture(96, 96, 0.0, 0.0);
+        let current = texture(96, 96, 9.0, 6.0);
+        let points = grid(96, 96, 24, 12);
+        assert_tracked(&previous, &current, &points, (9.0, 6.0), LucasKanadeOptions::default());
+
+        let single_level = LucasKanadeOptions { pyramid_levels: 1, window_radius: 3, ..LucasKanadeOptions::default() };
+        let tracked = lucas_kanade(&previous, &current, &points, single_level).unwrap();
+        let correct = points.iter().zip(&tracked)
+            .filter(|&(p, t)| t.found && (t.position.0 - p.0 - 9.0).abs() < 0.25 && (t.position.1 - p.1 - 6.0).abs() < 0.25)
+            .count();
+        assert!(correct < points.len(), "{} of {}", correct, points.len());
+    }
+
+    #[test]
+    fn test_lucas_kanade_tracks_fast_corners() {
+        let previous = GrayImage::from_fn(80, 80, |x, y| {
+            if x >= 30 && x < 50 && y >= 30 && y < 50 { Luma([220]) } else { Luma([30]) }
+        });
+        let current = GrayImage::from_fn(80, 80, |x, y| {
+            if x >= 34 && x < 54 && y >= 33 && y < 53 { Luma([220]) } else { Luma([30]) }
+        });
+        let points: Vec<(f32, f32)> = corners_fast9(&previous, 50).iter().map(|c| (c.x as f32, c.y as f32)).collect();
+        assert!(!points.is_empty());
+        let options = LucasKanadeOptions { window_radius: 5, ..LucasKanadeOptions::default() };
+        assert_tracked(&previous, &current, &points, (4.0, 3.0), options);
+    }
+
+    #[test]
+    fn test_lucas_kanade_loses_points_without_texture() {
+        let flat = GrayImage::from_pixel(40, 40, Luma([100]));
+        let tracked = lucas_kanade(&flat, &flat, &[(20.0, 20.0)], LucasKanadeOptions::default()).unwrap();
+        assert_eq!(tracked[0], TrackedPoint { position: (20.0, 20.0), found: false, error: f32::INFINITY });
+
+        // A vertical edge only constrains horizontal motion.
+        let edge = GrayImage::from_fn(40, 40, |x, _| if x < 20 { Luma([0]) } else { Luma([255]) });
+        let tracked = lucas_kanade(&edge, &edge, &[(20.0, 20.0)], LucasKanadeOptions::default()).unwrap();
+        assert!(!tracked[0].found);
+    }
+
+    #[test]
+    fn test_lucas_kanade_invalid_inputs() {
+        let image = texture(20, 20, 0.0, 0.0);
+        let options = LucasKanadeOptions::default();
+        assert_eq!(
+            lucas_kanade(&image, &texture(20, 21, 0.0, 0.0), &[], options),
+            Err(ImageProcError::DimensionMismatch { expected: (20, 20), actual: (20, 21) })
+        );
+        assert_eq!(lucas_kanade(&GrayImage::new(0, 0), &GrayImage::new(0, 0), &[], options), Err(ImageProcError::EmptyImage));
+        let invalid = LucasKanadeOptions { window_radius: 0, epsilon: 0.0, ..options };
+        assert_eq!(
+            lucas_kanade(&image, &image, &[], invalid),
+            Err(ImageProcError::InvalidOptions("window_radius must be at least 1, epsilon must be positive".into()))
+        );
+    }
+
+    fn assert_flow(flow: &Array3d<f32>, shift: (f32, f32), border: usize, max_error: f32, mean_error: f32) {
+        let lengths = flow.lengths();
+        let (mut max, mut sum, mut count) = (0f32, 0f32, 0);
+        for y in border..lengths[2] - border {
+            for x in border..lengths[1] - border {
+                let v = flow.inner_slice(x, y);
+                let error = (v[0] - shift.0).abs().max((v[1] - shift.1).abs());
+                max = max.max(error);
+                sum += error;
+                count += 1;
+            }
+        }
+        assert!(max < max_error, "max error {}", max);
+        assert!(sum / (count as f32) < mean_error, "mean error {}", sum / count as f32);
+    }
+
+    #[test]
+    fn test_farneback_subpixel_motion() {
+        let flow = farneback(&texture(64, 64, 0.0, 0.0), &texture(64, 64, 1.5, -1.0), FarnebackOptions::default()).unwrap();
+        assert_eq!(flow.lengths(), [2, 64, 64]);
+        assert_flow(&flow, (1.5, -1.0), 8, 0.5, 0.05);
+    }
+
+    #[test]
+    fn test_farneback_large_motion() {
+        let flow = farneback(&texture(96, 96, 0.0, 0.0), &texture(96, 96, 6.0, 4.0), FarnebackOptions::default()).unwrap();
+        assert_flow(&flow, (6.0, 4.0), 16, 0.1, 0.01);
+    }
+
+    #[test]
+    fn test_farneback_static_frames() {
+        let image = GrayImage::from_pixel(30, 30, Luma([100]));
+        let flow = farneback(&image, &image, FarnebackOptions::default()).unwrap();
+        assert!(flow.data().iter().all(|v| *v == 0.0));
+
+        let image = texture(30, 30, 0.0, 0.0);
+        let flow = farneback(&image, &image, FarnebackOptions::default()).unwrap();
+        assert!(flow.data().iter().all(|v| v.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_farneback_invalid_inputs() {
+        let image = texture(20, 20, 0.0, 0.0);
+        let options = FarnebackOptions::default();
+        assert_eq!(
+            farneback(&image, &texture(21, 20, 0.0, 0.0), options),
+            Err(ImageProcError::DimensionMismatch { expected: (20, 20), actual: (21, 20) })
+        );
+        let invalid = FarnebackOptions { poly_sigma: -1.0, iterations: 0, ..options };
+        assert_eq!(
+            farneback(&image, &image, invalid),
+            Err(ImageProcError::InvalidOptions("poly_sigma must be positive, iterations must be at least 1".into()))
+        );
+    }
+
+    #[test]
+    fn test_flow_to_color() {
+        // Moving left, up, and left at twice the speed.
+        let flow = Array3d::from_vec(vec![-1.0, 0.0, 0.0, -1.0, -2.0, 0.0], [2, 3, 1]);
+        let colors = flow_to_color(&flow, Some(1.0));
+        assert_eq!(*colors.get_pixel(0, 0), Rgb([0, 255, 255]));
+        assert_eq!(*colors.get_pixel(1, 0), Rgb([128, 0, 255]));
+        assert_eq!(*colors.get_pixel(2, 0), Rgb([0, 255, 255]));
+
+        let still = Array3d::new([2, 4, 4]);
+        assert!(flow_to_color(&still, None).pixels().all(|p| *p == Rgb([0, 0, 0])));
+    }
+
+    #[bench]
+    fn bench_farneback(b: &mut Bencher) {
+        let previous = texture(100, 100, 0.0, 0.0);
+        let current = texture(100, 100, 2.5, 1.5);
+        b.iter(|| {
+            let flow = farneback(&previous, &current, FarnebackOptions::default()).unwrap();
+            black_box(flow);
+        });
+    }
+
+    #[bench]
+    fn bench_lucas_kanade(b: &mut Bencher) {
+        let previous = texture(200, 200, 0.0, 0.0);
+        let current = texture(200, 200, 3.5, 2.5);
+        let points = grid(200, 200, 20, 16);
+        b.iter(|| {
+            let tracked = lucas_kanade(&previous, &current, &points, LucasKanadeOptions::default()).unwrap();
+            black_box(tracked);
+        });
+    }
+}
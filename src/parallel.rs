@@ -0,0 +1,99 @@
+//! Helpers for running independent per-row computations, in parallel if the
+//! `rayon` feature is enabled and serially otherwise.
+//!
+//! Every function in this module hands each row to the provided closure exactly
+//! once, and rows never observe each other's outputs. This means that results
+//! are identical whether or not the `rayon` feature is enabled, and do not depend
+//! on the number of threads used.
+
+use image::Pixel;
+use definitions::{Image, MaybeSend, MaybeSync};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Calls `f(index, chunk)` for each consecutive chunk of length `chunk_len` in `buffer`.
+///
+/// If `chunk_len` does not divide the buffer length then the final chunk is shorter.
+pub(crate) fn for_each_chunk<T, F>(buffer: &mut [T], chunk_len: usize, f: F)
+where
+    T: MaybeSend,
+    F: Fn(usize, &mut [T]) + MaybeSync + MaybeSend,
+{
+    if chunk_len == 0 || buffer.is_empty() {
+        return;
+    }
+
+    #[cfg(feature = "rayon")]
+    buffer
+        .par_chunks_mut(chunk_len)
+        .enumerate()
+        .for_each(|(i, chunk)| f(i, chunk));
+
+    #[cfg(not(feature = "rayon"))]
+    for (i, chunk) in buffer.chunks_mut(chunk_len).enumerate() {
+        f(i, chunk);
+    }
+}
+
+/// Calls `f(y, row)` for each row of `image`, where `row` contains the subpixels
+/// of that row in the image's storage order.
+pub(crate) fn for_each_row<P, F>(image: &mut Image<P>, f: F)
+where
+    P: Pixel + 'static,
+    P::Subpixel: MaybeSend,
+    F: Fn(u32, &mut [P::Subpixel]) + MaybeSync + MaybeSend,
+{
+    let row_len = image.width() as usize * P::channel_count() as usize;
+    let buffer: &mut [P::Subpixel] = &mut *image;
+    for_each_chunk(buffer, row_len, |y, row| f(y as u32, row));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Rgb, RgbImage};
+
+    #[test]
+    fn test_for_each_row_visits_every_row_once() {
+        let mut image = RgbImage::new(3, 4);
+        for_each_row(&mut image, |y, row| {
+            assert_eq!(row.len(), 9);
+            for p in row.iter_mut() {
+                *p += y as u8 + 1;
+            }
+        });
+        for (_, y, p) in image.enumerate_pixels() {
+            let v = y as u8 + 1;
+            assert_eq!(*p, Rgb([v, v, v]));
+        }
+    }
+
+    #[test]
+    fn test_for_each_row_empty_image() {
+        let mut image = GrayImage::new(0, 5);
+        for_each_row(&mut image, |_, _| panic!("no rows should be visited"));
+        let mut image = GrayImage::new(5, 0);
+        for_each_row(&mut image, |_, _| panic!("no rows should be visited"));
+    }
+
+    #[test]
+    fn test_for_each_chunk_short_final_chunk() {
+        let mut buffer = vec![0usize; 7];
+        for_each_chunk(&mut buffer, 3, |i, chunk| {
+            for c in chunk.iter_mut() {
+                *c = i;
+            }
+        });
+        assert_eq!(buffer, vec![0, 0, 0, 1, 1, 1, 2]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "rayon"))]
+    fn test_for_each_row_accepts_non_sync_closures() {
+        use std::cell::Cell;
+        let visited = Cell::new(0);
+        for_each_row(&mut GrayImage::new(2, 3), |_, _| visited.set(visited.get() + 1));
+        assert_eq!(visited.get(), 3);
+    }
+}
@@ -0,0 +1,424 @@
+//! Lazily evaluated chains of operations on grayscale images.
+//!
+//! Calling a sequence of whole-image functions allocates a new buffer for each
+//! intermediate result and reads every pixel once per function. A [`Pipeline`](struct.Pipeline.html)
+//! instead records the requested operations and only evaluates them when
+//! [`run`](struct.Pipeline.html#method.run) is called. Consecutive pointwise stages
+//! (thresholding, contrast stretching, inversion, arbitrary intensity maps) are fused
+//! into a single lookup table, histogram-based stages (Otsu thresholding, histogram
+//! equalisation) compute their histograms without materialising the pending pointwise
+//! stages, and in-place stages (morphological operators) reuse the current buffer.
+//!
+//! The output of a pipeline is always identical to that of calling the corresponding
+//! functions in sequence.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # extern crate imageproc;
+//! # fn main() {
+//! use image::GrayImage;
+//! use imageproc::contrast::{otsu_level, threshold};
+//! use imageproc::distance_transform::Norm;
+//! use imageproc::filter::gaussian_blur_f32;
+//! use imageproc::morphology::erode;
+//! use imageproc::pipeline::Pipe;
+//!
+//! let image = GrayImage::from_fn(20, 20, |x, y| image::Luma([(x * y) as u8]));
+//!
+//! let piped = image.pipe()
+//!     .blur(2.0)
+//!     .threshold_otsu()
+//!     .erode(Norm::LInf, 1)
+//!     .run();
+//!
+//! let blurred = gaussian_blur_f32(&image, 2.0);
+//! let thresholded = threshold(&blurred, otsu_level(&blurred));
+//! let sequential = erode(&thresholded, Norm::LInf, 1);
+//!
+//! assert_eq!(piped.into_raw(), sequential.into_raw());
+//! # }
+//! ```
+
+use image::GrayImage;
+use contrast::{
+    apply_lut_into, apply_lut_mut, equalize_histogram_lut, gamma_lut, otsu_level_from_histogram,
+    stretch_contrast_lut, threshold_lut,
+};
+use distance_transform::Norm;
+use filter::{gaussian_blur_f32, median_filter};
+use morphology::{close_mut, dilate_mut, erode_mut, open_mut};
+use stats::histogram;
+
+/// Starts a [`Pipeline`](struct.Pipeline.html) from an image.
+pub trait Pipe {
+    /// Returns a pipeline with no stages, whose input is `self`.
+    fn pipe<'a>(&'a self) -> Pipeline<'a>;
+}
+
+impl Pipe for GrayImage {
+    fn pipe<'a>(&'a self) -> Pipeline<'a> {
+        Pipeline::new(self)
+    }
+}
+
+/// A lazily evaluated sequence of operations on a grayscale image.
+///
+/// See the [module documentation](index.html) for details.
+pub struct Pipeline<'a> {
+    source: &'a GrayImage,
+    stages: Vec<Stage>,
+}
+
+enum Stage {
+    /// Maps each intensity `p` to `lut[p]`.
+    Lookup(Box<[u8; 256]>),
+    /// Maps intensities via a lookup table computed from the histogram of the image.
+    HistogramLookup(fn(&[u32; 256]) -> [u8; 256]),
+    /// Modifies the image in place.
+    InPlace(Box<dyn Fn(&mut GrayImage)>),
+    /// Computes a new image from the current one.
+    Filter(Box<dyn Fn(&GrayImage) -> GrayImage>),
+}
+
+impl<'a> Pipeline<'a> {
+    /// A pipeline with no stages, whose input is `source`.
+    pub fn new(source: &'a GrayImage) -> Pipeline<'a> {
+        Pipeline { source, stages: vec![] }
+    }
+
+    fn then(mut self, stage: Stage) -> Pipeline<'a> {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Applies a Gaussian blur. See [`gaussian_blur_f32`](../filter/fn.gaussian_blur_f32.html).
+    pub fn blur(self, sigma: f32) -> Pipeline<'a> {
+        self.then(Stage::Filter(Box::new(move |image| gaussian_blur_f32(image, sigma))))
+    }
+
+    /// Applies a median filter. See [`median_filter`](../filter/fn.median_filter.html).
+    pub fn median(self, radius: u32) -> Pipeline<'a> {
+        self.then(Stage::Filter(Box::new(move |image| median_filter(image, radius, radius))))
+    }
+
+    /// Applies a fixed threshold. See [`threshold`](../contrast/fn.threshold.html).
+    pub fn threshold(self, thresh: u8) -> Pipeline<'a> {
+        self.then(Stage::Lookup(Box::new(threshold_lut(thresh))))
+    }
+
+    /// Thresholds at the Otsu level of the image produced by the preceding stages.
+    /// See [`otsu_level`](../contrast/fn.otsu_level.html).
+    pub fn threshold_otsu(self) -> Pipeline<'a> {
+        self.then(Stage::HistogramLookup(|hist| threshold_lut(otsu_level_from_histogram(hist))))
+    }
+
+    /// Equalises the histogram of the image produced by the preceding stages.
+    /// See [`equalize_histogram`](../contrast/fn.equalize_histogram.html).
+    pub fn equalize_histogram(self) -> Pipeline<'a> {
+        self.then(Stage::HistogramLookup(|hist| {
+            let mut cumulative = *hist;
+            for i in 1..cumulative.len() {
+                cumulative[i] += cumulative[i - 1];
+            }
+            equalize_histogram_lut(&cumulative)
+        }))
+    }
+
+    /// Linearly stretches intensities. See [`stretch_contrast`](../contrast/fn.stretch_contrast.html).
+    ///
+    /// # Panics
+    /// If `upper` is not strictly greater than `lower`.
+    pub fn stretch_contrast(self, lower: u8, upper: u8) -> Pipeline<'a> {
+        self.then(Stage::Lookup(Box::new(stretch_contrast_lut(lower, upper))))
+    }
+
+    /// Applies gamma correction. See [`gamma_correct`](../contrast/fn.gamma_correct.html).
+    ///
+    /// # Panics
+    /// If `gamma` is not positive.
+    pub fn gamma_correct(self, gamma: f32) -> Pipeline<'a> {
+        self.then(Stage::Lookup(Box::new(gamma_lut(gamma))))
+    }
+
+    /// Replaces each intensity `p` with `255 - p`.
+    pub fn invert(self) -> Pipeline<'a> {
+        self.map(|p| 255 - p)
+    }
+
+    /// Replaces each intensity `p` with `f(p)`.
+    ///
+    /// `f` is evaluated once for every possible intensity, not just those present in the image.
+    pub fn map<F: Fn(u8) -> u8>(self, f: F) -> Pipeline<'a> {
+        let mut lut = [0u8; 256];
+        for (p, l) in lut.iter_mut().enumerate() {
+            *l = f(p as u8);
+        }
+        self.then(Stage::Lookup(Box::new(lut)))
+    }
+
+    /// Applies a dilation. See [`dilate`](../morphology/fn.dilate.html).
+    pub fn dilate(self, norm: Norm, k: u8) -> Pipeline<'a> {
+        self.then(Stage::InPlace(Box::new(move |image| dilate_mut(image, norm, k))))
+    }
+
+    /// Applies an erosion. See [`erode`](../morphology/fn.erode.html).
+    pub fn erode(self, norm: Norm, k: u8) -> Pipeline<'a> {
+        self.then(Stage::InPlace(Box::new(move |image| erode_mut(image, norm, k))))
+    }
+
+    /// Applies a morphological opening. See [`open`](../morphology/fn.open.html).
+    pub fn open(self, norm: Norm, k: u8) -> Pipeline<'a> {
+        self.then(Stage::InPlace(Box::new(move |image| open_mut(image, norm, k))))
+    }
+
+    /// Applies a morphological closing. See [`close`](../morphology/fn.close.html).
+    pub fn close(self, norm: Norm, k: u8) -> Pipeline<'a> {
+        self.then(Stage::InPlace(Box::new(move |image| close_mut(image, norm, k))))
+    }
+
+    /// Appends an arbitrary operation which modifies the image in place.
+    pub fn apply_mut<F: Fn(&mut GrayImage) + 'static>(self, f: F) -> Pipeline<'a> {
+        self.then(Stage::InPlace(Box::new(f)))
+    }
+
+    /// Appends an arbitrary operation which computes a new image from the current one.
+    pub fn apply<F: Fn(&GrayImage) -> GrayImage + 'static>(self, f: F) -> Pipeline<'a> {
+        self.then(Stage::Filter(Box::new(f)))
+    }
+
+    /// Evaluates all stages, returning the final image.
+    pub fn run(self) -> GrayImage {
+        let source = self.source;
+        // The most recently materialised image, or None if this is still the source.
+        let mut current: Option<GrayImage> = None;
+        // Pointwise stages which have not yet been applied to current.
+        let mut pending: Option<[u8; 256]> = None;
+
+        for stage in self.stages {
+            match stage {
+                Stage::Lookup(lut) => {
+                    pending = Some(compose(pending, &lut));
+                }
+                Stage::HistogramLookup(f) => {
+                    let hist = histogram(current.as_ref().unwrap_or(source));
+                    let lut = match pending {
+                        Some(ref p) => f(&map_histogram(&hist, p)),
+                        None => f(&hist),
+                    };
+                    pending = Some(compose(pending, &lut));
+                }
+                Stage::InPlace(f) => {
+                    let mut image = materialise(source, current.take(), pending.take());
+                    f(&mut image);
+                    current = Some(image);
+                }
+                Stage::Filter(f) => {
+                    let image = match (current.take(), pending.take()) {
+                        (None, None) => f(source),
+                        (c, p) => f(&materialise(source, c, p)),
+                    };
+                    current = Some(image);
+                }
+            }
+        }
+
+        materialise(source, current, pending)
+    }
+}
+
+/// Returns the lookup table equivalent to applying `first` and then `second`.
+fn compose(first: Option<[u8; 256]>, second: &[u8; 256]) -> [u8; 256] {
+    match first {
+        None => *second,
+        Some(mut first) => {
+            for l in first.iter_mut() {
+                *l = second[*l as usize];
+            }
+            first
+        }
+    }
+}
+
+/// Returns the histogram of an image after mapping its intensities via `lut`.
+fn map_histogram(hist: &[u32; 256], lut: &[u8; 256]) -> [u32; 256] {
+    let mut mapped = [0u32; 256];
+    for (count, l) in hist.iter().zip(lut.iter()) {
+        mapped[*l as usize] += *count;
+    }
+    mapped
+}
+
+/// Applies `lut` to `current`, or to a copy of `source` if `current` is None.
+fn materialise(
+    source: &GrayImage,
+    current: Option<GrayImage>,
+    lut: Option<[u8; 256]>,
+) -> GrayImage {
+    match (current, lut) {
+        (Some(image), None) => image,
+        (None, None) => source.clone(),
+        (Some(mut image), Some(lut)) => {
+            apply_lut_mut(&mut image, &lut);
+            image
+        }
+        (None, Some(lut)) => {
+            let mut image = GrayImage::new(source.width(), source.height());
+            apply_lut_into(source, &lut, &mut image);
+            image
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use contrast::{equalize_histogram, gamma_correct, otsu_level, stretch_contrast, threshold};
+    use image::{GrayImage, Luma};
+    use morphology::{dilate, erode, open};
+    use property_testing::GrayTestImage;
+    use quickcheck::{quickcheck, TestResult};
+    use utils::{gray_bench_image, pixel_diff_summary};
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_empty_pipeline_returns_copy_of_source() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        assert_pixels_eq!(image.pipe().run(), image);
+    }
+
+    #[test]
+    fn test_pipeline_fuses_pointwise_stages() {
+        let image = gray_image!(
+            10, 80, 20;
+            50, 90, 70);
+
+        let piped = image.pipe()
+            .stretch_contrast(10, 90)
+            .invert()
+            .threshold(100)
+            .run();
+
+        let expected = threshold(
+            &map_colors(&stretch_contrast(&image, 10, 90), |p| Luma([255 - p[0]])),
+            100);
+
+        assert_pixels_eq!(piped, expected);
+    }
+
+    #[test]
+    fn test_gamma_correct_stage_matches_gamma_correct() {
+        let image = gray_bench_image(12, 9);
+        assert_pixels_eq!(image.pipe().gamma_correct(0.7).run(), gamma_correct(&image, 0.7));
+    }
+
+    #[test]
+    fn test_threshold_otsu_after_pointwise_stage_matches_sequential() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let image = image.0;
+            let piped = image.pipe().stretch_contrast(30, 200).threshold_otsu().run();
+
+            let stretched = stretch_contrast(&image, 30, 200);
+            let expected = threshold(&stretched, otsu_level(&stretched));
+
+            match pixel_diff_summary(&piped, &expected) {
+                Some(err) => TestResult::error(err),
+                None => TestResult::passed(),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_equalize_histogram_after_filter_matches_sequential() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let image = image.0;
+            let piped = image.pipe().median(1).invert().equalize_histogram().run();
+
+            let inverted = map_colors(&median_filter(&image, 1, 1), |p| Luma([255 - p[0]]));
+            let expected = equalize_histogram(&inverted);
+
+            match pixel_diff_summary(&piped, &expected) {
+                Some(err) => TestResult::error(err),
+                None => TestResult::passed(),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_mixed_pipeline_matches_sequential() {
+        fn prop(image: GrayTestImage) -> TestResult {
+            let image = image.0;
+            let piped = image.pipe()
+                .blur(1.5)
+                .threshold_otsu()
+                .erode(Norm::L1, 1)
+                .dilate(Norm::LInf, 2)
+                .open(Norm::LInf, 1)
+                .run();
+
+            let blurred = gaussian_blur_f32(&image, 1.5);
+            let thresholded = threshold(&blurred, otsu_level(&blurred));
+            let eroded = erode(&thresholded, Norm::L1, 1);
+            let dilated = dilate(&eroded, Norm::LInf, 2);
+            let expected = open(&dilated, Norm::LInf, 1);
+
+            match pixel_diff_summary(&piped, &expected) {
+                Some(err) => TestResult::error(err),
+                None => TestResult::passed(),
+            }
+        }
+        quickcheck(prop as fn(GrayTestImage) -> TestResult);
+    }
+
+    #[test]
+    fn test_apply_stages() {
+        let image = gray_image!(
+            2, 4;
+            6, 8);
+
+        let piped = image.pipe()
+            .map(|p| p / 2)
+            .apply(|i| GrayImage::from_fn(i.height(), i.width(), |x, y| *i.get_pixel(y, x)))
+            .apply_mut(|i| i.put_pixel(0, 0, Luma([9])))
+            .map(|p| p.saturating_add(1))
+            .run();
+
+        let expected = gray_image!(
+            10, 4;
+             3, 5);
+
+        assert_pixels_eq!(piped, expected);
+    }
+
+    fn map_colors<F: Fn(Luma<u8>) -> Luma<u8>>(image: &GrayImage, f: F) -> GrayImage {
+        GrayImage::from_fn(image.width(), image.height(), |x, y| f(*image.get_pixel(x, y)))
+    }
+
+    #[bench]
+    fn bench_pipeline_pointwise(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let piped = image.pipe()
+                .stretch_contrast(20, 220)
+                .invert()
+                .threshold_otsu()
+                .run();
+            black_box(piped);
+        });
+    }
+
+    #[bench]
+    fn bench_sequential_pointwise(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| {
+            let stretched = stretch_contrast(&image, 20, 220);
+            let inverted = map_colors(&stretched, |p| Luma([255 - p[0]]));
+            let thresholded = threshold(&inverted, otsu_level(&inverted));
+            black_box(thresholded);
+        });
+    }
+}
@@ -1,6 +1,6 @@
 //! Pixel manipulations.
 
-use image::Pixel;
+use image::{ColorType, Pixel};
 use conv::ValueInto;
 use math::cast;
 use definitions::Clamp;
@@ -55,6 +55,14 @@ where
     weighted_sum(left, right, left_weight, 1.0 - left_weight)
 }
 
+/// Returns true if pixels of type `P` have an alpha channel.
+pub(crate) fn has_alpha<P: Pixel>() -> bool {
+    match P::color_type() {
+        ColorType::GrayA(_) | ColorType::RGBA(_) | ColorType::BGRA(_) => true,
+        _ => false,
+    }
+}
+
 #[inline(always)]
 fn weighted_channel_sum<C>(left: C, right: C, left_weight: f32, right_weight: f32) -> C
 where
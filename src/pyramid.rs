@@ -0,0 +1,431 @@
+//! Gaussian and Laplacian image pyramids.
+//!
+//! A pyramid is a sequence of progressively smaller copies of an image. Each level of a
+//! [Gaussian pyramid](fn.gaussian_pyramid.html) is a blurred and downsampled copy of the
+//! level above it, and each level of a [Laplacian pyramid](fn.laplacian_pyramid.html)
+//! holds the detail lost between consecutive levels of a Gaussian pyramid.
+
+use conv::ValueInto;
+use definitions::{Clamp, Image, MaybeSend, MaybeSync};
+use error::{check_non_empty, ImageProcError, Result};
+use filter::separable_filter_equal;
+use image::{GenericImageView, ImageBuffer, Pixel};
+use map::{map_subpixels, ChannelMap, WithChannel};
+use math::cast;
+
+/// Options for [`gaussian_pyramid`](fn.gaussian_pyramid.html) and
+/// [`laplacian_pyramid`](fn.laplacian_pyramid.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PyramidOptions {
+    /// Maximum number of levels, including the full size level. Must be at least 1.
+    pub max_levels: u32,
+    /// Ratio between the sizes of consecutive levels. Must be greater than 0 and less than 1.
+    /// The default of 0.5 gives one level per octave.
+    pub scale_factor: f32,
+    /// Standard deviation of the Gaussian blur applied to each level before it is
+    /// downsampled to create the next level. Must be positive.
+    pub sigma: f32,
+    /// Levels whose width or height would be less than this are not created. Must be at least 1.
+    pub min_size: u32,
+}
+
+impl Default for PyramidOptions {
+    fn default() -> Self {
+        PyramidOptions {
+            max_levels: u32::max_value(),
+            scale_factor: 0.5,
+            sigma: 1.0,
+            min_size: 1,
+        }
+    }
+}
+
+impl PyramidOptions {
+    /// Returns an error describing every invalid option.
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.max_levels == 0 {
+            errors.push("max_levels must be at least 1");
+        }
+        if !(self.scale_factor > 0.0 && self.scale_factor < 1.0) {
+            errors.push("scale_factor must be greater than 0 and less than 1");
+        }
+        if self.sigma <= 0.0 || self.sigma.is_nan() {
+            errors.push("sigma must be positive");
+        }
+        if self.min_size == 0 {
+            errors.push("min_size must be at least 1");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// A sequence of images whose sizes decrease by a constant factor. Level 0 has
+/// the size of the image the pyramid was created from.
+#[derive(Clone)]
+pub struct Pyramid<P: Pixel> {
+    levels: Vec<Image<P>>,
+    scale_factor: f32,
+}
+
+impl<P: Pixel + 'static> Pyramid<P> {
+    /// The levels of this pyramid, from largest to smallest.
+    pub fn levels(&self) -> &[Image<P>] {
+        &self.levels
+    }
+
+    /// Returns the given level, or `None` if the pyramid does not have this many levels.
+    pub fn level(&self, level: usize) -> Option<&Image<P>> {
+        self.levels.get(level)
+    }
+
+    /// Consumes the pyramid and returns its levels, from largest to smallest.
+    pub fn into_levels(self) -> Vec<Image<P>> {
+        self.levels
+    }
+
+    /// Number of levels in this pyramid. This is always at least 1.
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Ratio between the sizes of consecutive levels.
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Size of the given level relative to level 0, before rounding to a whole number of pixels.
+    pub fn scale(&self, level: usize) -> f32 {
+        self.scale_factor.powi(level as i32)
+    }
+
+    /// Number of times the image has been halved in size at the given level.
+    /// This need not be a whole number.
+    pub fn octave(&self, level: usize) -> f32 {
+        -self.scale(level).log2()
+    }
+
+    /// Number of levels needed to halve the size of the image.
+    pub fn levels_per_octave(&self) -> f32 {
+        -1.0 / self.scale_factor.log2()
+    }
+}
+
+impl<P> Pyramid<P>
+where
+    P: Pixel<Subpixel = f32> + 'static,
+{
+    /// Reconstructs the image that a [Laplacian pyramid](fn.laplacian_pyramid.html) was created
+    /// from, by repeatedly upsampling the reconstruction of the smaller levels and adding the
+    /// detail stored in the next level up. The result is exact up to rounding errors.
+    ///
+    /// The result is only meaningful if this pyramid was returned by `laplacian_pyramid`.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate image;
+    /// # #[macro_use]
+    /// # extern crate imageproc;
+    /// # fn main() {
+    /// use imageproc::pyramid::{laplacian_pyramid, PyramidOptions};
+    ///
+    /// let image = gray_image!(
+    ///     1, 2, 3, 4;
+    ///     5, 6, 7, 8;
+    ///     9, 10, 11, 12);
+    ///
+    /// let pyramid = laplacian_pyramid(&image, PyramidOptions::default()).unwrap();
+    /// let reconstructed = pyramid.reconstruct();
+    ///
+    /// for (x, y, p) in image.enumerate_pixels() {
+    ///     assert!((reconstructed.get_pixel(x, y)[0] - p[0] as f32).abs() < 1e-4);
+    /// }
+    /// # }
+    /// ```
+    pub fn reconstruct(&self) -> Image<P> {
+        let (smallest, larger) = self.levels.split_last().unwrap();
+        let mut image = smallest.clone();
+        for detail in larger.iter().rev() {
+            let (width, height) = detail.dimensions();
+            image = resample(&image, width, height);
+            for (p, d) in image.iter_mut().zip(detail.iter()) {
+                *p += *d;
+            }
+        }
+        image
+    }
+}
+
+/// Returns a Gaussian pyramid for an image. Each level is created by blurring the level
+/// above with a Gaussian of standard deviation `options.sigma` and resampling it using
+/// bilinear interpolation.
+///
+/// Level `i` has the size of `image` multiplied by `options.scale_factor` to the power `i`,
+/// rounded to the nearest pixel. Levels are added until the pyramid has `options.max_levels`
+/// levels, the next level would be narrower or shorter than `options.min_size`, or rounding
+/// would make the next level the same size as the current one.
+///
+/// Returns `ImageProcError::EmptyImage` if `image` is empty, and
+/// `ImageProcError::InvalidOptions` if any option is invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::GrayImage;
+/// use imageproc::pyramid::{gaussian_pyramid, PyramidOptions};
+///
+/// let image = GrayImage::new(40, 25);
+/// let options = PyramidOptions { min_size: 4, ..PyramidOptions::default() };
+/// let pyramid = gaussian_pyramid(&image, options).unwrap();
+///
+/// let sizes: Vec<(u32, u32)> = pyramid.levels().iter().map(|l| l.dimensions()).collect();
+/// assert_eq!(sizes, vec![(40, 25), (20, 13), (10, 6)]);
+/// assert_eq!(pyramid.octave(2), 2.0);
+/// # }
+/// ```
+pub fn gaussian_pyramid<P>(image: &Image<P>, options: PyramidOptions) -> Result<Pyramid<P>>
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    check_non_empty(image.dimensions())?;
+    options.validate()?;
+
+    let kernel = normalized_gaussian_kernel(options.sigma);
+    let (width, height) = image.dimensions();
+    let mut levels = vec![image.clone()];
+    while levels.len() < options.max_levels as usize {
+        let scale = options.scale_factor.powi(levels.len() as i32);
+        let level_width = (width as f32 * scale).round() as u32;
+        let level_height = (height as f32 * scale).round() as u32;
+        let previous = levels.last().unwrap();
+        if level_width < options.min_size || level_height < options.min_size
+            || (level_width, level_height) == previous.dimensions() {
+            break;
+        }
+        let blurred = separable_filter_equal(previous, &kernel);
+        let level = resample(&blurred, level_width, level_height);
+        levels.push(level);
+    }
+
+    Ok(Pyramid { levels, scale_factor: options.scale_factor })
+}
+
+/// Returns a Laplacian pyramid for an image. Level `i` is the difference between level `i`
+/// of the [Gaussian pyramid](fn.gaussian_pyramid.html) for `image` and level `i + 1`
+/// upsampled to the same size, except for the smallest level which is equal to the smallest
+/// level of the Gaussian pyramid. Levels have `f32` channels so that they can store
+/// negative values.
+///
+/// The original image can be recovered using [`Pyramid::reconstruct`](struct.Pyramid.html#method.reconstruct).
+///
+/// Returns `ImageProcError::EmptyImage` if `image` is empty, and
+/// `ImageProcError::InvalidOptions` if any option is invalid.
+pub fn laplacian_pyramid<P>(image: &Image<P>, options: PyramidOptions) -> Result<Pyramid<ChannelMap<P, f32>>>
+where
+    P: WithChannel<f32> + MaybeSync + 'static,
+    P::Subpixel: ValueInto<f32> + MaybeSync,
+{
+    let image: Image<ChannelMap<P, f32>> = map_subpixels(image, |c| cast(c));
+    let mut levels = gaussian_pyramid(&image, options)?.into_levels();
+
+    for i in 0..levels.len() - 1 {
+        let (width, height) = levels[i].dimensions();
+        let upsampled = resample(&levels[i + 1], width, height);
+        for (p, u) in levels[i].iter_mut().zip(upsampled.iter()) {
+            *p -= *u;
+        }
+    }
+
+    Ok(Pyramid { levels, scale_factor: options.scale_factor })
+}
+
+/// A Gaussian kernel of radius `ceil(2 * sigma)`, scaled to sum to 1 so that
+/// repeated blurring does not change the brightness of an image.
+pub(crate) fn normalized_gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (2.0 * sigma).ceil() as i32;
+    let kernel: Vec<f32> = (-radius..radius + 1)
+        .map(|x| (-(x * x) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter().map(|k| k / sum).collect()
+}
+
+/// Resizes an image using bilinear interpolation. Pixel centres are aligned so that
+/// the input and output cover the same region, and the image is padded by continuity.
+fn resample<P>(image: &Image<P>, width: u32, height: u32) -> Image<P>
+where
+    P: Pixel + 'static,
+    <P as Pixel>::Subpixel: ValueInto<f32> + Clamp<f32>,
+{
+    let (in_width, in_height) = image.dimensions();
+    // Position in the input of each output row or column, as the index of
+    // the preceding input row or column and the weight of the next one.
+    let sample_positions = |in_len: u32, out_len: u32| -> Vec<(u32, u32, f32)> {
+        let ratio = in_len as f32 / out_len as f32;
+        (0..out_len)
+            .map(|i| {
+                let position = ((i as f32 + 0.5) * ratio - 0.5).max(0.0).min((in_len - 1) as f32);
+                let before = position.floor() as u32;
+                let after = (before + 1).min(in_len - 1);
+                (before, after, position - before as f32)
+            })
+            .collect()
+    };
+    let xs = sample_positions(in_width, width);
+    let ys = sample_positions(in_height, height);
+
+    let mut out: Image<P> = ImageBuffer::new(width, height);
+    for (y, &(top, bottom, bottom_weight)) in ys.iter().enumerate() {
+        for (x, &(left, right, right_weight)) in xs.iter().enumerate() {
+            let (tl, tr, bl, br) = unsafe {
+                (
+                    image.unsafe_get_pixel(left, top),
+                    image.unsafe_get_pixel(right, top),
+                    image.unsafe_get_pixel(left, bottom),
+                    image.unsafe_get_pixel(right, bottom),
+                )
+            };
+            let out_channels = out.get_pixel_mut(x as u32, y as u32).channels_mut();
+            for c in 0..out_channels.len() {
+                let upper = (1.0 - right_weight) * cast(tl.channels()[c]) + right_weight * cast(tr.channels()[c]);
+                let lower = (1.0 - right_weight) * cast(bl.channels()[c]) + right_weight * cast(br.channels()[c]);
+                out_channels[c] = P::Subpixel::clamp((1.0 - bottom_weight) * upper + bottom_weight * lower);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+    use utils::{gray_bench_image, rgb_bench_image};
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_gaussian_pyramid_sizes() {
+        let image = GrayImage::new(17, 9);
+        let sizes = |options| -> Vec<(u32, u32)> {
+            gaussian_pyramid(&image, options).unwrap().levels().iter().map(|l| l.dimensions()).collect()
+        };
+        // 9 * 0.125 and 9 * 0.0625 both round to 1, but 9 * 0.03125 rounds to 0.
+        assert_eq!(sizes(PyramidOptions::default()), vec![(17, 9), (9, 5), (4, 2), (2, 1), (1, 1)]);
+        assert_eq!(sizes(PyramidOptions { max_levels: 2, ..PyramidOptions::default() }), vec![(17, 9), (9, 5)]);
+        assert_eq!(sizes(PyramidOptions { min_size: 3, ..PyramidOptions::default() }), vec![(17, 9), (9, 5)]);
+        // Levels stop once rounding stops reducing their size. Both 17 * 0.9^11 and 17 * 0.9^12
+        // round to 5, and both 9 * 0.9^11 and 9 * 0.9^12 round to 3.
+        let options = PyramidOptions { scale_factor: 0.9, ..PyramidOptions::default() };
+        assert_eq!(sizes(options).len(), 12);
+        assert_eq!(sizes(options).last(), Some(&(5, 3)));
+    }
+
+    #[test]
+    fn test_gaussian_pyramid_constant_image() {
+        let image = RgbImage::from_pixel(20, 12, Rgb([10, 200, 35]));
+        let options = PyramidOptions { scale_factor: 0.7, ..PyramidOptions::default() };
+        let pyramid = gaussian_pyramid(&image, options).unwrap();
+        assert!(pyramid.num_levels() > 3);
+        for level in pyramid.levels() {
+            assert!(level.pixels().all(|p| *p == Rgb([10, 200, 35])));
+        }
+    }
+
+    #[test]
+    fn test_gaussian_pyramid_first_level_is_input() {
+        let image = gray_bench_image(10, 8);
+        let pyramid = gaussian_pyramid(&image, PyramidOptions::default()).unwrap();
+        assert_pixels_eq!(*pyramid.level(0).unwrap(), image);
+        assert!(pyramid.level(pyramid.num_levels()).is_none());
+    }
+
+    #[test]
+    fn test_gaussian_pyramid_errors() {
+        let options = PyramidOptions { max_levels: 0, scale_factor: 1.0, sigma: 0.0, min_size: 0 };
+        assert_eq!(
+            gaussian_pyramid(&GrayImage::new(4, 4), options).err(),
+            Some(ImageProcError::InvalidOptions(
+                "max_levels must be at least 1, scale_factor must be greater than 0 and less than 1, \
+                 sigma must be positive, min_size must be at least 1".to_owned()
+            ))
+        );
+        assert_eq!(
+            gaussian_pyramid(&GrayImage::new(0, 4), PyramidOptions::default()).err(),
+            Some(ImageProcError::EmptyImage)
+        );
+    }
+
+    #[test]
+    fn test_pyramid_scales() {
+        let image = GrayImage::new(64, 64);
+        let options = PyramidOptions { scale_factor: 0.25f32.sqrt().sqrt(), ..PyramidOptions::default() };
+        let pyramid = gaussian_pyramid(&image, options).unwrap();
+        assert!((pyramid.levels_per_octave() - 2.0).abs() < 1e-5);
+        assert!((pyramid.scale(4) - 0.25).abs() < 1e-5);
+        assert!((pyramid.octave(3) - 1.5).abs() < 1e-5);
+        assert_eq!(pyramid.level(2).unwrap().dimensions(), (32, 32));
+    }
+
+    #[test]
+    fn test_resample() {
+        let image = gray_image!(
+            0, 10;
+            20, 30);
+        // Upsampled pixel centres lie a quarter of an input pixel from the nearest input
+        // pixel centre. Results are truncated when converting back to u8.
+        let expected = gray_image!(
+             0,  2,  7, 10;
+             5,  7, 12, 15;
+            15, 17, 22, 25;
+            20, 22, 27, 30);
+        assert_pixels_eq!(resample(&image, 4, 4), expected);
+        assert_pixels_eq!(resample(&expected, 2, 2), gray_image!(3, 11; 18, 26));
+    }
+
+    #[test]
+    fn test_laplacian_pyramid_reconstruct() {
+        let image = rgb_bench_image(23, 14);
+        let options = PyramidOptions { scale_factor: 0.6, ..PyramidOptions::default() };
+        let pyramid = laplacian_pyramid(&image, options).unwrap();
+        assert_eq!(pyramid.num_levels(), gaussian_pyramid(&image, options).unwrap().num_levels());
+
+        let reconstructed = pyramid.reconstruct();
+        for (x, y, p) in image.enumerate_pixels() {
+            let q = reconstructed.get_pixel(x, y);
+            for c in 0..3 {
+                assert!((q[c] - p[c] as f32).abs() < 1e-3, "({}, {}): {:?} != {:?}", x, y, q, p);
+            }
+        }
+    }
+
+    #[test]
+    fn test_laplacian_pyramid_smallest_level() {
+        let image = gray_bench_image(16, 16);
+        let options = PyramidOptions { max_levels: 3, ..PyramidOptions::default() };
+        let laplacian = laplacian_pyramid(&image, options).unwrap();
+        let gaussian = gaussian_pyramid(&map_subpixels(&image, |c| c as f32), options).unwrap();
+        assert_pixels_eq!(*laplacian.level(2).unwrap(), *gaussian.level(2).unwrap());
+        // Detail levels of a constant image are zero.
+        let constant = laplacian_pyramid(&GrayImage::from_pixel(16, 16, Luma([7])), options).unwrap();
+        assert!(constant.level(0).unwrap().pixels().all(|p| p[0].abs() < 1e-5));
+        assert!(constant.level(2).unwrap().pixels().all(|p| (p[0] - 7.0).abs() < 1e-5));
+    }
+
+    #[bench]
+    fn bench_gaussian_pyramid(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| black_box(gaussian_pyramid(&image, PyramidOptions::default()).unwrap()));
+    }
+
+    #[bench]
+    fn bench_laplacian_pyramid_reconstruct(b: &mut Bencher) {
+        let pyramid = laplacian_pyramid(&gray_bench_image(500, 500), PyramidOptions::default()).unwrap();
+        b.iter(|| black_box(pyramid.reconstruct()));
+    }
+}
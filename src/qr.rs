@@ -0,0 +1,441 @@
+//! Localization of [QR code] finder patterns.
+//!
+//! Each QR code has three finder patterns, at its top-left, top-right and bottom-left
+//! corners. A finder pattern is a dark 7x7 square of modules, containing a light 5x5 square,
+//! containing a dark 3x3 square, so any line through its centre crosses runs of dark, light,
+//! dark, light and dark pixels with lengths in the ratio 1:1:3:1:1.
+//!
+//! [`find_finder_patterns`](fn.find_finder_patterns.html) locates finder patterns, and
+//! [`group_finder_patterns`](fn.group_finder_patterns.html) groups them into candidate codes
+//! with known orientations. Decoding the codes is out of scope for this crate.
+//!
+//! [QR code]: https://en.wikipedia.org/wiki/QR_code
+
+use image::GrayImage;
+
+/// A QR code finder pattern.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FinderPattern {
+    /// The centre of the pattern, in pixel coordinates.
+    pub center: (f32, f32),
+    /// The estimated width of a single module of the code, in pixels.
+    pub module_size: f32,
+    /// The number of scanlines on which the pattern was detected. Larger values indicate
+    /// more reliable detections.
+    pub count: u32,
+}
+
+/// Three finder patterns that are positioned relative to each other as in a QR code.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QrCandidate {
+    /// The finder pattern at the top-left corner of the code.
+    pub top_left: FinderPattern,
+    /// The finder pattern at the top-right corner of the code.
+    pub top_right: FinderPattern,
+    /// The finder pattern at the bottom-left corner of the code.
+    pub bottom_left: FinderPattern,
+}
+
+impl QrCandidate {
+    /// The angle in radians by which the code is rotated clockwise, in the range (-pi, pi].
+    /// This is the direction from the top-left to the top-right finder pattern.
+    pub fn angle(&self) -> f32 {
+        let (x, y) = sub(self.top_right.center, self.top_left.center);
+        y.atan2(x)
+    }
+
+    /// The estimated number of modules along each side of the code.
+    pub fn dimension(&self) -> u32 {
+        let module = (self.top_left.module_size + self.top_right.module_size + self.bottom_left.module_size) / 3.0;
+        let across = norm(sub(self.top_right.center, self.top_left.center));
+        let down = norm(sub(self.bottom_left.center, self.top_left.center));
+        // The distance between finder pattern centres is the dimension less 7 modules.
+        ((across + down) / (2.0 * module) + 7.0).round() as u32
+    }
+}
+
+/// Returns the QR code finder patterns in a binarized image, in decreasing order of
+/// [`count`](struct.FinderPattern.html#structfield.count).
+///
+/// Pixels with intensity less than 128 are treated as dark. Each row is scanned for runs of
+/// dark and light pixels with lengths in the ratio 1:1:3:1:1. Each match is cross-checked
+/// along the column through its centre, and then along the row through the refined centre.
+/// Matches that agree on the position and size of a pattern are merged, and patterns that
+/// are only matched on a single row are discarded.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::qr::find_finder_patterns;
+///
+/// // A finder pattern with 3 pixel modules, on a light background.
+/// let image = GrayImage::from_fn(33, 33, |x, y| {
+///     let module = |p: u32| (p as i32 - 6) / 3;
+///     let (mx, my) = (module(x), module(y));
+///     let ring = if x < 6 || y < 6 || mx > 6 || my > 6 { -1 } else { mx.min(my).min(6 - mx).min(6 - my) };
+///     Luma([if ring == 0 || ring == 2 || ring == 3 { 0 } else { 255 }])
+/// });
+///
+/// let patterns = find_finder_patterns(&image);
+/// assert_eq!(patterns.len(), 1);
+/// assert_eq!(patterns[0].center, (16.0, 16.0));
+/// assert_eq!(patterns[0].module_size, 3.0);
+/// # }
+/// ```
+pub fn find_finder_patterns(image: &GrayImage) -> Vec<FinderPattern> {
+    let mut patterns: Vec<FinderPattern> = vec![];
+    let mut runs = vec![];
+
+    for y in 0..image.height() {
+        row_runs(image, y, &mut runs);
+        if runs.len() < 5 {
+            continue;
+        }
+        for i in 0..runs.len() - 4 {
+            if !runs[i].dark {
+                continue;
+            }
+            let counts = [runs[i].len, runs[i + 1].len, runs[i + 2].len, runs[i + 3].len, runs[i + 4].len];
+            if !has_finder_ratios(&counts) {
+                continue;
+            }
+            let total: u32 = counts.iter().sum();
+            let x = runs[i + 2].start + runs[i + 2].len / 2;
+
+            let vertical = match cross_check(image, (x, y), (0, 1), total) {
+                Some(v) => v,
+                None => continue,
+            };
+            let cy = y as f32 + vertical.0;
+            let horizontal = match cross_check(image, (x, cy as u32), (1, 0), total) {
+                Some(h) => h,
+                None => continue,
+            };
+            let cx = x as f32 + horizontal.0;
+            let module_size = (vertical.1 + horizontal.1) as f32 / 14.0;
+            add_candidate(&mut patterns, (cx, cy), module_size);
+        }
+    }
+
+    patterns.retain(|p| p.count > 1);
+    patterns.sort_by(|a, b| b.count.cmp(&a.count));
+    patterns
+}
+
+/// Groups finder patterns into candidate QR codes.
+///
+/// A triple of finder patterns is a candidate if the patterns have similar module sizes,
+/// and form a right isosceles triangle with legs at least 7 modules long. The pattern at
+/// the right angle is the top-left of the code, and the other two are assigned so that
+/// the code reads clockwise from top-left to top-right to bottom-left. Candidates are
+/// chosen greedily, best fitting first, so that each pattern is used at most once.
+///
+/// # Examples
+/// ```
+/// use imageproc::qr::{group_finder_patterns, FinderPattern};
+///
+/// let pattern = |x, y| FinderPattern { center: (x, y), module_size: 2.0, count: 3 };
+///
+/// // A code rotated by 90 degrees clockwise, so its top-left corner is at the top right.
+/// let patterns = [pattern(10.0, 10.0), pattern(40.0, 40.0), pattern(40.0, 10.0)];
+/// let codes = group_finder_patterns(&patterns);
+///
+/// assert_eq!(codes.len(), 1);
+/// assert_eq!(codes[0].top_left.center, (40.0, 10.0));
+/// assert_eq!(codes[0].top_right.center, (40.0, 40.0));
+/// assert_eq!(codes[0].bottom_left.center, (10.0, 10.0));
+/// assert_eq!(codes[0].dimension(), 22);
+/// assert!((codes[0].angle() - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+/// ```
+pub fn group_finder_patterns(patterns: &[FinderPattern]) -> Vec<QrCandidate> {
+    let n = patterns.len();
+    let mut scored = vec![];
+    for i in 0..n {
+        for j in i + 1..n {
+            for k in j + 1..n {
+                if let Some(scored_candidate) = fit_candidate(patterns, [i, j, k]) {
+                    scored.push(scored_candidate);
+                }
+            }
+        }
+    }
+    scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut used = vec![false; n];
+    let mut candidates = vec![];
+    for (_, indices, candidate) in scored {
+        if indices.iter().any(|&i| used[i]) {
+            continue;
+        }
+        for &i in &indices {
+            used[i] = true;
+        }
+        candidates.push(candidate);
+    }
+    candidates
+}
+
+/// Returns a measure of how far the given patterns are from forming a QR code, with
+/// smaller values fitting better, along with their indices and the resulting candidate.
+fn fit_candidate(patterns: &[FinderPattern], indices: [usize; 3]) -> Option<(f32, [usize; 3], QrCandidate)> {
+    let p = [patterns[indices[0]], patterns[indices[1]], patterns[indices[2]]];
+    let sizes = [p[0].module_size, p[1].module_size, p[2].module_size];
+    let smallest = sizes.iter().fold(::std::f32::INFINITY, |a, &b| a.min(b));
+    let largest = sizes.iter().fold(0f32, |a, &b| a.max(b));
+    if largest > 1.5 * smallest {
+        return None;
+    }
+
+    // The top-left pattern is opposite the longest side.
+    let side = |a: usize, b: usize| norm(sub(p[a].center, p[b].center));
+    let opposite = [side(1, 2), side(0, 2), side(0, 1)];
+    let corner = (0..3)
+        .fold(0, |best, i| if opposite[i] > opposite[best] { i } else { best });
+    let (a, b) = ((corner + 1) % 3, (corner + 2) % 3);
+    let (leg_a, leg_b) = (opposite[b], opposite[a]);
+    let hypotenuse = opposite[corner];
+
+    let module = (sizes[0] + sizes[1] + sizes[2]) / 3.0;
+    if leg_a.min(leg_b) < 7.0 * module {
+        return None;
+    }
+    let leg_error = (leg_a - leg_b).abs() / leg_a.max(leg_b);
+    let angle_error = (hypotenuse / (leg_a * leg_a + leg_b * leg_b).sqrt() - 1.0).abs();
+    if leg_error > 0.2 || angle_error > 0.1 {
+        return None;
+    }
+
+    let (ua, ub) = (sub(p[a].center, p[corner].center), sub(p[b].center, p[corner].center));
+    let (top_right, bottom_left) = if ua.0 * ub.1 - ua.1 * ub.0 > 0.0 { (a, b) } else { (b, a) };
+    let candidate = QrCandidate {
+        top_left: p[corner],
+        top_right: p[top_right],
+        bottom_left: p[bottom_left],
+    };
+    Some((leg_error + angle_error, indices, candidate))
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn norm(v: (f32, f32)) -> f32 {
+    (v.0 * v.0 + v.1 * v.1).sqrt()
+}
+
+fn is_dark(intensity: u8) -> bool {
+    intensity < 128
+}
+
+/// A maximal run of dark or light pixels in a row.
+struct Run {
+    start: u32,
+    len: u32,
+    dark: bool,
+}
+
+fn row_runs(image: &GrayImage, y: u32, runs: &mut Vec<Run>) {
+    runs.clear();
+    for x in 0..image.width() {
+        let dark = is_dark(image.get_pixel(x, y)[0]);
+        let extend = match runs.last() {
+            Some(run) => run.dark == dark,
+            None => false,
+        };
+        if extend {
+            runs.last_mut().unwrap().len += 1;
+        } else {
+            runs.push(Run { start: x, len: 1, dark });
+        }
+    }
+}
+
+/// Whether five run lengths are within tolerance of the ratio 1:1:3:1:1.
+fn has_finder_ratios(counts: &[u32; 5]) -> bool {
+    let total: u32 = counts.iter().sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f32 / 7.0;
+    let tolerance = module / 2.0;
+    let expected = [1.0, 1.0, 3.0, 1.0, 1.0];
+    counts
+        .iter()
+        .zip(expected.iter())
+        .all(|(&c, &e)| (c as f32 - e * module).abs() < e * tolerance)
+}
+
+/// Checks for a finder pattern along the line through `start` in direction `step`, whose
+/// central dark run contains `start`, and with total length similar to `expected_total`.
+/// Returns the offset of the centre of the pattern from `start` along this line, and the
+/// total length of the pattern.
+fn cross_check(image: &GrayImage, start: (u32, u32), step: (i32, i32), expected_total: u32) -> Option<(f32, u32)> {
+    let (width, height) = image.dimensions();
+    let dark_at = |k: i32| -> Option<bool> {
+        let (x, y) = (start.0 as i64 + (k * step.0) as i64, start.1 as i64 + (k * step.1) as i64);
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            None
+        } else {
+            Some(is_dark(image.get_pixel(x as u32, y as u32)[0]))
+        }
+    };
+
+    let mut counts = [0u32; 5];
+    // Walk backwards through the central dark run and the two runs before it, and then
+    // forwards through the rest of the central run and the two runs after it.
+    let mut k = 0;
+    for &(index, dark) in &[(2, true), (1, false), (0, true)] {
+        while dark_at(k) == Some(dark) {
+            counts[index] += 1;
+            k -= 1;
+        }
+    }
+    let mut k = 1;
+    for &(index, dark) in &[(2, true), (3, false), (4, true)] {
+        while dark_at(k) == Some(dark) {
+            counts[index] += 1;
+            k += 1;
+        }
+    }
+
+    let total: u32 = counts.iter().sum();
+    let difference = (total as i64 - expected_total as i64).abs();
+    if 5 * difference >= 2 * expected_total as i64 || !has_finder_ratios(&counts) {
+        return None;
+    }
+    // k is the first position after the pattern. The centre of the central run is at
+    // a half-integer offset from it, and pixel centres are at integer offsets.
+    let center = k as f32 - counts[4] as f32 - counts[3] as f32 - counts[2] as f32 / 2.0 - 0.5;
+    Some((center, total))
+}
+
+/// Merges a detection into an existing pattern with a similar position and size, or adds
+/// it as a new pattern.
+fn add_candidate(patterns: &mut Vec<FinderPattern>, center: (f32, f32), module_size: f32) {
+    for p in patterns.iter_mut() {
+        let close = (p.center.0 - center.0).abs() <= p.module_size
+            && (p.center.1 - center.1).abs() <= p.module_size;
+        let similar = (p.module_size - module_size).abs() <= 1.0 || (p.module_size - module_size).abs() <= 0.2 * p.module_size;
+        if close && similar {
+            let n = p.count as f32;
+            p.center = (
+                (n * p.center.0 + center.0) / (n + 1.0),
+                (n * p.center.1 + center.1) / (n + 1.0),
+            );
+            p.module_size = (n * p.module_size + module_size) / (n + 1.0);
+            p.count += 1;
+            return;
+        }
+    }
+    patterns.push(FinderPattern { center, module_size, count: 1 });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Luma;
+    use geometric_transformations::rotate90;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+    use std::f32;
+
+    /// A 21x21 module code with the given module size and a 4 module quiet zone, containing
+    /// only its finder patterns and a diagonal stripe of dark modules between them.
+    fn synthetic_code(module_size: u32) -> GrayImage {
+        let side = 29 * module_size;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (mx, my) = ((x / module_size) as i32 - 4, (y / module_size) as i32 - 4);
+            if mx < 0 || my < 0 || mx > 20 || my > 20 {
+                return Luma([255]);
+            }
+            let in_finder = |ox: i32, oy: i32| -> Option<bool> {
+                let (fx, fy) = (mx - ox, my - oy);
+                if fx < 0 || fy < 0 || fx > 6 || fy > 6 {
+                    return None;
+                }
+                let ring = fx.min(fy).min(6 - fx).min(6 - fy);
+                Some(ring != 1)
+            };
+            let dark = in_finder(0, 0)
+                .or_else(|| in_finder(14, 0))
+                .or_else(|| in_finder(0, 14))
+                .unwrap_or(mx >= 8 && mx <= 12 && (mx + my) % 2 == 0);
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn test_find_finder_patterns_in_synthetic_code() {
+        for &module_size in &[1, 2, 3, 5] {
+            let image = synthetic_code(module_size);
+            let mut patterns = find_finder_patterns(&image);
+            assert_eq!(patterns.len(), 3, "module size {}", module_size);
+
+            patterns.sort_by(|a, b| (a.center.1, a.center.0).partial_cmp(&(b.center.1, b.center.0)).unwrap());
+            let expected = |mx: f32, my: f32| {
+                let m = module_size as f32;
+                ((mx + 3.5) * m - 0.5, (my + 3.5) * m - 0.5)
+            };
+            let centers: Vec<(f32, f32)> = patterns.iter().map(|p| p.center).collect();
+            assert_eq!(centers, vec![expected(4.0, 4.0), expected(18.0, 4.0), expected(4.0, 18.0)]);
+            for p in &patterns {
+                assert_eq!(p.module_size, module_size as f32);
+                assert!(p.count >= 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_finder_patterns_orientation() {
+        let image = synthetic_code(3);
+        let codes = group_finder_patterns(&find_finder_patterns(&image));
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].top_left.center, (22.0, 22.0));
+        assert_eq!(codes[0].dimension(), 21);
+        assert_eq!(codes[0].angle(), 0.0);
+
+        let rotated = rotate90(&image);
+        let codes = group_finder_patterns(&find_finder_patterns(&rotated));
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].top_left.center, (64.0, 22.0));
+        assert_eq!(codes[0].top_right.center, (64.0, 64.0));
+        assert!((codes[0].angle() - f32::consts::FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_group_finder_patterns_rejects_poor_fits() {
+        let pattern = |x, y, module_size| FinderPattern { center: (x, y), module_size, count: 2 };
+        // Collinear.
+        assert!(group_finder_patterns(&[pattern(0.0, 0.0, 1.0), pattern(20.0, 0.0, 1.0), pattern(40.0, 0.0, 1.0)]).is_empty());
+        // Too close together for their module size.
+        assert!(group_finder_patterns(&[pattern(0.0, 0.0, 4.0), pattern(20.0, 0.0, 4.0), pattern(0.0, 20.0, 4.0)]).is_empty());
+        // Mismatched module sizes.
+        assert!(group_finder_patterns(&[pattern(0.0, 0.0, 1.0), pattern(20.0, 0.0, 1.0), pattern(0.0, 20.0, 2.0)]).is_empty());
+        // Two codes sharing no patterns, plus an extra pattern.
+        let patterns = [
+            pattern(0.0, 0.0, 1.0), pattern(20.0, 0.0, 1.0), pattern(0.0, 20.0, 1.0),
+            pattern(100.0, 0.0, 1.0), pattern(130.0, 0.0, 1.0), pattern(100.0, 30.0, 1.0),
+            pattern(75.0, 90.0, 1.0),
+        ];
+        assert_eq!(group_finder_patterns(&patterns).len(), 2);
+    }
+
+    #[test]
+    fn test_find_finder_patterns_ignores_noise() {
+        let image = gray_bench_image(60, 60);
+        let binary = GrayImage::from_fn(60, 60, |x, y| Luma([if image.get_pixel(x, y)[0] < 128 { 0 } else { 255 }]));
+        for p in find_finder_patterns(&binary) {
+            assert!(p.count >= 2);
+        }
+        assert!(find_finder_patterns(&GrayImage::new(0, 0)).is_empty());
+    }
+
+    #[bench]
+    fn bench_find_finder_patterns(b: &mut Bencher) {
+        let image = synthetic_code(6);
+        b.iter(|| black_box(find_finder_patterns(&image)));
+    }
+}
@@ -0,0 +1,287 @@
+//! Functions for reducing the number of colors in an image.
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use bag_of_words::{build_vocabulary, Initialization, KMeansOptions, Vocabulary};
+use error::{ImageProcError, Result};
+
+/// How pixels are mapped to palette colors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dithering {
+    /// Each pixel is mapped to its nearest palette color.
+    None,
+    /// Each pixel is mapped to the palette color nearest to its value plus the error diffused
+    /// from previously mapped pixels, using the
+    /// [Floyd-Steinberg](https://en.wikipedia.org/wiki/Floyd%E2%80%93Steinberg_dithering)
+    /// weights. This avoids banding in smooth gradients, at the cost of adding noise.
+    FloydSteinberg,
+}
+
+/// An image whose pixels are indices into a palette of colors.
+#[derive(Clone, Debug)]
+pub struct Quantized {
+    /// The index in `palette` of the color of each pixel.
+    pub indices: GrayImage,
+    /// The colors used by the image.
+    pub palette: Vec<Rgb<u8>>,
+}
+
+impl Quantized {
+    /// Returns the image with each index replaced by its palette color.
+    pub fn to_rgb(&self) -> RgbImage {
+        RgbImage::from_fn(self.indices.width(), self.indices.height(), |x, y| {
+            self.palette[self.indices.get_pixel(x, y)[0] as usize]
+        })
+    }
+}
+
+fn to_f32(p: Rgb<u8>) -> [f32; 3] {
+    [p[0] as f32, p[1] as f32, p[2] as f32]
+}
+
+fn to_u8(c: [f32; 3]) -> Rgb<u8> {
+    let channel = |v: f32| v.round().max(0.0).min(255.0) as u8;
+    Rgb([channel(c[0]), channel(c[1]), channel(c[2])])
+}
+
+/// Reduces an image to at most `k` colors, chosen by k-means clustering of its pixels in RGB
+/// space. The palette is ordered by decreasing number of pixels, so `palette[0]` is the most
+/// common color in the quantized image. This makes `kmeans` suitable for extracting the
+/// dominant colors of an image.
+///
+/// Clustering is initialised using k-means++ and runs for at most `max_iterations` iterations.
+/// The result depends only on the inputs and `seed`. If the image has fewer than `k` pixels
+/// then the palette has one entry per pixel, and the palette may contain repeated colors if
+/// the image has fewer than `k` distinct colors.
+///
+/// Returns `ImageProcError::EmptyImage` if the image is empty, and
+/// `ImageProcError::InvalidOptions` if `k` is not in the range [1, 256].
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::quantize::{kmeans, Dithering};
+///
+/// let image = rgb_image!(
+///     [250, 0, 0], [255, 5, 0], [0, 0, 200];
+///     [255, 0, 5], [250, 5, 5], [0, 0, 210]);
+///
+/// let quantized = kmeans(&image, 2, 10, 1, Dithering::None).unwrap();
+///
+/// // Red is the dominant color.
+/// assert_eq!(quantized.palette, vec![Rgb([253, 3, 3]), Rgb([0, 0, 205])]);
+/// assert_pixels_eq!(
+///     quantized.indices,
+///     gray_image!(
+///         0, 0, 1;
+///         0, 0, 1));
+/// # }
+/// ```
+pub fn kmeans(image: &RgbImage, k: usize, max_iterations: usize, seed: usize, dithering: Dithering) -> Result<Quantized> {
+    if image.width() == 0 || image.height() == 0 {
+        return Err(ImageProcError::EmptyImage);
+    }
+    if k == 0 || k > 256 {
+        return Err(ImageProcError::InvalidOptions(format!("k must be in the range [1, 256], got {}", k)));
+    }
+
+    let pixels: Vec<[f32; 3]> = image.pixels().map(|p| to_f32(*p)).collect();
+    let options = KMeansOptions {
+        num_words: k.min(pixels.len()),
+        max_iterations,
+        initialization: Initialization::PlusPlus,
+        batch_size: None,
+    };
+    let vocabulary = build_vocabulary(&pixels, options, seed)?;
+    let palette: Vec<Rgb<u8>> = (0..vocabulary.num_words())
+        .map(|i| {
+            let w = vocabulary.word(i);
+            to_u8([w[0], w[1], w[2]])
+        })
+        .collect();
+
+    let indices = apply_palette(image, &palette, dithering);
+
+    // Order the palette by decreasing frequency, breaking ties by original index.
+    let mut counts = vec![0usize; palette.len()];
+    for p in indices.pixels() {
+        counts[p[0] as usize] += 1;
+    }
+    let mut order: Vec<usize> = (0..palette.len()).collect();
+    order.sort_by(|&a, &b| counts[b].cmp(&counts[a]).then(a.cmp(&b)));
+    let mut new_index = vec![0u8; palette.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new as u8;
+    }
+
+    Ok(Quantized {
+        indices: GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            Luma([new_index[indices.get_pixel(x, y)[0] as usize]])
+        }),
+        palette: order.iter().map(|&i| palette[i]).collect(),
+    })
+}
+
+/// Maps each pixel of an image to the index of a color in `palette`, choosing the nearest
+/// color in RGB space. Ties are resolved in favour of the color with the smaller index.
+///
+/// # Panics
+///
+/// If `palette` is empty or has more than 256 entries.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Rgb;
+/// use imageproc::quantize::{apply_palette, Dithering};
+///
+/// let image = rgb_image!([10, 10, 10], [200, 220, 250], [90, 90, 90]);
+/// let palette = [Rgb([0, 0, 0]), Rgb([255, 255, 255])];
+///
+/// assert_pixels_eq!(
+///     apply_palette(&image, &palette, Dithering::None),
+///     gray_image!(0, 1, 0));
+/// # }
+/// ```
+pub fn apply_palette(image: &RgbImage, palette: &[Rgb<u8>], dithering: Dithering) -> GrayImage {
+    assert!(!palette.is_empty() && palette.len() <= 256, "palette must have between 1 and 256 colors");
+    let colors: Vec<[f32; 3]> = palette.iter().map(|p| to_f32(*p)).collect();
+    let vocabulary = Vocabulary::new(&colors);
+    let (width, height) = image.dimensions();
+
+    match dithering {
+        Dithering::None => GrayImage::from_fn(width, height, |x, y| {
+            Luma([vocabulary.nearest_word(&to_f32(*image.get_pixel(x, y))).0 as u8])
+        }),
+        Dithering::FloydSteinberg => {
+            let mut out = GrayImage::new(width, height);
+            // Errors diffused to the current and next rows.
+            let mut current = vec![[0f32; 3]; width as usize + 2];
+            let mut next = vec![[0f32; 3]; width as usize + 2];
+            for y in 0..height {
+                for x in 0..width {
+                    let i = x as usize + 1;
+                    let p = to_f32(*image.get_pixel(x, y));
+                    let mut value = [0f32; 3];
+                    for c in 0..3 {
+                        value[c] = (p[c] + current[i][c]).max(0.0).min(255.0);
+                    }
+                    let nearest = vocabulary.nearest_word(&value).0;
+                    out.put_pixel(x, y, Luma([nearest as u8]));
+
+                    let chosen = colors[nearest];
+                    for c in 0..3 {
+                        let error = value[c] - chosen[c];
+                        current[i + 1][c] += error * 7.0 / 16.0;
+                        next[i - 1][c] += error * 3.0 / 16.0;
+                        next[i][c] += error * 5.0 / 16.0;
+                        next[i + 1][c] += error * 1.0 / 16.0;
+                    }
+                }
+                ::std::mem::swap(&mut current, &mut next);
+                for e in next.iter_mut() {
+                    *e = [0.0; 3];
+                }
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::rgb_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_kmeans_reproduces_images_with_few_colors() {
+        let colors = [Rgb([255u8, 0, 0]), Rgb([0u8, 255, 0]), Rgb([0u8, 0, 255])];
+        let image = RgbImage::from_fn(10, 10, |x, y| colors[((x + 2 * y) % 3) as usize]);
+        let quantized = kmeans(&image, 3, 20, 7, Dithering::None).unwrap();
+        assert_eq!(quantized.palette.len(), 3);
+        for c in &colors {
+            assert!(quantized.palette.contains(c));
+        }
+        assert_pixels_eq!(quantized.to_rgb(), image);
+    }
+
+    #[test]
+    fn test_kmeans_palette_ordered_by_frequency() {
+        let image = RgbImage::from_fn(10, 1, |x, _| if x < 3 { Rgb([0, 0, 0]) } else { Rgb([200, 200, 200]) });
+        let quantized = kmeans(&image, 2, 10, 1, Dithering::None).unwrap();
+        assert_eq!(quantized.palette, vec![Rgb([200, 200, 200]), Rgb([0, 0, 0])]);
+        assert_eq!(quantized.indices.get_pixel(0, 0)[0], 1);
+        assert_eq!(quantized.indices.get_pixel(9, 0)[0], 0);
+    }
+
+    #[test]
+    fn test_kmeans_is_deterministic() {
+        let image = rgb_bench_image(20, 20);
+        let first = kmeans(&image, 4, 10, 3, Dithering::FloydSteinberg).unwrap();
+        let second = kmeans(&image, 4, 10, 3, Dithering::FloydSteinberg).unwrap();
+        assert_eq!(first.palette, second.palette);
+        assert_pixels_eq!(first.indices, second.indices);
+    }
+
+    #[test]
+    fn test_kmeans_fewer_pixels_than_k() {
+        let image = rgb_image!([1, 2, 3], [4, 5, 6]);
+        let quantized = kmeans(&image, 5, 10, 1, Dithering::None).unwrap();
+        assert_eq!(quantized.palette.len(), 2);
+        assert_pixels_eq!(quantized.to_rgb(), image);
+    }
+
+    #[test]
+    fn test_kmeans_invalid_inputs() {
+        let image = rgb_bench_image(4, 4);
+        assert!(kmeans(&image, 0, 10, 1, Dithering::None).is_err());
+        assert!(kmeans(&image, 257, 10, 1, Dithering::None).is_err());
+        assert_eq!(kmeans(&RgbImage::new(0, 3), 2, 10, 1, Dithering::None).err(), Some(ImageProcError::EmptyImage));
+    }
+
+    #[test]
+    fn test_floyd_steinberg_preserves_mean_intensity() {
+        let image = RgbImage::from_pixel(16, 16, Rgb([64, 64, 64]));
+        let palette = [Rgb([0, 0, 0]), Rgb([255, 255, 255])];
+
+        let undithered = apply_palette(&image, &palette, Dithering::None);
+        assert!(undithered.pixels().all(|p| p[0] == 0));
+
+        let dithered = apply_palette(&image, &palette, Dithering::FloydSteinberg);
+        let white = dithered.pixels().filter(|p| p[0] == 1).count();
+        // A quarter of the pixels should be white.
+        assert!(white >= 56 && white <= 72, "{}", white);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_apply_palette_empty_palette() {
+        apply_palette(&rgb_bench_image(2, 2), &[], Dithering::None);
+    }
+
+    #[bench]
+    fn bench_kmeans(b: &mut Bencher) {
+        let image = rgb_bench_image(100, 100);
+        b.iter(|| {
+            let quantized = kmeans(&image, 8, 10, 1, Dithering::None).unwrap();
+            black_box(quantized);
+        });
+    }
+
+    #[bench]
+    fn bench_apply_palette_floyd_steinberg(b: &mut Bencher) {
+        let image = rgb_bench_image(200, 200);
+        let palette = [Rgb([0, 0, 0]), Rgb([255, 0, 0]), Rgb([0, 255, 0]), Rgb([255, 255, 255])];
+        b.iter(|| {
+            let indices = apply_palette(&image, &palette, Dithering::FloydSteinberg);
+            black_box(indices);
+        });
+    }
+}
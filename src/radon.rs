@@ -0,0 +1,332 @@
+//! Functions for computing the [Radon transform] of an image, and for reconstructing
+//! an image from its projections by filtered back-projection.
+//!
+//! A sinogram is stored as an image with one row per projection angle. Each row holds
+//! the sums of pixel intensities along the parallel lines `x cos(theta) + y sin(theta) = t`,
+//! where `x` and `y` are measured from the centre of the image and `theta` is measured
+//! clockwise from the x-axis, as in the [`hough`](../hough/index.html) module. Column `j`
+//! of a sinogram with width `n` corresponds to offset `t = j - (n - 1) / 2`.
+//!
+//! [Radon transform]: https://en.wikipedia.org/wiki/Radon_transform
+
+use image::{GenericImage, GenericImageView, Luma, Primitive};
+use conv::ValueInto;
+use definitions::Image;
+use std::f32;
+
+/// Returns `count` angles evenly spaced over [0, pi), in radians.
+pub fn uniform_angles(count: usize) -> Vec<f32> {
+    (0..count).map(|i| i as f32 * f32::consts::PI / count as f32).collect()
+}
+
+/// Returns the width of the sinogram of an image with the given dimensions.
+/// This is odd and large enough to hold the projection at every angle.
+pub fn sinogram_width(width: u32, height: u32) -> u32 {
+    let half_diagonal = 0.5 * ((width as f32).powi(2) + (height as f32).powi(2)).sqrt();
+    // One bin of slack on each side, as each pixel is split between two adjacent bins.
+    2 * (half_diagonal.ceil() as u32 + 1) + 1
+}
+
+/// Computes the Radon transform of an image at the given angles, in radians.
+///
+/// Returns a sinogram with one row per angle and width
+/// [`sinogram_width(image.width(), image.height())`](fn.sinogram_width.html).
+/// Each pixel's intensity is split between the two bins nearest its offset, so each row
+/// sums to the total intensity of the image.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::radon::radon_transform;
+/// use std::f32;
+///
+/// // A single bright pixel, one pixel right of the image centre.
+/// let image = gray_image!(
+///     0, 0, 0, 0, 0;
+///     0, 0, 0, 0, 0;
+///     0, 0, 0, 4, 0;
+///     0, 0, 0, 0, 0;
+///     0, 0, 0, 0, 0);
+///
+/// let sinogram = radon_transform(&image, &[0.0, f32::consts::FRAC_PI_2]);
+/// let centre = sinogram.width() / 2;
+///
+/// // Projecting onto the x-axis puts the pixel at offset 1,
+/// // and projecting onto the y-axis puts it at offset 0.
+/// assert_eq!(sinogram.get_pixel(centre + 1, 0)[0], 4.0);
+/// assert!((sinogram.get_pixel(centre, 1)[0] - 4.0).abs() < 1e-5);
+/// # }
+/// ```
+pub fn radon_transform<T>(image: &Image<Luma<T>>, angles: &[f32]) -> Image<Luma<f32>>
+where
+    T: Primitive + ValueInto<f32> + 'static,
+{
+    let (width, height) = image.dimensions();
+    let bins = sinogram_width(width, height);
+    let centre_bin = (bins / 2) as f32;
+    let (cx, cy) = centre(width, height);
+    let mut sinogram = Image::new(bins, angles.len() as u32);
+
+    for (row, &angle) in sinogram.chunks_mut(bins as usize).zip(angles) {
+        let (sin, cos) = angle.sin_cos();
+        for y in 0..height {
+            let y_offset = (y as f32 - cy) * sin;
+            for x in 0..width {
+                let value: f32 = unsafe { image.unsafe_get_pixel(x, y)[0] }.value_into().unwrap();
+                if value == 0.0 {
+                    continue;
+                }
+                let position = centre_bin + (x as f32 - cx) * cos + y_offset;
+                let left = position.floor();
+                let weight = position - left;
+                let left = left as usize;
+                row[left] += value * (1.0 - weight);
+                row[left + 1] += value * weight;
+            }
+        }
+    }
+
+    sinogram
+}
+
+/// Reconstructs an image with the given dimensions from its sinogram, using filtered
+/// back-projection with a [Ram-Lak](https://en.wikipedia.org/wiki/Tomographic_reconstruction)
+/// (ramp) filter.
+///
+/// `sinogram` must have been computed at `angles`, which should cover [0, pi)
+/// evenly for an accurate reconstruction, e.g. those returned by
+/// [`uniform_angles`](fn.uniform_angles.html).
+///
+/// # Panics
+/// If the height of `sinogram` is not equal to `angles.len()`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::draw_filled_circle_mut;
+/// use imageproc::radon::{filtered_back_projection, radon_transform, uniform_angles};
+///
+/// let mut image = GrayImage::new(41, 41);
+/// draw_filled_circle_mut(&mut image, (20, 20), 10, Luma([100]));
+///
+/// let angles = uniform_angles(90);
+/// let sinogram = radon_transform(&image, &angles);
+/// let reconstruction = filtered_back_projection(&sinogram, &angles, 41, 41);
+///
+/// assert!((reconstruction.get_pixel(16, 20)[0] - 100.0).abs() < 5.0);
+/// assert!(reconstruction.get_pixel(3, 3)[0].abs() < 5.0);
+/// # }
+/// ```
+pub fn filtered_back_projection(
+    sinogram: &Image<Luma<f32>>,
+    angles: &[f32],
+    width: u32,
+    height: u32,
+) -> Image<Luma<f32>> {
+    assert_eq!(
+        sinogram.height() as usize,
+        angles.len(),
+        "sinogram must have one row per angle"
+    );
+    let bins = sinogram.width() as usize;
+    let centre_bin = (bins / 2) as f32;
+    let (cx, cy) = centre(width, height);
+    let mut out = Image::new(width, height);
+    if bins == 0 || angles.is_empty() {
+        return out;
+    }
+
+    let kernel = ramp_kernel(bins);
+    let mut filtered = vec![0f32; bins];
+    let scale = f32::consts::PI / angles.len() as f32;
+
+    for (row, &angle) in sinogram.chunks(bins).zip(angles) {
+        for (i, f) in filtered.iter_mut().enumerate() {
+            // Kernel entry for offset k is at index k + bins - 1.
+            *f = row.iter().enumerate().map(|(j, p)| p * kernel[i + bins - 1 - j]).sum();
+        }
+
+        let (sin, cos) = angle.sin_cos();
+        for y in 0..height {
+            let y_offset = (y as f32 - cy) * sin;
+            for x in 0..width {
+                let position = centre_bin + (x as f32 - cx) * cos + y_offset;
+                let left = position.floor();
+                if left < 0.0 || left as usize + 1 >= bins {
+                    continue;
+                }
+                let weight = position - left;
+                let left = left as usize;
+                let value = filtered[left] * (1.0 - weight) + filtered[left + 1] * weight;
+                unsafe {
+                    let current = out.unsafe_get_pixel(x, y)[0];
+                    out.unsafe_put_pixel(x, y, Luma([current + scale * value]));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Returns the angle whose projection in `sinogram` has the greatest variance, or `None` if
+/// `angles` is empty.
+///
+/// The projection of an image with strong linear structure has the greatest variance when
+/// its lines are parallel to the lines of integration, i.e. at the angle normal to them. For
+/// example, an image of horizontal lines of text has its dominant angle at pi / 2, and the
+/// difference between the dominant angle and pi / 2 estimates the skew of a scanned document.
+///
+/// # Panics
+/// If the height of `sinogram` is not equal to `angles.len()`.
+pub fn dominant_angle(sinogram: &Image<Luma<f32>>, angles: &[f32]) -> Option<f32> {
+    assert_eq!(
+        sinogram.height() as usize,
+        angles.len(),
+        "sinogram must have one row per angle"
+    );
+    let bins = sinogram.width() as usize;
+    if bins == 0 {
+        return angles.first().cloned();
+    }
+    let variance = |row: &[f32]| {
+        let mean = row.iter().sum::<f32>() / bins as f32;
+        row.iter().map(|p| (p - mean) * (p - mean)).sum::<f32>()
+    };
+    sinogram
+        .chunks(bins)
+        .map(variance)
+        .zip(angles)
+        .fold(None, |best: Option<(f32, f32)>, (v, &a)| match best {
+            Some((best_variance, _)) if best_variance >= v => best,
+            _ => Some((v, a)),
+        })
+        .map(|(_, a)| a)
+}
+
+/// The point about which projections are taken.
+fn centre(width: u32, height: u32) -> (f32, f32) {
+    ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0)
+}
+
+/// The discrete Ram-Lak filter for offsets from 1 - len to len - 1.
+fn ramp_kernel(len: usize) -> Vec<f32> {
+    (0..2 * len - 1)
+        .map(|i| {
+            let k = i as i64 - (len as i64 - 1);
+            if k == 0 {
+                0.25
+            } else if k % 2 == 0 {
+                0.0
+            } else {
+                -1.0 / (f32::consts::PI * k as f32).powi(2)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::GrayImage;
+    use drawing::{draw_filled_circle_mut, draw_filled_rect_mut};
+    use rect::Rect;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_uniform_angles() {
+        let angles = uniform_angles(4);
+        assert_eq!(angles, vec![0.0, f32::consts::FRAC_PI_4, f32::consts::FRAC_PI_2, 3.0 * f32::consts::FRAC_PI_4]);
+    }
+
+    #[test]
+    fn test_sinogram_width() {
+        assert_eq!(sinogram_width(0, 0), 3);
+        assert_eq!(sinogram_width(3, 4), 9);
+    }
+
+    #[test]
+    fn test_radon_transform_preserves_total_intensity() {
+        let image = gray_bench_image(15, 10);
+        let total: f32 = image.iter().map(|&p| p as f32).sum();
+        let angles = uniform_angles(12);
+        let sinogram = radon_transform(&image, &angles);
+        assert_eq!(sinogram.dimensions(), (sinogram_width(15, 10), 12));
+        for row in sinogram.chunks(sinogram.width() as usize) {
+            let sum: f32 = row.iter().sum();
+            assert!((sum - total).abs() / total < 1e-4, "{} != {}", sum, total);
+        }
+    }
+
+    #[test]
+    fn test_radon_transform_of_bar() {
+        // A horizontal bar.
+        let mut image = GrayImage::new(21, 21);
+        draw_filled_rect_mut(&mut image, Rect::at(0, 10).of_size(21, 1), Luma([1]));
+        let sinogram = radon_transform(&image, &[0.0, f32::consts::FRAC_PI_2]);
+        let centre = sinogram.width() / 2;
+        for t in 0..sinogram.width() {
+            let expected = if t >= centre - 10 && t <= centre + 10 { 1.0 } else { 0.0 };
+            assert_eq!(sinogram.get_pixel(t, 0)[0], expected);
+        }
+        assert!((sinogram.get_pixel(centre, 1)[0] - 21.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_filtered_back_projection_of_disk() {
+        let mut image = GrayImage::new(31, 31);
+        draw_filled_circle_mut(&mut image, (15, 15), 8, Luma([200]));
+        let angles = uniform_angles(60);
+        let reconstruction = filtered_back_projection(&radon_transform(&image, &angles), &angles, 31, 31);
+
+        let mut error = 0.0;
+        for (r, p) in reconstruction.iter().zip(image.iter()) {
+            error += (r - *p as f32).abs();
+        }
+        let mean_error = error / (31.0 * 31.0);
+        assert!(mean_error < 10.0, "mean error {}", mean_error);
+    }
+
+    #[test]
+    fn test_filtered_back_projection_empty() {
+        let reconstruction = filtered_back_projection(&Image::new(5, 0), &[], 4, 4);
+        assert!(reconstruction.iter().all(|p| *p == 0.0));
+    }
+
+    #[test]
+    fn test_dominant_angle() {
+        // Horizontal lines, rotated slightly.
+        let image = GrayImage::from_fn(40, 40, |x, y| Luma([if (y * 10 + x) / 10 % 8 < 2 { 255 } else { 0 }]));
+        let angles: Vec<f32> = (0..180).map(|d| (d as f32).to_radians()).collect();
+        let sinogram = radon_transform(&image, &angles);
+        let angle = dominant_angle(&sinogram, &angles).unwrap();
+        // The stripes have direction (10, -1), so are normal to (1, 10).
+        let expected = (10f32).atan();
+        assert!((angle - expected).abs() <= 1f32.to_radians(), "{} != {}", angle, expected);
+
+        assert_eq!(dominant_angle(&Image::new(3, 0), &[]), None);
+    }
+
+    #[bench]
+    fn bench_radon_transform(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        let angles = uniform_angles(90);
+        b.iter(|| black_box(radon_transform(&image, &angles)));
+    }
+
+    #[bench]
+    fn bench_filtered_back_projection(b: &mut Bencher) {
+        let image = gray_bench_image(50, 50);
+        let angles = uniform_angles(45);
+        let sinogram = radon_transform(&image, &angles);
+        b.iter(|| black_box(filtered_back_projection(&sinogram, &angles, 50, 50)));
+    }
+}
@@ -1,5 +1,6 @@
 //! Basic manipulation of rectangles.
 
+use geometry::Polygon;
 use std::cmp;
 
 /// A rectangular region of non-zero width and height.
@@ -22,6 +23,7 @@ use std::cmp;
 /// assert!(rect.contains(rect.right(), rect.bottom()));
 /// ```
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rect {
     left: i32,
     top: i32,
@@ -121,8 +123,174 @@ impl Rect {
             height: (bottom - top) as u32 + 1,
         })
     }
+
+    /// Returns the rect with the given inclusive bounds, or `None` if
+    /// `right < left` or `bottom < top`.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::rect::Rect;
+    ///
+    /// assert_eq!(Rect::from_bounds(1, 2, 3, 2), Some(Rect::at(1, 2).of_size(3, 1)));
+    /// assert_eq!(Rect::from_bounds(1, 2, 0, 2), None);
+    /// ```
+    pub fn from_bounds(left: i32, top: i32, right: i32, bottom: i32) -> Option<Rect> {
+        if right < left || bottom < top {
+            return None;
+        }
+        Some(Rect {
+            left,
+            top,
+            width: (right as i64 - left as i64 + 1) as u32,
+            height: (bottom as i64 - top as i64 + 1) as u32,
+        })
+    }
+
+    /// Number of pixels in rect.
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Returns the smallest rect containing both self and other.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::rect::Rect;
+    ///
+    /// let r = Rect::at(0, 0).of_size(2, 2);
+    /// let s = Rect::at(5, -1).of_size(1, 1);
+    /// assert_eq!(r.union(s), Rect::at(0, -1).of_size(6, 3));
+    /// ```
+    pub fn union(&self, other: Rect) -> Rect {
+        let left = cmp::min(self.left, other.left);
+        let top = cmp::min(self.top, other.top);
+        let right = cmp::max(self.right(), other.right());
+        let bottom = cmp::max(self.bottom(), other.bottom());
+        Rect::from_bounds(left, top, right, bottom).unwrap()
+    }
+
+    /// Returns the area of the intersection of self and other divided by the area
+    /// of their union. This is 1 for equal rects and 0 for disjoint rects.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::rect::Rect;
+    ///
+    /// let r = Rect::at(0, 0).of_size(10, 10);
+    /// assert_eq!(r.iou(r), 1.0);
+    /// assert_eq!(r.iou(Rect::at(5, 0).of_size(10, 10)), 50.0 / 150.0);
+    /// assert_eq!(r.iou(Rect::at(20, 20).of_size(5, 5)), 0.0);
+    /// ```
+    pub fn iou(&self, other: Rect) -> f32 {
+        let intersection = match self.intersect(other) {
+            Some(i) => i.area() as f64,
+            None => return 0.0,
+        };
+        let union = self.area() as f64 + other.area() as f64 - intersection;
+        (intersection / union) as f32
+    }
+
+    /// Whether other lies entirely within self.
+    pub fn contains_rect(&self, other: Rect) -> bool {
+        self.left <= other.left && other.right() <= self.right() &&
+        self.top <= other.top && other.bottom() <= self.bottom()
+    }
+
+    /// Returns the rect obtained by moving each of the left and right edges of self
+    /// outwards by `dx` and each of the top and bottom edges outwards by `dy`.
+    pub fn inflate(&self, dx: u32, dy: u32) -> Rect {
+        Rect {
+            left: self.left - dx as i32,
+            top: self.top - dy as i32,
+            width: self.width + 2 * dx,
+            height: self.height + 2 * dy,
+        }
+    }
+
+    /// Returns the rect obtained by moving each of the left and right edges of self
+    /// inwards by `dx` and each of the top and bottom edges inwards by `dy`, or `None`
+    /// if this leaves no pixels.
+    pub fn deflate(&self, dx: u32, dy: u32) -> Option<Rect> {
+        if self.width <= 2 * dx || self.height <= 2 * dy {
+            return None;
+        }
+        Some(Rect {
+            left: self.left + dx as i32,
+            top: self.top + dy as i32,
+            width: self.width - 2 * dx,
+            height: self.height - 2 * dy,
+        })
+    }
+
+    /// Returns the rect moved by `dx` horizontally and `dy` vertically.
+    pub fn translate(&self, dx: i32, dy: i32) -> Rect {
+        Rect { left: self.left + dx, top: self.top + dy, ..*self }
+    }
+
+    /// Returns the intersection of self with an image of the given dimensions,
+    /// or `None` if they are disjoint.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::rect::Rect;
+    ///
+    /// let r = Rect::at(-2, 3).of_size(5, 5);
+    /// assert_eq!(r.clamp_to_image(10, 6), Some(Rect::at(0, 3).of_size(3, 3)));
+    /// assert_eq!(r.clamp_to_image(10, 3), None);
+    /// ```
+    pub fn clamp_to_image(&self, width: u32, height: u32) -> Option<Rect> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        self.intersect(Rect::at(0, 0).of_size(width, height))
+    }
+
+    /// Returns an iterator over the coordinates of all pixels in the rect,
+    /// in row-major order.
+    ///
+    /// # Examples
+    /// ```
+    /// use imageproc::rect::Rect;
+    ///
+    /// let r = Rect::at(-1, 2).of_size(2, 2);
+    /// let points: Vec<(i32, i32)> = r.points().collect();
+    /// assert_eq!(points, vec![(-1, 2), (0, 2), (-1, 3), (0, 3)]);
+    /// ```
+    pub fn points(&self) -> RectPoints {
+        RectPoints { rect: *self, index: 0 }
+    }
+}
+
+/// Iterator over the coordinates of the pixels in a [`Rect`](struct.Rect.html),
+/// in row-major order. Returned by [`Rect::points`](struct.Rect.html#method.points).
+#[derive(Clone, Debug)]
+pub struct RectPoints {
+    rect: Rect,
+    index: u64,
 }
 
+impl Iterator for RectPoints {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<(i32, i32)> {
+        if self.index >= self.rect.area() {
+            return None;
+        }
+        let width = self.rect.width as u64;
+        let x = self.rect.left as i64 + (self.index % width) as i64;
+        let y = self.rect.top as i64 + (self.index / width) as i64;
+        self.index += 1;
+        Some((x as i32, y as i32))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.rect.area() - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for RectPoints {}
+
 impl Region<i32> for Rect {
     fn contains(&self, x: i32, y: i32) -> bool {
         self.left <= x && x <= self.right() &&
@@ -137,6 +305,317 @@ impl Region<f32> for Rect {
     }
 }
 
+/// A rectangle with floating point position and size.
+///
+/// A `RectF` with left `l`, top `t`, width `w` and height `h` covers the points `(x, y)`
+/// with `l <= x < l + w` and `t <= y < t + h`. A [`Rect`](struct.Rect.html) covering
+/// the pixels in columns `l..l + w` and rows `t..t + h` converts to a `RectF`
+/// with the same position and size.
+///
+/// # Examples
+/// ```
+/// use imageproc::rect::{Rect, RectF, Region};
+///
+/// let r = RectF::new(1.5, 2.0, 3.0, 0.5);
+/// assert_eq!(r.right(), 4.5);
+/// assert!(r.contains(4.0, 2.25));
+/// assert!(!r.contains(4.5, 2.25));
+///
+/// assert_eq!(r.enclosing_rect(), Rect::at(1, 2).of_size(4, 1));
+/// assert_eq!(RectF::from(Rect::at(1, 2).of_size(4, 1)), RectF::new(1.0, 2.0, 4.0, 1.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RectF {
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+}
+
+impl RectF {
+    /// Construct a rect from its top-left corner and size.
+    ///
+    /// # Panics
+    /// If `width` or `height` is not strictly positive.
+    pub fn new(left: f32, top: f32, width: f32, height: f32) -> RectF {
+        assert!(width > 0.0, "width must be strictly positive");
+        assert!(height > 0.0, "height must be strictly positive");
+        RectF { left, top, width, height }
+    }
+
+    /// Construct a rect with the given center and size.
+    ///
+    /// # Panics
+    /// If `width` or `height` is not strictly positive.
+    pub fn from_center(center: (f32, f32), width: f32, height: f32) -> RectF {
+        RectF::new(center.0 - width / 2.0, center.1 - height / 2.0, width, height)
+    }
+
+    /// Smallest x-coordinate of the rect.
+    pub fn left(&self) -> f32 {
+        self.left
+    }
+
+    /// Smallest y-coordinate of the rect.
+    pub fn top(&self) -> f32 {
+        self.top
+    }
+
+    /// The x-coordinate of the right edge of the rect, `left + width`.
+    pub fn right(&self) -> f32 {
+        self.left + self.width
+    }
+
+    /// The y-coordinate of the bottom edge of the rect, `top + height`.
+    pub fn bottom(&self) -> f32 {
+        self.top + self.height
+    }
+
+    /// Width of the rect.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Height of the rect.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// Center of the rect.
+    pub fn center(&self) -> (f32, f32) {
+        (self.left + self.width / 2.0, self.top + self.height / 2.0)
+    }
+
+    /// Area of the rect.
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    /// Returns the intersection of self and other, or `None` if it has zero area.
+    pub fn intersect(&self, other: RectF) -> Option<RectF> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(RectF { left, top, width: right - left, height: bottom - top })
+    }
+
+    /// Returns the smallest rect containing both self and other.
+    pub fn union(&self, other: RectF) -> RectF {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        RectF { left, top, width: right - left, height: bottom - top }
+    }
+
+    /// Returns the area of the intersection of self and other divided by the area
+    /// of their union.
+    pub fn iou(&self, other: RectF) -> f32 {
+        let intersection = match self.intersect(other) {
+            Some(i) => i.area(),
+            None => return 0.0,
+        };
+        intersection / (self.area() + other.area() - intersection)
+    }
+
+    /// Whether other lies entirely within self.
+    pub fn contains_rect(&self, other: RectF) -> bool {
+        self.left <= other.left && other.right() <= self.right() &&
+        self.top <= other.top && other.bottom() <= self.bottom()
+    }
+
+    /// Returns the rect obtained by moving each of the left and right edges of self
+    /// outwards by `dx` and each of the top and bottom edges outwards by `dy`, or `None`
+    /// if this leaves a rect with non-positive width or height. Negative values
+    /// move edges inwards.
+    pub fn inflate(&self, dx: f32, dy: f32) -> Option<RectF> {
+        let (width, height) = (self.width + 2.0 * dx, self.height + 2.0 * dy);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        Some(RectF { left: self.left - dx, top: self.top - dy, width, height })
+    }
+
+    /// Returns the rect moved by `dx` horizontally and `dy` vertically.
+    pub fn translate(&self, dx: f32, dy: f32) -> RectF {
+        RectF { left: self.left + dx, top: self.top + dy, ..*self }
+    }
+
+    /// Returns the intersection of self with the region `[0, width) x [0, height)`
+    /// covered by an image of the given dimensions, or `None` if it has zero area.
+    pub fn clamp_to_image(&self, width: u32, height: u32) -> Option<RectF> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        self.intersect(RectF::new(0.0, 0.0, width as f32, height as f32))
+    }
+
+    /// Returns the smallest [`Rect`](struct.Rect.html) covering every pixel that
+    /// self overlaps.
+    pub fn enclosing_rect(&self) -> Rect {
+        let left = self.left.floor();
+        let top = self.top.floor();
+        let right = self.right().ceil().max(left + 1.0);
+        let bottom = self.bottom().ceil().max(top + 1.0);
+        Rect::at(left as i32, top as i32).of_size((right - left) as u32, (bottom - top) as u32)
+    }
+
+    /// Returns the [`Rect`](struct.Rect.html) obtained by rounding each edge of self
+    /// to the nearest integer, or `None` if this leaves no pixels.
+    pub fn round(&self) -> Option<Rect> {
+        let left = self.left.round() as i32;
+        let top = self.top.round() as i32;
+        let right = self.right().round() as i32;
+        let bottom = self.bottom().round() as i32;
+        Rect::from_bounds(left, top, right - 1, bottom - 1)
+    }
+}
+
+impl From<Rect> for RectF {
+    fn from(rect: Rect) -> RectF {
+        RectF::new(rect.left as f32, rect.top as f32, rect.width as f32, rect.height as f32)
+    }
+}
+
+impl Region<f32> for RectF {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.left <= x && x < self.right() &&
+        self.top <= y && y < self.bottom()
+    }
+}
+
+/// A rectangle of non-zero width and height, rotated clockwise about its center.
+///
+/// Before rotation the rect covers the points within `width / 2` of its center
+/// horizontally and within `height / 2` of its center vertically. It is then rotated
+/// clockwise by `angle` radians, using the same convention as
+/// [`geometric_transformations::rotate`](../geometric_transformations/fn.rotate.html).
+///
+/// # Examples
+/// ```
+/// use imageproc::rect::{RectF, Region, RotatedRect};
+/// use std::f32::consts::PI;
+///
+/// let r = RotatedRect::new((2.0, 3.0), 4.0, 2.0, PI / 2.0);
+/// assert!(r.contains(2.0, 4.5));
+/// assert!(!r.contains(3.5, 3.0));
+///
+/// let bounds = r.bounding_rect();
+/// assert!((bounds.left() - 1.0).abs() < 1e-5 && (bounds.top() - 1.0).abs() < 1e-5);
+/// assert!((bounds.width() - 2.0).abs() < 1e-5 && (bounds.height() - 4.0).abs() < 1e-5);
+///
+/// // Rotating by a quarter turn about the center of a square doesn't change it.
+/// let square = RotatedRect::new((0.0, 0.0), 2.0, 2.0, 0.0);
+/// let turned = RotatedRect::new((0.0, 0.0), 2.0, 2.0, PI / 2.0);
+/// assert!((square.iou(&turned) - 1.0).abs() < 1e-5);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RotatedRect {
+    center: (f32, f32),
+    width: f32,
+    height: f32,
+    angle: f32,
+}
+
+impl RotatedRect {
+    /// Construct a rotated rect from its center, size and clockwise rotation in radians.
+    ///
+    /// # Panics
+    /// If `width` or `height` is not strictly positive.
+    pub fn new(center: (f32, f32), width: f32, height: f32, angle: f32) -> RotatedRect {
+        assert!(width > 0.0, "width must be strictly positive");
+        assert!(height > 0.0, "height must be strictly positive");
+        RotatedRect { center, width, height, angle }
+    }
+
+    /// Center of the rect.
+    pub fn center(&self) -> (f32, f32) {
+        self.center
+    }
+
+    /// Width of the rect before rotation.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Height of the rect before rotation.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    /// Clockwise rotation of the rect in radians.
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// Area of the rect.
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    /// The corners of the rect. These are the images under rotation of the top left,
+    /// top right, bottom right and bottom left corners of the unrotated rect, in that order.
+    pub fn corners(&self) -> [(f32, f32); 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        let (hw, hh) = (self.width / 2.0, self.height / 2.0);
+        let (cx, cy) = self.center;
+        let corner = |dx: f32, dy: f32| (cx + cos * dx - sin * dy, cy + sin * dx + cos * dy);
+        [corner(-hw, -hh), corner(hw, -hh), corner(hw, hh), corner(-hw, hh)]
+    }
+
+    /// The rect as a polygon, with vertices given by its [`corners`](#method.corners).
+    pub fn polygon(&self) -> Polygon {
+        Polygon::new(self.corners().to_vec())
+    }
+
+    /// The smallest axis-aligned rect containing self.
+    pub fn bounding_rect(&self) -> RectF {
+        let corners = self.corners();
+        let min_x = corners.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+        let max_x = corners.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+        let max_y = corners.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+        RectF::new(min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// Area of the intersection of self and other.
+    pub fn intersection_area(&self, other: &RotatedRect) -> f32 {
+        self.polygon().clip_convex(&other.polygon()).area()
+    }
+
+    /// Returns the area of the intersection of self and other divided by the area
+    /// of their union.
+    pub fn iou(&self, other: &RotatedRect) -> f32 {
+        let intersection = self.intersection_area(other);
+        if intersection <= 0.0 {
+            return 0.0;
+        }
+        intersection / (self.area() + other.area() - intersection)
+    }
+}
+
+impl From<RectF> for RotatedRect {
+    fn from(rect: RectF) -> RotatedRect {
+        RotatedRect::new(rect.center(), rect.width, rect.height, 0.0)
+    }
+}
+
+impl Region<f32> for RotatedRect {
+    /// Points on the boundary of the rect are considered to be contained in it.
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let (sin, cos) = self.angle.sin_cos();
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        // Rotate anticlockwise into the frame of the unrotated rect.
+        let u = cos * dx + sin * dy;
+        let v = -sin * dx + cos * dy;
+        u.abs() <= self.width / 2.0 && v.abs() <= self.height / 2.0
+    }
+}
+
 /// Position of the top left of a rectangle.
 /// Only used when building a [`Rect`](struct.Rect.html).
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -164,7 +643,9 @@ impl RectPosition {
 
 #[cfg(test)]
 mod test {
-    use super::{Rect, Region};
+    use super::{Rect, RectF, Region, RotatedRect};
+    use std::collections::HashSet;
+    use std::f32::consts::PI;
 
     #[test]
     #[should_panic]
@@ -172,6 +653,25 @@ mod test {
         Rect::at(1, 2).of_size(0, 1);
     }
 
+    #[test]
+    fn test_points() {
+        let r = Rect::at(-3, 5).of_size(4, 3);
+        let points: Vec<(i32, i32)> = r.points().collect();
+        assert_eq!(r.points().len(), 12);
+        assert_eq!(points.len(), 12);
+        assert_eq!(points[0], (r.left(), r.top()));
+        assert_eq!(points[11], (r.right(), r.bottom()));
+        assert!(points.iter().all(|&(x, y)| r.contains(x, y)));
+        let distinct: HashSet<(i32, i32)> = points.iter().cloned().collect();
+        assert_eq!(distinct.len(), 12);
+    }
+
+    #[test]
+    fn test_points_single_pixel() {
+        let r = Rect::at(7, -2).of_size(1, 1);
+        assert_eq!(r.points().collect::<Vec<_>>(), vec![(7, -2)]);
+    }
+
     #[test]
     fn test_contains_i32() {
         let r = Rect::at(5, 5).of_size(6, 6);
@@ -187,4 +687,129 @@ mod test {
         assert!(r.contains(5f32, 5f32));
         assert!(!r.contains(10.1f32, 10f32));
     }
+
+    #[test]
+    fn test_from_bounds_and_area() {
+        let r = Rect::from_bounds(-3, 1, 2, 1).unwrap();
+        assert_eq!(r, Rect::at(-3, 1).of_size(6, 1));
+        assert_eq!(r.area(), 6);
+        assert_eq!(Rect::from_bounds(0, 1, 0, 0), None);
+    }
+
+    #[test]
+    fn test_contains_rect() {
+        let r = Rect::at(0, 0).of_size(5, 5);
+        assert!(r.contains_rect(r));
+        assert!(r.contains_rect(Rect::at(1, 1).of_size(4, 4)));
+        assert!(!r.contains_rect(Rect::at(1, 1).of_size(5, 4)));
+        assert!(!r.contains_rect(Rect::at(-1, 1).of_size(2, 2)));
+    }
+
+    #[test]
+    fn test_inflate_deflate() {
+        let r = Rect::at(2, 3).of_size(4, 5);
+        assert_eq!(r.inflate(1, 2), Rect::at(1, 1).of_size(6, 9));
+        assert_eq!(r.inflate(1, 2).deflate(1, 2), Some(r));
+        assert_eq!(r.deflate(1, 2), Some(Rect::at(3, 5).of_size(2, 1)));
+        assert_eq!(r.deflate(2, 0), None);
+        assert_eq!(r.translate(-3, 1), Rect::at(-1, 4).of_size(4, 5));
+    }
+
+    #[test]
+    fn test_rect_f_intersect_union_iou() {
+        let r = RectF::new(0.0, 0.0, 2.0, 2.0);
+        let s = RectF::new(1.0, 1.0, 2.0, 2.0);
+        assert_eq!(r.intersect(s), Some(RectF::new(1.0, 1.0, 1.0, 1.0)));
+        assert_eq!(r.union(s), RectF::new(0.0, 0.0, 3.0, 3.0));
+        assert_eq!(r.iou(s), 1.0 / 7.0);
+        // Rects sharing only an edge don't intersect.
+        assert_eq!(r.intersect(RectF::new(2.0, 0.0, 1.0, 1.0)), None);
+        assert_eq!(r.iou(RectF::new(2.0, 0.0, 1.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn test_rect_f_conversions() {
+        let r = Rect::at(-2, 3).of_size(4, 5);
+        let f = RectF::from(r);
+        assert_eq!(f.enclosing_rect(), r);
+        assert_eq!(f.round(), Some(r));
+        assert_eq!(f.iou(RectF::from(Rect::at(0, 3).of_size(4, 5))), r.iou(Rect::at(0, 3).of_size(4, 5)));
+
+        let f = RectF::new(0.4, 0.6, 0.2, 0.2);
+        assert_eq!(f.enclosing_rect(), Rect::at(0, 0).of_size(1, 1));
+        assert_eq!(f.round(), None);
+        assert_eq!(RectF::from_center((1.0, 1.0), 1.0, 2.0), RectF::new(0.5, 0.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn test_rect_f_inflate_and_clamp() {
+        let r = RectF::new(-1.0, 1.0, 3.0, 2.0);
+        assert_eq!(r.inflate(0.5, -0.5), Some(RectF::new(-1.5, 1.5, 4.0, 1.0)));
+        assert_eq!(r.inflate(0.0, -1.0), None);
+        assert_eq!(r.clamp_to_image(10, 10), Some(RectF::new(0.0, 1.0, 2.0, 2.0)));
+        assert_eq!(r.clamp_to_image(10, 1), None);
+        assert_eq!(r.translate(1.0, 1.0).center(), (1.5, 3.0));
+    }
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-4 && (actual.1 - expected.1).abs() < 1e-4,
+            "expected {:?}, found {:?}", expected, actual
+        );
+    }
+
+    #[test]
+    fn test_rotated_rect_corners() {
+        let r = RotatedRect::new((1.0, 2.0), 4.0, 2.0, 0.0);
+        assert_eq!(r.corners(), [(-1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (-1.0, 3.0)]);
+
+        // A clockwise quarter turn takes the positive x-axis to the positive y-axis.
+        let r = RotatedRect::new((1.0, 2.0), 4.0, 2.0, PI / 2.0);
+        let corners = r.corners();
+        assert_close(corners[0], (2.0, 0.0));
+        assert_close(corners[1], (2.0, 4.0));
+        assert_close(corners[2], (0.0, 4.0));
+        assert_close(corners[3], (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_rotated_rect_contains() {
+        let r = RotatedRect::new((0.0, 0.0), 2.0 * 2f32.sqrt(), 2f32.sqrt(), PI / 4.0);
+        assert!(r.contains(0.0, 0.0));
+        assert!(r.contains(0.9, 0.9));
+        assert!(!r.contains(0.9, -0.9));
+        assert!(r.contains(0.4, -0.4));
+        assert!(!r.contains(1.1, 1.1));
+    }
+
+    #[test]
+    fn test_rotated_rect_from_rect_f() {
+        let f = RectF::new(1.0, 2.0, 3.0, 4.0);
+        let r = RotatedRect::from(f);
+        assert_eq!(r.center(), (2.5, 4.0));
+        assert_eq!(r.bounding_rect(), f);
+    }
+
+    #[test]
+    fn test_rotated_rect_intersection_area() {
+        // Unrotated rects match RectF.
+        let a = RectF::new(0.0, 0.0, 4.0, 2.0);
+        let b = RectF::new(1.0, 1.0, 4.0, 4.0);
+        let (ra, rb) = (RotatedRect::from(a), RotatedRect::from(b));
+        assert!((ra.intersection_area(&rb) - 3.0).abs() < 1e-5);
+        assert!((ra.iou(&rb) - a.iou(b)).abs() < 1e-5);
+        assert_eq!(ra.iou(&RotatedRect::new((10.0, 10.0), 1.0, 1.0, 0.3)), 0.0);
+
+        // A unit square and the same square rotated by 45 degrees about its center
+        // intersect in a regular octagon.
+        let square = RotatedRect::new((0.0, 0.0), 1.0, 1.0, 0.0);
+        let diamond = RotatedRect::new((0.0, 0.0), 1.0, 1.0, PI / 4.0);
+        let octagon = 2.0 * (2f32.sqrt() - 1.0);
+        assert!((square.intersection_area(&diamond) - octagon).abs() < 1e-5);
+        assert!((diamond.intersection_area(&square) - octagon).abs() < 1e-5);
+
+        // Containment.
+        let small = RotatedRect::new((0.1, 0.0), 0.2, 0.2, 1.0);
+        assert!((square.intersection_area(&small) - small.area()).abs() < 1e-5);
+    }
 }
@@ -3,6 +3,7 @@
 use image::{GenericImage, GenericImageView, ImageBuffer, Luma};
 
 use definitions::Image;
+use rect::Rect;
 use union_find::DisjointSetForest;
 use std::cmp;
 
@@ -132,7 +133,7 @@ where
     }
 
     let image_size = (width * height) as usize;
-    let mut forest = DisjointSetForest::new(image_size);
+    let mut forest: DisjointSetForest<u32> = DisjointSetForest::new(image_size);
     let mut adj_labels = [0u32; 4];
     let mut next_label = 1;
 
@@ -200,7 +201,7 @@ where
                     out.unsafe_put_pixel(x, y, Luma([min_label]));
                 }
                 for n in 0..num_adj {
-                    forest.union(min_label as usize, adj_labels[n] as usize);
+                    forest.union(min_label, adj_labels[n]);
                 }
             }
         }
@@ -219,7 +220,7 @@ where
                     }
                     out.unsafe_get_pixel(x, y)[0]
                 };
-                let root = forest.root(label as usize);
+                let root = forest.root(label) as usize;
                 let mut output_label = *output_labels.get_unchecked(root);
                 if output_label < 1 {
                     output_label = count;
@@ -234,9 +235,93 @@ where
     out
 }
 
+/// Summary statistics for a labelled connected component.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ComponentStats {
+    /// The label of the component.
+    pub label: u32,
+    /// The number of pixels in the component.
+    pub area: u32,
+    /// The smallest rectangle containing all pixels in the component.
+    pub bounding_box: Rect,
+    /// The mean `(x, y)` position of the pixels in the component.
+    pub centroid: (f32, f32),
+}
+
+/// Computes the area, bounding box and centroid of each component in an image of labels,
+/// such as that returned by [`connected_components`](fn.connected_components.html).
+///
+/// Pixels with label 0 are treated as background. The results are sorted by label,
+/// and labels with no pixels are omitted.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::rect::Rect;
+/// use imageproc::region_labelling::{component_stats, connected_components, Connectivity};
+///
+/// let image = gray_image!(
+///     1, 1, 0, 0;
+///     1, 0, 0, 1;
+///     0, 0, 0, 1);
+///
+/// let labels = connected_components(&image, Connectivity::Four, Luma([0]));
+/// let stats = component_stats(&labels);
+///
+/// assert_eq!(stats.len(), 2);
+/// assert_eq!(stats[0].area, 3);
+/// assert_eq!(stats[0].bounding_box, Rect::at(0, 0).of_size(2, 2));
+/// assert_eq!(stats[0].centroid, (1.0 / 3.0, 1.0 / 3.0));
+/// assert_eq!(stats[1].bounding_box, Rect::at(3, 1).of_size(1, 2));
+/// assert_eq!(stats[1].centroid, (3.0, 1.5));
+/// # }
+/// ```
+pub fn component_stats(labels: &Image<Luma<u32>>) -> Vec<ComponentStats> {
+    let num_labels = labels.iter().cloned().max().unwrap_or(0) as usize;
+
+    // Area, sum of x, sum of y, and bounds (left, top, right, bottom) of each label.
+    let mut areas = vec![0u32; num_labels];
+    let mut sums = vec![(0u64, 0u64); num_labels];
+    let mut bounds = vec![(u32::max_value(), u32::max_value(), 0u32, 0u32); num_labels];
+
+    for (x, y, p) in labels.enumerate_pixels() {
+        if p[0] == 0 {
+            continue;
+        }
+        let i = p[0] as usize - 1;
+        areas[i] += 1;
+        sums[i].0 += x as u64;
+        sums[i].1 += y as u64;
+        let b = &mut bounds[i];
+        *b = (cmp::min(b.0, x), cmp::min(b.1, y), cmp::max(b.2, x), cmp::max(b.3, y));
+    }
+
+    (0..num_labels)
+        .filter(|&i| areas[i] > 0)
+        .map(|i| {
+            let (left, top, right, bottom) = bounds[i];
+            let area = areas[i];
+            ComponentStats {
+                label: i as u32 + 1,
+                area,
+                bounding_box: Rect::at(left as i32, top as i32).of_size(right - left + 1, bottom - top + 1),
+                centroid: (
+                    (sums[i].0 as f64 / area as f64) as f32,
+                    (sums[i].1 as f64 / area as f64) as f32,
+                ),
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
-    use super::connected_components;
+    use super::{component_stats, connected_components};
+    use rect::Rect;
     use super::Connectivity::{Four, Eight};
     use definitions::{HasBlack, HasWhite};
     use image::{GrayImage, ImageBuffer, Luma};
@@ -286,6 +371,39 @@ mod test {
         assert_eq!(max_component, Some(450u32));
     }
 
+    #[test]
+    fn test_component_stats() {
+        let labels = gray_image!(type: u32,
+            0, 2, 2, 0, 5;
+            0, 2, 0, 0, 5;
+            0, 0, 0, 0, 5;
+            2, 0, 0, 0, 0);
+
+        let stats = component_stats(&labels);
+        assert_eq!(stats.len(), 2);
+
+        assert_eq!(stats[0].label, 2);
+        assert_eq!(stats[0].area, 4);
+        assert_eq!(stats[0].bounding_box, Rect::at(0, 0).of_size(3, 4));
+        assert_eq!(stats[0].centroid, (1.0, 1.0));
+
+        assert_eq!(stats[1].label, 5);
+        assert_eq!(stats[1].area, 3);
+        assert_eq!(stats[1].bounding_box, Rect::at(4, 0).of_size(1, 3));
+        assert_eq!(stats[1].centroid, (4.0, 1.0));
+
+        assert!(component_stats(&gray_image!(type: u32, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_component_stats_areas_sum_to_foreground() {
+        let image = chessboard(30, 30);
+        let components = connected_components(&image, Four, Luma::black());
+        let stats = component_stats(&components);
+        assert_eq!(stats.len(), 450);
+        assert!(stats.iter().all(|s| s.area == 1 && s.bounding_box.width() == 1));
+    }
+
     #[bench]
     fn bench_connected_components_eight_chessboard(b: &mut test::Bencher) {
         let image = chessboard(300, 300);
@@ -0,0 +1,770 @@
+//! Functions for partitioning an image into regions.
+
+use image::{GenericImage, GenericImageView, GrayImage, ImageBuffer, Luma, Pixel, Rgb, RgbImage};
+use color::rgb_to_lab;
+use conv::ValueInto;
+use definitions::Image;
+use error::{ImageProcError, Result};
+use math::{cast, squared_distance};
+use region_labelling::Connectivity;
+use std::cmp::{max, min, Reverse};
+use std::collections::BinaryHeap;
+use std::f32;
+use std::u32;
+
+const FOUR_NEIGHBOURS: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+const EIGHT_NEIGHBOURS: [(i32, i32); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1, 0), (1, 0),
+    (-1, 1), (0, 1), (1, 1),
+];
+
+pub(crate) fn neighbour_offsets(connectivity: Connectivity) -> &'static [(i32, i32)] {
+    match connectivity {
+        Connectivity::Four => &FOUR_NEIGHBOURS,
+        Connectivity::Eight => &EIGHT_NEIGHBOURS,
+    }
+}
+
+/// Calls `f` with the in-bounds neighbours of `(x, y)`.
+fn for_each_neighbour<F>(offsets: &[(i32, i32)], width: u32, height: u32, x: u32, y: u32, mut f: F)
+where
+    F: FnMut(u32, u32),
+{
+    for &(dx, dy) in offsets {
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        if nx >= 0 && ny >= 0 && nx < width as i32 && ny < height as i32 {
+            f(nx as u32, ny as u32);
+        }
+    }
+}
+
+/// Marker-controlled [watershed segmentation].
+///
+/// `markers` contains a positive label for each seed pixel and 0 elsewhere. Regions are
+/// grown outwards from the seeds in order of increasing `elevation`, which is typically a
+/// gradient magnitude image, so that region boundaries form along ridges in `elevation`.
+/// Pixels of equal elevation are flooded in breadth-first order.
+///
+/// Returns a label image in which every pixel connected to a seed has the label of the
+/// region that reached it first. If `mark_lines` is true then pixels adjacent to a
+/// differently labelled region when flooded are instead labelled 0, so that regions are
+/// separated by watershed lines. Pixels are also labelled 0 if no seed can reach them,
+/// e.g. if `markers` contains no seeds.
+///
+/// A common way of separating touching blobs is to compute the
+/// [distance transform](../distance_transform/index.html) of the foreground, use its
+/// local maxima as seeds and flood its negation.
+///
+/// [watershed segmentation]: https://en.wikipedia.org/wiki/Watershed_(image_processing)
+///
+/// # Panics
+///
+/// If `elevation` and `markers` do not have the same dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::region_labelling::Connectivity;
+/// use imageproc::segmentation::watershed;
+///
+/// // Two basins separated by a ridge in the middle column.
+/// let elevation = gray_image!(
+///     0, 1, 9, 1, 0;
+///     0, 1, 9, 1, 0);
+///
+/// let markers = gray_image!(type: u32,
+///     1, 0, 0, 0, 2;
+///     0, 0, 0, 0, 0);
+///
+/// assert_pixels_eq!(
+///     watershed(&elevation, &markers, Connectivity::Four, true),
+///     gray_image!(type: u32,
+///         1, 1, 0, 2, 2;
+///         1, 1, 0, 2, 2));
+/// # }
+/// ```
+pub fn watershed(
+    elevation: &GrayImage,
+    markers: &Image<Luma<u32>>,
+    connectivity: Connectivity,
+    mark_lines: bool,
+) -> Image<Luma<u32>> {
+    assert_dimensions_match!(elevation, markers);
+    let (width, height) = elevation.dimensions();
+    let offsets = neighbour_offsets(connectivity);
+    let mut labels: Image<Luma<u32>> = ImageBuffer::new(width, height);
+    // Pixels are queued at most once, with the label of the region that queued them.
+    let mut queued = vec![false; (width * height) as usize];
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+
+    // Entries are ordered by elevation and then by insertion order, so that pixels
+    // at the same elevation are flooded breadth-first.
+    let mut heap = BinaryHeap::new();
+    let mut insertions = 0u64;
+
+    for y in 0..height {
+        for x in 0..width {
+            let label = markers.get_pixel(x, y)[0];
+            if label > 0 {
+                labels.put_pixel(x, y, Luma([label]));
+                queued[index(x, y)] = true;
+            }
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let label = labels.get_pixel(x, y)[0];
+            if label == 0 {
+                continue;
+            }
+            let level = elevation.get_pixel(x, y)[0];
+            for_each_neighbour(offsets, width, height, x, y, |nx, ny| {
+                if !queued[index(nx, ny)] {
+                    queued[index(nx, ny)] = true;
+                    let priority = max(level, elevation.get_pixel(nx, ny)[0]);
+                    heap.push(Reverse((priority, insertions, nx, ny, label)));
+                    insertions += 1;
+                }
+            });
+        }
+    }
+
+    while let Some(Reverse((level, _, x, y, label))) = heap.pop() {
+        if mark_lines {
+            let mut on_boundary = false;
+            for_each_neighbour(offsets, width, height, x, y, |nx, ny| {
+                let neighbour = labels.get_pixel(nx, ny)[0];
+                on_boundary |= neighbour != 0 && neighbour != label;
+            });
+            if on_boundary {
+                continue;
+            }
+        }
+
+        labels.put_pixel(x, y, Luma([label]));
+        for_each_neighbour(offsets, width, height, x, y, |nx, ny| {
+            if !queued[index(nx, ny)] {
+                queued[index(nx, ny)] = true;
+                let priority = max(level, elevation.get_pixel(nx, ny)[0]);
+                heap.push(Reverse((priority, insertions, nx, ny, label)));
+                insertions += 1;
+            }
+        });
+    }
+
+    labels
+}
+
+/// Options for [`slic`](fn.slic.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SlicOptions {
+    /// Approximate width and height of each superpixel, in pixels. Must be positive.
+    pub region_size: u32,
+    /// Weight of spatial distance relative to color distance. Larger values give more
+    /// regular superpixels which adhere less closely to image edges. Typical values are
+    /// between 1 and 40. Must be positive.
+    pub compactness: f32,
+    /// Number of iterations of k-means clustering.
+    pub iterations: usize,
+}
+
+impl Default for SlicOptions {
+    fn default() -> Self {
+        SlicOptions {
+            region_size: 10,
+            compactness: 10.0,
+            iterations: 10,
+        }
+    }
+}
+
+/// A partition of an image into superpixels, as computed by [`slic`](fn.slic.html).
+#[derive(Clone, Debug)]
+pub struct Superpixels {
+    /// The label of the superpixel containing each pixel. Labels are `0..mean_colors.len()`.
+    pub labels: Image<Luma<u32>>,
+    /// The mean color of each superpixel, indexed by label.
+    pub mean_colors: Vec<Rgb<u8>>,
+}
+
+/// Returns `count` positions spread evenly over `0..length`.
+fn grid_positions(length: u32, count: u32) -> Vec<u32> {
+    (0..count).map(|i| ((i as f32 + 0.5) * length as f32 / count as f32) as u32).collect()
+}
+
+/// Partitions an image into [SLIC superpixels], i.e. compact regions of similar color.
+///
+/// Cluster centres are initialised on a grid with spacing `options.region_size`, and moved
+/// to the position of lowest gradient in their 3x3 neighbourhood. Pixels are then
+/// clustered by k-means on their CIE Lab colors and positions, with each pixel only compared
+/// to centres within `region_size` of it. Finally, any disconnected fragment of a cluster
+/// smaller than a quarter of `region_size * region_size` pixels is merged into an adjacent
+/// superpixel, so that every superpixel is 4-connected.
+///
+/// Returns `ImageProcError::InvalidOptions` if `region_size` or `compactness` is not positive.
+///
+/// [SLIC superpixels]: https://infoscience.epfl.ch/record/177415/files/Superpixel_PAMI2011-2.pdf
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{Rgb, RgbImage};
+/// use imageproc::segmentation::{slic, SlicOptions};
+///
+/// // Red on the left, blue on the right.
+/// let image = RgbImage::from_fn(20, 10, |x, _| if x < 10 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) });
+///
+/// let options = SlicOptions { region_size: 5, ..SlicOptions::default() };
+/// let superpixels = slic(&image, options).unwrap();
+///
+/// // No superpixel crosses the edge between the two colors.
+/// for (x, _, label) in superpixels.labels.enumerate_pixels() {
+///     let mean = superpixels.mean_colors[label[0] as usize];
+///     assert_eq!(mean, if x < 10 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) });
+/// }
+/// # }
+/// ```
+pub fn slic(image: &RgbImage, options: SlicOptions) -> Result<Superpixels> {
+    let mut errors = vec![];
+    if options.region_size == 0 {
+        errors.push("region_size must be positive");
+    }
+    if options.compactness <= 0.0 || options.compactness.is_nan() {
+        errors.push("compactness must be positive");
+    }
+    if !errors.is_empty() {
+        return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+    }
+
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Ok(Superpixels { labels: ImageBuffer::new(width, height), mean_colors: vec![] });
+    }
+
+    let region_size = options.region_size;
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+    let lab: Vec<[f32; 3]> = image.pixels().map(|p| rgb_to_lab(*p)).collect();
+
+    // Centres are stored as [l, a, b, x, y].
+    let gradient = |x: u32, y: u32| {
+        let (left, right) = (x.saturating_sub(1), min(x + 1, width - 1));
+        let (up, down) = (y.saturating_sub(1), min(y + 1, height - 1));
+        squared_distance(&lab[index(right, y)], &lab[index(left, y)])
+            + squared_distance(&lab[index(x, down)], &lab[index(x, up)])
+    };
+    let mut centres: Vec<[f32; 5]> = vec![];
+    let columns = max(1, (width as f32 / region_size as f32).round() as u32);
+    let rows = max(1, (height as f32 / region_size as f32).round() as u32);
+    for &cy in &grid_positions(height, rows) {
+        for &cx in &grid_positions(width, columns) {
+            let mut best = (cx, cy);
+            for y in cy.saturating_sub(1)..min(cy + 2, height) {
+                for x in cx.saturating_sub(1)..min(cx + 2, width) {
+                    if gradient(x, y) < gradient(best.0, best.1) {
+                        best = (x, y);
+                    }
+                }
+            }
+            let c = lab[index(best.0, best.1)];
+            centres.push([c[0], c[1], c[2], best.0 as f32, best.1 as f32]);
+        }
+    }
+
+    let spatial_weight = (options.compactness / region_size as f32).powi(2);
+    let mut clusters = vec![0usize; lab.len()];
+    let mut distances = vec![f32::INFINITY; lab.len()];
+    for _ in 0..options.iterations {
+        for d in distances.iter_mut() {
+            *d = f32::INFINITY;
+        }
+        for (k, centre) in centres.iter().enumerate() {
+            let (cx, cy) = (centre[3], centre[4]);
+            let x_range = (cx - region_size as f32).max(0.0) as u32..min(width, (cx + region_size as f32) as u32 + 1);
+            let y_range = (cy - region_size as f32).max(0.0) as u32..min(height, (cy + region_size as f32) as u32 + 1);
+            for y in y_range {
+                for x in x_range.clone() {
+                    let i = index(x, y);
+                    let spatial = (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+                    let d = squared_distance(&lab[i], &centre[..3]) + spatial_weight * spatial;
+                    if d < distances[i] {
+                        distances[i] = d;
+                        clusters[i] = k;
+                    }
+                }
+            }
+        }
+
+        let mut sums = vec![[0f64; 6]; centres.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let i = index(x, y);
+                let sum = &mut sums[clusters[i]];
+                for c in 0..3 {
+                    sum[c] += lab[i][c] as f64;
+                }
+                sum[3] += x as f64;
+                sum[4] += y as f64;
+                sum[5] += 1.0;
+            }
+        }
+        for (centre, sum) in centres.iter_mut().zip(&sums) {
+            if sum[5] > 0.0 {
+                for c in 0..5 {
+                    centre[c] = (sum[c] / sum[5]) as f32;
+                }
+            }
+        }
+    }
+
+    // Label the 4-connected components of each cluster, merging small components
+    // into a previously labelled neighbour.
+    let min_size = max(1, (region_size * region_size / 4) as usize);
+    let mut labels = vec![u32::MAX; lab.len()];
+    let mut num_labels = 0;
+    let mut component = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            if labels[index(x, y)] != u32::MAX {
+                continue;
+            }
+            let cluster = clusters[index(x, y)];
+            labels[index(x, y)] = num_labels;
+            component.clear();
+            component.push((x, y));
+            let mut next = 0;
+            while next < component.len() {
+                let (px, py) = component[next];
+                next += 1;
+                for_each_neighbour(&FOUR_NEIGHBOURS, width, height, px, py, |nx, ny| {
+                    let i = index(nx, ny);
+                    if labels[i] == u32::MAX && clusters[i] == cluster {
+                        labels[i] = num_labels;
+                        component.push((nx, ny));
+                    }
+                });
+            }
+
+            let mut adjacent = None;
+            if component.len() < min_size {
+                for &(px, py) in &component {
+                    for_each_neighbour(&FOUR_NEIGHBOURS, width, height, px, py, |nx, ny| {
+                        let label = labels[index(nx, ny)];
+                        if adjacent.is_none() && label != u32::MAX && label != num_labels {
+                            adjacent = Some(label);
+                        }
+                    });
+                }
+            }
+            match adjacent {
+                Some(label) => {
+                    for &(px, py) in &component {
+                        labels[index(px, py)] = label;
+                    }
+                }
+                None => num_labels += 1,
+            }
+        }
+    }
+
+    let mut color_sums = vec![[0u64; 4]; num_labels as usize];
+    for (p, &label) in image.pixels().zip(&labels) {
+        let sum = &mut color_sums[label as usize];
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+        sum[3] += 1;
+    }
+    let mean_colors = color_sums
+        .iter()
+        .map(|s| {
+            let mean = |c: usize| ((s[c] as f64 / s[3] as f64).round()) as u8;
+            Rgb([mean(0), mean(1), mean(2)])
+        })
+        .collect();
+
+    Ok(Superpixels {
+        labels: ImageBuffer::from_raw(width, height, labels).unwrap(),
+        mean_colors,
+    })
+}
+
+/// Returns a mask of the pixels connected to `seed` whose channels each differ from
+/// those of the seed pixel by at most `tolerance`. Pixels in the region have value 255
+/// in the mask and all other pixels have value 0.
+///
+/// The region is grown outwards from `seed`, so pixels within tolerance of the seed color
+/// which are only connected to it via pixels outside tolerance are not included.
+///
+/// # Panics
+///
+/// If `seed` lies outside `image`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::region_labelling::Connectivity;
+/// use imageproc::segmentation::region_growing;
+///
+/// let image = gray_image!(
+///     10, 12, 50, 11;
+///     11, 50, 10, 10;
+///     13, 14, 50, 12);
+///
+/// // The pixel at (2, 1) is only diagonally connected to the seed's region.
+/// assert_pixels_eq!(
+///     region_growing(&image, (0, 0), 5.0, Connectivity::Four),
+///     gray_image!(
+///         255, 255, 0, 0;
+///         255,   0, 0, 0;
+///         255, 255, 0, 0));
+///
+/// assert_pixels_eq!(
+///     region_growing(&image, (0, 0), 5.0, Connectivity::Eight),
+///     gray_image!(
+///         255, 255, 0, 255;
+///         255,   0, 255, 255;
+///         255, 255, 0, 255));
+/// # }
+/// ```
+pub fn region_growing<I>(image: &I, seed: (u32, u32), tolerance: f32, connectivity: Connectivity) -> GrayImage
+where
+    I: GenericImageView,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f32>,
+{
+    let (width, height) = image.dimensions();
+    let (x, y) = seed;
+    assert!(x < width && y < height, "seed {:?} is outside image of size {}x{}", seed, width, height);
+
+    let offsets = neighbour_offsets(connectivity);
+    let seed_color = image.get_pixel(x, y);
+    let within_tolerance = |p: I::Pixel| {
+        p.channels().iter().zip(seed_color.channels()).all(|(&c, &s)| {
+            let (c, s): (f32, f32) = (cast(c), cast(s));
+            (c - s).abs() <= tolerance
+        })
+    };
+
+    let mut mask = GrayImage::new(width, height);
+    mask.put_pixel(x, y, Luma([255u8]));
+    let mut stack = vec![seed];
+    while let Some((px, py)) = stack.pop() {
+        for_each_neighbour(offsets, width, height, px, py, |nx, ny| {
+            if mask.get_pixel(nx, ny)[0] == 0 && within_tolerance(image.get_pixel(nx, ny)) {
+                mask.put_pixel(nx, ny, Luma([255u8]));
+                stack.push((nx, ny));
+            }
+        });
+    }
+    mask
+}
+
+/// Replaces the pixels found by [`region_growing`](fn.region_growing.html) with `color`
+/// in a copy of `image`. See [`flood_fill_mut`](fn.flood_fill_mut.html).
+///
+/// # Panics
+///
+/// If `seed` lies outside `image`.
+pub fn flood_fill<I>(
+    image: &I,
+    seed: (u32, u32),
+    color: I::Pixel,
+    tolerance: f32,
+    connectivity: Connectivity,
+) -> Image<I::Pixel>
+where
+    I: GenericImageView,
+    I::Pixel: 'static,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f32>,
+{
+    let mut out = ImageBuffer::new(image.width(), image.height());
+    out.copy_from(image, 0, 0);
+    flood_fill_mut(&mut out, seed, color, tolerance, connectivity);
+    out
+}
+
+/// Replaces the pixels connected to `seed` whose channels each differ from those of the
+/// seed pixel by at most `tolerance` with `color`. A tolerance of 0 fills the region of
+/// exactly the seed color, as in the bucket fill tool of a paint program.
+///
+/// # Panics
+///
+/// If `seed` lies outside `image`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::region_labelling::Connectivity;
+/// use imageproc::segmentation::flood_fill_mut;
+///
+/// let mut image = gray_image!(
+///     0, 0, 9;
+///     9, 0, 9;
+///     0, 9, 0);
+///
+/// flood_fill_mut(&mut image, (0, 0), Luma([5]), 0.0, Connectivity::Four);
+///
+/// assert_pixels_eq!(
+///     image,
+///     gray_image!(
+///         5, 5, 9;
+///         9, 5, 9;
+///         0, 9, 0));
+/// # }
+/// ```
+pub fn flood_fill_mut<I>(image: &mut I, seed: (u32, u32), color: I::Pixel, tolerance: f32, connectivity: Connectivity)
+where
+    I: GenericImage,
+    <I::Pixel as Pixel>::Subpixel: ValueInto<f32>,
+{
+    let mask = region_growing(&*image, seed, tolerance, connectivity);
+    for (x, y, m) in mask.enumerate_pixels() {
+        if m[0] > 0 {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::GrayImage;
+    use region_labelling::connected_components;
+    use utils::{gray_bench_image, rgb_bench_image};
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_watershed_without_lines_labels_every_pixel() {
+        let elevation = gray_image!(
+            0, 1, 9, 1, 0;
+            0, 1, 9, 1, 0;
+            0, 1, 9, 1, 0);
+
+        let markers = gray_image!(type: u32,
+            0, 0, 0, 0, 0;
+            1, 0, 0, 0, 2;
+            0, 0, 0, 0, 0);
+
+        let labels = watershed(&elevation, &markers, Connectivity::Four, false);
+        assert!(labels.pixels().all(|p| p[0] == 1 || p[0] == 2));
+        for y in 0..3 {
+            assert_eq!(labels.get_pixel(0, y)[0], 1);
+            assert_eq!(labels.get_pixel(1, y)[0], 1);
+            assert_eq!(labels.get_pixel(3, y)[0], 2);
+            assert_eq!(labels.get_pixel(4, y)[0], 2);
+        }
+    }
+
+    #[test]
+    fn test_watershed_follows_ridges() {
+        // A diagonal ridge separates the top right from the bottom left.
+        let elevation = gray_image!(
+            0, 9, 0, 0;
+            0, 0, 9, 0;
+            0, 0, 0, 9;
+            0, 0, 0, 0);
+
+        let markers = gray_image!(type: u32,
+            0, 0, 0, 2;
+            0, 0, 0, 0;
+            1, 0, 0, 0;
+            0, 0, 0, 0);
+
+        let expected = gray_image!(type: u32,
+            1, 0, 2, 2;
+            1, 1, 0, 2;
+            1, 1, 1, 0;
+            1, 1, 1, 1);
+
+        assert_pixels_eq!(watershed(&elevation, &markers, Connectivity::Four, true), expected);
+    }
+
+    #[test]
+    fn test_watershed_keeps_markers() {
+        let elevation = gray_bench_image(10, 8);
+        let mut markers: Image<Luma<u32>> = ImageBuffer::new(10, 8);
+        markers.put_pixel(1, 1, Luma([3]));
+        markers.put_pixel(8, 6, Luma([7]));
+        for &mark_lines in &[false, true] {
+            let labels = watershed(&elevation, &markers, Connectivity::Eight, mark_lines);
+            assert_eq!(labels.get_pixel(1, 1)[0], 3);
+            assert_eq!(labels.get_pixel(8, 6)[0], 7);
+            assert!(labels.pixels().all(|p| [0, 3, 7].contains(&p[0])));
+        }
+    }
+
+    #[test]
+    fn test_watershed_no_markers() {
+        let elevation = gray_bench_image(4, 3);
+        let markers: Image<Luma<u32>> = ImageBuffer::new(4, 3);
+        let labels = watershed(&elevation, &markers, Connectivity::Four, false);
+        assert!(labels.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_watershed_mismatched_dimensions() {
+        let elevation = GrayImage::new(3, 2);
+        let markers: Image<Luma<u32>> = ImageBuffer::new(2, 3);
+        watershed(&elevation, &markers, Connectivity::Four, false);
+    }
+
+    fn two_color_image() -> RgbImage {
+        RgbImage::from_fn(30, 20, |x, y| {
+            if x + y < 25 { Rgb([200, 40, 40]) } else { Rgb([30, 60, 220]) }
+        })
+    }
+
+    #[test]
+    fn test_slic_labels_are_connected_and_contiguous() {
+        let image = two_color_image();
+        let options = SlicOptions { region_size: 6, ..SlicOptions::default() };
+        let superpixels = slic(&image, options).unwrap();
+        let num_labels = superpixels.mean_colors.len();
+        assert!(num_labels >= 10);
+
+        let mut used = vec![false; num_labels];
+        for p in superpixels.labels.pixels() {
+            used[p[0] as usize] = true;
+        }
+        assert!(used.iter().all(|&u| u));
+
+        // Shift labels so that none equal the background value 0.
+        let shifted: Image<Luma<u32>> = ImageBuffer::from_fn(30, 20, |x, y| {
+            Luma([superpixels.labels.get_pixel(x, y)[0] + 1])
+        });
+        let components = connected_components(&shifted, Connectivity::Four, Luma([0u32]));
+        let num_components = components.pixels().map(|p| p[0]).max().unwrap() as usize;
+        assert_eq!(num_components, num_labels);
+    }
+
+    #[test]
+    fn test_slic_respects_edges() {
+        let image = two_color_image();
+        let options = SlicOptions { region_size: 6, ..SlicOptions::default() };
+        let superpixels = slic(&image, options).unwrap();
+        for (x, y, label) in superpixels.labels.enumerate_pixels() {
+            assert_eq!(superpixels.mean_colors[label[0] as usize], *image.get_pixel(x, y));
+        }
+    }
+
+    #[test]
+    fn test_slic_invalid_options() {
+        let image = two_color_image();
+        assert!(slic(&image, SlicOptions { region_size: 0, ..SlicOptions::default() }).is_err());
+        assert!(slic(&image, SlicOptions { compactness: 0.0, ..SlicOptions::default() }).is_err());
+    }
+
+    #[test]
+    fn test_slic_small_images() {
+        let empty = slic(&RgbImage::new(0, 5), SlicOptions::default()).unwrap();
+        assert!(empty.mean_colors.is_empty());
+
+        let single = slic(&RgbImage::from_pixel(1, 1, Rgb([1, 2, 3])), SlicOptions::default()).unwrap();
+        assert_eq!(single.mean_colors, vec![Rgb([1, 2, 3])]);
+        assert_eq!(single.labels.get_pixel(0, 0)[0], 0);
+    }
+
+    #[test]
+    fn test_region_growing_rgb_tolerance() {
+        let image = rgb_image!(
+            [100, 100, 100], [104, 97, 100], [100, 100, 110];
+            [100, 100, 100], [100, 100, 100], [100, 100, 100]);
+
+        // Every channel must be within tolerance.
+        assert_pixels_eq!(
+            region_growing(&image, (0, 0), 5.0, Connectivity::Four),
+            gray_image!(
+                255, 255, 0;
+                255, 255, 255));
+
+        assert_pixels_eq!(
+            region_growing(&image, (0, 0), 10.0, Connectivity::Four),
+            gray_image!(
+                255, 255, 255;
+                255, 255, 255));
+    }
+
+    #[test]
+    fn test_region_growing_compares_with_seed_color() {
+        // Each step is within tolerance of the previous pixel, but not of the seed.
+        let image = gray_image!(0, 3, 6, 9, 12);
+        assert_pixels_eq!(
+            region_growing(&image, (0, 0), 4.0, Connectivity::Four),
+            gray_image!(255, 255, 0, 0, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_region_growing_seed_outside_image() {
+        let image = GrayImage::new(3, 3);
+        region_growing(&image, (3, 0), 0.0, Connectivity::Four);
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let image = gray_image!(
+            1, 1, 7;
+            7, 1, 7;
+            1, 7, 1);
+
+        assert_pixels_eq!(
+            flood_fill(&image, (1, 1), Luma([4]), 0.0, Connectivity::Four),
+            gray_image!(
+                4, 4, 7;
+                7, 4, 7;
+                1, 7, 1));
+
+        assert_pixels_eq!(
+            flood_fill(&image, (1, 1), Luma([4]), 0.0, Connectivity::Eight),
+            gray_image!(
+                4, 4, 7;
+                7, 4, 7;
+                4, 7, 4));
+    }
+
+    #[bench]
+    fn bench_flood_fill(b: &mut Bencher) {
+        let image = GrayImage::from_fn(200, 200, |x, y| Luma([((x / 10 + y / 10) % 2) as u8]));
+        b.iter(|| {
+            let filled = flood_fill(&image, (0, 0), Luma([255]), 0.0, Connectivity::Eight);
+            black_box(filled);
+        });
+    }
+
+    #[bench]
+    fn bench_slic(b: &mut Bencher) {
+        let image = rgb_bench_image(200, 200);
+        b.iter(|| {
+            let superpixels = slic(&image, SlicOptions::default()).unwrap();
+            black_box(superpixels);
+        });
+    }
+
+    #[bench]
+    fn bench_watershed(b: &mut Bencher) {
+        let elevation = gray_bench_image(200, 200);
+        let mut markers: Image<Luma<u32>> = ImageBuffer::new(200, 200);
+        for i in 0..10 {
+            markers.put_pixel(20 * i + 5, 20 * i + 5, Luma([i + 1]));
+        }
+        b.iter(|| {
+            let labels = watershed(&elevation, &markers, Connectivity::Four, true);
+            black_box(labels);
+        });
+    }
+}
@@ -0,0 +1,225 @@
+//! Functions for matching shapes given as binary edge images or point sets.
+//!
+//! [`chamfer_match`](fn.chamfer_match.html) slides a binary template over an edge image
+//! and scores each placement by the mean distance from the template's edge pixels to the
+//! nearest edge in the image. [`hausdorff_distance`](fn.hausdorff_distance.html) and
+//! [`directed_hausdorff_distance`](fn.directed_hausdorff_distance.html) compare two
+//! point sets directly.
+
+use image::{GrayImage, Luma};
+use definitions::Image;
+use distance_transform::euclidean_distance_transform;
+use error::{ImageProcError, Result};
+
+/// Slides a binary `template` over a binary `edges` image and returns the chamfer
+/// distance of each placement.
+///
+/// Pixels with non-zero intensity are treated as edges in both images. The score at
+/// `(x, y)` is the mean Euclidean distance from each edge pixel of the template, placed
+/// with its top-left corner at `(x, y)`, to the nearest edge pixel of `edges`. Lower
+/// scores indicate better matches, and a score of 0 means that every template edge lies
+/// on an image edge. All scores are infinite if `edges` contains no edge pixels.
+///
+/// The returned image has dimensions `edges.width() - template.width() + 1` by
+/// `edges.height() - template.height() + 1`. Use
+/// [`chamfer_match_with_distances`](fn.chamfer_match_with_distances.html) to reuse the
+/// distance transform of `edges` when matching several templates.
+///
+/// Returns `ImageProcError::KernelTooLarge` if either dimension of `template` is larger
+/// than the corresponding dimension of `edges`, and `ImageProcError::InvalidOptions` if
+/// `template` contains no edge pixels.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::shape_matching::chamfer_match;
+///
+/// let edges = gray_image!(
+///     0, 0, 0, 0, 0;
+///     0, 0, 1, 1, 0;
+///     0, 0, 1, 0, 0;
+///     0, 0, 0, 0, 0);
+///
+/// let template = gray_image!(
+///     1, 1;
+///     1, 0);
+///
+/// let scores = chamfer_match(&edges, &template).unwrap();
+/// assert_eq!(scores.dimensions(), (4, 3));
+/// assert_eq!(scores.get_pixel(2, 1)[0], 0.0);
+/// assert!(scores.get_pixel(1, 1)[0] > 0.0);
+/// # }
+/// ```
+pub fn chamfer_match(edges: &GrayImage, template: &GrayImage) -> Result<Image<Luma<f32>>> {
+    check_template(edges.dimensions(), template)?;
+    let distances = euclidean_distance_transform(edges);
+    chamfer_match_with_distances(&distances, template)
+}
+
+/// Computes the same scores as [`chamfer_match`](fn.chamfer_match.html), given the
+/// [`euclidean_distance_transform`](../distance_transform/fn.euclidean_distance_transform.html)
+/// of the edge image.
+///
+/// Returns `ImageProcError::KernelTooLarge` if either dimension of `template` is larger
+/// than the corresponding dimension of `distances`, and `ImageProcError::InvalidOptions`
+/// if `template` contains no edge pixels.
+pub fn chamfer_match_with_distances(distances: &Image<Luma<f32>>, template: &GrayImage) -> Result<Image<Luma<f32>>> {
+    check_template(distances.dimensions(), template)?;
+    let (width, height) = distances.dimensions();
+    let (template_width, template_height) = template.dimensions();
+
+    let offsets: Vec<(u32, u32)> = template
+        .enumerate_pixels()
+        .filter(|&(_, _, p)| p[0] != 0)
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    let count = offsets.len() as f32;
+
+    let mut scores = Image::new(width - template_width + 1, height - template_height + 1);
+    for (x, y, score) in scores.enumerate_pixels_mut() {
+        let sum: f32 = offsets
+            .iter()
+            .map(|&(dx, dy)| distances.get_pixel(x + dx, y + dy)[0])
+            .sum();
+        *score = Luma([sum / count]);
+    }
+    Ok(scores)
+}
+
+fn check_template(image: (u32, u32), template: &GrayImage) -> Result<()> {
+    let (template_width, template_height) = template.dimensions();
+    if template_width > image.0 || template_height > image.1 {
+        return Err(ImageProcError::KernelTooLarge {
+            kernel: (template_width, template_height),
+            image,
+        });
+    }
+    if template.iter().all(|p| *p == 0) {
+        return Err(ImageProcError::InvalidOptions(
+            "template must contain at least one edge pixel".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the directed Hausdorff distance from `from` to `to`: the largest Euclidean
+/// distance from a point of `from` to its nearest point in `to`.
+///
+/// This is not symmetric; see [`hausdorff_distance`](fn.hausdorff_distance.html).
+/// Returns 0 if `from` is empty, and infinity if `from` is non-empty and `to` is empty.
+///
+/// # Examples
+/// ```
+/// use imageproc::shape_matching::directed_hausdorff_distance;
+///
+/// let a = [(0.0, 0.0), (1.0, 0.0)];
+/// let b = [(0.0, 0.0), (1.0, 0.0), (1.0, 4.0)];
+///
+/// // Every point of a is in b, but (1, 4) is at distance 4 from a.
+/// assert_eq!(directed_hausdorff_distance(&a, &b), 0.0);
+/// assert_eq!(directed_hausdorff_distance(&b, &a), 4.0);
+/// ```
+pub fn directed_hausdorff_distance(from: &[(f32, f32)], to: &[(f32, f32)]) -> f32 {
+    let mut max_squared = 0f32;
+    for &(fx, fy) in from {
+        let mut min_squared = ::std::f32::INFINITY;
+        for &(tx, ty) in to {
+            let d = (fx - tx) * (fx - tx) + (fy - ty) * (fy - ty);
+            if d < min_squared {
+                min_squared = d;
+                // The maximum can't increase from this point of from.
+                if min_squared <= max_squared {
+                    break;
+                }
+            }
+        }
+        max_squared = max_squared.max(min_squared);
+    }
+    max_squared.sqrt()
+}
+
+/// Returns the Hausdorff distance between two point sets: the larger of the two
+/// [`directed_hausdorff_distance`](fn.directed_hausdorff_distance.html)s between them.
+///
+/// Returns 0 if both sets are empty, and infinity if exactly one is.
+pub fn hausdorff_distance(a: &[(f32, f32)], b: &[(f32, f32)]) -> f32 {
+    directed_hausdorff_distance(a, b).max(directed_hausdorff_distance(b, a))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::GrayImage;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_chamfer_match_matches_brute_force() {
+        let edges = gray_image!(
+            0, 1, 0, 0, 0, 0;
+            0, 1, 0, 1, 0, 0;
+            0, 0, 0, 1, 1, 0;
+            1, 0, 0, 0, 0, 0);
+        let template = gray_image!(
+            1, 0, 0;
+            1, 1, 0);
+
+        let edge_points: Vec<(f32, f32)> = edges
+            .enumerate_pixels()
+            .filter(|&(_, _, p)| p[0] != 0)
+            .map(|(x, y, _)| (x as f32, y as f32))
+            .collect();
+
+        let scores = chamfer_match(&edges, &template).unwrap();
+        assert_eq!(scores.dimensions(), (4, 3));
+        for (x, y, score) in scores.enumerate_pixels() {
+            let mut sum = 0.0;
+            for &(dx, dy) in &[(0, 0), (0, 1), (1, 1)] {
+                let p = ((x + dx) as f32, (y + dy) as f32);
+                sum += directed_hausdorff_distance(&[p], &edge_points);
+            }
+            assert!((score[0] - sum / 3.0).abs() < 1e-5, "({}, {})", x, y);
+        }
+        assert_eq!(scores.get_pixel(3, 1)[0], 0.0);
+    }
+
+    #[test]
+    fn test_chamfer_match_invalid_inputs() {
+        let edges = GrayImage::from_pixel(3, 3, Luma([1]));
+        match chamfer_match(&edges, &GrayImage::from_pixel(4, 2, Luma([1]))) {
+            Err(ImageProcError::KernelTooLarge { .. }) => {}
+            other => panic!("expected KernelTooLarge, got {:?}", other),
+        }
+        match chamfer_match(&edges, &GrayImage::new(2, 2)) {
+            Err(ImageProcError::InvalidOptions(_)) => {}
+            other => panic!("expected InvalidOptions, got {:?}", other),
+        }
+        let scores = chamfer_match(&edges, &GrayImage::from_pixel(3, 3, Luma([1]))).unwrap();
+        assert_eq!(scores.dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn test_hausdorff_distance() {
+        let a = [(0.0, 0.0), (3.0, 0.0)];
+        let b = [(0.0, 1.0), (3.0, 4.0), (1.0, 0.0)];
+        assert_eq!(directed_hausdorff_distance(&a, &b), 2.0);
+        assert_eq!(directed_hausdorff_distance(&b, &a), 4.0);
+        assert_eq!(hausdorff_distance(&a, &b), 4.0);
+        assert_eq!(hausdorff_distance(&b, &a), 4.0);
+
+        assert_eq!(hausdorff_distance(&[], &[]), 0.0);
+        assert_eq!(directed_hausdorff_distance(&[], &a), 0.0);
+        assert!(hausdorff_distance(&a, &[]).is_infinite());
+    }
+
+    #[bench]
+    fn bench_chamfer_match(b: &mut Bencher) {
+        let image = gray_bench_image(100, 100);
+        let edges = GrayImage::from_fn(100, 100, |x, y| Luma([(image.get_pixel(x, y)[0] > 200) as u8]));
+        let template = GrayImage::from_fn(10, 10, |x, y| Luma([(x == 0 || y == 9) as u8]));
+        b.iter(|| black_box(chamfer_match(&edges, &template).unwrap()));
+    }
+}
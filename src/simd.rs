@@ -0,0 +1,671 @@
+//! Vectorised inner loops for filtering, integral images and Haar-like features.
+//!
+//! Each function computes as many leading entries of its output as the instruction sets
+//! available on the current machine allow, and returns the number of entries written.
+//! Callers are responsible for computing any remaining entries, and the entries written
+//! are identical to those computed by the scalar code.
+//!
+//! On x86_64 the available instruction sets are detected at runtime. WebAssembly has no
+//! runtime feature detection, so on wasm32 the `simd128` correlation loops are used only if
+//! the crate is compiled with that target feature enabled (e.g.
+//! `RUSTFLAGS="-C target-feature=+simd128"`). The other loops are only vectorised on x86_64.
+//!
+//! All loops can be disabled at runtime using
+//! [`bench_utils::set_simd_enabled`](../bench_utils/fn.set_simd_enabled.html), so that
+//! benchmarks can compare them with the scalar code.
+
+use std::any::TypeId;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enables or disables every function in this module. Disabled functions write nothing.
+pub(crate) fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// The correlation functions compute
+//
+// `out[x] = sum over (k_y, k_x) of kernel[k_y * k_width + k_x] * rows[k_y][x + k_x]`
+//
+// accumulating the terms of each sum in the same order as in `Kernel::filter` and
+// without fused multiply-adds, so that results are identical to the scalar code.
+
+/// Correlates rows of `u8`s with an `i32` kernel.
+pub(crate) fn correlate_u8_i32(rows: &[&[u8]], kernel: &[i32], k_width: usize, out: &mut [i32]) -> usize {
+    check_dimensions(rows, kernel, k_width, out.len());
+    if !enabled() {
+        return 0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::correlate_u8_i32_avx2(rows, kernel, k_width, out) };
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return unsafe { x86::correlate_u8_i32_sse41(rows, kernel, k_width, out) };
+        }
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        return unsafe { wasm::correlate_u8_i32_simd128(rows, kernel, k_width, out) };
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+/// Correlates rows of `u8`s with an `f32` kernel.
+pub(crate) fn correlate_u8_f32(rows: &[&[u8]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+    check_dimensions(rows, kernel, k_width, out.len());
+    if !enabled() {
+        return 0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::correlate_u8_f32_avx2(rows, kernel, k_width, out) };
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return unsafe { x86::correlate_u8_f32_sse41(rows, kernel, k_width, out) };
+        }
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        return unsafe { wasm::correlate_u8_f32_simd128(rows, kernel, k_width, out) };
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+/// Correlates rows of `f32`s with an `f32` kernel.
+pub(crate) fn correlate_f32_f32(rows: &[&[f32]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+    check_dimensions(rows, kernel, k_width, out.len());
+    if !enabled() {
+        return 0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            return unsafe { x86::correlate_f32_f32_avx(rows, kernel, k_width, out) };
+        }
+        // SSE is part of the x86_64 baseline.
+        return unsafe { x86::correlate_f32_f32_sse(rows, kernel, k_width, out) };
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        return unsafe { wasm::correlate_f32_f32_simd128(rows, kernel, k_width, out) };
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+/// Writes `numerators[x] / divisor`, rounded down, to `out[x]`. Each quotient must be at most 255.
+pub(crate) fn divide_u32_u8(numerators: &[u32], divisor: u32, out: &mut [u8]) -> usize {
+    assert!(divisor > 0 && numerators.len() == out.len());
+    // The vectorised loops correct an estimated quotient using exact float arithmetic,
+    // which requires every numerator, and so every divisor times 256, to be below 2^24.
+    if !enabled() || divisor >= 1 << 16 {
+        return 0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            return unsafe { x86::divide_u32_u8_avx(numerators, divisor, out) };
+        }
+        // SSE2 is part of the x86_64 baseline.
+        return unsafe { x86::divide_u32_u8_sse2(numerators, divisor, out) };
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+/// Writes `initial` plus the running sums of `row`, or of its squares if `square` is true,
+/// to `out`, wrapping on overflow.
+pub(crate) fn prefix_sum_u8_u32(row: &[u8], square: bool, initial: u32, out: &mut [u32]) -> usize {
+    assert_eq!(row.len(), out.len());
+    if !enabled() {
+        return 0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        return unsafe { x86::prefix_sum_u8_u32_sse2(row, square, initial, out) };
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+/// Computes `out[x] = sum over i of weights[i] * rows[i][x]`, wrapping on overflow.
+pub(crate) fn weighted_sum_u32_i32(rows: &[&[u32]], weights: &[i32], out: &mut [i32]) -> usize {
+    assert_eq!(rows.len(), weights.len());
+    for row in rows {
+        assert!(row.len() >= out.len());
+    }
+    if !enabled() {
+        return 0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::weighted_sum_u32_i32_avx2(rows, weights, out) };
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return unsafe { x86::weighted_sum_u32_i32_sse41(rows, weights, out) };
+        }
+    }
+
+    #[allow(unreachable_code)]
+    0
+}
+
+// Whether T and U are the same type.
+pub(crate) fn is_type<T: 'static, U: 'static>() -> bool {
+    TypeId::of::<T>() == TypeId::of::<U>()
+}
+
+// Reinterprets a slice of T as a slice of U. Panics unless T and U are the same type.
+pub(crate) fn cast_slice<T: 'static, U: 'static>(slice: &[T]) -> &[U] {
+    assert!(is_type::<T, U>());
+    unsafe { slice::from_raw_parts(slice.as_ptr() as *const U, slice.len()) }
+}
+
+// Reinterprets a mutable slice of T as a mutable slice of U. Panics unless T and U are the same type.
+pub(crate) fn cast_slice_mut<T: 'static, U: 'static>(slice: &mut [T]) -> &mut [U] {
+    assert!(is_type::<T, U>());
+    unsafe { slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut U, slice.len()) }
+}
+
+pub(crate) fn cast_rows<'a, T: 'static, U: 'static>(rows: &[&'a [T]]) -> Vec<&'a [U]> {
+    rows.iter().map(|r| cast_slice(r)).collect()
+}
+
+// The vectorised loops don't perform bounds checks, so we need to validate their inputs.
+fn check_dimensions<T, K>(rows: &[&[T]], kernel: &[K], k_width: usize, out_len: usize) {
+    assert!(k_width > 0 && kernel.len() == rows.len() * k_width);
+    for row in rows {
+        assert!(row.len() + 1 >= out_len + k_width);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+    use std::ptr;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn correlate_u8_i32_avx2(rows: &[&[u8]], kernel: &[i32], k_width: usize, out: &mut [i32]) -> usize {
+        let n = out.len() - out.len() % 8;
+        for x in (0..n).step_by(8) {
+            let mut acc = _mm256_setzero_si256();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = _mm256_set1_epi32(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = _mm_loadl_epi64(row.as_ptr().add(x + k_x) as *const __m128i);
+                    let p = _mm256_cvtepu8_epi32(p);
+                    acc = _mm256_add_epi32(acc, _mm256_mullo_epi32(p, k));
+                }
+            }
+            _mm256_storeu_si256(out.as_mut_ptr().add(x) as *mut __m256i, acc);
+        }
+        n
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn correlate_u8_i32_sse41(rows: &[&[u8]], kernel: &[i32], k_width: usize, out: &mut [i32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = _mm_setzero_si128();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = _mm_set1_epi32(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = load_4_u8(row.as_ptr().add(x + k_x));
+                    acc = _mm_add_epi32(acc, _mm_mullo_epi32(p, k));
+                }
+            }
+            _mm_storeu_si128(out.as_mut_ptr().add(x) as *mut __m128i, acc);
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn correlate_u8_f32_avx2(rows: &[&[u8]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+        let n = out.len() - out.len() % 8;
+        for x in (0..n).step_by(8) {
+            let mut acc = _mm256_setzero_ps();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = _mm256_set1_ps(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = _mm_loadl_epi64(row.as_ptr().add(x + k_x) as *const __m128i);
+                    let p = _mm256_cvtepi32_ps(_mm256_cvtepu8_epi32(p));
+                    acc = _mm256_add_ps(acc, _mm256_mul_ps(p, k));
+                }
+            }
+            _mm256_storeu_ps(out.as_mut_ptr().add(x), acc);
+        }
+        n
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn correlate_u8_f32_sse41(rows: &[&[u8]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = _mm_setzero_ps();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = _mm_set1_ps(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = _mm_cvtepi32_ps(load_4_u8(row.as_ptr().add(x + k_x)));
+                    acc = _mm_add_ps(acc, _mm_mul_ps(p, k));
+                }
+            }
+            _mm_storeu_ps(out.as_mut_ptr().add(x), acc);
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx")]
+    pub unsafe fn correlate_f32_f32_avx(rows: &[&[f32]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+        let n = out.len() - out.len() % 8;
+        for x in (0..n).step_by(8) {
+            let mut acc = _mm256_setzero_ps();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = _mm256_set1_ps(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = _mm256_loadu_ps(row.as_ptr().add(x + k_x));
+                    acc = _mm256_add_ps(acc, _mm256_mul_ps(p, k));
+                }
+            }
+            _mm256_storeu_ps(out.as_mut_ptr().add(x), acc);
+        }
+        n
+    }
+
+    pub unsafe fn correlate_f32_f32_sse(rows: &[&[f32]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = _mm_setzero_ps();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = _mm_set1_ps(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = _mm_loadu_ps(row.as_ptr().add(x + k_x));
+                    acc = _mm_add_ps(acc, _mm_mul_ps(p, k));
+                }
+            }
+            _mm_storeu_ps(out.as_mut_ptr().add(x), acc);
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx")]
+    pub unsafe fn divide_u32_u8_avx(numerators: &[u32], divisor: u32, out: &mut [u8]) -> usize {
+        let n = out.len() - out.len() % 8;
+        let d = _mm256_set1_ps(divisor as f32);
+        let inv = _mm256_set1_ps(1.0 / divisor as f32);
+        let (zero, one) = (_mm256_setzero_ps(), _mm256_set1_ps(1.0));
+        for x in (0..n).step_by(8) {
+            let s = _mm256_cvtepi32_ps(_mm256_loadu_si256(numerators.as_ptr().add(x) as *const __m256i));
+            // The estimate is off by at most one, and the remainder is computed exactly.
+            let q = _mm256_cvtepi32_ps(_mm256_cvttps_epi32(_mm256_mul_ps(s, inv)));
+            let r = _mm256_sub_ps(s, _mm256_mul_ps(q, d));
+            let q = _mm256_sub_ps(q, _mm256_and_ps(_mm256_cmp_ps(r, zero, _CMP_LT_OQ), one));
+            let q = _mm256_add_ps(q, _mm256_and_ps(_mm256_cmp_ps(r, d, _CMP_GE_OQ), one));
+            let q = _mm256_cvttps_epi32(q);
+            let q = _mm_packs_epi32(_mm256_castsi256_si128(q), _mm256_extractf128_si256(q, 1));
+            _mm_storel_epi64(out.as_mut_ptr().add(x) as *mut __m128i, _mm_packus_epi16(q, q));
+        }
+        n
+    }
+
+    pub unsafe fn divide_u32_u8_sse2(numerators: &[u32], divisor: u32, out: &mut [u8]) -> usize {
+        let n = out.len() - out.len() % 4;
+        let d = _mm_set1_ps(divisor as f32);
+        let inv = _mm_set1_ps(1.0 / divisor as f32);
+        let (zero, one) = (_mm_setzero_ps(), _mm_set1_ps(1.0));
+        for x in (0..n).step_by(4) {
+            let s = _mm_cvtepi32_ps(_mm_loadu_si128(numerators.as_ptr().add(x) as *const __m128i));
+            // The estimate is off by at most one, and the remainder is computed exactly.
+            let q = _mm_cvtepi32_ps(_mm_cvttps_epi32(_mm_mul_ps(s, inv)));
+            let r = _mm_sub_ps(s, _mm_mul_ps(q, d));
+            let q = _mm_sub_ps(q, _mm_and_ps(_mm_cmplt_ps(r, zero), one));
+            let q = _mm_add_ps(q, _mm_and_ps(_mm_cmpge_ps(r, d), one));
+            let q = _mm_cvttps_epi32(q);
+            let q = _mm_packs_epi32(q, q);
+            let bytes = _mm_cvtsi128_si32(_mm_packus_epi16(q, q));
+            ptr::write_unaligned(out.as_mut_ptr().add(x) as *mut i32, bytes);
+        }
+        n
+    }
+
+    pub unsafe fn prefix_sum_u8_u32_sse2(row: &[u8], square: bool, initial: u32, out: &mut [u32]) -> usize {
+        let n = row.len() - row.len() % 16;
+        let zero = _mm_setzero_si128();
+        let mut carry = _mm_set1_epi32(initial as i32);
+        for x in (0..n).step_by(16) {
+            let bytes = _mm_loadu_si128(row.as_ptr().add(x) as *const __m128i);
+            for (i, &half) in [_mm_unpacklo_epi8(bytes, zero), _mm_unpackhi_epi8(bytes, zero)].iter().enumerate() {
+                // Squares of u8s fit in u16 lanes.
+                let half = if square { _mm_mullo_epi16(half, half) } else { half };
+                for (j, &quarter) in [_mm_unpacklo_epi16(half, zero), _mm_unpackhi_epi16(half, zero)].iter().enumerate() {
+                    let v = _mm_add_epi32(quarter, _mm_slli_si128(quarter, 4));
+                    let v = _mm_add_epi32(v, _mm_slli_si128(v, 8));
+                    let v = _mm_add_epi32(v, carry);
+                    _mm_storeu_si128(out.as_mut_ptr().add(x + 8 * i + 4 * j) as *mut __m128i, v);
+                    carry = _mm_shuffle_epi32(v, 0xff);
+                }
+            }
+        }
+        n
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn weighted_sum_u32_i32_avx2(rows: &[&[u32]], weights: &[i32], out: &mut [i32]) -> usize {
+        let n = out.len() - out.len() % 8;
+        for x in (0..n).step_by(8) {
+            let mut acc = _mm256_setzero_si256();
+            for (row, &w) in rows.iter().zip(weights) {
+                let p = _mm256_loadu_si256(row.as_ptr().add(x) as *const __m256i);
+                acc = _mm256_add_epi32(acc, _mm256_mullo_epi32(p, _mm256_set1_epi32(w)));
+            }
+            _mm256_storeu_si256(out.as_mut_ptr().add(x) as *mut __m256i, acc);
+        }
+        n
+    }
+
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn weighted_sum_u32_i32_sse41(rows: &[&[u32]], weights: &[i32], out: &mut [i32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = _mm_setzero_si128();
+            for (row, &w) in rows.iter().zip(weights) {
+                let p = _mm_loadu_si128(row.as_ptr().add(x) as *const __m128i);
+                acc = _mm_add_epi32(acc, _mm_mullo_epi32(p, _mm_set1_epi32(w)));
+            }
+            _mm_storeu_si128(out.as_mut_ptr().add(x) as *mut __m128i, acc);
+        }
+        n
+    }
+
+    // Loads four bytes starting at p and zero-extends them to i32 lanes.
+    #[inline]
+    #[target_feature(enable = "sse4.1")]
+    unsafe fn load_4_u8(p: *const u8) -> __m128i {
+        let bytes = ptr::read_unaligned(p as *const i32);
+        _mm_cvtepu8_epi32(_mm_cvtsi32_si128(bytes))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+    use std::arch::wasm32::*;
+    use std::ptr;
+
+    pub unsafe fn correlate_u8_i32_simd128(rows: &[&[u8]], kernel: &[i32], k_width: usize, out: &mut [i32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = i32x4_splat(0);
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = i32x4_splat(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = load_4_u8(row.as_ptr().add(x + k_x));
+                    acc = i32x4_add(acc, i32x4_mul(p, k));
+                }
+            }
+            v128_store(out.as_mut_ptr().add(x) as *mut v128, acc);
+        }
+        n
+    }
+
+    pub unsafe fn correlate_u8_f32_simd128(rows: &[&[u8]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = f32x4_splat(0.0);
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = f32x4_splat(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = f32x4_convert_i32x4(load_4_u8(row.as_ptr().add(x + k_x)));
+                    acc = f32x4_add(acc, f32x4_mul(p, k));
+                }
+            }
+            v128_store(out.as_mut_ptr().add(x) as *mut v128, acc);
+        }
+        n
+    }
+
+    pub unsafe fn correlate_f32_f32_simd128(rows: &[&[f32]], kernel: &[f32], k_width: usize, out: &mut [f32]) -> usize {
+        let n = out.len() - out.len() % 4;
+        for x in (0..n).step_by(4) {
+            let mut acc = f32x4_splat(0.0);
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    let k = f32x4_splat(*kernel.get_unchecked(k_y * k_width + k_x));
+                    let p = v128_load(row.as_ptr().add(x + k_x) as *const v128);
+                    acc = f32x4_add(acc, f32x4_mul(p, k));
+                }
+            }
+            v128_store(out.as_mut_ptr().add(x) as *mut v128, acc);
+        }
+        n
+    }
+
+    // Loads four bytes starting at p and zero-extends them to i32 lanes.
+    #[inline]
+    unsafe fn load_4_u8(p: *const u8) -> v128 {
+        let bytes = ptr::read_unaligned(p as *const u32);
+        u32x4_extend_low_u16x8(u16x8_extend_low_u8x16(u32x4(bytes, 0, 0, 0)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::{quickcheck, TestResult};
+
+    // Computes every entry of out using the same order of operations as Kernel::filter.
+    fn correlate_reference<T, K, F>(rows: &[&[T]], kernel: &[K], k_width: usize, out: &mut [K], cast: F)
+    where
+        T: Copy,
+        K: Copy + ::std::ops::Add<Output = K> + ::std::ops::Mul<Output = K> + Default,
+        F: Fn(T) -> K,
+    {
+        for x in 0..out.len() {
+            let mut acc = K::default();
+            for (k_y, row) in rows.iter().enumerate() {
+                for k_x in 0..k_width {
+                    acc = acc + cast(row[x + k_x]) * kernel[k_y * k_width + k_x];
+                }
+            }
+            out[x] = acc;
+        }
+    }
+
+    // Checks that each vectorised function agrees with the reference implementation
+    // on rows of the given width built by repeating data, for a k_width x k_height kernel.
+    fn check_all(data: &[u8], width: usize, k_width: usize, k_height: usize) -> TestResult {
+        if data.is_empty() || width < k_width {
+            return TestResult::discard();
+        }
+        let data: Vec<u8> = data.iter().cloned().cycle().take(width * k_height).collect();
+        let out_len = width + 1 - k_width;
+        let rows_u8: Vec<&[u8]> = data.chunks(width).collect();
+        let data_f32: Vec<f32> = data.iter().map(|&b| b as f32 * 0.37 - 11.0).collect();
+        let rows_f32: Vec<&[f32]> = data_f32.chunks(width).collect();
+        let kernel_i32: Vec<i32> = (0..k_width * k_height).map(|i| i as i32 * 7 - 20).collect();
+        let kernel_f32: Vec<f32> = kernel_i32.iter().map(|&k| k as f32 * 0.13).collect();
+
+        let mut expected = vec![0i32; out_len];
+        correlate_reference(&rows_u8, &kernel_i32, k_width, &mut expected, |p| p as i32);
+        let mut actual = vec![0i32; out_len];
+        let n = correlate_u8_i32(&rows_u8, &kernel_i32, k_width, &mut actual);
+        if actual[..n] != expected[..n] {
+            return TestResult::error(format!("u8/i32: {:?} != {:?}", actual, expected));
+        }
+
+        let mut expected = vec![0f32; out_len];
+        correlate_reference(&rows_u8, &kernel_f32, k_width, &mut expected, |p| p as f32);
+        let mut actual = vec![0f32; out_len];
+        let n = correlate_u8_f32(&rows_u8, &kernel_f32, k_width, &mut actual);
+        if actual[..n] != expected[..n] {
+            return TestResult::error(format!("u8/f32: {:?} != {:?}", actual, expected));
+        }
+
+        let mut expected = vec![0f32; out_len];
+        correlate_reference(&rows_f32, &kernel_f32, k_width, &mut expected, |p| p);
+        let mut actual = vec![0f32; out_len];
+        let n = correlate_f32_f32(&rows_f32, &kernel_f32, k_width, &mut actual);
+        if actual[..n] != expected[..n] {
+            return TestResult::error(format!("f32/f32: {:?} != {:?}", actual, expected));
+        }
+
+        TestResult::passed()
+    }
+
+    #[test]
+    fn test_correlate_3x3_matches_reference() {
+        fn prop(data: Vec<u8>, width: u8) -> TestResult {
+            check_all(&data, 3 + width as usize % 40, 3, 3)
+        }
+        quickcheck(prop as fn(Vec<u8>, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_correlate_5x5_matches_reference() {
+        fn prop(data: Vec<u8>, width: u8) -> TestResult {
+            check_all(&data, 5 + width as usize % 40, 5, 5)
+        }
+        quickcheck(prop as fn(Vec<u8>, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_correlate_other_kernel_sizes() {
+        let data: Vec<u8> = (0..97).map(|i| (i * 31 % 256) as u8).collect();
+        for &(k_width, k_height) in &[(1, 1), (7, 2), (2, 7)] {
+            assert!(!check_all(&data, 67, k_width, k_height).is_failure());
+        }
+    }
+
+    #[test]
+    fn test_divide_u32_u8_matches_integer_division() {
+        fn prop(quotients: Vec<u8>, remainders: Vec<u16>, divisor: u16) -> TestResult {
+            let divisor = divisor as u32 + 1;
+            if quotients.is_empty() || remainders.is_empty() {
+                return TestResult::discard();
+            }
+            let numerators: Vec<u32> = quotients
+                .iter()
+                .zip(remainders.iter().cycle())
+                .map(|(&q, &r)| q as u32 * divisor + r as u32 % divisor)
+                .collect();
+            let mut out = vec![0u8; numerators.len()];
+            let n = divide_u32_u8(&numerators, divisor, &mut out);
+            for (o, s) in out[..n].iter().zip(&numerators) {
+                if *o as u32 != s / divisor {
+                    return TestResult::error(format!("{} / {} != {}", s, divisor, o));
+                }
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(Vec<u8>, Vec<u16>, u16) -> TestResult);
+    }
+
+    #[test]
+    fn test_divide_u32_u8_exact_multiples() {
+        for &divisor in &[1u32, 3, 7, 255, 65535] {
+            let numerators: Vec<u32> = (0..256 * 3).map(|i| (i % 256) * divisor + i / 256 * (divisor - 1) / 2).collect();
+            let mut out = vec![0u8; numerators.len()];
+            let n = divide_u32_u8(&numerators, divisor, &mut out);
+            for (o, s) in out[..n].iter().zip(&numerators) {
+                assert_eq!(*o as u32, s / divisor);
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix_sum_u8_u32_matches_reference() {
+        fn prop(row: Vec<u8>, initial: u32) -> bool {
+            [false, true].iter().all(|&square| {
+                let mut out = vec![0u32; row.len()];
+                let n = prefix_sum_u8_u32(&row, square, initial, &mut out);
+                let mut sum = initial;
+                row.iter().zip(&out).take(n).all(|(&p, &o)| {
+                    let p = p as u32;
+                    sum = sum.wrapping_add(if square { p * p } else { p });
+                    sum == o
+                })
+            })
+        }
+        quickcheck(prop as fn(Vec<u8>, u32) -> bool);
+    }
+
+    #[test]
+    fn test_weighted_sum_u32_i32_matches_reference() {
+        fn prop(data: Vec<u32>, weights: Vec<i8>) -> TestResult {
+            if data.is_empty() || weights.is_empty() {
+                return TestResult::discard();
+            }
+            let len = data.len();
+            let rows: Vec<Vec<u32>> = (0..weights.len())
+                .map(|i| data.iter().cycle().skip(i).take(len).cloned().collect())
+                .collect();
+            let rows: Vec<&[u32]> = rows.iter().map(|r| &r[..]).collect();
+            let weights: Vec<i32> = weights.iter().map(|&w| w as i32).collect();
+
+            let mut out = vec![0i32; len];
+            let n = weighted_sum_u32_i32(&rows, &weights, &mut out);
+            for x in 0..n {
+                let expected = rows
+                    .iter()
+                    .zip(&weights)
+                    .fold(0i32, |acc, (row, &w)| acc.wrapping_add((row[x] as i32).wrapping_mul(w)));
+                if out[x] != expected {
+                    return TestResult::error(format!("at {}: {} != {}", x, out[x], expected));
+                }
+            }
+            TestResult::passed()
+        }
+        quickcheck(prop as fn(Vec<u32>, Vec<i8>) -> TestResult);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_narrower_x86_loops_match_scalar() {
+        let numerators: Vec<u32> = (0..1001).map(|i| i * 37 % 4000).collect();
+        let mut out = vec![0u8; numerators.len()];
+        let n = unsafe { x86::divide_u32_u8_sse2(&numerators, 17, &mut out) };
+        assert_eq!(n, 1000);
+        for (o, s) in out[..n].iter().zip(&numerators) {
+            assert_eq!(*o as u32, s / 17);
+        }
+
+        if is_x86_feature_detected!("sse4.1") {
+            let data: Vec<u32> = (0..103).map(|i| i * 1_000_003).collect();
+            let rows = [&data[..100], &data[1..101], &data[3..]];
+            let weights = [1, -2, 4];
+            let mut out = vec![0i32; 100];
+            let n = unsafe { x86::weighted_sum_u32_i32_sse41(&rows, &weights, &mut out) };
+            assert_eq!(n, 100);
+            for x in 0..n {
+                let expected = (rows[0][x] as i32)
+                    .wrapping_sub((rows[1][x] as i32).wrapping_mul(2))
+                    .wrapping_add((rows[2][x] as i32).wrapping_mul(4));
+                assert_eq!(out[x], expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! [Active contours], or snakes, for refining an approximate object boundary.
+//!
+//! A snake is a closed contour that moves to minimise the sum of an internal energy, which
+//! penalises stretching and bending, and an external energy, which is lower at strong
+//! edges. Starting from a rough outline, such as one drawn by a user or the boundary of a
+//! coarse segmentation, it settles onto nearby edges while staying smooth across gaps in
+//! them.
+//!
+//! [Active contours]: https://en.wikipedia.org/wiki/Active_contour_model
+
+use image::Luma;
+use definitions::Image;
+use error::{check_non_empty, ImageProcError, Result};
+use std::f64::consts::PI;
+
+/// Options for [`active_contour`](fn.active_contour.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SnakeOptions {
+    /// Weight of the elastic energy, which penalises the length of the contour. Larger
+    /// values make the snake shrink more strongly. Must not be negative.
+    pub alpha: f32,
+    /// Weight of the bending energy, which penalises the curvature of the contour. Larger
+    /// values give smoother contours. Must not be negative.
+    pub beta: f32,
+    /// Inverse of the step size of each iteration. Smaller values make the snake move
+    /// faster, at the cost of stability. Must be positive.
+    pub gamma: f32,
+    /// Maximum distance in pixels that each point can move along each axis in a single
+    /// iteration. Must be positive.
+    pub max_step: f32,
+    /// Maximum number of iterations.
+    pub max_iterations: u32,
+    /// Iteration stops once no point moves further than this many pixels in an iteration.
+    /// Must not be negative.
+    pub convergence: f32,
+}
+
+impl Default for SnakeOptions {
+    fn default() -> Self {
+        SnakeOptions {
+            alpha: 0.01,
+            beta: 0.1,
+            gamma: 0.1,
+            max_step: 0.5,
+            max_iterations: 2500,
+            convergence: 0.1,
+        }
+    }
+}
+
+impl SnakeOptions {
+    /// Returns an error describing every invalid option.
+    fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        if self.alpha < 0.0 || self.alpha.is_nan() {
+            errors.push("alpha must not be negative");
+        }
+        if self.beta < 0.0 || self.beta.is_nan() {
+            errors.push("beta must not be negative");
+        }
+        if self.gamma <= 0.0 || self.gamma.is_nan() {
+            errors.push("gamma must be positive");
+        }
+        if self.max_step <= 0.0 || self.max_step.is_nan() {
+            errors.push("max_step must be positive");
+        }
+        if self.convergence < 0.0 || self.convergence.is_nan() {
+            errors.push("convergence must not be negative");
+        }
+        if !errors.is_empty() {
+            return Err(ImageProcError::InvalidOptions(errors.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Moves the closed contour `initial` towards strong edges in `edges`, and returns the
+/// final positions of its points.
+///
+/// `edges` gives the strength of edges at each pixel, for example the
+/// [`gradient_magnitude`](../gradients/fn.gradient_magnitude.html) of a smoothed image,
+/// and the external energy of the snake is the negated sum of edge strengths along it.
+/// Edge strengths should be scaled to roughly `[0, 1]` for the default options to work
+/// well. Edges are only felt by points close to them, so blurring `edges` more widens the
+/// range over which the snake is attracted to them.
+///
+/// Uses the semi-implicit scheme of Kass, Witkin and Terzopoulos: the internal energy is
+/// minimised implicitly, and the external force explicitly, at each iteration. Points are
+/// kept within the image and keep their order, but are not resampled, so `initial` should
+/// have roughly evenly spaced points. The result has one point per point of `initial`.
+///
+/// Returns `ImageProcError::EmptyImage` if `edges` is empty, and
+/// `ImageProcError::InvalidOptions` if `initial` has fewer than three points or any
+/// option is invalid.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::definitions::Image;
+/// use imageproc::snakes::{active_contour, SnakeOptions};
+///
+/// // A blurred circular edge of radius 10, centred at (30, 30).
+/// let edges = Image::from_fn(60, 60, |x, y| {
+///     let d = (x as f32 - 30.0).hypot(y as f32 - 30.0) - 10.0;
+///     Luma([(-d * d / 8.0).exp()])
+/// });
+///
+/// // A circle of radius 16 shrinks onto it.
+/// let initial: Vec<(f32, f32)> = (0..40)
+///     .map(|i| (i as f32 * std::f32::consts::PI / 20.0).sin_cos())
+///     .map(|(sin, cos)| (30.0 + 16.0 * cos, 30.0 + 16.0 * sin))
+///     .collect();
+///
+/// let snake = active_contour(&edges, &initial, SnakeOptions::default()).unwrap();
+/// for &(x, y) in &snake {
+///     assert!(((x - 30.0).hypot(y - 30.0) - 10.0).abs() < 1.0);
+/// }
+/// # }
+/// ```
+pub fn active_contour(
+    edges: &Image<Luma<f32>>,
+    initial: &[(f32, f32)],
+    options: SnakeOptions,
+) -> Result<Vec<(f32, f32)>> {
+    check_non_empty(edges.dimensions())?;
+    options.validate()?;
+    if initial.len() < 3 {
+        return Err(ImageProcError::InvalidOptions(
+            "initial contour must have at least 3 points".into(),
+        ));
+    }
+
+    let (width, height) = edges.dimensions();
+    let clamp = |(x, y): (f32, f32)| {
+        (x.max(0.0).min((width - 1) as f32), y.max(0.0).min((height - 1) as f32))
+    };
+    let (gx, gy) = central_differences(edges);
+    let n = initial.len();
+    let inverse = inverse_first_column(n, options);
+
+    let mut points: Vec<(f32, f32)> = initial.iter().cloned().map(&clamp).collect();
+    let mut rhs = vec![(0f32, 0f32); n];
+    for _ in 0..options.max_iterations {
+        for (r, &(x, y)) in rhs.iter_mut().zip(&points) {
+            *r = (options.gamma * x + sample(&gx, x, y), options.gamma * y + sample(&gy, x, y));
+        }
+        let mut max_move = 0f32;
+        for (i, point) in points.iter_mut().enumerate() {
+            // The system matrix is circulant, so its inverse is too.
+            let (mut tx, mut ty) = (0.0, 0.0);
+            for (j, r) in rhs.iter().enumerate() {
+                let c = inverse[(i + n - j) % n];
+                tx += c * r.0;
+                ty += c * r.1;
+            }
+            let (x, y) = *point;
+            let dx = options.max_step * (tx - x).tanh();
+            let dy = options.max_step * (ty - y).tanh();
+            *point = clamp((x + dx, y + dy));
+            max_move = max_move.max(dx.hypot(dy));
+        }
+        if max_move < options.convergence {
+            break;
+        }
+    }
+    Ok(points)
+}
+
+/// The first column of the inverse of the matrix A + gamma * I, where A is the cyclic
+/// pentadiagonal matrix of the internal energy of a snake with n points.
+fn inverse_first_column(n: usize, options: SnakeOptions) -> Vec<f32> {
+    let (alpha, beta, gamma) = (options.alpha as f64, options.beta as f64, options.gamma as f64);
+    // A circulant matrix is diagonalised by the discrete Fourier transform. Each row of A
+    // is alpha * (-1, 2, -1) + beta * (1, -4, 6, -4, 1), centred on the diagonal.
+    let eigenvalues: Vec<f64> = (0..n)
+        .map(|k| {
+            let c = 1.0 - (2.0 * PI * k as f64 / n as f64).cos();
+            gamma + 2.0 * alpha * c + 4.0 * beta * c * c
+        })
+        .collect();
+    (0..n)
+        .map(|j| {
+            let sum: f64 = eigenvalues
+                .iter()
+                .enumerate()
+                .map(|(k, l)| (2.0 * PI * (j * k % n) as f64 / n as f64).cos() / l)
+                .sum();
+            (sum / n as f64) as f32
+        })
+        .collect()
+}
+
+/// Horizontal and vertical central differences, using one-sided differences at the
+/// image boundary.
+fn central_differences(image: &Image<Luma<f32>>) -> (Image<Luma<f32>>, Image<Luma<f32>>) {
+    let (width, height) = image.dimensions();
+    let at = |x: u32, y: u32| image.get_pixel(x, y)[0];
+    let gx = Image::from_fn(width, height, |x, y| {
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+        Luma([(at(right, y) - at(left, y)) / (right - left).max(1) as f32])
+    });
+    let gy = Image::from_fn(width, height, |x, y| {
+        let (top, bottom) = (y.saturating_sub(1), (y + 1).min(height - 1));
+        Luma([(at(x, bottom) - at(x, top)) / (bottom - top).max(1) as f32])
+    });
+    (gx, gy)
+}
+
+/// Bilinearly interpolates the intensity at a position within the image.
+fn sample(image: &Image<Luma<f32>>, x: f32, y: f32) -> f32 {
+    let (width, height) = image.dimensions();
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let (right, bottom) = ((left + 1).min(width - 1), (top + 1).min(height - 1));
+    let (fx, fy) = (x - left as f32, y - top as f32);
+    let at = |x, y| image.get_pixel(x, y)[0];
+    let upper = (1.0 - fx) * at(left, top) + fx * at(right, top);
+    let lower = (1.0 - fx) * at(left, bottom) + fx * at(right, bottom);
+    (1.0 - fy) * upper + fy * lower
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test::{Bencher, black_box};
+
+    fn ring(size: u32, center: (f32, f32), radius: f32) -> Image<Luma<f32>> {
+        Image::from_fn(size, size, |x, y| {
+            let d = (x as f32 - center.0).hypot(y as f32 - center.1) - radius;
+            Luma([(-d * d / 8.0).exp()])
+        })
+    }
+
+    fn circle(count: usize, center: (f32, f32), radius: f32) -> Vec<(f32, f32)> {
+        (0..count)
+            .map(|i| (2.0 * ::std::f32::consts::PI * i as f32 / count as f32).sin_cos())
+            .map(|(sin, cos)| (center.0 + radius * cos, center.1 + radius * sin))
+            .collect()
+    }
+
+    #[test]
+    fn test_active_contour_expands_and_shrinks_onto_edge() {
+        let edges = ring(60, (28.0, 31.0), 12.0);
+        for &radius in &[9.0, 17.0] {
+            let initial = circle(50, (30.0, 30.0), radius);
+            let snake = active_contour(&edges, &initial, SnakeOptions::default()).unwrap();
+            assert_eq!(snake.len(), initial.len());
+            for &(x, y) in &snake {
+                let r = (x - 28.0).hypot(y - 31.0);
+                assert!((r - 12.0).abs() < 1.0, "point ({}, {}) at radius {}", x, y, r);
+            }
+        }
+    }
+
+    #[test]
+    fn test_active_contour_shrinks_without_edges() {
+        let edges = Image::new(40, 40);
+        let initial = circle(30, (20.0, 20.0), 15.0);
+        let options = SnakeOptions { max_iterations: 20, ..SnakeOptions::default() };
+        let snake = active_contour(&edges, &initial, options).unwrap();
+        for &(x, y) in &snake {
+            let r = (x - 20.0).hypot(y - 20.0);
+            assert!(r < 15.0 && r > 5.0);
+        }
+    }
+
+    #[test]
+    fn test_active_contour_keeps_points_in_image() {
+        let edges = Image::new(20, 10);
+        let initial = [(-5.0, 3.0), (25.0, -1.0), (10.0, 30.0)];
+        let options = SnakeOptions { max_iterations: 1, ..SnakeOptions::default() };
+        for &(x, y) in &active_contour(&edges, &initial, options).unwrap() {
+            assert!(x >= 0.0 && x <= 19.0 && y >= 0.0 && y <= 9.0);
+        }
+    }
+
+    #[test]
+    fn test_active_contour_invalid_inputs() {
+        let edges = Image::new(10, 10);
+        let triangle = [(1.0, 1.0), (8.0, 1.0), (4.0, 8.0)];
+        match active_contour(&edges, &triangle[..2], SnakeOptions::default()) {
+            Err(ImageProcError::InvalidOptions(_)) => {}
+            other => panic!("expected InvalidOptions, got {:?}", other),
+        }
+        let options = SnakeOptions { gamma: 0.0, alpha: -1.0, ..SnakeOptions::default() };
+        match active_contour(&edges, &triangle, options) {
+            Err(ImageProcError::InvalidOptions(message)) => {
+                assert_eq!(message, "alpha must not be negative, gamma must be positive")
+            }
+            other => panic!("expected InvalidOptions, got {:?}", other),
+        }
+        let options = SnakeOptions { beta: f32::NAN, max_step: f32::NAN, ..SnakeOptions::default() };
+        match active_contour(&edges, &triangle, options) {
+            Err(ImageProcError::InvalidOptions(message)) => {
+                assert_eq!(message, "beta must not be negative, max_step must be positive")
+            }
+            other => panic!("expected InvalidOptions, got {:?}", other),
+        }
+        assert_eq!(
+            active_contour(&Image::new(0, 5), &triangle, SnakeOptions::default()),
+            Err(ImageProcError::EmptyImage)
+        );
+    }
+
+    #[bench]
+    fn bench_active_contour(b: &mut Bencher) {
+        let edges = ring(100, (50.0, 50.0), 25.0);
+        let initial = circle(100, (50.0, 50.0), 35.0);
+        let options = SnakeOptions { max_iterations: 50, ..SnakeOptions::default() };
+        b.iter(|| black_box(active_contour(&edges, &initial, options).unwrap()));
+    }
+}
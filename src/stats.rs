@@ -1,13 +1,17 @@
 //! Statistical properties of images.
 
-use image::{GenericImage, GrayImage, Pixel, Primitive};
+use image::{GenericImageView, GrayImage, Luma, Pixel, Primitive};
 
-use num::Bounded;
+use num::{Bounded, NumCast, Zero};
 use math::cast;
 use conv::ValueInto;
+use view::enumerate_pixels;
 
 /// Returns the histogram of grayscale values in an 8bpp
 /// grayscale image.
+///
+/// See [`histogram_view`](fn.histogram_view.html) for computing the histogram
+/// of a `GenericImageView`, such as a sub-image.
 pub fn histogram(image: &GrayImage) -> [u32; 256] {
     let mut hist = [0u32; 256];
 
@@ -18,11 +22,39 @@ pub fn histogram(image: &GrayImage) -> [u32; 256] {
     hist
 }
 
+/// Returns the histogram of grayscale values in an 8bpp grayscale image view.
+///
+/// This reads the view one pixel at a time, so prefer [`histogram`](fn.histogram.html)
+/// when the input is an `ImageBuffer`.
+pub fn histogram_view<I>(image: &I) -> [u32; 256]
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
+    let mut hist = [0u32; 256];
+
+    for (_, _, pix) in enumerate_pixels(image) {
+        hist[pix[0] as usize] += 1;
+    }
+
+    hist
+}
+
 /// Returns the cumulative histogram of grayscale values in an 8bpp
 /// grayscale image.
 pub fn cumulative_histogram(image: &GrayImage) -> [u32; 256] {
-    let mut hist = histogram(image);
+    accumulate(histogram(image))
+}
 
+/// Returns the cumulative histogram of grayscale values in an 8bpp
+/// grayscale image view. See [`histogram_view`](fn.histogram_view.html).
+pub fn cumulative_histogram_view<I>(image: &I) -> [u32; 256]
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
+    accumulate(histogram_view(image))
+}
+
+fn accumulate(mut hist: [u32; 256]) -> [u32; 256] {
     for i in 1..hist.len() {
         hist[i] += hist[i - 1];
     }
@@ -30,6 +62,128 @@ pub fn cumulative_histogram(image: &GrayImage) -> [u32; 256] {
     hist
 }
 
+/// Returns the histogram of grayscale values in a 16bpp grayscale image.
+/// The returned vector has length 65536.
+pub fn histogram_u16<I>(image: &I) -> Vec<u32>
+where
+    I: GenericImageView<Pixel = Luma<u16>>,
+{
+    let mut hist = vec![0u32; 65536];
+
+    for (_, _, pix) in enumerate_pixels(image) {
+        hist[pix[0] as usize] += 1;
+    }
+
+    hist
+}
+
+/// Returns a histogram of the intensities in a grayscale image, with `num_bins`
+/// equally sized bins covering the range from `lower` to `upper`.
+///
+/// Values less than `lower` are counted in the first bin, values greater than or
+/// equal to `upper` are counted in the final bin and `NaN`s are ignored.
+///
+/// # Panics
+/// If `num_bins` is zero or `upper` is not greater than `lower`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::stats::binned_histogram;
+///
+/// let image = gray_image!(type: f32,
+///     0.1, 0.3, 0.6;
+///     0.9, 1.0, -2.0);
+///
+/// assert_eq!(binned_histogram(&image, 4, 0.0, 1.0), vec![2, 1, 1, 2]);
+/// # }
+/// ```
+pub fn binned_histogram<I, T>(image: &I, num_bins: usize, lower: f64, upper: f64) -> Vec<u32>
+where
+    I: GenericImageView<Pixel = Luma<T>>,
+    T: Primitive + ValueInto<f64> + 'static,
+{
+    let mut hist = vec![0u32; num_bins];
+    let bins = Bins::new(num_bins, lower, upper);
+
+    for (_, _, pix) in enumerate_pixels(image) {
+        if let Some(bin) = bins.index(cast(pix[0])) {
+            hist[bin] += 1;
+        }
+    }
+
+    hist
+}
+
+/// Equally sized bins covering a range of values.
+pub(crate) struct Bins {
+    num_bins: usize,
+    lower: f64,
+    scale: f64,
+}
+
+impl Bins {
+    pub(crate) fn new(num_bins: usize, lower: f64, upper: f64) -> Bins {
+        assert!(num_bins > 0, "num_bins must be non-zero");
+        assert!(upper > lower, "upper must be greater than lower");
+        Bins { num_bins, lower, scale: num_bins as f64 / (upper - lower) }
+    }
+
+    /// Returns the bin containing `x`, or None if `x` is `NaN`.
+    pub(crate) fn index(&self, x: f64) -> Option<usize> {
+        if x.is_nan() {
+            return None;
+        }
+        let bin = ((x - self.lower) * self.scale).floor();
+        Some(if bin < 0.0 {
+            0
+        } else if bin >= (self.num_bins - 1) as f64 {
+            self.num_bins - 1
+        } else {
+            bin as usize
+        })
+    }
+}
+
+/// Returns the smallest and largest subpixel values in an image, ignoring `NaN`s,
+/// or None if the image contains no other values.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::stats::min_max;
+///
+/// let image = gray_image!(type: u16,
+///     300, 2;
+///     7, 4000);
+///
+/// assert_eq!(min_max(&image), Some((2, 4000)));
+/// # }
+/// ```
+pub fn min_max<I, P>(image: &I) -> Option<(P::Subpixel, P::Subpixel)>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+{
+    let mut extremes: Option<(P::Subpixel, P::Subpixel)> = None;
+    for (_, _, pix) in enumerate_pixels(image) {
+        // Skip NaNs, which are the only values unordered with respect to themselves.
+        for &v in pix.channels().iter().filter(|v| v.partial_cmp(v).is_some()) {
+            extremes = Some(match extremes {
+                None => (v, v),
+                Some((lo, hi)) => (if v < lo { v } else { lo }, if v > hi { v } else { hi }),
+            });
+        }
+    }
+    extremes
+}
+
 /// Returns the `p`th percentile of the pixel intensities in an image.
 ///
 /// We define the `p`th percentile intensity to be the least `x` such
@@ -65,13 +219,28 @@ pub fn cumulative_histogram(image: &GrayImage) -> [u32; 256] {
 /// # }
 /// ```
 pub fn percentile(image: &GrayImage, p: u8) -> u8 {
+    percentile_from_cumulative_histogram(&cumulative_histogram(image), p)
+}
+
+/// Returns the `p`th percentile of the pixel intensities in an image view.
+/// See [`percentile`](fn.percentile.html).
+///
+/// # Panics
+/// If `p > 100`.
+pub fn percentile_view<I>(image: &I, p: u8) -> u8
+where
+    I: GenericImageView<Pixel = Luma<u8>>,
+{
+    percentile_from_cumulative_histogram(&cumulative_histogram_view(image), p)
+}
+
+fn percentile_from_cumulative_histogram(cum_hist: &[u32; 256], p: u8) -> u8 {
     assert!(p <= 100, "requested percentile must be <= 100");
 
-    let cum_hist = cumulative_histogram(&image);
     let total = cum_hist[255] as u64;
 
-    for i in 0..256 {
-        if 100 * cum_hist[i] as u64 / total >= p as u64 {
+    for (i, &count) in cum_hist.iter().enumerate() {
+        if 100 * count as u64 / total >= p as u64 {
             return i as u8;
         }
     }
@@ -79,14 +248,148 @@ pub fn percentile(image: &GrayImage, p: u8) -> u8 {
     unreachable!();
 }
 
+/// Returns a histogram of the values of each channel in an image with 8-bit channels.
+/// The `c`th entry of the returned vector is the histogram of the `c`th channel.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::stats::channel_histograms;
+///
+/// let image = rgb_image!(
+///     [1, 2, 3], [1, 5, 3];
+///     [1, 2, 4], [0, 0, 0]);
+///
+/// let hists = channel_histograms(&image);
+/// assert_eq!(hists.len(), 3);
+/// assert_eq!((hists[0][0], hists[0][1]), (1, 3));
+/// assert_eq!((hists[1][0], hists[1][2], hists[1][5]), (1, 2, 1));
+/// assert_eq!((hists[2][0], hists[2][3], hists[2][4]), (1, 2, 1));
+/// # }
+/// ```
+pub fn channel_histograms<I, P>(image: &I) -> Vec<[u32; 256]>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = u8> + 'static,
+{
+    let mut hists = vec![[0u32; 256]; P::channel_count() as usize];
+
+    for (_, _, pix) in enumerate_pixels(image) {
+        for (hist, &c) in hists.iter_mut().zip(pix.channels()) {
+            hist[c as usize] += 1;
+        }
+    }
+
+    hists
+}
+
+/// Returns the cumulative histogram of the values of each channel in an image
+/// with 8-bit channels. See [`channel_histograms`](fn.channel_histograms.html).
+pub fn cumulative_channel_histograms<I, P>(image: &I) -> Vec<[u32; 256]>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel<Subpixel = u8> + 'static,
+{
+    let mut hists = channel_histograms(image);
+
+    for hist in &mut hists {
+        for i in 1..hist.len() {
+            hist[i] += hist[i - 1];
+        }
+    }
+
+    hists
+}
+
+/// Returns the mean value of each channel in an image.
+///
+/// # Panics
+/// If the image is empty.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::stats::channel_means;
+///
+/// let image = rgb_image!(
+///     [1, 2, 3], [3, 4, 5]);
+///
+/// assert_eq!(channel_means(&image), vec![2.0, 3.0, 4.0]);
+/// # }
+/// ```
+pub fn channel_means<I, P>(image: &I) -> Vec<f64>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f64>,
+{
+    let count = image.width() as u64 * image.height() as u64;
+    assert!(count > 0, "image must be non-empty");
+
+    let mut sums = vec![0f64; P::channel_count() as usize];
+    for (_, _, pix) in enumerate_pixels(image) {
+        for (sum, &c) in sums.iter_mut().zip(pix.channels()) {
+            *sum += cast::<P::Subpixel, f64>(c);
+        }
+    }
+
+    sums.iter().map(|s| s / count as f64).collect()
+}
+
+/// Returns the (population) variance of each channel in an image.
+///
+/// # Panics
+/// If the image is empty.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::stats::channel_variances;
+///
+/// let image = gray_image!(
+///     1, 3;
+///     5, 7);
+///
+/// assert_eq!(channel_variances(&image), vec![5.0]);
+/// # }
+/// ```
+pub fn channel_variances<I, P>(image: &I) -> Vec<f64>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f64>,
+{
+    let means = channel_means(image);
+    let count = image.width() as u64 * image.height() as u64;
+
+    let mut sums = vec![0f64; means.len()];
+    for (_, _, pix) in enumerate_pixels(image) {
+        for ((sum, mean), &c) in sums.iter_mut().zip(&means).zip(pix.channels()) {
+            let diff = cast::<P::Subpixel, f64>(c) - mean;
+            *sum += diff * diff;
+        }
+    }
+
+    sums.iter().map(|s| s / count as f64).collect()
+}
+
 /// Returns the square root of the mean of the squares of differences
 /// between all subpixels in left and right. All channels are considered
 /// equally. If you do not want this (e.g. if using RGBA) then change
 /// image formats first.
 pub fn root_mean_squared_error<I, J, P>(left: &I, right: &J) -> f64
 where
-    I: GenericImage<Pixel = P>,
-    J: GenericImage<Pixel = P>,
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
     P: Pixel,
     P::Subpixel: ValueInto<f64>,
 {
@@ -97,22 +400,27 @@ where
 /// aproximation. All channels are considered equally. If you do not want this
 /// (e.g. if using RGBA) then change image formats first.
 /// See also [peak signal-to-noise ratio (wikipedia)](https://en.wikipedia.org/wiki/Peak_signal-to-noise_ratio).
+///
+/// The peak signal is the maximum value of the subpixel type for integer subpixels,
+/// and 1 for floating point subpixels.
 pub fn peak_signal_to_noise_ratio<I, J, P>(original: &I, noisy: &J) -> f64
 where
-    I: GenericImage<Pixel = P>,
-    J: GenericImage<Pixel = P>,
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
     P: Pixel,
     P::Subpixel: ValueInto<f64> + Primitive,
 {
-    let max: f64 = cast(<P::Subpixel as Bounded>::max_value());
+    // Casting 0.5 to an integer type truncates it to zero.
+    let is_float = <P::Subpixel as NumCast>::from(0.5).map_or(false, |h| h != P::Subpixel::zero());
+    let max: f64 = if is_float { 1.0 } else { cast(<P::Subpixel as Bounded>::max_value()) };
     let mse = mean_squared_error(original, noisy);
     20f64 * max.log(10f64) - 10f64 * mse.log(10f64)
 }
 
 fn mean_squared_error<I, J, P>(left: &I, right: &J) -> f64
 where
-    I: GenericImage<Pixel = P>,
-    J: GenericImage<Pixel = P>,
+    I: GenericImageView<Pixel = P>,
+    J: GenericImageView<Pixel = P>,
     P: Pixel,
     P::Subpixel: ValueInto<f64>,
 {
@@ -135,6 +443,7 @@ mod test {
     use super::*;
     use image::{GrayImage, RgbImage, Luma, Rgb};
     use test::{Bencher, black_box};
+    use utils::gray_bench_image;
 
     #[test]
     fn test_cumulative_histogram() {
@@ -159,6 +468,92 @@ mod test {
         assert_eq!(hist[3], 1);
     }
 
+    #[test]
+    fn test_channel_histograms_gray_matches_histogram() {
+        let image = gray_image!(1u8, 2u8, 3u8, 2u8, 1u8);
+        let hists = channel_histograms(&image);
+        assert_eq!(hists.len(), 1);
+        assert_eq!(&hists[0][..], &histogram(&image)[..]);
+
+        let cum_hists = cumulative_channel_histograms(&image);
+        assert_eq!(&cum_hists[0][..], &cumulative_histogram(&image)[..]);
+    }
+
+    #[test]
+    fn test_cumulative_channel_histograms_rgb() {
+        let image = rgb_image!([0, 10, 255], [1, 10, 0]);
+        let hists = cumulative_channel_histograms(&image);
+        assert_eq!(hists.len(), 3);
+        assert_eq!((hists[0][0], hists[0][1], hists[0][255]), (1, 2, 2));
+        assert_eq!((hists[1][9], hists[1][10]), (0, 2));
+        assert_eq!((hists[2][0], hists[2][254], hists[2][255]), (1, 1, 2));
+    }
+
+    #[test]
+    fn test_channel_means_and_variances_rgb() {
+        let image = rgb_image!(
+            [0, 10, 4], [2, 10, 4];
+            [4, 10, 8], [6, 10, 8]);
+        assert_eq!(channel_means(&image), vec![3.0, 10.0, 6.0]);
+        assert_eq!(channel_variances(&image), vec![5.0, 0.0, 4.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_channel_means_rejects_empty_image() {
+        let image = GrayImage::new(0, 0);
+        let _ = channel_means(&image);
+    }
+
+    #[test]
+    fn test_histogram_u16() {
+        let image = gray_image!(type: u16,
+            1, 60000, 1;
+            65535, 0, 1);
+
+        let hist = histogram_u16(&image);
+        assert_eq!(hist.len(), 65536);
+        assert_eq!((hist[0], hist[1], hist[60000], hist[65535]), (1, 3, 1, 1));
+        assert_eq!(hist.iter().sum::<u32>(), 6);
+    }
+
+    #[test]
+    fn test_stats_of_view_match_stats_of_copy() {
+        let image = gray_image!(
+            1, 2, 3, 4;
+            5, 6, 7, 8;
+            9, 10, 11, 12);
+        let view = image.view(1, 1, 2, 2);
+        let copy = view.to_image();
+
+        assert_eq!(&histogram_view(&view)[..], &histogram(&copy)[..]);
+        assert_eq!(&cumulative_histogram_view(&view)[..], &cumulative_histogram(&copy)[..]);
+        assert_eq!(percentile_view(&view, 50), percentile(&copy, 50));
+        assert_eq!(percentile_view(&view, 50), 7);
+        assert_eq!(min_max(&view), Some((6, 11)));
+        assert_eq!(root_mean_squared_error(&view, &copy), 0.0);
+    }
+
+    #[test]
+    fn test_min_max_ignores_nans() {
+        let image = gray_image!(type: f32,
+            ::std::f32::NAN, 0.5;
+            -1.0, ::std::f32::NAN);
+        assert_eq!(min_max(&image), Some((-1.0, 0.5)));
+
+        let nans = gray_image!(type: f32, ::std::f32::NAN);
+        assert_eq!(min_max(&nans), None);
+    }
+
+    #[test]
+    fn test_peak_signal_to_noise_ratio_float_peak_is_one() {
+        let original = gray_image!(type: f32, 0.0, 1.0);
+        let noisy = gray_image!(type: f32, 0.1, 0.9);
+        // MSE is 0.01, so PSNR is -10 log10(0.01).
+        let psnr = peak_signal_to_noise_ratio(&original, &noisy);
+        assert!((psnr - 20.0).abs() < 1e-4);
+    }
+
     #[test]
     fn test_root_mean_squared_error_grayscale() {
         let left = gray_image!(
@@ -228,4 +623,24 @@ mod test {
             test::black_box(error);
         });
     }
+
+    #[bench]
+    fn bench_histogram(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+
+        b.iter(|| {
+            let hist = histogram(&image);
+            black_box(hist);
+        });
+    }
+
+    #[bench]
+    fn bench_histogram_view(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+
+        b.iter(|| {
+            let hist = histogram_view(&image);
+            black_box(hist);
+        });
+    }
 }
@@ -0,0 +1,664 @@
+//! Functions for computing disparity maps from rectified stereo image pairs.
+//!
+//! Throughout this module the input images are assumed to be rectified, so that
+//! the pixel at `(x, y)` in the left image corresponds to the pixel at `(x - d, y)`
+//! in the right image for some non-negative disparity `d`.
+
+use image::{GenericImageView, GrayImage, Luma};
+use definitions::Image;
+use error::{check_dimensions, Result};
+use std::cmp::{max, min};
+
+/// Disparity value used to mark pixels for which no reliable match was found.
+pub const INVALID_DISPARITY: f32 = -1.0;
+
+/// The cost used to compare a pixel in the left image with a candidate match in the right image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MatchingCost {
+    /// Absolute difference of pixel intensities, summed over the matching block.
+    SumOfAbsoluteDifferences,
+    /// Hamming distance between [census transforms] computed over a 5x5 window,
+    /// summed over the matching block. More robust than `SumOfAbsoluteDifferences`
+    /// to differences in exposure between the two cameras.
+    ///
+    /// [census transforms]: https://en.wikipedia.org/wiki/Census_transform
+    Census,
+}
+
+/// Options for block-matching stereo.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockMatchingOptions {
+    /// The largest disparity to search, in pixels. All disparities
+    /// in the range `[0, max_disparity]` are considered.
+    pub max_disparity: u32,
+    /// Matching costs are summed over the block of side length
+    /// `2 * block_radius + 1` centred on each pixel.
+    pub block_radius: u32,
+    /// How to compare left and right pixels.
+    pub cost: MatchingCost,
+    /// If set, disparities are also computed with the right image as the reference
+    /// and left pixels whose disparity differs from that of their match in the right
+    /// image by more than this amount are marked as `INVALID_DISPARITY`. This removes
+    /// most matches in occluded regions.
+    pub left_right_tolerance: Option<f32>,
+    /// Whether to refine integer disparities by fitting a parabola to the
+    /// matching costs of the best disparity and its two neighbours.
+    pub subpixel: bool,
+}
+
+/// Computes a disparity map for a rectified stereo pair by matching square blocks
+/// centred on each pixel of the left image against blocks in the same row of the right image.
+///
+/// The output has the dimensions of the left image. Pixels which fail the optional
+/// left-right consistency check are set to `INVALID_DISPARITY`. The disparity searched
+/// at column `x` is limited to `x`, so that matches never lie outside the right image.
+///
+/// Returns `ImageProcError::DimensionMismatch` if the two images have different dimensions.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::stereo::{block_matching, BlockMatchingOptions, MatchingCost};
+///
+/// // The right image is the left image shifted three pixels to the left.
+/// let intensity = |x: u32, y: u32| ((x * 7 + y * 13) * (x ^ y) % 251) as u8;
+/// let left = GrayImage::from_fn(30, 20, |x, y| Luma([intensity(x, y)]));
+/// let right = GrayImage::from_fn(30, 20, |x, y| Luma([intensity(x + 3, y)]));
+///
+/// let options = BlockMatchingOptions {
+///     max_disparity: 8,
+///     block_radius: 2,
+///     cost: MatchingCost::SumOfAbsoluteDifferences,
+///     left_right_tolerance: None,
+///     subpixel: false,
+/// };
+///
+/// let disparity = block_matching(&left, &right, options).unwrap();
+/// assert_eq!(disparity.get_pixel(15, 10)[0], 3.0);
+/// # }
+/// ```
+pub fn block_matching(left: &GrayImage, right: &GrayImage, options: BlockMatchingOptions) -> Result<Image<Luma<f32>>> {
+    check_dimensions(left.dimensions(), right.dimensions())?;
+    let costs = pixel_costs(left, right, options.max_disparity, options.cost);
+    let aggregated = aggregate_block_costs(&costs, options.block_radius);
+    Ok(select_disparities(&aggregated, options.subpixel, None, options.left_right_tolerance))
+}
+
+/// The directions along which costs are aggregated in semi-global matching.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SgmPaths {
+    /// Aggregate along the horizontal and vertical directions.
+    Four,
+    /// Aggregate along the horizontal, vertical and diagonal directions.
+    /// Slower than `Four`, but produces fewer streaking artefacts.
+    Eight,
+}
+
+/// Options for semi-global matching.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SemiGlobalMatchingOptions {
+    /// The largest disparity to search, in pixels. All disparities
+    /// in the range `[0, max_disparity]` are considered.
+    pub max_disparity: u32,
+    /// The directions along which to aggregate matching costs.
+    pub paths: SgmPaths,
+    /// Penalty for a change in disparity of one pixel between neighbouring pixels.
+    pub p1: u32,
+    /// Penalty for a change in disparity of more than one pixel between neighbouring
+    /// pixels. Should be greater than `p1`.
+    pub p2: u32,
+    /// If set, a pixel is marked as `INVALID_DISPARITY` if any disparity other than the
+    /// best and its immediate neighbours has aggregated cost within a factor of
+    /// `1 - uniqueness_ratio` of the best cost. Typical values are between 0.05 and 0.15.
+    pub uniqueness_ratio: Option<f32>,
+    /// See [`BlockMatchingOptions::left_right_tolerance`](struct.BlockMatchingOptions.html).
+    pub left_right_tolerance: Option<f32>,
+    /// Whether to refine integer disparities by fitting a parabola to the
+    /// aggregated costs of the best disparity and its two neighbours.
+    pub subpixel: bool,
+}
+
+/// Computes a disparity map for a rectified stereo pair using [semi-global matching].
+///
+/// Census matching costs computed over a 5x5 window are aggregated along several
+/// one-dimensional paths through the image, penalising changes in disparity between
+/// neighbouring pixels. This approximates a global smoothness constraint, giving
+/// far less noisy results than [`block_matching`](fn.block_matching.html) in weakly
+/// textured regions.
+///
+/// The output has the dimensions of the left image. Pixels rejected by the optional
+/// uniqueness or left-right consistency checks are set to `INVALID_DISPARITY`.
+///
+/// [semi-global matching]: https://en.wikipedia.org/wiki/Semi-global_matching
+///
+/// Returns `ImageProcError::DimensionMismatch` if the two images have different dimensions.
+pub fn semi_global_matching(
+    left: &GrayImage,
+    right: &GrayImage,
+    options: SemiGlobalMatchingOptions,
+) -> Result<Image<Luma<f32>>> {
+    check_dimensions(left.dimensions(), right.dimensions())?;
+    let costs = pixel_costs(left, right, options.max_disparity, MatchingCost::Census);
+    let aggregated = aggregate_path_costs(&costs, options.paths, options.p1, options.p2);
+    Ok(select_disparities(
+        &aggregated,
+        options.subpixel,
+        options.uniqueness_ratio,
+        options.left_right_tolerance,
+    ))
+}
+
+/// Per-pixel matching costs for every candidate disparity.
+struct CostVolume<T> {
+    width: u32,
+    height: u32,
+    /// Number of disparities, i.e. `max_disparity + 1`.
+    disparities: u32,
+    /// Costs, with the disparity varying fastest, then x, then y.
+    costs: Vec<T>,
+}
+
+impl<T: Copy> CostVolume<T> {
+    fn new(width: u32, height: u32, disparities: u32, value: T) -> CostVolume<T> {
+        CostVolume {
+            width,
+            height,
+            disparities,
+            costs: vec![value; (width * height * disparities) as usize],
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((y * self.width + x) * self.disparities) as usize
+    }
+
+    /// The costs of all disparities at the given pixel.
+    fn costs_at(&self, x: u32, y: u32) -> &[T] {
+        let start = self.index(x, y);
+        &self.costs[start..start + self.disparities as usize]
+    }
+
+    /// Number of disparities it's valid to consider at column x.
+    fn valid_disparities(&self, x: u32) -> u32 {
+        min(x + 1, self.disparities)
+    }
+}
+
+/// Computes the unaggregated matching cost between each left pixel and each
+/// candidate right pixel. Right pixels which would lie outside the image are
+/// replaced by the leftmost pixel in their row. The images must have the same dimensions.
+fn pixel_costs(
+    left: &GrayImage,
+    right: &GrayImage,
+    max_disparity: u32,
+    cost: MatchingCost,
+) -> CostVolume<u32> {
+    let (width, height) = left.dimensions();
+    let mut volume = CostVolume::new(width, height, max_disparity + 1, 0u32);
+
+    match cost {
+        MatchingCost::SumOfAbsoluteDifferences => {
+            for y in 0..height {
+                for x in 0..width {
+                    let l = unsafe { left.unsafe_get_pixel(x, y)[0] } as i32;
+                    let start = volume.index(x, y);
+                    for d in 0..volume.disparities {
+                        let rx = x.saturating_sub(d);
+                        let r = unsafe { right.unsafe_get_pixel(rx, y)[0] } as i32;
+                        volume.costs[start + d as usize] = (l - r).unsigned_abs();
+                    }
+                }
+            }
+        }
+        MatchingCost::Census => {
+            let left_census = census_transform(left, 2);
+            let right_census = census_transform(right, 2);
+            for y in 0..height {
+                for x in 0..width {
+                    let l = left_census[(y * width + x) as usize];
+                    let start = volume.index(x, y);
+                    for d in 0..volume.disparities {
+                        let rx = x.saturating_sub(d);
+                        let r = right_census[(y * width + rx) as usize];
+                        volume.costs[start + d as usize] = (l ^ r).count_ones();
+                    }
+                }
+            }
+        }
+    }
+
+    volume
+}
+
+/// Computes the census transform of an image over windows of side length `2 * radius + 1`,
+/// returning one bit string per pixel in row-major order. Bit `i` is set if the `i`th pixel
+/// in the window (in row-major order, skipping the centre) is darker than the centre pixel.
+/// Windows are padded by continuity at the image boundary.
+fn census_transform(image: &GrayImage, radius: u32) -> Vec<u64> {
+    assert!(radius <= 3, "census windows larger than 7x7 do not fit in 64 bits");
+    let (width, height) = image.dimensions();
+    let r = radius as i32;
+    let mut out = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let centre = unsafe { image.unsafe_get_pixel(x as u32, y as u32)[0] };
+            let mut bits = 0u64;
+            for dy in -r..r + 1 {
+                let py = max(0, min(height as i32 - 1, y + dy)) as u32;
+                for dx in -r..r + 1 {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+                    let px = max(0, min(width as i32 - 1, x + dx)) as u32;
+                    let p = unsafe { image.unsafe_get_pixel(px, py)[0] };
+                    bits = (bits << 1) | (p < centre) as u64;
+                }
+            }
+            out.push(bits);
+        }
+    }
+
+    out
+}
+
+/// Sums the costs for each disparity over the block of side length `2 * radius + 1`
+/// centred on each pixel. Blocks are truncated at the image boundary.
+fn aggregate_block_costs(volume: &CostVolume<u32>, radius: u32) -> CostVolume<u32> {
+    let (width, height, disparities) = (volume.width, volume.height, volume.disparities);
+    let mut out = CostVolume::new(width, height, disparities, 0u32);
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    // Integral image of the costs for a single disparity.
+    let stride = (width + 1) as usize;
+    let mut integral = vec![0u64; stride * (height + 1) as usize];
+
+    for d in 0..disparities as usize {
+        for y in 0..height {
+            let mut row_sum = 0u64;
+            for x in 0..width {
+                row_sum += volume.costs[volume.index(x, y) + d] as u64;
+                let i = (y as usize + 1) * stride + x as usize + 1;
+                integral[i] = integral[i - stride] + row_sum;
+            }
+        }
+
+        for y in 0..height {
+            let top = y.saturating_sub(radius) as usize;
+            let bottom = min(height - 1, y + radius) as usize + 1;
+            for x in 0..width {
+                let left = x.saturating_sub(radius) as usize;
+                let right = min(width - 1, x + radius) as usize + 1;
+                let sum = integral[bottom * stride + right] + integral[top * stride + left]
+                    - integral[top * stride + right]
+                    - integral[bottom * stride + left];
+                let i = out.index(x, y) + d;
+                out.costs[i] = min(sum, u32::MAX as u64) as u32;
+            }
+        }
+    }
+
+    out
+}
+
+/// Sums the costs of the minimum-cost paths ending at each pixel along each of
+/// the given directions, following Hirschmüller's recurrence.
+fn aggregate_path_costs(volume: &CostVolume<u32>, paths: SgmPaths, p1: u32, p2: u32) -> CostVolume<u32> {
+    let directions: &[(i32, i32)] = match paths {
+        SgmPaths::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+        SgmPaths::Eight => &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, 1), (1, -1), (-1, -1)],
+    };
+
+    let mut total = CostVolume::new(volume.width, volume.height, volume.disparities, 0u32);
+    let mut path = CostVolume::new(volume.width, volume.height, volume.disparities, 0u32);
+    for &(dx, dy) in directions {
+        aggregate_along_direction(volume, &mut path, dx, dy, p1, p2);
+        for (t, p) in total.costs.iter_mut().zip(path.costs.iter()) {
+            *t += *p;
+        }
+    }
+    total
+}
+
+/// Computes into `path` the cost of the minimum-cost path arriving at each pixel
+/// from direction `(dx, dy)`.
+fn aggregate_along_direction(
+    volume: &CostVolume<u32>,
+    path: &mut CostVolume<u32>,
+    dx: i32,
+    dy: i32,
+    p1: u32,
+    p2: u32,
+) {
+    let (width, height) = (volume.width as i32, volume.height as i32);
+    let disparities = volume.disparities as usize;
+
+    // Visit pixels in an order in which each pixel's predecessor
+    // along the path has already been visited.
+    let ys: Vec<i32> = if dy >= 0 { (0..height).collect() } else { (0..height).rev().collect() };
+    let xs: Vec<i32> = if dx >= 0 { (0..width).collect() } else { (0..width).rev().collect() };
+
+    for &y in &ys {
+        for &x in &xs {
+            let current = path.index(x as u32, y as u32);
+            let costs = volume.costs_at(x as u32, y as u32);
+            let (px, py) = (x - dx, y - dy);
+
+            if px < 0 || px >= width || py < 0 || py >= height {
+                path.costs[current..current + disparities].copy_from_slice(costs);
+                continue;
+            }
+
+            let previous = path.index(px as u32, py as u32);
+            let previous_min = *path.costs[previous..previous + disparities].iter().min().unwrap();
+
+            for (d, &cost) in costs.iter().enumerate() {
+                let same = path.costs[previous + d];
+                let lower = if d > 0 { path.costs[previous + d - 1] + p1 } else { u32::MAX };
+                let higher = if d + 1 < disparities { path.costs[previous + d + 1] + p1 } else { u32::MAX };
+                let jump = previous_min + p2;
+                let best = min(min(same, jump), min(lower, higher));
+                path.costs[current + d] = cost + best - previous_min;
+            }
+        }
+    }
+}
+
+/// Chooses the disparity with least cost at each pixel, optionally refining to
+/// sub-pixel accuracy and rejecting ambiguous matches or matches that fail a
+/// left-right consistency check.
+fn select_disparities(
+    volume: &CostVolume<u32>,
+    subpixel: bool,
+    uniqueness_ratio: Option<f32>,
+    left_right_tolerance: Option<f32>,
+) -> Image<Luma<f32>> {
+    let mut out = Image::new(volume.width, volume.height);
+
+    for y in 0..volume.height {
+        for x in 0..volume.width {
+            let costs = &volume.costs_at(x, y)[..volume.valid_disparities(x) as usize];
+            let best = argmin(costs);
+            let disparity = if uniqueness_ratio.map_or(false, |r| !is_unique(costs, best, r)) {
+                INVALID_DISPARITY
+            } else if subpixel {
+                best as f32 + subpixel_offset(costs, best)
+            } else {
+                best as f32
+            };
+            out.put_pixel(x, y, Luma([disparity]));
+        }
+    }
+
+    if let Some(tolerance) = left_right_tolerance {
+        let right = right_disparities(volume);
+        for y in 0..volume.height {
+            for x in 0..volume.width {
+                let disparity = out.get_pixel(x, y)[0];
+                if disparity == INVALID_DISPARITY {
+                    continue;
+                }
+                let rx = x as i32 - disparity.round() as i32;
+                let consistent = rx >= 0 && {
+                    let r = right[(y * volume.width + rx as u32) as usize];
+                    (disparity - r as f32).abs() <= tolerance
+                };
+                if !consistent {
+                    out.put_pixel(x, y, Luma([INVALID_DISPARITY]));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Returns false if any disparity not adjacent to `best` has cost within a factor
+/// of `1 - ratio` of the cost of `best`. In particular, ties are never unique.
+fn is_unique(costs: &[u32], best: usize, ratio: f32) -> bool {
+    let best_cost = costs[best] as f32;
+    costs.iter().enumerate().all(|(d, c)| {
+        (d as i32 - best as i32).abs() <= 1 || *c as f32 * (1.0 - ratio) > best_cost
+    })
+}
+
+/// Computes integer disparities with the right image as the reference, i.e. for each
+/// right pixel `(x, y)` finds the disparity `d` minimising the cost of matching it to the
+/// left pixel `(x + d, y)`.
+fn right_disparities(volume: &CostVolume<u32>) -> Vec<u32> {
+    let mut out = Vec::with_capacity((volume.width * volume.height) as usize);
+    for y in 0..volume.height {
+        for x in 0..volume.width {
+            let max_d = min(volume.disparities, volume.width - x);
+            let mut best = 0;
+            let mut best_cost = u32::MAX;
+            for d in 0..max_d {
+                let cost = volume.costs[volume.index(x + d, y) + d as usize];
+                if cost < best_cost {
+                    best = d;
+                    best_cost = cost;
+                }
+            }
+            out.push(best);
+        }
+    }
+    out
+}
+
+/// Index of the smallest value in a non-empty slice. Ties are resolved in favour of the lower index.
+fn argmin(costs: &[u32]) -> usize {
+    let mut best = 0;
+    for (i, c) in costs.iter().enumerate() {
+        if *c < costs[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+/// Offset in `(-0.5, 0.5)` of the minimum of the parabola through the costs at
+/// `best - 1`, `best` and `best + 1`, or 0 if `best` is at either end of `costs`.
+fn subpixel_offset(costs: &[u32], best: usize) -> f32 {
+    if best == 0 || best + 1 >= costs.len() {
+        return 0.0;
+    }
+    let prev = costs[best - 1] as f32;
+    let curr = costs[best] as f32;
+    let next = costs[best + 1] as f32;
+    let denominator = prev - 2.0 * curr + next;
+    if denominator <= 0.0 {
+        return 0.0;
+    }
+    let offset = (prev - next) / (2.0 * denominator);
+    offset.clamp(-0.5, 0.5)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::ImageProcError;
+    use test::{Bencher, black_box};
+
+    // A textured image with no repeated structure at the scales we search over.
+    fn textured(width: u32, height: u32, shift: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let x = x + shift;
+            Luma([((x * 7 + y * 13) * (x ^ y) % 251) as u8])
+        })
+    }
+
+    fn options(cost: MatchingCost) -> BlockMatchingOptions {
+        BlockMatchingOptions {
+            max_disparity: 10,
+            block_radius: 2,
+            cost: cost,
+            left_right_tolerance: None,
+            subpixel: false,
+        }
+    }
+
+    #[test]
+    fn test_block_matching_recovers_constant_shift() {
+        let left = textured(40, 20, 0);
+        let right = textured(40, 20, 4);
+
+        for &cost in [MatchingCost::SumOfAbsoluteDifferences, MatchingCost::Census].iter() {
+            let disparity = block_matching(&left, &right, options(cost)).unwrap();
+            for y in 2..18 {
+                for x in 14..38 {
+                    assert_eq!(disparity.get_pixel(x, y)[0], 4.0, "cost {:?}, x {}, y {}", cost, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_census_cost_is_invariant_to_brightness() {
+        let left = textured(40, 20, 0);
+        let right = GrayImage::from_fn(40, 20, |x, y| {
+            Luma([textured(40, 20, 4).get_pixel(x, y)[0] / 2 + 20])
+        });
+
+        let disparity = block_matching(&left, &right, options(MatchingCost::Census)).unwrap();
+        assert_eq!(disparity.get_pixel(20, 10)[0], 4.0);
+    }
+
+    #[test]
+    fn test_left_right_check_invalidates_unmatched_pixels() {
+        let left = textured(40, 20, 0);
+        let right = textured(40, 20, 4);
+        let mut opts = options(MatchingCost::SumOfAbsoluteDifferences);
+        opts.left_right_tolerance = Some(0.0);
+
+        let disparity = block_matching(&left, &right, opts).unwrap();
+        // The first four columns of the left image are not visible in the right image.
+        assert_eq!(disparity.get_pixel(1, 10)[0], INVALID_DISPARITY);
+        assert_eq!(disparity.get_pixel(20, 10)[0], 4.0);
+    }
+
+    #[test]
+    fn test_census_transform() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6;
+            7, 8, 9);
+        let census = census_transform(&image, 1);
+        // Neighbours of the centre pixel in row-major order: 1, 2, 3, 4, 6, 7, 8, 9
+        assert_eq!(census[4], 0b1111_0000);
+    }
+
+    #[test]
+    fn test_subpixel_offset() {
+        // Symmetric costs have their minimum exactly at the best disparity.
+        assert_eq!(subpixel_offset(&[4, 1, 4], 1), 0.0);
+        // Minimum of the parabola through (0, 4), (1, 1), (2, 2) is at 1.25.
+        assert_eq!(subpixel_offset(&[4, 1, 2], 1), 0.25);
+        // No refinement is possible at the ends of the search range.
+        assert_eq!(subpixel_offset(&[1, 4, 2], 0), 0.0);
+    }
+
+    #[test]
+    fn test_mismatched_dimensions_are_rejected() {
+        let left = GrayImage::new(5, 5);
+        let right = GrayImage::new(6, 5);
+        let mismatch = ImageProcError::DimensionMismatch { expected: (5, 5), actual: (6, 5) };
+        assert_eq!(
+            block_matching(&left, &right, options(MatchingCost::SumOfAbsoluteDifferences)).unwrap_err(),
+            mismatch);
+        assert_eq!(semi_global_matching(&left, &right, sgm_options()).unwrap_err(), mismatch);
+    }
+
+    fn sgm_options() -> SemiGlobalMatchingOptions {
+        SemiGlobalMatchingOptions {
+            max_disparity: 10,
+            paths: SgmPaths::Eight,
+            p1: 8,
+            p2: 32,
+            uniqueness_ratio: None,
+            left_right_tolerance: None,
+            subpixel: false,
+        }
+    }
+
+    #[test]
+    fn test_semi_global_matching_recovers_constant_shift() {
+        let left = textured(40, 20, 0);
+        let right = textured(40, 20, 4);
+
+        for &paths in [SgmPaths::Four, SgmPaths::Eight].iter() {
+            let mut options = sgm_options();
+            options.paths = paths;
+            let disparity = semi_global_matching(&left, &right, options).unwrap();
+            for y in 0..20 {
+                for x in 14..40 {
+                    assert_eq!(disparity.get_pixel(x, y)[0], 4.0, "paths {:?}, x {}, y {}", paths, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_semi_global_matching_fills_textureless_regions() {
+        // Both images contain a flat band, which can be matched at any
+        // disparity when considered in isolation.
+        let flat_band = |x: u32, y: u32, shift: u32| {
+            let x = x + shift;
+            if x >= 15 && x < 27 { 100 } else { ((x * 7 + y * 13) * (x ^ y) % 251) as u8 }
+        };
+        let left = GrayImage::from_fn(40, 20, |x, y| Luma([flat_band(x, y, 0)]));
+        let right = GrayImage::from_fn(40, 20, |x, y| Luma([flat_band(x, y, 4)]));
+
+        let disparity = semi_global_matching(&left, &right, sgm_options()).unwrap();
+        assert_eq!(disparity.get_pixel(21, 10)[0], 4.0);
+
+        let mut block_options = options(MatchingCost::Census);
+        block_options.block_radius = 1;
+        let block_disparity = block_matching(&left, &right, block_options).unwrap();
+        assert!(block_disparity.get_pixel(21, 10)[0] != 4.0);
+    }
+
+    #[test]
+    fn test_uniqueness_check_rejects_ambiguous_matches() {
+        let flat = GrayImage::from_pixel(20, 10, Luma([50u8]));
+        let mut options = sgm_options();
+        options.uniqueness_ratio = Some(0.1);
+
+        let disparity = semi_global_matching(&flat, &flat, options).unwrap();
+        assert_eq!(disparity.get_pixel(15, 5)[0], INVALID_DISPARITY);
+    }
+
+    #[test]
+    fn test_is_unique() {
+        assert!(is_unique(&[10, 2, 3, 10], 1, 0.1));
+        // Costs adjacent to the minimum are ignored.
+        assert!(is_unique(&[10, 2, 2, 10], 1, 0.5));
+        assert!(!is_unique(&[10, 2, 9, 2], 1, 0.1));
+        assert!(!is_unique(&[0, 0, 0, 0], 0, 0.1));
+    }
+
+    #[bench]
+    fn bench_block_matching_sad(b: &mut Bencher) {
+        let left = textured(200, 100, 0);
+        let right = textured(200, 100, 5);
+        b.iter(|| black_box(block_matching(&left, &right, options(MatchingCost::SumOfAbsoluteDifferences))));
+    }
+
+    #[bench]
+    fn bench_block_matching_census(b: &mut Bencher) {
+        let left = textured(200, 100, 0);
+        let right = textured(200, 100, 5);
+        b.iter(|| black_box(block_matching(&left, &right, options(MatchingCost::Census))));
+    }
+
+    #[bench]
+    fn bench_semi_global_matching(b: &mut Bencher) {
+        let left = textured(200, 100, 0);
+        let right = textured(200, 100, 5);
+        b.iter(|| black_box(semi_global_matching(&left, &right, sgm_options())));
+    }
+}
@@ -0,0 +1,267 @@
+//! Functions for computing the [structure tensor](https://en.wikipedia.org/wiki/Structure_tensor)
+//! of an image, and the local orientation and anisotropy derived from it.
+//!
+//! The tensors are used by [`corner_response`](../corners/fn.corner_response.html) and
+//! [`coherence_enhancing`](../diffusion/fn.coherence_enhancing.html) diffusion.
+
+use image::{Luma, Primitive};
+use conv::ValueInto;
+use definitions::{Image, MaybeSync};
+use filter::gaussian_blur_f32;
+use gradients::{horizontal_sobel_f32, vertical_sobel_f32};
+use std::f32;
+
+/// The structure tensor at a single point, i.e. the symmetric 2x2 matrix
+/// `[[xx, xy], [xy, yy]]` of locally averaged products of image gradients.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StructureTensor {
+    /// Average of the squared horizontal gradient.
+    pub xx: f32,
+    /// Average of the product of the horizontal and vertical gradients.
+    pub xy: f32,
+    /// Average of the squared vertical gradient.
+    pub yy: f32,
+}
+
+impl StructureTensor {
+    /// Returns the eigenvalues of the tensor, larger first. Both are non-negative
+    /// for tensors computed from an image.
+    pub fn eigenvalues(&self) -> (f32, f32) {
+        let mean = 0.5 * (self.xx + self.yy);
+        let root = (0.25 * (self.xx - self.yy).powi(2) + self.xy * self.xy).sqrt();
+        (mean + root, mean - root)
+    }
+
+    /// Returns `((l1 - l2) / (l1 + l2))^2`, where `l1` and `l2` are the eigenvalues.
+    ///
+    /// This is 1 for a neighbourhood whose gradients all have the same direction, e.g.
+    /// a straight edge, and 0 if the gradients have no preferred direction, e.g. at a
+    /// corner or in a region of constant intensity.
+    pub fn coherence(&self) -> f32 {
+        let trace = self.xx + self.yy;
+        if trace <= 0.0 {
+            return 0.0;
+        }
+        let (l1, l2) = self.eigenvalues();
+        ((l1 - l2) / trace).powi(2)
+    }
+
+    /// Returns the [Harris corner response](https://en.wikipedia.org/wiki/Harris_Corner_Detector)
+    /// `det - sensitivity * trace^2`. This is large and positive at corners, negative
+    /// at edges and small in flat regions. Typical values of `sensitivity` are 0.04 to 0.06.
+    pub fn harris_response(&self, sensitivity: f32) -> f32 {
+        let trace = self.xx + self.yy;
+        self.xx * self.yy - self.xy * self.xy - sensitivity * trace * trace
+    }
+
+    /// Returns the direction of the eigenvector with the larger eigenvalue, i.e. the dominant
+    /// gradient direction, in radians in the range (-pi / 2, pi / 2]. Angles are measured
+    /// clockwise from the positive x-axis, as the y-axis points down. Edges and texture
+    /// flow run perpendicular to this direction.
+    ///
+    /// Returns 0 if the tensor has no dominant direction.
+    pub fn orientation(&self) -> f32 {
+        let angle = 0.5 * (2.0 * self.xy).atan2(self.xx - self.yy);
+        if angle <= -f32::consts::FRAC_PI_2 { angle + f32::consts::PI } else { angle }
+    }
+}
+
+/// Per-pixel structure tensors of an image, stored as one image per tensor entry.
+#[derive(Clone)]
+pub struct StructureTensors {
+    /// Average of the squared horizontal gradient at each pixel.
+    pub xx: Image<Luma<f32>>,
+    /// Average of the product of the horizontal and vertical gradients at each pixel.
+    pub xy: Image<Luma<f32>>,
+    /// Average of the squared vertical gradient at each pixel.
+    pub yy: Image<Luma<f32>>,
+}
+
+impl StructureTensors {
+    /// Width and height of the images the tensors were computed from.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.xx.dimensions()
+    }
+
+    /// Returns the tensor at pixel (x, y).
+    ///
+    /// # Panics
+    /// If (x, y) is outside the image bounds.
+    pub fn get(&self, x: u32, y: u32) -> StructureTensor {
+        StructureTensor {
+            xx: self.xx.get_pixel(x, y)[0],
+            xy: self.xy.get_pixel(x, y)[0],
+            yy: self.yy.get_pixel(x, y)[0],
+        }
+    }
+
+    /// Applies `f` to the tensor at each pixel.
+    pub fn map<F>(&self, f: F) -> Image<Luma<f32>>
+    where
+        F: Fn(StructureTensor) -> f32,
+    {
+        let (width, height) = self.dimensions();
+        let mut out = Image::new(width, height);
+        for ((o, xx), (xy, yy)) in out.iter_mut().zip(self.xx.iter()).zip(self.xy.iter().zip(self.yy.iter())) {
+            *o = f(StructureTensor { xx: *xx, xy: *xy, yy: *yy });
+        }
+        out
+    }
+
+    /// Returns images of the larger and smaller eigenvalues of each tensor.
+    pub fn eigenvalues(&self) -> (Image<Luma<f32>>, Image<Luma<f32>>) {
+        (self.map(|t| t.eigenvalues().0), self.map(|t| t.eigenvalues().1))
+    }
+
+    /// Returns the [coherence](struct.StructureTensor.html#method.coherence) of each tensor.
+    pub fn coherence(&self) -> Image<Luma<f32>> {
+        self.map(|t| t.coherence())
+    }
+
+    /// Returns the [dominant orientation](struct.StructureTensor.html#method.orientation)
+    /// of each tensor.
+    pub fn orientation(&self) -> Image<Luma<f32>> {
+        self.map(|t| t.orientation())
+    }
+}
+
+/// Computes the structure tensor at each pixel of an image, using Sobel gradients
+/// averaged by a Gaussian with standard deviation `sigma`. If `sigma` is 0 then
+/// the gradient products are not smoothed.
+///
+/// # Panics
+/// If `sigma` is negative.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::structure_tensor::structure_tensor;
+///
+/// // A vertical edge.
+/// let image = gray_image!(
+///     0, 0, 0, 9, 9, 9;
+///     0, 0, 0, 9, 9, 9;
+///     0, 0, 0, 9, 9, 9;
+///     0, 0, 0, 9, 9, 9);
+///
+/// let tensors = structure_tensor(&image, 1.0);
+/// let t = tensors.get(3, 2);
+///
+/// // All gradients point along the x-axis.
+/// assert_eq!(t.xy, 0.0);
+/// assert_eq!(t.yy, 0.0);
+/// assert!(t.xx > 0.0);
+/// assert_eq!(t.coherence(), 1.0);
+/// assert_eq!(t.orientation(), 0.0);
+/// # }
+/// ```
+pub fn structure_tensor<T>(image: &Image<Luma<T>>, sigma: f32) -> StructureTensors
+where
+    T: Primitive + ValueInto<f32> + MaybeSync + 'static,
+{
+    assert!(sigma >= 0.0, "sigma must be non-negative, got {}", sigma);
+    let gx: Image<Luma<f32>> = horizontal_sobel_f32(image);
+    let gy: Image<Luma<f32>> = vertical_sobel_f32(image);
+
+    let (width, height) = image.dimensions();
+    let mut xx = Image::new(width, height);
+    let mut xy = Image::new(width, height);
+    let mut yy = Image::new(width, height);
+    for (((dx, dy), xx), (xy, yy)) in gx.iter().zip(gy.iter()).zip(xx.iter_mut()).zip(xy.iter_mut().zip(yy.iter_mut())) {
+        *xx = dx * dx;
+        *xy = dx * dy;
+        *yy = dy * dy;
+    }
+
+    if sigma > 0.0 {
+        xx = gaussian_blur_f32(&xx, sigma);
+        xy = gaussian_blur_f32(&xy, sigma);
+        yy = gaussian_blur_f32(&yy, sigma);
+    }
+
+    StructureTensors { xx, xy, yy }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::GrayImage;
+    use utils::gray_bench_image;
+    use test::{Bencher, black_box};
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "actual: {}, expected: {}", actual, expected);
+    }
+
+    #[test]
+    fn test_tensor_eigenvalues() {
+        let t = StructureTensor { xx: 2.0, xy: 1.0, yy: 2.0 };
+        assert_eq!(t.eigenvalues(), (3.0, 1.0));
+        assert_close(t.coherence(), 0.25);
+        assert_close(t.orientation(), f32::consts::FRAC_PI_4);
+
+        let isotropic = StructureTensor { xx: 5.0, xy: 0.0, yy: 5.0 };
+        assert_eq!(isotropic.eigenvalues(), (5.0, 5.0));
+        assert_eq!(isotropic.coherence(), 0.0);
+
+        assert_eq!(t.harris_response(0.05), 3.0 - 0.05 * 16.0);
+
+        let zero = StructureTensor { xx: 0.0, xy: 0.0, yy: 0.0 };
+        assert_eq!(zero.coherence(), 0.0);
+        assert_eq!(zero.orientation(), 0.0);
+    }
+
+    #[test]
+    fn test_tensor_orientation_range() {
+        // Gradients along the y-axis.
+        let vertical = StructureTensor { xx: 0.0, xy: 0.0, yy: 1.0 };
+        assert_close(vertical.orientation(), f32::consts::FRAC_PI_2);
+        let anticlockwise = StructureTensor { xx: 2.0, xy: -1.0, yy: 2.0 };
+        assert_close(anticlockwise.orientation(), -f32::consts::FRAC_PI_4);
+    }
+
+    #[test]
+    fn test_structure_tensor_diagonal_stripes() {
+        // Intensity increases along (1, 1), so gradients point diagonally down and right.
+        let image = GrayImage::from_fn(20, 20, |x, y| Luma([((x + y) % 8 * 30) as u8]));
+        let tensors = structure_tensor(&image, 3.0);
+        let t = tensors.get(10, 10);
+        assert_close(t.orientation(), f32::consts::FRAC_PI_4);
+        assert!(t.coherence() > 0.99);
+        assert_eq!(tensors.orientation().get_pixel(10, 10)[0], t.orientation());
+    }
+
+    #[test]
+    fn test_structure_tensor_maps() {
+        let image = gray_image!(
+            0, 0, 0;
+            0, 9, 0;
+            0, 0, 0);
+        let tensors = structure_tensor(&image, 0.0);
+        assert_eq!(tensors.dimensions(), (3, 3));
+        // Unsmoothed tensors are rank one, so the smaller eigenvalue is 0.
+        let (larger, smaller) = tensors.eigenvalues();
+        for (l, s) in larger.iter().zip(smaller.iter()) {
+            assert!(*l >= 0.0);
+            assert_close(*s, 0.0);
+        }
+        let coherence = tensors.coherence();
+        assert_close(coherence.get_pixel(0, 1)[0], 1.0);
+        assert_eq!(coherence.get_pixel(1, 1)[0], 0.0);
+    }
+
+    #[test]
+    fn test_structure_tensor_empty() {
+        let tensors = structure_tensor(&GrayImage::new(0, 0), 1.0);
+        assert_eq!(tensors.dimensions(), (0, 0));
+    }
+
+    #[bench]
+    fn bench_structure_tensor(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        b.iter(|| black_box(structure_tensor(&image, 2.0)));
+    }
+}
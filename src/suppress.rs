@@ -1,17 +1,41 @@
 //! Functions for suppressing non-maximal values.
 
 use std::cmp;
+use std::cmp::Ordering;
+use std::f32;
 use definitions::{Position, Score};
+use evaluation::Detection;
 use image::{GenericImage, ImageBuffer, Luma, Primitive};
 use itertools::Itertools;
+use rect::Rect;
 
 /// Returned image has zeroes for all inputs pixels which do not have the greatest
 /// intensity in the (2 * radius + 1) square block centred on them.
 /// Ties are resolved lexicographically.
+///
+/// Accepts any scalar pixel type, including `f32` score maps such as the outputs of
+/// [`corner_response`](../corners/fn.corner_response.html) or
+/// [`match_template`](../template_matching/fn.match_template.html). The output is
+/// unspecified if the image contains NaN values.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{ImageBuffer, Luma};
+/// use imageproc::definitions::Image;
+/// use imageproc::suppress::suppress_non_maximum;
+///
+/// let scores: Image<Luma<f32>> = ImageBuffer::from_raw(5, 1, vec![0.5, 0.9, 0.7, -0.2, 0.8]).unwrap();
+/// let maxima = suppress_non_maximum(&scores, 1);
+/// assert_eq!(maxima.into_raw(), vec![0.0, 0.9, 0.0, 0.0, 0.8]);
+/// # }
+/// ```
 pub fn suppress_non_maximum<I, C>(image: &I, radius: u32) -> ImageBuffer<Luma<C>, Vec<C>>
 where
     I: GenericImage<Pixel = Luma<C>>,
-    C: Primitive + Ord + 'static,
+    C: Primitive + PartialOrd + 'static,
 {
     let (width, height) = image.dimensions();
     let mut out: ImageBuffer<Luma<C>, Vec<C>> = ImageBuffer::new(width, height);
@@ -90,7 +114,7 @@ fn contains_greater_value<I, C>(
 ) -> bool
 where
     I: GenericImage<Pixel = Luma<C>>,
-    C: Primitive + Ord + 'static,
+    C: Primitive + PartialOrd + 'static,
 {
     for cy in y_lower..y_upper {
         for cx in x_lower..x_upper {
@@ -171,9 +195,267 @@ where
     max_ts
 }
 
+/// How [`soft_non_maximum_suppression`](fn.soft_non_maximum_suppression.html) reduces
+/// the score of a detection that overlaps a detection with a higher score.
+///
+/// See [Improving Object Detection With One Line of Code](https://arxiv.org/abs/1704.04503).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScoreDecay {
+    /// Remove detections whose intersection over union with a higher scoring detection
+    /// is at least `iou_threshold`. This is standard non-maximum suppression.
+    Hard {
+        /// Minimum overlap at which a detection is removed.
+        iou_threshold: f32,
+    },
+    /// Multiply the score of a detection by `1 - iou` for each higher scoring detection
+    /// it overlaps with an intersection over union `iou` of at least `iou_threshold`.
+    Linear {
+        /// Minimum overlap at which a score is reduced.
+        iou_threshold: f32,
+    },
+    /// Multiply the score of a detection by `exp(-iou * iou / sigma)` for each higher
+    /// scoring detection it overlaps with an intersection over union `iou`.
+    Gaussian {
+        /// Controls how quickly scores fall with overlap. Must be positive.
+        sigma: f32,
+    },
+}
+
+impl ScoreDecay {
+    fn weight(&self, iou: f32) -> f32 {
+        match *self {
+            ScoreDecay::Hard { iou_threshold } => if iou >= iou_threshold { 0.0 } else { 1.0 },
+            ScoreDecay::Linear { iou_threshold } => if iou >= iou_threshold { 1.0 - iou } else { 1.0 },
+            ScoreDecay::Gaussian { sigma } => (-iou * iou / sigma).exp(),
+        }
+    }
+}
+
+/// Returns the detections that don't overlap a higher scoring detection with an
+/// intersection over union of at least `iou_threshold`, in decreasing order of score.
+/// Ties in score are resolved in favour of the earlier detection.
+///
+/// # Examples
+/// ```
+/// use imageproc::evaluation::Detection;
+/// use imageproc::rect::Rect;
+/// use imageproc::suppress::non_maximum_suppression;
+///
+/// let detections = [
+///     Detection { rect: Rect::at(0, 0).of_size(10, 10), score: 0.8 },
+///     Detection { rect: Rect::at(1, 0).of_size(10, 10), score: 0.9 },
+///     Detection { rect: Rect::at(20, 0).of_size(10, 10), score: 0.5 },
+/// ];
+///
+/// assert_eq!(non_maximum_suppression(&detections, 0.5), vec![detections[1], detections[2]]);
+/// ```
+pub fn non_maximum_suppression(detections: &[Detection], iou_threshold: f32) -> Vec<Detection> {
+    soft_non_maximum_suppression(detections, ScoreDecay::Hard { iou_threshold }, 0.0)
+}
+
+/// Soft non-maximum suppression. Repeatedly selects the remaining detection with the
+/// highest score and reduces the scores of the remaining detections that overlap it
+/// according to `decay`. Detections whose score is reduced below `min_score` are discarded.
+///
+/// Returns the selected detections with their reduced scores, in the order in which they
+/// were selected. This is in decreasing order of (reduced) score. Ties in score are resolved
+/// in favour of the earlier detection. Detections with an input score below `min_score`
+/// are never selected.
+///
+/// This takes O(n^2) time for n detections.
+///
+/// # Examples
+/// ```
+/// use imageproc::evaluation::Detection;
+/// use imageproc::rect::Rect;
+/// use imageproc::suppress::{soft_non_maximum_suppression, ScoreDecay};
+///
+/// let detections = [
+///     Detection { rect: Rect::at(0, 0).of_size(10, 10), score: 0.9 },
+///     Detection { rect: Rect::at(5, 0).of_size(10, 10), score: 0.8 },
+/// ];
+///
+/// // The boxes overlap with an intersection over union of 1/3.
+/// let decay = ScoreDecay::Linear { iou_threshold: 0.3 };
+/// let kept = soft_non_maximum_suppression(&detections, decay, 0.1);
+///
+/// assert_eq!(kept.len(), 2);
+/// assert_eq!(kept[0], detections[0]);
+/// assert!((kept[1].score - 0.8 * 2.0 / 3.0).abs() < 1e-6);
+/// ```
+pub fn soft_non_maximum_suppression(
+    detections: &[Detection],
+    decay: ScoreDecay,
+    min_score: f32,
+) -> Vec<Detection> {
+    let mut remaining: Vec<Detection> = detections.iter().filter(|d| d.score >= min_score).cloned().collect();
+    let mut selected = Vec::new();
+
+    while !remaining.is_empty() {
+        let mut best = 0;
+        for (i, d) in remaining.iter().enumerate() {
+            if d.score > remaining[best].score {
+                best = i;
+            }
+        }
+        // Preserve the order of the remaining detections, so that ties
+        // continue to be resolved in favour of earlier inputs.
+        let current = remaining.remove(best);
+        remaining = remaining
+            .into_iter()
+            .filter_map(|mut d| {
+                d.score *= decay.weight(current.rect.iou(d.rect));
+                if d.score > 0.0 && d.score >= min_score { Some(d) } else { None }
+            })
+            .collect();
+        selected.push(current);
+    }
+
+    selected
+}
+
+/// Non-maximum suppression which merges each group of overlapping detections into a single
+/// detection, rather than discarding all but the highest scoring detection in the group.
+///
+/// Repeatedly selects the remaining detection with the highest score and removes it along
+/// with every remaining detection whose intersection over union with it is at least
+/// `iou_threshold`. Each edge of the merged box is the average of the corresponding edges
+/// of the removed boxes, weighted by their scores, and rounded to the nearest pixel.
+/// The merged detection has the score of the selected detection. Detections with
+/// non-positive scores are removed from groups but do not contribute to the average.
+///
+/// Returns the merged detections in decreasing order of score. Ties in score are resolved
+/// in favour of the earlier detection. This takes O(n^2) time for n detections.
+///
+/// # Examples
+/// ```
+/// use imageproc::evaluation::Detection;
+/// use imageproc::rect::Rect;
+/// use imageproc::suppress::weighted_non_maximum_suppression;
+///
+/// let detections = [
+///     Detection { rect: Rect::at(0, 0).of_size(10, 10), score: 0.6 },
+///     Detection { rect: Rect::at(5, 0).of_size(10, 10), score: 0.2 },
+///     Detection { rect: Rect::at(50, 0).of_size(10, 10), score: 0.5 },
+/// ];
+///
+/// // The first two boxes overlap with an intersection over union of 1/3.
+/// assert_eq!(
+///     weighted_non_maximum_suppression(&detections, 0.3),
+///     vec![
+///         Detection { rect: Rect::at(1, 0).of_size(10, 10), score: 0.6 },
+///         detections[2],
+///     ]
+/// );
+/// ```
+pub fn weighted_non_maximum_suppression(detections: &[Detection], iou_threshold: f32) -> Vec<Detection> {
+    let mut remaining = detections.to_vec();
+    let mut merged = Vec::new();
+
+    while !remaining.is_empty() {
+        let mut best = 0;
+        for (i, d) in remaining.iter().enumerate() {
+            if d.score > remaining[best].score {
+                best = i;
+            }
+        }
+        let current = remaining.remove(best);
+        let (group, rest): (Vec<Detection>, Vec<Detection>) = remaining
+            .into_iter()
+            .partition(|d| current.rect.iou(d.rect) >= iou_threshold);
+        remaining = rest;
+
+        let mut total = 0f64;
+        let mut bounds = [0f64; 4];
+        for d in Some(current).iter().chain(&group).filter(|d| d.score > 0.0) {
+            let weight = d.score as f64;
+            let edges = [d.rect.left(), d.rect.top(), d.rect.right(), d.rect.bottom()];
+            for (bound, &edge) in bounds.iter_mut().zip(&edges) {
+                *bound += weight * edge as f64;
+            }
+            total += weight;
+        }
+        let rect = if total > 0.0 {
+            let edge = |i: usize| (bounds[i] / total).round() as i32;
+            // Each edge is a weighted average of edges, so right >= left and bottom >= top.
+            Rect::from_bounds(edge(0), edge(1), edge(2), edge(3)).unwrap()
+        } else {
+            current.rect
+        };
+        merged.push(Detection { rect, score: current.score });
+    }
+
+    merged
+}
+
+/// Adaptive non-maximal suppression. Returns up to `count` of the given items, chosen to
+/// have high scores and be well distributed across the image.
+///
+/// The suppression radius of an item is the distance to the nearest item whose score
+/// multiplied by `robustness` is greater than its own score. The items with the largest
+/// suppression radii are returned, in decreasing order of radius. Ties are resolved in
+/// favour of the higher score and then lexicographically by `(y, x)`.
+///
+/// `robustness` is usually slightly less than 1, so that an item is only suppressed by a
+/// significantly stronger neighbour. A value of 0.9 is typical.
+///
+/// See [Multi-Image Matching using Multi-Scale Oriented Patches](https://www.microsoft.com/en-us/research/publication/multi-image-matching-using-multi-scale-oriented-patches/).
+/// This takes O(n^2) time for n items.
+///
+/// # Examples
+/// ```
+/// use imageproc::corners::Corner;
+/// use imageproc::suppress::adaptive_non_maximal_suppression;
+///
+/// // A cluster of strong corners and a single weaker corner far from it.
+/// let corners = [
+///     Corner::new(10, 10, 100.0),
+///     Corner::new(11, 10, 85.0),
+///     Corner::new(10, 12, 80.0),
+///     Corner::new(50, 40, 30.0),
+/// ];
+///
+/// let chosen = adaptive_non_maximal_suppression(&corners, 2, 0.9);
+/// assert_eq!(chosen, vec![corners[0], corners[3]]);
+/// ```
+pub fn adaptive_non_maximal_suppression<T>(ts: &[T], count: usize, robustness: f32) -> Vec<T>
+where
+    T: Position + Score + Copy,
+{
+    let mut by_score = ts.to_vec();
+    by_score.sort_by(|c, d| {
+        d.score()
+            .partial_cmp(&c.score())
+            .unwrap_or(Ordering::Equal)
+            .then((c.y(), c.x()).cmp(&(d.y(), d.x())))
+    });
+
+    let mut radii: Vec<(f32, usize)> = Vec::with_capacity(by_score.len());
+    for (i, t) in by_score.iter().enumerate() {
+        let mut radius_squared = f32::INFINITY;
+        // Only items earlier in score order can suppress t.
+        for s in &by_score[..i] {
+            if s.score() * robustness > t.score() {
+                let dx = s.x() as f32 - t.x() as f32;
+                let dy = s.y() as f32 - t.y() as f32;
+                radius_squared = radius_squared.min(dx * dx + dy * dy);
+            }
+        }
+        radii.push((radius_squared, i));
+    }
+
+    // Sorting is stable, so equal radii remain in score order.
+    radii.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    radii.iter().take(count).map(|&(_, i)| by_score[i]).collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::{local_maxima, suppress_non_maximum};
+    use super::{adaptive_non_maximal_suppression, non_maximum_suppression, soft_non_maximum_suppression, ScoreDecay};
+    use super::weighted_non_maximum_suppression;
+    use evaluation::Detection;
+    use rect::Rect;
     use definitions::{Position, Score};
     use image::{GenericImage, GrayImage, ImageBuffer, Luma, Primitive};
     use noise::gaussian_noise_mut;
@@ -348,7 +630,7 @@ mod test {
     fn suppress_non_maximum_reference<I, C>(image: &I, radius: u32) -> ImageBuffer<Luma<C>, Vec<C>>
     where
         I: GenericImage<Pixel = Luma<C>>,
-        C: Primitive + Ord + 'static,
+        C: Primitive + PartialOrd + 'static,
     {
         let (width, height) = image.dimensions();
         let mut out = ImageBuffer::new(width, height);
@@ -391,6 +673,18 @@ mod test {
         out
     }
 
+    #[test]
+    fn test_suppress_non_maximum_f32_matches_reference_implementation() {
+        let image: ImageBuffer<Luma<f32>, Vec<f32>> = ImageBuffer::from_fn(40, 30, |x, y| {
+            Luma([((x * 7 + y * 13) % 17) as f32 * 0.25 - 1.5 + (x as f32 * 0.3).sin()])
+        });
+        for &radius in &[0, 1, 3, 8] {
+            let expected = suppress_non_maximum_reference(&image, radius);
+            let actual = suppress_non_maximum(&image, radius);
+            assert_eq!(actual.into_raw(), expected.into_raw(), "radius {}", radius);
+        }
+    }
+
     #[test]
     fn test_suppress_non_maximum_matches_reference_implementation() {
         fn prop(image: GrayTestImage) -> TestResult {
@@ -410,4 +704,139 @@ mod test {
         assert_eq!((0u32..4).step(4).collect::<Vec<u32>>(), vec![0]);
         assert_eq!((4u32..4).step(4).collect::<Vec<u32>>(), vec![]);
     }
+
+    fn detection(x: i32, y: i32, score: f32) -> Detection {
+        Detection { rect: Rect::at(x, y).of_size(10, 10), score }
+    }
+
+    #[test]
+    fn test_non_maximum_suppression() {
+        let detections = [
+            detection(0, 0, 0.5),
+            detection(2, 0, 0.9),
+            detection(4, 0, 0.7),
+            detection(30, 0, 0.6),
+            // Tie with the first detection, but doesn't overlap it enough to be suppressed.
+            detection(6, 6, 0.5),
+        ];
+        // iou of boxes offset by 2 is 80 / 120, and of those offset by 4 is 60 / 140.
+        assert_eq!(
+            non_maximum_suppression(&detections, 0.5),
+            vec![detections[1], detections[3], detections[4]]
+        );
+        assert_eq!(
+            non_maximum_suppression(&detections, 0.7),
+            vec![detections[1], detections[2], detections[3], detections[0], detections[4]]
+        );
+        assert_eq!(non_maximum_suppression(&[], 0.5), vec![]);
+    }
+
+    #[test]
+    fn test_soft_non_maximum_suppression_gaussian() {
+        let detections = [detection(0, 0, 1.0), detection(0, 0, 0.9), detection(50, 0, 0.2)];
+        let decay = ScoreDecay::Gaussian { sigma: 0.5 };
+
+        let kept = soft_non_maximum_suppression(&detections, decay, 0.0);
+        // The duplicate box has iou 1, so its score is multiplied by exp(-2).
+        let reduced = 0.9 * (-2f32).exp();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0], detections[0]);
+        assert_eq!(kept[1], detections[2]);
+        assert!((kept[2].score - reduced).abs() < 1e-6);
+        assert_eq!(kept[2].rect, detections[1].rect);
+
+        // A high enough minimum score discards the duplicate.
+        let kept = soft_non_maximum_suppression(&detections, decay, 0.15);
+        assert_eq!(kept, vec![detections[0], detections[2]]);
+    }
+
+    #[test]
+    fn test_soft_non_maximum_suppression_linear_threshold() {
+        // iou of 60 / 140, below the threshold.
+        let detections = [detection(0, 0, 0.9), detection(4, 0, 0.8)];
+        let kept = soft_non_maximum_suppression(&detections, ScoreDecay::Linear { iou_threshold: 0.5 }, 0.0);
+        assert_eq!(kept, detections.to_vec());
+    }
+
+    #[test]
+    fn test_weighted_non_maximum_suppression() {
+        let detections = [
+            detection(0, 0, 0.5),
+            detection(2, 0, 0.9),
+            detection(4, 2, 0.4),
+            detection(30, 0, 0.6),
+            // Overlaps the highest scoring detection, but doesn't contribute to the merged box.
+            detection(2, 1, 0.0),
+        ];
+        // The first three are merged: left = (0.5 * 0 + 0.9 * 2 + 0.4 * 4) / 1.8 = 1.89 and
+        // top = (0.4 * 2) / 1.8 = 0.44.
+        assert_eq!(
+            weighted_non_maximum_suppression(&detections, 0.4),
+            vec![
+                Detection { rect: Rect::at(2, 0).of_size(10, 10), score: 0.9 },
+                detections[3],
+            ]
+        );
+        // With a higher threshold the third box is no longer merged, and is
+        // kept after the higher scoring fourth box.
+        assert_eq!(
+            weighted_non_maximum_suppression(&detections, 0.6),
+            vec![
+                Detection { rect: Rect::at(1, 0).of_size(10, 10), score: 0.9 },
+                detections[3],
+                detections[2],
+            ]
+        );
+        assert_eq!(weighted_non_maximum_suppression(&[], 0.5), vec![]);
+    }
+
+    #[test]
+    fn test_weighted_non_maximum_suppression_different_sizes() {
+        let detections = [
+            Detection { rect: Rect::at(0, 0).of_size(10, 10), score: 1.0 },
+            Detection { rect: Rect::at(0, 0).of_size(12, 12), score: 1.0 },
+            Detection { rect: Rect::at(-1, -1).of_size(13, 13), score: 0.0 },
+        ];
+        // Right and bottom edges are the average of 9 and 11.
+        assert_eq!(
+            weighted_non_maximum_suppression(&detections, 0.5),
+            vec![Detection { rect: Rect::at(0, 0).of_size(11, 11), score: 1.0 }]
+        );
+        // Groups with no positive scores keep the selected box.
+        assert_eq!(
+            weighted_non_maximum_suppression(&detections[2..], 0.5),
+            vec![detections[2]]
+        );
+    }
+
+    #[test]
+    fn test_adaptive_non_maximal_suppression() {
+        let ts = vec![
+            T::new(0, 0, 10.0),
+            T::new(1, 0, 9.5),
+            T::new(3, 0, 5.0),
+            T::new(10, 0, 6.0),
+            T::new(0, 20, 1.0),
+        ];
+        // (1, 0) isn't suppressed by (0, 0) as 10 * 0.9 < 9.5, so both have infinite
+        // radius. The other radii are 2 for (3, 0), 9 for (10, 0) and 20 for (0, 20).
+        assert_eq!(
+            adaptive_non_maximal_suppression(&ts, 5, 0.9),
+            vec![ts[0], ts[1], ts[4], ts[3], ts[2]]
+        );
+        assert_eq!(adaptive_non_maximal_suppression(&ts, 2, 0.9), vec![ts[0], ts[1]]);
+        // With robustness 1, (1, 0) has radius 1.
+        assert_eq!(
+            adaptive_non_maximal_suppression(&ts, 5, 1.0),
+            vec![ts[0], ts[4], ts[3], ts[2], ts[1]]
+        );
+        assert_eq!(adaptive_non_maximal_suppression(&ts[..0], 2, 0.9), vec![]);
+    }
+
+    #[test]
+    fn test_adaptive_non_maximal_suppression_ties() {
+        let ts = vec![T::new(5, 5, 1.0), T::new(0, 0, 1.0), T::new(3, 0, 1.0)];
+        // No item suppresses another, so all radii are infinite.
+        assert_eq!(adaptive_non_maximal_suppression(&ts, 3, 0.9), vec![ts[1], ts[2], ts[0]]);
+    }
 }
@@ -1,9 +1,12 @@
 //! Functions for performing template matching.
 use image::Primitive;
 use definitions::Image;
-use rect::Rect;
-use integral_image::{integral_squared_image, sum_image_pixels};
-use image::{GrayImage, Luma};
+use error::{check_non_empty, ImageProcError, Result};
+use fft::{correlate, fft_is_faster};
+use integral_image::{integral_image_as, integral_squared_image_as, sum_image_pixels};
+use view::enumerate_pixels;
+use image::{GenericImageView, GrayImage, Luma};
+use std::cmp::Ordering;
 
 /// Method used to compute the matching score between a template and an image region.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -13,76 +16,237 @@ pub enum MatchTemplateMethod {
     SumOfSquaredErrors,
     /// Divides the sum computed using `SumOfSquaredErrors` by a normalization term.
     SumOfSquaredErrorsNormalized,
+    /// Sum of the products of image and template pixel intensities.
+    CrossCorrelation,
+    /// Divides the sum computed using `CrossCorrelation` by the square root of the product
+    /// of the sums of squares of image and template pixel intensities. Scores lie in `[0, 1]`.
+    CrossCorrelationNormalized,
+    /// Normalized cross-correlation of the image and template after subtracting their means,
+    /// i.e. the Pearson correlation coefficient of their pixel intensities. Scores lie in
+    /// `[-1, 1]`, and are unaffected by changes in the brightness or contrast of the image.
+    /// The score is 0 if either the image region or the template is constant.
+    CorrelationCoefficientNormalized,
+}
+
+impl MatchTemplateMethod {
+    /// Returns true if lower scores indicate better matches for this method,
+    /// and false if higher scores do.
+    pub fn lower_is_better(&self) -> bool {
+        match *self {
+            MatchTemplateMethod::SumOfSquaredErrors
+            | MatchTemplateMethod::SumOfSquaredErrorsNormalized => true,
+            MatchTemplateMethod::CrossCorrelation
+            | MatchTemplateMethod::CrossCorrelationNormalized
+            | MatchTemplateMethod::CorrelationCoefficientNormalized => false,
+        }
+    }
 }
 
 /// Slides a `template` over an `image` and scores the match at each point using
 /// the requested `method`.
 ///
 /// The returned image has dimensions `image.width() - template.width() + 1` by
-/// `image.height() - template.height() + 1`.
+/// `image.height() - template.height() + 1`. The score at `(x, y)` is for the region
+/// of `image` with top-left corner `(x, y)`.
 ///
-/// # Panics
+/// Use [`best_match`](fn.best_match.html) or [`find_matches`](fn.find_matches.html)
+/// to locate matches from the returned scores.
 ///
-/// If either dimension of `template` is not strictly less than the corresponding dimension
-/// of `image`.
-pub fn match_template(image: &GrayImage, template: &GrayImage, method: MatchTemplateMethod) -> Image<Luma<f32>> {
-    use image::GenericImageView;
-
+/// Sums of products of image and template pixels are computed directly for small
+/// templates, and using the fast Fourier transform for templates large enough that this
+/// is quicker. Both approaches give the same results.
+///
+/// Returns `ImageProcError::KernelTooLarge` if either dimension of `template` is not strictly
+/// less than the corresponding dimension of `image`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::template_matching::{best_match, match_template, MatchTemplateMethod};
+///
+/// let image = gray_image!(
+///     10, 10, 10, 10, 10;
+///     10, 10, 10, 10, 10;
+///     10, 10, 60, 90, 10;
+///     10, 10, 30, 40, 10);
+///
+/// // The same pattern as the bright region of the image, at a lower contrast.
+/// let template = gray_image!(
+///     20, 35;
+///      5, 10);
+///
+/// let method = MatchTemplateMethod::CorrelationCoefficientNormalized;
+/// let scores = match_template(&image, &template, method).unwrap();
+/// let best = best_match(&scores, method).unwrap();
+///
+/// assert_eq!(best.location, (2, 2));
+/// assert!((best.score - 1.0).abs() < 1e-6);
+/// # }
+/// ```
+pub fn match_template(image: &GrayImage, template: &GrayImage, method: MatchTemplateMethod) -> Result<Image<Luma<f32>>> {
     let (image_width, image_height) = image.dimensions();
     let (template_width, template_height) = template.dimensions();
 
-    assert!(image_width > template_width, "image width must strictly exceed template width");
-    assert!(image_height > template_height, "image height must strictly exceed template height");
+    if image_width <= template_width || image_height <= template_height {
+        return Err(ImageProcError::KernelTooLarge {
+            kernel: (template_width, template_height),
+            image: (image_width, image_height),
+        });
+    }
+
+    let use_fft = fft_is_faster(image.dimensions(), template.dimensions());
+    Ok(match_template_impl(image, template, method, use_fft))
+}
+
+fn match_template_impl(image: &GrayImage, template: &GrayImage, method: MatchTemplateMethod, use_fft: bool) -> Image<Luma<f32>> {
+    let (image_width, image_height) = image.dimensions();
+    let (template_width, template_height) = template.dimensions();
 
-    let should_normalize = method == MatchTemplateMethod::SumOfSquaredErrorsNormalized;
-    let image_squared_integral = if should_normalize { Some(integral_squared_image(&image)) } else { None };
-    let template_squared_sum = if should_normalize { Some(sum_squares(&template)) } else { None };
+    let use_products = !method.lower_is_better();
+    // The FFT computes products, so sums of squared errors are recovered from the
+    // identity (i - t)^2 = i^2 - 2it + t^2 using the sums of squared image pixels.
+    let image_squared_integral = match method {
+        MatchTemplateMethod::CrossCorrelation => None,
+        MatchTemplateMethod::SumOfSquaredErrors if !use_fft => None,
+        _ => Some(integral_squared_image_as::<u64, _>(image)),
+    };
+    let image_integral = match method {
+        MatchTemplateMethod::CorrelationCoefficientNormalized => Some(integral_image_as::<u64, _>(image)),
+        _ => None,
+    };
+    let template_count = (template_width * template_height) as f64;
+    let template_sum = template.iter().map(|p| *p as f64).sum::<f64>();
+    let template_squared_sum = sum_squares(template);
 
     let mut result = Image::new(image_width - template_width + 1, image_height - template_height + 1);
 
+    let products = if use_fft {
+        let to_f64 = |image: &GrayImage| image.iter().map(|p| *p as f64).collect::<Vec<_>>();
+        Some(correlate(
+            &to_f64(image), image_width as usize, image_height as usize,
+            &to_f64(template), template_width as usize, template_height as usize,
+        ))
+    } else {
+        None
+    };
+
     for y in 0..result.height() {
         for x in 0..result.width() {
-            let mut score = 0f32;
-
-            for dy in 0..template_height {
-                for dx in 0..template_width {
-                    let image_value = unsafe { image.unsafe_get_pixel(x + dx, y + dy)[0] as f32 };
-                    let template_value = unsafe { template.unsafe_get_pixel(dx, dy)[0] as f32 };
-                    score += (image_value - template_value).powf(2.0);
+            let (right, bottom) = (x + template_width - 1, y + template_height - 1);
+            let region_sum = |integral: &Image<Luma<u64>>| sum_image_pixels(integral, x, y, right, bottom) as f64;
+
+            let mut score = 0f64;
+
+            if let Some(ref products) = products {
+                // The exact sum of products is an integer, so rounding removes
+                // the FFT's floating point errors.
+                let product = products[(y * result.width() + x) as usize].round();
+                score = if use_products {
+                    product
+                } else {
+                    let squares = image_squared_integral.as_ref().unwrap();
+                    region_sum(squares) - 2.0 * product + template_squared_sum
+                };
+            } else {
+                for dy in 0..template_height {
+                    for dx in 0..template_width {
+                        let image_value = unsafe { image.unsafe_get_pixel(x + dx, y + dy)[0] as f64 };
+                        let template_value = unsafe { template.unsafe_get_pixel(dx, dy)[0] as f64 };
+                        if use_products {
+                            score += image_value * template_value;
+                        } else {
+                            score += (image_value - template_value).powi(2);
+                        }
+                    }
                 }
             }
 
-            if let (&Some(ref i), &Some(t)) = (&image_squared_integral, &template_squared_sum) {
-                let region = Rect::at(x as i32, y as i32).of_size(template_width, template_height);
-                let norm = normalization_term(i, t, region);
-                score /= norm;
+            match (method, &image_squared_integral, &image_integral) {
+                (_, &Some(ref squares), &Some(ref sums)) => {
+                    // Subtracting the means from the template and image region.
+                    let image_sum = region_sum(sums);
+                    let covariance = score - image_sum * template_sum / template_count;
+                    let image_variance = region_sum(squares) - image_sum * image_sum / template_count;
+                    let template_variance = template_squared_sum - template_sum * template_sum / template_count;
+                    let norm = (image_variance * template_variance).sqrt();
+                    score = if norm > 0.0 { covariance / norm } else { 0.0 };
+                }
+                (MatchTemplateMethod::SumOfSquaredErrors, _, _) => {}
+                (_, &Some(ref squares), &None) => {
+                    let norm = (region_sum(squares) as f32 * template_squared_sum as f32).sqrt();
+                    score = (score as f32 / norm) as f64;
+                }
+                _ => {}
             }
 
-            result.put_pixel(x, y, Luma([score]));
+            result.put_pixel(x, y, Luma([score as f32]));
         }
     }
 
     result
 }
 
-fn sum_squares(template: &GrayImage) -> f32 {
-    template.iter().map(|p| *p as f32 * *p as f32).sum()
+fn sum_squares(template: &GrayImage) -> f64 {
+    template.iter().map(|p| *p as f64 * *p as f64).sum()
 }
 
-/// Returns the square root of the product of the sum of squares of
-/// pixel intensities in template and the provided region of image.
-fn normalization_term(
-    image_squared_integral: &Image<Luma<u32>>,
-    template_squared_sum: f32,
-    region: Rect) -> f32 {
-    let image_sum = sum_image_pixels(
-        image_squared_integral,
-        region.left() as u32,
-        region.top() as u32,
-        region.right() as u32,
-        region.bottom() as u32
-    ) as f32;
-    (image_sum * template_squared_sum).sqrt()
+/// The location and score of a match between a template and an image region.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TemplateMatch {
+    /// Top-left corner of the matching region of the image.
+    pub location: (u32, u32),
+    /// Score of the match, as computed by [`match_template`](fn.match_template.html).
+    pub score: f32,
+}
+
+/// Returns the location of the best score in the output of [`match_template`](fn.match_template.html)
+/// for the given `method`. If there are multiple such
+/// locations then the lexicographically smallest is returned.
+///
+/// Returns `ImageProcError::EmptyImage` if `scores` has zero width or height.
+pub fn best_match(scores: &Image<Luma<f32>>, method: MatchTemplateMethod) -> Result<TemplateMatch> {
+    let extremes = find_extremes(scores)?;
+    let (location, score) = if method.lower_is_better() {
+        (extremes.min_value_location, extremes.min_value)
+    } else {
+        (extremes.max_value_location, extremes.max_value)
+    };
+    Ok(TemplateMatch { location, score })
+}
+
+/// Returns all matches in the output of [`match_template`](fn.match_template.html) whose
+/// scores are at least as good as `threshold` for the given `method`, i.e. less than or
+/// equal to `threshold` for methods where lower scores are better and greater than or
+/// equal to it otherwise.
+///
+/// Matches are returned in order from best to worst, with ties ordered by location.
+///
+/// Neighbouring locations often both score well against the same image feature. Use
+/// [`local_maxima`](../suppress/fn.local_maxima.html) or
+/// [`suppress_non_maximum`](../suppress/fn.suppress_non_maximum.html) to keep only
+/// the best match in each neighbourhood.
+pub fn find_matches(scores: &Image<Luma<f32>>, method: MatchTemplateMethod, threshold: f32) -> Vec<TemplateMatch> {
+    let lower_is_better = method.lower_is_better();
+    let mut matches: Vec<TemplateMatch> = scores
+        .enumerate_pixels()
+        .filter(|&(_, _, p)| if lower_is_better { p[0] <= threshold } else { p[0] >= threshold })
+        .map(|(x, y, p)| TemplateMatch { location: (x, y), score: p[0] })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        let by_score = if lower_is_better {
+            a.score.partial_cmp(&b.score)
+        } else {
+            b.score.partial_cmp(&a.score)
+        };
+        by_score
+            .unwrap_or(Ordering::Equal)
+            .then((a.location.1, a.location.0).cmp(&(b.location.1, b.location.0)))
+    });
+    matches
 }
 
 /// The largest and smallest values in an image,
@@ -101,11 +265,14 @@ pub struct Extremes<T> {
 
 /// Finds the largest and smallest values in an image and their locations.
 /// If there are multiple such values then the lexicographically smallest is returned.
-pub fn find_extremes<T>(image: &Image<Luma<T>>) -> Extremes<T>
+///
+/// Returns `ImageProcError::EmptyImage` if `image` has zero width or height.
+pub fn find_extremes<I, T>(image: &I) -> Result<Extremes<T>>
 where
+    I: GenericImageView<Pixel = Luma<T>>,
     T : Primitive + 'static
 {
-    assert!(image.width() > 0 && image.height() > 0, "image must be non-empty");
+    check_non_empty(image.dimensions())?;
 
     let mut min_value = image.get_pixel(0, 0)[0];
     let mut max_value = image.get_pixel(0, 0)[0];
@@ -113,7 +280,7 @@ where
     let mut min_value_location = (0, 0);
     let mut max_value_location = (0, 0);
 
-    for (x, y, p) in image.enumerate_pixels() {
+    for (x, y, p) in enumerate_pixels(image) {
         if p[0] < min_value {
             min_value = p[0];
             min_value_location = (x, y);
@@ -124,31 +291,32 @@ where
         }
     }
 
-    Extremes { max_value, min_value, max_value_location, min_value_location }
+    Ok(Extremes { max_value, min_value, max_value_location, min_value_location })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use utils::gray_bench_image;
-    use image::GrayImage;
+    use image::{GrayImage, Luma};
     use test::{Bencher, black_box};
 
     #[test]
-    #[should_panic]
-    fn match_template_panics_if_image_width_does_not_exceed_template_width() {
-        let _ = match_template(&GrayImage::new(5, 5), &GrayImage::new(5, 4), MatchTemplateMethod::SumOfSquaredErrors);
+    fn match_template_rejects_template_as_wide_as_image() {
+        let result = match_template(&GrayImage::new(5, 5), &GrayImage::new(5, 4), MatchTemplateMethod::SumOfSquaredErrors);
+        assert_eq!(result.unwrap_err(), ImageProcError::KernelTooLarge { kernel: (5, 4), image: (5, 5) });
     }
 
     #[test]
-    #[should_panic]
-    fn match_template_panics_if_image_height_does_not_exceed_template_height() {
-        let _ = match_template(&GrayImage::new(5, 5), &GrayImage::new(4, 5), MatchTemplateMethod::SumOfSquaredErrors);
+    fn match_template_rejects_template_as_tall_as_image() {
+        let result = match_template(&GrayImage::new(5, 5), &GrayImage::new(4, 5), MatchTemplateMethod::SumOfSquaredErrors);
+        assert_eq!(result.unwrap_err(), ImageProcError::KernelTooLarge { kernel: (4, 5), image: (5, 5) });
     }
 
     #[test]
     fn match_template_accepts_valid_template_size() {
-        let _ = match_template(&GrayImage::new(5, 5), &GrayImage::new(4, 4), MatchTemplateMethod::SumOfSquaredErrors);
+        let result = match_template(&GrayImage::new(5, 5), &GrayImage::new(4, 4), MatchTemplateMethod::SumOfSquaredErrors);
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -163,7 +331,7 @@ mod tests {
             3, 4
         );
 
-        let actual = match_template(&image, &template, MatchTemplateMethod::SumOfSquaredErrors);
+        let actual = match_template(&image, &template, MatchTemplateMethod::SumOfSquaredErrors).unwrap();
         let expected = gray_image!(type: f32,
             14.0, 14.0;
             3.0, 1.0
@@ -184,7 +352,7 @@ mod tests {
             3, 4
         );
 
-        let actual = match_template(&image, &template, MatchTemplateMethod::SumOfSquaredErrorsNormalized);
+        let actual = match_template(&image, &template, MatchTemplateMethod::SumOfSquaredErrorsNormalized).unwrap();
         let tss = 30f32;
         let expected = gray_image!(type: f32,
             14.0 / (22.0 * tss).sqrt(), 14.0 / (30.0 * tss).sqrt();
@@ -201,7 +369,7 @@ mod tests {
                 let image = gray_bench_image($s, $s);
                 let template = gray_bench_image($t, $t);
                 b.iter(|| {
-                    let result = match_template(&image, &template, MatchTemplateMethod::SumOfSquaredErrors);
+                    let result = match_template(&image, &template, $m).unwrap();
                     black_box(result);
                 })
             }
@@ -226,7 +394,7 @@ mod tests {
         template_size: 16,
         method: MatchTemplateMethod::SumOfSquaredErrors);
 
-        bench_match_template!(
+    bench_match_template!(
         bench_match_template_s100_t1_sse_norm,
         image_size: 100,
         template_size: 1,
@@ -244,6 +412,130 @@ mod tests {
         template_size: 16,
         method: MatchTemplateMethod::SumOfSquaredErrorsNormalized);
 
+    bench_match_template!(
+        bench_match_template_s100_t16_ccoeff_norm,
+        image_size: 100,
+        template_size: 16,
+        method: MatchTemplateMethod::CorrelationCoefficientNormalized);
+
+    bench_match_template!(
+        bench_match_template_s400_t64_cross_correlation,
+        image_size: 400,
+        template_size: 64,
+        method: MatchTemplateMethod::CrossCorrelation);
+
+    #[test]
+    fn match_template_cross_correlation() {
+        let image = gray_image!(
+            1, 4, 2;
+            2, 1, 3;
+            3, 3, 4
+        );
+        let template = gray_image!(
+            1, 2;
+            3, 4
+        );
+
+        let actual = match_template(&image, &template, MatchTemplateMethod::CrossCorrelation).unwrap();
+        let expected = gray_image!(type: f32,
+            19.0, 23.0;
+            25.0, 32.0
+        );
+        assert_pixels_eq!(actual, expected);
+
+        let actual = match_template(&image, &template, MatchTemplateMethod::CrossCorrelationNormalized).unwrap();
+        let tss = 30f32;
+        let expected = gray_image!(type: f32,
+            19.0 / (22.0 * tss).sqrt(), 23.0 / (30.0 * tss).sqrt();
+            25.0 / (23.0 * tss).sqrt(), 32.0 / (35.0 * tss).sqrt()
+        );
+        assert_pixels_eq_within!(actual, expected, 1e-6);
+    }
+
+    #[test]
+    fn match_template_correlation_coefficient_normalized() {
+        let image = gray_image!(
+            1, 4, 2;
+            2, 1, 3;
+            3, 3, 4
+        );
+        let template = gray_image!(
+            1, 2;
+            3, 4
+        );
+
+        let actual = match_template(&image, &template, MatchTemplateMethod::CorrelationCoefficientNormalized).unwrap();
+
+        // Pearson correlation of each region with the template, whose deviations
+        // from its mean are (-1.5, -0.5, 0.5, 1.5).
+        let pearson = |region: [f32; 4]| {
+            let mean = region.iter().sum::<f32>() / 4.0;
+            let template = [-1.5, -0.5, 0.5, 1.5];
+            let covariance: f32 = region.iter().zip(template.iter()).map(|(r, t)| (r - mean) * t).sum();
+            let variance: f32 = region.iter().map(|r| (r - mean).powi(2)).sum();
+            covariance / (variance * 5.0).sqrt()
+        };
+        let expected = gray_image!(type: f32,
+            pearson([1.0, 4.0, 2.0, 1.0]), pearson([4.0, 2.0, 1.0, 3.0]);
+            pearson([2.0, 1.0, 3.0, 3.0]), pearson([1.0, 3.0, 3.0, 4.0])
+        );
+        assert_pixels_eq_within!(actual, expected, 1e-5);
+    }
+
+    #[test]
+    fn match_template_fft_matches_direct_computation() {
+        let image = gray_bench_image(40, 30);
+        let template = gray_bench_image(12, 9);
+        let methods = [
+            MatchTemplateMethod::SumOfSquaredErrors,
+            MatchTemplateMethod::SumOfSquaredErrorsNormalized,
+            MatchTemplateMethod::CrossCorrelation,
+            MatchTemplateMethod::CrossCorrelationNormalized,
+            MatchTemplateMethod::CorrelationCoefficientNormalized,
+        ];
+        for &method in &methods {
+            let direct = match_template_impl(&image, &template, method, false);
+            let fft = match_template_impl(&image, &template, method, true);
+            assert_pixels_eq!(fft, direct);
+        }
+    }
+
+    #[test]
+    fn match_template_correlation_coefficient_normalized_constant_region() {
+        let image = GrayImage::from_pixel(4, 4, Luma([7]));
+        let template = gray_image!(
+            1, 2;
+            3, 4
+        );
+        let actual = match_template(&image, &template, MatchTemplateMethod::CorrelationCoefficientNormalized).unwrap();
+        assert!(actual.pixels().all(|p| p[0] == 0.0));
+    }
+
+    #[test]
+    fn test_best_match_and_find_matches() {
+        let scores = gray_image!(type: f32,
+            0.5, 0.9, 0.2;
+            0.9, 0.1, 0.7
+        );
+
+        let method = MatchTemplateMethod::CorrelationCoefficientNormalized;
+        assert_eq!(best_match(&scores, method), Ok(TemplateMatch { location: (1, 0), score: 0.9 }));
+        assert_eq!(find_matches(&scores, method, 0.6), vec![
+            TemplateMatch { location: (1, 0), score: 0.9 },
+            TemplateMatch { location: (0, 1), score: 0.9 },
+            TemplateMatch { location: (2, 1), score: 0.7 },
+        ]);
+
+        let method = MatchTemplateMethod::SumOfSquaredErrors;
+        assert_eq!(best_match(&scores, method), Ok(TemplateMatch { location: (1, 1), score: 0.1 }));
+        assert_eq!(find_matches(&scores, method, 0.2), vec![
+            TemplateMatch { location: (1, 1), score: 0.1 },
+            TemplateMatch { location: (2, 0), score: 0.2 },
+        ]);
+
+        assert_eq!(best_match(&Image::new(0, 2), method), Err(ImageProcError::EmptyImage));
+    }
+
     #[test]
     fn test_find_extremes() {
         let image = gray_image!(
@@ -258,6 +550,12 @@ mod tests {
             min_value_location: (3, 0)
         };
 
-        assert_eq!(find_extremes(&image), expected);
+        assert_eq!(find_extremes(&image), Ok(expected));
+    }
+
+    #[test]
+    fn test_find_extremes_empty_image() {
+        let image = GrayImage::new(0, 3);
+        assert_eq!(find_extremes(&image), Err(ImageProcError::EmptyImage));
     }
 }
@@ -0,0 +1,28 @@
+//! Functions for thresholding grayscale images: global fixed thresholds, Otsu's
+//! automatic threshold selection and adaptive thresholds over a local window.
+//!
+//! These functions are also available from the [`contrast`](../contrast/index.html) module.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # #[macro_use]
+//! # extern crate imageproc;
+//! # fn main() {
+//! use imageproc::threshold::{otsu_threshold, threshold};
+//!
+//! let image = gray_image!(
+//!     10,  12, 200;
+//!     11, 201, 199);
+//!
+//! let (binary, level) = otsu_threshold(&image);
+//! assert_pixels_eq!(binary, threshold(&image, level));
+//! # }
+//! ```
+
+pub use contrast::{
+    adaptive_threshold, adaptive_threshold_mut, adaptive_threshold_with_method,
+    adaptive_threshold_with_method_mut, binarize, bradley_threshold, bradley_threshold_mut,
+    otsu_level, otsu_level_f32, otsu_level_u16, otsu_threshold, threshold, threshold_into,
+    threshold_mut, AdaptiveMethod,
+};
@@ -0,0 +1,544 @@
+//! Processing images in tiles, for images too large to hold in memory at once.
+//!
+//! A [`TiledOperation`](trait.TiledOperation.html) declares its halo: the distance
+//! beyond which input pixels cannot affect an output pixel. [`process_tiled`](fn.process_tiled.html)
+//! splits the output into tiles using a [`TileIterator`](struct.TileIterator.html), reads
+//! each tile together with its halo from a [`TileSource`](trait.TileSource.html), applies
+//! the operation and passes the central region of the result to a
+//! [`TileSink`](trait.TileSink.html). Only one tile and its halo is held in memory at a
+//! time, and as the halo of each tile consists of real image pixels the stitched result
+//! is identical to applying the operation to the whole image at once.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # extern crate imageproc;
+//! # fn main() {
+//! use image::{GrayImage, Luma};
+//! use imageproc::filter::gaussian_blur_f32;
+//! use imageproc::tiles::{map_tiled, GaussianBlur};
+//!
+//! let image = GrayImage::from_fn(50, 40, |x, y| Luma([(x * y) as u8]));
+//!
+//! let tiled = map_tiled(&image, &GaussianBlur { sigma: 2.0 }, 16, 16);
+//! let whole = gaussian_blur_f32(&image, 2.0);
+//!
+//! assert_eq!(tiled.into_raw(), whole.into_raw());
+//! # }
+//! ```
+
+use image::{GrayImage, Luma, Pixel};
+use conv::ValueInto;
+use definitions::{Clamp, Image, MaybeSend, MaybeSync};
+use distance_transform::Norm;
+use filter::{gaussian_blur_f32, median_filter};
+use morphology::{dilate, erode};
+use rect::Rect;
+use std::cmp::min;
+
+/// A provider of image regions, e.g. an image stored on disk.
+pub trait TileSource<P: Pixel> {
+    /// The width and height of the full image.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Returns the pixels of the full image within `region`.
+    ///
+    /// `region` always lies entirely within the full image.
+    fn read(&mut self, region: Rect) -> Image<P>;
+}
+
+impl<P: Pixel + 'static> TileSource<P> for Image<P> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    fn read(&mut self, region: Rect) -> Image<P> {
+        copy_region(self, region)
+    }
+}
+
+/// A consumer of processed tiles, e.g. an image stored on disk.
+pub trait TileSink<P: Pixel> {
+    /// Writes `tile` to the full output image with its top left corner at `(x, y)`.
+    fn write(&mut self, x: u32, y: u32, tile: &Image<P>);
+}
+
+impl<P: Pixel + 'static> TileSink<P> for Image<P> {
+    fn write(&mut self, x: u32, y: u32, tile: &Image<P>) {
+        let channels = P::channel_count() as usize;
+        let (out_width, tile_width) = (self.width() as usize, tile.width() as usize);
+        let row_len = tile_width * channels;
+        let out: &mut [P::Subpixel] = &mut *self;
+        for (ty, tile_row) in tile.chunks(row_len).enumerate() {
+            let start = ((y as usize + ty) * out_width + x as usize) * channels;
+            out[start..start + row_len].copy_from_slice(tile_row);
+        }
+    }
+}
+
+/// An operation whose output at each pixel depends only on the input pixels within
+/// a fixed distance of it, and which produces an output of the same size as its input.
+pub trait TiledOperation<P: Pixel, Q: Pixel> {
+    /// The maximum horizontal or vertical distance from an output pixel of an input
+    /// pixel which can affect its value.
+    fn halo(&self) -> u32;
+
+    /// Applies this operation to a whole image.
+    fn apply(&self, image: &Image<P>) -> Image<Q>;
+
+    /// Returns the operation which applies `self` and then `next`.
+    fn then<R, O>(self, next: O) -> Chain<Self, O, Q>
+    where
+        Self: Sized,
+        R: Pixel,
+        O: TiledOperation<Q, R>,
+    {
+        Chain { first: self, second: next, intermediate: Default::default() }
+    }
+}
+
+/// Two operations applied in sequence. See [`TiledOperation::then`](trait.TiledOperation.html#method.then).
+pub struct Chain<A, B, Q> {
+    first: A,
+    second: B,
+    intermediate: ::std::marker::PhantomData<Q>,
+}
+
+impl<P, Q, R, A, B> TiledOperation<P, R> for Chain<A, B, Q>
+where
+    P: Pixel,
+    Q: Pixel,
+    R: Pixel,
+    A: TiledOperation<P, Q>,
+    B: TiledOperation<Q, R>,
+{
+    fn halo(&self) -> u32 {
+        self.first.halo() + self.second.halo()
+    }
+
+    fn apply(&self, image: &Image<P>) -> Image<R> {
+        self.second.apply(&self.first.apply(image))
+    }
+}
+
+/// An arbitrary function, with a halo provided by the caller. See [`local`](fn.local.html).
+pub struct Local<F> {
+    halo: u32,
+    f: F,
+}
+
+/// Wraps a function as a `TiledOperation` with the given halo.
+///
+/// Stitched results are only guaranteed to match applying `f` to the whole image
+/// if `f` satisfies the requirements of [`TiledOperation`](trait.TiledOperation.html).
+pub fn local<F>(halo: u32, f: F) -> Local<F> {
+    Local { halo, f }
+}
+
+impl<P, Q, F> TiledOperation<P, Q> for Local<F>
+where
+    P: Pixel,
+    Q: Pixel,
+    F: Fn(&Image<P>) -> Image<Q>,
+{
+    fn halo(&self) -> u32 {
+        self.halo
+    }
+
+    fn apply(&self, image: &Image<P>) -> Image<Q> {
+        (self.f)(image)
+    }
+}
+
+/// See [`gaussian_blur_f32`](../filter/fn.gaussian_blur_f32.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GaussianBlur {
+    /// Standard deviation of the Gaussian.
+    pub sigma: f32,
+}
+
+impl<P> TiledOperation<P, P> for GaussianBlur
+where
+    P: Pixel + 'static,
+    P::Subpixel: ValueInto<f32> + Clamp<f32> + MaybeSend + MaybeSync,
+{
+    fn halo(&self) -> u32 {
+        (2.0 * self.sigma).ceil() as u32
+    }
+
+    fn apply(&self, image: &Image<P>) -> Image<P> {
+        gaussian_blur_f32(image, self.sigma)
+    }
+}
+
+/// See [`median_filter`](../filter/fn.median_filter.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Median {
+    /// Radius of the square filter window.
+    pub radius: u32,
+}
+
+impl<P> TiledOperation<P, P> for Median
+where
+    P: Pixel<Subpixel = u8> + 'static,
+{
+    fn halo(&self) -> u32 {
+        self.radius
+    }
+
+    fn apply(&self, image: &Image<P>) -> Image<P> {
+        median_filter(image, self.radius, self.radius)
+    }
+}
+
+/// See [`dilate`](../morphology/fn.dilate.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dilate {
+    /// Norm used to measure distances.
+    pub norm: Norm,
+    /// Radius of the dilation.
+    pub k: u8,
+}
+
+impl TiledOperation<Luma<u8>, Luma<u8>> for Dilate {
+    fn halo(&self) -> u32 {
+        self.k as u32
+    }
+
+    fn apply(&self, image: &GrayImage) -> GrayImage {
+        dilate(image, self.norm, self.k)
+    }
+}
+
+/// See [`erode`](../morphology/fn.erode.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Erode {
+    /// Norm used to measure distances.
+    pub norm: Norm,
+    /// Radius of the erosion.
+    pub k: u8,
+}
+
+impl TiledOperation<Luma<u8>, Luma<u8>> for Erode {
+    fn halo(&self) -> u32 {
+        self.k as u32
+    }
+
+    fn apply(&self, image: &GrayImage) -> GrayImage {
+        erode(image, self.norm, self.k)
+    }
+}
+
+/// A tile yielded by a [`TileIterator`](struct.TileIterator.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    /// The pixels of the full image belonging to this tile. The cores of
+    /// the tiles yielded by a `TileIterator` partition the image.
+    pub core: Rect,
+    /// `core` extended by the overlap on each side, clipped to the full image.
+    pub region: Rect,
+}
+
+impl Tile {
+    /// The position of `core` relative to the top left of `region`.
+    pub fn core_in_region(&self) -> Rect {
+        Rect::at(self.core.left() - self.region.left(), self.core.top() - self.region.top())
+            .of_size(self.core.width(), self.core.height())
+    }
+}
+
+/// Iterates over the tiles of an image in row-major order.
+///
+/// The cores of the tiles have size `tile_width` by `tile_height`, except in the final
+/// row and column of tiles, which may be smaller. The region of each tile extends its core
+/// by `overlap` pixels on each side, so that a filter of radius at most `overlap` applied
+/// to the region gives the same results within the core as when applied to the whole image.
+///
+/// # Examples
+/// ```
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::rect::Rect;
+/// use imageproc::tiles::TileIterator;
+///
+/// let tiles: Vec<_> = TileIterator::new(10, 4, 6, 4, 1).collect();
+///
+/// assert_eq!(tiles.len(), 2);
+/// assert_eq!(tiles[1].core, Rect::at(6, 0).of_size(4, 4));
+/// assert_eq!(tiles[1].region, Rect::at(5, 0).of_size(5, 4));
+/// assert_eq!(tiles[1].core_in_region(), Rect::at(1, 0).of_size(4, 4));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TileIterator {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    overlap: u32,
+    x: u32,
+    y: u32,
+}
+
+impl TileIterator {
+    /// Iterates over the tiles of an image of size `width` by `height`.
+    ///
+    /// # Panics
+    /// If `tile_width` or `tile_height` is zero.
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32, overlap: u32) -> TileIterator {
+        assert!(tile_width > 0 && tile_height > 0, "tile dimensions must be non-zero");
+        TileIterator {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            overlap,
+            x: 0,
+            // An empty image has no tiles.
+            y: if width == 0 { height } else { 0 },
+        }
+    }
+}
+
+impl Iterator for TileIterator {
+    type Item = Tile;
+
+    fn next(&mut self) -> Option<Tile> {
+        if self.y >= self.height {
+            return None;
+        }
+        let (x, y) = (self.x, self.y);
+        let core = Rect::at(x as i32, y as i32)
+            .of_size(min(self.tile_width, self.width - x), min(self.tile_height, self.height - y));
+
+        let left = x.saturating_sub(self.overlap);
+        let top = y.saturating_sub(self.overlap);
+        let right = min(self.width, x + core.width() + self.overlap);
+        let bottom = min(self.height, y + core.height() + self.overlap);
+        let region = Rect::at(left as i32, top as i32).of_size(right - left, bottom - top);
+
+        self.x += core.width();
+        if self.x >= self.width {
+            self.x = 0;
+            self.y += core.height();
+        }
+        Some(Tile { core, region })
+    }
+}
+
+/// Applies `op` to the image provided by `source` one tile at a time, writing
+/// the results to `sink`.
+///
+/// Tiles have size `tile_width` by `tile_height`, except in the final row and column
+/// of tiles, which may be smaller. Tiles are processed in row-major order, and each
+/// is read with an overlap of `op.halo()` pixels, as for [`TileIterator`](struct.TileIterator.html).
+///
+/// # Panics
+/// If `tile_width` or `tile_height` is zero.
+pub fn process_tiled<P, Q, S, O, K>(
+    source: &mut S,
+    op: &O,
+    tile_width: u32,
+    tile_height: u32,
+    sink: &mut K,
+) where
+    P: Pixel + 'static,
+    Q: Pixel + 'static,
+    S: TileSource<P>,
+    O: TiledOperation<P, Q>,
+    K: TileSink<Q>,
+{
+    let (width, height) = source.dimensions();
+
+    for tile in TileIterator::new(width, height, tile_width, tile_height, op.halo()) {
+        let input = source.read(tile.region);
+        let output = op.apply(&input);
+        assert_eq!(
+            output.dimensions(),
+            input.dimensions(),
+            "tiled operations must not change image dimensions"
+        );
+
+        let (x, y) = (tile.core.left() as u32, tile.core.top() as u32);
+        sink.write(x, y, &copy_region(&output, tile.core_in_region()));
+    }
+}
+/// Applies `op` to an image one tile at a time. The result is identical to
+/// `op.apply(image)`, but less memory is used for intermediate results.
+///
+/// See [`process_tiled`](fn.process_tiled.html).
+pub fn map_tiled<P, Q, O>(image: &Image<P>, op: &O, tile_width: u32, tile_height: u32) -> Image<Q>
+where
+    P: Pixel + 'static,
+    Q: Pixel + 'static,
+    O: TiledOperation<P, Q>,
+{
+    let mut source = ImageSource(image);
+    let mut out = Image::new(image.width(), image.height());
+    process_tiled(&mut source, op, tile_width, tile_height, &mut out);
+    out
+}
+
+/// Reads regions from a borrowed image.
+struct ImageSource<'a, P: Pixel + 'a>(&'a Image<P>);
+
+impl<'a, P: Pixel + 'static> TileSource<P> for ImageSource<'a, P> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    fn read(&mut self, region: Rect) -> Image<P> {
+        copy_region(self.0, region)
+    }
+}
+
+fn copy_region<P: Pixel + 'static>(image: &Image<P>, region: Rect) -> Image<P> {
+    let channels = P::channel_count() as usize;
+    let width = image.width() as usize;
+    let row_len = region.width() as usize * channels;
+    let raw: &[P::Subpixel] = &**image;
+
+    let mut data = Vec::with_capacity(row_len * region.height() as usize);
+    for y in region.top()..region.bottom() + 1 {
+        let start = (y as usize * width + region.left() as usize) * channels;
+        data.extend_from_slice(&raw[start..start + row_len]);
+    }
+    Image::from_raw(region.width(), region.height(), data).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use std::cmp::max;
+    use property_testing::GrayTestImage;
+    use quickcheck::{quickcheck, TestResult};
+    use utils::{gray_bench_image, pixel_diff_summary};
+    use test::{Bencher, black_box};
+
+    fn check_tiled<O>(image: &GrayImage, op: &O, tile_width: u32, tile_height: u32) -> TestResult
+    where
+        O: TiledOperation<Luma<u8>, Luma<u8>>,
+    {
+        let expected = op.apply(image);
+        let actual = map_tiled(image, op, tile_width, tile_height);
+        match pixel_diff_summary(&actual, &expected) {
+            Some(err) => TestResult::error(err),
+            None => TestResult::passed(),
+        }
+    }
+
+    #[test]
+    fn test_tiled_gaussian_blur_matches_whole_image() {
+        fn prop(image: GrayTestImage, tile_width: u8, tile_height: u8) -> TestResult {
+            let op = GaussianBlur { sigma: 1.7 };
+            check_tiled(&image.0, &op, tile_width as u32 % 7 + 1, tile_height as u32 % 7 + 1)
+        }
+        quickcheck(prop as fn(GrayTestImage, u8, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_tiled_chain_matches_whole_image() {
+        fn prop(image: GrayTestImage, tile_width: u8, tile_height: u8) -> TestResult {
+            let op = Median { radius: 1 }
+                .then(Erode { norm: Norm::L1, k: 2 })
+                .then(Dilate { norm: Norm::LInf, k: 1 });
+            check_tiled(&image.0, &op, tile_width as u32 % 7 + 1, tile_height as u32 % 7 + 1)
+        }
+        quickcheck(prop as fn(GrayTestImage, u8, u8) -> TestResult);
+    }
+
+    #[test]
+    fn test_tiled_local_operation_changing_pixel_type() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        let op = local(0, |i: &GrayImage| {
+            RgbImage::from_fn(i.width(), i.height(), |x, y| {
+                let p = i.get_pixel(x, y)[0];
+                Rgb([p, 2 * p, 3 * p])
+            })
+        });
+        let tiled: RgbImage = map_tiled(&image, &op, 2, 1);
+        assert_eq!(tiled.into_raw(), op.apply(&image).into_raw());
+    }
+
+    /// Records every region read.
+    struct LoggingSource {
+        image: GrayImage,
+        reads: Vec<Rect>,
+    }
+
+    impl TileSource<Luma<u8>> for LoggingSource {
+        fn dimensions(&self) -> (u32, u32) {
+            self.image.dimensions()
+        }
+
+        fn read(&mut self, region: Rect) -> GrayImage {
+            self.reads.push(region);
+            self.image.read(region)
+        }
+    }
+
+    #[test]
+    fn test_process_tiled_reads_tiles_with_halo() {
+        let mut source = LoggingSource { image: GrayImage::new(10, 7), reads: vec![] };
+        let mut out = GrayImage::new(10, 7);
+        process_tiled(&mut source, &Median { radius: 2 }, 4, 4, &mut out);
+
+        let expected = vec![
+            Rect::at(0, 0).of_size(6, 6),
+            Rect::at(2, 0).of_size(8, 6),
+            Rect::at(6, 0).of_size(4, 6),
+            Rect::at(0, 2).of_size(6, 5),
+            Rect::at(2, 2).of_size(8, 5),
+            Rect::at(6, 2).of_size(4, 5),
+        ];
+        assert_eq!(source.reads, expected);
+    }
+
+    #[test]
+    fn test_tile_iterator_cores_partition_image() {
+        let tiles: Vec<_> = TileIterator::new(11, 7, 4, 3, 2).collect();
+        assert_eq!(tiles.len(), 9);
+
+        let mut counts = GrayImage::new(11, 7);
+        for tile in &tiles {
+            let core = tile.core;
+            for y in core.top()..core.bottom() + 1 {
+                for x in core.left()..core.right() + 1 {
+                    counts.get_pixel_mut(x as u32, y as u32)[0] += 1;
+                }
+            }
+            assert_eq!(tile.region.left(), max(0, core.left() - 2));
+            assert_eq!(tile.region.top(), max(0, core.top() - 2));
+            assert_eq!(tile.region.right(), min(10, core.right() + 2));
+            assert_eq!(tile.region.bottom(), min(6, core.bottom() + 2));
+        }
+        assert!(counts.iter().all(|c| *c == 1));
+        assert_eq!(tiles[8].core, Rect::at(8, 6).of_size(3, 1));
+    }
+
+    #[test]
+    fn test_tile_iterator_empty_image() {
+        assert_eq!(TileIterator::new(0, 5, 2, 2, 1).count(), 0);
+        assert_eq!(TileIterator::new(5, 0, 2, 2, 1).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_process_tiled_rejects_empty_tiles() {
+        let image = GrayImage::new(5, 5);
+        map_tiled(&image, &Median { radius: 1 }, 0, 3);
+    }
+
+    #[bench]
+    fn bench_map_tiled_gaussian_blur(b: &mut Bencher) {
+        let image = gray_bench_image(500, 500);
+        let op = GaussianBlur { sigma: 3.0 };
+        b.iter(|| {
+            let blurred = map_tiled(&image, &op, 128, 128);
+            black_box(blurred);
+        });
+    }
+}
@@ -0,0 +1,313 @@
+//! Constant-velocity Kalman filters for smoothing and predicting positions across frames.
+
+use rect::Rect;
+
+/// Noise parameters for [`PointKalmanFilter`](struct.PointKalmanFilter.html)
+/// and [`BoxKalmanFilter`](struct.BoxKalmanFilter.html).
+///
+/// All values are variances, in units of pixels squared (per frame squared
+/// for velocities and accelerations). Each coordinate is filtered independently.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KalmanOptions {
+    /// Variance of the random acceleration applied to the tracked object each frame.
+    /// Larger values make the filter respond more quickly to changes in velocity,
+    /// at the expense of smoothing less.
+    pub process_noise: f32,
+    /// Variance of the error in each measurement.
+    pub measurement_noise: f32,
+    /// Variance of the initial velocity estimate, which is zero.
+    pub initial_velocity_variance: f32,
+}
+
+impl Default for KalmanOptions {
+    fn default() -> Self {
+        KalmanOptions {
+            process_noise: 1.0,
+            measurement_noise: 10.0,
+            initial_velocity_variance: 100.0,
+        }
+    }
+}
+
+/// Tracks a 2D point moving at roughly constant velocity from noisy measurements
+/// of its position, one per frame.
+///
+/// Call [`predict`](#method.predict) once per frame, and then [`update`](#method.update)
+/// if a measurement is available for that frame.
+///
+/// # Examples
+/// ```
+/// use imageproc::tracking::{KalmanOptions, PointKalmanFilter};
+///
+/// let mut filter = PointKalmanFilter::new((0.0, 0.0), KalmanOptions::default());
+/// for t in 1..30 {
+///     filter.predict();
+///     filter.update((2.0 * t as f32, 10.0));
+/// }
+///
+/// // With no further measurements the filter extrapolates the motion so far.
+/// let (x, y) = filter.predict();
+/// assert!((x - 60.0).abs() < 0.5 && (y - 10.0).abs() < 0.5);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointKalmanFilter {
+    x: Axis,
+    y: Axis,
+}
+
+impl PointKalmanFilter {
+    /// Creates a filter for a point initially observed at `position`, with zero initial velocity.
+    pub fn new(position: (f32, f32), options: KalmanOptions) -> PointKalmanFilter {
+        PointKalmanFilter {
+            x: Axis::new(position.0, options),
+            y: Axis::new(position.1, options),
+        }
+    }
+
+    /// Advances the filter by one frame and returns the predicted position.
+    pub fn predict(&mut self) -> (f32, f32) {
+        self.x.predict();
+        self.y.predict();
+        self.position()
+    }
+
+    /// Incorporates a measured position for the current frame and returns the corrected position.
+    pub fn update(&mut self, measurement: (f32, f32)) -> (f32, f32) {
+        self.x.update(measurement.0);
+        self.y.update(measurement.1);
+        self.position()
+    }
+
+    /// The current position estimate.
+    pub fn position(&self) -> (f32, f32) {
+        (self.x.position, self.y.position)
+    }
+
+    /// The current velocity estimate, in pixels per frame.
+    pub fn velocity(&self) -> (f32, f32) {
+        (self.x.velocity, self.y.velocity)
+    }
+
+    /// The variances of the current position estimate in x and y.
+    pub fn position_variance(&self) -> (f32, f32) {
+        (self.x.covariance[0], self.y.covariance[0])
+    }
+}
+
+/// Tracks a bounding box whose centre and size each change at roughly constant
+/// velocity, from noisy per-frame detections.
+///
+/// The centre, width and height are filtered independently. Call
+/// [`predict`](#method.predict) once per frame, and then [`update`](#method.update)
+/// if a detection is available for that frame.
+///
+/// # Examples
+/// ```
+/// use imageproc::rect::Rect;
+/// use imageproc::tracking::{BoxKalmanFilter, KalmanOptions};
+///
+/// let mut filter = BoxKalmanFilter::new(Rect::at(10, 10).of_size(20, 20), KalmanOptions::default());
+///
+/// // Detections jitter by a pixel either side of a stationary box.
+/// for t in 0..40 {
+///     let jitter = if t % 2 == 0 { 1 } else { -1 };
+///     filter.predict();
+///     filter.update(Rect::at(10 + jitter, 10 - jitter).of_size(20, 20));
+/// }
+///
+/// assert_eq!(filter.rect(), Rect::at(10, 10).of_size(20, 20));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoxKalmanFilter {
+    center_x: Axis,
+    center_y: Axis,
+    width: Axis,
+    height: Axis,
+}
+
+impl BoxKalmanFilter {
+    /// Creates a filter for a box initially observed at `rect`, with zero initial velocity.
+    pub fn new(rect: Rect, options: KalmanOptions) -> BoxKalmanFilter {
+        let (cx, cy, w, h) = box_state(rect);
+        BoxKalmanFilter {
+            center_x: Axis::new(cx, options),
+            center_y: Axis::new(cy, options),
+            width: Axis::new(w, options),
+            height: Axis::new(h, options),
+        }
+    }
+
+    /// Advances the filter by one frame and returns the predicted box.
+    pub fn predict(&mut self) -> Rect {
+        self.center_x.predict();
+        self.center_y.predict();
+        self.width.predict();
+        self.height.predict();
+        self.rect()
+    }
+
+    /// Incorporates a detection for the current frame and returns the corrected box.
+    pub fn update(&mut self, detection: Rect) -> Rect {
+        let (cx, cy, w, h) = box_state(detection);
+        self.center_x.update(cx);
+        self.center_y.update(cy);
+        self.width.update(w);
+        self.height.update(h);
+        self.rect()
+    }
+
+    /// The current estimate of the box, rounded to integer coordinates.
+    /// The width and height are always at least 1.
+    pub fn rect(&self) -> Rect {
+        let (cx, cy) = self.center();
+        let (w, h) = self.size();
+        let w = w.round().max(1.0);
+        let h = h.round().max(1.0);
+        let left = (cx - (w - 1.0) / 2.0).round() as i32;
+        let top = (cy - (h - 1.0) / 2.0).round() as i32;
+        Rect::at(left, top).of_size(w as u32, h as u32)
+    }
+
+    /// The current estimate of the centre of the box.
+    pub fn center(&self) -> (f32, f32) {
+        (self.center_x.position, self.center_y.position)
+    }
+
+    /// The current estimate of the width and height of the box.
+    pub fn size(&self) -> (f32, f32) {
+        (self.width.position, self.height.position)
+    }
+
+    /// The current estimate of the velocity of the centre of the box, in pixels per frame.
+    pub fn velocity(&self) -> (f32, f32) {
+        (self.center_x.velocity, self.center_y.velocity)
+    }
+}
+
+/// Returns the centre, width and height of a rect.
+fn box_state(rect: Rect) -> (f32, f32, f32, f32) {
+    let w = rect.width() as f32;
+    let h = rect.height() as f32;
+    (rect.left() as f32 + (w - 1.0) / 2.0, rect.top() as f32 + (h - 1.0) / 2.0, w, h)
+}
+
+/// A Kalman filter for a single coordinate with state (position, velocity),
+/// a time step of one frame, and white noise acceleration.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Axis {
+    position: f32,
+    velocity: f32,
+    /// The state covariance matrix [p00, p01, p11], which is symmetric.
+    covariance: [f32; 3],
+    process_noise: f32,
+    measurement_noise: f32,
+}
+
+impl Axis {
+    fn new(position: f32, options: KalmanOptions) -> Axis {
+        Axis {
+            position,
+            velocity: 0.0,
+            covariance: [options.measurement_noise, 0.0, options.initial_velocity_variance],
+            process_noise: options.process_noise,
+            measurement_noise: options.measurement_noise,
+        }
+    }
+
+    fn predict(&mut self) {
+        let [p00, p01, p11] = self.covariance;
+        let q = self.process_noise;
+        self.position += self.velocity;
+        // P = F P F^T + Q, with F = [1 1; 0 1] and Q = q [1/4 1/2; 1/2 1].
+        self.covariance = [
+            p00 + 2.0 * p01 + p11 + q / 4.0,
+            p01 + p11 + q / 2.0,
+            p11 + q,
+        ];
+    }
+
+    fn update(&mut self, measurement: f32) {
+        let [p00, p01, p11] = self.covariance;
+        let innovation = measurement - self.position;
+        let innovation_variance = p00 + self.measurement_noise;
+        let k0 = p00 / innovation_variance;
+        let k1 = p01 / innovation_variance;
+
+        self.position += k0 * innovation;
+        self.velocity += k1 * innovation;
+        self.covariance = [
+            (1.0 - k0) * p00,
+            (1.0 - k0) * p01,
+            p11 - k1 * p01,
+        ];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use rand::distributions::{IndependentSample, Normal};
+
+    #[test]
+    fn test_point_filter_reduces_measurement_noise() {
+        let mut rng: StdRng = SeedableRng::from_seed(&[1usize][..]);
+        let noise = Normal::new(0.0, 3.0);
+
+        let options = KalmanOptions { process_noise: 0.01, measurement_noise: 9.0, initial_velocity_variance: 10.0 };
+        let mut filter = PointKalmanFilter::new((0.0, 0.0), options);
+
+        let (mut raw_error, mut filtered_error) = (0.0, 0.0);
+        for t in 1..200 {
+            let truth = (t as f32 * 0.5, 100.0 - t as f32 * 0.25);
+            let measured = (
+                truth.0 + noise.ind_sample(&mut rng) as f32,
+                truth.1 + noise.ind_sample(&mut rng) as f32,
+            );
+            filter.predict();
+            let estimate = filter.update(measured);
+            if t > 20 {
+                raw_error += (measured.0 - truth.0).powi(2) + (measured.1 - truth.1).powi(2);
+                filtered_error += (estimate.0 - truth.0).powi(2) + (estimate.1 - truth.1).powi(2);
+            }
+        }
+
+        assert!(filtered_error < raw_error / 4.0, "raw {}, filtered {}", raw_error, filtered_error);
+    }
+
+    #[test]
+    fn test_predict_increases_uncertainty() {
+        let mut filter = PointKalmanFilter::new((5.0, 5.0), KalmanOptions::default());
+        let initial = filter.position_variance().0;
+        filter.predict();
+        let predicted = filter.position_variance().0;
+        filter.update((5.0, 5.0));
+        let updated = filter.position_variance().0;
+        assert!(predicted > initial);
+        assert!(updated < predicted);
+    }
+
+    #[test]
+    fn test_box_filter_tracks_growing_box() {
+        let mut filter = BoxKalmanFilter::new(Rect::at(0, 0).of_size(10, 10), KalmanOptions::default());
+        for t in 1..40 {
+            filter.predict();
+            filter.update(Rect::at(3 * t, t).of_size(10 + 2 * t as u32, 10));
+        }
+        assert_eq!(filter.predict(), Rect::at(120, 40).of_size(90, 10));
+    }
+
+    #[test]
+    fn test_box_filter_rect_is_never_empty() {
+        let mut filter = BoxKalmanFilter::new(Rect::at(0, 0).of_size(2, 2), KalmanOptions::default());
+        for _ in 0..10 {
+            filter.predict();
+            filter.update(Rect::at(0, 0).of_size(1, 1));
+        }
+        // Width is shrinking, so extrapolating will eventually give negative sizes.
+        for _ in 0..50 {
+            let rect = filter.predict();
+            assert!(rect.width() >= 1 && rect.height() >= 1);
+        }
+    }
+}
@@ -0,0 +1,660 @@
+//! Mean-shift and CAMShift tracking of regions via histogram back-projection.
+
+use image::{GrayImage, Luma, Pixel, Rgb, RgbImage};
+use color::rgb_to_hsv;
+use definitions::Image;
+use rect::Rect;
+use std::f32;
+
+/// A histogram of the colours in an image region, with each channel
+/// quantized into `bins_per_channel` equally sized bins.
+///
+/// Used to compute the [back-projection] of an image, which is the input
+/// to [`mean_shift`](fn.mean_shift.html) and [`cam_shift`](fn.cam_shift.html).
+///
+/// [back-projection]: https://docs.opencv.org/3.4/da/d7f/tutorial_back_projection.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorHistogram {
+    bins_per_channel: u32,
+    channels: u32,
+    counts: Vec<f32>,
+}
+
+impl ColorHistogram {
+    /// Computes the histogram of the pixels of `image` inside `region`.
+    ///
+    /// # Panics
+    /// If `bins_per_channel` is not between 1 and 256, or if `region` does not intersect `image`.
+    pub fn from_region<P>(image: &Image<P>, region: Rect, bins_per_channel: u32) -> ColorHistogram
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        assert!(bins_per_channel > 0 && bins_per_channel <= 256, "bins_per_channel must be between 1 and 256");
+        let region = region.clamp_to_image(image.width(), image.height())
+            .expect("region must intersect image");
+
+        let channels = P::channel_count() as u32;
+        let mut histogram = ColorHistogram {
+            bins_per_channel,
+            channels,
+            counts: vec![0.0; bins_per_channel.pow(channels) as usize],
+        };
+
+        for y in region.top()..region.bottom() + 1 {
+            for x in region.left()..region.right() + 1 {
+                let bin = histogram.bin(image.get_pixel(x as u32, y as u32));
+                histogram.counts[bin] += 1.0;
+            }
+        }
+
+        histogram
+    }
+
+    /// Returns an image whose intensity at each pixel is proportional to the
+    /// frequency of that pixel's colour in this histogram. The most frequent colour
+    /// is mapped to 255.
+    ///
+    /// # Panics
+    /// If `P` does not have the same number of channels as the image this histogram was built from.
+    pub fn back_project<P>(&self, image: &Image<P>) -> GrayImage
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        assert_eq!(P::channel_count() as u32, self.channels, "pixel type must match histogram");
+        let max = self.counts.iter().cloned().fold(0.0, f32::max);
+        let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+        GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let count = self.counts[self.bin(image.get_pixel(x, y))];
+            Luma([(count * scale).round() as u8])
+        })
+    }
+
+    fn bin<P: Pixel<Subpixel = u8>>(&self, p: &P) -> usize {
+        p.channels().iter().fold(0, |acc, c| {
+            acc * self.bins_per_channel as usize + (*c as usize * self.bins_per_channel as usize) / 256
+        })
+    }
+}
+
+/// Options for a [`HueHistogram`](struct.HueHistogram.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HueHistogramOptions {
+    /// Number of equally sized bins that the range of hues [0, 360) is divided into.
+    pub bins: u32,
+    /// Pixels with [HSV](../color/fn.rgb_to_hsv.html) saturation less than this are ignored,
+    /// as the hues of nearly gray pixels are dominated by noise.
+    pub min_saturation: f32,
+    /// Pixels with HSV value less than this are ignored, as the hues of
+    /// nearly black pixels are dominated by noise.
+    pub min_value: f32,
+}
+
+impl Default for HueHistogramOptions {
+    fn default() -> Self {
+        HueHistogramOptions {
+            bins: 32,
+            min_saturation: 0.2,
+            min_value: 0.1,
+        }
+    }
+}
+
+/// A histogram of the [HSV](../color/fn.rgb_to_hsv.html) hues of the pixels in an
+/// image region.
+///
+/// Hue is largely unaffected by changes in lighting, so the back-projection of a hue
+/// histogram is more robust than that of a [`ColorHistogram`](struct.ColorHistogram.html)
+/// when tracking objects through video, as in the original CAMShift algorithm.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{Rgb, RgbImage};
+/// use imageproc::drawing::draw_filled_rect_mut;
+/// use imageproc::rect::Rect;
+/// use imageproc::tracking::{cam_shift, HueHistogram, HueHistogramOptions, MeanShiftOptions};
+///
+/// // An orange square on a gray background, which becomes darker as it moves.
+/// let frame = |x, brightness| {
+///     let mut image = RgbImage::from_pixel(80, 40, Rgb([90, 90, 90]));
+///     let orange = Rgb([brightness, brightness / 2, 0]);
+///     draw_filled_rect_mut(&mut image, Rect::at(x, 10).of_size(12, 12), orange);
+///     image
+/// };
+///
+/// let mut window = Rect::at(10, 10).of_size(12, 12);
+/// let histogram = HueHistogram::from_region(&frame(10, 250), window, HueHistogramOptions::default());
+///
+/// for i in 1..10 {
+///     let probability = histogram.back_project(&frame(10 + 4 * i, 250 - 15 * i as u8));
+///     window = cam_shift(&probability, window, MeanShiftOptions::default()).window;
+/// }
+///
+/// assert!((window.left() - 46).abs() <= 1 && (window.top() - 10).abs() <= 1);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HueHistogram {
+    options: HueHistogramOptions,
+    counts: Vec<f32>,
+}
+
+impl HueHistogram {
+    /// Computes the histogram of the hues of the pixels of `image` inside `region`,
+    /// ignoring pixels whose saturation or value is too low.
+    ///
+    /// # Panics
+    /// If `options.bins` is not between 1 and 360, or if `region` does not intersect `image`.
+    pub fn from_region(image: &RgbImage, region: Rect, options: HueHistogramOptions) -> HueHistogram {
+        assert!(options.bins > 0 && options.bins <= 360, "bins must be between 1 and 360");
+        let region = region.clamp_to_image(image.width(), image.height())
+            .expect("region must intersect image");
+
+        let mut histogram = HueHistogram {
+            options,
+            counts: vec![0.0; options.bins as usize],
+        };
+
+        for y in region.top()..region.bottom() + 1 {
+            for x in region.left()..region.right() + 1 {
+                if let Some(bin) = histogram.bin(*image.get_pixel(x as u32, y as u32)) {
+                    histogram.counts[bin] += 1.0;
+                }
+            }
+        }
+
+        histogram
+    }
+
+    /// Returns an image whose intensity at each pixel is proportional to the frequency
+    /// of that pixel's hue in this histogram. The most frequent hue is mapped to 255, and
+    /// pixels whose saturation or value is too low are mapped to 0.
+    pub fn back_project(&self, image: &RgbImage) -> GrayImage {
+        let max = self.counts.iter().cloned().fold(0.0, f32::max);
+        let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+        GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let count = match self.bin(*image.get_pixel(x, y)) {
+                Some(bin) => self.counts[bin],
+                None => 0.0,
+            };
+            Luma([(count * scale).round() as u8])
+        })
+    }
+
+    fn bin(&self, p: Rgb<u8>) -> Option<usize> {
+        let hsv = rgb_to_hsv(p);
+        if hsv[1] < self.options.min_saturation || hsv[2] < self.options.min_value {
+            return None;
+        }
+        let bin = (hsv[0] * self.options.bins as f32 / 360.0) as usize;
+        Some(bin.min(self.options.bins as usize - 1))
+    }
+}
+
+/// Stopping conditions for [`mean_shift`](fn.mean_shift.html) and [`cam_shift`](fn.cam_shift.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeanShiftOptions {
+    /// Maximum number of times the window is moved.
+    pub max_iterations: u32,
+    /// Iteration stops when the centroid of the window is less than
+    /// `epsilon` pixels from its centre in both directions.
+    pub epsilon: f32,
+}
+
+impl Default for MeanShiftOptions {
+    fn default() -> Self {
+        MeanShiftOptions {
+            max_iterations: 10,
+            epsilon: 1.0,
+        }
+    }
+}
+
+/// Repeatedly moves `window` to be centred on the centroid of the intensities of
+/// `probability` inside it, until the window stops moving or `options.max_iterations`
+/// is reached. Returns the final window.
+///
+/// `probability` is typically the [back-projection](struct.ColorHistogram.html#method.back_project)
+/// of a frame onto the colour histogram of the object being tracked. The window is
+/// kept inside the image, and is left unchanged if it contains no probability mass.
+///
+/// # Panics
+/// If `window` does not intersect `probability`.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::drawing::draw_filled_circle_mut;
+/// use imageproc::rect::Rect;
+/// use imageproc::tracking::{mean_shift, MeanShiftOptions};
+///
+/// let mut probability = GrayImage::new(60, 60);
+/// draw_filled_circle_mut(&mut probability, (35, 30), 6, Luma([255u8]));
+///
+/// let start = Rect::at(20, 20).of_size(15, 15);
+/// let end = mean_shift(&probability, start, MeanShiftOptions::default());
+/// assert_eq!(end, Rect::at(28, 23).of_size(15, 15));
+/// # }
+/// ```
+pub fn mean_shift(probability: &GrayImage, window: Rect, options: MeanShiftOptions) -> Rect {
+    let (width, height) = probability.dimensions();
+    let mut window = window.clamp_to_image(width, height).expect("window must intersect image");
+
+    for _ in 0..options.max_iterations {
+        let moments = Moments::of(probability, window);
+        if moments.m00 == 0.0 {
+            break;
+        }
+        let (cx, cy) = moments.centroid();
+        let dx = cx - window_center(window).0;
+        let dy = cy - window_center(window).1;
+
+        window = move_inside_image(
+            window.left() + dx.round() as i32,
+            window.top() + dy.round() as i32,
+            window.width(),
+            window.height(),
+            width,
+            height,
+        );
+
+        if dx.abs() < options.epsilon && dy.abs() < options.epsilon {
+            break;
+        }
+    }
+
+    window
+}
+
+/// The position, size and orientation of a region found by
+/// [`cam_shift`](fn.cam_shift.html), described as an ellipse fitted to
+/// the probability mass in the final search window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrackedRegion {
+    /// The search window to use for the next frame.
+    pub window: Rect,
+    /// Centroid of the region.
+    pub center: (f32, f32),
+    /// Length of the major axis of the region.
+    pub major_axis: f32,
+    /// Length of the minor axis of the region.
+    pub minor_axis: f32,
+    /// Angle in radians between the x-axis and the major axis, measured clockwise
+    /// (as y increases downwards), between -pi/2 and pi/2.
+    pub angle: f32,
+}
+
+/// [CAMShift] (Continuously Adaptive Mean Shift): runs [`mean_shift`](fn.mean_shift.html) and then
+/// resizes and orients the window to fit the probability mass found.
+///
+/// The region is described by the ellipse with the same second moments as the
+/// probability mass in the converged window, with axes extending two standard
+/// deviations either side of the centroid. The returned `window` is the bounding box
+/// of this ellipse, clipped to the image. If the converged window contains no
+/// probability mass then the input window is returned with zero-sized axes.
+///
+/// [CAMShift]: https://en.wikipedia.org/wiki/Mean_shift#Camshift
+///
+/// # Panics
+/// If `window` does not intersect `probability`.
+pub fn cam_shift(probability: &GrayImage, window: Rect, options: MeanShiftOptions) -> TrackedRegion {
+    let (width, height) = probability.dimensions();
+    let converged = mean_shift(probability, window, options);
+
+    // Look slightly beyond the converged window, so that the window can grow.
+    let margin = 1 + (converged.width().max(converged.height()) / 8) as i32;
+    let search = converged
+        .inflate(margin as u32, margin as u32)
+        .clamp_to_image(width, height)
+        .unwrap();
+
+    let moments = Moments::of(probability, search);
+    if moments.m00 == 0.0 {
+        return TrackedRegion {
+            window: converged,
+            center: window_center(converged),
+            major_axis: 0.0,
+            minor_axis: 0.0,
+            angle: 0.0,
+        };
+    }
+
+    let (cx, cy) = moments.centroid();
+    let (a, b, c) = moments.normalized_central();
+
+    let angle = 0.5 * (2.0 * b).atan2(a - c);
+    let root = (b * b + (a - c) * (a - c) / 4.0).sqrt();
+    let major_axis = 4.0 * ((a + c) / 2.0 + root).max(0.0).sqrt();
+    let minor_axis = 4.0 * ((a + c) / 2.0 - root).max(0.0).sqrt();
+
+    let (sin, cos) = angle.sin_cos();
+    let box_width = (major_axis * cos.abs() + minor_axis * sin.abs()).round().max(1.0) as u32;
+    let box_height = (major_axis * sin.abs() + minor_axis * cos.abs()).round().max(1.0) as u32;
+    let left = (cx - (box_width as f32 - 1.0) / 2.0).round() as i32;
+    let top = (cy - (box_height as f32 - 1.0) / 2.0).round() as i32;
+
+    TrackedRegion {
+        window: move_inside_image(left, top, box_width, box_height, width, height),
+        center: (cx, cy),
+        major_axis,
+        minor_axis,
+        angle,
+    }
+}
+
+/// Options for a [`RegionTracker`](struct.RegionTracker.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RegionTrackerOptions {
+    /// Number of bins per channel in the colour histogram of the tracked region.
+    pub bins_per_channel: u32,
+    /// If true, the search window is resized each frame using
+    /// [`cam_shift`](fn.cam_shift.html). Otherwise it keeps its
+    /// initial size and is moved using [`mean_shift`](fn.mean_shift.html).
+    pub adaptive_window: bool,
+    /// Stopping conditions for each frame's search.
+    pub mean_shift: MeanShiftOptions,
+}
+
+impl Default for RegionTrackerOptions {
+    fn default() -> Self {
+        RegionTrackerOptions {
+            bins_per_channel: 16,
+            adaptive_window: true,
+            mean_shift: MeanShiftOptions::default(),
+        }
+    }
+}
+
+/// Tracks a region through a sequence of frames by its colour distribution,
+/// using mean-shift or CAMShift on the back-projection of each frame.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{Rgb, RgbImage};
+/// use imageproc::drawing::draw_filled_rect_mut;
+/// use imageproc::rect::Rect;
+/// use imageproc::tracking::{RegionTracker, RegionTrackerOptions};
+///
+/// // A red square moving right across a blue background.
+/// let frame = |x| {
+///     let mut image = RgbImage::from_pixel(80, 40, Rgb([0, 0, 200]));
+///     draw_filled_rect_mut(&mut image, Rect::at(x, 10).of_size(12, 12), Rgb([220, 20, 20]));
+///     image
+/// };
+///
+/// let mut tracker = RegionTracker::new(&frame(10), Rect::at(10, 10).of_size(12, 12), RegionTrackerOptions::default());
+/// for x in 1..10 {
+///     tracker.update(&frame(10 + 4 * x));
+/// }
+///
+/// let window = tracker.window();
+/// assert!((window.left() - 46).abs() <= 1 && (window.top() - 10).abs() <= 1);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RegionTracker {
+    histogram: ColorHistogram,
+    window: Rect,
+    options: RegionTrackerOptions,
+}
+
+impl RegionTracker {
+    /// Creates a tracker for the object occupying `region` in `frame`.
+    ///
+    /// # Panics
+    /// If `region` does not intersect `frame`.
+    pub fn new<P>(frame: &Image<P>, region: Rect, options: RegionTrackerOptions) -> RegionTracker
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        RegionTracker {
+            histogram: ColorHistogram::from_region(frame, region, options.bins_per_channel),
+            window: region.clamp_to_image(frame.width(), frame.height()).unwrap(),
+            options,
+        }
+    }
+
+    /// Locates the tracked object in a new frame.
+    ///
+    /// # Panics
+    /// If `P` does not have the same number of channels as the frame used to create this tracker.
+    pub fn update<P>(&mut self, frame: &Image<P>) -> TrackedRegion
+    where
+        P: Pixel<Subpixel = u8> + 'static,
+    {
+        let probability = self.histogram.back_project(frame);
+        let window = self.window.clamp_to_image(frame.width(), frame.height())
+            .unwrap_or_else(|| Rect::at(0, 0).of_size(1, 1));
+
+        let region = if self.options.adaptive_window {
+            cam_shift(&probability, window, self.options.mean_shift)
+        } else {
+            let window = mean_shift(&probability, window, self.options.mean_shift);
+            TrackedRegion {
+                window,
+                center: window_center(window),
+                major_axis: window.width().max(window.height()) as f32,
+                minor_axis: window.width().min(window.height()) as f32,
+                angle: if window.height() > window.width() { f32::consts::FRAC_PI_2 } else { 0.0 },
+            }
+        };
+
+        // Don't let the window collapse to nothing if the object is briefly lost.
+        if region.major_axis > 0.0 {
+            self.window = region.window;
+        }
+        region
+    }
+
+    /// The current search window.
+    pub fn window(&self) -> Rect {
+        self.window
+    }
+
+    /// The colour histogram of the tracked object.
+    pub fn histogram(&self) -> &ColorHistogram {
+        &self.histogram
+    }
+}
+
+/// Raw moments of an image region, accumulated in absolute image coordinates.
+struct Moments {
+    m00: f32,
+    m10: f32,
+    m01: f32,
+    m20: f32,
+    m11: f32,
+    m02: f32,
+}
+
+impl Moments {
+    fn of(image: &GrayImage, region: Rect) -> Moments {
+        let (mut m00, mut m10, mut m01, mut m20, mut m11, mut m02) = (0f64, 0f64, 0f64, 0f64, 0f64, 0f64);
+        for y in region.top()..region.bottom() + 1 {
+            for x in region.left()..region.right() + 1 {
+                let w = image.get_pixel(x as u32, y as u32)[0] as f64;
+                let (x, y) = (x as f64, y as f64);
+                m00 += w;
+                m10 += w * x;
+                m01 += w * y;
+                m20 += w * x * x;
+                m11 += w * x * y;
+                m02 += w * y * y;
+            }
+        }
+        Moments {
+            m00: m00 as f32,
+            m10: m10 as f32,
+            m01: m01 as f32,
+            m20: m20 as f32,
+            m11: m11 as f32,
+            m02: m02 as f32,
+        }
+    }
+
+    fn centroid(&self) -> (f32, f32) {
+        (self.m10 / self.m00, self.m01 / self.m00)
+    }
+
+    /// Returns the central second moments (mu20, mu11, mu02), divided by m00.
+    fn normalized_central(&self) -> (f32, f32, f32) {
+        let (cx, cy) = self.centroid();
+        (
+            self.m20 / self.m00 - cx * cx,
+            self.m11 / self.m00 - cx * cy,
+            self.m02 / self.m00 - cy * cy,
+        )
+    }
+}
+
+fn window_center(window: Rect) -> (f32, f32) {
+    (
+        window.left() as f32 + (window.width() as f32 - 1.0) / 2.0,
+        window.top() as f32 + (window.height() as f32 - 1.0) / 2.0,
+    )
+}
+
+/// Returns the rect with the given position and size, shrunk if larger than
+/// the image and then translated to lie entirely inside it.
+fn move_inside_image(left: i32, top: i32, w: u32, h: u32, width: u32, height: u32) -> Rect {
+    let w = w.min(width);
+    let h = h.min(height);
+    let left = left.max(0).min((width - w) as i32);
+    let top = top.max(0).min((height - h) as i32);
+    Rect::at(left, top).of_size(w, h)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use drawing::{draw_filled_ellipse_mut, draw_filled_rect_mut};
+    use test::{Bencher, black_box};
+
+    #[test]
+    fn test_color_histogram_back_projection() {
+        let image = gray_image!(
+            10,  10, 200;
+            10, 200, 200);
+
+        let histogram = ColorHistogram::from_region(&image, Rect::at(0, 0).of_size(2, 2), 4);
+
+        // Three of the four region pixels are in the lowest bin and one in the highest.
+        let expected = gray_image!(
+            255, 255, 85;
+            255,  85, 85);
+
+        assert_pixels_eq!(histogram.back_project(&image), expected);
+    }
+
+    #[test]
+    fn test_hue_histogram_back_projection() {
+        let image = rgb_image!(
+            [200, 0, 0], [100, 0, 0], [0, 200, 0];
+            [200, 190, 190], [5, 0, 0], [0, 100, 0]);
+
+        let options = HueHistogramOptions { bins: 6, ..HueHistogramOptions::default() };
+        let histogram = HueHistogram::from_region(&image, Rect::at(0, 0).of_size(3, 2), options);
+
+        // The two reds and the two greens have the same hues as each other. The nearly
+        // gray and nearly black pixels are ignored.
+        let expected = gray_image!(
+            255, 255, 255;
+              0,   0, 255);
+
+        assert_pixels_eq!(histogram.back_project(&image), expected);
+
+        let blue = RgbImage::from_pixel(2, 2, Rgb([0, 0, 255]));
+        assert!(histogram.back_project(&blue).iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hue_histogram_rejects_zero_bins() {
+        let image = RgbImage::new(3, 3);
+        let options = HueHistogramOptions { bins: 0, ..HueHistogramOptions::default() };
+        HueHistogram::from_region(&image, Rect::at(0, 0).of_size(3, 3), options);
+    }
+
+    #[test]
+    fn test_mean_shift_stays_inside_image() {
+        let mut probability = GrayImage::new(30, 30);
+        draw_filled_rect_mut(&mut probability, Rect::at(0, 0).of_size(3, 3), Luma([255u8]));
+        let window = mean_shift(&probability, Rect::at(2, 2).of_size(10, 10), MeanShiftOptions::default());
+        assert_eq!(window, Rect::at(0, 0).of_size(10, 10));
+    }
+
+    #[test]
+    fn test_mean_shift_empty_window_does_not_move() {
+        let probability = GrayImage::new(30, 30);
+        let window = Rect::at(5, 6).of_size(7, 8);
+        assert_eq!(mean_shift(&probability, window, MeanShiftOptions::default()), window);
+    }
+
+    #[test]
+    fn test_cam_shift_fits_rotated_ellipse() {
+        let mut probability = GrayImage::new(100, 100);
+        draw_filled_ellipse_mut(&mut probability, (50, 45), 20, 6, Luma([255u8]));
+
+        // The window can only grow a little each call, as when tracking across frames.
+        let mut window = Rect::at(35, 35).of_size(20, 20);
+        for _ in 0..5 {
+            window = cam_shift(&probability, window, MeanShiftOptions::default()).window;
+        }
+        let region = cam_shift(&probability, window, MeanShiftOptions::default());
+
+        assert!((region.center.0 - 50.0).abs() < 0.5);
+        assert!((region.center.1 - 45.0).abs() < 0.5);
+        assert!(region.angle.abs() < 0.05);
+        // For a uniform ellipse with semi-axis r the standard deviation along that axis is r / 2.
+        assert!((region.major_axis - 40.0).abs() < 3.0, "{}", region.major_axis);
+        assert!((region.minor_axis - 12.0).abs() < 3.0, "{}", region.minor_axis);
+        assert!(region.window.width() > region.window.height());
+    }
+
+    #[test]
+    fn test_cam_shift_finds_diagonal_orientation() {
+        let mut probability = GrayImage::new(60, 60);
+        for t in -15..16 {
+            for s in -2..3 {
+                let x = (30 + t + s) as u32;
+                let y = (30 + t - s) as u32;
+                probability.put_pixel(x, y, Luma([255u8]));
+            }
+        }
+        let region = cam_shift(&probability, Rect::at(25, 25).of_size(10, 10), MeanShiftOptions::default());
+        assert!((region.angle - f32::consts::FRAC_PI_4).abs() < 0.1, "{}", region.angle);
+    }
+
+    #[test]
+    fn test_region_tracker_follows_object_without_adapting() {
+        let frame = |x: i32, y: i32| {
+            let mut image = RgbImage::from_pixel(80, 80, Rgb([20, 120, 20]));
+            draw_filled_rect_mut(&mut image, Rect::at(x, y).of_size(10, 10), Rgb([200, 200, 0]));
+            image
+        };
+
+        let options = RegionTrackerOptions { adaptive_window: false, ..RegionTrackerOptions::default() };
+        let mut tracker = RegionTracker::new(&frame(5, 5), Rect::at(5, 5).of_size(10, 10), options);
+        for i in 1..12 {
+            tracker.update(&frame(5 + 5 * i, 5 + 3 * i));
+        }
+        assert_eq!(tracker.window(), Rect::at(60, 38).of_size(10, 10));
+    }
+
+    #[bench]
+    fn bench_region_tracker_update(b: &mut Bencher) {
+        let mut image = RgbImage::from_pixel(200, 200, Rgb([20, 120, 20]));
+        draw_filled_rect_mut(&mut image, Rect::at(80, 80).of_size(30, 30), Rgb([200, 200, 0]));
+        let mut tracker = RegionTracker::new(&image, Rect::at(80, 80).of_size(30, 30), RegionTrackerOptions::default());
+        b.iter(|| black_box(tracker.update(&image)));
+    }
+}
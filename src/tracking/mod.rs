@@ -0,0 +1,28 @@
+//! Tracking of objects through sequences of video frames.
+
+mod kalman;
+pub use self::kalman::{
+    BoxKalmanFilter,
+    KalmanOptions,
+    PointKalmanFilter
+};
+
+mod mean_shift;
+pub use self::mean_shift::{
+    ColorHistogram,
+    HueHistogram,
+    HueHistogramOptions,
+    MeanShiftOptions,
+    RegionTracker,
+    RegionTrackerOptions,
+    TrackedRegion,
+    cam_shift,
+    mean_shift
+};
+
+mod multi_object;
+pub use self::multi_object::{
+    MultiObjectTracker,
+    MultiObjectTrackerOptions,
+    Track
+};
@@ -0,0 +1,395 @@
+//! Tracking of multiple objects by associating per-frame detections, in the style of
+//! [SORT](https://arxiv.org/abs/1602.00763) but without an appearance model.
+
+use rect::Rect;
+use std::f64;
+use super::kalman::{BoxKalmanFilter, KalmanOptions};
+
+/// Options for a [`MultiObjectTracker`](struct.MultiObjectTracker.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MultiObjectTrackerOptions {
+    /// A detection can only be assigned to a track if the intersection over union
+    /// of the detection and the track's predicted box is at least this value.
+    pub iou_threshold: f32,
+    /// A track is deleted once it has gone this many consecutive frames without
+    /// being assigned a detection.
+    pub max_age: u32,
+    /// A track is only reported once it has been assigned this many detections,
+    /// to suppress spurious one-off detections. This requirement is waived during
+    /// the first `min_hits` frames seen by the tracker.
+    pub min_hits: u32,
+    /// Noise parameters of the Kalman filter used to predict the motion of each track.
+    pub kalman: KalmanOptions,
+}
+
+impl Default for MultiObjectTrackerOptions {
+    fn default() -> Self {
+        MultiObjectTrackerOptions {
+            iou_threshold: 0.3,
+            max_age: 1,
+            min_hits: 3,
+            kalman: KalmanOptions::default(),
+        }
+    }
+}
+
+/// A tracked object, as reported by [`MultiObjectTracker::update`](struct.MultiObjectTracker.html#method.update).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Track {
+    /// Identifier of the track, which is unique for the lifetime of the tracker.
+    pub id: u64,
+    /// Current estimate of the object's bounding box.
+    pub rect: Rect,
+    /// Total number of detections assigned to this track.
+    pub hits: u32,
+    /// Number of frames since a detection was last assigned to this track.
+    pub time_since_update: u32,
+}
+
+/// Tracks multiple objects through a video given per-frame detections, assigning each
+/// object a stable identifier.
+///
+/// Each frame, the boxes of existing tracks are predicted using a constant-velocity
+/// [`BoxKalmanFilter`](struct.BoxKalmanFilter.html) and detections are assigned to tracks
+/// by the Hungarian algorithm, maximising the total intersection over union of assigned pairs.
+/// Unassigned detections start new tracks and tracks that remain unassigned for too long are deleted.
+///
+/// # Examples
+/// ```
+/// use imageproc::rect::Rect;
+/// use imageproc::tracking::{MultiObjectTracker, MultiObjectTrackerOptions};
+///
+/// let mut tracker = MultiObjectTracker::new(MultiObjectTrackerOptions::default());
+///
+/// // Two objects moving in opposite directions.
+/// let mut ids = vec![];
+/// for t in 0..10 {
+///     let detections = [
+///         Rect::at(10 + 3 * t, 10).of_size(20, 20),
+///         Rect::at(100 - 3 * t, 50).of_size(20, 20),
+///     ];
+///     let tracks = tracker.update(&detections);
+///     ids = tracks.iter().map(|t| t.id).collect();
+/// }
+///
+/// // The objects keep the identifiers they were given in the first frame.
+/// ids.sort();
+/// assert_eq!(ids, vec![0, 1]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiObjectTracker {
+    options: MultiObjectTrackerOptions,
+    tracks: Vec<TrackState>,
+    next_id: u64,
+    frames_seen: u32,
+}
+
+#[derive(Clone, Debug)]
+struct TrackState {
+    id: u64,
+    filter: BoxKalmanFilter,
+    hits: u32,
+    time_since_update: u32,
+}
+
+impl TrackState {
+    fn track(&self) -> Track {
+        Track {
+            id: self.id,
+            rect: self.filter.rect(),
+            hits: self.hits,
+            time_since_update: self.time_since_update,
+        }
+    }
+}
+
+impl MultiObjectTracker {
+    /// Creates a tracker with no tracks.
+    pub fn new(options: MultiObjectTrackerOptions) -> MultiObjectTracker {
+        MultiObjectTracker {
+            options,
+            tracks: vec![],
+            next_id: 0,
+            frames_seen: 0,
+        }
+    }
+
+    /// Updates the tracker with the detections for a new frame, and returns the tracks
+    /// that were assigned a detection in this frame and have enough hits to be reported.
+    ///
+    /// This must be called once per frame, even if there are no detections.
+    pub fn update(&mut self, detections: &[Rect]) -> Vec<Track> {
+        self.frames_seen = self.frames_seen.saturating_add(1);
+
+        let predicted: Vec<Rect> = self.tracks.iter_mut().map(|t| t.filter.predict()).collect();
+        for track in &mut self.tracks {
+            track.time_since_update += 1;
+        }
+
+        let rows = predicted.len();
+        let cols = detections.len();
+        let mut costs = vec![0.0; rows * cols];
+        for (r, p) in predicted.iter().enumerate() {
+            for (c, d) in detections.iter().enumerate() {
+                costs[r * cols + c] = 1.0 - p.iou(*d) as f64;
+            }
+        }
+
+        let mut detection_assigned = vec![false; cols];
+        for (r, assignment) in hungarian(&costs, rows, cols).into_iter().enumerate() {
+            if let Some(c) = assignment {
+                if 1.0 - costs[r * cols + c] >= self.options.iou_threshold as f64 {
+                    let track = &mut self.tracks[r];
+                    track.filter.update(detections[c]);
+                    track.hits += 1;
+                    track.time_since_update = 0;
+                    detection_assigned[c] = true;
+                }
+            }
+        }
+
+        for (detection, assigned) in detections.iter().zip(detection_assigned) {
+            if !assigned {
+                self.tracks.push(TrackState {
+                    id: self.next_id,
+                    filter: BoxKalmanFilter::new(*detection, self.options.kalman),
+                    hits: 1,
+                    time_since_update: 0,
+                });
+                self.next_id += 1;
+            }
+        }
+
+        let max_age = self.options.max_age;
+        self.tracks.retain(|t| t.time_since_update <= max_age);
+
+        let min_hits = self.options.min_hits;
+        let warming_up = self.frames_seen <= min_hits;
+        self.tracks
+            .iter()
+            .filter(|t| t.time_since_update == 0 && (t.hits >= min_hits || warming_up))
+            .map(|t| t.track())
+            .collect()
+    }
+
+    /// Returns all current tracks, including those not assigned a detection in the
+    /// most recent frame and those without enough hits to be reported by `update`.
+    pub fn tracks(&self) -> Vec<Track> {
+        self.tracks.iter().map(|t| t.track()).collect()
+    }
+}
+
+/// Solves the assignment problem for the row-major `rows` by `cols` matrix `costs`,
+/// returning for each row the column assigned to it. Every row is assigned a column
+/// if `rows <= cols`, and otherwise every column is assigned to a row.
+///
+/// Uses the O(n^2 m) shortest augmenting path formulation of the Hungarian algorithm.
+fn hungarian(costs: &[f64], rows: usize, cols: usize) -> Vec<Option<usize>> {
+    if rows > cols {
+        let transposed: Vec<f64> = (0..cols * rows).map(|i| costs[(i % rows) * cols + i / rows]).collect();
+        let mut assignment = vec![None; rows];
+        for (c, r) in hungarian(&transposed, cols, rows).into_iter().enumerate() {
+            if let Some(r) = r {
+                assignment[r] = Some(c);
+            }
+        }
+        return assignment;
+    }
+
+    // Potentials and matching use 1-based indices, with index 0 as a sentinel.
+    let (n, m) = (rows, cols);
+    let cost = |i: usize, j: usize| costs[(i - 1) * cols + j - 1];
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    // row_of[j] is the row matched to column j, or 0 if unmatched.
+    let mut row_of = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+
+    for i in 1..n + 1 {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_slack = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..m + 1 {
+                if !used[j] {
+                    let slack = cost(i0, j) - u[i0] - v[j];
+                    if slack < min_slack[j] {
+                        min_slack[j] = slack;
+                        way[j] = j0;
+                    }
+                    if min_slack[j] < delta {
+                        delta = min_slack[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..m + 1 {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if row_of[j0] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path.
+        loop {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+    for j in 1..m + 1 {
+        if row_of[j] != 0 {
+            assignment[row_of[j] - 1] = Some(j - 1);
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test::{Bencher, black_box};
+
+    fn total_cost(costs: &[f64], cols: usize, assignment: &[Option<usize>]) -> f64 {
+        assignment.iter().enumerate()
+            .filter_map(|(r, c)| c.map(|c| costs[r * cols + c]))
+            .sum()
+    }
+
+    /// Minimum cost over all assignments of rows to distinct columns, assuming rows <= cols.
+    fn brute_force_cost(costs: &[f64], rows: usize, cols: usize) -> f64 {
+        fn go(costs: &[f64], row: usize, rows: usize, cols: usize, used: &mut Vec<bool>) -> f64 {
+            if row == rows {
+                return 0.0;
+            }
+            let mut best = f64::INFINITY;
+            for c in 0..cols {
+                if !used[c] {
+                    used[c] = true;
+                    best = best.min(costs[row * cols + c] + go(costs, row + 1, rows, cols, used));
+                    used[c] = false;
+                }
+            }
+            best
+        }
+        go(costs, 0, rows, cols, &mut vec![false; cols])
+    }
+
+    #[test]
+    fn test_hungarian_square() {
+        let costs = vec![
+            4.0, 1.0, 3.0,
+            2.0, 0.0, 5.0,
+            3.0, 2.0, 2.0,
+        ];
+        assert_eq!(hungarian(&costs, 3, 3), vec![Some(1), Some(0), Some(2)]);
+    }
+
+    #[test]
+    fn test_hungarian_matches_brute_force() {
+        let mut seed = 7u64;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 40) as f64 / (1u64 << 24) as f64
+        };
+        for &(rows, cols) in &[(1, 1), (2, 5), (4, 4), (5, 3), (6, 6)] {
+            let costs: Vec<f64> = (0..rows * cols).map(|_| next()).collect();
+            let assignment = hungarian(&costs, rows, cols);
+            assert_eq!(assignment.iter().filter(|a| a.is_some()).count(), rows.min(cols));
+
+            let expected = if rows <= cols {
+                brute_force_cost(&costs, rows, cols)
+            } else {
+                let transposed: Vec<f64> = (0..cols * rows).map(|i| costs[(i % rows) * cols + i / rows]).collect();
+                brute_force_cost(&transposed, cols, rows)
+            };
+            assert!((total_cost(&costs, cols, &assignment) - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_hungarian_empty() {
+        assert_eq!(hungarian(&[], 0, 3), vec![]);
+        assert_eq!(hungarian(&[], 2, 0), vec![None, None]);
+    }
+
+    #[test]
+    fn test_iou() {
+        let a = Rect::at(0, 0).of_size(10, 10);
+        assert_eq!(a.iou(a), 1.0);
+        assert_eq!(a.iou(Rect::at(5, 0).of_size(10, 10)), 50.0 / 150.0);
+        assert_eq!(a.iou(Rect::at(20, 20).of_size(5, 5)), 0.0);
+    }
+
+    #[test]
+    fn test_tracker_keeps_ids_through_crossing() {
+        let mut tracker = MultiObjectTracker::new(MultiObjectTrackerOptions::default());
+        let mut first_ids = vec![];
+        for t in 0..30 {
+            // Objects moving in opposite directions along slightly different rows.
+            let detections = [
+                Rect::at(10 + 4 * t, 20).of_size(16, 16),
+                Rect::at(130 - 4 * t, 26).of_size(16, 16),
+            ];
+            let mut tracks = tracker.update(&detections);
+            tracks.sort_by_key(|t| t.rect.left());
+            let ids: Vec<u64> = tracks.iter().map(|t| t.id).collect();
+            if t == 0 {
+                first_ids = ids;
+            } else if t == 29 {
+                // The objects have swapped sides.
+                assert_eq!(ids, vec![first_ids[1], first_ids[0]]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tracker_deletes_stale_tracks_and_delays_new_ones() {
+        let options = MultiObjectTrackerOptions { max_age: 2, ..MultiObjectTrackerOptions::default() };
+        let mut tracker = MultiObjectTracker::new(options);
+        let a = Rect::at(0, 0).of_size(10, 10);
+        let b = Rect::at(50, 50).of_size(10, 10);
+
+        for _ in 0..5 {
+            assert_eq!(tracker.update(&[a]).len(), 1);
+        }
+
+        // A new detection isn't reported until it has been seen min_hits times.
+        assert_eq!(tracker.update(&[a, b]).len(), 1);
+        assert_eq!(tracker.update(&[a, b]).len(), 1);
+        let tracks = tracker.update(&[a, b]);
+        assert_eq!(tracks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![0, 1]);
+
+        // Track 0 survives being missed for max_age frames.
+        tracker.update(&[b]);
+        tracker.update(&[b]);
+        assert_eq!(tracker.tracks().len(), 2);
+        tracker.update(&[b]);
+        assert_eq!(tracker.tracks().iter().map(|t| t.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[bench]
+    fn bench_tracker_update(b: &mut Bencher) {
+        let detections: Vec<Rect> = (0..50)
+            .map(|i| Rect::at(30 * (i % 10), 30 * (i / 10)).of_size(20, 20))
+            .collect();
+        let mut tracker = MultiObjectTracker::new(MultiObjectTrackerOptions::default());
+        b.iter(|| black_box(tracker.update(&detections)));
+    }
+}
@@ -0,0 +1,370 @@
+//! Preparation of training data for sliding window object detectors.
+//!
+//! Positive samples are crops of annotated objects, randomly perturbed so that the
+//! detector learns to tolerate small errors in position and scale. Negative samples
+//! are windows chosen at random from the parts of the image not covered by any object.
+
+use image::{GrayImage, Luma};
+use definitions::Image;
+use integral_image::integral_image;
+use rect::Rect;
+use rand::{Rng, SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Range};
+
+/// Options for [`extract_positive_patches`](fn.extract_positive_patches.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositiveSamplingOptions {
+    /// Width of each output patch.
+    pub patch_width: u32,
+    /// Height of each output patch.
+    pub patch_height: u32,
+    /// Number of patches generated from each object.
+    pub samples_per_box: usize,
+    /// Maximum shift of the crop window in each direction, as a fraction of the object's size.
+    pub max_translation: f32,
+    /// Maximum relative change in the size of the crop window. The window is
+    /// scaled by a factor chosen uniformly from `[1 - max_scale_change, 1 + max_scale_change]`.
+    pub max_scale_change: f32,
+    /// If true, each patch is mirrored horizontally with probability one half.
+    pub random_flip: bool,
+}
+
+impl Default for PositiveSamplingOptions {
+    fn default() -> Self {
+        PositiveSamplingOptions {
+            patch_width: 24,
+            patch_height: 24,
+            samples_per_box: 10,
+            max_translation: 0.1,
+            max_scale_change: 0.1,
+            random_flip: true,
+        }
+    }
+}
+
+/// Crops randomly jittered windows around each object in `boxes` and resizes them to
+/// the patch size given in `options`, using bilinear interpolation.
+///
+/// The windows are not required to have the same aspect ratio as the patches. Parts of
+/// a window outside the image are filled by repeating the nearest edge pixel.
+/// Setting `max_translation` and `max_scale_change` to 0 and `random_flip` to false
+/// gives exact crops of each box.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::{GrayImage, Luma};
+/// use imageproc::rect::Rect;
+/// use imageproc::training::{extract_positive_patches, PositiveSamplingOptions};
+///
+/// let image = GrayImage::from_fn(100, 100, |x, y| Luma([(x + y) as u8]));
+/// let boxes = [Rect::at(10, 10).of_size(40, 40), Rect::at(60, 20).of_size(20, 30)];
+///
+/// let options = PositiveSamplingOptions { samples_per_box: 5, ..PositiveSamplingOptions::default() };
+/// let patches = extract_positive_patches(&image, &boxes, options, 42);
+///
+/// assert_eq!(patches.len(), 10);
+/// assert!(patches.iter().all(|p| p.dimensions() == (24, 24)));
+/// # }
+/// ```
+pub fn extract_positive_patches(
+    image: &GrayImage,
+    boxes: &[Rect],
+    options: PositiveSamplingOptions,
+    seed: usize,
+) -> Vec<GrayImage> {
+    let seed_array: &[_] = &[seed];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+
+    let mut patches = Vec::with_capacity(boxes.len() * options.samples_per_box);
+    for b in boxes {
+        for _ in 0..options.samples_per_box {
+            let scale = 1.0 + symmetric_sample(&mut rng, options.max_scale_change);
+            let dx = symmetric_sample(&mut rng, options.max_translation) * b.width() as f32;
+            let dy = symmetric_sample(&mut rng, options.max_translation) * b.height() as f32;
+            let flip = options.random_flip && rng.gen::<bool>();
+
+            let width = b.width() as f32 * scale;
+            let height = b.height() as f32 * scale;
+            let center_x = b.left() as f32 + b.width() as f32 / 2.0 + dx;
+            let center_y = b.top() as f32 + b.height() as f32 / 2.0 + dy;
+
+            patches.push(resample(
+                image,
+                (center_x - width / 2.0, center_y - height / 2.0, width, height),
+                options.patch_width,
+                options.patch_height,
+                flip,
+            ));
+        }
+    }
+    patches
+}
+
+/// Options for [`sample_negative_windows`](fn.sample_negative_windows.html)
+/// and [`extract_negative_patches`](fn.extract_negative_patches.html).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NegativeSamplingOptions {
+    /// Width of each output patch.
+    pub patch_width: u32,
+    /// Height of each output patch.
+    pub patch_height: u32,
+    /// Number of windows to sample.
+    pub count: usize,
+    /// Each window has the aspect ratio of the patches and is between `min_scale`
+    /// and `max_scale` times their size.
+    pub min_scale: f32,
+    /// See `min_scale`.
+    pub max_scale: f32,
+    /// Windows whose intersection over union with any ground truth box
+    /// exceeds this value are rejected.
+    pub max_overlap: f32,
+    /// Sampling gives up after this many candidate windows, even if
+    /// fewer than `count` have been accepted.
+    pub max_attempts: usize,
+}
+
+impl Default for NegativeSamplingOptions {
+    fn default() -> Self {
+        NegativeSamplingOptions {
+            patch_width: 24,
+            patch_height: 24,
+            count: 100,
+            min_scale: 1.0,
+            max_scale: 4.0,
+            max_overlap: 0.1,
+            max_attempts: 10000,
+        }
+    }
+}
+
+/// Returns up to `options.count` windows lying entirely within an image of the given
+/// size which do not overlap any of `ground_truth` by more than `options.max_overlap`.
+///
+/// Fewer windows are returned if the image is too small for the smallest allowed window
+/// or if `options.max_attempts` candidates are rejected.
+pub fn sample_negative_windows(
+    width: u32,
+    height: u32,
+    ground_truth: &[Rect],
+    options: NegativeSamplingOptions,
+    seed: usize,
+) -> Vec<Rect> {
+    let seed_array: &[_] = &[seed];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+
+    let aspect = options.patch_height as f32 / options.patch_width as f32;
+    // The largest window width that fits in the image.
+    let max_width = (width as f32).min(height as f32 / aspect);
+    let min_window_width = options.min_scale * options.patch_width as f32;
+    let max_window_width = (options.max_scale * options.patch_width as f32).min(max_width);
+    if min_window_width < 1.0 || min_window_width > max_window_width {
+        return vec![];
+    }
+
+    let mut windows = Vec::with_capacity(options.count);
+    for _ in 0..options.max_attempts {
+        if windows.len() == options.count {
+            break;
+        }
+        let w = if max_window_width > min_window_width {
+            Range::new(min_window_width, max_window_width).ind_sample(&mut rng)
+        } else {
+            min_window_width
+        };
+        let w = (w.round() as u32).max(1).min(width);
+        let h = ((w as f32 * aspect).round() as u32).max(1).min(height);
+        let left = rng.gen_range(0, width - w + 1);
+        let top = rng.gen_range(0, height - h + 1);
+        let window = Rect::at(left as i32, top as i32).of_size(w, h);
+
+        if ground_truth.iter().all(|g| window.iou(*g) <= options.max_overlap) {
+            windows.push(window);
+        }
+    }
+    windows
+}
+
+/// Crops the windows returned by [`sample_negative_windows`](fn.sample_negative_windows.html)
+/// from `image` and resizes them to the patch size given in `options`.
+pub fn extract_negative_patches(
+    image: &GrayImage,
+    ground_truth: &[Rect],
+    options: NegativeSamplingOptions,
+    seed: usize,
+) -> Vec<GrayImage> {
+    sample_negative_windows(image.width(), image.height(), ground_truth, options, seed)
+        .into_iter()
+        .map(|w| extract_patch(image, w, options.patch_width, options.patch_height))
+        .collect()
+}
+
+/// Crops `window` from `image` and resizes it to the given size using bilinear interpolation.
+/// Parts of the window outside the image are filled by repeating the nearest edge pixel.
+///
+/// # Panics
+/// If `image` is empty.
+pub fn extract_patch(image: &GrayImage, window: Rect, patch_width: u32, patch_height: u32) -> GrayImage {
+    resample(
+        image,
+        (window.left() as f32, window.top() as f32, window.width() as f32, window.height() as f32),
+        patch_width,
+        patch_height,
+        false,
+    )
+}
+
+/// Computes the [`integral_image`](../integral_image/fn.integral_image.html) of each patch,
+/// which is the form expected by [Haar feature](../haar/index.html) evaluation.
+pub fn integral_images(patches: &[GrayImage]) -> Vec<Image<Luma<u32>>> {
+    patches.iter().map(integral_image).collect()
+}
+
+/// Samples the window `(left, top, width, height)` of `image` on a regular grid of
+/// `out_width` by `out_height` points, optionally mirroring horizontally.
+fn resample(image: &GrayImage, window: (f32, f32, f32, f32), out_width: u32, out_height: u32, flip: bool) -> GrayImage {
+    assert!(image.width() > 0 && image.height() > 0, "image must be non-empty");
+    let (left, top, width, height) = window;
+    let step_x = width / out_width as f32;
+    let step_y = height / out_height as f32;
+
+    GrayImage::from_fn(out_width, out_height, |x, y| {
+        let x = if flip { out_width - 1 - x } else { x };
+        // Sample at the centre of each output pixel's footprint.
+        let sx = left + (x as f32 + 0.5) * step_x - 0.5;
+        let sy = top + (y as f32 + 0.5) * step_y - 0.5;
+        Luma([bilinear_clamped(image, sx, sy).round() as u8])
+    })
+}
+
+fn bilinear_clamped(image: &GrayImage, x: f32, y: f32) -> f32 {
+    let max_x = (image.width() - 1) as f32;
+    let max_y = (image.height() - 1) as f32;
+    let x = x.max(0.0).min(max_x);
+    let y = y.max(0.0).min(max_y);
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let x1 = (x0 + 1.0).min(max_x);
+    let y1 = (y0 + 1.0).min(max_y);
+    let (fx, fy) = (x - x0, y - y0);
+
+    let p = |px: f32, py: f32| image.get_pixel(px as u32, py as u32)[0] as f32;
+    let top = p(x0, y0) * (1.0 - fx) + p(x1, y0) * fx;
+    let bottom = p(x0, y1) * (1.0 - fx) + p(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Returns a value chosen uniformly from `[-max, max]`, or 0 if `max` is not positive.
+pub(crate) fn symmetric_sample<R: Rng>(rng: &mut R, max: f32) -> f32 {
+    if max > 0.0 {
+        Range::new(-max, max).ind_sample(rng)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test::{Bencher, black_box};
+
+    fn gradient_image(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| Luma([(2 * x + y) as u8]))
+    }
+
+    #[test]
+    fn test_extract_patch_exact_crop() {
+        let image = gradient_image(20, 20);
+        let patch = extract_patch(&image, Rect::at(3, 4).of_size(5, 6), 5, 6);
+        let expected = GrayImage::from_fn(5, 6, |x, y| *image.get_pixel(x + 3, y + 4));
+        assert_pixels_eq!(patch, expected);
+    }
+
+    #[test]
+    fn test_extract_patch_downsamples() {
+        let image = gray_image!(
+            0, 2, 10, 10;
+            2, 4, 10, 10);
+        assert_pixels_eq!(extract_patch(&image, Rect::at(0, 0).of_size(4, 2), 2, 1), gray_image!(2, 10));
+    }
+
+    #[test]
+    fn test_extract_patch_clamps_to_edges() {
+        let image = gray_image!(
+            1, 2;
+            3, 4);
+        let expected = gray_image!(
+            1, 1, 2;
+            1, 1, 2;
+            3, 3, 4);
+        assert_pixels_eq!(extract_patch(&image, Rect::at(-1, -1).of_size(3, 3), 3, 3), expected);
+    }
+
+    #[test]
+    fn test_extract_positive_patches_without_jitter() {
+        let image = gradient_image(40, 40);
+        let boxes = [Rect::at(5, 5).of_size(10, 10)];
+        let options = PositiveSamplingOptions {
+            patch_width: 10,
+            patch_height: 10,
+            samples_per_box: 3,
+            max_translation: 0.0,
+            max_scale_change: 0.0,
+            random_flip: false,
+        };
+        let expected = extract_patch(&image, boxes[0], 10, 10);
+        for patch in extract_positive_patches(&image, &boxes, options, 1) {
+            assert_pixels_eq!(patch, expected);
+        }
+    }
+
+    #[test]
+    fn test_extract_positive_patches_is_deterministic() {
+        let image = gradient_image(40, 40);
+        let boxes = [Rect::at(5, 5).of_size(10, 10)];
+        let options = PositiveSamplingOptions::default();
+        let first = extract_positive_patches(&image, &boxes, options, 7);
+        let second = extract_positive_patches(&image, &boxes, options, 7);
+        let raw = |patches: &[GrayImage]| patches.iter().map(|p| p.clone().into_raw()).collect::<Vec<_>>();
+        assert_eq!(raw(&first), raw(&second));
+        // Flipping and jitter mean the samples should not all be identical.
+        assert!(raw(&first).iter().any(|p| *p != raw(&first)[0]));
+    }
+
+    #[test]
+    fn test_sample_negative_windows_avoid_ground_truth() {
+        let ground_truth = [Rect::at(20, 20).of_size(40, 40), Rect::at(100, 10).of_size(30, 60)];
+        let options = NegativeSamplingOptions { count: 50, max_overlap: 0.0, ..NegativeSamplingOptions::default() };
+        let windows = sample_negative_windows(160, 120, &ground_truth, options, 3);
+
+        assert_eq!(windows.len(), 50);
+        for w in windows {
+            assert!(w.left() >= 0 && w.top() >= 0 && w.right() < 160 && w.bottom() < 120);
+            assert!(w.width() >= 24 && w.width() <= 96 && w.width() == w.height());
+            assert!(ground_truth.iter().all(|g| w.intersect(*g).is_none()));
+        }
+    }
+
+    #[test]
+    fn test_sample_negative_windows_image_too_small() {
+        let options = NegativeSamplingOptions::default();
+        assert_eq!(sample_negative_windows(20, 100, &[], options, 1), vec![]);
+    }
+
+    #[test]
+    fn test_integral_images() {
+        let patches = vec![gray_image!(1, 2; 3, 4)];
+        let integrals = integral_images(&patches);
+        assert_eq!(integrals[0].get_pixel(2, 2)[0], 10);
+    }
+
+    #[bench]
+    fn bench_extract_positive_patches(b: &mut Bencher) {
+        let image = gradient_image(200, 200);
+        let boxes = [Rect::at(20, 20).of_size(60, 60), Rect::at(100, 80).of_size(80, 80)];
+        b.iter(|| black_box(extract_positive_patches(&image, &boxes, PositiveSamplingOptions::default(), 1)));
+    }
+}
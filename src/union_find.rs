@@ -1,112 +1,206 @@
 //! An implementation of disjoint set forests for union find.
+//!
+//! # Examples
+//! ```
+//! use imageproc::union_find::DisjointSetForest;
+//!
+//! let mut forest: DisjointSetForest<u32> = DisjointSetForest::new(5);
+//! forest.union(0, 3);
+//! forest.union(4, 1);
+//! forest.union(3, 2);
+//!
+//! assert!(forest.find(0, 2));
+//! assert!(!forest.find(0, 1));
+//! assert_eq!(forest.num_trees(), 2);
+//! assert_eq!(forest.relabel(), vec![0, 1, 0, 0, 1]);
+//! ```
 
-/// Data structure for efficient union find.
-pub struct DisjointSetForest {
-    /// Number of forest elements.
-    count: usize,
+use std::fmt::Debug;
+
+/// Integer types that can be used to index the elements of a
+/// [`DisjointSetForest`](struct.DisjointSetForest.html).
+///
+/// Using `u32` rather than `usize` halves the memory used by a forest on 64-bit targets.
+///
+/// This trait is sealed, and implemented only for `u32` and `usize`. The forest skips
+/// bounds checks on indices that have been through these conversions, so they must be
+/// exact inverses for every index in the forest.
+pub trait ForestIndex: private::Sealed + Copy + Eq + Debug {
+    /// Converts from a `usize`, which must be representable by this type.
+    fn from_usize(i: usize) -> Self;
+    /// Converts to a `usize`.
+    fn to_usize(self) -> usize;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for u32 {}
+    impl Sealed for usize {}
+}
+
+impl ForestIndex for u32 {
+    #[inline]
+    fn from_usize(i: usize) -> u32 {
+        i as u32
+    }
+    #[inline]
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl ForestIndex for usize {
+    #[inline]
+    fn from_usize(i: usize) -> usize {
+        i
+    }
+    #[inline]
+    fn to_usize(self) -> usize {
+        self
+    }
+}
+
+/// Data structure for efficient union find, using union by rank and path compression.
+///
+/// Elements are identified by their indices, of type `T`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisjointSetForest<T = usize> {
     /// parent[i] is the index of the parent
     /// of the element with index i. If parent[i] == i
     /// then i is a root.
-    parent: Vec<usize>,
-    /// tree_size[i] is the size of the tree rooted at i.
-    tree_size: Vec<usize>,
+    parent: Vec<T>,
+    /// rank[i] is an upper bound on the height of the tree rooted at i.
+    rank: Vec<u8>,
+    /// Number of trees in the forest.
+    num_trees: usize,
 }
 
-impl DisjointSetForest {
+impl<T: ForestIndex> DisjointSetForest<T> {
     /// Constructs forest of singletons with count elements.
-    pub fn new(count: usize) -> DisjointSetForest {
-        let parent: Vec<usize> = (0..count).collect();
-        let tree_size = vec![1 as usize; count];
+    ///
+    /// # Panics
+    /// If `count - 1` is not representable by `T`.
+    pub fn new(count: usize) -> DisjointSetForest<T> {
+        assert!(
+            count == 0 || T::from_usize(count - 1).to_usize() == count - 1,
+            "count {} is too large for index type", count
+        );
         DisjointSetForest {
-            count: count,
-            parent: parent,
-            tree_size: tree_size,
+            parent: (0..count).map(T::from_usize).collect(),
+            rank: vec![0; count],
+            num_trees: count,
         }
     }
 
+    /// Returns the number of elements in the forest.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// Returns true if the forest has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
     /// Returns the number of trees in the forest.
     pub fn num_trees(&self) -> usize {
-        self.parent.iter().enumerate().fold(0, |acc, (i, p)| {
-            acc + if i == *p { 1 } else { 0 }
-        })
+        self.num_trees
     }
 
     /// Returns index of the root of the tree containing i.
     /// Needs mutable reference to self for path compression.
-    pub fn root(&mut self, i: usize) -> usize {
-        assert!(i < self.count);
-        let mut j = i;
-        loop {
-            unsafe {
-                let p = *self.parent.get_unchecked(j);
-                *self.parent.get_unchecked_mut(j) = *self.parent.get_unchecked(p);
-                if j == p {
+    pub fn root(&mut self, i: T) -> T {
+        let i = i.to_usize();
+        assert!(i < self.len());
+        unsafe {
+            let mut root = i;
+            loop {
+                let p = self.parent.get_unchecked(root).to_usize();
+                if p == root {
                     break;
                 }
+                root = p;
+            }
+            // Point every element on the path from i directly at the root.
+            let mut j = i;
+            while j != root {
+                let p = self.parent.get_unchecked(j).to_usize();
+                *self.parent.get_unchecked_mut(j) = T::from_usize(root);
                 j = p;
             }
+            T::from_usize(root)
         }
-        j
     }
 
     /// Returns true if i and j are in the same tree.
     /// Need mutable reference to self for path compression.
-    pub fn find(&mut self, i: usize, j: usize) -> bool {
-        assert!(i < self.count && j < self.count);
+    pub fn find(&mut self, i: T, j: T) -> bool {
         self.root(i) == self.root(j)
     }
 
     /// Unions the trees containing i and j.
-    pub fn union(&mut self, i: usize, j: usize) {
-        assert!(i < self.count && j < self.count);
-        let p = self.root(i);
-        let q = self.root(j);
+    pub fn union(&mut self, i: T, j: T) {
+        self.union_sets(i, j);
+    }
+
+    /// Unions the trees containing i and j. Returns false if they were already
+    /// in the same tree.
+    pub fn union_sets(&mut self, i: T, j: T) -> bool {
+        let p = self.root(i).to_usize();
+        let q = self.root(j).to_usize();
         if p == q {
-            return;
+            return false;
         }
         unsafe {
-            let p_size = *self.tree_size.get_unchecked(p);
-            let q_size = *self.tree_size.get_unchecked(q);
-            if p_size < q_size {
-                *self.parent.get_unchecked_mut(p) = q;
-                *self.tree_size.get_unchecked_mut(q) = p_size + q_size;
+            let p_rank = *self.rank.get_unchecked(p);
+            let q_rank = *self.rank.get_unchecked(q);
+            if p_rank < q_rank {
+                *self.parent.get_unchecked_mut(p) = T::from_usize(q);
             } else {
-                *self.parent.get_unchecked_mut(q) = p;
-                *self.tree_size.get_unchecked_mut(p) = p_size + q_size;
+                *self.parent.get_unchecked_mut(q) = T::from_usize(p);
+                if p_rank == q_rank {
+                    *self.rank.get_unchecked_mut(p) = p_rank + 1;
+                }
             }
         }
+        self.num_trees -= 1;
+        true
     }
 
-    /// Returns the elements of each tree.
-    pub fn trees(&mut self) -> Vec<Vec<usize>> {
-        use std::collections::HashMap;
-
-        // Maps a tree root to the index of the set
-        // containing its children
-        let mut root_sets: HashMap<usize, usize> = HashMap::new();
+    /// Returns the elements of each tree, ordered by their smallest element.
+    pub fn trees(&mut self) -> Vec<Vec<T>> {
+        let labels = self.relabel();
+        let mut sets: Vec<Vec<T>> = vec![vec![]; self.num_trees];
+        for (i, label) in labels.into_iter().enumerate() {
+            sets[label.to_usize()].push(T::from_usize(i));
+        }
+        sets
+    }
 
-        let mut sets: Vec<Vec<usize>> = vec![];
-        for i in 0..self.count {
-            let root = self.root(i);
-            match root_sets.get(&root).cloned() {
-                Some(set_idx) => {
-                    sets[set_idx].push(i);
-                }
-                None => {
-                    let idx = sets.len();
-                    let set = vec![i];
-                    sets.push(set);
-                    root_sets.insert(root, idx);
-                }
+    /// Returns a label in `0..self.num_trees()` for each element, such that elements
+    /// have the same label if and only if they are in the same tree. Trees are labelled
+    /// in increasing order of their smallest element.
+    pub fn relabel(&mut self) -> Vec<T> {
+        const UNLABELLED: usize = usize::max_value();
+        let mut root_labels = vec![UNLABELLED; self.len()];
+        let mut next_label = 0;
+        let mut labels = Vec::with_capacity(self.len());
+        for i in 0..self.len() {
+            let root = self.root(T::from_usize(i)).to_usize();
+            if root_labels[root] == UNLABELLED {
+                root_labels[root] = next_label;
+                next_label += 1;
             }
+            labels.push(T::from_usize(root_labels[root]));
         }
-        sets
+        labels
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::DisjointSetForest;
+    use super::ForestIndex;
     use test;
     use rand::{SeedableRng, StdRng};
     use rand::distributions::{IndependentSample, Range};
@@ -118,20 +212,22 @@ mod test {
         //    1       5    7
         //   /  \     |
         //  0    2    6
-        let mut forest = DisjointSetForest {
-            count: 8,
+        let mut forest: DisjointSetForest<usize> = DisjointSetForest {
             // element:     0, 1, 2, 3, 4, 5, 6, 7
             parent:    vec![1, 3, 1, 3, 4, 4, 5, 4],
-            tree_size: vec![1, 3, 1, 4, 4, 2, 1, 1],
+            rank:      vec![0, 1, 0, 2, 2, 1, 0, 0],
+            num_trees: 2,
         };
 
         assert_eq!(forest.trees(), vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]);
+        // Path compression.
+        assert_eq!(forest.parent, vec![3, 3, 3, 3, 4, 4, 4, 4]);
     }
 
     #[test]
     fn test_union_find_sequence() {
 
-        let mut forest = DisjointSetForest::new(6);
+        let mut forest: DisjointSetForest<usize> = DisjointSetForest::new(6);
         // 0  1  2  3  4  5
 
         //                             0, 1, 2, 3, 4, 5
@@ -177,8 +273,48 @@ mod test {
         assert_eq!(forest.num_trees(), 2);
     }
 
-    #[bench]
-    fn bench_disjoint_set_forest(b: &mut test::Bencher) {
+    #[test]
+    fn test_union_sets_returns_whether_merged() {
+        let mut forest: DisjointSetForest<u32> = DisjointSetForest::new(3);
+        assert!(forest.union_sets(0, 1));
+        assert!(!forest.union_sets(1, 0));
+        assert!(forest.union_sets(2, 0));
+        assert_eq!(forest.num_trees(), 1);
+        assert_eq!(forest.trees(), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_relabel() {
+        let mut forest: DisjointSetForest<u32> = DisjointSetForest::new(7);
+        forest.union(6, 1);
+        forest.union(5, 3);
+        forest.union(3, 1);
+        assert_eq!(forest.relabel(), vec![0, 1, 2, 1, 3, 1, 1]);
+
+        let mut empty: DisjointSetForest<u32> = DisjointSetForest::new(0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.relabel(), vec![]);
+        assert_eq!(empty.trees(), Vec::<Vec<u32>>::new());
+    }
+
+    #[test]
+    fn test_u32_and_usize_forests_agree() {
+        let seed_array: &[_] = &[3usize];
+        let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+        let uniform = Range::new(0, 100);
+
+        let mut small: DisjointSetForest<u32> = DisjointSetForest::new(100);
+        let mut large: DisjointSetForest<usize> = DisjointSetForest::new(100);
+        for _ in 0..60 {
+            let (u, v) = (uniform.ind_sample(&mut rng), uniform.ind_sample(&mut rng));
+            assert_eq!(small.union_sets(u32::from_usize(u), u32::from_usize(v)), large.union_sets(u, v));
+        }
+        assert_eq!(small.num_trees(), large.num_trees());
+        let small_labels: Vec<usize> = small.relabel().into_iter().map(|l| l.to_usize()).collect();
+        assert_eq!(small_labels, large.relabel());
+    }
+
+    fn bench_forest<T: ForestIndex>(b: &mut test::Bencher) {
         let num_nodes = 500;
         let num_edges = 20 * num_nodes;
 
@@ -186,16 +322,26 @@ mod test {
         let mut rng: StdRng = SeedableRng::from_seed(seed_array);
         let uniform = Range::new(0, num_nodes);
 
-        let mut forest = DisjointSetForest::new(num_nodes);
+        let mut forest: DisjointSetForest<T> = DisjointSetForest::new(num_nodes);
         b.iter(|| {
             let mut count = 0;
             while count < num_edges {
                 let u = uniform.ind_sample(&mut rng);
                 let v = uniform.ind_sample(&mut rng);
-                forest.union(u, v);
+                forest.union(T::from_usize(u), T::from_usize(v));
                 count += 1;
             }
             test::black_box(forest.num_trees());
         });
     }
+
+    #[bench]
+    fn bench_disjoint_set_forest(b: &mut test::Bencher) {
+        bench_forest::<usize>(b);
+    }
+
+    #[bench]
+    fn bench_disjoint_set_forest_u32(b: &mut test::Bencher) {
+        bench_forest::<u32>(b);
+    }
 }
@@ -1,6 +1,6 @@
 //! Utils for testing and debugging.
 
-use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, open, Pixel, Rgb, RgbImage};
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, ImageBuffer, Luma, open, Pixel, Rgb, RgbImage};
 
 use std::u32;
 use std::fmt;
@@ -9,6 +9,8 @@ use std::path::Path;
 use itertools::Itertools;
 use std::collections::HashSet;
 use std::cmp::{max, min};
+use rand::{Rand, Rng, SeedableRng, StdRng};
+use definitions::Image;
 
 /// Helper for defining greyscale images.
 ///
@@ -554,9 +556,65 @@ pub fn rgb_bench_image(width: u32, height: u32) -> RgbImage {
     image
 }
 
+/// Returns an image with pseudo-random channel values, which are fully determined
+/// by `seed`.
+///
+/// Channel values are drawn uniformly from the full range of integer subpixel types
+/// and from `[0, 1)` for floating point subpixel types.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::GrayImage;
+/// use imageproc::utils::random_image;
+///
+/// let image: GrayImage = random_image(10, 8, 7);
+/// assert_eq!(image.dimensions(), (10, 8));
+///
+/// // The same seed always gives the same image.
+/// let again: GrayImage = random_image(10, 8, 7);
+/// assert_eq!(image.into_raw(), again.into_raw());
+/// # }
+/// ```
+pub fn random_image<P>(width: u32, height: u32, seed: usize) -> Image<P>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Rand,
+{
+    let seed_array: &[_] = &[seed];
+    let mut rng: StdRng = SeedableRng::from_seed(seed_array);
+
+    let mut image: Image<P> = ImageBuffer::new(width, height);
+    for pix in image.pixels_mut() {
+        for c in pix.channels_mut() {
+            *c = rng.gen();
+        }
+    }
+    image
+}
+
 #[cfg(test)]
 mod test {
-    
+    use super::random_image;
+    use image::{GrayImage, ImageBuffer, Rgb};
+
+    #[test]
+    fn test_random_image_is_deterministic() {
+        let first: GrayImage = random_image(12, 9, 3);
+        let second: GrayImage = random_image(12, 9, 3);
+        let other: GrayImage = random_image(12, 9, 4);
+        assert_pixels_eq!(first, second);
+        assert_ne!(first.into_raw(), other.into_raw());
+    }
+
+    #[test]
+    fn test_random_image_float_range() {
+        let image: ImageBuffer<Rgb<f32>, Vec<f32>> = random_image(10, 10, 1);
+        assert!(image.pixels().all(|p| p.data.iter().all(|c| *c >= 0.0 && *c < 1.0)));
+    }
+
     #[test]
     fn test_assert_pixels_eq_passes() {
         let image = gray_image!(
@@ -0,0 +1,529 @@
+//! Views of rectangular regions of images.
+//!
+//! Many functions in this crate that only read their input accept any
+//! `GenericImageView`, so they can be run over a region of interest
+//! of a larger image without first copying it out. Coordinates in the
+//! results of these functions are relative to the top-left of the view.
+//!
+//! [`enumerate_pixels`](fn.enumerate_pixels.html) iterates over the pixels of
+//! any `GenericImageView`, including empty images and views.
+//! [`windows`](fn.windows.html) iterates over views of equally sized windows
+//! of an image, and [`Neighborhood`](struct.Neighborhood.html) reads the pixels
+//! around a given pixel with configurable handling of pixels outside the image.
+//!
+//! # Examples
+//! ```
+//! # extern crate image;
+//! # #[macro_use]
+//! # extern crate imageproc;
+//! # fn main() {
+//! use imageproc::rect::Rect;
+//! use imageproc::stats::histogram_view;
+//! use imageproc::view::region_view;
+//!
+//! let image = gray_image!(
+//!     1, 1, 2;
+//!     1, 3, 3;
+//!     1, 3, 3);
+//!
+//! let roi = region_view(&image, Rect::at(1, 1).of_size(2, 2)).unwrap();
+//! assert_eq!(histogram_view(&roi)[3], 4);
+//! # }
+//! ```
+
+use image::{GenericImageView, Pixel, SubImage};
+use definitions::Image;
+use error::{ImageProcError, Result};
+use rect::Rect;
+
+/// Returns a view of the pixels of `image` within `region`.
+///
+/// Returns `ImageProcError::OutOfBounds` if `region` is not contained in `image`.
+pub fn region_view<I>(image: &I, region: Rect) -> Result<SubImage<&I::InnerImageView>>
+where
+    I: GenericImageView,
+{
+    let (width, height) = image.dimensions();
+    if region.left() < 0
+        || region.top() < 0
+        || region.right() as i64 >= width as i64
+        || region.bottom() as i64 >= height as i64
+    {
+        return Err(ImageProcError::OutOfBounds { region, image: (width, height) });
+    }
+    Ok(image.view(region.left() as u32, region.top() as u32, region.width(), region.height()))
+}
+
+/// Returns a view of the pixels of `image` within the intersection of `region`
+/// with the image bounds, or `None` if this intersection is empty.
+///
+/// The top-left of the returned view is at `(max(region.left(), 0), max(region.top(), 0))`
+/// in `image`.
+pub fn clipped_region_view<I>(image: &I, region: Rect) -> Option<SubImage<&I::InnerImageView>>
+where
+    I: GenericImageView,
+{
+    let (width, height) = image.dimensions();
+    region
+        .clamp_to_image(width, height)
+        .map(|r| image.view(r.left() as u32, r.top() as u32, r.width(), r.height()))
+}
+
+/// Returns an iterator over the pixels of `image` in row-major order,
+/// together with their coordinates.
+///
+/// Unlike `GenericImageView::pixels`, this is empty for images with zero width.
+pub fn enumerate_pixels<'a, I: GenericImageView>(image: &'a I) -> EnumeratePixels<'a, I> {
+    let (width, height) = image.dimensions();
+    EnumeratePixels { image, x: 0, y: if width == 0 { height } else { 0 }, width, height }
+}
+
+/// Iterator over the pixels of an image and their coordinates.
+///
+/// See [`enumerate_pixels`](fn.enumerate_pixels.html).
+pub struct EnumeratePixels<'a, I: 'a> {
+    image: &'a I,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl<'a, I: GenericImageView> Iterator for EnumeratePixels<'a, I> {
+    type Item = (u32, u32, I::Pixel);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.y >= self.height {
+            return None;
+        }
+        let (x, y) = (self.x, self.y);
+        self.x += 1;
+        if self.x == self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some((x, y, unsafe { self.image.unsafe_get_pixel(x, y) }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = if self.y >= self.height {
+            0
+        } else {
+            (self.height - self.y) as usize * self.width as usize - self.x as usize
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+/// Returns an iterator over the `window_width` by `window_height` windows of `image`
+/// whose top-left corners lie on a grid with spacing `stride_x` horizontally and
+/// `stride_y` vertically, starting at `(0, 0)`.
+///
+/// Only windows lying entirely within the image are produced. Each item is the
+/// position of the top-left of the window and a view of the window. Windows are
+/// produced in row-major order.
+///
+/// # Panics
+/// If any of the window dimensions or strides are zero.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use imageproc::stats::min_max;
+/// use imageproc::view::windows;
+///
+/// let image = gray_image!(
+///     1, 2, 3, 4, 5;
+///     6, 7, 8, 9, 10;
+///     11, 12, 13, 14, 15);
+///
+/// let maxima: Vec<_> = windows(&image, 2, 2, 2, 1)
+///     .map(|(position, window)| (position, min_max(&window).unwrap().1))
+///     .collect();
+///
+/// assert_eq!(maxima, vec![((0, 0), 7), ((2, 0), 9), ((0, 1), 12), ((2, 1), 14)]);
+/// # }
+/// ```
+pub fn windows<'a, I>(
+    image: &'a I,
+    window_width: u32,
+    window_height: u32,
+    stride_x: u32,
+    stride_y: u32,
+) -> Windows<'a, I>
+where
+    I: GenericImageView,
+{
+    assert!(window_width > 0 && window_height > 0, "window dimensions must be non-zero");
+    assert!(stride_x > 0 && stride_y > 0, "strides must be non-zero");
+    let (width, height) = image.dimensions();
+    Windows {
+        image,
+        window_width,
+        window_height,
+        stride_x,
+        stride_y,
+        x: 0,
+        y: 0,
+        done: window_width > width || window_height > height,
+    }
+}
+
+/// Iterator over the windows of an image.
+///
+/// See [`windows`](fn.windows.html).
+pub struct Windows<'a, I: 'a> {
+    image: &'a I,
+    window_width: u32,
+    window_height: u32,
+    stride_x: u32,
+    stride_y: u32,
+    x: u32,
+    y: u32,
+    done: bool,
+}
+
+impl<'a, I: GenericImageView> Iterator for Windows<'a, I> {
+    type Item = ((u32, u32), SubImage<&'a I::InnerImageView>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (width, height) = self.image.dimensions();
+        let (x, y) = (self.x, self.y);
+        let window = self.image.view(x, y, self.window_width, self.window_height);
+
+        // Compare against the space left to avoid overflow for large strides.
+        if width - x - self.window_width >= self.stride_x {
+            self.x += self.stride_x;
+        } else if height - y - self.window_height >= self.stride_y {
+            self.x = 0;
+            self.y += self.stride_y;
+        } else {
+            self.done = true;
+        }
+
+        Some(((x, y), window))
+    }
+}
+
+/// How to read pixels outside the bounds of an image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Border<P> {
+    /// Use the value of the nearest pixel in the image.
+    Replicate,
+    /// Reflect about the edge pixel, so that the pixel at `x = -1` has the
+    /// value of the pixel at `x = 1`.
+    Reflect,
+    /// Treat the image as periodic, so that the pixel at `x = -1` has the
+    /// value of the pixel at `x = width - 1`.
+    Wrap,
+    /// Use the given value.
+    Constant(P),
+}
+
+/// Returns the pixel of `image` at `(x, y)`, or the value given by `border`
+/// if this position is outside the image.
+///
+/// # Panics
+/// If `image` is empty and `border` is not `Border::Constant`.
+pub fn get_pixel_with_border<I, P>(image: &I, x: i64, y: i64, border: Border<P>) -> P
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel,
+{
+    let (width, height) = image.dimensions();
+    if x >= 0 && y >= 0 && x < width as i64 && y < height as i64 {
+        return unsafe { image.unsafe_get_pixel(x as u32, y as u32) };
+    }
+    if let Border::Constant(value) = border {
+        return value;
+    }
+    assert!(width > 0 && height > 0, "image must be non-empty");
+    let x = border_index(x, width, &border);
+    let y = border_index(y, height, &border);
+    unsafe { image.unsafe_get_pixel(x, y) }
+}
+
+/// Maps an index outside `0..len` to an index inside it, for all border
+/// modes except `Border::Constant`. `len` must be non-zero.
+fn border_index<P>(index: i64, len: u32, border: &Border<P>) -> u32 {
+    let len = len as i64;
+    let mapped = match *border {
+        Border::Replicate => index.max(0).min(len - 1),
+        Border::Reflect => {
+            if len == 1 {
+                0
+            } else {
+                let period = 2 * (len - 1);
+                let i = ((index % period) + period) % period;
+                if i < len { i } else { period - i }
+            }
+        }
+        Border::Wrap => ((index % len) + len) % len,
+        Border::Constant(_) => unreachable!(),
+    };
+    mapped as u32
+}
+
+/// Returns a copy of `image` with `left`, `right`, `top` and `bottom` extra
+/// columns and rows added on each side, filled according to `border`.
+///
+/// Filters that take a `Border` are implemented by padding their input with this
+/// function, so padding an image yourself and filtering with the default border
+/// handling gives the same results in the interior.
+///
+/// # Panics
+/// If `image` is empty, `border` is not `Border::Constant` and the output is non-empty.
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::view::{pad, Border};
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// assert_pixels_eq!(
+///     pad(&image, 2, 1, 1, 0, Border::Reflect),
+///     gray_image!(
+///         6, 5, 4, 5, 6, 5;
+///         3, 2, 1, 2, 3, 2;
+///         6, 5, 4, 5, 6, 5));
+///
+/// assert_pixels_eq!(
+///     pad(&image, 1, 0, 0, 1, Border::Constant(Luma([0]))),
+///     gray_image!(
+///         0, 1, 2, 3;
+///         0, 4, 5, 6;
+///         0, 0, 0, 0));
+/// # }
+/// ```
+pub fn pad<I, P>(image: &I, left: u32, right: u32, top: u32, bottom: u32, border: Border<P>) -> Image<P>
+where
+    I: GenericImageView<Pixel = P>,
+    P: Pixel + 'static,
+{
+    let (width, height) = image.dimensions();
+    let (left, top) = (left as i64, top as i64);
+    Image::<P>::from_fn(width + left as u32 + right, height + top as u32 + bottom, |x, y| {
+        get_pixel_with_border(image, x as i64 - left, y as i64 - top, border)
+    })
+}
+
+/// The pixels around a given pixel of an image, reading pixels outside the
+/// image according to a [`Border`](enum.Border.html).
+///
+/// # Examples
+/// ```
+/// # extern crate image;
+/// # #[macro_use]
+/// # extern crate imageproc;
+/// # fn main() {
+/// use image::Luma;
+/// use imageproc::view::{Border, Neighborhood};
+///
+/// let image = gray_image!(
+///     1, 2, 3;
+///     4, 5, 6);
+///
+/// let n = Neighborhood::new(&image, 0, 1, Border::Reflect);
+/// assert_eq!(n.center(), Luma([4]));
+/// assert_eq!(n.get(1, -1), Luma([2]));
+/// assert_eq!(n.get(-1, 1), Luma([2]));
+///
+/// let n = Neighborhood::new(&image, 0, 1, Border::Constant(Luma([0])));
+/// assert_eq!(n.get(-1, 0), Luma([0]));
+/// # }
+/// ```
+pub struct Neighborhood<'a, I: 'a + GenericImageView> {
+    image: &'a I,
+    x: u32,
+    y: u32,
+    border: Border<I::Pixel>,
+}
+
+impl<'a, I: GenericImageView> Neighborhood<'a, I> {
+    /// The neighborhood of the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    /// If `(x, y)` is outside `image`.
+    pub fn new(image: &'a I, x: u32, y: u32, border: Border<I::Pixel>) -> Neighborhood<'a, I> {
+        assert!(image.in_bounds(x, y), "({}, {}) is outside the image", x, y);
+        Neighborhood { image, x, y, border }
+    }
+
+    /// The pixel this neighborhood is centered on.
+    pub fn center(&self) -> I::Pixel {
+        unsafe { self.image.unsafe_get_pixel(self.x, self.y) }
+    }
+
+    /// The pixel at offset `(dx, dy)` from the center.
+    pub fn get(&self, dx: i32, dy: i32) -> I::Pixel {
+        get_pixel_with_border(
+            self.image,
+            self.x as i64 + dx as i64,
+            self.y as i64 + dy as i64,
+            self.border,
+        )
+    }
+
+    /// Returns the pixels in the `2 * x_radius + 1` by `2 * y_radius + 1`
+    /// window centered on this pixel, in row-major order.
+    pub fn window(&self, x_radius: u32, y_radius: u32) -> Vec<I::Pixel> {
+        let (rx, ry) = (x_radius as i32, y_radius as i32);
+        let mut pixels = Vec::with_capacity(((2 * x_radius + 1) * (2 * y_radius + 1)) as usize);
+        for dy in -ry..ry + 1 {
+            for dx in -rx..rx + 1 {
+                pixels.push(self.get(dx, dy));
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn test_region_view() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6;
+            7, 8, 9);
+
+        let view = region_view(&image, Rect::at(1, 1).of_size(2, 2)).unwrap();
+        assert_pixels_eq!(view.to_image(), gray_image!(5, 6; 8, 9));
+
+        // Views of views are relative to the outer view.
+        let inner = region_view(&view, Rect::at(1, 0).of_size(1, 2)).unwrap();
+        assert_eq!(inner.get_pixel(0, 1), Luma([9]));
+    }
+
+    #[test]
+    fn test_region_view_out_of_bounds() {
+        let image = GrayImage::new(3, 3);
+        for &region in &[
+            Rect::at(-1, 0).of_size(2, 2),
+            Rect::at(0, -1).of_size(2, 2),
+            Rect::at(2, 0).of_size(2, 2),
+            Rect::at(0, 2).of_size(2, 2),
+        ] {
+            assert_eq!(
+                region_view(&image, region).err(),
+                Some(ImageProcError::OutOfBounds { region, image: (3, 3) })
+            );
+        }
+    }
+
+    #[test]
+    fn test_enumerate_pixels() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        let view = image.view(1, 0, 2, 2);
+
+        let pixels: Vec<_> = enumerate_pixels(&view).map(|(x, y, p)| (x, y, p[0])).collect();
+        assert_eq!(pixels, vec![(0, 0, 2), (1, 0, 3), (0, 1, 5), (1, 1, 6)]);
+
+        assert_eq!(enumerate_pixels(&GrayImage::new(0, 3)).count(), 0);
+        assert_eq!(enumerate_pixels(&GrayImage::new(3, 0)).count(), 0);
+    }
+
+    #[test]
+    fn test_clipped_region_view() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6;
+            7, 8, 9);
+
+        let view = clipped_region_view(&image, Rect::at(-1, 1).of_size(3, 5)).unwrap();
+        assert_pixels_eq!(view.to_image(), gray_image!(4, 5; 7, 8));
+
+        assert!(clipped_region_view(&image, Rect::at(3, 0).of_size(2, 2)).is_none());
+        assert!(clipped_region_view(&GrayImage::new(0, 0), Rect::at(0, 0).of_size(1, 1)).is_none());
+    }
+
+    #[test]
+    fn test_windows() {
+        let image = GrayImage::from_fn(5, 4, |x, y| Luma([(10 * y + x) as u8]));
+
+        let positions: Vec<_> = windows(&image, 2, 3, 2, 2).map(|(p, _)| p).collect();
+        assert_eq!(positions, vec![(0, 0), (2, 0)]);
+
+        let (_, window) = windows(&image, 2, 3, 2, 2).nth(1).unwrap();
+        assert_pixels_eq!(window.to_image(), gray_image!(2, 3; 12, 13; 22, 23));
+
+        assert_eq!(windows(&image, 1, 1, 1, 1).count(), 20);
+        assert_eq!(windows(&image, 5, 4, 1, 1).count(), 1);
+        assert_eq!(windows(&image, 6, 1, 1, 1).count(), 0);
+        assert_eq!(windows(&image, 1, 1, u32::max_value(), u32::max_value()).count(), 1);
+    }
+
+    #[test]
+    fn test_get_pixel_with_border() {
+        let image = gray_image!(1, 2, 3);
+        let row = |border: Border<Luma<u8>>| -> Vec<u8> {
+            (-4..7).map(|x| get_pixel_with_border(&image, x, 0, border)[0]).collect()
+        };
+
+        assert_eq!(row(Border::Replicate), vec![1, 1, 1, 1, 1, 2, 3, 3, 3, 3, 3]);
+        assert_eq!(row(Border::Reflect), vec![1, 2, 3, 2, 1, 2, 3, 2, 1, 2, 3]);
+        assert_eq!(row(Border::Wrap), vec![3, 1, 2, 3, 1, 2, 3, 1, 2, 3, 1]);
+        assert_eq!(row(Border::Constant(Luma([9]))), vec![9, 9, 9, 9, 1, 2, 3, 9, 9, 9, 9]);
+
+        let single = gray_image!(7);
+        assert_eq!(get_pixel_with_border(&single, -3, 2, Border::Reflect), Luma([7]));
+    }
+
+    #[test]
+    fn test_pad() {
+        let image = gray_image!(
+            1, 2;
+            3, 4);
+
+        assert_pixels_eq!(
+            pad(&image, 1, 2, 0, 1, Border::Replicate),
+            gray_image!(
+                1, 1, 2, 2, 2;
+                3, 3, 4, 4, 4;
+                3, 3, 4, 4, 4));
+
+        assert_pixels_eq!(
+            pad(&image, 3, 0, 1, 0, Border::Wrap),
+            gray_image!(
+                4, 3, 4, 3, 4;
+                2, 1, 2, 1, 2;
+                4, 3, 4, 3, 4));
+
+        assert_pixels_eq!(pad(&image, 0, 0, 0, 0, Border::Reflect), image);
+
+        let empty = GrayImage::new(0, 2);
+        assert_pixels_eq!(
+            pad(&empty, 1, 1, 0, 0, Border::Constant(Luma([5]))),
+            GrayImage::from_pixel(2, 2, Luma([5])));
+        assert_eq!(pad(&empty, 0, 0, 3, 3, Border::Wrap).dimensions(), (0, 8));
+    }
+
+    #[test]
+    fn test_neighborhood_window() {
+        let image = gray_image!(
+            1, 2, 3;
+            4, 5, 6);
+        let n = Neighborhood::new(&image, 2, 0, Border::Replicate);
+        let values: Vec<u8> = n.window(1, 1).iter().map(|p| p[0]).collect();
+        assert_eq!(values, vec![2, 3, 3, 2, 3, 3, 5, 6, 6]);
+    }
+}
@@ -5,6 +5,8 @@
 #![feature(test)]
 #![feature(unboxed_closures)]
 #![feature(fn_traits)]
+// The affine tests exercise the deprecated affine module until it is removed.
+#![allow(deprecated)]
 
 extern crate image;
 extern crate test;
@@ -181,7 +183,7 @@ fn test_affine_bilinear_rgb() {
 #[test]
 fn test_sobel_gradients() {
     fn sobel_gradients(image: &GrayImage) -> GrayImage {
-        imageproc::map::map_subpixels(&gradients::sobel_gradients(image), u8::clamp)
+        imageproc::map::map_subpixels(&gradients::sobel_gradients(image), <u8 as Clamp<u16>>::clamp)
     }
     compare_to_truth_grayscale("elephant.png", "elephant_gradients.png", sobel_gradients);
 }
@@ -226,6 +228,14 @@ fn test_otsu_threshold() {
     compare_to_truth_grayscale("zebra.png", "zebra_otsu.png", otsu_threshold);
 }
 
+#[test]
+fn test_otsu_threshold_combined() {
+    use imageproc::contrast::{otsu_level, otsu_threshold};
+    let image = load_input_image("zebra.png").to_luma();
+    assert_eq!(otsu_threshold(&image).1, otsu_level(&image));
+    compare_to_truth_grayscale("zebra.png", "zebra_otsu.png", |image| otsu_threshold(image).0);
+}
+
 #[test]
 fn test_draw_antialiased_line_segment_rgb() {
     use image::{Rgb};
@@ -0,0 +1,92 @@
+//! Round trips values through JSON using the `serde` feature.
+
+#![cfg(feature = "serde")]
+
+extern crate image;
+extern crate imageproc;
+extern crate serde_json;
+
+use image::{GrayImage, Luma};
+use imageproc::binary_descriptors::{brief_descriptors, BinaryDescriptor, BriefPattern};
+use imageproc::corners::Corner;
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::haar::{Cascade, HaarFeature, Stage, WeakClassifier};
+use imageproc::noise::gaussian_noise;
+
+#[test]
+fn test_cascade_round_trip() {
+    let classifier = WeakClassifier {
+        feature: HaarFeature::enumerate(4, 4).next().unwrap(),
+        threshold: 100.0,
+        below: -1.0,
+        above: 1.0,
+    };
+    let cascade = Cascade::new(4, 4, vec![Stage { classifiers: vec![classifier], threshold: 0.0 }]).unwrap();
+    let json = serde_json::to_string(&cascade).unwrap();
+    let restored: Cascade = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, cascade);
+}
+
+#[test]
+fn test_cascade_with_tilted_feature_fails_to_deserialize() {
+    let classifier = WeakClassifier {
+        feature: HaarFeature::enumerate(4, 4).next().unwrap(),
+        threshold: 100.0,
+        below: -1.0,
+        above: 1.0,
+    };
+    let cascade = Cascade::new(4, 4, vec![Stage { classifiers: vec![classifier], threshold: 0.0 }]).unwrap();
+    let json = serde_json::to_string(&cascade).unwrap();
+    assert!(json.contains("\"tilted\":false"));
+
+    let tilted = json.replace("\"tilted\":false", "\"tilted\":true");
+    assert!(serde_json::from_str::<Cascade>(&tilted).is_err());
+}
+
+#[test]
+fn test_cascade_with_feature_outside_window_fails_to_deserialize() {
+    let classifier = WeakClassifier {
+        feature: HaarFeature::enumerate(4, 4).last().unwrap(),
+        threshold: 100.0,
+        below: -1.0,
+        above: 1.0,
+    };
+    let cascade = Cascade::new(4, 4, vec![Stage { classifiers: vec![classifier], threshold: 0.0 }]).unwrap();
+    let json = serde_json::to_string(&cascade).unwrap();
+    assert!(json.contains("\"window_width\":4"));
+
+    let narrow = json.replace("\"window_width\":4", "\"window_width\":1");
+    assert!(serde_json::from_str::<Cascade>(&narrow).is_err());
+
+    let empty = json.replace("\"window_width\":4", "\"window_width\":0");
+    assert!(serde_json::from_str::<Cascade>(&empty).is_err());
+}
+
+#[test]
+fn test_feature_with_empty_blocks_fails_to_deserialize() {
+    let feature = HaarFeature::enumerate(4, 4).next().unwrap();
+    let json = serde_json::to_string(&feature).unwrap();
+    assert!(json.contains("\"block_size\":{\"width\":1"));
+
+    let empty = json.replace("\"block_size\":{\"width\":1", "\"block_size\":{\"width\":0");
+    assert!(serde_json::from_str::<HaarFeature>(&empty).is_err());
+}
+
+#[test]
+fn test_descriptor_round_trip() {
+    let image = gaussian_noise(&GrayImage::from_pixel(41, 41, Luma([128])), 0.0, 60.0, 1);
+    let image = gaussian_blur_f32(&image, 1.5);
+    let pattern = BriefPattern::random(200, 21, 1);
+    let descriptors = brief_descriptors(&image, &[Corner::new(20, 20, 0.0)], &pattern, 0.0);
+    let json = serde_json::to_string(&descriptors).unwrap();
+    let restored: Vec<BinaryDescriptor> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, descriptors);
+}
+
+#[test]
+fn test_corner_round_trip() {
+    let corners = vec![Corner::new(3, 4, 1.5), Corner::new(0, 7, -2.0)];
+    let json = serde_json::to_string(&corners).unwrap();
+    let restored: Vec<Corner> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, corners);
+}